@@ -0,0 +1,54 @@
+// The earth: the standard material, with the night map showing through
+// where the sun is down (see `sim_render::earth`).
+
+#import bevy_pbr::{
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::alpha_discard,
+}
+
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::{
+    prepass_io::{VertexOutput, FragmentOutput},
+    pbr_deferred_functions::deferred_output,
+}
+#else
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+}
+#endif
+
+// The direction to the sun, and in w, how bright the night map is.
+@group(#{MATERIAL_BIND_GROUP}) @binding(100) var<uniform> sun: vec4<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(101) var night_texture: texture_2d<f32>;
+@group(#{MATERIAL_BIND_GROUP}) @binding(102) var night_sampler: sampler;
+
+// How far past the terminator, as the sine of the sun's elevation, the
+// lights fade in over.
+const TWILIGHT: f32 = 0.1;
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+#ifdef VERTEX_UVS_A
+    // The lights glow on their own, so they go in as emissive, unlit.
+    let elevation = dot(normalize(pbr_input.world_normal), normalize(sun.xyz));
+    let dark = 1.0 - smoothstep(-TWILIGHT, TWILIGHT, elevation);
+    let lights = textureSample(night_texture, night_sampler, in.uv).rgb;
+    pbr_input.material.emissive = vec4<f32>(lights * dark * sun.w, 0.0);
+#endif
+
+#ifdef PREPASS_PIPELINE
+    let out = deferred_output(in, pbr_input);
+#else
+    var out: FragmentOutput;
+    out.color = apply_pbr_lighting(pbr_input);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+#endif
+    return out;
+}
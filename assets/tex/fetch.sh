@@ -0,0 +1,41 @@
+#!/bin/bash
+
+# Download the earth's day and night maps for scifisim
+# These are NASA's Blue Marble and Black Marble, from NASA Visible Earth,
+# converted to PNG with ImageMagick
+
+set -e  # Exit on any error
+
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+cd "$SCRIPT_DIR"
+
+if command -v magick > /dev/null; then
+    CONVERT=magick
+elif command -v convert > /dev/null; then
+    CONVERT=convert
+else
+    echo "ImageMagick (magick or convert) is needed to make the PNGs" >&2
+    exit 1
+fi
+
+echo "Downloading the earth's maps to $(pwd)..."
+
+# Base URL for NASA Visible Earth images
+VISIBLE_EARTH="https://eoimages.gsfc.nasa.gov/images/imagerecords"
+
+# Blue Marble: land surface, shallow water, and shaded topography
+echo "Downloading earth_day.png..."
+curl -L -o earth_day.jpg "${VISIBLE_EARTH}/57000/57752/land_shallow_topo_2048.jpg"
+$CONVERT earth_day.jpg earth_day.png
+rm earth_day.jpg
+
+# Black Marble: the city lights, 2012
+echo "Downloading earth_night.png..."
+curl -L -o earth_night.jpg "${VISIBLE_EARTH}/79000/79765/dnb_land_ocean_ice.2012.3600x1800.jpg"
+$CONVERT earth_night.jpg earth_night.png
+rm earth_night.jpg
+
+echo "All maps downloaded successfully!"
+echo ""
+echo "Downloaded files:"
+ls -lh earth_day.png earth_night.png
@@ -0,0 +1,155 @@
+//! Demonstration of a flat spin, like Explorer 1's.
+//!
+//! Explorer 1 was spun up about its long axis, the one with the least
+//! inertia.  For a rigid body, that spin is stable, but its whip antennas
+//! flexed, and the energy they soaked up could only come out of the spin by
+//! tipping it over, with its angular momentum kept, until it was tumbling end
+//! over end about a transverse axis, the one with the most.
+//!
+//! This demo spins a long body, with four whips, about its long axis, with a
+//! slight wobble, and a `NutationDamper` to stand in for the whips.  The
+//! wobble grows, over a few of the damper's time constants, until the body
+//! is turning end over end.  The angle of the long axis off the angular
+//! momentum is shown as it goes.  The `flat_spin` example in `sim-core`
+//! checks the same physics.
+
+extern crate nalgebra as na;
+
+use bevy::{
+    color::palettes::css::{GOLD, SILVER},
+    prelude::*,
+};
+use sim_core::{AttitudeIntegrator, AttitudeState, NutationDamper, RigidBody, RotationScheme};
+use sim_render::sim_quat_to_bevy;
+
+#[derive(Component)]
+struct TiltText;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .insert_resource(AttitudeIntegrator {
+            scheme: RotationScheme::Rk4,
+            ..default()
+        })
+        .add_systems(Startup, setup)
+        .add_systems(Update, (update_bevy_rot, update_tilt_text))
+        .add_systems(FixedUpdate, update_rotational_physics)
+        .run();
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let whip = meshes.add(Cylinder {
+        radius: 0.01,
+        half_height: 0.6,
+    });
+    let whip_material = materials.add(StandardMaterial {
+        base_color: SILVER.into(),
+        ..default()
+    });
+    // About a 14 kg rod two meters long, spun at about 1 rev/s.
+    let mut body = commands.spawn((
+        Transform::default(),
+        AttitudeState {
+            q_bw: na::UnitQuaternion::identity(),
+            omega_b: na::Vector3::new(0.05, 0.0, 6.0),
+        },
+        RigidBody {
+            inertia_b: na::Vector3::new(4.8, 4.6, 0.5),
+        },
+        NutationDamper { time_constant: 5.0 },
+    ));
+    body.with_child((
+        Mesh3d(meshes.add(Cylinder {
+            radius: 0.08,
+            half_height: 1.0,
+        })),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: GOLD.into(),
+            ..default()
+        })),
+    ));
+    // The whips, out across the middle.
+    for (axis, angle) in [
+        (Vec3::X, std::f32::consts::FRAC_PI_2),
+        (Vec3::Z, std::f32::consts::FRAC_PI_2),
+    ] {
+        body.with_child((
+            Mesh3d(whip.clone()),
+            MeshMaterial3d(whip_material.clone()),
+            Transform::from_rotation(Quat::from_axis_angle(axis, angle)),
+        ));
+    }
+
+    commands.spawn((
+        PointLight {
+            intensity: 5_500_000.0,
+            ..default()
+        },
+        Transform::from_xyz(4.0, 8.0, 4.0),
+    ));
+
+    commands.spawn((
+        Camera3d { ..default() },
+        Transform::from_xyz(-2.5, 3.0, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    commands.spawn((
+        Text::new(""),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(12.0),
+            left: Val::Px(12.0),
+            ..default()
+        },
+        TiltText,
+    ));
+}
+
+/// Update any object with an AttitudeState to update the Bevy Transform. Should be called in Update.
+fn update_bevy_rot(mut query: Query<(&mut Transform, &AttitudeState)>) {
+    for (mut transform, state) in query.iter_mut() {
+        transform.rotation = sim_quat_to_bevy(&state.q_bw);
+    }
+}
+
+/// Show how far the long axis is off the angular momentum: about 0° while it
+/// spins, and 90° once it is flat.
+fn update_tilt_text(
+    body: Query<(&AttitudeState, &RigidBody)>,
+    mut text: Query<&mut Text, With<TiltText>>,
+) {
+    let (Ok((state, rigid)), Ok(mut text)) = (body.single(), text.single_mut()) else {
+        return;
+    };
+    let momentum_b = rigid.inertia_b.component_mul(&state.omega_b);
+    let tilt = na::Vector3::z().angle(&momentum_b).to_degrees();
+    **text = format!(
+        "Long axis {:.1}° off the angular momentum",
+        tilt.min(180.0 - tilt)
+    );
+}
+
+/// Simulate the rotational physics, with the damper, and no torque.
+fn update_rotational_physics(
+    mut query: Query<(&mut AttitudeState, &RigidBody, &NutationDamper)>,
+    integrator: Res<AttitudeIntegrator>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs_f64();
+
+    for (mut attitude, rigid, damper) in query.iter_mut() {
+        let alpha_b = na::Vector3::zeros();
+        integrator.step(
+            &mut attitude,
+            Some(&rigid.inertia_b),
+            Some(damper),
+            &alpha_b,
+            dt,
+        );
+    }
+}
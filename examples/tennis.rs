@@ -8,7 +8,9 @@
 //! This demo shows a pair of cylinders setup to be similar to the handle in
 //! this video: https://www.youtube.com/watch?v=1x5UiwEEvpQ that clearly
 //! demonstrates the flipping effect. If the rotation physics are implemented
-//! correctly, this demo should show a similar flipping effect.
+//! correctly, this demo should show a similar flipping effect.  The
+//! `precession` example in `sim-core` checks the same physics against the
+//! analytic solution.
 
 extern crate nalgebra as na;
 
@@ -17,13 +19,18 @@ use bevy::{
     post_process::motion_blur::MotionBlur,
     prelude::*,
 };
-use sim_physics::AttitudeState;
+use sim_core::{AttitudeIntegrator, AttitudeState, RigidBody, RotationScheme};
+use sim_render::sim_quat_to_bevy;
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         // Bump up the Fixed Update interval so that we can spin this faster to better observe the effect.
         // .insert_resource(Time::<Fixed>::from_hz(500.0))
+        .insert_resource(AttitudeIntegrator {
+            scheme: RotationScheme::Pcdm,
+            ..default()
+        })
         .add_systems(Startup, setup)
         .add_systems(Update, update_bevy_rot)
         .add_systems(FixedUpdate, update_rotational_physics)
@@ -38,12 +45,13 @@ fn setup(
     commands
         .spawn((
             Transform::default(),
-            AttitudeState::new_with_omega_b(
-                na::UnitQuaternion::identity(),
-                na::Vector3::new(3000.0 / 373.0, 0.0, 3.0 / 78.0),
-                na::Vector3::new(373.0, 415.0, 78.0),
-                na::Vector3::zeros(),
-            ),
+            AttitudeState {
+                q_bw: na::UnitQuaternion::identity(),
+                omega_b: na::Vector3::new(3000.0 / 373.0, 0.0, 3.0 / 78.0),
+            },
+            RigidBody {
+                inertia_b: na::Vector3::new(373.0, 415.0, 78.0),
+            },
         ))
         .with_child((
             Mesh3d(meshes.add(Cylinder {
@@ -121,21 +129,16 @@ fn update_bevy_rot(mut query: Query<(&mut Transform, &AttitudeState)>) {
 /// Simulate the rotational physics.
 ///
 /// For now, no torque is implemented.
-fn update_rotational_physics(mut query: Query<&mut AttitudeState>, time: Res<Time>) {
+fn update_rotational_physics(
+    mut query: Query<(&mut AttitudeState, &RigidBody)>,
+    integrator: Res<AttitudeIntegrator>,
+    time: Res<Time>,
+) {
     let dt = time.delta_secs_f64();
 
-    for mut attitude in query.iter_mut() {
+    for (mut attitude, rigid) in query.iter_mut() {
         // No torque for now.
-        let torque_w_now = na::Vector3::zeros();
-        attitude.step_rot_fixed_tau_b(dt, torque_w_now);
+        let alpha_b = na::Vector3::zeros();
+        integrator.step(&mut attitude, Some(&rigid.inertia_b), None, &alpha_b, dt);
     }
 }
-
-/// Convert a nalgebra quaternion (f64) to a bevy quaternion (f32).  This
-/// includes the basis change between the Z-up sim and the Y-up bevy.
-pub fn sim_quat_to_bevy(q: &na::UnitQuaternion<f64>) -> Quat {
-    let r =
-        na::UnitQuaternion::from_axis_angle(&na::Vector3::x_axis(), -std::f64::consts::FRAC_PI_2);
-    let q = r.conjugate() * q * r;
-    Quat::from_array([q.i as f32, q.j as f32, q.k as f32, q.w as f32])
-}
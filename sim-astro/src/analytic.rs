@@ -0,0 +1,825 @@
+//! Ephemerides worked out from series, for running without SPICE.
+//!
+//! The kernels need the CSPICE library, which is a nuisance to build and link
+//! on some platforms, and can't be had at all on others, such as the web.
+//! This stands in for them, working the major bodies' states out from series,
+//! as the almanacs did before there were kernels: the sun, the planets,
+//! Pluto, and the moon, and none of the other moons, or the small bodies.
+//!
+//! - The planets go around the sun on Keplerian orbits whose elements drift
+//!   at constant rates: the mean elements a truncated VSOP87 starts from, as
+//!   JPL fitted them to its own ephemeris for 1800 to 2050 (Standish,
+//!   "Keplerian Elements for Approximate Positions of the Major Planets").
+//!   The outer planets' are for their systems' barycenters, so that is where
+//!   those planets are put.
+//! - The moon is from the main terms of ELP2000, as Meeus truncates it
+//!   (Astronomical Algorithms, ch. 47), moved from the ecliptic of the date
+//!   to that of J2000.
+//! - The sun is wherever puts the solar system's barycenter at the origin, as
+//!   it is in the kernels.
+//! - The attitudes are from the IAU's rotation models, without most of their
+//!   periodic terms.
+//!
+//! This is nothing like as accurate as the kernels.  Against them, at the
+//! start of 2024, the earth and Mercury are some 1000 km out, Venus 7000 km,
+//! and Mars 40000 km, while the outer planets, which pull each other about
+//! more than an orbit with drifting elements can follow, are out by one to
+//! two million km.  The moon is within a few km of where it should be about
+//! the earth, though ELP2000 cut down this far can be out by some tens of km
+//! at other times, and the poles and prime meridians are within a few
+//! hundredths of a degree.  That does for flying about the earth and the
+//! moon, and for a look at the rest, but not for an encounter planned against
+//! the kernels.  Outside of 1800 to 2050 it gets worse.
+//!
+//! This is only built with the `analytic` feature.
+
+use bevy::prelude::*;
+use na::{Rotation3, UnitQuaternion, Vector3};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, SizedBody};
+use std::f64::consts::{FRAC_PI_2, TAU};
+
+use crate::{
+    Body, SolarState, SpiceId, frames,
+    hierarchy::{Barycenter, SUN_ID},
+    loading::LoadMessage,
+};
+
+/// When the game starts, 2024-01-01T00:00:00 UTC, as it does from the
+/// kernels, in seconds past J2000.
+pub const EPOCH: f64 = 757_339_269.183_906_1;
+const EPOCH_NAME: &str = "2024-01-01T00:00:00";
+
+/// 1800 to 2050, which the planets' elements are fitted for, in seconds past
+/// J2000.
+pub const COVERAGE: (f64, f64) = (-6_311_390_400.0, 1_577_880_000.0);
+
+/// The bodies there are, by NAIF id.
+pub const IDS: [i32; 11] = [10, 199, 299, 399, 301, 499, 599, 699, 799, 899, 999];
+
+const AU: f64 = 149_597_870.7;
+const DAY: f64 = 86_400.0;
+const CENTURY: f64 = 36_525.0 * DAY;
+
+/// The mass of the earth over that of the earth and the moon.
+const EARTH_FRACTION: f64 = 398_600.435 / (398_600.435 + 4902.800);
+
+/// A body's name, GM (km^3/s^2) and radii (km), as the kernels have them.
+struct Constants {
+    id: i32,
+    name: &'static str,
+    gm: f64,
+    radii: [f64; 3],
+}
+
+const CONSTANTS: [Constants; 11] = [
+    Constants {
+        id: 10,
+        name: "SUN",
+        gm: 132_712_440_041.279,
+        radii: [695_700.0, 695_700.0, 695_700.0],
+    },
+    Constants {
+        id: 199,
+        name: "MERCURY",
+        gm: 22_031.869,
+        radii: [2440.53, 2440.53, 2438.26],
+    },
+    Constants {
+        id: 299,
+        name: "VENUS",
+        gm: 324_858.592,
+        radii: [6051.8, 6051.8, 6051.8],
+    },
+    Constants {
+        id: 399,
+        name: "EARTH",
+        gm: 398_600.435,
+        radii: [6378.1366, 6378.1366, 6356.7519],
+    },
+    Constants {
+        id: 301,
+        name: "MOON",
+        gm: 4902.800,
+        radii: [1737.4, 1737.4, 1737.4],
+    },
+    Constants {
+        id: 499,
+        name: "MARS",
+        gm: 42_828.374,
+        radii: [3396.19, 3396.19, 3376.2],
+    },
+    Constants {
+        id: 599,
+        name: "JUPITER",
+        gm: 126_686_531.900,
+        radii: [71_492.0, 71_492.0, 66_854.0],
+    },
+    Constants {
+        id: 699,
+        name: "SATURN",
+        gm: 37_931_206.234,
+        radii: [60_268.0, 60_268.0, 54_364.0],
+    },
+    Constants {
+        id: 799,
+        name: "URANUS",
+        gm: 5_793_951.257,
+        radii: [25_559.0, 25_559.0, 24_973.0],
+    },
+    Constants {
+        id: 899,
+        name: "NEPTUNE",
+        gm: 6_835_103.145,
+        radii: [24_764.0, 24_764.0, 24_341.0],
+    },
+    Constants {
+        id: 999,
+        name: "PLUTO",
+        gm: 869.614,
+        radii: [1188.3, 1188.3, 1188.3],
+    },
+];
+
+/// The barycenters of the planets' systems, for their names.
+const BARYCENTERS: [&str; 9] = [
+    "MERCURY BARYCENTER",
+    "VENUS BARYCENTER",
+    "EARTH BARYCENTER",
+    "MARS BARYCENTER",
+    "JUPITER BARYCENTER",
+    "SATURN BARYCENTER",
+    "URANUS BARYCENTER",
+    "NEPTUNE BARYCENTER",
+    "PLUTO BARYCENTER",
+];
+
+fn constants(id: i32) -> Option<&'static Constants> {
+    CONSTANTS.iter().find(|c| c.id == id)
+}
+
+/// A planet's mean elements at J2000, and how much each changes in a
+/// century: the semi-major axis (au), the eccentricity, and the inclination,
+/// mean longitude, longitude of perihelion and longitude of the ascending
+/// node (degrees), on the ecliptic of J2000.
+struct Elements {
+    id: i32,
+    at: [f64; 6],
+    rate: [f64; 6],
+}
+
+/// Standish's table 1, for 1800 to 2050.  The earth's are for the
+/// barycenter of it and the moon, numbered as that system's.
+const ELEMENTS: [Elements; 9] = [
+    Elements {
+        id: 199,
+        at: [
+            0.38709927,
+            0.20563593,
+            7.00497902,
+            252.25032350,
+            77.45779628,
+            48.33076593,
+        ],
+        rate: [
+            0.00000037,
+            0.00001906,
+            -0.00594749,
+            149_472.67411175,
+            0.16047689,
+            -0.12534081,
+        ],
+    },
+    Elements {
+        id: 299,
+        at: [
+            0.72333566,
+            0.00677672,
+            3.39467605,
+            181.97909950,
+            131.60246718,
+            76.67984255,
+        ],
+        rate: [
+            0.00000390,
+            -0.00004107,
+            -0.00078890,
+            58_517.81538729,
+            0.00268329,
+            -0.27769418,
+        ],
+    },
+    Elements {
+        id: 3,
+        at: [
+            1.00000261,
+            0.01671123,
+            -0.00001531,
+            100.46457166,
+            102.93768193,
+            0.0,
+        ],
+        rate: [
+            0.00000562,
+            -0.00004392,
+            -0.01294668,
+            35_999.37244981,
+            0.32327364,
+            0.0,
+        ],
+    },
+    Elements {
+        id: 499,
+        at: [
+            1.52371034,
+            0.09339410,
+            1.84969142,
+            -4.55343205,
+            -23.94362959,
+            49.55953891,
+        ],
+        rate: [
+            0.00001847,
+            0.00007882,
+            -0.00813131,
+            19_140.30268499,
+            0.44441088,
+            -0.29257343,
+        ],
+    },
+    Elements {
+        id: 599,
+        at: [
+            5.20288700,
+            0.04838624,
+            1.30439695,
+            34.39644051,
+            14.72847983,
+            100.47390909,
+        ],
+        rate: [
+            -0.00011607,
+            -0.00013253,
+            -0.00183714,
+            3034.74612775,
+            0.21252668,
+            0.20469106,
+        ],
+    },
+    Elements {
+        id: 699,
+        at: [
+            9.53667594,
+            0.05386179,
+            2.48599187,
+            49.95424423,
+            92.59887831,
+            113.66242448,
+        ],
+        rate: [
+            -0.00125060,
+            -0.00050991,
+            0.00193609,
+            1222.49362201,
+            -0.41897216,
+            -0.28867794,
+        ],
+    },
+    Elements {
+        id: 799,
+        at: [
+            19.18916464,
+            0.04725744,
+            0.77263783,
+            313.23810451,
+            170.95427630,
+            74.01692503,
+        ],
+        rate: [
+            -0.00196176,
+            -0.00004397,
+            -0.00242939,
+            428.48202785,
+            0.40805281,
+            0.04240589,
+        ],
+    },
+    Elements {
+        id: 899,
+        at: [
+            30.06992276,
+            0.00859048,
+            1.77004347,
+            -55.12002969,
+            44.96476227,
+            131.78422574,
+        ],
+        rate: [
+            0.00026291,
+            0.00005105,
+            0.00035372,
+            218.45945325,
+            -0.32241464,
+            -0.00508664,
+        ],
+    },
+    Elements {
+        id: 999,
+        at: [
+            39.48211675,
+            0.24882730,
+            17.14001206,
+            238.92903833,
+            224.06891629,
+            110.30393684,
+        ],
+        rate: [
+            -0.00031596,
+            0.00005170,
+            0.00004818,
+            145.20780515,
+            -0.04062942,
+            -0.01183482,
+        ],
+    },
+];
+
+impl Elements {
+    /// The position (km) and velocity (km/s) about the sun, `t` centuries
+    /// after J2000, of a body with the given GM, added to the sun's.
+    fn state(&self, t: f64, gm: f64) -> (Vector3<f64>, Vector3<f64>) {
+        let el: Vec<f64> = (0..6).map(|k| self.at[k] + self.rate[k] * t).collect();
+        let (a, e) = (el[0] * AU, el[1]);
+        let [i, l, peri, node] = [el[2], el[3], el[4], el[5]].map(f64::to_radians);
+        let m = (l - peri).rem_euclid(TAU);
+        let mut ecc = if e < 0.8 { m } else { std::f64::consts::PI };
+        for _ in 0..50 {
+            let step = (ecc - e * ecc.sin() - m) / (1.0 - e * ecc.cos());
+            ecc -= step;
+            if step.abs() < 1.0e-14 {
+                break;
+            }
+        }
+        let (sin_e, cos_e) = ecc.sin_cos();
+        let b = (1.0 - e * e).sqrt();
+        let r = a * (1.0 - e * cos_e);
+        let pos = Vector3::new(a * (cos_e - e), a * b * sin_e, 0.0);
+        let vel = Vector3::new(-sin_e, b * cos_e, 0.0) * ((gm * a).sqrt() / r);
+        let frame = Rotation3::from_axis_angle(&Vector3::z_axis(), node)
+            * Rotation3::from_axis_angle(&Vector3::x_axis(), i)
+            * Rotation3::from_axis_angle(&Vector3::z_axis(), peri - node);
+        (frame * pos, frame * vel)
+    }
+}
+
+/// The terms of the moon's longitude (1e-6 degrees) and distance (m), by the
+/// multiples of D, M, M' and F in their arguments (Meeus, table 47.A).
+#[rustfmt::skip]
+const MOON_LONGITUDE: [(i8, i8, i8, i8, f64, f64); 60] = [
+    (0, 0, 1, 0, 6288774.0, -20905355.0),
+    (2, 0, -1, 0, 1274027.0, -3699111.0),
+    (2, 0, 0, 0, 658314.0, -2955968.0),
+    (0, 0, 2, 0, 213618.0, -569925.0),
+    (0, 1, 0, 0, -185116.0, 48888.0),
+    (0, 0, 0, 2, -114332.0, -3149.0),
+    (2, 0, -2, 0, 58793.0, 246158.0),
+    (2, -1, -1, 0, 57066.0, -152138.0),
+    (2, 0, 1, 0, 53322.0, -170733.0),
+    (2, -1, 0, 0, 45758.0, -204586.0),
+    (0, 1, -1, 0, -40923.0, -129620.0),
+    (1, 0, 0, 0, -34720.0, 108743.0),
+    (0, 1, 1, 0, -30383.0, 104755.0),
+    (2, 0, 0, -2, 15327.0, 10321.0),
+    (0, 0, 1, 2, -12528.0, 0.0),
+    (0, 0, 1, -2, 10980.0, 79661.0),
+    (4, 0, -1, 0, 10675.0, -34782.0),
+    (0, 0, 3, 0, 10034.0, -23210.0),
+    (4, 0, -2, 0, 8548.0, -21636.0),
+    (2, 1, -1, 0, -7888.0, 24208.0),
+    (2, 1, 0, 0, -6766.0, 30824.0),
+    (1, 0, -1, 0, -5163.0, -8379.0),
+    (1, 1, 0, 0, 4987.0, -16675.0),
+    (2, -1, 1, 0, 4036.0, -12831.0),
+    (2, 0, 2, 0, 3994.0, -10445.0),
+    (4, 0, 0, 0, 3861.0, -11650.0),
+    (2, 0, -3, 0, 3665.0, 14403.0),
+    (0, 1, -2, 0, -2689.0, -7003.0),
+    (2, 0, -1, 2, -2602.0, 0.0),
+    (2, -1, -2, 0, 2390.0, 10056.0),
+    (1, 0, 1, 0, -2348.0, 6322.0),
+    (2, -2, 0, 0, 2236.0, -9884.0),
+    (0, 1, 2, 0, -2120.0, 5751.0),
+    (0, 2, 0, 0, -2069.0, 0.0),
+    (2, -2, -1, 0, 2048.0, -4950.0),
+    (2, 0, 1, -2, -1773.0, 4130.0),
+    (2, 0, 0, 2, -1595.0, 0.0),
+    (4, -1, -1, 0, 1215.0, -3958.0),
+    (0, 0, 2, 2, -1110.0, 0.0),
+    (3, 0, -1, 0, -892.0, 3258.0),
+    (2, 1, 1, 0, -810.0, 2616.0),
+    (4, -1, -2, 0, 759.0, -1897.0),
+    (0, 2, -1, 0, -713.0, -2117.0),
+    (2, 2, -1, 0, -700.0, 2354.0),
+    (2, 1, -2, 0, 691.0, 0.0),
+    (2, -1, 0, -2, 596.0, 0.0),
+    (4, 0, 1, 0, 549.0, -1423.0),
+    (0, 0, 4, 0, 537.0, -1117.0),
+    (4, -1, 0, 0, 520.0, -1571.0),
+    (1, 0, -2, 0, -487.0, -1739.0),
+    (2, 1, 0, -2, -399.0, 0.0),
+    (0, 0, 2, -2, -381.0, -4421.0),
+    (1, 1, 1, 0, 351.0, 0.0),
+    (3, 0, -2, 0, -340.0, 0.0),
+    (4, 0, -3, 0, 330.0, 0.0),
+    (2, -1, 2, 0, 327.0, 0.0),
+    (0, 2, 1, 0, -323.0, 1165.0),
+    (1, 1, -1, 0, 299.0, 0.0),
+    (2, 0, 3, 0, 294.0, 0.0),
+    (2, 0, -1, -2, 0.0, 8752.0),
+];
+
+/// The terms of the moon's latitude (1e-6 degrees), likewise (table 47.B).
+#[rustfmt::skip]
+const MOON_LATITUDE: [(i8, i8, i8, i8, f64); 60] = [
+    (0, 0, 0, 1, 5128122.0),
+    (0, 0, 1, 1, 280602.0),
+    (0, 0, 1, -1, 277693.0),
+    (2, 0, 0, -1, 173237.0),
+    (2, 0, -1, 1, 55413.0),
+    (2, 0, -1, -1, 46271.0),
+    (2, 0, 0, 1, 32573.0),
+    (0, 0, 2, 1, 17198.0),
+    (2, 0, 1, -1, 9266.0),
+    (0, 0, 2, -1, 8822.0),
+    (2, -1, 0, -1, 8216.0),
+    (2, 0, -2, -1, 4324.0),
+    (2, 0, 1, 1, 4200.0),
+    (2, 1, 0, -1, -3359.0),
+    (2, -1, -1, 1, 2463.0),
+    (2, -1, 0, 1, 2211.0),
+    (2, -1, -1, -1, 2065.0),
+    (0, 1, -1, -1, -1870.0),
+    (4, 0, -1, -1, 1828.0),
+    (0, 1, 0, 1, -1794.0),
+    (0, 0, 0, 3, -1749.0),
+    (0, 1, -1, 1, -1565.0),
+    (1, 0, 0, 1, -1491.0),
+    (0, 1, 1, 1, -1475.0),
+    (0, 1, 1, -1, -1410.0),
+    (0, 1, 0, -1, -1344.0),
+    (1, 0, 0, -1, -1335.0),
+    (0, 0, 3, 1, 1107.0),
+    (4, 0, 0, -1, 1021.0),
+    (4, 0, -1, 1, 833.0),
+    (0, 0, 1, -3, 777.0),
+    (4, 0, -2, 1, 671.0),
+    (2, 0, 0, -3, 607.0),
+    (2, 0, 2, -1, 596.0),
+    (2, -1, 1, -1, 491.0),
+    (2, 0, -2, 1, -451.0),
+    (0, 0, 3, -1, 439.0),
+    (2, 0, 2, 1, 422.0),
+    (2, 0, -3, -1, 421.0),
+    (2, 1, -1, 1, -366.0),
+    (2, 1, 0, 1, -351.0),
+    (4, 0, 0, 1, 331.0),
+    (2, -1, 1, 1, 315.0),
+    (2, -2, 0, -1, 302.0),
+    (0, 0, 1, 3, -283.0),
+    (2, 1, 1, -1, -229.0),
+    (1, 1, 0, -1, 223.0),
+    (1, 1, 0, 1, 223.0),
+    (0, 1, -2, -1, -220.0),
+    (2, 1, -1, -1, -220.0),
+    (1, 0, 1, 1, -185.0),
+    (2, -1, -2, -1, 181.0),
+    (0, 1, 2, 1, -177.0),
+    (4, 0, -2, -1, 176.0),
+    (4, -1, -1, -1, 166.0),
+    (1, 0, 1, -1, -164.0),
+    (4, 0, 1, -1, 132.0),
+    (1, 0, -1, -1, -119.0),
+    (4, -1, 0, -1, 115.0),
+    (2, -2, 0, 1, 107.0),
+];
+
+/// The moon's position about the earth, km, on the ecliptic of J2000, `t`
+/// centuries after J2000.
+fn moon_position(t: f64) -> Vector3<f64> {
+    let poly = |c: [f64; 5]| c[0] + t * (c[1] + t * (c[2] + t * (c[3] + t * c[4])));
+    // The moon's mean longitude, its mean elongation, the sun's mean anomaly,
+    // the moon's, and its argument of latitude.
+    let l = poly([
+        218.3164477,
+        481_267.88123421,
+        -0.0015786,
+        1.0 / 538_841.0,
+        -1.0 / 65_194_000.0,
+    ]);
+    let d = poly([
+        297.8501921,
+        445_267.1114034,
+        -0.0018819,
+        1.0 / 545_868.0,
+        -1.0 / 113_065_000.0,
+    ]);
+    let m = poly([
+        357.5291092,
+        35_999.0502909,
+        -0.0001536,
+        1.0 / 24_490_000.0,
+        0.0,
+    ]);
+    let mm = poly([
+        134.9633964,
+        477_198.8675055,
+        0.0087414,
+        1.0 / 69_699.0,
+        -1.0 / 14_712_000.0,
+    ]);
+    let f = poly([
+        93.2720950,
+        483_202.0175233,
+        -0.0036539,
+        -1.0 / 3_526_000.0,
+        1.0 / 863_310_000.0,
+    ]);
+    // The earth's orbit is getting rounder, which weakens the terms in M.
+    let e = 1.0 - 0.002516 * t - 0.0000074 * t * t;
+    let argument = |dd: i8, ms: i8, mm_: i8, ff: i8| {
+        let scale = e.powi(ms.unsigned_abs() as i32);
+        let angle = (dd as f64 * d + ms as f64 * m + mm_ as f64 * mm + ff as f64 * f).to_radians();
+        (angle, scale)
+    };
+
+    let mut lon = 0.0;
+    let mut dist = 0.0;
+    for &(dd, ms, mm_, ff, sl, sr) in &MOON_LONGITUDE {
+        let (angle, scale) = argument(dd, ms, mm_, ff);
+        lon += sl * scale * angle.sin();
+        dist += sr * scale * angle.cos();
+    }
+    let mut lat = 0.0;
+    for &(dd, ms, mm_, ff, sb) in &MOON_LATITUDE {
+        let (angle, scale) = argument(dd, ms, mm_, ff);
+        lat += sb * scale * angle.sin();
+    }
+    // Venus, Jupiter, and the flattening of the earth.
+    let a1 = (119.75 + 131.849 * t).to_radians();
+    let a2 = (53.09 + 479_264.290 * t).to_radians();
+    let a3 = (313.45 + 481_266.484 * t).to_radians();
+    let (lr, mr, fr) = (l.to_radians(), mm.to_radians(), f.to_radians());
+    lon += 3958.0 * a1.sin() + 1962.0 * (lr - fr).sin() + 318.0 * a2.sin();
+    lat += -2235.0 * lr.sin()
+        + 382.0 * a3.sin()
+        + 175.0 * (a1 - fr).sin()
+        + 175.0 * (a1 + fr).sin()
+        + 127.0 * (lr - mr).sin()
+        - 115.0 * (lr + mr).sin();
+
+    let lon = (l + lon / 1.0e6).to_radians();
+    let lat = (lat / 1.0e6).to_radians();
+    let dist = 385_000.56 + dist / 1000.0;
+    let (lon, lat) = to_j2000(lon, lat, t);
+    Vector3::new(
+        dist * lat.cos() * lon.cos(),
+        dist * lat.cos() * lon.sin(),
+        dist * lat.sin(),
+    )
+}
+
+/// An ecliptic longitude and latitude (radians) on the mean ecliptic and
+/// equinox `t` centuries after J2000, on those of J2000 (Meeus, 21.5).
+fn to_j2000(lon: f64, lat: f64, t: f64) -> (f64, f64) {
+    // From the date, back to J2000.
+    let (big_t, t) = (t, -t);
+    let arcsec = |x: f64| (x / 3600.0).to_radians();
+    let eta = arcsec(
+        (47.0029 - 0.06603 * big_t + 0.000598 * big_t * big_t) * t
+            + (-0.03302 + 0.000598 * big_t) * t * t
+            + 0.000060 * t * t * t,
+    );
+    let pi = 174.876384_f64.to_radians()
+        + arcsec(
+            3289.4789 * big_t + 0.60622 * big_t * big_t - (869.8089 + 0.50491 * big_t) * t
+                + 0.03536 * t * t,
+        );
+    let p = arcsec(
+        (5029.0966 + 2.22226 * big_t - 0.000042 * big_t * big_t) * t
+            + (1.11113 - 0.000042 * big_t) * t * t
+            - 0.000006 * t * t * t,
+    );
+    let a = eta.cos() * lat.cos() * (pi - lon).sin() - eta.sin() * lat.sin();
+    let b = lat.cos() * (pi - lon).cos();
+    let c = eta.cos() * lat.sin() + eta.sin() * lat.cos() * (pi - lon).sin();
+    (p + pi - a.atan2(b), c.asin())
+}
+
+/// The states of all the bodies, about the solar system barycenter, at `et`.
+fn states(et: f64) -> Vec<(i32, OrbitalBody)> {
+    let t = et / CENTURY;
+    let sun_gm = CONSTANTS[0].gm;
+    let gm = |id: i32| constants(id).map_or(0.0, |c| c.gm);
+
+    // About the sun, first.
+    let mut states = vec![(SUN_ID, Vector3::zeros(), Vector3::zeros())];
+    for elements in &ELEMENTS {
+        if elements.id == 3 {
+            let (pos, vel) = elements.state(t, sun_gm + gm(399) + gm(301));
+            // The moon's velocity, from either side.
+            let h = 60.0;
+            let moon = moon_position(t);
+            let moon_vel =
+                (moon_position(t + h / CENTURY) - moon_position(t - h / CENTURY)) / (2.0 * h);
+            let f = EARTH_FRACTION;
+            states.push((399, pos - moon * (1.0 - f), vel - moon_vel * (1.0 - f)));
+            states.push((301, pos + moon * f, vel + moon_vel * f));
+        } else {
+            let (pos, vel) = elements.state(t, sun_gm + gm(elements.id));
+            states.push((elements.id, pos, vel));
+        }
+    }
+
+    // Then about the barycenter.
+    let total: f64 = states.iter().map(|(id, ..)| gm(*id)).sum();
+    let center_pos = states
+        .iter()
+        .map(|(id, pos, _)| pos * gm(*id))
+        .sum::<Vector3<f64>>()
+        / total;
+    let center_vel = states
+        .iter()
+        .map(|(id, _, vel)| vel * gm(*id))
+        .sum::<Vector3<f64>>()
+        / total;
+    states
+        .into_iter()
+        .map(|(id, pos, vel)| {
+            (
+                id,
+                OrbitalBody {
+                    pos: pos - center_pos,
+                    vel: vel - center_vel,
+                },
+            )
+        })
+        .collect()
+}
+
+/// The state of the body `id` at `et`, about the solar system barycenter, on
+/// the ecliptic of J2000, as from the kernels, if it is one of `IDS`.
+pub fn state(id: i32, et: f64) -> Option<OrbitalBody> {
+    states(et)
+        .into_iter()
+        .find(|(other, _)| *other == id)
+        .map(|(_, state)| state)
+}
+
+/// The IAU rotation model of the body `id`, with the constants the kernels
+/// have, `d` days and `t` centuries after J2000: the right ascension and
+/// declination of its pole, and its prime meridian, in degrees, and how fast
+/// that turns, degrees a day.
+fn rotation(id: i32, d: f64, t: f64) -> Option<(f64, f64, f64, f64)> {
+    let sin = |x: f64| x.to_radians().sin();
+    let cos = |x: f64| x.to_radians().cos();
+    Some(match id {
+        10 => (286.13, 63.87, 84.176 + 14.1844000 * d, 14.1844000),
+        199 => (
+            281.0097 - 0.0328 * t,
+            61.4143 - 0.0049 * t,
+            329.5988 + 6.1385108 * d,
+            6.1385108,
+        ),
+        299 => (272.76, 67.16, 160.20 - 1.4813688 * d, -1.4813688),
+        399 => (
+            -0.641 * t,
+            90.0 - 0.557 * t,
+            190.147 + 360.9856235 * d,
+            360.9856235,
+        ),
+        301 => {
+            // Only the two largest of the periodic terms, which follow the
+            // regression of the moon's nodes.
+            let e1 = 125.045 - 0.0529921 * d;
+            let e2 = 250.089 - 0.1059842 * d;
+            (
+                269.9949 + 0.0031 * t - 3.8787 * sin(e1) - 0.1204 * sin(e2),
+                66.5392 + 0.0130 * t + 1.5419 * cos(e1) + 0.0239 * cos(e2),
+                38.3213 + 13.17635815 * d + 3.5610 * sin(e1) + 0.1208 * sin(e2),
+                13.17635815,
+            )
+        }
+        499 => (
+            317.68143 - 0.1061 * t,
+            52.88650 - 0.0609 * t,
+            176.630 + 350.89198226 * d,
+            350.89198226,
+        ),
+        599 => (
+            268.056595 - 0.006499 * t,
+            64.495303 + 0.002413 * t,
+            284.95 + 870.5360000 * d,
+            870.5360000,
+        ),
+        699 => (
+            40.589 - 0.036 * t,
+            83.537 - 0.004 * t,
+            38.90 + 810.7939024 * d,
+            810.7939024,
+        ),
+        799 => (257.311, -15.175, 203.81 - 501.1600928 * d, -501.1600928),
+        899 => {
+            let n = 357.85 + 52.316 * t;
+            (
+                299.36 + 0.70 * sin(n),
+                43.46 - 0.51 * cos(n),
+                249.978 + 541.1397757 * d - 0.48 * sin(n),
+                541.1397757,
+            )
+        }
+        999 => (132.993, -6.163, 302.695 + 56.3625225 * d, 56.3625225),
+        _ => return None,
+    })
+}
+
+/// The attitude of the body `id` at `et`, from its IAU rotation model, as
+/// `sim_spice` takes it from the body's IAU frame, if it is one of `IDS`.
+pub fn attitude(id: i32, et: f64) -> Option<AttitudeState> {
+    let (ra, dec, w, rate) = rotation(id, et / DAY, et / CENTURY)?;
+    let about = |axis, degrees: f64| UnitQuaternion::from_axis_angle(&axis, degrees.to_radians());
+    let x = Vector3::x_axis();
+    let z = Vector3::z_axis();
+    let q_bw = frames::eme2000_to_ecliptic()
+        * UnitQuaternion::from_axis_angle(&z, ra.to_radians() + FRAC_PI_2)
+        * UnitQuaternion::from_axis_angle(&x, FRAC_PI_2 - dec.to_radians())
+        * about(z, w);
+    Some(AttitudeState {
+        q_bw,
+        // As `sim_spice` has it.
+        omega_b: Vector3::new(0.0, 0.0, -rate.to_radians() / DAY),
+    })
+}
+
+/// The body `id` at `et`, if it is one of `IDS`.
+pub fn body(id: i32, et: f64) -> Option<Body> {
+    let constants = constants(id)?;
+    Some(Body {
+        id: SpiceId(id),
+        name: Name::new(constants.name),
+        massive: MassiveBody { gm: constants.gm },
+        orbital: state(id, et)?,
+        size: SizedBody {
+            radii: Vector3::from(constants.radii),
+        },
+        attitude: attitude(id, et)?,
+        parent: None,
+    })
+}
+
+/// The solar system, as worked out here, at the game's start.
+pub fn solar_state() -> SolarState {
+    solar_state_with(&mut |_| {})
+}
+
+/// The solar system, as `solar_state` has it, giving each body as it is
+/// worked out to `progress` (see `loading`).
+pub fn solar_state_with(progress: &mut dyn FnMut(LoadMessage)) -> SolarState {
+    progress(LoadMessage::Stage(
+        "Working out the ephemerides".to_string(),
+    ));
+    let mut bodies = Vec::new();
+    for (id, orbital) in states(EPOCH) {
+        let (Some(constants), Some(attitude)) = (constants(id), attitude(id, EPOCH)) else {
+            continue;
+        };
+        let body = Body {
+            id: SpiceId(id),
+            name: Name::new(constants.name),
+            massive: MassiveBody { gm: constants.gm },
+            orbital,
+            size: SizedBody {
+                radii: Vector3::from(constants.radii),
+            },
+            attitude,
+            parent: None,
+        };
+        progress(LoadMessage::Body(body.clone()));
+        bodies.push(body);
+    }
+    // Heaviest first, as from the kernels.
+    bodies.sort_by(|a, b| b.massive.gm.total_cmp(&a.massive.gm));
+    SolarState {
+        et: EPOCH,
+        time: EPOCH_NAME.to_string(),
+        bodies,
+        coverage: Some(COVERAGE),
+        small_bodies: Vec::new(),
+        barycenters: BARYCENTERS
+            .iter()
+            .zip(1..)
+            .map(|(name, id)| Barycenter {
+                id,
+                name: name.to_string(),
+            })
+            .collect(),
+    }
+}
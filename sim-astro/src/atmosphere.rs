@@ -0,0 +1,159 @@
+//! Atmospheres.
+//!
+//! The density is modeled as a stack of exponential layers, each with its own
+//! base density and scale height, which is crude, but good to within the
+//! day-to-day variation of the real upper atmosphere.  The air turns with the
+//! body, so the wind a craft sees is its velocity relative to the rotating
+//! surface.
+//!
+//! Two altitudes are read off the layers, for the map: the atmosphere's
+//! effective thickness, where entry starts, and the lowest safe periapsis,
+//! above which drag takes many orbits to bring a craft down.
+//!
+//! The speed of sound, for the Mach number, goes with the air's temperature,
+//! and is given at a few altitudes, straight between them, and held past the
+//! last.  For the earth, these are the breaks in the 1976 standard
+//! atmosphere's temperature, up to where it stops being one gas.
+
+use bevy::prelude::*;
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+use sim_core::{AttitudeState, OrbitalBody};
+
+/// The density, kg/m^3, taken as the top of the atmosphere, as far as a craft
+/// goes: where entry starts, about 120 km up on the earth.
+pub const ENTRY_DENSITY: f64 = 2.0e-8;
+
+/// The density, kg/m^3, below which a periapsis is safe for a while: about
+/// 240 km up on the earth.
+pub const SAFE_DENSITY: f64 = 1.0e-10;
+
+/// The atmosphere of a body.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct Atmosphere {
+    /// The layers, lowest first, as (base altitude km, density at the base
+    /// kg/m^3, scale height km).
+    pub layers: Vec<[f64; 3]>,
+    /// Above this altitude, in km, there is taken to be no atmosphere at all.
+    pub top: f64,
+    /// The speed of sound, as (altitude km, speed m/s), lowest first, or
+    /// nothing if it isn't known.
+    #[serde(default)]
+    pub sound: Vec<[f64; 2]>,
+}
+
+impl Atmosphere {
+    /// The earth's atmosphere, from the exponential model in Vallado,
+    /// "Fundamentals of Astrodynamics and Applications", table 8-4.
+    pub fn earth() -> Self {
+        Atmosphere {
+            layers: vec![
+                [0.0, 1.225, 7.249],
+                [25.0, 3.899e-2, 6.349],
+                [30.0, 1.774e-2, 6.682],
+                [40.0, 3.972e-3, 7.554],
+                [50.0, 1.057e-3, 8.382],
+                [60.0, 3.206e-4, 7.714],
+                [70.0, 8.770e-5, 6.549],
+                [80.0, 1.905e-5, 5.799],
+                [90.0, 3.396e-6, 5.382],
+                [100.0, 5.297e-7, 5.877],
+                [110.0, 9.661e-8, 7.263],
+                [120.0, 2.438e-8, 9.473],
+                [130.0, 8.484e-9, 12.636],
+                [140.0, 3.845e-9, 16.149],
+                [150.0, 2.070e-9, 22.523],
+                [180.0, 5.464e-10, 29.740],
+                [200.0, 2.789e-10, 37.105],
+                [250.0, 7.248e-11, 45.546],
+                [300.0, 2.418e-11, 53.628],
+                [350.0, 9.518e-12, 53.298],
+                [400.0, 3.725e-12, 58.515],
+                [450.0, 1.585e-12, 60.828],
+                [500.0, 6.967e-13, 63.822],
+                [600.0, 1.454e-13, 71.835],
+                [700.0, 3.614e-14, 88.667],
+                [800.0, 1.170e-14, 124.64],
+                [900.0, 5.245e-15, 181.05],
+                [1000.0, 3.019e-15, 268.00],
+            ],
+            top: 1500.0,
+            sound: vec![
+                [0.0, 340.29],
+                [11.0, 295.07],
+                [20.0, 295.07],
+                [32.0, 303.13],
+                [47.0, 329.80],
+                [51.0, 329.80],
+                [71.0, 293.70],
+                [84.85, 274.10],
+            ],
+        }
+    }
+
+    /// The density, in kg/m^3, at the given altitude, in km.
+    pub fn density(&self, alt: f64) -> f64 {
+        if alt > self.top {
+            return 0.0;
+        }
+        let alt = alt.max(0.0);
+        let layer = self
+            .layers
+            .iter()
+            .rev()
+            .find(|layer| layer[0] <= alt)
+            .or(self.layers.first());
+        match layer {
+            Some([base, density, scale]) => density * (-(alt - base) / scale).exp(),
+            None => 0.0,
+        }
+    }
+
+    /// The speed of sound, in m/s, at the given altitude, in km, if it is
+    /// known.
+    pub fn speed_of_sound(&self, alt: f64) -> Option<f64> {
+        let above = self.sound.partition_point(|point| point[0] <= alt);
+        match (self.sound.get(above.wrapping_sub(1)), self.sound.get(above)) {
+            (Some([alt0, speed0]), Some([alt1, speed1])) => {
+                Some(speed0 + (speed1 - speed0) * (alt - alt0) / (alt1 - alt0))
+            }
+            (Some([_, speed]), None) | (None, Some([_, speed])) => Some(*speed),
+            (None, None) => None,
+        }
+    }
+
+    /// The altitude, in km, at which the density falls to `density`, or the top
+    /// if it is still more there.
+    pub fn altitude_at(&self, density: f64) -> f64 {
+        for (i, [base, base_density, scale]) in self.layers.iter().enumerate() {
+            let next = self.layers.get(i + 1).map_or(self.top, |layer| layer[0]);
+            let alt = base + scale * (base_density / density).ln();
+            if alt < next {
+                return alt.max(*base);
+            }
+        }
+        self.top
+    }
+
+    /// The effective thickness of the atmosphere, in km.
+    pub fn thickness(&self) -> f64 {
+        self.altitude_at(ENTRY_DENSITY)
+    }
+
+    /// The lowest altitude, in km, at which a periapsis is safe.
+    pub fn safe_periapsis(&self) -> f64 {
+        self.altitude_at(SAFE_DENSITY)
+    }
+
+    /// The velocity, in km/s, of a craft at `pos` moving at `vel` (both world
+    /// frame) relative to the air of the given body.
+    pub fn wind_relative(
+        body: &OrbitalBody,
+        attitude: &AttitudeState,
+        pos: &Vector3<f64>,
+        vel: &Vector3<f64>,
+    ) -> Vector3<f64> {
+        let omega_w = attitude.q_bw.transform_vector(&attitude.omega_b);
+        vel - body.vel - omega_w.cross(&(pos - body.pos))
+    }
+}
@@ -0,0 +1,166 @@
+//! Collisions between crafts and bodies.
+//!
+//! After each physics step, every craft (anything with an orbit, but no mass
+//! of its own) is checked against the surface of every sized body.  The
+//! surface is the body's ellipsoid, raised by its `Terrain` if it has one.  A
+//! craft that reaches it slowly enough lands (see `contact`), and one that
+//! doesn't is wrecked, and frozen.  Either way, a `CollisionEvent` is sent with
+//! where it hit, and how fast.
+
+use bevy::prelude::*;
+use sim_core::{
+    AttitudeState, MassiveBody, OrbitalBody, PhysicsModels, PostPhysicsSet, SizedBody,
+    model_enabled,
+    watchdog::{Frozen, physics_watchdog},
+};
+
+use crate::{
+    contact::{LANDING_MAX_SPEED, Landed, landed_step},
+    geodesy::{Geodetic, world_to_body},
+};
+
+/// A height model for the surface of a body, such as a height map or a DEM.
+pub trait TerrainModel: Send + Sync {
+    /// The height of the surface, in km, above the body's ellipsoid at the
+    /// given geodetic latitude and longitude (radians).
+    fn height(&self, lat: f64, lon: f64) -> f64;
+
+    /// The greatest height anywhere, in km.  Crafts farther out than this are
+    /// not looked at any closer.
+    fn max_height(&self) -> f64;
+}
+
+/// The terrain of a body.  Bodies without one are smooth ellipsoids.
+// Nothing has terrain yet.
+#[allow(dead_code)]
+#[derive(Component)]
+pub struct Terrain(pub Box<dyn TerrainModel>);
+
+/// Sent when a craft hits the surface of a body.
+#[derive(Clone, Debug, Message)]
+pub struct CollisionEvent {
+    pub craft: Entity,
+    pub body: Entity,
+    /// The sim time of the step it was found on.
+    pub time: f64,
+    /// The speed of the craft relative to the (rotating) surface, in km/s.
+    pub impact_speed: f64,
+    /// Whether it was slow enough to land, rather than crash.
+    pub landed: bool,
+    /// Where it hit.  The altitude is how far the craft was below the surface
+    /// when it was found, as a negative value.
+    pub location: Geodetic,
+}
+
+#[derive(Default)]
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<CollisionEvent>();
+        PhysicsModels::add(app, "collision");
+        app.add_systems(
+            FixedUpdate,
+            (collision_check, landed_step)
+                .chain()
+                .in_set(PostPhysicsSet)
+                .after(physics_watchdog)
+                .run_if(model_enabled("collision")),
+        );
+        app.add_systems(Update, log_collisions);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn collision_check(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut collisions: MessageWriter<CollisionEvent>,
+    crafts: Query<
+        (Entity, &OrbitalBody, &AttitudeState),
+        (Without<MassiveBody>, Without<Frozen>, Without<Landed>),
+    >,
+    bodies: Query<(
+        Entity,
+        &Name,
+        &OrbitalBody,
+        &SizedBody,
+        &AttitudeState,
+        Option<&Terrain>,
+    )>,
+) {
+    for (craft, orbital, craft_attitude) in crafts.iter() {
+        for (body, name, body_orbital, size, attitude, terrain) in bodies.iter() {
+            // A small body is a craft, as far as moving goes, but has a
+            // surface, too.
+            if body == craft {
+                continue;
+            }
+            let reach = size.radii.max() + terrain.map_or(0.0, |t| t.0.max_height());
+            if (orbital.pos - body_orbital.pos).norm() > reach {
+                continue;
+            }
+
+            let pos_f = world_to_body(body_orbital, attitude, &orbital.pos);
+            let location = Geodetic::from_body(&pos_f, &size.radii);
+            let surface = terrain.map_or(0.0, |t| t.0.height(location.lat, location.lon));
+            if location.alt > surface {
+                continue;
+            }
+
+            // The velocity relative to the ground under the craft.
+            let omega_w = attitude.q_bw.transform_vector(&attitude.omega_b);
+            let ground = omega_w.cross(&(orbital.pos - body_orbital.pos));
+            let impact_speed = (orbital.vel - body_orbital.vel - ground).norm();
+
+            let landed = impact_speed <= LANDING_MAX_SPEED;
+            if landed {
+                commands.entity(craft).insert(Landed::touch_down(
+                    body,
+                    &location,
+                    surface,
+                    &size.radii,
+                    craft_attitude,
+                    attitude,
+                ));
+            } else {
+                commands.entity(craft).insert(Frozen {
+                    reason: format!("crashed into {} at {:.1} m/s", name, impact_speed * 1000.0),
+                });
+            }
+            collisions.write(CollisionEvent {
+                craft,
+                body,
+                time: time.elapsed_secs_f64(),
+                impact_speed,
+                landed,
+                location: Geodetic {
+                    alt: location.alt - surface,
+                    ..location
+                },
+            });
+            break;
+        }
+    }
+}
+
+fn log_collisions(mut collisions: MessageReader<CollisionEvent>, names: Query<&Name>) {
+    for hit in collisions.read() {
+        let name = |e: Entity| {
+            names
+                .get(e)
+                .map_or_else(|_| format!("{}", e), |n| n.to_string())
+        };
+        info!(
+            "{} at {:.3} s: {} hit {} at {:.1} m/s, lat {:.4}, lon {:.4}, {:.3} km deep",
+            if hit.landed { "Landing" } else { "Crash" },
+            hit.time,
+            name(hit.craft),
+            name(hit.body),
+            hit.impact_speed * 1000.0,
+            hit.location.lat.to_degrees(),
+            hit.location.lon.to_degrees(),
+            -hit.location.alt
+        );
+    }
+}
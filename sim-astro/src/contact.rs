@@ -0,0 +1,152 @@
+//! Surface contact.
+//!
+//! A craft that touches down gently enough is `Landed`: it sits on the surface,
+//! and is carried around with the body as it turns.  The ground pushes back
+//! with whatever normal force is needed to hold it up, and friction, in
+//! proportion to that, holds it in place unless its thrust along the surface
+//! is enough to make it slide.  Once its thrust away from the surface is more
+//! than its weight, it lifts off, and is back to ordinary orbital physics.
+//!
+//! A craft that is also `Clamped`, as by the launch clamps on a pad, is held
+//! rigid: it neither slides nor lifts off, whatever its thrust, until the
+//! clamps let go.
+
+use bevy::prelude::*;
+use na::{UnitQuaternion, Vector3};
+use sim_core::{AttitudeState, LinearControl, MassiveBody, OrbitalBody, SizedBody};
+
+use crate::{collision::Terrain, frames::Axes, geodesy::Geodetic};
+
+/// The fastest, in km/s relative to the ground, a craft can touch down without
+/// crashing.
+pub const LANDING_MAX_SPEED: f64 = 0.01;
+
+/// The coefficient of friction between a landed craft and the ground.
+const SURFACE_FRICTION: f64 = 0.5;
+
+/// A craft resting on the surface of a body.
+#[derive(Clone, Component, Debug)]
+pub struct Landed {
+    /// The body it is resting on.
+    pub body: Entity,
+    /// Where it is, in km, in the body fixed frame.
+    pub pos_f: Vector3<f64>,
+    /// Its orientation, craft body to the fixed frame of the body it sits on.
+    pub q_bf: UnitQuaternion<f64>,
+    /// How fast it is sliding, in km/s, in the body fixed frame.
+    pub slide_f: Vector3<f64>,
+}
+
+/// A landed craft held down, so that it stays put under any thrust.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Clamped;
+
+impl Landed {
+    /// Set a craft down on a body, where it is now.  `surface` is the terrain
+    /// height there.
+    pub fn touch_down(
+        body: Entity,
+        location: &Geodetic,
+        surface: f64,
+        radii: &Vector3<f64>,
+        craft_attitude: &AttitudeState,
+        body_attitude: &AttitudeState,
+    ) -> Self {
+        let pos_f = Geodetic {
+            alt: surface,
+            ..*location
+        }
+        .to_body(radii);
+        Landed {
+            body,
+            pos_f,
+            q_bf: body_attitude.q_bw.inverse() * craft_attitude.q_bw,
+            slide_f: Vector3::zeros(),
+        }
+    }
+}
+
+/// Carry landed crafts around with their body, sliding them where the thrust
+/// overcomes friction, and lifting them off where it overcomes their weight.
+#[allow(clippy::type_complexity)]
+pub(crate) fn landed_step(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut crafts: Query<(
+        Entity,
+        &mut OrbitalBody,
+        &mut AttitudeState,
+        &mut Landed,
+        Option<&LinearControl>,
+        Has<Clamped>,
+    )>,
+    bodies: Query<
+        (
+            &OrbitalBody,
+            &AttitudeState,
+            &SizedBody,
+            Option<&MassiveBody>,
+            Option<&Terrain>,
+        ),
+        Without<Landed>,
+    >,
+) {
+    let dt = time.delta_secs_f64();
+
+    for (craft, mut orbital, mut attitude, mut landed, control, clamped) in crafts.iter_mut() {
+        let Ok((body, body_attitude, size, massive, terrain)) = bodies.get(landed.body) else {
+            commands.entity(craft).remove::<Landed>();
+            continue;
+        };
+        let q_body = body_attitude.q_bw;
+        let omega_w = q_body.transform_vector(&body_attitude.omega_b);
+
+        // The craft's own acceleration, in the fixed frame, against the local
+        // gravity.
+        let accel_f = control.map_or(Vector3::zeros(), |c| {
+            q_body.inverse_transform_vector(&attitude.q_bw.transform_vector(&c.accel_b))
+        });
+        let up = Geodetic::from_body(&landed.pos_f, &size.radii).up();
+        let gravity = massive.map_or(0.0, |m| m.gm / landed.pos_f.norm_squared());
+        let lift = accel_f.dot(&up);
+
+        if clamped {
+            // The clamps take whatever the thrust is.
+            landed.slide_f = Vector3::zeros();
+        } else if lift <= gravity {
+            // The normal force (per unit mass) is whatever holds the craft up,
+            // and friction opposes sliding, up to its share of that.
+            let normal = gravity - lift;
+            landed.slide_f += (accel_f - up * lift) * dt;
+            let friction = SURFACE_FRICTION * normal * dt;
+            let speed = landed.slide_f.norm();
+            landed.slide_f = if speed <= friction {
+                Vector3::zeros()
+            } else {
+                landed.slide_f * ((speed - friction) / speed)
+            };
+
+            // Slide along, staying on the surface.
+            let moved = landed.pos_f + landed.slide_f * dt;
+            let location = Geodetic::from_body(&moved, &size.radii);
+            let surface = terrain.map_or(0.0, |t| t.0.height(location.lat, location.lon));
+            landed.pos_f = Geodetic {
+                alt: surface,
+                ..location
+            }
+            .to_body(&size.radii);
+            let up = location.up();
+            let slide = landed.slide_f;
+            landed.slide_f = slide - up * slide.dot(&up);
+        } else {
+            commands.entity(craft).remove::<Landed>();
+        }
+
+        // Pinned to the body, turning with it.
+        let (r_w, v_w) = Axes::body_fixed(body_attitude).from_frame(&landed.pos_f, &landed.slide_f);
+        orbital.pos = body.pos + r_w;
+        orbital.vel = body.vel + v_w;
+        attitude.q_bw = q_body * landed.q_bf;
+        attitude.omega_b = attitude.q_bw.inverse_transform_vector(&omega_w);
+    }
+}
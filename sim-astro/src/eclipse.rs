@@ -0,0 +1,107 @@
+//! Eclipses.
+//!
+//! The sun is a disk, not a point, so a body passing in front of it hides it a
+//! little at a time.  From the umbra, all of it is hidden; from the penumbra,
+//! some of it; and from the antumbra, beyond the umbra's point, the body is too
+//! small to cover it, and leaves a ring.  How much is left is the overlap of
+//! the two disks as seen from the craft (Montenbruck and Gill, "Satellite
+//! Orbits", 3.4.2).
+//!
+//! The sunlight also pushes, and powers the solar panels: both the solar
+//! radiation pressure and the flux fall off as the square of the distance from
+//! the sun, and with what is left of its disk.
+
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+
+/// The solar flux at 1 AU, W/m^2.
+pub const SOLAR_CONSTANT: f64 = 1361.0;
+
+/// The solar radiation pressure at 1 AU, N/m^2.
+pub const SOLAR_PRESSURE: f64 = 4.56e-6;
+
+/// The astronomical unit, km.
+pub const AU: f64 = 149_597_870.7;
+
+/// Where a craft is, as far as a body's shadow goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Shadow {
+    #[default]
+    Sunlit,
+    /// Some of the sun is hidden, or, in the antumbra, all but a ring of it.
+    Penumbra,
+    Umbra,
+}
+
+impl std::fmt::Display for Shadow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Shadow::Sunlit => write!(f, "sunlit"),
+            Shadow::Penumbra => write!(f, "penumbra"),
+            Shadow::Umbra => write!(f, "umbra"),
+        }
+    }
+}
+
+impl Shadow {
+    /// The shadow that leaves `fraction` of the sun's disk.
+    pub fn of(fraction: f64) -> Self {
+        if fraction >= 1.0 {
+            Shadow::Sunlit
+        } else if fraction <= 0.0 {
+            Shadow::Umbra
+        } else {
+            Shadow::Penumbra
+        }
+    }
+}
+
+/// The fraction of the sun's disk that a craft at `pos` sees past a body, from
+/// 0 in the umbra to 1 in full sun.  The sun, of radius `sun_radius`, is at
+/// `sun`, and the body, of radius `radius`, at `body`, all in km.
+pub fn sunlight(
+    pos: &Vector3<f64>,
+    sun: &Vector3<f64>,
+    sun_radius: f64,
+    body: &Vector3<f64>,
+    radius: f64,
+) -> f64 {
+    let to_sun = sun - pos;
+    let to_body = body - pos;
+    // A body beyond the sun, or the craft inside the body, which the surface
+    // is left to deal with.
+    if to_body.norm() >= to_sun.norm() || to_body.norm() <= radius {
+        return 1.0;
+    }
+    // The apparent radii of the two disks, and the angle between them.
+    let a = (sun_radius / to_sun.norm()).asin();
+    let b = (radius / to_body.norm()).asin();
+    let c = to_sun.angle(&to_body);
+    if c >= a + b {
+        1.0
+    } else if c <= b - a {
+        0.0
+    } else if c <= a - b {
+        1.0 - (b * b) / (a * a)
+    } else {
+        // Two disks overlapping, in part.
+        let x = (c * c + a * a - b * b) / (2.0 * c);
+        let y = (a * a - x * x).max(0.0).sqrt();
+        let area = a * a * (x / a).clamp(-1.0, 1.0).acos()
+            + b * b * ((c - x) / b).clamp(-1.0, 1.0).acos()
+            - c * y;
+        1.0 - area / (std::f64::consts::PI * a * a)
+    }
+}
+
+/// The solar radiation pressure, N/m^2, at `distance` km from the sun, with
+/// `fraction` of its disk showing.
+pub fn pressure(distance: f64, fraction: f64) -> f64 {
+    SOLAR_PRESSURE * (AU / distance).powi(2) * fraction
+}
+
+/// The solar flux, W/m^2, at `distance` km from the sun, with `fraction` of
+/// its disk showing.
+pub fn flux(distance: f64, fraction: f64) -> f64 {
+    SOLAR_CONSTANT * (AU / distance).powi(2) * fraction
+}
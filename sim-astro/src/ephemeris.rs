@@ -0,0 +1,185 @@
+//! A cache of the bodies' ephemerides, as Chebyshev series.
+//!
+//! SPICE can say where any body is at any time, but each call takes its lock,
+//! and goes to the kernels, which is fine for a lookup now and then, and much
+//! too slow for every body on every step.  So the states are sampled once, at
+//! coarse intervals, and each interval is fitted with a Chebyshev series for
+//! each component of the position and of the velocity, as SPK kernels of
+//! type 3 do.  A state is then a few dozen multiplications, with no lock and
+//! no SPICE at all, so the cache can be saved, and used without the kernels.
+//!
+//! An interval has to be short against the body's orbit about its primary
+//! for the fit to hold: a day does for the planets, while the moons close
+//! in to theirs need hours.  `sim_spice::cache` picks each body's from its
+//! period, and does the sampling.  Outside of what was sampled, there is no
+//! state; it isn't extrapolated.
+
+use bevy::prelude::*;
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+use sim_core::OrbitalBody;
+
+/// The degree of the series, unless told otherwise.
+pub const DEFAULT_DEGREE: usize = 12;
+
+/// One body's ephemeris, in equal intervals from `start`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BodyEphemeris {
+    /// Its NAIF id.
+    pub id: i32,
+    /// The start of the first interval, in seconds past J2000.
+    pub start: f64,
+    /// The length of each interval, in s.
+    pub interval: f64,
+    /// The coefficients for each interval: for each degree, from 0, the
+    /// position (km) and the velocity (km/s).
+    pub segments: Vec<Vec<[f64; 6]>>,
+}
+
+impl BodyEphemeris {
+    /// Fit the ephemeris from `start` to `stop`, in intervals no longer than
+    /// `interval`, with series of the given degree.  `sample` gives the
+    /// states at the times asked for, all at once.
+    pub fn fit<F>(
+        id: i32,
+        start: f64,
+        stop: f64,
+        interval: f64,
+        degree: usize,
+        sample: F,
+    ) -> Result<Self, String>
+    where
+        F: FnOnce(&[f64]) -> Result<Vec<[f64; 6]>, String>,
+    {
+        if !(stop > start && interval > 0.0) {
+            return Err(format!("Nothing to fit from {} to {}", start, stop));
+        }
+        let count = ((stop - start) / interval).ceil().max(1.0) as usize;
+        let interval = (stop - start) / count as f64;
+        let n = degree + 1;
+        let nodes = nodes(n);
+
+        let times: Vec<f64> = (0..count)
+            .flat_map(|i| {
+                let mid = start + (i as f64 + 0.5) * interval;
+                nodes.iter().map(move |x| mid + 0.5 * interval * x)
+            })
+            .collect();
+        let states = sample(&times)?;
+        if states.len() != times.len() {
+            return Err(format!(
+                "Asked for {} states, and had {}",
+                times.len(),
+                states.len()
+            ));
+        }
+
+        let segments = states
+            .chunks(n)
+            .map(|values| {
+                (0..n)
+                    .map(|j| {
+                        let mut c = [0.0; 6];
+                        for (k, value) in values.iter().enumerate() {
+                            let w = (std::f64::consts::PI * j as f64 * (k as f64 + 0.5) / n as f64)
+                                .cos();
+                            for (c, v) in c.iter_mut().zip(value) {
+                                *c += v * w;
+                            }
+                        }
+                        let scale = if j == 0 { 1.0 } else { 2.0 } / n as f64;
+                        c.map(|c| c * scale)
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(BodyEphemeris {
+            id,
+            start,
+            interval,
+            segments,
+        })
+    }
+
+    /// The end of the last interval.
+    pub fn stop(&self) -> f64 {
+        self.start + self.interval * self.segments.len() as f64
+    }
+
+    /// The state at `et`, or None if it is outside of what was fitted.
+    pub fn state(&self, et: f64) -> Option<OrbitalBody> {
+        if et < self.start || et > self.stop() {
+            return None;
+        }
+        let i = (((et - self.start) / self.interval) as usize).min(self.segments.len() - 1);
+        let mid = self.start + (i as f64 + 0.5) * self.interval;
+        let x = (et - mid) / (0.5 * self.interval);
+        let s = clenshaw(&self.segments[i], x);
+        Some(OrbitalBody {
+            pos: Vector3::new(s[0], s[1], s[2]),
+            vel: Vector3::new(s[3], s[4], s[5]),
+        })
+    }
+}
+
+/// The Chebyshev nodes, on -1 to 1, from the top down.
+fn nodes(n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|k| (std::f64::consts::PI * (k as f64 + 0.5) / n as f64).cos())
+        .collect()
+}
+
+/// The sum of the series with coefficients `c` at `x`.
+fn clenshaw(c: &[[f64; 6]], x: f64) -> [f64; 6] {
+    let mut b1 = [0.0; 6];
+    let mut b2 = [0.0; 6];
+    for c in c.iter().skip(1).rev() {
+        let mut b = [0.0; 6];
+        for m in 0..6 {
+            b[m] = 2.0 * x * b1[m] - b2[m] + c[m];
+        }
+        b2 = b1;
+        b1 = b;
+    }
+    let mut s = [0.0; 6];
+    for m in 0..6 {
+        s[m] = x * b1[m] - b2[m] + c[0][m];
+    }
+    s
+}
+
+/// The bodies' fitted ephemerides.  Empty, unless filled from SPICE (see
+/// `sim_spice::cache`), or loaded.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EphemerisCache {
+    pub bodies: Vec<BodyEphemeris>,
+}
+
+impl EphemerisCache {
+    /// Add a body's ephemeris, in place of any it had.
+    pub fn insert(&mut self, body: BodyEphemeris) {
+        self.bodies.retain(|b| b.id != body.id);
+        self.bodies.push(body);
+    }
+
+    pub fn get(&self, id: i32) -> Option<&BodyEphemeris> {
+        self.bodies.iter().find(|b| b.id == id)
+    }
+
+    /// The state of the body with NAIF id `id` at `et`, if it is in the
+    /// cache, and the cache goes that far.
+    pub fn state(&self, id: i32, et: f64) -> Option<OrbitalBody> {
+        self.get(id)?.state(et)
+    }
+
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+    }
+
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer(std::io::BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
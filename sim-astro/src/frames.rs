@@ -0,0 +1,248 @@
+//! Reference frames.
+//!
+//! The sim keeps everything in the inertial frame (ECLIPJ2000), relative to
+//! the solar system barycenter.  `Frames` gives the state of any entity in some
+//! other frame and center, in a single call, so that nothing else has to redo
+//! the frame math.  Frames are written as `kind:center`, such as `j2000:earth`,
+//! `fixed:moon`, or `lvlh:playership`.
+//!
+//! Underneath, a frame's axes are an `Axes`, which turns states into and out
+//! of them, and the ways to make the ones the sim uses are here too: a body's
+//! own, an orbit's LVLH, and the local horizon, of which a surface's east,
+//! north and up is one.  Anything that needs those by itself, rather than for
+//! an entity, uses these, so that there is only the one of each.  So does
+//! the turn from the earth's equator to the ecliptic, for what comes in or
+//! goes out on the equator.  The last step, from the sim's axes to Bevy's
+//! for drawing, is `sim_render`'s.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use na::{Rotation3, UnitQuaternion, Vector3};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody};
+
+/// The obliquity of the ecliptic at J2000, degrees, as SPICE's ECLIPJ2000
+/// has it.
+pub const OBLIQUITY_J2000: f64 = 84_381.448 / 3600.0;
+
+/// From the earth's mean equator of J2000 (EME2000, or SPICE's J2000) to the
+/// ecliptic of J2000 the sim works in.  Its inverse goes the other way.
+pub fn eme2000_to_ecliptic() -> UnitQuaternion<f64> {
+    UnitQuaternion::from_axis_angle(&Vector3::x_axis(), -OBLIQUITY_J2000.to_radians())
+}
+
+/// A frame, and the entity it is centered on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Frame {
+    /// Inertial (ECLIPJ2000) axes.
+    Inertial(Entity),
+    /// Axes fixed to the center, rotating with it.
+    BodyFixed(Entity),
+    /// Local vertical, local horizontal of the center's orbit around whatever
+    /// is pulling on it the most.  X is radial (up), Z is the orbit normal, and
+    /// Y, along track, completes the set.
+    Lvlh(Entity),
+}
+
+impl Frame {
+    pub fn center(&self) -> Entity {
+        match *self {
+            Frame::Inertial(e) | Frame::BodyFixed(e) | Frame::Lvlh(e) => e,
+        }
+    }
+}
+
+/// A frame's axes, and how they turn.
+#[derive(Clone, Debug)]
+pub struct Axes {
+    /// From the frame's axes to the world's.
+    pub q_fw: UnitQuaternion<f64>,
+    /// Their angular velocity, rad/s, world frame.
+    pub omega_w: Vector3<f64>,
+}
+
+impl Axes {
+    /// The world's own, inertial, axes.
+    pub fn inertial() -> Self {
+        Axes {
+            q_fw: UnitQuaternion::identity(),
+            omega_w: Vector3::zeros(),
+        }
+    }
+
+    /// The axes fixed to something with `attitude`, turning with it.
+    pub fn body_fixed(attitude: &AttitudeState) -> Self {
+        Axes {
+            q_fw: attitude.q_bw,
+            omega_w: attitude.q_bw.transform_vector(&attitude.omega_b),
+        }
+    }
+
+    /// The LVLH axes of an orbit, at `r` and moving at `v`, relative to what
+    /// it is around: X radial (up), Z the orbit normal, and Y, along track,
+    /// completing the set.
+    pub fn lvlh(r: &Vector3<f64>, v: &Vector3<f64>) -> Self {
+        let h = r.cross(v);
+        let x = r.normalize();
+        let z = h.normalize();
+        let y = z.cross(&x);
+        Axes {
+            q_fw: UnitQuaternion::from_rotation_matrix(&Rotation3::from_basis_unchecked(&[
+                x, y, z,
+            ])),
+            omega_w: h / r.norm_squared(),
+        }
+    }
+
+    /// The local horizon at `up`: Z up, Y along the horizontal part of
+    /// `heading`, and X to the right of it.  With the pole for the heading,
+    /// that is east, north and up.  None if `heading` is straight up or down.
+    /// They are taken as they are now, not turning.
+    pub fn horizon(up: &Vector3<f64>, heading: &Vector3<f64>) -> Option<Self> {
+        let z = up.normalize();
+        let y = (heading - z * heading.dot(&z)).try_normalize(1.0e-6)?;
+        let x = y.cross(&z);
+        Some(Axes {
+            q_fw: UnitQuaternion::from_rotation_matrix(&Rotation3::from_basis_unchecked(&[
+                x, y, z,
+            ])),
+            omega_w: Vector3::zeros(),
+        })
+    }
+
+    /// A position and velocity relative to the frame's origin, world frame,
+    /// in the frame: on its axes, and as seen turning with them.
+    pub fn to_frame(&self, r_w: &Vector3<f64>, v_w: &Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+        let v_w = v_w - self.omega_w.cross(r_w);
+        (
+            self.q_fw.inverse_transform_vector(r_w),
+            self.q_fw.inverse_transform_vector(&v_w),
+        )
+    }
+
+    /// The other way, from a position and velocity in the frame to the world,
+    /// still relative to the frame's origin.
+    pub fn from_frame(
+        &self,
+        pos_f: &Vector3<f64>,
+        vel_f: &Vector3<f64>,
+    ) -> (Vector3<f64>, Vector3<f64>) {
+        let r_w = self.q_fw.transform_vector(pos_f);
+        (
+            r_w,
+            self.q_fw.transform_vector(vel_f) + self.omega_w.cross(&r_w),
+        )
+    }
+}
+
+/// The state of an entity in a frame.
+#[derive(Clone, Debug)]
+pub struct FrameState {
+    /// Position, in km.
+    pub pos: Vector3<f64>,
+    /// Velocity, in km/s, as seen by an observer in the frame.
+    pub vel: Vector3<f64>,
+    /// The entity's orientation relative to the frame's axes, body to frame, if
+    /// it has one.
+    pub q_bf: Option<UnitQuaternion<f64>>,
+}
+
+/// States in other frames, as a system parameter.
+#[derive(SystemParam)]
+#[allow(clippy::type_complexity)]
+pub struct Frames<'w, 's> {
+    entities: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Name,
+            &'static OrbitalBody,
+            Option<&'static AttitudeState>,
+            Option<&'static MassiveBody>,
+        ),
+    >,
+}
+
+impl Frames<'_, '_> {
+    /// Look an entity up by name, ignoring case.
+    pub fn find(&self, name: &str) -> Option<Entity> {
+        self.entities
+            .iter()
+            .find(|(_, n, ..)| n.as_str().eq_ignore_ascii_case(name))
+            .map(|(e, ..)| e)
+    }
+
+    /// Parse a frame written as `kind:center`.
+    pub fn parse(&self, spec: &str) -> Result<Frame, String> {
+        let (kind, center) = spec
+            .split_once(':')
+            .ok_or_else(|| format!("Frame {:?} is not of the form kind:center", spec))?;
+        let center = self
+            .find(center)
+            .ok_or_else(|| format!("No such entity: {:?}", center))?;
+        match kind.to_ascii_lowercase().as_str() {
+            "j2000" | "inertial" => Ok(Frame::Inertial(center)),
+            "fixed" => Ok(Frame::BodyFixed(center)),
+            "lvlh" => Ok(Frame::Lvlh(center)),
+            _ => Err(format!(
+                "Unknown frame kind {:?}, expecting j2000, fixed, or lvlh",
+                kind
+            )),
+        }
+    }
+
+    /// The state of `entity` in `frame`.  Fails if either is missing, or the
+    /// frame can't be made (a fixed frame needs an attitude, and LVLH needs
+    /// something to orbit).
+    pub fn state(&self, entity: Entity, frame: Frame) -> Result<FrameState, String> {
+        let (_, _, orbital, attitude, _) = self
+            .entities
+            .get(entity)
+            .map_err(|_| format!("No orbital state for {}", entity))?;
+        let (_, _, center, _, _) = self
+            .entities
+            .get(frame.center())
+            .map_err(|_| format!("No orbital state for {}", frame.center()))?;
+
+        let axes = self.axes(frame)?;
+        let (pos, vel) = axes.to_frame(&(orbital.pos - center.pos), &(orbital.vel - center.vel));
+        Ok(FrameState {
+            pos,
+            vel,
+            q_bf: attitude.map(|a| axes.q_fw.inverse() * a.q_bw),
+        })
+    }
+
+    /// The axes of `frame`.
+    pub fn axes(&self, frame: Frame) -> Result<Axes, String> {
+        let (_, center_name, center, center_attitude, _) = self
+            .entities
+            .get(frame.center())
+            .map_err(|_| format!("No orbital state for {}", frame.center()))?;
+        Ok(match frame {
+            Frame::Inertial(_) => Axes::inertial(),
+            Frame::BodyFixed(_) => Axes::body_fixed(
+                center_attitude.ok_or_else(|| format!("{} has no attitude", center_name))?,
+            ),
+            Frame::Lvlh(_) => {
+                let primary = self
+                    .primary(frame.center())
+                    .ok_or_else(|| format!("{} isn't orbiting anything", center_name))?;
+                Axes::lvlh(&(center.pos - primary.pos), &(center.vel - primary.vel))
+            }
+        })
+    }
+
+    /// The massive body pulling hardest on `entity`.
+    fn primary(&self, entity: Entity) -> Option<&OrbitalBody> {
+        let (_, _, orbital, _, _) = self.entities.get(entity).ok()?;
+        self.entities
+            .iter()
+            .filter(|(e, ..)| *e != entity)
+            .filter_map(|(_, _, other, _, massive)| {
+                let pull = massive?.gm / (other.pos - orbital.pos).norm_squared();
+                Some((pull, other))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, other)| other)
+    }
+}
@@ -0,0 +1,171 @@
+//! Frames and geodesy.
+//!
+//! Conversions between the world (inertial) frame, a body's fixed frame, and
+//! geodetic coordinates over the body's ellipsoid.  The ellipsoid is the
+//! triaxial one from `SizedBody`, so this works for lumpy moons as well as for
+//! the earth.
+//!
+//! Geodetic latitude and longitude are those of the surface normal below the
+//! point, and the altitude is measured along that normal.  For an oblate body,
+//! this is the usual geodetic latitude, and the longitude is the same as the
+//! geocentric one.
+//!
+//! From a point on the ground, anything else is seen at an azimuth and
+//! elevation in the local horizon (east, north, up) frame.
+
+use na::Vector3;
+use sim_core::{AttitudeState, OrbitalBody};
+
+/// Convert a world position (km) into the fixed frame of a body.
+pub fn world_to_body(
+    body: &OrbitalBody,
+    attitude: &AttitudeState,
+    pos_w: &Vector3<f64>,
+) -> Vector3<f64> {
+    attitude.q_bw.inverse_transform_vector(&(pos_w - body.pos))
+}
+
+/// Where a target is in the sky of an observer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LookAngles {
+    /// Azimuth, in radians, from north, toward the east.
+    pub azimuth: f64,
+    /// Elevation, in radians, above the horizon.
+    pub elevation: f64,
+    /// Range, in km.
+    pub range: f64,
+}
+
+/// A position over an ellipsoid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Geodetic {
+    /// Latitude, in radians, north positive.
+    pub lat: f64,
+    /// Longitude, in radians, east positive.
+    pub lon: f64,
+    /// Height above the ellipsoid, in km.  Negative is below the surface.
+    pub alt: f64,
+}
+
+impl Geodetic {
+    /// The geodetic coordinates of `pos_f`, a position in the body fixed frame,
+    /// over the ellipsoid with the given semi-axes (`radii`, km).
+    pub fn from_body(pos_f: &Vector3<f64>, radii: &Vector3<f64>) -> Self {
+        let closest = closest_on_ellipsoid(pos_f, radii);
+        let normal = closest
+            .component_div(&radii.component_mul(radii))
+            .normalize();
+        let offset = pos_f - closest;
+        let alt = offset.norm().copysign(offset.dot(&normal));
+        Geodetic {
+            lat: normal.z.clamp(-1.0, 1.0).asin(),
+            lon: normal.y.atan2(normal.x),
+            alt,
+        }
+    }
+
+    /// The local up direction (the ellipsoid normal), in the body fixed frame.
+    pub fn up(&self) -> Vector3<f64> {
+        let (slat, clat) = self.lat.sin_cos();
+        let (slon, clon) = self.lon.sin_cos();
+        Vector3::new(clat * clon, clat * slon, slat)
+    }
+
+    /// The position in the body fixed frame, over the ellipsoid with the given
+    /// semi-axes (`radii`, km).
+    pub fn to_body(self, radii: &Vector3<f64>) -> Vector3<f64> {
+        let normal = self.up();
+        // The point on the surface with that normal.
+        let r2 = radii.component_mul(radii);
+        let surface = r2.component_mul(&normal) / r2.dot(&normal.component_mul(&normal)).sqrt();
+        surface + normal * self.alt
+    }
+
+    /// The local east and north directions, in the body fixed frame.  With
+    /// `up`, these make the horizon frame.
+    pub fn east_north(&self) -> (Vector3<f64>, Vector3<f64>) {
+        let (slon, clon) = self.lon.sin_cos();
+        let east = Vector3::new(-slon, clon, 0.0);
+        (east, self.up().cross(&east))
+    }
+
+    /// Where `target_f`, a position in the body fixed frame, is seen from
+    /// here, over the ellipsoid with the given semi-axes (`radii`, km).
+    pub fn look_angles(&self, radii: &Vector3<f64>, target_f: &Vector3<f64>) -> LookAngles {
+        let rel = target_f - self.to_body(radii);
+        let (east, north) = self.east_north();
+        let range = rel.norm();
+        LookAngles {
+            azimuth: rel
+                .dot(&east)
+                .atan2(rel.dot(&north))
+                .rem_euclid(std::f64::consts::TAU),
+            elevation: (rel.dot(&self.up()) / range).clamp(-1.0, 1.0).asin(),
+            range,
+        }
+    }
+
+    /// The geodetic coordinates of a world position over a body.
+    pub fn from_world(
+        body: &OrbitalBody,
+        attitude: &AttitudeState,
+        radii: &Vector3<f64>,
+        pos_w: &Vector3<f64>,
+    ) -> Self {
+        Geodetic::from_body(&world_to_body(body, attitude, pos_w), radii)
+    }
+}
+
+/// The point on the ellipsoid closest to `p`.
+///
+/// The closest point `x` has `x_i = p_i a_i^2 / (a_i^2 + t)` for some `t`,
+/// which has to put it on the surface.  That is a single, well behaved
+/// equation in `t`, solved here with Newton's method, falling back on
+/// bisection when Newton strays out of the bracket.
+fn closest_on_ellipsoid(p: &Vector3<f64>, radii: &Vector3<f64>) -> Vector3<f64> {
+    let r2 = radii.component_mul(radii);
+    let min2 = r2.min();
+    if p.norm() < 1.0e-9 * min2.sqrt() {
+        // At the center, any nearest axis will do.
+        let mut x = Vector3::zeros();
+        x[radii.imin()] = radii.min();
+        return x;
+    }
+
+    // f(t) = sum (a_i p_i / (a_i^2 + t))^2 - 1, which falls from +inf at
+    // -min(a_i^2) to -1 at +inf.
+    let f = |t: f64| {
+        let mut value = -1.0;
+        let mut slope = 0.0;
+        for i in 0..3 {
+            let d = r2[i] + t;
+            let term = radii[i] * p[i] / d;
+            value += term * term;
+            slope -= 2.0 * term * term / d;
+        }
+        (value, slope)
+    };
+
+    let mut lo = -min2;
+    let mut hi = radii.max() * p.norm();
+    let mut t = 0.0f64.clamp(lo, hi);
+    for _ in 0..100 {
+        let (value, slope) = f(t);
+        if value > 0.0 {
+            lo = t;
+        } else {
+            hi = t;
+        }
+        let mut next = t - value / slope;
+        if !(next > lo && next < hi) {
+            next = (lo + hi) / 2.0;
+        }
+        if (next - t).abs() <= 1.0e-12 * (1.0 + t.abs()) {
+            t = next;
+            break;
+        }
+        t = next;
+    }
+
+    p.component_mul(&r2).component_div(&r2.add_scalar(t))
+}
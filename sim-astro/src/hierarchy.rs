@@ -0,0 +1,267 @@
+//! The bodies' family tree.
+//!
+//! NAIF numbers the bodies so that where each sits in the solar system can be
+//! read off its id: the sun is 10, the barycenters of the planets' systems
+//! are 1 to 9, each planet is 100 times its system's number plus 99 (399 for
+//! the earth), and its moons are 100 times that number plus 1 on up (301 for
+//! the moon).  So the tree goes from the sun, to each system's barycenter, to
+//! the planet, to its moons.  A body can be given its parent instead (see
+//! `Body::parent`), as a fictional one has to be, and one with neither hangs
+//! off the sun.  The small bodies aren't in it.
+//!
+//! A barycenter is only a point, with nothing there to pull, so it isn't
+//! spawned.  The physics already moves each body by the pull of all the
+//! others, which is what keeps the moons going around their planets, and the
+//! planets around the sun.  The tree is for finding the way among them: the
+//! body a moon goes around, and so whose sphere of influence it is in, and
+//! which bodies make up a planet's system, and how far out it goes.  Where a
+//! barycenter is, when wanted, is worked out from its bodies, by their GMs.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use sim_core::{MassiveBody, OrbitalBody};
+
+use crate::SolarState;
+
+/// The sun's NAIF id.
+pub const SUN_ID: i32 = 10;
+
+/// A barycenter of one of the planets' systems, as SPICE names it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Barycenter {
+    pub id: i32,
+    pub name: String,
+}
+
+/// What a body is, by its place in the tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BodyKind {
+    Star,
+    Barycenter,
+    Planet,
+    Moon,
+    /// Anything else, such as a fictional body.
+    Other,
+}
+
+impl BodyKind {
+    /// What the body with NAIF id `id` is.
+    pub fn of(id: i32) -> Self {
+        match id {
+            SUN_ID => BodyKind::Star,
+            1..=9 => BodyKind::Barycenter,
+            100..=999 if id % 100 == 99 => BodyKind::Planet,
+            100..=999 if id % 100 != 0 => BodyKind::Moon,
+            _ => BodyKind::Other,
+        }
+    }
+}
+
+/// The parent of the body with NAIF id `id`, by its number, if it has one.
+pub fn naif_parent(id: i32) -> Option<i32> {
+    match BodyKind::of(id) {
+        BodyKind::Barycenter => Some(SUN_ID),
+        BodyKind::Planet => Some(id / 100),
+        BodyKind::Moon => Some(id / 100 * 100 + 99),
+        BodyKind::Star | BodyKind::Other => None,
+    }
+}
+
+/// A body, or a barycenter, in the tree.
+#[derive(Clone, Debug)]
+pub struct TreeNode {
+    pub id: i32,
+    pub name: String,
+    pub kind: BodyKind,
+    /// The body's entity, or None for a barycenter.
+    pub entity: Option<Entity>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+/// The massive bodies, and their systems' barycenters, from the sun down.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct BodyTree {
+    pub nodes: Vec<TreeNode>,
+    /// The sun, or failing that, the heaviest body.
+    pub root: usize,
+}
+
+impl BodyTree {
+    /// The tree for the bodies of `solar`, spawned as `entities`, in the same
+    /// order.
+    pub fn new(solar: &SolarState, entities: &[Entity]) -> Self {
+        let mut tree = BodyTree::default();
+        for (body, entity) in solar.bodies.iter().zip(entities) {
+            tree.nodes.push(TreeNode {
+                id: body.id.0,
+                name: body.name.to_string(),
+                kind: BodyKind::of(body.id.0),
+                entity: Some(*entity),
+                parent: None,
+                children: Vec::new(),
+            });
+        }
+        if tree.nodes.is_empty() {
+            return tree;
+        }
+        // They are kept heaviest first, so failing a sun, the first will do.
+        tree.root = tree.find_id(SUN_ID).unwrap_or(0);
+
+        for (i, body) in solar.bodies.iter().enumerate() {
+            let mut wanted = body
+                .parent
+                .as_ref()
+                .map(|id| id.0)
+                .or(naif_parent(body.id.0));
+            let parent = loop {
+                let Some(id) = wanted else {
+                    break tree.root;
+                };
+                if let Some(parent) = tree.find_id(id) {
+                    break parent;
+                }
+                if BodyKind::of(id) == BodyKind::Barycenter {
+                    break tree.add_barycenter(solar, id);
+                }
+                wanted = naif_parent(id);
+            };
+            // Not under itself, however it was told.
+            if i != tree.root && !tree.is_under(parent, i) {
+                tree.attach(i, parent);
+            } else if i != tree.root {
+                tree.attach(i, tree.root);
+            }
+        }
+        tree
+    }
+
+    /// Add the barycenter with NAIF id `id`, under the sun.
+    fn add_barycenter(&mut self, solar: &SolarState, id: i32) -> usize {
+        // Named as SPICE has it, or else after its planet.
+        let name = solar
+            .barycenters
+            .iter()
+            .find(|b| b.id == id)
+            .map(|b| b.name.clone())
+            .or_else(|| {
+                let planet = self.find_id(id * 100 + 99)?;
+                Some(format!("{} BARYCENTER", self.nodes[planet].name))
+            })
+            .unwrap_or_else(|| format!("BARYCENTER {}", id));
+        let index = self.nodes.len();
+        self.nodes.push(TreeNode {
+            id,
+            name,
+            kind: BodyKind::Barycenter,
+            entity: None,
+            parent: None,
+            children: Vec::new(),
+        });
+        self.attach(index, self.root);
+        index
+    }
+
+    fn attach(&mut self, child: usize, parent: usize) {
+        self.nodes[child].parent = Some(parent);
+        self.nodes[parent].children.push(child);
+    }
+
+    /// Whether `index` is `ancestor`, or anywhere under it.
+    fn is_under(&self, mut index: usize, ancestor: usize) -> bool {
+        loop {
+            if index == ancestor {
+                return true;
+            }
+            match self.nodes[index].parent {
+                Some(parent) => index = parent,
+                None => return false,
+            }
+        }
+    }
+
+    fn find_id(&self, id: i32) -> Option<usize> {
+        self.nodes.iter().position(|node| node.id == id)
+    }
+
+    /// The node for `entity`.
+    pub fn index(&self, entity: Entity) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.entity == Some(entity))
+    }
+
+    /// The node called `name`, in any case.
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The body `entity` goes around: its parent, or above a barycenter, the
+    /// barycenter's.
+    pub fn primary(&self, entity: Entity) -> Option<Entity> {
+        let mut index = self.nodes[self.index(entity)?].parent?;
+        loop {
+            let node = &self.nodes[index];
+            if let Some(entity) = node.entity {
+                return Some(entity);
+            }
+            index = node.parent?;
+        }
+    }
+
+    /// The system `index` heads: the node's own body, if it has one, and
+    /// every one under it.
+    pub fn system(&self, index: usize) -> Vec<Entity> {
+        let mut entities = Vec::new();
+        let mut open = vec![index];
+        while let Some(index) = open.pop() {
+            let node = &self.nodes[index];
+            entities.extend(node.entity);
+            open.extend(node.children.iter().copied());
+        }
+        entities
+    }
+
+    /// The top of the system `entity` is in, short of the root: a planet's
+    /// or a moon's barycenter, or the root, for the sun.
+    pub fn system_of(&self, entity: Entity) -> Option<usize> {
+        let mut index = self.index(entity)?;
+        while let Some(parent) = self.nodes[index].parent {
+            if parent == self.root {
+                break;
+            }
+            index = parent;
+        }
+        Some(index)
+    }
+
+    /// The center of mass of the system `index` heads, and its total GM, from
+    /// where its bodies are in `bodies`.
+    pub fn barycenter(
+        &self,
+        index: usize,
+        bodies: &Query<(&OrbitalBody, &MassiveBody)>,
+    ) -> Option<(OrbitalBody, f64)> {
+        let mut center = OrbitalBody {
+            pos: na::Vector3::zeros(),
+            vel: na::Vector3::zeros(),
+        };
+        let mut gm = 0.0;
+        for entity in self.system(index) {
+            let Ok((orbital, massive)) = bodies.get(entity) else {
+                continue;
+            };
+            center.pos += orbital.pos * massive.gm;
+            center.vel += orbital.vel * massive.gm;
+            gm += massive.gm;
+        }
+        if gm <= 0.0 {
+            return None;
+        }
+        center.pos /= gm;
+        center.vel /= gm;
+        Some((center, gm))
+    }
+}
@@ -0,0 +1,273 @@
+//! Bodies from JPL Horizons, for those the kernels don't have.
+//!
+//! Every comet and asteroid has an SPK file of its own, which has to be found,
+//! fetched, and listed with the rest of the kernels.  Horizons has them all,
+//! for any time, over the web.  This asks it for a body's state at one time,
+//! in the frame the sim takes the kernels in (the ecliptic of J2000, about
+//! the solar system barycenter), along with the GM and radius it has for the
+//! body, if any.  What it says is kept on disk (`HorizonsCache`), so each body
+//! is only asked for once at each time, and the sim can run again without
+//! the network.  The state is made into the same `SmallBody`, or `Body`, as
+//! one from the kernels would be.
+//!
+//! Bodies are asked for by their NAIF id: the planets and moons by theirs,
+//! and the small bodies by their SPK ids (2000000 and their number for the
+//! numbered asteroids, 1000000 and up for the comets).
+//!
+//! This is only built with the `horizons` feature.
+
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use na::{UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, SizedBody};
+
+use crate::{Body, SmallBody, SolarState, SpiceId};
+
+/// Where the answers are kept, unless told otherwise.
+pub const DEFAULT_CACHE: &str = "assets/horizons";
+
+const API: &str = "https://ssd.jpl.nasa.gov/api/horizons.api";
+
+/// The Julian date of J2000, on the TDB scale, as the sim's time is.
+const J2000_JD: f64 = 2_451_545.0;
+
+/// The NAIF ids from here up are small bodies.
+const SMALL_BODY_IDS: i32 = 1_000_000;
+
+/// What Horizons has for a body at one time.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HorizonsState {
+    pub id: i32,
+    pub name: String,
+    /// In seconds past J2000.
+    pub et: f64,
+    pub orbital: OrbitalBody,
+    /// km^3/s^2, if it has one.
+    pub gm: Option<f64>,
+    /// The mean radius, km, if it has one.
+    pub radius: Option<f64>,
+}
+
+impl HorizonsState {
+    /// Read what Horizons said of the body `id` at `et`.
+    pub fn parse(id: i32, et: f64, text: &str) -> Result<Self, String> {
+        let data = text
+            .split_once("$$SOE")
+            .and_then(|(_, rest)| rest.split_once("$$EOE"))
+            .map(|(data, _)| data)
+            .ok_or_else(|| format!("No state for {} in Horizons' reply", id))?;
+        // JDTDB, Calendar Date (TDB), X, Y, Z, VX, VY, VZ
+        let fields: Vec<f64> = data
+            .trim()
+            .lines()
+            .next()
+            .unwrap_or("")
+            .split(',')
+            .skip(2)
+            .filter_map(|field| field.trim().parse().ok())
+            .collect();
+        let [x, y, z, vx, vy, vz, ..] = fields[..] else {
+            return Err(format!("Can't read the state of {} from Horizons", id));
+        };
+        let name = text
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("Target body name:"))
+            .map(|name| name.split('{').next().unwrap_or(name).trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| id.to_string());
+        Ok(HorizonsState {
+            id,
+            name,
+            et,
+            orbital: OrbitalBody {
+                pos: Vector3::new(x, y, z),
+                vel: Vector3::new(vx, vy, vz),
+            },
+            gm: header_value(text, &["GM, km^3/s^2", "GM (km^3/s^2)", "GM="]),
+            radius: header_value(text, &["Vol. Mean Radius (km)", "Mean radius (km)", "RAD="]),
+        })
+    }
+
+    /// Ask Horizons for the body `id` at `et`.
+    pub fn fetch(id: i32, et: f64) -> Result<Self, String> {
+        #[derive(Deserialize)]
+        struct Reply {
+            result: Option<String>,
+            error: Option<String>,
+        }
+
+        let command = if id >= SMALL_BODY_IDS {
+            format!("'DES={};'", id)
+        } else {
+            format!("'{}'", id)
+        };
+        let time = format!("'{}'", J2000_JD + et / 86400.0);
+        let mut response = ureq::get(API)
+            .query("format", "json")
+            .query("COMMAND", &command)
+            .query("OBJ_DATA", "'YES'")
+            .query("MAKE_EPHEM", "'YES'")
+            .query("EPHEM_TYPE", "'VECTORS'")
+            .query("CENTER", "'500@0'")
+            .query("REF_PLANE", "'ECLIPTIC'")
+            .query("REF_SYSTEM", "'ICRF'")
+            .query("VEC_TABLE", "'2'")
+            .query("OUT_UNITS", "'KM-S'")
+            .query("CSV_FORMAT", "'YES'")
+            .query("TIME_TYPE", "'TDB'")
+            .query("TLIST_TYPE", "'JD'")
+            .query("TLIST", &time)
+            .call()
+            .map_err(|e| format!("Can't ask Horizons for {}: {}", id, e))?;
+        let body = response
+            .body_mut()
+            .read_to_string()
+            .map_err(|e| format!("Can't read Horizons' reply for {}: {}", id, e))?;
+        let reply: Reply = serde_json::from_str(&body)
+            .map_err(|e| format!("Can't read Horizons' reply for {}: {}", id, e))?;
+        if let Some(error) = reply.error {
+            return Err(format!("Horizons has no {}: {}", id, error.trim()));
+        }
+        Self::parse(id, et, reply.result.as_deref().unwrap_or(""))
+    }
+
+    /// As a small body, pulled on, but not pulling.
+    pub fn small_body(&self) -> SmallBody {
+        SmallBody {
+            id: SpiceId(self.id),
+            name: self.name.as_str().into(),
+            orbital: self.orbital.clone(),
+            size: self.radius.map(|radius| SizedBody {
+                radii: Vector3::repeat(radius),
+            }),
+            attitude: still(),
+        }
+    }
+
+    /// As a body that pulls, if Horizons has its GM and radius.
+    pub fn body(&self) -> Option<Body> {
+        Some(Body {
+            id: SpiceId(self.id),
+            name: self.name.as_str().into(),
+            massive: MassiveBody { gm: self.gm? },
+            orbital: self.orbital.clone(),
+            size: SizedBody {
+                radii: Vector3::repeat(self.radius?),
+            },
+            attitude: still(),
+            parent: None,
+        })
+    }
+}
+
+/// Horizons doesn't give an attitude, so it is left as it is.
+fn still() -> AttitudeState {
+    AttitudeState {
+        q_bw: UnitQuaternion::identity(),
+        omega_b: Vector3::zeros(),
+    }
+}
+
+/// The number after the first of `labels` in the header, skipping the `=`,
+/// or None if there is none, or it is "n.a.".
+fn header_value(text: &str, labels: &[&str]) -> Option<f64> {
+    let header = text.split("$$SOE").next()?;
+    let rest = labels.iter().find_map(|label| {
+        let at = header.find(label)?;
+        Some(&header[at + label.len()..])
+    })?;
+    let rest = rest.trim_start_matches([' ', '=']);
+    let bytes = rest.as_bytes();
+    let end = bytes
+        .iter()
+        .enumerate()
+        .position(|(i, &c)| {
+            let sign = (c == b'-' || c == b'+') && (i == 0 || matches!(bytes[i - 1], b'e' | b'E'));
+            !(c.is_ascii_digit() || c == b'.' || c == b'e' || c == b'E' || sign)
+        })
+        .unwrap_or(bytes.len());
+    rest[..end].parse().ok()
+}
+
+/// Horizons' answers, kept on disk, one file for each body and time.
+#[derive(Clone, Debug)]
+pub struct HorizonsCache {
+    pub dir: PathBuf,
+}
+
+impl Default for HorizonsCache {
+    fn default() -> Self {
+        HorizonsCache::new(DEFAULT_CACHE)
+    }
+}
+
+impl HorizonsCache {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        HorizonsCache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    fn path(&self, id: i32, et: f64) -> PathBuf {
+        self.dir.join(format!("{}_{:.3}.json", id, et))
+    }
+
+    /// What was kept for the body `id` at `et`, if anything.
+    pub fn get(&self, id: i32, et: f64) -> Option<HorizonsState> {
+        let file = std::fs::File::open(self.path(id, et)).ok()?;
+        serde_json::from_reader(std::io::BufReader::new(file)).ok()
+    }
+
+    /// Keep `state`.
+    pub fn insert(&self, state: &HorizonsState) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Can't make {}: {}", self.dir.display(), e))?;
+        let path = self.path(state.id, state.et);
+        let file = std::fs::File::create(&path)
+            .map_err(|e| format!("Can't write {}: {}", path.display(), e))?;
+        serde_json::to_writer_pretty(file, state)
+            .map_err(|e| format!("Can't write {}: {}", path.display(), e))
+    }
+
+    /// The body `id` at `et`, as kept, or else from Horizons, keeping it.
+    pub fn state(&self, id: i32, et: f64) -> Result<HorizonsState, String> {
+        if let Some(state) = self.get(id, et) {
+            return Ok(state);
+        }
+        let state = HorizonsState::fetch(id, et)?;
+        info!("{} ({}) from Horizons", state.name, id);
+        if let Err(e) = self.insert(&state) {
+            warn!("{}", e);
+        }
+        Ok(state)
+    }
+
+    /// Add to `solar` each of `ids` it doesn't already have, at its time:
+    /// the planets and moons as bodies that pull, and the rest as small
+    /// bodies.
+    pub fn add_to(&self, solar: &mut SolarState, ids: &[i32]) -> Result<(), String> {
+        for &id in ids {
+            let known = solar.bodies.iter().any(|b| b.id.0 == id)
+                || solar.small_bodies.iter().any(|b| b.id.0 == id);
+            if known {
+                continue;
+            }
+            let state = self.state(id, solar.et)?;
+            if id < SMALL_BODY_IDS {
+                let body = state
+                    .body()
+                    .ok_or_else(|| format!("Horizons has no GM or radius for {}", state.name))?;
+                solar.bodies.push(body);
+            } else {
+                solar.small_bodies.push(state.small_body());
+            }
+        }
+        // Heaviest first, as they are kept.
+        solar
+            .bodies
+            .sort_by(|a, b| b.massive.gm.total_cmp(&a.massive.gm));
+        Ok(())
+    }
+}
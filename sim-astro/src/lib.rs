@@ -0,0 +1,176 @@
+//! Solar system modeling.
+//!
+//! The solar system comes from an ephemeris, a `SolarState`, which is a
+//! snapshot taken from SPICE (see `sim_spice`), so that the game itself can run
+//! without the kernels.  Where there is no SPICE at all, the major bodies can
+//! be worked out from series instead, less accurately (see `analytic`).  A
+//! scenario can change its bodies, or add its own (see `overrides`).
+//! `SolarPlugin` spawns its bodies, with the physics from `sim_core` to move
+//! them, once it has them (see `loading`).  The ephemeris can also have small
+//! bodies, such as asteroids and comets, which are moved like crafts: pulled
+//! on, but not pulling.  Those the kernels don't have can be had from JPL
+//! Horizons (see `horizons`), and the earth's satellites from their two-line
+//! element sets (see `tle`).  Where a body is at other times can be cached, as
+//! fitted series (see `ephemeris`).  Around that are the models of the bodies'
+//! own environments: atmospheres, radiation, eclipses, and their surfaces.
+
+// Recommended alias.
+extern crate nalgebra as na;
+
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, PhysicsPlugin, SizedBody};
+
+#[cfg(feature = "analytic")]
+pub mod analytic;
+pub mod atmosphere;
+pub mod collision;
+pub mod contact;
+pub mod eclipse;
+pub mod ephemeris;
+pub mod frames;
+pub mod geodesy;
+pub mod hierarchy;
+#[cfg(feature = "horizons")]
+pub mod horizons;
+pub mod loading;
+pub mod overrides;
+pub mod radiation;
+pub mod tle;
+
+/// A marker for the Earth.
+#[derive(Component)]
+pub struct EarthMarker;
+
+/// Indicates the id of an item that came (directly or indirectly) from SPICE.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct SpiceId(pub i32);
+
+/// The state of a body, from `sim_core`, is captured by "Body" which is
+/// primarily used to serialize data in and out to avoid needing the entire set
+/// of SPICE kernels for normal gameplay.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Body {
+    pub id: SpiceId,
+    pub name: Name,
+    pub massive: MassiveBody,
+    pub orbital: OrbitalBody,
+    pub size: SizedBody,
+    pub attitude: AttitudeState,
+    /// The NAIF id of what it goes around, if not the one its own id says
+    /// (see `hierarchy`).
+    #[serde(default)]
+    pub parent: Option<SpiceId>,
+}
+
+/// A small body, such as an asteroid or a comet, from SPICE.  These have too
+/// little mass to pull on anything, so they are moved as crafts are, but are
+/// drawn, and can be targeted, like any other body.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SmallBody {
+    pub id: SpiceId,
+    pub name: Name,
+    pub orbital: OrbitalBody,
+    /// Its ellipsoid, if the kernels, or the list it was picked from, give
+    /// one.  Without one, it can't be landed on.
+    pub size: Option<SizedBody>,
+    pub attitude: AttitudeState,
+}
+
+/// A marker for the small bodies.
+#[derive(Component)]
+pub struct SmallBodyMarker;
+
+#[derive(Component, Resource, Debug, Serialize, Deserialize)]
+pub struct SolarState {
+    /// The time represented by this snapshot, in seconds past J2000.
+    pub et: f64,
+    /// The time, as a string, to make this more readable.
+    pub time: String,
+    /// The bodies in the solar system.
+    pub bodies: Vec<Body>,
+    /// The span, `(start, stop)` in seconds past J2000, the kernels this was
+    /// taken from cover for every one of the bodies, if known.  The sim's
+    /// clock should stay inside it, for anything looked up in the kernels to
+    /// agree.
+    #[serde(default)]
+    pub coverage: Option<(f64, f64)>,
+    /// The small bodies picked out of their own kernels, if any.
+    #[serde(default)]
+    pub small_bodies: Vec<SmallBody>,
+    /// The barycenters of the planets' systems, for their names.
+    #[serde(default)]
+    pub barycenters: Vec<hierarchy::Barycenter>,
+}
+
+impl SolarState {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        serde_json::to_writer_pretty(file, self)
+            .map_err(|e| std::io::Error::other(format!("Serialization error: {}", e)))
+    }
+
+    pub fn load(arg: &str) -> std::io::Result<Self> {
+        let file = std::fs::File::open(arg)?;
+        let state = serde_json::from_reader(file)
+            .map_err(|e| std::io::Error::other(format!("Deserialization error: {}", e)))?;
+        Ok(state)
+    }
+}
+
+#[derive(Default)]
+pub struct SolarPlugin;
+
+impl Plugin for SolarPlugin {
+    fn build(&self, app: &mut bevy::prelude::App) {
+        app.init_resource::<hierarchy::BodyTree>();
+        app.init_resource::<ephemeris::EphemerisCache>();
+        if !app.is_plugin_added::<loading::LoadingPlugin>() {
+            app.add_plugins(loading::LoadingPlugin);
+        }
+        app.add_systems(OnEnter(loading::SimPhase::Running), setup_solar);
+        if !app.is_plugin_added::<PhysicsPlugin>() {
+            app.add_plugins(PhysicsPlugin);
+        }
+    }
+}
+
+pub fn setup_solar(ephem: Res<SolarState>, mut commands: bevy::prelude::Commands) {
+    let mut entities = Vec::new();
+    for body in &ephem.bodies {
+        let e = commands
+            .spawn((
+                body.id.clone(),
+                body.massive.clone(),
+                body.orbital.clone(),
+                body.size.clone(),
+                body.attitude.clone(),
+                body.name.clone(),
+            ))
+            .id();
+        entities.push(e);
+
+        if body.name.as_str() == "EARTH" {
+            commands.entity(e).insert((
+                EarthMarker,
+                atmosphere::Atmosphere::earth(),
+                radiation::Magnetosphere::earth(),
+            ));
+        }
+    }
+    commands.insert_resource(hierarchy::BodyTree::new(&ephem, &entities));
+    for body in &ephem.small_bodies {
+        let mut e = commands.spawn((
+            body.id.clone(),
+            body.orbital.clone(),
+            body.attitude.clone(),
+            body.name.clone(),
+            SmallBodyMarker,
+        ));
+        if let Some(size) = &body.size {
+            e.insert(size.clone());
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Starting before the solar system is there.
+//!
+//! Usually the `SolarState` is read from its file before the app is built,
+//! which takes no time at all.  Taken from the kernels instead, it takes many
+//! seconds: loading them, reading every GM in them, and then each body's
+//! state.  So that the window isn't left hanging, the sim starts in
+//! `SimPhase::Loading`, and a `SolarLoader` does the work on its own thread,
+//! saying what it is doing as it goes (`LoadProgress`).  The bodies are added
+//! to the `SolarState` as each is read, so a loading screen can list them.
+//! Once it has the whole of it, the sim goes on to `SimPhase::Running`, and
+//! everything that needs the bodies to start with is set up then, on
+//! `OnEnter(SimPhase::Running)`, in place of `Startup`.  With a `SolarState`
+//! already in place, it starts out running.
+//!
+//! Nothing moves while loading: the virtual clock is paused, so no physics
+//! steps are run (see `sim_core::clock`).
+
+use bevy::prelude::*;
+use std::sync::{
+    Mutex,
+    mpsc::{Receiver, channel},
+};
+
+use crate::{Body, SolarState};
+
+/// Whether the sim is waiting for its solar system, or running.
+#[derive(States, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SimPhase {
+    Loading,
+    Running,
+}
+
+impl FromWorld for SimPhase {
+    /// Running, if there is a solar system to run.
+    fn from_world(world: &mut World) -> Self {
+        if world.contains_resource::<SolarState>() {
+            SimPhase::Running
+        } else {
+            SimPhase::Loading
+        }
+    }
+}
+
+/// What the loader sends back.
+pub enum LoadMessage {
+    /// What it is doing now.
+    Stage(String),
+    /// A body, as it is read.
+    Body(Body),
+    Done(SolarState),
+    Failed(String),
+}
+
+/// The solar system being loaded, on its own thread.
+#[derive(Resource)]
+pub struct SolarLoader(Mutex<Receiver<LoadMessage>>);
+
+impl SolarLoader {
+    /// Load the solar system with `load`, which is given a way to say how it
+    /// is going.
+    pub fn spawn<F>(load: F) -> Self
+    where
+        F: FnOnce(&mut dyn FnMut(LoadMessage)) -> Result<SolarState, String> + Send + 'static,
+    {
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let result = load(&mut |message| {
+                let _ = tx.send(message);
+            });
+            let _ = tx.send(match result {
+                Ok(solar) => LoadMessage::Done(solar),
+                Err(e) => LoadMessage::Failed(e),
+            });
+        });
+        SolarLoader(Mutex::new(rx))
+    }
+}
+
+/// How the loading is going.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct LoadProgress {
+    pub stage: String,
+    /// The bodies read so far, in order.
+    pub bodies: Vec<String>,
+    /// Why it couldn't be loaded, if it couldn't.
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<bevy::state::app::StatesPlugin>() {
+            app.add_plugins(bevy::state::app::StatesPlugin);
+        }
+        app.init_state::<SimPhase>();
+        app.init_resource::<LoadProgress>();
+        app.add_systems(OnEnter(SimPhase::Loading), start_loading);
+        app.add_systems(
+            OnExit(SimPhase::Loading),
+            |mut time: ResMut<Time<Virtual>>| {
+                time.unpause();
+            },
+        );
+        app.add_systems(PreUpdate, receive_solar.run_if(in_state(SimPhase::Loading)));
+    }
+}
+
+/// Stop the clock, and put an empty solar system in place, for the bodies to
+/// be added to.
+fn start_loading(mut commands: Commands, mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+    commands.insert_resource(SolarState {
+        et: 0.0,
+        time: String::new(),
+        bodies: Vec::new(),
+        coverage: None,
+        small_bodies: Vec::new(),
+        barycenters: Vec::new(),
+    });
+}
+
+/// Take what the loader has sent, and once it has it all, run.
+fn receive_solar(
+    mut commands: Commands,
+    loader: Option<Res<SolarLoader>>,
+    mut solar: ResMut<SolarState>,
+    mut progress: ResMut<LoadProgress>,
+    mut next: ResMut<NextState<SimPhase>>,
+) {
+    let Some(loader) = loader else {
+        return;
+    };
+    let Ok(rx) = loader.0.lock() else {
+        return;
+    };
+    for message in rx.try_iter() {
+        match message {
+            LoadMessage::Stage(stage) => {
+                info!("{}", stage);
+                progress.stage = stage;
+            }
+            LoadMessage::Body(body) => {
+                progress.bodies.push(body.name.to_string());
+                solar.bodies.push(body);
+            }
+            LoadMessage::Done(loaded) => {
+                info!("Loaded {} bodies", loaded.bodies.len());
+                *solar = loaded;
+                commands.remove_resource::<SolarLoader>();
+                next.set(SimPhase::Running);
+            }
+            LoadMessage::Failed(e) => {
+                error!("Can't load the solar system: {}", e);
+                progress.error = Some(e);
+                commands.remove_resource::<SolarLoader>();
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Bodies changed, or added, by hand.
+//!
+//! A scenario can carry overrides for the bodies taken from SPICE, such as a
+//! heavier moon for gameplay, or whole new bodies that SPICE has never heard
+//! of.  They are merged into the `SolarState` before its bodies are spawned,
+//! so a fictional planet goes through the same pipeline as a real one.
+//!
+//! The rule is simple: an override for a body that is already there (by
+//! name) replaces whichever of its fields the override gives, and leaves the
+//! rest as SPICE had them.  An override for a body that isn't there adds it,
+//! and must give at least its GM, its radii and its state; its attitude
+//! defaults to lined up with the world, not turning.  Overrides are applied in
+//! order, so of two for the same body, the later one wins.
+
+use na::{UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, SizedBody};
+
+use crate::{Body, SolarState, SpiceId};
+
+/// Fictional bodies given no id are numbered up from this, clear of the
+/// ids NAIF uses.
+pub const FICTIONAL_ID_BASE: i32 = 9_000_000;
+
+/// A change to a body, or a new one.  The units are as for `Body`: km, km/s,
+/// and km^3/s^2, with the state relative to the solar system barycenter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BodyOverride {
+    pub name: String,
+    #[serde(default)]
+    pub id: Option<i32>,
+    #[serde(default)]
+    pub gm: Option<f64>,
+    #[serde(default)]
+    pub radii: Option<Vector3<f64>>,
+    #[serde(default)]
+    pub orbital: Option<OrbitalBody>,
+    #[serde(default)]
+    pub attitude: Option<AttitudeState>,
+    /// The NAIF id of the body (or barycenter) it goes around, where its own
+    /// id doesn't say (see `hierarchy`).  A new body without one is taken to
+    /// go around the sun.
+    #[serde(default)]
+    pub parent: Option<i32>,
+}
+
+impl BodyOverride {
+    /// Change `body` to match.
+    fn apply(&self, body: &mut Body) {
+        if let Some(id) = self.id {
+            body.id = SpiceId(id);
+        }
+        if let Some(gm) = self.gm {
+            body.massive.gm = gm;
+        }
+        if let Some(radii) = self.radii {
+            body.size.radii = radii;
+        }
+        if let Some(orbital) = &self.orbital {
+            body.orbital = orbital.clone();
+        }
+        if let Some(attitude) = &self.attitude {
+            body.attitude = attitude.clone();
+        }
+        if let Some(parent) = self.parent {
+            body.parent = Some(SpiceId(parent));
+        }
+    }
+
+    /// The new body this describes, with `id` if it doesn't give its own.
+    fn body(&self, id: i32) -> Result<Body, String> {
+        let missing = |field| format!("New body {} needs its {}", self.name, field);
+        Ok(Body {
+            id: SpiceId(self.id.unwrap_or(id)),
+            name: self.name.as_str().into(),
+            massive: MassiveBody {
+                gm: self.gm.ok_or_else(|| missing("gm"))?,
+            },
+            orbital: self.orbital.clone().ok_or_else(|| missing("orbital"))?,
+            size: SizedBody {
+                radii: self.radii.ok_or_else(|| missing("radii"))?,
+            },
+            attitude: self.attitude.clone().unwrap_or(AttitudeState {
+                q_bw: UnitQuaternion::identity(),
+                omega_b: Vector3::zeros(),
+            }),
+            parent: self.parent.map(SpiceId),
+        })
+    }
+}
+
+impl SolarState {
+    /// Merge the overrides into the bodies.  An override that would add a
+    /// body, but doesn't say enough about it, is left out, and its error
+    /// returned with any others; the rest are still applied.
+    pub fn merge(&mut self, overrides: &[BodyOverride]) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        let mut next_id = FICTIONAL_ID_BASE;
+        for item in overrides {
+            match self
+                .bodies
+                .iter_mut()
+                .find(|b| b.name.as_str() == item.name)
+            {
+                Some(body) => item.apply(body),
+                None => match item.body(next_id) {
+                    Ok(body) => {
+                        next_id += 1;
+                        self.bodies.push(body);
+                    }
+                    Err(e) => errors.push(e),
+                },
+            }
+        }
+        // Keep them heaviest first, as they come from SPICE.
+        self.bodies
+            .sort_by(|a, b| b.massive.gm.total_cmp(&a.massive.gm));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
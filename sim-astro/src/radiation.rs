@@ -0,0 +1,250 @@
+//! The radiation environment.
+//!
+//! There are three sources, each as simple as it can be while still putting
+//! the dose in the right places:
+//!
+//! - The trapped radiation belts of a body with a `Magnetosphere`.  The field
+//!   is a tilted and offset dipole, and each belt is a band of McIlwain L (the
+//!   distance, in body radii, at which a field line crosses the magnetic
+//!   equator), with a dose rate that falls off either side of its heart,
+//!   sharply on the inside, where the atmosphere soaks the particles up.  This
+//!   gets the Van Allen belts' shape right: a craft in LEO only clips the
+//!   inner belt, where the offset brings it low (over the South Atlantic), and
+//!   a transfer to GTO or the moon flies straight through both.
+//! - Galactic cosmic rays, a steady background everywhere.
+//! - Solar particle events, which are scripted (from the console, or by a
+//!   scenario), as the sim has no space weather of its own.  Their dose falls
+//!   off with the square of the distance from the sun.
+//!
+//! A magnetosphere also shields what is inside it from the last two, most of
+//! all near the magnetic equator, close in.  Field lines near the poles go
+//! out into space, so the shielding goes away there.
+//!
+//! The same dipole gives the field itself, for the craft's magnetometers and
+//! magnetorquers.  It is set from the first three Gauss coefficients of a
+//! field model, such as IGRF, which are all the dipole is.
+//!
+//! The rates are absorbed dose, in Gy/s, behind a typical craft's hull.
+
+use bevy::prelude::*;
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+use sim_core::{AttitudeState, OrbitalBody};
+
+/// The galactic cosmic ray dose rate, in Gy/s, in interplanetary space (about
+/// 0.5 mGy/day, as measured on the way to Mars).
+pub const GCR_RATE: f64 = 0.5e-3 / 86400.0;
+
+/// The astronomical unit, km.
+const AU: f64 = 149_597_870.7;
+
+/// A trapped radiation belt, as a band of L.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Belt {
+    /// Where the belt is at its most intense, and how fast it falls off
+    /// toward the body, and away from it (as standard deviations), in body
+    /// radii.
+    pub l: f64,
+    pub inner: f64,
+    pub outer: f64,
+    /// The dose rate at its heart, Gy/s.
+    pub peak: f64,
+}
+
+/// The magnetic field of a body, and its radiation belts.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct Magnetosphere {
+    /// The dipole's north pole, and its center (km), in the body's frame.
+    pub axis_b: Vector3<f64>,
+    pub center_b: Vector3<f64>,
+    /// The field at the magnetic equator, at the body's radius, in T.
+    pub strength: f64,
+    pub belts: Vec<Belt>,
+    /// The L of the magnetopause.  Field lines beyond it are open, so there
+    /// are no belts, and no shielding.
+    pub magnetopause: f64,
+    /// The least fraction of the cosmic rays and solar particles that get
+    /// through, deep inside the field.  Some always come in along the field
+    /// lines, or straight down them.
+    pub min_exposure: f64,
+}
+
+impl Magnetosphere {
+    /// The earth's, as the eccentric dipole for 2020 (from IGRF-13): the pole
+    /// at 80.6 N, 72.7 W, 29.8 uT at the equator, and the center 570 km off
+    /// toward 22.6 N, 141.6 E.  The belts are the inner (proton) and outer
+    /// (electron) Van Allen belts.
+    pub fn earth() -> Self {
+        let unit = |lat: f64, lon: f64| {
+            let (lat, lon) = (lat.to_radians(), lon.to_radians());
+            Vector3::new(lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin())
+        };
+        let (axis_b, strength) = gauss_dipole(-29404.8, -1450.9, 4652.5);
+        Magnetosphere {
+            axis_b,
+            center_b: unit(22.6, 141.6) * 570.0,
+            strength,
+            belts: vec![
+                Belt {
+                    l: 1.5,
+                    inner: 0.1,
+                    outer: 0.4,
+                    peak: 10.0e-3 / 3600.0,
+                },
+                Belt {
+                    l: 4.5,
+                    inner: 1.0,
+                    outer: 1.5,
+                    peak: 2.0e-3 / 3600.0,
+                },
+            ],
+            magnetopause: 10.0,
+            min_exposure: 0.3,
+        }
+    }
+
+    /// Set the dipole from the Gauss coefficients g(1,0), g(1,1) and h(1,1),
+    /// in nT.  The center stays where it is.
+    pub fn set_dipole(&mut self, g10: f64, g11: f64, h11: f64) {
+        (self.axis_b, self.strength) = gauss_dipole(g10, g11, h11);
+    }
+
+    /// The field at `pos_b`, a position in the body's frame (km), for a body
+    /// of the given radius (km), in T, in the body's frame.
+    pub fn field_b(&self, pos_b: &Vector3<f64>, radius: f64) -> Vector3<f64> {
+        let pos_b = pos_b - self.center_b;
+        let r = pos_b.norm();
+        let r_hat = pos_b / r;
+        // The dipole points south: the field comes out of the southern
+        // hemisphere, and goes back in at the north.
+        let m_hat = -self.axis_b;
+        (r_hat * (3.0 * m_hat.dot(&r_hat)) - m_hat) * (self.strength * (radius / r).powi(3))
+    }
+
+    /// The L shell through `pos_b`, a position in the body's frame, for a
+    /// body of the given radius (both in km).
+    pub fn l_shell(&self, pos_b: &Vector3<f64>, radius: f64) -> f64 {
+        let pos_b = pos_b - self.center_b;
+        let r = pos_b.norm();
+        let sin_lat = pos_b.dot(&self.axis_b) / r;
+        let cos2_lat = 1.0 - sin_lat * sin_lat;
+        if cos2_lat < 1.0e-12 {
+            return f64::INFINITY;
+        }
+        r / (radius * cos2_lat)
+    }
+
+    /// The dose rate, Gy/s, from the belts, at the given L.
+    pub fn belt_rate(&self, l: f64) -> f64 {
+        if l > self.magnetopause {
+            return 0.0;
+        }
+        self.belts
+            .iter()
+            .map(|belt| {
+                let width = if l < belt.l { belt.inner } else { belt.outer };
+                belt.peak * (-0.5 * ((l - belt.l) / width).powi(2)).exp()
+            })
+            .sum()
+    }
+
+    /// The fraction of the cosmic rays and solar particles that get in as far
+    /// as the given L.
+    pub fn exposure(&self, l: f64) -> f64 {
+        (l / self.magnetopause).clamp(self.min_exposure, 1.0)
+    }
+}
+
+/// The north pole, as a unit vector, and the equatorial field, in T, of the
+/// dipole with the Gauss coefficients g(1,0), g(1,1) and h(1,1), in nT.
+pub fn gauss_dipole(g10: f64, g11: f64, h11: f64) -> (Vector3<f64>, f64) {
+    // The moment is along (g11, h11, g10), which, for the earth, is to the
+    // south.
+    let moment = Vector3::new(g11, h11, g10);
+    let strength = moment.norm();
+    (-moment / strength, strength * 1.0e-9)
+}
+
+/// A solar particle event: a burst of energetic protons, that builds to its
+/// peak, and then dies away over a day or so.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SolarParticleEvent {
+    /// When it starts, in `Time<Fixed>` seconds.
+    pub start: f64,
+    /// How long it takes to build to its peak, and the time for it to fall
+    /// off by e after that, in seconds.
+    pub rise: f64,
+    pub decay: f64,
+    /// The dose rate at its peak, at 1 AU, Gy/s.
+    pub peak: f64,
+}
+
+impl SolarParticleEvent {
+    /// The dose rate, Gy/s, at 1 AU, at time `t`.
+    pub fn rate(&self, t: f64) -> f64 {
+        let since = t - self.start;
+        if since < 0.0 {
+            0.0
+        } else if since < self.rise {
+            self.peak * since / self.rise
+        } else {
+            self.peak * (-(since - self.rise) / self.decay).exp()
+        }
+    }
+
+    /// Whether it has died away to nothing by time `t`.
+    pub fn is_over(&self, t: f64) -> bool {
+        t > self.start + self.rise + 20.0 * self.decay
+    }
+}
+
+/// The solar particle events, past, present and to come.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SolarParticleEvents(pub Vec<SolarParticleEvent>);
+
+impl SolarParticleEvents {
+    /// The dose rate, Gy/s, at 1 AU, at time `t`.
+    pub fn rate(&self, t: f64) -> f64 {
+        self.0.iter().map(|event| event.rate(t)).sum()
+    }
+}
+
+/// The dose rate at a point, by source, in Gy/s.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DoseRate {
+    pub belts: f64,
+    pub cosmic: f64,
+    pub solar: f64,
+}
+
+impl DoseRate {
+    pub fn total(&self) -> f64 {
+        self.belts + self.cosmic + self.solar
+    }
+}
+
+/// The dose rate at `pos` (world frame, km), at time `t`.  `sun` is the sun's
+/// position, and `magnetospheres` are each body's magnetosphere, position,
+/// attitude and equatorial radius.
+pub fn dose_rate<'a>(
+    pos: &Vector3<f64>,
+    t: f64,
+    sun: &Vector3<f64>,
+    events: &SolarParticleEvents,
+    magnetospheres: impl Iterator<Item = (&'a Magnetosphere, &'a OrbitalBody, &'a AttitudeState, f64)>,
+) -> DoseRate {
+    let mut belts = 0.0;
+    let mut exposure = 1.0;
+    for (magnetosphere, body, attitude, radius) in magnetospheres {
+        let pos_b = attitude.q_bw.inverse_transform_vector(&(pos - body.pos));
+        let l = magnetosphere.l_shell(&pos_b, radius);
+        belts += magnetosphere.belt_rate(l);
+        exposure *= magnetosphere.exposure(l);
+    }
+    let au = (pos - sun).norm() / AU;
+    DoseRate {
+        belts,
+        cosmic: GCR_RATE * exposure,
+        solar: events.rate(t) / (au * au).max(1.0e-4) * exposure,
+    }
+}
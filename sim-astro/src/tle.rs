@@ -0,0 +1,170 @@
+//! Two-line element sets, as the public satellite catalogs give them.
+//!
+//! A TLE's elements are the mean elements of the SGP4 model, in the TEME
+//! frame of their epoch, on the UTC scale.  There is no SGP4 here: each set
+//! is taken as the osculating elements of a two-body conic at its epoch, in
+//! the earth's mean equator of J2000, and moved to the ecliptic of J2000,
+//! which the sim works in.  That puts an object within some tens of km of
+//! where it is near its epoch, and further off from there, which is enough
+//! for a catalog to screen against, but not for real conjunction warnings.
+//!
+//! The epoch goes from UTC to TDB by a fixed 69.184 s, which has been right
+//! since the last leap second, at the start of 2017.
+//!
+//! The sets can come with a name line before each (with or without the `0 `
+//! some catalogs start it with), or without, when the catalog number stands
+//! in for the name.  Each line's checksum is checked.
+
+use na::{UnitQuaternion, Vector3};
+use sim_core::kepler;
+
+use crate::frames;
+
+/// TDB less UTC, in seconds: 37 leap seconds, and TT's 32.184.
+const TDB_MINUS_UTC: f64 = 69.184;
+
+/// One object's element set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tle {
+    pub name: String,
+    pub catalog_number: u32,
+    /// In seconds past J2000, TDB.
+    pub epoch: f64,
+    /// The angles, in degrees.
+    pub inclination: f64,
+    pub raan: f64,
+    pub eccentricity: f64,
+    pub arg_periapsis: f64,
+    pub mean_anomaly: f64,
+    /// In revolutions a day.
+    pub mean_motion: f64,
+}
+
+/// The characters in `line` from column `from` to `to`, counting from 1, as
+/// TLE formats are given.
+fn field(line: &str, from: usize, to: usize) -> Result<&str, String> {
+    line.get(from - 1..to)
+        .map(str::trim)
+        .ok_or_else(|| format!("Line too short for columns {} to {}: {:?}", from, to, line))
+}
+
+fn number<T: std::str::FromStr>(line: &str, from: usize, to: usize) -> Result<T, String> {
+    let text = field(line, from, to)?;
+    text.parse()
+        .map_err(|_| format!("Not a number in columns {} to {}: {:?}", from, to, text))
+}
+
+/// Check a line's last digit against the sum of the others, with a minus
+/// sign counting as one, mod 10.
+fn check(line: &str) -> Result<(), String> {
+    let sum: u32 = line
+        .chars()
+        .take(68)
+        .map(|c| {
+            if c == '-' {
+                1
+            } else {
+                c.to_digit(10).unwrap_or(0)
+            }
+        })
+        .sum();
+    match line.chars().nth(68).and_then(|c| c.to_digit(10)) {
+        Some(checksum) if checksum == sum % 10 => Ok(()),
+        _ => Err(format!("Bad checksum: {:?}", line)),
+    }
+}
+
+/// Days from 0001-01-01 to the first of January of `year`.
+fn days_to(year: i64) -> i64 {
+    let y = year - 1;
+    365 * y + y / 4 - y / 100 + y / 400
+}
+
+impl Tle {
+    /// Read a set from its two lines, and its name, if it has one.
+    pub fn parse_lines(name: Option<&str>, line1: &str, line2: &str) -> Result<Self, String> {
+        if !line1.starts_with("1 ") || !line2.starts_with("2 ") {
+            return Err(format!("Not a two-line element set: {:?}", line1));
+        }
+        check(line1)?;
+        check(line2)?;
+        let catalog_number: u32 = number(line1, 3, 7)?;
+
+        // Two digit years, from the first launch, in 1957.
+        let year: i64 = number(line1, 19, 20)?;
+        let year = if year < 57 { 2000 + year } else { 1900 + year };
+        let day: f64 = number(line1, 21, 32)?;
+        // J2000 is noon on the first of January, 2000.
+        let epoch = (days_to(year) - days_to(2000)) as f64 * 86400.0 - 43200.0
+            + (day - 1.0) * 86400.0
+            + TDB_MINUS_UTC;
+
+        let eccentricity: f64 = format!("0.{}", field(line2, 27, 33)?)
+            .parse()
+            .map_err(|_| format!("Not an eccentricity: {:?}", line2))?;
+        Ok(Tle {
+            name: name
+                .map(|name| name.trim_start_matches("0 ").trim().to_string())
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(|| catalog_number.to_string()),
+            catalog_number,
+            epoch,
+            inclination: number(line2, 9, 16)?,
+            raan: number(line2, 18, 25)?,
+            eccentricity,
+            arg_periapsis: number(line2, 35, 42)?,
+            mean_anomaly: number(line2, 44, 51)?,
+            mean_motion: number(line2, 53, 63)?,
+        })
+    }
+
+    /// Read all the sets in a catalog.
+    pub fn parse_all(text: &str) -> Result<Vec<Self>, String> {
+        let lines: Vec<&str> = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let mut sets = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let (name, first) = if lines[i].starts_with("1 ") {
+                (None, i)
+            } else {
+                (Some(lines[i]), i + 1)
+            };
+            let (Some(line1), Some(line2)) = (lines.get(first), lines.get(first + 1)) else {
+                return Err(format!("An element set cut short: {:?}", lines[i]));
+            };
+            sets.push(Self::parse_lines(name, line1, line2)?);
+            i = first + 2;
+        }
+        Ok(sets)
+    }
+
+    /// The position, in km, and velocity, in km/s, at the epoch, relative to
+    /// the earth, with `gm`, on the ecliptic of J2000.
+    pub fn state(&self, gm: f64) -> (Vector3<f64>, Vector3<f64>) {
+        let e = self.eccentricity;
+        let n = self.mean_motion * std::f64::consts::TAU / 86400.0;
+        let a = (gm / (n * n)).cbrt();
+
+        // At periapsis, in the orbit's plane, with periapsis along X.
+        let periapsis = a * (1.0 - e);
+        let pos = Vector3::new(periapsis, 0.0, 0.0);
+        let vel = Vector3::new(0.0, (gm * (1.0 + e) / periapsis).sqrt(), 0.0);
+
+        // From there to the equator, and then to the ecliptic, and on along
+        // the conic to the mean anomaly.
+        let to_ecliptic = frames::eme2000_to_ecliptic()
+            * UnitQuaternion::from_axis_angle(&Vector3::z_axis(), self.raan.to_radians())
+            * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), self.inclination.to_radians())
+            * UnitQuaternion::from_axis_angle(&Vector3::z_axis(), self.arg_periapsis.to_radians());
+        kepler::propagate(
+            &(to_ecliptic * pos),
+            &(to_ecliptic * vel),
+            gm,
+            self.mean_anomaly.to_radians() / n,
+        )
+    }
+}
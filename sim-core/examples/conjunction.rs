@@ -0,0 +1,163 @@
+//! Check conjunction screening against approaches made to order.
+//!
+//! The craft is in a circular orbit, 500 km up.  Each object is put where
+//! the craft will be at a chosen time, off to one side of it, square to
+//! their relative velocity, so that then is when it comes closest, and the
+//! offset is how close.  It is then taken back along its conic to now, and
+//! screened, and its first approach has to be that one.  The cases cover a
+//! fast crossing from another plane, a slow one in the same plane, and one
+//! just outside the screening distance, which has to be left out, as does an
+//! object up at geostationary height.
+//! A time of closest approach off by more than a millisecond, or a miss off
+//! by more than a metre, fails.
+//!
+//! Run with: cargo run --release -p sim-core --example conjunction
+//!
+//! `cargo test` runs it too, and fails if any approach is missed.
+
+extern crate nalgebra as na;
+
+use na::{Rotation3, Vector3};
+use sim_core::{
+    conjunction::{Approach, Screen},
+    kepler,
+};
+
+const EARTH_GM: f64 = 398600.4418;
+const EARTH_RADIUS: f64 = 6378.137;
+
+/// How far ahead, and how often, in seconds, the craft's conic is sampled.
+const SPAN: f64 = 6000.0;
+const STEP: f64 = 30.0;
+
+/// The screening distance, in km.
+const DISTANCE: f64 = 5.0;
+
+/// The most the time, in seconds, and the miss, in km, may be off by.
+const TIME_ERROR: f64 = 1.0e-3;
+const MISS_ERROR: f64 = 1.0e-3;
+
+/// An approach: at a time, in seconds, with an offset, in km, from the
+/// craft, along its radial, normal, or along-track direction, and the
+/// object's velocity, from the craft's, turned about the radial, in
+/// degrees, and with a radial part, in km/s.
+struct Case {
+    name: &'static str,
+    time: f64,
+    offset: Vector3<f64>,
+    turn: f64,
+    climb: f64,
+    /// Whether it is close enough to be reported.
+    reported: bool,
+}
+
+const CASES: [Case; 4] = [
+    Case {
+        name: "crossing from 60° off",
+        time: 2000.0,
+        offset: Vector3::new(0.2, 0.0, 0.0),
+        turn: 60.0,
+        climb: 0.0,
+        reported: true,
+    },
+    Case {
+        name: "slow, in the same plane",
+        time: 4321.5,
+        offset: Vector3::new(0.0, 1.5, 0.0),
+        turn: 0.0,
+        climb: 0.05,
+        reported: true,
+    },
+    Case {
+        name: "head on",
+        time: 17.25,
+        offset: Vector3::new(0.0, 0.01, 0.0),
+        turn: 180.0,
+        climb: 0.0,
+        reported: true,
+    },
+    Case {
+        name: "just outside",
+        time: 3000.0,
+        offset: Vector3::new(DISTANCE + 0.5, 0.0, 0.0),
+        turn: 90.0,
+        climb: 0.0,
+        reported: false,
+    },
+];
+
+fn main() -> Result<(), String> {
+    let radius = EARTH_RADIUS + 500.0;
+    let craft_pos = Vector3::new(radius, 0.0, 0.0);
+    let craft_vel = Vector3::new(0.0, (EARTH_GM / radius).sqrt(), 0.0);
+    let screen = Screen::new(&craft_pos, &craft_vel, EARTH_GM, SPAN, STEP);
+
+    println!("| approach | time (s) | found (s) | miss (km) | found (km) | speed (km/s) |");
+    println!("|---|---:|---:|---:|---:|---:|");
+    let mut failed = Vec::new();
+    for case in &CASES {
+        let (pos, vel) = kepler::propagate(&craft_pos, &craft_vel, EARTH_GM, case.time);
+        let radial = pos.normalize();
+        let normal = pos.cross(&vel).normalize();
+        let along = normal.cross(&radial);
+        let offset = radial * case.offset.x + normal * case.offset.y + along * case.offset.z;
+        let turn =
+            Rotation3::from_axis_angle(&na::Unit::new_normalize(radial), case.turn.to_radians());
+        let object_vel = turn * vel + radial * case.climb;
+        // Square to the relative velocity, so this is the closest.
+        let rel_vel = object_vel - vel;
+        let offset = offset - rel_vel * (offset.dot(&rel_vel) / rel_vel.norm_squared());
+        let (object_pos, object_vel) =
+            kepler::propagate(&(pos + offset), &object_vel, EARTH_GM, -case.time);
+
+        let approaches = screen.screen(&object_pos, &object_vel, DISTANCE);
+        // Objects crossing its orbit come by again, half an orbit or so
+        // later: the first time is the one made to order.
+        match (approaches.first(), case.reported) {
+            (None, false) => println!(
+                "| {} | {:.3} | - | {:.3} | - | - |",
+                case.name,
+                case.time,
+                offset.norm()
+            ),
+            (Some(Approach { time, miss, speed }), true) => {
+                println!(
+                    "| {} | {:.3} | {:.4} | {:.4} | {:.4} | {:.3} |",
+                    case.name,
+                    case.time,
+                    time,
+                    offset.norm(),
+                    miss,
+                    speed
+                );
+                if (time - case.time).abs() > TIME_ERROR
+                    || (miss - offset.norm()).abs() > MISS_ERROR
+                {
+                    failed.push(case.name);
+                }
+            }
+            _ => {
+                println!("| {} | {:.3} | {:?} |", case.name, case.time, approaches);
+                failed.push(case.name);
+            }
+        }
+    }
+
+    // Up at geostationary height, it never comes near.
+    let geo = 42164.0;
+    let far = screen.screen(
+        &Vector3::new(0.0, geo, 0.0),
+        &Vector3::new(-(EARTH_GM / geo).sqrt(), 0.0, 0.0),
+        DISTANCE,
+    );
+    if !far.is_empty() {
+        println!("geostationary: {:?}", far);
+        failed.push("geostationary");
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Screening got these wrong: {}", failed.join(", ")))
+    }
+}
@@ -0,0 +1,177 @@
+//! Check that a nutation damper turns a spin about the minor axis into a flat
+//! spin.
+//!
+//! Explorer 1 was a long, thin body, spun up about its long axis, which has
+//! the least inertia.  Without any loss of energy, that spin is stable.  But
+//! its whip antennas flexed, and soaked up energy, and with its angular
+//! momentum kept, the only way to lose energy was to tip over, until, within
+//! its first orbit, it was tumbling end over end about a transverse axis,
+//! the one with the most inertia: a flat spin.
+//!
+//! This spins a body shaped about like Explorer 1 about its long axis, with a
+//! small wobble, and a `NutationDamper`, and prints the angle between the
+//! long axis and the angular momentum as it goes over.  It has to end up
+//! within `ERROR` of a spin about the major axis, with the angular momentum
+//! kept to within `DRIFT`, and the energy down to the least it can have.  The
+//! same body without the damper has to keep to its wobble about its long
+//! axis.
+//!
+//! Run with: cargo run --release -p sim-core --example flat_spin
+//!
+//! `cargo test` runs it too, and fails if it doesn't go flat.
+
+extern crate nalgebra as na;
+
+use na::{UnitQuaternion, Vector3};
+use sim_core::{AttitudeIntegrator, AttitudeState, NutationDamper, Renormalize, RotationScheme};
+
+/// The principal moments, kg*m^2: two transverse, and the long axis, about
+/// that of a 14 kg rod two meters long.
+const INERTIA: [f64; 3] = [4.8, 4.6, 0.5];
+
+/// The spin about the long axis, and the wobble across it, in rad/s.
+const SPIN: f64 = 6.0;
+const WOBBLE: f64 = 0.05;
+
+/// The damper's time constant, in seconds.
+const TIME_CONSTANT: f64 = 20.0;
+
+/// The step, and how long it runs, in seconds.
+const DT: f64 = 0.005;
+const DURATION: f64 = 1200.0;
+
+/// How often, in seconds, the angle is printed.
+const REPORT: f64 = 60.0;
+
+/// How far, in degrees, the rate may end up from the major axis, and the
+/// body without the damper may wander from where it started, off the long
+/// axis.
+const ERROR: f64 = 1.0;
+
+/// How far, relative to its size, the angular momentum may drift.
+const DRIFT: f64 = 1.0e-6;
+
+struct Spin {
+    attitude: AttitudeState,
+    inertia: Vector3<f64>,
+}
+
+impl Spin {
+    fn new() -> Self {
+        Spin {
+            attitude: AttitudeState {
+                q_bw: UnitQuaternion::identity(),
+                omega_b: Vector3::new(WOBBLE, 0.0, SPIN),
+            },
+            inertia: Vector3::from(INERTIA),
+        }
+    }
+
+    /// The angular momentum, world frame.
+    fn momentum(&self) -> Vector3<f64> {
+        self.attitude
+            .q_bw
+            .transform_vector(&self.inertia.component_mul(&self.attitude.omega_b))
+    }
+
+    fn energy(&self) -> f64 {
+        0.5 * self
+            .attitude
+            .omega_b
+            .dot(&self.inertia.component_mul(&self.attitude.omega_b))
+    }
+
+    /// The angle, in degrees, between a BODY axis and the angular momentum.
+    fn tilt(&self, axis: &Vector3<f64>) -> f64 {
+        let angle = self
+            .attitude
+            .q_bw
+            .transform_vector(axis)
+            .angle(&self.momentum())
+            .to_degrees();
+        // Either way along the axis will do.
+        angle.min(180.0 - angle)
+    }
+}
+
+fn main() {
+    let integrator = AttitudeIntegrator {
+        scheme: RotationScheme::Rk4,
+        renormalize: Renormalize::EveryStep,
+    };
+    let damper = NutationDamper {
+        time_constant: TIME_CONSTANT,
+    };
+    let mut damped = Spin::new();
+    let mut free = Spin::new();
+    let h_0 = damped.momentum();
+    let tilt_0 = free.tilt(&Vector3::z());
+    let steps = (DURATION / DT).round() as usize;
+    let report = (REPORT / DT).round() as usize;
+
+    println!("| t (s) | long axis off H (deg) | without damper (deg) | energy (J) |");
+    println!("|---|---|---|---|");
+    for n in 0..=steps {
+        if n % report == 0 {
+            println!(
+                "| {:.0} | {:.2} | {:.2} | {:.3} |",
+                n as f64 * DT,
+                damped.tilt(&Vector3::z()),
+                free.tilt(&Vector3::z()),
+                damped.energy()
+            );
+        }
+        let (damped_inertia, free_inertia) = (damped.inertia, free.inertia);
+        integrator.step(
+            &mut damped.attitude,
+            Some(&damped_inertia),
+            Some(&damper),
+            &Vector3::zeros(),
+            DT,
+        );
+        integrator.step(
+            &mut free.attitude,
+            Some(&free_inertia),
+            None,
+            &Vector3::zeros(),
+            DT,
+        );
+    }
+
+    let major = damped.tilt(&Vector3::x());
+    let drift = (damped.momentum() - h_0).norm() / h_0.norm();
+    // The least energy for the momentum: all of it about the major axis.
+    let least = h_0.norm_squared() / (2.0 * INERTIA[0]);
+    let wander = (free.tilt(&Vector3::z()) - tilt_0).abs();
+    println!(
+        "Ended {:.3}° off the major axis, with the momentum off by {:.1e}, \
+         and {:.4} J against the least, {:.4} J; without the damper, the long \
+         axis wandered {:.3}°",
+        major,
+        drift,
+        damped.energy(),
+        least,
+        wander
+    );
+
+    let mut failed = false;
+    if major > ERROR {
+        println!("FAILED: it didn't go into a flat spin");
+        failed = true;
+    }
+    if drift > DRIFT {
+        println!("FAILED: the damper changed the angular momentum");
+        failed = true;
+    }
+    if damped.energy() > least * (1.0 + 1.0e-4) {
+        println!("FAILED: the energy didn't come down to the least");
+        failed = true;
+    }
+    if wander > ERROR {
+        println!("FAILED: without the damper, the spin didn't hold");
+        failed = true;
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
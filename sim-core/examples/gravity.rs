@@ -0,0 +1,171 @@
+//! Benchmark summing the pull of many bodies, directly and with the tree.
+//!
+//! A belt of asteroids goes around the sun, with a few crafts among them
+//! pulled by all of it (the third-body perturbation), and is run for a
+//! day, once summing the pull directly, and once with the Barnes-Hut tree,
+//! for a range of belt sizes.  For each, the table has the time a step
+//! takes each way, how far the tree's accelerations are from the exact ones
+//! (at the start, over every body), and how far the crafts have drifted from
+//! the direct run by the end.  Where the tree starts to win is where
+//! `GravitySolver`'s threshold belongs.  The result is printed as a markdown
+//! table, so it can be pasted straight into docs.
+//!
+//! Run with: cargo run --release -p sim-core --example gravity
+
+extern crate nalgebra as na;
+
+use na::Vector3;
+use sim_core::{
+    GravitySolver, MassiveBody, OrbitalBody, Perturbation, Simulation, SimulationBuilder,
+    barnes_hut::{Octree, TreeBody},
+};
+use std::time::Instant;
+
+const SUN_GM: f64 = 1.32712440018e11;
+const AU: f64 = 1.495978707e8;
+
+/// The belt sizes to try.
+const SIZES: [usize; 8] = [32, 64, 128, 256, 512, 1024, 2048, 4096];
+
+/// How many crafts go around in the belt.
+const CRAFTS: usize = 4;
+
+/// The length of a step, and how many are run, in seconds.
+const STEP: f64 = 3600.0;
+const STEPS: usize = 24;
+
+/// A xorshift, so the belt is the same every run.
+struct Random(u64);
+
+impl Random {
+    /// Uniform, in `[lo, hi)`.
+    fn uniform(&mut self, lo: f64, hi: f64) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        lo + (hi - lo) * (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// A circular orbit about the sun, at a random radius, phase, and tilt.
+fn belt_orbit(random: &mut Random) -> OrbitalBody {
+    let r = random.uniform(2.2, 3.3) * AU;
+    let phase = random.uniform(0.0, std::f64::consts::TAU);
+    let tilt = random.uniform(-0.2, 0.2);
+    let (s, c) = phase.sin_cos();
+    let v = (SUN_GM / r).sqrt();
+    OrbitalBody {
+        pos: Vector3::new(c, s, tilt * s) * r,
+        vel: Vector3::new(-s, c, tilt * c) * v,
+    }
+}
+
+/// The sun, a belt of `size` asteroids, and the crafts, each in a low orbit
+/// about one of the biggest, summing gravity with `solver`.
+fn belt(size: usize, solver: GravitySolver) -> (Simulation, Vec<TreeBody>) {
+    let mut random = Random(0x5eed_0123_4567_89ab);
+    let mut builder = SimulationBuilder::new()
+        .set_gravity_solver(solver)
+        .enable_perturbation(Perturbation::ThirdBody)
+        .add_body(
+            "SUN",
+            MassiveBody { gm: SUN_GM },
+            OrbitalBody {
+                pos: Vector3::zeros(),
+                vel: Vector3::zeros(),
+            },
+        );
+    let mut bodies = vec![TreeBody::fixed(Vector3::zeros(), SUN_GM)];
+    for i in 0..size {
+        let orbital = belt_orbit(&mut random);
+        // The first few are the size of Ceres, to have the crafts about.
+        let gm = if i < CRAFTS {
+            62.6
+        } else {
+            random.uniform(0.001, 1.0)
+        };
+        bodies.push(TreeBody::fixed(orbital.pos, gm));
+        if i < CRAFTS {
+            let v = (gm / 1000.0).sqrt();
+            builder = builder.add_craft(
+                format!("CRAFT {}", i),
+                OrbitalBody {
+                    pos: orbital.pos + Vector3::new(0.0, 0.0, 1000.0),
+                    vel: orbital.vel + Vector3::new(v, 0.0, 0.0),
+                },
+            );
+        }
+        builder = builder.add_body(format!("ASTEROID {}", i), MassiveBody { gm }, orbital);
+    }
+    (builder.build().unwrap(), bodies)
+}
+
+/// The rms of how far the tree's accelerations are from the exact ones,
+/// relative to the exact ones.
+fn accel_error(bodies: &[TreeBody], theta: f64) -> f64 {
+    let tree = Octree::new(bodies.to_vec(), theta);
+    let mut sum = 0.0;
+    for (i, body) in bodies.iter().enumerate() {
+        let mut exact = Vector3::zeros();
+        for (j, other) in bodies.iter().enumerate() {
+            if i != j {
+                let rel = other.pos - body.pos;
+                let d = rel.norm();
+                exact += rel * (other.gm / (d * d * d));
+            }
+        }
+        let error = (tree.accel(&body.pos, 0.0, Some(i)) - exact).norm() / exact.norm();
+        sum += error * error;
+    }
+    (sum / bodies.len() as f64).sqrt()
+}
+
+/// Run, and how long each step took, in ms.
+fn run(sim: &mut Simulation) -> f64 {
+    let start = Instant::now();
+    sim.run(STEP * STEPS as f64, STEP);
+    start.elapsed().as_secs_f64() * 1000.0 / STEPS as f64
+}
+
+fn main() {
+    let theta = GravitySolver::default().theta;
+    let direct = GravitySolver {
+        threshold: usize::MAX,
+        theta,
+    };
+    let tree = GravitySolver {
+        threshold: 0,
+        theta,
+    };
+
+    println!(
+        "The sun, a belt of asteroids, and {} crafts, for {} steps of {} s, with theta {}.",
+        CRAFTS, STEPS, STEP, theta
+    );
+    println!();
+    println!(
+        "| bodies | direct (ms/step) | tree (ms/step) | speedup | accel error (rms) | craft drift (km) |"
+    );
+    println!("|---:|---:|---:|---:|---:|---:|");
+    for size in SIZES {
+        let (mut exact, bodies) = belt(size, direct);
+        let (mut fast, _) = belt(size, tree);
+        let direct_ms = run(&mut exact);
+        let tree_ms = run(&mut fast);
+        let drift = (0..CRAFTS)
+            .map(|i| {
+                let name = format!("CRAFT {}", i);
+                (exact.state(&name).unwrap().pos - fast.state(&name).unwrap().pos).norm()
+            })
+            .fold(0.0, f64::max);
+        println!(
+            "| {} | {:.3} | {:.3} | {:.2} | {:.1e} | {:.1e} |",
+            bodies.len(),
+            direct_ms,
+            tree_ms,
+            direct_ms / tree_ms,
+            accel_error(&bodies, theta),
+            drift
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! Compare the translational integrators.
+//!
+//! Each scenario is run through every method in lockstep, with the same outer
+//! time step, and the positions are compared against a reference run made with
+//! a very tight RK45 tolerance, and a tenth of the step.  The result is printed
+//! as a markdown table, so it can be pasted straight into docs.
+//!
+//! Run with: cargo run --release -p sim-core --example integrators
+
+extern crate nalgebra as na;
+
+use na::Vector3;
+use sim_core::integrator::{Gravity, Method, Propagator, State};
+
+/// Earth, in km^3/s^2.
+const EARTH_GM: f64 = 398600.4418;
+const EARTH_RADIUS: f64 = 6378.137;
+
+/// The moon, on a circular orbit, which is plenty for a comparison.
+const MOON_GM: f64 = 4902.800066;
+const MOON_DISTANCE: f64 = 384400.0;
+const MOON_PERIOD: f64 = 27.321661 * 86400.0;
+
+/// How long each scenario is run, in seconds.
+const DURATION: f64 = 86400.0;
+
+/// The outer (lockstep) time steps to try, in seconds.
+const STEPS: [f64; 3] = [1.0, 10.0, 60.0];
+
+/// The reference takes this many steps for each lockstep step.  Otherwise, a
+/// short enough step would keep it from being any better than RK45 at the
+/// normal tolerance.
+const REFERENCE_SUBSTEPS: usize = 10;
+
+/// The earth, with the moon as a perturbation.
+struct EarthMoon;
+
+impl EarthMoon {
+    fn moon(&self, t: f64) -> Vector3<f64> {
+        let angle = std::f64::consts::TAU * t / MOON_PERIOD;
+        Vector3::new(angle.cos(), angle.sin(), 0.0) * MOON_DISTANCE
+    }
+}
+
+impl Gravity for EarthMoon {
+    fn gm(&self) -> f64 {
+        EARTH_GM
+    }
+
+    fn perturbation(&self, t: f64, pos: &Vector3<f64>) -> Vector3<f64> {
+        // The pull on the craft, less the pull on the earth, since the earth is
+        // our origin.
+        let moon = self.moon(t);
+        let rel = moon - pos;
+        let d = rel.norm();
+        let m = moon.norm();
+        rel * (MOON_GM / (d * d * d)) - moon * (MOON_GM / (m * m * m))
+    }
+}
+
+/// A circular orbit at the given altitude, inclined about the X axis.
+fn circular(altitude: f64, inclination: f64) -> State {
+    let r = EARTH_RADIUS + altitude;
+    let v = (EARTH_GM / r).sqrt();
+    let (s, c) = inclination.to_radians().sin_cos();
+    State {
+        pos: Vector3::new(r, 0.0, 0.0),
+        vel: Vector3::new(0.0, c, s) * v,
+    }
+}
+
+/// An orbit with the given periapsis and apoapsis altitudes, starting at
+/// periapsis.
+fn elliptic(periapsis: f64, apoapsis: f64) -> State {
+    let rp = EARTH_RADIUS + periapsis;
+    let ra = EARTH_RADIUS + apoapsis;
+    let a = (rp + ra) / 2.0;
+    let v = (EARTH_GM * (2.0 / rp - 1.0 / a)).sqrt();
+    State {
+        pos: Vector3::new(rp, 0.0, 0.0),
+        vel: Vector3::new(0.0, v, 0.0),
+    }
+}
+
+fn main() {
+    let gravity = EarthMoon;
+    let scenarios = [
+        ("LEO 400 km, 51.6°", circular(400.0, 51.6)),
+        ("GTO 300 x 35786 km", elliptic(300.0, 35786.0)),
+    ];
+    let methods = [
+        Method::Euler,
+        Method::Leapfrog,
+        Method::Rk45 { tolerance: 1.0e-9 },
+        Method::Encke { rectify: 1.0e-3 },
+    ];
+    let reference = Method::Rk45 { tolerance: 1.0e-13 };
+
+    println!(
+        "Position divergence from the reference (RK45, tolerance 1e-13, dt/{}) after {} s.",
+        REFERENCE_SUBSTEPS, DURATION
+    );
+    println!();
+    println!("| scenario | method | dt (s) | max error (km) | final error (km) | evaluations |");
+    println!("|---|---|---:|---:|---:|---:|");
+    for (name, initial) in &scenarios {
+        for dt in STEPS {
+            let mut truth = Propagator::new(reference, 0.0, *initial);
+            let mut crafts: Vec<_> = methods
+                .iter()
+                .map(|m| Propagator::new(*m, 0.0, *initial))
+                .collect();
+            let mut max_error = vec![0.0f64; crafts.len()];
+
+            let steps = (DURATION / dt).round() as usize;
+            for _ in 0..steps {
+                for _ in 0..REFERENCE_SUBSTEPS {
+                    truth.step(&gravity, dt / REFERENCE_SUBSTEPS as f64);
+                }
+                let expected = truth.state(&gravity).pos;
+                for (craft, max_error) in crafts.iter_mut().zip(max_error.iter_mut()) {
+                    craft.step(&gravity, dt);
+                    let error = (craft.state(&gravity).pos - expected).norm();
+                    *max_error = max_error.max(error);
+                }
+            }
+
+            let expected = truth.state(&gravity).pos;
+            for (craft, max_error) in crafts.iter().zip(max_error.iter()) {
+                let error = (craft.state(&gravity).pos - expected).norm();
+                println!(
+                    "| {} | {} | {} | {:.3e} | {:.3e} | {} |",
+                    name,
+                    craft.method.name(),
+                    dt,
+                    max_error,
+                    error,
+                    craft.evaluations
+                );
+            }
+        }
+    }
+}
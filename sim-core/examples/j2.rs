@@ -0,0 +1,127 @@
+//! Check the J2 perturbation against the nodal regression it should cause.
+//!
+//! The earth's oblateness turns an inclined orbit's plane about the pole, at a
+//! rate that first order theory has as
+//!
+//!   dΩ/dt = -3/2 n J2 (R/p)^2 cos i
+//!
+//! so that the space station's orbit goes back about 5° a day, and a sun
+//! synchronous one forward about 0.9856°, keeping up with the sun.  Each is
+//! run through a `Simulation` with `Perturbation::J2`, for a whole number of
+//! orbits, so that the wobble the perturbation also causes, twice an orbit,
+//! comes back to where it started.  The rate is then taken from the
+//! ascending node at the start and the end.  The theory is for the mean
+//! elements, not those the orbit starts with, which puts it off by a few
+//! tenths of a percent, so the bound is 1%.
+//!
+//! Run with: cargo run --release -p sim-core --example j2
+//!
+//! `cargo test` runs it too, and fails if either rate is off by more.
+
+extern crate nalgebra as na;
+
+use na::{Unit, Vector3};
+use sim_core::{
+    MassiveBody, OrbitalBody, Perturbation, SimulationBuilder, integrator::Method, kepler::period,
+};
+use std::f64::consts::TAU;
+
+const EARTH_GM: f64 = 398600.4418;
+const EARTH_RADIUS: f64 = 6378.137;
+const EARTH_J2: f64 = 1.08262668e-3;
+
+/// About how long each orbit is run, in seconds: the whole number of orbits
+/// nearest to a day.
+const DURATION: f64 = 86400.0;
+
+/// The time step, in seconds.
+const STEP: f64 = 30.0;
+
+/// How far, as a fraction, the rate can be from the theory.
+const BOUND: f64 = 0.01;
+
+/// A circular orbit at the given altitude and inclination, in degrees, with
+/// its ascending node on the X axis.
+fn circular(altitude: f64, inclination: f64) -> OrbitalBody {
+    let r = EARTH_RADIUS + altitude;
+    let v = (EARTH_GM / r).sqrt();
+    let (s, c) = inclination.to_radians().sin_cos();
+    OrbitalBody {
+        pos: Vector3::new(r, 0.0, 0.0),
+        vel: Vector3::new(0.0, c, s) * v,
+    }
+}
+
+/// The right ascension of the ascending node, in radians, about the Z axis.
+fn node(orbit: &OrbitalBody) -> f64 {
+    let h = orbit.pos.cross(&orbit.vel);
+    h.x.atan2(-h.y)
+}
+
+/// The regression, in radians a second, that theory gives for an orbit.
+fn theory(orbit: &OrbitalBody) -> f64 {
+    let h = orbit.pos.cross(&orbit.vel);
+    let a = 1.0 / (2.0 / orbit.pos.norm() - orbit.vel.norm_squared() / EARTH_GM);
+    let p = h.norm_squared() / EARTH_GM;
+    let n = (EARTH_GM / (a * a * a)).sqrt();
+    let cos_i = h.z / h.norm();
+    -1.5 * n * EARTH_J2 * (EARTH_RADIUS / p).powi(2) * cos_i
+}
+
+/// The regression, in radians a second, of an orbit run with J2.
+fn measured(initial: &OrbitalBody) -> Result<f64, String> {
+    let orbit_period = period(&initial.pos, &initial.vel, EARTH_GM).ok_or("not closed")?;
+    let duration = (DURATION / orbit_period).round() * orbit_period;
+    let mut sim = SimulationBuilder::new()
+        .add_body(
+            "earth",
+            MassiveBody { gm: EARTH_GM },
+            OrbitalBody {
+                pos: Vector3::zeros(),
+                vel: Vector3::zeros(),
+            },
+        )
+        .add_craft("craft", initial.clone())
+        .set_integrator(Method::Rk45 { tolerance: 1.0e-11 })
+        .enable_perturbation(Perturbation::J2 {
+            body: "earth".to_string(),
+            j2: EARTH_J2,
+            radius: EARTH_RADIUS,
+            pole: Unit::new_normalize(Vector3::z()),
+        })
+        .build()?;
+    sim.run(duration, STEP);
+    let end = sim.relative("craft", "earth").ok_or("no craft")?;
+    let turned = (node(&end) - node(initial) + TAU / 2.0).rem_euclid(TAU) - TAU / 2.0;
+    Ok(turned / duration)
+}
+
+fn main() -> Result<(), String> {
+    let orbits = [
+        ("ISS 420 km, 51.6°", circular(420.0, 51.6)),
+        ("sun synchronous 700 km, 98.19°", circular(700.0, 98.19)),
+    ];
+
+    // Radians a second to degrees a day.
+    let per_day = 86400.0f64.to_degrees();
+    println!("| orbit | theory (°/day) | measured (°/day) | difference |");
+    println!("|---|---:|---:|---:|");
+    let mut over = Vec::new();
+    for (name, initial) in &orbits {
+        let expected = theory(initial);
+        let rate = measured(initial)?;
+        let difference = (rate - expected) / expected;
+        println!(
+            "| {} | {:.4} | {:.4} | {:.2}% |",
+            name,
+            expected * per_day,
+            rate * per_day,
+            difference * 100.0
+        );
+        if difference.is_nan() || difference.abs() >= BOUND {
+            over.push(*name);
+        }
+    }
+    assert!(over.is_empty(), "Off the theory: {}", over.join(", "));
+    Ok(())
+}
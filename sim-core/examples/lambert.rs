@@ -0,0 +1,113 @@
+//! Check Lambert's problem by flying its transfers.
+//!
+//! For each case, `lambert` gives the velocity to leave one position on, to
+//! arrive at another a given time later.  A craft is started there on that
+//! velocity, and run through a `Simulation`, about the earth alone, for the
+//! time of flight: it should end up at the second position, going at the
+//! arrival velocity `lambert` gave.  The cases cover the short way and the
+//! long way round, an elliptic transfer up to geostationary height, and a
+//! quick, hyperbolic one.  A miss of more than a metre, or a velocity off by
+//! more than a millimetre a second, fails.
+//!
+//! Run with: cargo run --release -p sim-core --example lambert
+//!
+//! `cargo test` runs it too, and fails if any transfer misses.
+
+extern crate nalgebra as na;
+
+use na::Vector3;
+use sim_core::{MassiveBody, OrbitalBody, SimulationBuilder, integrator::Method, lambert::lambert};
+
+const EARTH_GM: f64 = 398600.4418;
+const EARTH_RADIUS: f64 = 6378.137;
+
+/// The time step, in seconds.
+const STEP: f64 = 10.0;
+
+/// The most the arrival can miss by, in km, and in km/s.
+const MISS: f64 = 1.0e-3;
+const MISS_VEL: f64 = 1.0e-6;
+
+/// A transfer: from a radius on the X axis, to one at an angle, in degrees,
+/// around the Z axis (or past 180°, the long way round), in a time, in
+/// seconds.
+struct Case {
+    name: &'static str,
+    from: f64,
+    to: f64,
+    angle: f64,
+    tof: f64,
+}
+
+const CASES: [Case; 4] = [
+    Case {
+        name: "LEO to LEO, a quarter turn",
+        from: EARTH_RADIUS + 400.0,
+        to: EARTH_RADIUS + 600.0,
+        angle: 90.0,
+        tof: 1500.0,
+    },
+    Case {
+        name: "LEO to LEO, the long way",
+        from: EARTH_RADIUS + 400.0,
+        to: EARTH_RADIUS + 600.0,
+        angle: 270.0,
+        tof: 4200.0,
+    },
+    Case {
+        name: "LEO to GEO",
+        from: EARTH_RADIUS + 300.0,
+        to: 42164.0,
+        angle: 170.0,
+        tof: 5.0 * 3600.0,
+    },
+    Case {
+        name: "LEO out, hyperbolic",
+        from: EARTH_RADIUS + 300.0,
+        to: 60000.0,
+        angle: 120.0,
+        tof: 3.0 * 3600.0,
+    },
+];
+
+fn main() -> Result<(), String> {
+    println!("| transfer | departure (km/s) | energy (km^2/s^2) | miss (km) | velocity (km/s) |");
+    println!("|---|---:|---:|---:|---:|");
+    let mut missed = Vec::new();
+    for case in &CASES {
+        let (s, c) = case.angle.to_radians().sin_cos();
+        let r1 = Vector3::new(case.from, 0.0, 0.0);
+        let r2 = Vector3::new(c, s, 0.0) * case.to;
+        let (v1, v2) = lambert(&r1, &r2, case.tof, EARTH_GM, &Vector3::z())
+            .ok_or_else(|| format!("No transfer for {}", case.name))?;
+
+        let mut sim = SimulationBuilder::new()
+            .add_body(
+                "earth",
+                MassiveBody { gm: EARTH_GM },
+                OrbitalBody {
+                    pos: Vector3::zeros(),
+                    vel: Vector3::zeros(),
+                },
+            )
+            .add_craft("craft", OrbitalBody { pos: r1, vel: v1 })
+            .set_integrator(Method::Rk45 { tolerance: 1.0e-12 })
+            .build()?;
+        sim.run(case.tof, STEP);
+        let end = sim.relative("craft", "earth").ok_or("no craft")?;
+        let (miss, miss_vel) = ((end.pos - r2).norm(), (end.vel - v2).norm());
+        println!(
+            "| {} | {:.4} | {:.3} | {:.2e} | {:.2e} |",
+            case.name,
+            v1.norm(),
+            v1.norm_squared() / 2.0 - EARTH_GM / case.from,
+            miss,
+            miss_vel
+        );
+        if miss.is_nan() || miss >= MISS || miss_vel >= MISS_VEL {
+            missed.push(case.name);
+        }
+    }
+    assert!(missed.is_empty(), "Missed: {}", missed.join(", "));
+    Ok(())
+}
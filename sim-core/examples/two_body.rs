@@ -0,0 +1,105 @@
+//! Check the crafts' two-body orbits against the conic.
+//!
+//! With nothing but its primary pulling on it, a craft should follow its
+//! Keplerian orbit, which `kepler::propagate` has in closed form.  A circular
+//! low orbit and a Molniya orbit are each run for a day through a
+//! `Simulation`, with every `integrator::Method` at the step it is meant for,
+//! and the positions compared with the conic at every step.  The largest
+//! errors are printed as a markdown table, and each has to be under its
+//! bound, which is about twice what it comes to now: the runs are the same
+//! every time, so this catches an integrator getting worse.  Encke's method
+//! has only the rounding to get wrong.
+//!
+//! Run with: cargo run --release -p sim-core --example two_body
+//!
+//! `cargo test` runs it too, and fails if any error is over its bound.
+
+extern crate nalgebra as na;
+
+use na::Vector3;
+use sim_core::{
+    MassiveBody, OrbitalBody, SimulationBuilder, integrator::Method, kepler::propagate,
+};
+
+const EARTH_GM: f64 = 398600.4418;
+const EARTH_RADIUS: f64 = 6378.137;
+
+/// How long each orbit is run, in seconds.
+const DURATION: f64 = 86400.0;
+
+/// Each method, the step it is run at, in seconds, and the most its position
+/// may be off the conic, in km, in the low orbit, and in the Molniya.
+const METHODS: [(Method, f64, [f64; 2]); 4] = [
+    (Method::Euler, 1.0, [30.0, 100.0]),
+    (Method::Leapfrog, 10.0, [60.0, 300.0]),
+    (Method::Rk45 { tolerance: 1.0e-9 }, 60.0, [3.0e-2, 1.0e-2]),
+    (Method::Encke { rectify: 1.0e-3 }, 60.0, [1.0e-6, 1.0e-6]),
+];
+
+/// An orbit with the given periapsis and apoapsis altitudes, and inclination,
+/// in degrees, starting at periapsis.
+fn orbit(periapsis: f64, apoapsis: f64, inclination: f64) -> OrbitalBody {
+    let rp = EARTH_RADIUS + periapsis;
+    let a = (rp + EARTH_RADIUS + apoapsis) / 2.0;
+    let v = (EARTH_GM * (2.0 / rp - 1.0 / a)).sqrt();
+    let (s, c) = inclination.to_radians().sin_cos();
+    OrbitalBody {
+        pos: Vector3::new(rp, 0.0, 0.0),
+        vel: Vector3::new(0.0, c, s) * v,
+    }
+}
+
+/// The largest distance, in km, the craft gets from the conic.
+fn largest_error(method: Method, dt: f64, initial: &OrbitalBody) -> Result<f64, String> {
+    let mut sim = SimulationBuilder::new()
+        .add_body(
+            "earth",
+            MassiveBody { gm: EARTH_GM },
+            OrbitalBody {
+                pos: Vector3::zeros(),
+                vel: Vector3::zeros(),
+            },
+        )
+        .add_craft("craft", initial.clone())
+        .set_integrator(method)
+        .build()?;
+    let mut largest = 0.0f64;
+    while sim.time() < DURATION {
+        sim.step(dt);
+        let (expected, _) = propagate(&initial.pos, &initial.vel, EARTH_GM, sim.time());
+        let craft = sim.relative("craft", "earth").ok_or("no craft")?;
+        largest = largest.max((craft.pos - expected).norm());
+    }
+    Ok(largest)
+}
+
+fn main() -> Result<(), String> {
+    let orbits = [
+        ("LEO 400 km, 51.6°", orbit(400.0, 400.0, 51.6)),
+        ("Molniya 500 x 39800 km, 63.4°", orbit(500.0, 39800.0, 63.4)),
+    ];
+
+    println!("Largest position error from the conic over {} s.", DURATION);
+    println!();
+    println!("| orbit | method | dt (s) | error (km) | bound (km) |");
+    println!("|---|---|---:|---:|---:|");
+    let mut over = Vec::new();
+    for (which, (name, initial)) in orbits.iter().enumerate() {
+        for (method, dt, bounds) in METHODS {
+            let error = largest_error(method, dt, initial)?;
+            println!(
+                "| {} | {} | {} | {:.3e} | {:.0e} |",
+                name,
+                method.name(),
+                dt,
+                error,
+                bounds[which]
+            );
+            if error.is_nan() || error >= bounds[which] {
+                over.push(format!("{} in {}", method.name(), name));
+            }
+        }
+    }
+    assert!(over.is_empty(), "Over the bound: {}", over.join(", "));
+    Ok(())
+}
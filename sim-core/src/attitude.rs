@@ -0,0 +1,255 @@
+//! Integrating the rotation.
+//!
+//! Every `AttitudeState` is stepped the same way, by the scheme in the
+//! `AttitudeIntegrator`.  A body with a `RigidBody` follows Euler's equations,
+//! so that its rate wanders as it tumbles; anything else just turns at its
+//! rate.  Either way, its `AttitudeControl` (if it has one) is held over the
+//! step.
+//!
+//! A rigid body with a `NutationDamper` loses energy inside it, as a real one
+//! does to fuel sloshing, or flexing antennas, while keeping its angular
+//! momentum.  So it settles, over the damper's time constant, into a spin
+//! about its major axis, the one with the least energy for the momentum.  A
+//! body spun about its minor axis, like Explorer 1, goes over into a flat
+//! spin.
+//!
+//! The schemes, cheapest first:
+//!
+//! - `Leapfrog`: the rate is kicked, and the attitude turned at the new rate.
+//!   First order in the rate, but it is what the sim has always done.
+//! - `Pcdm`: the improved predictor-corrector direct multiplication scheme
+//!   (Omelyan), second order.  It keeps the state half a step ahead of the
+//!   clock, which is all the same to anything that just reads it, but
+//!   anything that changes the rate has to allow for (see
+//!   `AttitudeIntegrator::lead`).
+//! - `Rk4`: classic fourth order Runge-Kutta on the quaternion and the rate.
+//!   The quaternion drifts off unit length, which `Renormalize` cleans up.
+
+extern crate nalgebra as na;
+use alloc::{format, string::String};
+#[cfg(feature = "bevy")]
+use bevy::prelude::*;
+use na::{Quaternion, UnitQuaternion, Vector3};
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+use serde::{Deserialize, Serialize};
+
+use crate::AttitudeState;
+
+/// An object whose rotation follows Euler's equations, with the principal
+/// moments of inertia along its BODY axes.  Units are kg*m^2, although only
+/// their ratios matter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Component))]
+pub struct RigidBody {
+    pub inertia_b: Vector3<f64>,
+}
+
+/// Internal energy dissipation, for a `RigidBody`.  The damper's torque is
+/// across the angular momentum, against the part of the rate that isn't
+/// along it, so that the nutation dies away over about `time_constant`
+/// seconds, where the spin is stable, and grows where it isn't.  Being
+/// inside the body, it can't turn the angular momentum in the world, so the
+/// body is turned under it instead.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Component))]
+pub struct NutationDamper {
+    pub time_constant: f64,
+}
+
+impl NutationDamper {
+    /// The damper's angular acceleration, BODY frame, at rate `omega_b`.
+    pub fn alpha_b(&self, inertia_b: &Vector3<f64>, omega_b: &Vector3<f64>) -> Vector3<f64> {
+        let h_b = inertia_b.component_mul(omega_b);
+        let h_squared = h_b.norm_squared();
+        if h_squared == 0.0 {
+            return Vector3::zeros();
+        }
+        let across = |v: &Vector3<f64>| v - h_b * (h_b.dot(v) / h_squared);
+        -across(&inertia_b.component_mul(&across(omega_b))).component_div(inertia_b)
+            / self.time_constant
+    }
+}
+
+/// How the rotation is integrated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RotationScheme {
+    #[default]
+    Leapfrog,
+    Pcdm,
+    Rk4,
+}
+
+impl RotationScheme {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "leapfrog" => Ok(RotationScheme::Leapfrog),
+            "pcdm" => Ok(RotationScheme::Pcdm),
+            "rk4" => Ok(RotationScheme::Rk4),
+            _ => Err(format!(
+                "Unknown scheme {:?}, try leapfrog, pcdm, or rk4",
+                name
+            )),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RotationScheme::Leapfrog => "leapfrog",
+            RotationScheme::Pcdm => "pcdm",
+            RotationScheme::Rk4 => "rk4",
+        }
+    }
+}
+
+/// When the attitude quaternion is brought back to unit length.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Renormalize {
+    #[default]
+    EveryStep,
+    /// Once its length is off by more than this.
+    Beyond(f64),
+    /// Never, such as to see how far a scheme drifts.
+    Never,
+}
+
+/// The scheme the rotation is integrated with, and how the quaternion is kept
+/// unit length.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Resource))]
+pub struct AttitudeIntegrator {
+    pub scheme: RotationScheme,
+    pub renormalize: Renormalize,
+}
+
+/// The angular acceleration, BODY frame, at rate `omega_b`: the control's
+/// `alpha_b`, less the gyroscopic term if the inertia is known, and with the
+/// damper's, if there is one.
+fn omega_dot_b(
+    inertia_b: Option<&Vector3<f64>>,
+    damper: Option<&NutationDamper>,
+    omega_b: &Vector3<f64>,
+    alpha_b: &Vector3<f64>,
+) -> Vector3<f64> {
+    match inertia_b {
+        Some(inertia) => {
+            let damping = damper.map_or_else(Vector3::zeros, |d| d.alpha_b(inertia, omega_b));
+            alpha_b + damping
+                - omega_b
+                    .cross(&inertia.component_mul(omega_b))
+                    .component_div(inertia)
+        }
+        None => *alpha_b,
+    }
+}
+
+/// The rate of change of the body to world quaternion, turning at `omega_b`.
+fn q_dot(q: &Quaternion<f64>, omega_b: &Vector3<f64>) -> Quaternion<f64> {
+    q * Quaternion::from_imag(*omega_b) * 0.5
+}
+
+impl AttitudeIntegrator {
+    /// How far ahead of the clock, in seconds, the states this keeps are,
+    /// stepping by `dt`.  Anything that changes a rate outside the physics
+    /// gives this to `AttitudeState::set_omega_b` and the like.
+    pub fn lead(&self, dt: f64) -> f64 {
+        match self.scheme {
+            RotationScheme::Pcdm => 0.5 * dt,
+            RotationScheme::Leapfrog | RotationScheme::Rk4 => 0.0,
+        }
+    }
+
+    /// Step `state` on by `dt`, with the inertia, and damper, if it has
+    /// them, and the commanded angular acceleration, BODY frame, held over
+    /// the step.  The damper does nothing without the inertia.
+    pub fn step(
+        &self,
+        state: &mut AttitudeState,
+        inertia_b: Option<&Vector3<f64>>,
+        damper: Option<&NutationDamper>,
+        alpha_b: &Vector3<f64>,
+        dt: f64,
+    ) {
+        // Where the angular momentum, world frame, should end up: moved by
+        // the control, but not by the damper.
+        let momentum_w = |state: &AttitudeState, inertia: &Vector3<f64>| {
+            state
+                .q_bw
+                .transform_vector(&inertia.component_mul(&state.omega_b))
+        };
+        let kept_w = match (inertia_b, damper) {
+            (Some(inertia), Some(_)) => Some(
+                momentum_w(state, inertia)
+                    + state.q_bw.transform_vector(&inertia.component_mul(alpha_b)) * dt,
+            ),
+            _ => None,
+        };
+
+        match self.scheme {
+            RotationScheme::Leapfrog => {
+                state.omega_b += omega_dot_b(inertia_b, damper, &state.omega_b, alpha_b) * dt;
+                state.q_bw = state.q_bw_after(dt);
+            }
+            RotationScheme::Pcdm => {
+                // The state is at the half step, n + 1/2, and the angular
+                // acceleration at n is taken from it.
+                let omega_half = state.omega_b;
+                let omega_dot_n = omega_dot_b(inertia_b, damper, &omega_half, alpha_b);
+
+                // Predict the attitude and rate at n + 1.
+                let omega_w_quarter = state
+                    .q_bw
+                    .transform_vector(&(omega_half + omega_dot_n * (0.25 * dt)));
+                let q_n1 =
+                    UnitQuaternion::from_scaled_axis(omega_w_quarter * (0.5 * dt)) * state.q_bw;
+                let omega_n1 = omega_half + omega_dot_n * (0.5 * dt);
+
+                // Correct to n + 3/2.
+                let omega_dot_n1 = omega_dot_b(inertia_b, damper, &omega_n1, alpha_b);
+                state.omega_b = omega_half + omega_dot_n1 * dt;
+                state.q_bw =
+                    UnitQuaternion::from_scaled_axis(q_n1.transform_vector(&omega_n1) * dt)
+                        * state.q_bw;
+            }
+            RotationScheme::Rk4 => {
+                let derivative = |q: &Quaternion<f64>, omega: &Vector3<f64>| {
+                    (
+                        q_dot(q, omega),
+                        omega_dot_b(inertia_b, damper, omega, alpha_b),
+                    )
+                };
+                let (q0, w0) = (*state.q_bw.quaternion(), state.omega_b);
+                let (dq1, dw1) = derivative(&q0, &w0);
+                let (dq2, dw2) = derivative(&(q0 + dq1 * (0.5 * dt)), &(w0 + dw1 * (0.5 * dt)));
+                let (dq3, dw3) = derivative(&(q0 + dq2 * (0.5 * dt)), &(w0 + dw2 * (0.5 * dt)));
+                let (dq4, dw4) = derivative(&(q0 + dq3 * dt), &(w0 + dw3 * dt));
+                let q = q0 + (dq1 + dq2 * 2.0 + dq3 * 2.0 + dq4) * (dt / 6.0);
+                state.omega_b = w0 + (dw1 + dw2 * 2.0 + dw3 * 2.0 + dw4) * (dt / 6.0);
+                state.q_bw = UnitQuaternion::new_unchecked(q);
+            }
+        }
+        if let (Some(inertia), Some(kept_w)) = (inertia_b, kept_w) {
+            // The turns are tiny, so the angle is taken from its sine and
+            // cosine both, rather than lost in the cosine alone.
+            let moved_w = momentum_w(state, inertia);
+            let axis = moved_w.cross(&kept_w);
+            let sine = axis.norm();
+            if sine > 0.0 {
+                let angle = sine.atan2(moved_w.dot(&kept_w));
+                state.q_bw = UnitQuaternion::from_scaled_axis(axis * (angle / sine)) * state.q_bw;
+            }
+        }
+
+        match self.renormalize {
+            Renormalize::EveryStep => {
+                state.q_bw.renormalize();
+            }
+            Renormalize::Beyond(tolerance) => {
+                if (state.q_bw.norm() - 1.0).abs() > tolerance {
+                    state.q_bw.renormalize();
+                }
+            }
+            Renormalize::Never => {}
+        }
+    }
+}
@@ -0,0 +1,283 @@
+//! Summing the pull of many bodies, with a Barnes-Hut tree.
+//!
+//! Summed directly, the bodies' pull on each other costs the square of how
+//! many there are, which is nothing for the planets and moons, but too much
+//! for a belt of hundreds of asteroids or debris.  The tree sorts the bodies
+//! into nested cubes (an octree), each knowing the total GM of what is in it,
+//! and where its center of mass is.  A cube that is small, as seen from where
+//! the pull is wanted, pulls as one body at its center of mass; one that
+//! isn't is opened, down to the bodies themselves.  Each pull then costs the
+//! log of the number of bodies.  How small is small is `theta`, the cube's
+//! width over its distance: the larger, the faster, and the rougher.
+//!
+//! Each body can carry its velocity and acceleration, too, and the tree be
+//! asked for the pull some time after it was built, with each body taken
+//! along a parabola from where it was.  Those are averages by GM, as the
+//! center of mass is, so the cubes' centers of mass move exactly as their
+//! bodies do, and one tree does for a whole step.  Only the cubes' sizes are
+//! left as they were built.
+//!
+//! `GravitySolver` picks between the two: directly, which is exact, for a
+//! handful of bodies, and the tree from `threshold` on.
+
+extern crate nalgebra as na;
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "bevy")]
+use bevy::prelude::*;
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+
+/// The most bodies left in a cube without opening it up.
+const LEAF_SIZE: usize = 8;
+
+/// How deep the cubes go, so that bodies at the same place (or nearly) end up
+/// in one cube, rather than in ever smaller ones.
+const MAX_DEPTH: usize = 32;
+
+/// When the pull of the bodies is summed with a tree, rather than directly.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Resource))]
+pub struct GravitySolver {
+    /// The number of bodies, with a GM, from which the tree is used.
+    pub threshold: usize,
+    /// The widest a cube may look, as its width over its distance, to pull
+    /// as one body.
+    pub theta: f64,
+}
+
+impl Default for GravitySolver {
+    /// The tree from 512 bodies, which is about where it starts to win (see
+    /// the `gravity` example), and well beyond the solar system's own.
+    fn default() -> Self {
+        GravitySolver {
+            threshold: 512,
+            theta: 0.5,
+        }
+    }
+}
+
+impl GravitySolver {
+    /// The tree for the bodies, if there are enough of them for one.
+    pub fn tree(&self, bodies: &[TreeBody]) -> Option<Octree> {
+        (bodies.len() >= self.threshold).then(|| Octree::new(bodies.to_vec(), self.theta))
+    }
+}
+
+/// A body in the tree.
+#[derive(Clone, Debug)]
+pub struct TreeBody {
+    pub pos: Vector3<f64>,
+    pub vel: Vector3<f64>,
+    pub accel: Vector3<f64>,
+    pub gm: f64,
+}
+
+impl TreeBody {
+    /// A body that is taken to stay put.
+    pub fn fixed(pos: Vector3<f64>, gm: f64) -> Self {
+        TreeBody {
+            pos,
+            vel: Vector3::zeros(),
+            accel: Vector3::zeros(),
+            gm,
+        }
+    }
+
+    fn pos_at(&self, dt: f64) -> Vector3<f64> {
+        self.pos + self.vel * dt + self.accel * (dt * dt / 2.0)
+    }
+}
+
+/// A cube, with its bodies at `start..end` in the tree's order, and its
+/// children, if it has been opened up, at `children` in the nodes.
+#[derive(Clone, Debug)]
+struct Node {
+    width: f64,
+    start: usize,
+    end: usize,
+    children: core::ops::Range<usize>,
+    gm: f64,
+    /// The center of mass, as a `TreeBody`, so it moves as its bodies do.
+    center: TreeBody,
+}
+
+/// The bodies, sorted into cubes.
+#[derive(Clone, Debug)]
+pub struct Octree {
+    theta: f64,
+    bodies: Vec<TreeBody>,
+    /// The bodies, by index, so that each cube's are together.
+    order: Vec<usize>,
+    /// Where each body is in `order`.
+    rank: Vec<usize>,
+    nodes: Vec<Node>,
+}
+
+impl Octree {
+    /// Sort the bodies into a tree, opening cubes wider than `theta` as seen
+    /// from where the pull is wanted.
+    pub fn new(bodies: Vec<TreeBody>, theta: f64) -> Self {
+        let mut tree = Octree {
+            theta,
+            order: (0..bodies.len()).collect(),
+            rank: vec![0; bodies.len()],
+            nodes: Vec::new(),
+            bodies,
+        };
+        if tree.bodies.is_empty() {
+            return tree;
+        }
+        let (min, max) = tree.bodies.iter().fold(
+            (tree.bodies[0].pos, tree.bodies[0].pos),
+            |(min, max), body| (min.inf(&body.pos), max.sup(&body.pos)),
+        );
+        tree.nodes
+            .push(tree.node(0, tree.bodies.len(), (max - min).max()));
+        tree.split(0, (min + max) / 2.0, 0);
+        for (rank, &i) in tree.order.iter().enumerate() {
+            tree.rank[i] = rank;
+        }
+        tree
+    }
+
+    /// A cube holding the bodies at `start..end`, with its center of mass.
+    fn node(&self, start: usize, end: usize, width: f64) -> Node {
+        let mut center = TreeBody::fixed(Vector3::zeros(), 0.0);
+        for &i in &self.order[start..end] {
+            let body = &self.bodies[i];
+            center.pos += body.pos * body.gm;
+            center.vel += body.vel * body.gm;
+            center.accel += body.accel * body.gm;
+            center.gm += body.gm;
+        }
+        if center.gm > 0.0 {
+            center.pos /= center.gm;
+            center.vel /= center.gm;
+            center.accel /= center.gm;
+        }
+        Node {
+            width,
+            start,
+            end,
+            children: 0..0,
+            gm: center.gm,
+            center,
+        }
+    }
+
+    /// Open up a node, centered at `mid`, if it holds enough bodies, and its
+    /// children, and theirs.
+    fn split(&mut self, index: usize, mid: Vector3<f64>, depth: usize) {
+        let Node {
+            width, start, end, ..
+        } = self.nodes[index];
+        if end - start <= LEAF_SIZE || depth >= MAX_DEPTH {
+            return;
+        }
+        let octant = |pos: &Vector3<f64>| {
+            usize::from(pos.x >= mid.x)
+                | usize::from(pos.y >= mid.y) << 1
+                | usize::from(pos.z >= mid.z) << 2
+        };
+        let bodies = &self.bodies;
+        self.order[start..end].sort_by_key(|&i| octant(&bodies[i].pos));
+
+        let first = self.nodes.len();
+        let mut from = start;
+        let mut mids = Vec::new();
+        for k in 0..8 {
+            let to = from + self.order[from..end].partition_point(|&i| octant(&bodies[i].pos) == k);
+            if to > from {
+                let node = self.node(from, to, width / 2.0);
+                self.nodes.push(node);
+                let sign = |bit: usize| if k & bit != 0 { 1.0 } else { -1.0 };
+                mids.push(mid + Vector3::new(sign(1), sign(2), sign(4)) * (width / 4.0));
+            }
+            from = to;
+        }
+        self.nodes[index].children = first..self.nodes.len();
+        for (child, mid) in (first..self.nodes.len()).zip(mids) {
+            self.split(child, mid, depth + 1);
+        }
+    }
+
+    /// The pull, at `pos`, of every body but `skip` (such as the one at
+    /// `pos`), as they are `dt` after the tree was built.
+    pub fn accel(&self, pos: &Vector3<f64>, dt: f64, skip: Option<usize>) -> Vector3<f64> {
+        let mut accel = Vector3::zeros();
+        self.visit(pos, dt, skip, |at, gm| accel += pull(pos, at, gm));
+        accel
+    }
+
+    /// The pull at `pos`, less that at `from`, of every body but `skip`, as
+    /// for `accel`.  This is the tide across the two, such as a craft and
+    /// its primary, which the same cubes pulling on both keeps smooth.
+    pub fn tide(
+        &self,
+        pos: &Vector3<f64>,
+        from: &Vector3<f64>,
+        dt: f64,
+        skip: Option<usize>,
+    ) -> Vector3<f64> {
+        let mut accel = Vector3::zeros();
+        self.visit(pos, dt, skip, |at, gm| {
+            accel += pull(pos, at, gm) - pull(from, at, gm);
+        });
+        accel
+    }
+
+    /// Each cube that is small enough from `pos` to pull as one body, and
+    /// each body in those that aren't, but `skip`, as where it is `dt` after
+    /// the tree was built, and its GM.
+    fn visit(
+        &self,
+        pos: &Vector3<f64>,
+        dt: f64,
+        skip: Option<usize>,
+        mut f: impl FnMut(&Vector3<f64>, f64),
+    ) {
+        let skip = skip.map(|i| self.rank[i]);
+        // The cubes still to look at.  Each level down leaves at most seven
+        // of one cube's children for later, so this is never outgrown.
+        let mut open = [0; 8 * MAX_DEPTH + 1];
+        let mut count = usize::from(!self.nodes.is_empty());
+        while count > 0 {
+            count -= 1;
+            let index = open[count];
+            let node = &self.nodes[index];
+            if node.gm == 0.0 {
+                continue;
+            }
+            if !node.children.is_empty() {
+                let at = node.center.pos_at(dt);
+                let holds_skip = skip.is_some_and(|rank| (node.start..node.end).contains(&rank));
+                if !holds_skip && node.width < self.theta * (at - pos).norm() {
+                    f(&at, node.gm);
+                } else {
+                    for child in node.children.clone() {
+                        open[count] = child;
+                        count += 1;
+                    }
+                }
+                continue;
+            }
+            for rank in node.start..node.end {
+                if skip != Some(rank) {
+                    let body = &self.bodies[self.order[rank]];
+                    f(&body.pos_at(dt), body.gm);
+                }
+            }
+        }
+    }
+}
+
+/// The pull at `pos` of a body at `at`, or none if it is right there.
+fn pull(pos: &Vector3<f64>, at: &Vector3<f64>, gm: f64) -> Vector3<f64> {
+    let rel = at - pos;
+    let d = rel.norm();
+    if d > 0.0 {
+        rel * (gm / (d * d * d))
+    } else {
+        Vector3::zeros()
+    }
+}
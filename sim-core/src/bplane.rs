@@ -0,0 +1,95 @@
+//! B-plane targeting.
+//!
+//! A hyperbolic approach to a body is told by where its incoming asymptote
+//! pierces the B-plane: the plane through the body's center, square to the
+//! asymptote's direction S.  B is the vector from the center to that point.
+//! T lies in the plane, along S × k, where k is the pole of some reference
+//! plane (such as the body's orbit), and R = S × T completes the frame.  B·T
+//! and B·R are what a targeter steers, being close to linear in the burns that
+//! change them, where the closest approach isn't.  |B| and the speed at
+//! infinity give the closest approach, and the direction of B, which side of
+//! the body it is on.
+//!
+//! The units are whatever the caller uses consistently, as in `kepler`, and
+//! the vectors are relative to the body.
+
+extern crate nalgebra as na;
+use na::Vector3;
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+/// Where a hyperbolic approach is headed.
+#[derive(Clone, Debug)]
+pub struct BPlane {
+    /// The direction of the incoming asymptote.
+    pub s: Vector3<f64>,
+    /// The axes of the B-plane.
+    pub t: Vector3<f64>,
+    pub r: Vector3<f64>,
+    /// The B vector.
+    pub b: Vector3<f64>,
+    /// The speed at infinity.
+    pub v_inf: f64,
+}
+
+impl BPlane {
+    /// The B-plane of the conic through `pos` and `vel`, about a body with the
+    /// given `gm`, with T in the plane with normal `pole`.  None unless the
+    /// conic is a hyperbola, or if the asymptote lies along the pole.
+    pub fn new(
+        pos: &Vector3<f64>,
+        vel: &Vector3<f64>,
+        gm: f64,
+        pole: &Vector3<f64>,
+    ) -> Option<Self> {
+        let r = pos.norm();
+        let v_inf2 = vel.norm_squared() - 2.0 * gm / r;
+        if v_inf2 <= 0.0 {
+            return None;
+        }
+        let h = pos.cross(vel);
+        let h_dir = h.try_normalize(1.0e-12)?;
+        let e_vec = (pos * (vel.norm_squared() - gm / r) - vel * pos.dot(vel)) / gm;
+        let e = e_vec.norm();
+        let p_dir = e_vec / e;
+        // The craft comes in from the true anomaly whose cosine is -1/e.
+        let s = p_dir / e + h_dir.cross(&p_dir) * (1.0 - 1.0 / (e * e)).sqrt();
+        let b_mag = h.norm() / v_inf2.sqrt();
+        let b = s.cross(&h_dir) * b_mag;
+        let t = s.cross(pole).try_normalize(1.0e-12)?;
+        let r = s.cross(&t);
+        Some(BPlane {
+            s,
+            t,
+            r,
+            b,
+            v_inf: v_inf2.sqrt(),
+        })
+    }
+
+    pub fn b_dot_t(&self) -> f64 {
+        self.b.dot(&self.t)
+    }
+
+    pub fn b_dot_r(&self) -> f64 {
+        self.b.dot(&self.r)
+    }
+
+    /// The closest approach, as a distance from the body's center.
+    pub fn periapsis(&self, gm: f64) -> f64 {
+        periapsis(self.b.norm(), self.v_inf, gm)
+    }
+}
+
+/// The closest approach of a hyperbola with a miss distance `b` (the length of
+/// B), and a speed at infinity `v_inf`, about a body with the given `gm`.
+pub fn periapsis(b: f64, v_inf: f64, gm: f64) -> f64 {
+    let a = gm / (v_inf * v_inf);
+    (a * a + b * b).sqrt() - a
+}
+
+/// The miss distance, the length of B, that the body's pull bends into a
+/// closest approach `periapsis`, for a speed at infinity `v_inf`.
+pub fn miss_distance(periapsis: f64, v_inf: f64, gm: f64) -> f64 {
+    periapsis * (1.0 + 2.0 * gm / (periapsis * v_inf * v_inf)).sqrt()
+}
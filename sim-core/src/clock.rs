@@ -0,0 +1,169 @@
+//! The sim clock: how many physics steps each frame runs.
+//!
+//! The physics only moves on the fixed step, so two runs given the same
+//! input end up in the same state exactly when they take the same steps,
+//! with the input arriving between the same two of them.  Frames can't be
+//! relied on for that: they come as fast as the machine manages, so how many
+//! steps fit in one depends on the machine.  A replay, or a second machine
+//! keeping in step with the first, has to be told how many to take.
+//!
+//! So the steps are counted in whole numbers.  Each frame's time, after the
+//! warp (`Time<Virtual>`), is added to what the clock is owed, in
+//! nanoseconds, and as many whole fixed steps as that covers are run, the
+//! rest being kept for the next frame.  Nothing is rounded along the way, so
+//! the same frame times give the same steps on any platform.  Better still,
+//! `SimClock::take` says how many the next frame runs, whatever its time:
+//! the replay does this, from the count recorded for each frame, so it takes
+//! the same steps however the frames were timed.  `SimClock::ticks` counts
+//! them all, and is the one clock two runs can be compared by.  With the
+//! time paused, a frame's own time is nothing, and it runs only the steps
+//! it is told to: this is how the sim is stepped through by hand.
+//!
+//! A frame runs no more than `SimClock::budget` steps of its own time, so
+//! that a warp the machine can't keep up with, or a frame held up, doesn't
+//! leave the next one with yet more to run, and so on, each frame slower
+//! than the last.  Past the budget, the steps aren't owed, but dropped, and
+//! the sim goes slower than the warp asks, in slow motion, until it can keep
+//! up again (`SimClock::dropped` says by how much).  The budget is a count of
+//! steps, not a time, so it is the same on any machine, and the runs still
+//! agree.  The steps a replay is told to take are taken in full.
+//!
+//! A step can also call the frame off (`SimClock::halt`), so that nothing
+//! runs past it: an alarm going off drops out of warp this way, at the step
+//! it is due, however many more the frame had to run.  The steps not run
+//! aren't owed; they are gone, as the warp they came from is.
+//!
+//! This takes over from bevy's own loop, which would otherwise run
+//! `FixedMain` as well.  `Time<Fixed>` is moved along a whole step at a time,
+//! as bevy does it, so the systems read the time from it just the same.
+
+use bevy::{
+    app::{FixedMain, RunFixedMainLoopSystems},
+    prelude::*,
+};
+use std::time::Duration;
+
+/// The most steps a frame runs, by default: at the usual 64 steps a second,
+/// a warp of 1000 at 60 frames a second.
+pub const DEFAULT_BUDGET: u64 = 1100;
+
+/// The steps the physics has taken, and is owed.
+#[derive(Resource, Clone, Debug)]
+pub struct SimClock {
+    /// Every fixed step run, from the start.
+    pub ticks: u64,
+    /// The time owed, in ns, short of a whole step.
+    pub owed: u128,
+    /// How many steps the last frame ran.
+    pub frame_steps: u64,
+    /// How many steps the next frame is to run, whatever its time.
+    pub scripted: Option<u64>,
+    /// Whether the frame's steps are to stop after the one running.
+    pub halted: bool,
+    /// The most steps a frame runs of its own time.
+    pub budget: u64,
+    /// How many steps the last frame dropped, being over its budget.
+    pub dropped: u64,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        SimClock {
+            ticks: 0,
+            owed: 0,
+            frame_steps: 0,
+            scripted: None,
+            halted: false,
+            budget: DEFAULT_BUDGET,
+            dropped: 0,
+        }
+    }
+}
+
+impl SimClock {
+    /// Run exactly `steps` in the next frame, rather than what its time
+    /// covers.
+    pub fn take(&mut self, steps: u64) {
+        self.scripted = Some(steps);
+    }
+
+    /// Run no more of this frame's steps after the one running.
+    pub fn halt(&mut self) {
+        self.halted = true;
+    }
+
+    /// How far, from 0 to 1, the clock is into the next step.  This stands
+    /// in for `Time<Fixed>::overstep_fraction`, which is left at 0.
+    pub fn fraction(&self, step: Duration) -> f64 {
+        self.owed as f64 / step.as_nanos().max(1) as f64
+    }
+
+    /// Add `delta`, and take the steps that covers, up to the budget, or
+    /// those scripted.
+    fn advance(&mut self, delta: Duration, step: Duration) -> u64 {
+        let step = step.as_nanos().max(1);
+        self.dropped = 0;
+        let steps = match self.scripted.take() {
+            Some(steps) => steps,
+            None => {
+                self.owed += delta.as_nanos();
+                let steps = (self.owed / step) as u64;
+                self.owed %= step;
+                self.dropped = steps.saturating_sub(self.budget);
+                steps - self.dropped
+            }
+        };
+        self.ticks += steps;
+        self.frame_steps = steps;
+        steps
+    }
+}
+
+#[derive(Default)]
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimClock>();
+        // Bevy's loop is left in place, but never runs, and this runs where
+        // it would have.
+        app.configure_sets(
+            RunFixedMainLoop,
+            RunFixedMainLoopSystems::FixedMainLoop.run_if(|| false),
+        );
+        app.add_systems(
+            RunFixedMainLoop,
+            run_steps
+                .after(RunFixedMainLoopSystems::BeforeFixedMainLoop)
+                .before(RunFixedMainLoopSystems::AfterFixedMainLoop),
+        );
+    }
+}
+
+/// Run `FixedMain` for each of this frame's steps.
+pub fn run_steps(world: &mut World) {
+    let delta = world.resource::<Time<Virtual>>().delta();
+    let step = world.resource::<Time<Fixed>>().timestep();
+    let steps = world.resource_mut::<SimClock>().advance(delta, step);
+
+    world.resource_mut::<SimClock>().halted = false;
+    let _ = world.try_schedule_scope(FixedMain, |world, schedule| {
+        for run in 1..=steps {
+            let mut fixed = world.resource_mut::<Time<Fixed>>();
+            fixed.advance_by(step);
+            let generic = fixed.as_generic();
+            *world.resource_mut::<Time>() = generic;
+            schedule.run(world);
+
+            let mut clock = world.resource_mut::<SimClock>();
+            if clock.halted {
+                clock.ticks -= steps - run;
+                clock.frame_steps = run;
+                clock.owed = 0;
+                break;
+            }
+        }
+    });
+
+    *world.resource_mut::<Time>() = world.resource::<Time<Virtual>>().as_generic();
+}
@@ -0,0 +1,189 @@
+//! Conjunction screening: when objects on conics about the same body come
+//! closest to a craft, and how close.
+//!
+//! Screening a catalog against a craft has to be cheap for the many objects
+//! that never come near it, and exact for the few that do, so it goes in
+//! three passes:
+//!
+//! - The apsides: an object that stays above the craft's apoapsis, or below
+//!   its periapsis, by more than the screening distance, can't come near.
+//! - Samples: the craft's conic is sampled once, every so often over the
+//!   span, and each object's at the same times.  An interval is kept only if
+//!   the range rate goes from closing to opening across it, and the range at
+//!   its ends, less what the relative speed covers in half of it, is within
+//!   the screening distance.
+//! - Root finding: in each interval kept, the time the range rate is zero,
+//!   the time of closest approach, is found by false position (the Illinois
+//!   variant), with both propagated along their conics to each guess.
+//!
+//! A closest approach before the span, or after it, isn't found: one that
+//! is still closing at the end is for the next screening.
+
+extern crate nalgebra as na;
+use alloc::vec::Vec;
+use na::Vector3;
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+use crate::{kepler, orbit::Conic};
+
+/// How closely, in seconds, the time of closest approach is found.
+const TIME_TOLERANCE: f64 = 1.0e-4;
+
+/// The most false position steps for one approach.
+const MAX_ITERATIONS: usize = 60;
+
+/// A close approach.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Approach {
+    /// The time of closest approach, in seconds after the screen's start.
+    pub time: f64,
+    /// How close they come, in km.
+    pub miss: f64,
+    /// How fast they pass each other, in km/s.
+    pub speed: f64,
+}
+
+/// The periapsis and apoapsis radii, in km, of the conic through a state.
+/// An open conic's apoapsis is infinite.
+pub fn apsides(pos: &Vector3<f64>, vel: &Vector3<f64>, gm: f64) -> (f64, f64) {
+    let conic = Conic::new(pos, vel, gm);
+    let e = conic.e();
+    let apoapsis = if e < 1.0 {
+        conic.p / (1.0 - e)
+    } else {
+        f64::INFINITY
+    };
+    (conic.p / (1.0 + e), apoapsis)
+}
+
+/// A craft's conic, sampled, to screen objects against.
+#[derive(Clone, Debug)]
+pub struct Screen {
+    gm: f64,
+    pos: Vector3<f64>,
+    vel: Vector3<f64>,
+    step: f64,
+    apsides: (f64, f64),
+    samples: Vec<(Vector3<f64>, Vector3<f64>)>,
+}
+
+impl Screen {
+    /// Sample the conic through `pos` and `vel`, about a body with `gm`,
+    /// every `step` seconds, for `span` seconds from now.
+    pub fn new(pos: &Vector3<f64>, vel: &Vector3<f64>, gm: f64, span: f64, step: f64) -> Self {
+        let count = (span / step).ceil().max(1.0) as usize;
+        let samples = (0..=count)
+            .map(|i| kepler::propagate(pos, vel, gm, i as f64 * step))
+            .collect();
+        Screen {
+            gm,
+            pos: *pos,
+            vel: *vel,
+            step,
+            apsides: apsides(pos, vel, gm),
+            samples,
+        }
+    }
+
+    /// How far ahead, in seconds, it looks.
+    pub fn span(&self) -> f64 {
+        (self.samples.len() - 1) as f64 * self.step
+    }
+
+    /// The object's position and velocity relative to the craft, `time`
+    /// seconds from now.
+    fn relative(
+        &self,
+        pos: &Vector3<f64>,
+        vel: &Vector3<f64>,
+        time: f64,
+    ) -> (Vector3<f64>, Vector3<f64>) {
+        let (object_pos, object_vel) = kepler::propagate(pos, vel, self.gm, time);
+        let (craft_pos, craft_vel) = kepler::propagate(&self.pos, &self.vel, self.gm, time);
+        (object_pos - craft_pos, object_vel - craft_vel)
+    }
+
+    /// The close approaches, within `distance` km, of the object with `pos`
+    /// and `vel` now, in the order they come.
+    pub fn screen(&self, pos: &Vector3<f64>, vel: &Vector3<f64>, distance: f64) -> Vec<Approach> {
+        let (periapsis, apoapsis) = apsides(pos, vel, self.gm);
+        if periapsis > self.apsides.1 + distance || apoapsis < self.apsides.0 - distance {
+            return Vec::new();
+        }
+
+        let relative: Vec<(Vector3<f64>, Vector3<f64>)> = self
+            .samples
+            .iter()
+            .enumerate()
+            .map(|(i, (craft_pos, craft_vel))| {
+                let (object_pos, object_vel) =
+                    kepler::propagate(pos, vel, self.gm, i as f64 * self.step);
+                (object_pos - craft_pos, object_vel - craft_vel)
+            })
+            .collect();
+
+        let mut approaches = Vec::new();
+        for (i, ends) in relative.windows(2).enumerate() {
+            let ((r0, v0), (r1, v1)) = (&ends[0], &ends[1]);
+            let (rate0, rate1) = (r0.dot(v0), r1.dot(v1));
+            if rate0 >= 0.0 || rate1 < 0.0 {
+                continue;
+            }
+            let reach = v0.norm().max(v1.norm()) * self.step / 2.0;
+            if r0.norm().min(r1.norm()) - reach > distance {
+                continue;
+            }
+            let start = i as f64 * self.step;
+            let time = self.closest(pos, vel, (start, rate0), (start + self.step, rate1));
+            let (r, v) = self.relative(pos, vel, time);
+            if r.norm() <= distance {
+                approaches.push(Approach {
+                    time,
+                    miss: r.norm(),
+                    speed: v.norm(),
+                });
+            }
+        }
+        approaches
+    }
+
+    /// The time the range rate is zero, between a time it is closing, and
+    /// one it is opening, each with its range rate.
+    fn closest(
+        &self,
+        pos: &Vector3<f64>,
+        vel: &Vector3<f64>,
+        (mut t0, mut rate0): (f64, f64),
+        (mut t1, mut rate1): (f64, f64),
+    ) -> f64 {
+        let mut time = t0;
+        // Which end moved last, so that one that stays put can be pulled
+        // in.
+        let mut moved = 0;
+        for _ in 0..MAX_ITERATIONS {
+            let next = (t0 * rate1 - t1 * rate0) / (rate1 - rate0);
+            let done = (next - time).abs() < TIME_TOLERANCE;
+            time = next;
+            if done {
+                break;
+            }
+            let (r, v) = self.relative(pos, vel, time);
+            let rate = r.dot(&v);
+            if rate < 0.0 {
+                (t0, rate0) = (time, rate);
+                if moved < 0 {
+                    rate1 /= 2.0;
+                }
+                moved = -1;
+            } else {
+                (t1, rate1) = (time, rate);
+                if moved > 0 {
+                    rate0 /= 2.0;
+                }
+                moved = 1;
+            }
+        }
+        time
+    }
+}
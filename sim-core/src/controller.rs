@@ -0,0 +1,147 @@
+//! Attitude control laws.
+
+extern crate nalgebra as na;
+#[cfg(feature = "bevy")]
+use bevy::prelude::*;
+
+/// A quaternion-feedback attitude controller.
+///
+/// The attitude error is taken as the rotation vector from the current body
+/// frame to the target body frame (the shortest way around).  That error is
+/// turned into a rate command (limited to `max_rate`), and the difference
+/// between that and the current rate is turned into a torque.  While neither
+/// limit is hit, this is a plain PD law on the quaternion error:
+///
+///   τ_b = I ( kp θ_b - kd (ω_b - ω_target) ) + ω_b × (I ω_b)
+///
+/// The gains are normalized by the inertia, so the same controller behaves the
+/// same on crafts of different sizes.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "bevy", derive(Component))]
+pub struct AttitudeController {
+    /// Proportional gain on the attitude error, (rad/s^2) / rad.
+    pub kp: f64,
+
+    /// Derivative gain on the rate error, (rad/s^2) / (rad/s).
+    pub kd: f64,
+
+    /// Largest angular rate, rad/s, commanded while slewing toward a target.
+    pub max_rate: f64,
+
+    /// Largest torque, N*m, about each BODY axis.  When the command exceeds
+    /// this, the whole vector is scaled down to keep its direction.
+    pub max_torque: na::Vector3<f64>,
+
+    /// Attitude errors, in rad, smaller than this are ignored.
+    pub deadband: f64,
+
+    /// Rate errors, in rad/s, smaller than this are ignored.  Along with
+    /// `deadband`, this keeps on/off actuators from chattering around the
+    /// target.
+    pub rate_deadband: f64,
+}
+
+impl Default for AttitudeController {
+    fn default() -> Self {
+        Self::with_natural_frequency(0.5, 0.9)
+    }
+}
+
+impl AttitudeController {
+    /// Build a controller from the desired closed-loop natural frequency
+    /// (rad/s) and damping ratio.  No rate or torque limits are applied.
+    pub fn with_natural_frequency(omega_n: f64, zeta: f64) -> Self {
+        Self {
+            kp: omega_n * omega_n,
+            kd: 2.0 * zeta * omega_n,
+            max_rate: f64::INFINITY,
+            max_torque: na::Vector3::repeat(f64::INFINITY),
+            deadband: 0.0,
+            rate_deadband: 0.0,
+        }
+    }
+
+    /// Set the slew rate limit, rad/s.
+    pub fn with_max_rate(mut self, max_rate: f64) -> Self {
+        self.max_rate = max_rate;
+        self
+    }
+
+    /// Set the per-axis torque limit, N*m.
+    pub fn with_max_torque(mut self, max_torque: na::Vector3<f64>) -> Self {
+        self.max_torque = max_torque;
+        self
+    }
+
+    /// Set the attitude (rad) and rate (rad/s) deadbands.
+    pub fn with_deadband(mut self, deadband: f64, rate_deadband: f64) -> Self {
+        self.deadband = deadband;
+        self.rate_deadband = rate_deadband;
+        self
+    }
+
+    /// The attitude error, as a BODY-frame rotation vector (axis * angle) that
+    /// takes `q_bw` to `q_target_bw`.  Always the short way around.
+    pub fn error_b(
+        q_bw: &na::UnitQuaternion<f64>,
+        q_target_bw: &na::UnitQuaternion<f64>,
+    ) -> na::Vector3<f64> {
+        let mut q_err = q_bw.inverse() * q_target_bw;
+        if q_err.w < 0.0 {
+            q_err = na::UnitQuaternion::new_unchecked(-q_err.into_inner());
+        }
+        q_err.scaled_axis()
+    }
+
+    /// Torque, BODY frame, to drive the craft toward `q_target_bw` while
+    /// rotating at `omega_target_b` (usually zero).
+    pub fn torque_b(
+        &self,
+        q_bw: &na::UnitQuaternion<f64>,
+        omega_b: &na::Vector3<f64>,
+        i_body: &na::Vector3<f64>,
+        q_target_bw: &na::UnitQuaternion<f64>,
+        omega_target_b: &na::Vector3<f64>,
+    ) -> na::Vector3<f64> {
+        let mut theta_b = Self::error_b(q_bw, q_target_bw);
+        if theta_b.norm() < self.deadband {
+            theta_b = na::Vector3::zeros();
+        }
+
+        // Outer loop: attitude error to a rate command.
+        let mut omega_cmd_b = theta_b * (self.kp / self.kd);
+        let rate = omega_cmd_b.norm();
+        if rate > self.max_rate {
+            omega_cmd_b *= self.max_rate / rate;
+        }
+
+        self.rate_torque_b(omega_b, i_body, &(omega_cmd_b + omega_target_b))
+    }
+
+    /// Torque, BODY frame, to bring the craft to the rate `omega_cmd_b`,
+    /// without regard to the attitude.
+    pub fn rate_torque_b(
+        &self,
+        omega_b: &na::Vector3<f64>,
+        i_body: &na::Vector3<f64>,
+        omega_cmd_b: &na::Vector3<f64>,
+    ) -> na::Vector3<f64> {
+        let rate_error_b = omega_cmd_b - omega_b;
+        if rate_error_b.norm() < self.rate_deadband {
+            return na::Vector3::zeros();
+        }
+        let alpha_b = rate_error_b * self.kd;
+        let gyro_b = omega_b.cross(&i_body.component_mul(omega_b));
+        self.limit(i_body.component_mul(&alpha_b) + gyro_b)
+    }
+
+    /// Scale `torque_b` down, if needed, so that no axis exceeds `max_torque`.
+    fn limit(&self, torque_b: na::Vector3<f64>) -> na::Vector3<f64> {
+        let scale = torque_b
+            .iter()
+            .zip(self.max_torque.iter())
+            .map(|(t, max)| if t.abs() > *max { max / t.abs() } else { 1.0 })
+            .fold(1.0, f64::min);
+        torque_b * scale
+    }
+}
@@ -0,0 +1,108 @@
+//! Differential correction.
+//!
+//! Finds the controls (such as a burn's components, or when it is made) that
+//! bring some targets (such as B·T and B·R, or an altitude) to the values
+//! wanted.  It is Newton's method, with the sensitivities found by finite
+//! differences: each control is nudged in turn, and the trajectory flown
+//! again.  The trajectory is whatever the caller flies, so the targets can
+//! come from a numerical propagation through several bodies' pulls, which no
+//! conic could give.
+//!
+//! With as many controls as targets, each step is a square solve.  With more
+//! controls, it is the smallest step (in the scaled controls) that does it.
+//! Steps are cut back to `max_step`, so that a poor first guess doesn't throw
+//! the trajectory somewhere the sensitivities no longer hold.
+
+extern crate nalgebra as na;
+use na::{SMatrix, SVector};
+
+/// How to correct `C` controls against `T` targets.
+#[derive(Clone, Debug)]
+pub struct Corrector<const C: usize, const T: usize> {
+    /// How far to nudge each control, to find the sensitivities.
+    pub perturbation: SVector<f64, C>,
+    /// The largest step in each control, per iteration.
+    pub max_step: SVector<f64, C>,
+    /// How close each target has to come to what is wanted.
+    pub tolerance: SVector<f64, T>,
+    pub max_iterations: usize,
+}
+
+/// What a correction came to.
+#[derive(Clone, Debug)]
+pub struct Correction<const C: usize, const T: usize> {
+    pub controls: SVector<f64, C>,
+    /// The targets, as flown with the controls.
+    pub targets: SVector<f64, T>,
+    pub iterations: usize,
+    /// Whether the targets are all within their tolerance.
+    pub converged: bool,
+}
+
+impl<const C: usize, const T: usize> Corrector<C, T> {
+    /// Correct `controls` until `fly` gives the `wanted` targets.  `fly` gives
+    /// None for controls that can't be flown (such as a trajectory that never
+    /// gets there).  None if the first guess can't be flown, or the targets
+    /// don't depend on the controls; otherwise, the best found, converged or
+    /// not.
+    pub fn correct(
+        &self,
+        mut controls: SVector<f64, C>,
+        wanted: &SVector<f64, T>,
+        fly: impl Fn(&SVector<f64, C>) -> Option<SVector<f64, T>>,
+    ) -> Option<Correction<C, T>> {
+        let within = |targets: &SVector<f64, T>| {
+            (wanted - targets)
+                .iter()
+                .zip(self.tolerance.iter())
+                .all(|(miss, tolerance)| miss.abs() <= *tolerance)
+        };
+        let mut targets = fly(&controls)?;
+        let mut iterations = 0;
+        while iterations < self.max_iterations && !within(&targets) {
+            iterations += 1;
+
+            // The sensitivities, to each control's perturbation, so that
+            // controls in different units weigh alike.
+            let mut jacobian = SMatrix::<f64, T, C>::zeros();
+            for i in 0..C {
+                let mut nudged = controls;
+                nudged[i] += self.perturbation[i];
+                jacobian.set_column(i, &(fly(&nudged)? - targets));
+            }
+            let inverse = (jacobian * jacobian.transpose()).try_inverse()?;
+            let mut step = (jacobian.transpose() * inverse * (wanted - targets))
+                .component_mul(&self.perturbation);
+
+            // Cut back the whole step, keeping its direction, to the largest
+            // allowed.
+            let over = step
+                .iter()
+                .zip(self.max_step.iter())
+                .map(|(step, max)| step.abs() / max)
+                .fold(1.0, f64::max);
+            step /= over;
+
+            // Halve the step until it can be flown.
+            let mut flown = None;
+            for _ in 0..self.max_iterations {
+                flown = fly(&(controls + step));
+                if flown.is_some() {
+                    break;
+                }
+                step /= 2.0;
+            }
+            let Some(flown) = flown else {
+                break;
+            };
+            controls += step;
+            targets = flown;
+        }
+        Some(Correction {
+            controls,
+            targets,
+            iterations,
+            converged: within(&targets),
+        })
+    }
+}
@@ -0,0 +1,245 @@
+//! The circular restricted three-body problem.
+//!
+//! Two bodies, the primary and the (lighter) secondary, go around their
+//! barycenter in circles, and a craft too light to move them coasts under
+//! both.  In the frame that turns with the pair, the bodies stand still, and
+//! so do the five Lagrange points, where the craft can too: L1 between the
+//! bodies, L2 beyond the secondary, L3 beyond the primary, and L4 and L5 at
+//! the third corner of the equilateral triangles, leading and trailing the
+//! secondary.  The frame is also where the craft's energy is conserved, as
+//! the Jacobi constant, and where halo and Lyapunov orbits about L1 and L2
+//! look like the closed loops they are.
+//!
+//! The model is normalized: the bodies are a unit apart, the pair turns at
+//! one radian per unit time, and the masses add up to one, with the
+//! secondary's being `mu`.  The primary sits at `(-mu, 0, 0)` and the
+//! secondary at `(1 - mu, 0, 0)`, with Z along the pair's angular momentum.
+//!
+//! No real pair is circular, or alone, so `RotatingFrame` takes the frame
+//! from where the two bodies are at the moment: X from the primary to the
+//! secondary, Z along their angular momentum, and the scale from how far
+//! apart they are now.  For the earth and moon, that pulsates by 5% over a
+//! month, which is why the sim's own physics doesn't hold a craft on the
+//! model's orbits for long.
+
+extern crate nalgebra as na;
+use alloc::{vec, vec::Vec};
+use na::{Rotation3, Vector3};
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+/// The Lagrange points of the model, L1 to L5.
+pub const LAGRANGE_NAMES: [&str; 5] = ["L1", "L2", "L3", "L4", "L5"];
+
+/// The largest step, in normalized time, for `Cr3bp::propagate`.
+const MAX_STEP: f64 = 1.0e-2;
+
+/// The step, as a fraction of the time to fall into the nearer body from
+/// where the craft is, for `Cr3bp::propagate`.  Small, to get past a close
+/// approach.
+const STEP_FRACTION: f64 = 2.0e-2;
+
+/// The circular restricted three-body model, with the secondary's share of
+/// the mass, `mu`.
+#[derive(Clone, Copy, Debug)]
+pub struct Cr3bp {
+    pub mu: f64,
+}
+
+impl Cr3bp {
+    /// The model for a pair with the given GMs.
+    pub fn new(gm_primary: f64, gm_secondary: f64) -> Self {
+        Cr3bp {
+            mu: gm_secondary / (gm_primary + gm_secondary),
+        }
+    }
+
+    /// Where the primary and the secondary are.
+    pub fn bodies(&self) -> [Vector3<f64>; 2] {
+        [
+            Vector3::new(-self.mu, 0.0, 0.0),
+            Vector3::new(1.0 - self.mu, 0.0, 0.0),
+        ]
+    }
+
+    /// The distances from the primary and the secondary.
+    fn distances(&self, pos: &Vector3<f64>) -> (f64, f64) {
+        let [primary, secondary] = self.bodies();
+        ((pos - primary).norm(), (pos - secondary).norm())
+    }
+
+    /// The five Lagrange points, L1 to L5.
+    pub fn lagrange_points(&self) -> [Vector3<f64>; 5] {
+        let mu = self.mu;
+        // On the X axis, the pull of both bodies and the centrifugal push
+        // balance.  Newton's method, from the usual first guesses, which are
+        // close for any mu up to the equal masses.
+        let collinear = |guess: f64| {
+            let mut x = guess;
+            for _ in 0..50 {
+                let (d1, d2) = (x + mu, x - 1.0 + mu);
+                let (r1, r2) = (d1.abs(), d2.abs());
+                let f = x - (1.0 - mu) * d1 / r1.powi(3) - mu * d2 / r2.powi(3);
+                let slope = 1.0 + 2.0 * (1.0 - mu) / r1.powi(3) + 2.0 * mu / r2.powi(3);
+                let step = f / slope;
+                x -= step;
+                if step.abs() < 1.0e-15 {
+                    break;
+                }
+            }
+            Vector3::new(x, 0.0, 0.0)
+        };
+        let hill = (mu / 3.0).cbrt();
+        let triangle = 3.0f64.sqrt() / 2.0;
+        [
+            collinear(1.0 - mu - hill),
+            collinear(1.0 - mu + hill),
+            collinear(-1.0 - 5.0 * mu / 12.0),
+            Vector3::new(0.5 - mu, triangle, 0.0),
+            Vector3::new(0.5 - mu, -triangle, 0.0),
+        ]
+    }
+
+    /// The acceleration in the turning frame: both bodies' pull, and the
+    /// centrifugal and Coriolis terms.
+    pub fn accel(&self, pos: &Vector3<f64>, vel: &Vector3<f64>) -> Vector3<f64> {
+        let mu = self.mu;
+        let [primary, secondary] = self.bodies();
+        let (d1, d2) = (pos - primary, pos - secondary);
+        let gravity = -d1 * ((1.0 - mu) / d1.norm().powi(3)) - d2 * (mu / d2.norm().powi(3));
+        gravity + Vector3::new(pos.x + 2.0 * vel.y, pos.y - 2.0 * vel.x, 0.0)
+    }
+
+    /// The Jacobi constant, which the model keeps, and the lower it is, the
+    /// more of the space the craft can reach.  At L1 it is a little under 3
+    /// plus mu.
+    pub fn jacobi(&self, pos: &Vector3<f64>, vel: &Vector3<f64>) -> f64 {
+        let (r1, r2) = self.distances(pos);
+        pos.x * pos.x + pos.y * pos.y + 2.0 * (1.0 - self.mu) / r1 + 2.0 * self.mu / r2
+            - vel.norm_squared()
+    }
+
+    /// Coast from `pos` and `vel` for `duration`, with RK4, sampling the
+    /// positions `samples` times along the way (the start included).  The
+    /// steps shorten near the bodies.  Stops early on coming within `radii`
+    /// of the primary or secondary, or after `max_steps`.
+    pub fn propagate(
+        &self,
+        pos: &Vector3<f64>,
+        vel: &Vector3<f64>,
+        duration: f64,
+        samples: usize,
+        radii: (f64, f64),
+        max_steps: usize,
+    ) -> Vec<Vector3<f64>> {
+        let mut state = (*pos, *vel);
+        let mut points = vec![state.0];
+        let interval = duration / samples.max(1) as f64;
+        let mut next = interval;
+        let mut t = 0.0;
+        let deriv = |(p, v): &(Vector3<f64>, Vector3<f64>)| (*v, self.accel(p, v));
+        for _ in 0..max_steps {
+            let (r1, r2) = self.distances(&state.0);
+            if t >= duration || r1 < radii.0 || r2 < radii.1 {
+                break;
+            }
+            // The free-fall time into the nearer body goes as r^1.5 / sqrt(gm).
+            let fall = (r1.powi(3) / (1.0 - self.mu))
+                .sqrt()
+                .min((r2.powi(3) / self.mu).sqrt());
+            let h = (STEP_FRACTION * fall).min(MAX_STEP).min(duration - t);
+            let k1 = deriv(&state);
+            let k2 = deriv(&(state.0 + k1.0 * (h / 2.0), state.1 + k1.1 * (h / 2.0)));
+            let k3 = deriv(&(state.0 + k2.0 * (h / 2.0), state.1 + k2.1 * (h / 2.0)));
+            let k4 = deriv(&(state.0 + k3.0 * h, state.1 + k3.1 * h));
+            state.0 += (k1.0 + (k2.0 + k3.0) * 2.0 + k4.0) * (h / 6.0);
+            state.1 += (k1.1 + (k2.1 + k3.1) * 2.0 + k4.1) * (h / 6.0);
+            t += h;
+            if t >= next {
+                points.push(state.0);
+                next += interval;
+            }
+        }
+        if points.last() != Some(&state.0) {
+            points.push(state.0);
+        }
+        points
+    }
+}
+
+/// The frame turning with a pair of bodies, as they are at the moment,
+/// centered on their barycenter.
+#[derive(Clone, Debug)]
+pub struct RotatingFrame {
+    /// The barycenter, and its velocity, in the world frame.
+    pub origin: Vector3<f64>,
+    pub origin_vel: Vector3<f64>,
+    /// The frame's axes, turning to world.
+    pub axes: Rotation3<f64>,
+    /// How fast it turns, in rad/s.
+    pub rate: f64,
+    /// How far apart the bodies are, in km.
+    pub separation: f64,
+    pub model: Cr3bp,
+}
+
+impl RotatingFrame {
+    /// The frame of a primary and a secondary, from their world positions,
+    /// velocities, and GMs.  None if they aren't going around each other.
+    pub fn new(
+        primary: (&Vector3<f64>, &Vector3<f64>, f64),
+        secondary: (&Vector3<f64>, &Vector3<f64>, f64),
+    ) -> Option<Self> {
+        let (p1, v1, gm1) = primary;
+        let (p2, v2, gm2) = secondary;
+        let r = p2 - p1;
+        let h = r.cross(&(v2 - v1));
+        if h.norm_squared() == 0.0 {
+            return None;
+        }
+        let x = r.normalize();
+        let z = h.normalize();
+        let axes = Rotation3::from_basis_unchecked(&[x, z.cross(&x), z]);
+        let total = gm1 + gm2;
+        Some(RotatingFrame {
+            origin: (p1 * gm1 + p2 * gm2) / total,
+            origin_vel: (v1 * gm1 + v2 * gm2) / total,
+            axes,
+            rate: h.norm() / r.norm_squared(),
+            separation: r.norm(),
+            model: Cr3bp::new(gm1, gm2),
+        })
+    }
+
+    /// The frame's axes `dt` seconds on, taking it to turn steadily.
+    pub fn axes_at(&self, dt: f64) -> Rotation3<f64> {
+        self.axes * Rotation3::from_axis_angle(&Vector3::z_axis(), self.rate * dt)
+    }
+
+    /// A world position and velocity, in the frame's normalized units, as
+    /// seen turning with it.
+    pub fn to_model(&self, pos: &Vector3<f64>, vel: &Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+        let rel = pos - self.origin;
+        let turning = vel - self.origin_vel - (self.axes * Vector3::z()).cross(&rel) * self.rate;
+        (
+            self.axes.inverse_transform_vector(&rel) / self.separation,
+            self.axes.inverse_transform_vector(&turning) / (self.separation * self.rate),
+        )
+    }
+
+    /// A position in the frame's normalized units, in the world, as things
+    /// stand now.
+    pub fn from_model(&self, pos: &Vector3<f64>) -> Vector3<f64> {
+        self.origin + self.axes * (pos * self.separation)
+    }
+
+    /// A time in the frame's normalized units, in seconds.
+    pub fn seconds(&self, t: f64) -> f64 {
+        t / self.rate
+    }
+
+    /// Where the Lagrange points are in the world now.
+    pub fn lagrange_points(&self) -> [Vector3<f64>; 5] {
+        self.model.lagrange_points().map(|l| self.from_model(&l))
+    }
+}
@@ -0,0 +1,319 @@
+//! Translational integrators.
+//!
+//! A handful of ways of advancing a point mass under gravity, all driven in
+//! lockstep by the same outer time step, so they can be compared on the same
+//! scenario.  Gravity is split into a primary at the origin, and whatever
+//! perturbs the craft away from the two-body conic, which is the split Encke's
+//! method needs.  A perturbation can depend on the velocity, as drag does.
+
+extern crate nalgebra as na;
+use na::Vector3;
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+use crate::kepler;
+
+/// Gravity acting on a craft, and anything else pushing on it.
+pub trait Gravity {
+    /// GM of the primary, which sits at the origin.
+    fn gm(&self) -> f64;
+
+    /// The acceleration, at time `t`, from everything but the primary.
+    fn perturbation(&self, t: f64, pos: &Vector3<f64>) -> Vector3<f64>;
+
+    /// The acceleration, at time `t`, from anything that depends on the
+    /// velocity, too, such as drag.  Nothing, unless overridden.
+    fn drag(&self, _t: f64, _pos: &Vector3<f64>, _vel: &Vector3<f64>) -> Vector3<f64> {
+        Vector3::zeros()
+    }
+
+    /// The total acceleration at time `t`.
+    fn accel(&self, t: f64, pos: &Vector3<f64>, vel: &Vector3<f64>) -> Vector3<f64> {
+        let r = pos.norm();
+        -pos * (self.gm() / (r * r * r)) + self.perturbation(t, pos) + self.drag(t, pos, vel)
+    }
+}
+
+/// The integration methods.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Method {
+    /// Semi-implicit Euler: the velocity is updated first, then the position
+    /// with the new velocity.  This is what the sim's physics step does.
+    Euler,
+    /// Kick-drift-kick leapfrog (velocity Verlet).  Second order, and
+    /// symplectic.
+    Leapfrog,
+    /// Dormand-Prince 5(4), taking as many adaptive substeps as needed to keep
+    /// the local error of each within `tolerance` (relative).
+    Rk45 { tolerance: f64 },
+    /// Encke's method: a two-body reference conic is propagated analytically,
+    /// and only the deviation from it is integrated (with RK4).  The reference
+    /// is rectified once the deviation grows past `rectify` (relative to the
+    /// radius).
+    Encke { rectify: f64 },
+}
+
+impl Method {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Method::Euler => "Euler",
+            Method::Leapfrog => "leapfrog",
+            Method::Rk45 { .. } => "RK45",
+            Method::Encke { .. } => "Encke",
+        }
+    }
+}
+
+/// The state of a point mass, relative to the primary.
+#[derive(Clone, Copy, Debug)]
+pub struct State {
+    pub pos: Vector3<f64>,
+    pub vel: Vector3<f64>,
+}
+
+/// A craft being advanced by one of the methods.
+#[derive(Clone, Debug)]
+pub struct Propagator {
+    pub method: Method,
+    /// The current time.
+    pub time: f64,
+    /// The number of times the acceleration has been evaluated, as a measure
+    /// of the cost.
+    pub evaluations: u64,
+    /// The state, for everything but Encke.  For Encke, this is the reference
+    /// conic at `epoch`.
+    state: State,
+    /// The step the adaptive integrator will try next.
+    substep: f64,
+    /// Encke: the time of the reference state, and the deviation from the
+    /// reference conic.
+    epoch: f64,
+    deviation: State,
+}
+
+impl Propagator {
+    pub fn new(method: Method, time: f64, state: State) -> Self {
+        Propagator {
+            method,
+            time,
+            evaluations: 0,
+            state,
+            substep: 0.0,
+            epoch: time,
+            deviation: State {
+                pos: Vector3::zeros(),
+                vel: Vector3::zeros(),
+            },
+        }
+    }
+
+    /// The current state.
+    pub fn state(&self, gravity: &impl Gravity) -> State {
+        match self.method {
+            Method::Encke { .. } => {
+                let (pos, vel) = kepler::propagate(
+                    &self.state.pos,
+                    &self.state.vel,
+                    gravity.gm(),
+                    self.time - self.epoch,
+                );
+                State {
+                    pos: pos + self.deviation.pos,
+                    vel: vel + self.deviation.vel,
+                }
+            }
+            _ => self.state,
+        }
+    }
+
+    /// Advance by `dt` seconds.
+    pub fn step(&mut self, gravity: &impl Gravity, dt: f64) {
+        match self.method {
+            Method::Euler => self.step_euler(gravity, dt),
+            Method::Leapfrog => self.step_leapfrog(gravity, dt),
+            Method::Rk45 { tolerance } => self.step_rk45(gravity, dt, tolerance),
+            Method::Encke { rectify } => self.step_encke(gravity, dt, rectify),
+        }
+        self.time += dt;
+    }
+
+    fn accel(
+        &mut self,
+        gravity: &impl Gravity,
+        t: f64,
+        pos: Vector3<f64>,
+        vel: Vector3<f64>,
+    ) -> Vector3<f64> {
+        self.evaluations += 1;
+        gravity.accel(t, &pos, &vel)
+    }
+
+    fn step_euler(&mut self, gravity: &impl Gravity, dt: f64) {
+        let a = self.accel(gravity, self.time, self.state.pos, self.state.vel);
+        self.state.vel += a * dt;
+        self.state.pos += self.state.vel * dt;
+    }
+
+    /// Drag, if any, is taken at the half step's velocity on the way out.
+    fn step_leapfrog(&mut self, gravity: &impl Gravity, dt: f64) {
+        let a = self.accel(gravity, self.time, self.state.pos, self.state.vel);
+        let half = self.state.vel + a * (dt / 2.0);
+        self.state.pos += half * dt;
+        let a = self.accel(gravity, self.time + dt, self.state.pos, half);
+        self.state.vel = half + a * (dt / 2.0);
+    }
+
+    fn step_rk45(&mut self, gravity: &impl Gravity, dt: f64, tolerance: f64) {
+        let end = self.time + dt;
+        let mut t = self.time;
+        let mut h = if self.substep > 0.0 { self.substep } else { dt };
+        while t < end {
+            let last = h >= end - t;
+            if last {
+                h = end - t;
+            }
+            let (next, error) = self.dormand_prince(gravity, t, h);
+            let scale = tolerance * (next.pos.norm() + next.vel.norm() * h);
+            let ratio = error / scale.max(f64::MIN_POSITIVE);
+            if ratio <= 1.0 {
+                t += h;
+                self.state = next;
+            }
+            // The usual step size control, with some safety margin.
+            let factor = (0.9 * ratio.max(1.0e-10).powf(-0.2)).clamp(0.2, 5.0);
+            if ratio <= 1.0 && last {
+                // Don't let the clipped final step shrink the next one.
+                break;
+            }
+            h *= factor;
+            self.substep = h;
+        }
+    }
+
+    /// One Dormand-Prince step of `h` from `t`.  Returns the fifth order
+    /// result, and an estimate of its error.
+    fn dormand_prince(&mut self, gravity: &impl Gravity, t: f64, h: f64) -> (State, f64) {
+        const C: [f64; 7] = [0.0, 1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0];
+        const A: [[f64; 6]; 7] = [
+            [0.0; 6],
+            [1.0 / 5.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            [3.0 / 40.0, 9.0 / 40.0, 0.0, 0.0, 0.0, 0.0],
+            [44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0, 0.0, 0.0, 0.0],
+            [
+                19372.0 / 6561.0,
+                -25360.0 / 2187.0,
+                64448.0 / 6561.0,
+                -212.0 / 729.0,
+                0.0,
+                0.0,
+            ],
+            [
+                9017.0 / 3168.0,
+                -355.0 / 33.0,
+                46732.0 / 5247.0,
+                49.0 / 176.0,
+                -5103.0 / 18656.0,
+                0.0,
+            ],
+            [
+                35.0 / 384.0,
+                0.0,
+                500.0 / 1113.0,
+                125.0 / 192.0,
+                -2187.0 / 6784.0,
+                11.0 / 84.0,
+            ],
+        ];
+        // Fifth order weights (the same as the last row of A), and the
+        // difference to the fourth order ones.
+        const B: [f64; 7] = [
+            35.0 / 384.0,
+            0.0,
+            500.0 / 1113.0,
+            125.0 / 192.0,
+            -2187.0 / 6784.0,
+            11.0 / 84.0,
+            0.0,
+        ];
+        const E: [f64; 7] = [
+            71.0 / 57600.0,
+            0.0,
+            -71.0 / 16695.0,
+            71.0 / 1920.0,
+            -17253.0 / 339200.0,
+            22.0 / 525.0,
+            -1.0 / 40.0,
+        ];
+
+        let start = self.state;
+        let mut kp = [Vector3::zeros(); 7];
+        let mut kv = [Vector3::zeros(); 7];
+        for i in 0..7 {
+            let mut pos = start.pos;
+            let mut vel = start.vel;
+            for j in 0..i {
+                pos += kp[j] * (h * A[i][j]);
+                vel += kv[j] * (h * A[i][j]);
+            }
+            kp[i] = vel;
+            kv[i] = self.accel(gravity, t + C[i] * h, pos, vel);
+        }
+
+        let mut next = start;
+        let mut error_pos = Vector3::zeros();
+        let mut error_vel = Vector3::zeros();
+        for i in 0..7 {
+            next.pos += kp[i] * (h * B[i]);
+            next.vel += kv[i] * (h * B[i]);
+            error_pos += kp[i] * (h * E[i]);
+            error_vel += kv[i] * (h * E[i]);
+        }
+        (next, error_pos.norm() + error_vel.norm() * h)
+    }
+
+    fn step_encke(&mut self, gravity: &impl Gravity, dt: f64, rectify: f64) {
+        let gm = gravity.gm();
+        // The deviation from the reference conic, as a function of time.
+        let deviation_accel = |this: &mut Self, t: f64, deviation: &State| {
+            let (rho, rho_vel) =
+                kepler::propagate(&this.state.pos, &this.state.vel, gm, t - this.epoch);
+            let pos = rho + deviation.pos;
+            let r = pos.norm();
+            let rho_n = rho.norm();
+            this.evaluations += 1;
+            rho * (gm / (rho_n * rho_n * rho_n)) - pos * (gm / (r * r * r))
+                + gravity.perturbation(t, &pos)
+                + gravity.drag(t, &pos, &(rho_vel + deviation.vel))
+        };
+        let at = |pos: Vector3<f64>, vel: Vector3<f64>| State { pos, vel };
+
+        // RK4 on the deviation.
+        let t = self.time;
+        let d = self.deviation;
+        let k1v = deviation_accel(self, t, &d);
+        let k1p = d.vel;
+        let k2p = d.vel + k1v * (dt / 2.0);
+        let k2v = deviation_accel(self, t + dt / 2.0, &at(d.pos + k1p * (dt / 2.0), k2p));
+        let k3p = d.vel + k2v * (dt / 2.0);
+        let k3v = deviation_accel(self, t + dt / 2.0, &at(d.pos + k2p * (dt / 2.0), k3p));
+        let k4p = d.vel + k3v * dt;
+        let k4v = deviation_accel(self, t + dt, &at(d.pos + k3p * dt, k4p));
+        self.deviation.pos += (k1p + k2p * 2.0 + k3p * 2.0 + k4p) * (dt / 6.0);
+        self.deviation.vel += (k1v + k2v * 2.0 + k3v * 2.0 + k4v) * (dt / 6.0);
+
+        // Rectify: start a new reference conic from the full state.
+        let (rho, rho_vel) =
+            kepler::propagate(&self.state.pos, &self.state.vel, gm, t + dt - self.epoch);
+        if self.deviation.pos.norm() > rectify * rho.norm() {
+            self.state = State {
+                pos: rho + self.deviation.pos,
+                vel: rho_vel + self.deviation.vel,
+            };
+            self.epoch = t + dt;
+            self.deviation = State {
+                pos: Vector3::zeros(),
+                vel: Vector3::zeros(),
+            };
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Two-body (Keplerian) propagation.
+//!
+//! Units are whatever the caller uses consistently; the sim uses km, km/s, and
+//! km^3/s^2 for GM.  Vectors are relative to the central body.
+
+extern crate nalgebra as na;
+use na::Vector3;
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+/// The Stumpff functions C(z) and S(z).
+pub(crate) fn stumpff(z: f64) -> (f64, f64) {
+    if z.abs() < 1.0e-6 {
+        // Series, to avoid the cancellation near zero.
+        (0.5 - z / 24.0, 1.0 / 6.0 - z / 120.0)
+    } else if z > 0.0 {
+        let sz = z.sqrt();
+        ((1.0 - sz.cos()) / z, (sz - sz.sin()) / (sz * sz * sz))
+    } else {
+        let sz = (-z).sqrt();
+        ((sz.cosh() - 1.0) / -z, (sz.sinh() - sz) / (sz * sz * sz))
+    }
+}
+
+/// The period, in seconds, of the orbit through the given state, or None if it
+/// isn't closed.
+pub fn period(pos: &Vector3<f64>, vel: &Vector3<f64>, gm: f64) -> Option<f64> {
+    // Reciprocal of the semi-major axis.
+    let alpha = 2.0 / pos.norm() - vel.norm_squared() / gm;
+    if alpha > 1.0e-12 {
+        Some(core::f64::consts::TAU / (gm * alpha * alpha * alpha).sqrt())
+    } else {
+        None
+    }
+}
+
+/// Propagate a state `dt` seconds along its conic, about a central body with
+/// the given `gm`.  This uses the universal variable formulation, so it works
+/// for elliptic, parabolic, and hyperbolic orbits alike.
+pub fn propagate(
+    pos: &Vector3<f64>,
+    vel: &Vector3<f64>,
+    gm: f64,
+    dt: f64,
+) -> (Vector3<f64>, Vector3<f64>) {
+    let r0 = pos.norm();
+    let vr0 = pos.dot(vel) / r0;
+    let sqrt_mu = gm.sqrt();
+    // Reciprocal of the semi-major axis.
+    let alpha = 2.0 / r0 - vel.norm_squared() / gm;
+
+    // For closed orbits, whole revolutions don't matter, and keeping dt small
+    // keeps the iteration well behaved.
+    let mut dt = dt;
+    if let Some(period) = period(pos, vel, gm) {
+        dt %= period;
+    }
+
+    // Solve the universal Kepler equation for chi with Newton's method.
+    let mut chi = sqrt_mu * alpha.abs() * dt;
+    for _ in 0..64 {
+        let z = alpha * chi * chi;
+        let (c, s) = stumpff(z);
+        let f = r0 * vr0 / sqrt_mu * chi * chi * c
+            + (1.0 - alpha * r0) * chi * chi * chi * s
+            + r0 * chi
+            - sqrt_mu * dt;
+        let df = r0 * vr0 / sqrt_mu * chi * (1.0 - z * s) + (1.0 - alpha * r0) * chi * chi * c + r0;
+        let step = f / df;
+        chi -= step;
+        if step.abs() < 1.0e-10 {
+            break;
+        }
+    }
+
+    // Lagrange coefficients.
+    let z = alpha * chi * chi;
+    let (c, s) = stumpff(z);
+    let f = 1.0 - chi * chi / r0 * c;
+    let g = dt - chi * chi * chi * s / sqrt_mu;
+    let new_pos = pos * f + vel * g;
+    let r = new_pos.norm();
+    let fdot = sqrt_mu / (r * r0) * (alpha * chi * chi * chi * s - chi);
+    let gdot = 1.0 - chi * chi / r * c;
+    let new_vel = pos * fdot + vel * gdot;
+
+    (new_pos, new_vel)
+}
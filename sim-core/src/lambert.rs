@@ -0,0 +1,101 @@
+//! Lambert's problem: the conic between two positions in a given time.
+//!
+//! This uses the universal variable formulation (as in Curtis, Algorithm 5.2),
+//! so the transfer can be elliptic, parabolic, or hyperbolic, but it is always
+//! less than one revolution.  The units are whatever the caller uses
+//! consistently, and the vectors are relative to the central body, as in
+//! `kepler`.
+
+extern crate nalgebra as na;
+use core::f64::consts::TAU;
+use na::Vector3;
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+use crate::kepler::stumpff;
+
+/// How far apart, in radians, the two positions must be from lined up (on the
+/// same, or opposite, sides) for the transfer plane to be well defined.
+const LAMBERT_MIN_ANGLE: f64 = 1.0e-6;
+
+/// The velocities at `r1` and at `r2` on the conic from one to the other in
+/// `tof` seconds, about a body with the given `gm`.  The transfer goes around
+/// the same way as `h` (such as the departing orbit's angular momentum), the
+/// short or the long way, as it must.  None if there is no single transfer,
+/// such as when the positions are lined up with the center.
+pub fn lambert(
+    r1: &Vector3<f64>,
+    r2: &Vector3<f64>,
+    tof: f64,
+    gm: f64,
+    h: &Vector3<f64>,
+) -> Option<(Vector3<f64>, Vector3<f64>)> {
+    let (n1, n2) = (r1.norm(), r2.norm());
+    if tof <= 0.0 || n1 == 0.0 || n2 == 0.0 {
+        return None;
+    }
+    let mut angle = (r1.dot(r2) / (n1 * n2)).clamp(-1.0, 1.0).acos();
+    if r1.cross(r2).dot(h) < 0.0 {
+        angle = TAU - angle;
+    }
+    if angle < LAMBERT_MIN_ANGLE || (angle - TAU / 2.0).abs() < LAMBERT_MIN_ANGLE {
+        return None;
+    }
+    let a = angle.sin() * (n1 * n2 / (1.0 - angle.cos())).sqrt();
+    let sqrt_mu = gm.sqrt();
+
+    // The time of flight for a value of z, the square of the change in the
+    // universal anomaly over the semi-major axis.  Where y is negative, there
+    // is no conic, and the transfer would have to be quicker still.
+    let y = |z: f64| {
+        let (c, s) = stumpff(z);
+        n1 + n2 + a * (z * s - 1.0) / c.sqrt()
+    };
+    let time = |z: f64| {
+        let y = y(z);
+        if y < 0.0 {
+            return 0.0;
+        }
+        let (c, s) = stumpff(z);
+        ((y / c).powf(1.5) * s + a * y.sqrt()) / sqrt_mu
+    };
+
+    // The time grows with z, up to a whole revolution at 4 π^2, so bracket the
+    // root, and bisect.
+    let mut high = TAU * TAU;
+    let mut low = -TAU * TAU;
+    for _ in 0..12 {
+        if time(low) < tof {
+            break;
+        }
+        low *= 2.0;
+    }
+    if time(low) >= tof {
+        return None;
+    }
+    let mut z = low;
+    for _ in 0..200 {
+        z = (low + high) / 2.0;
+        if time(z) < tof {
+            low = z;
+        } else {
+            high = z;
+        }
+        if high - low < 1.0e-12 * high.abs().max(1.0) {
+            break;
+        }
+    }
+    let error = (time(z) - tof).abs();
+    if error.is_nan() || error > 1.0e-6 * tof.max(1.0) {
+        return None;
+    }
+
+    // The Lagrange coefficients give the velocities.
+    let y = y(z);
+    let f = 1.0 - y / n1;
+    let g = a * (y / gm).sqrt();
+    let gdot = 1.0 - y / n2;
+    let v1 = (r2 - r1 * f) / g;
+    let v2 = (r2 * gdot - r1) / g;
+    Some((v1, v2))
+}
@@ -0,0 +1,77 @@
+//! Physics simulation library for rigid body dynamics.
+//!
+//! This is the bottom of the sim: the state every body and craft carries, the
+//! physics that moves it each fixed step (`PhysicsPlugin`), the clock that
+//! counts out those steps (`clock`), and the watchdog that keeps a bad state
+//! from spreading.  It also has the integrators, the
+//! two-body tools (propagation, Lambert's problem, orbit determination
+//! from position fixes, and conjunction screening), the restricted
+//! three-body model (the Lagrange points, and the frame turning with a pair
+//! of bodies), and the targeting tools (the B-plane, and differential
+//! correction), which don't need bevy at all, and neither does
+//! `SimulationBuilder`, for putting a world together and running it from
+//! code.  With many bodies, their pull is summed with a Barnes-Hut tree.
+//!
+//! The plugins, and the clock and watchdog, are behind the `bevy` feature,
+//! which is on by default.  Without it, the rest builds as plain Rust, with
+//! the state as plain structs, for flight software prototypes and tests that
+//! want the math without the engine.  `prelude` has the lot.
+//!
+//! Without the `std` feature as well, which `bevy` needs, the math is
+//! `no_std`, for flight computers without an OS: it needs only `alloc`, for
+//! the screens, solvers and trees that keep a `Vec`, and takes its float
+//! functions from `libm`, through `num_traits::Float`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+mod attitude;
+pub mod barnes_hut;
+pub mod bplane;
+#[cfg(feature = "bevy")]
+pub mod clock;
+pub mod conjunction;
+mod controller;
+pub mod correction;
+pub mod cr3bp;
+pub mod integrator;
+pub mod kepler;
+pub mod lambert;
+pub mod orbit;
+pub mod orbit_determination;
+#[cfg(feature = "bevy")]
+mod physics;
+pub mod porkchop;
+pub mod simulation;
+mod state;
+#[cfg(feature = "bevy")]
+pub mod watchdog;
+
+pub use attitude::{AttitudeIntegrator, NutationDamper, Renormalize, RigidBody, RotationScheme};
+pub use barnes_hut::GravitySolver;
+pub use controller::AttitudeController;
+#[cfg(feature = "bevy")]
+pub use physics::{PhysicsModels, PhysicsPlugin, PhysicsSet, PostPhysicsSet, model_enabled};
+pub use simulation::{Perturbation, Simulation, SimulationBuilder};
+pub use state::{
+    AttitudeControl, AttitudeState, LinearControl, MassiveBody, OrbitalBody, SizedBody,
+};
+
+/// The math, without bevy: the state, attitude integration and control, and
+/// the orbit tools.
+pub mod prelude {
+    pub use crate::{
+        AttitudeControl, AttitudeController, AttitudeIntegrator, AttitudeState, GravitySolver,
+        LinearControl, MassiveBody, NutationDamper, OrbitalBody, Perturbation, Renormalize,
+        RigidBody, RotationScheme, Simulation, SimulationBuilder, SizedBody,
+        bplane::BPlane,
+        conjunction::{Approach, Screen},
+        cr3bp::{Cr3bp, RotatingFrame},
+        integrator::{Method, Propagator, State},
+        kepler,
+        lambert::lambert,
+        orbit::{Conic, OrbitFrame, clohessy_wiltshire},
+        orbit_determination::{Fit, Fix},
+    };
+}
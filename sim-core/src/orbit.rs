@@ -0,0 +1,173 @@
+//! Two-body orbital mechanics.
+//!
+//! These are the analytic (Keplerian) tools used for planning: where will a
+//! craft be after some time, ignoring everything but the central body. The
+//! units match `OrbitalBody` (km, km/s, and km^3/s^2 for GM), and all vectors
+//! are relative to the central body.  The propagation itself lives in
+//! `kepler`, where the integrators can share it.
+
+extern crate nalgebra as na;
+use na::Vector3;
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+pub use crate::kepler::{period, propagate};
+
+/// The orbital frame of a craft relative to its central body.  Maneuvers are
+/// usually expressed in this frame.
+#[derive(Clone, Debug)]
+pub struct OrbitFrame {
+    /// Along the velocity.
+    pub prograde: Vector3<f64>,
+    /// Along the angular momentum, perpendicular to the orbital plane.
+    pub normal: Vector3<f64>,
+    /// In the orbital plane, perpendicular to the velocity, and away from the
+    /// central body.
+    pub radial: Vector3<f64>,
+}
+
+impl OrbitFrame {
+    pub fn new(pos: &Vector3<f64>, vel: &Vector3<f64>) -> Self {
+        let prograde = vel.normalize();
+        let normal = pos.cross(vel).normalize();
+        let radial = prograde.cross(&normal);
+        OrbitFrame {
+            prograde,
+            normal,
+            radial,
+        }
+    }
+
+    /// Convert a (prograde, normal, radial) vector into the world frame.
+    pub fn to_world(&self, pnr: &Vector3<f64>) -> Vector3<f64> {
+        self.prograde * pnr.x + self.normal * pnr.y + self.radial * pnr.z
+    }
+}
+
+/// Where something near a craft in a circular orbit will be after `t` seconds,
+/// by the Clohessy–Wiltshire equations.  The position and velocity are in the
+/// craft's local vertical, local horizontal frame, which turns with it: X is
+/// radial (up), Y along track, and Z along the orbit normal.  `n` is the
+/// craft's mean motion, rad/s.  This holds while the distance is small
+/// compared to the orbit.
+pub fn clohessy_wiltshire(
+    pos: &Vector3<f64>,
+    vel: &Vector3<f64>,
+    n: f64,
+    t: f64,
+) -> (Vector3<f64>, Vector3<f64>) {
+    let (s, c) = (n * t).sin_cos();
+    let nt = n * t;
+    let new_pos = Vector3::new(
+        (4.0 - 3.0 * c) * pos.x + s / n * vel.x + 2.0 / n * (1.0 - c) * vel.y,
+        6.0 * (s - nt) * pos.x + pos.y - 2.0 / n * (1.0 - c) * vel.x
+            + (4.0 * s - 3.0 * nt) / n * vel.y,
+        c * pos.z + s / n * vel.z,
+    );
+    let new_vel = Vector3::new(
+        3.0 * n * s * pos.x + c * vel.x + 2.0 * s * vel.y,
+        6.0 * n * (c - 1.0) * pos.x - 2.0 * s * vel.x + (4.0 * c - 3.0) * vel.y,
+        -n * s * pos.z + c * vel.z,
+    );
+    (new_pos, new_vel)
+}
+
+/// The shape of the two-body orbit through a state, for finding the points of
+/// interest along it.
+#[derive(Clone, Debug)]
+pub struct Conic {
+    pub gm: f64,
+    /// The eccentricity vector, pointing toward periapsis.  For a circular
+    /// orbit, this is tiny, and periapsis is taken to be the current position.
+    pub eccentricity: Vector3<f64>,
+    /// The specific angular momentum, km^2/s.
+    pub momentum: Vector3<f64>,
+    /// The semi-latus rectum, km.
+    pub p: f64,
+    /// The direction of periapsis.
+    periapsis_dir: Vector3<f64>,
+}
+
+impl Conic {
+    pub fn new(pos: &Vector3<f64>, vel: &Vector3<f64>, gm: f64) -> Self {
+        let momentum = pos.cross(vel);
+        let eccentricity = (pos * (vel.norm_squared() - gm / pos.norm()) - vel * pos.dot(vel)) / gm;
+        let periapsis_dir = if eccentricity.norm() > 1.0e-9 {
+            eccentricity.normalize()
+        } else {
+            pos.normalize()
+        };
+        Conic {
+            gm,
+            eccentricity,
+            momentum,
+            p: momentum.norm_squared() / gm,
+            periapsis_dir,
+        }
+    }
+
+    pub fn e(&self) -> f64 {
+        self.eccentricity.norm()
+    }
+
+    /// The true anomaly of a direction in (or projected onto) the orbital
+    /// plane.
+    pub fn true_anomaly(&self, dir: &Vector3<f64>) -> f64 {
+        let normal = self.momentum.normalize();
+        let y = normal.cross(&self.periapsis_dir);
+        dir.dot(&y).atan2(dir.dot(&self.periapsis_dir))
+    }
+
+    /// The position at the given true anomaly, or None if the orbit never gets
+    /// there (the far side of a hyperbola).
+    pub fn position_at(&self, nu: f64) -> Option<Vector3<f64>> {
+        let denom = 1.0 + self.e() * nu.cos();
+        if denom <= 1.0e-9 {
+            return None;
+        }
+        let normal = self.momentum.normalize();
+        let y = normal.cross(&self.periapsis_dir);
+        Some((self.periapsis_dir * nu.cos() + y * nu.sin()) * (self.p / denom))
+    }
+
+    pub fn periapsis(&self) -> Vector3<f64> {
+        self.periapsis_dir * (self.p / (1.0 + self.e()))
+    }
+
+    /// Apoapsis, for closed orbits.
+    pub fn apoapsis(&self) -> Option<Vector3<f64>> {
+        if self.e() < 1.0 {
+            Some(-self.periapsis_dir * (self.p / (1.0 - self.e())))
+        } else {
+            None
+        }
+    }
+
+    /// The true anomalies of the ascending and descending nodes, relative to
+    /// the plane with the given `pole`.  None if the orbit lies in that plane.
+    pub fn nodes(&self, pole: &Vector3<f64>) -> Option<(f64, f64)> {
+        let line = pole.cross(&self.momentum);
+        if line.norm() < 1.0e-9 * self.momentum.norm() {
+            return None;
+        }
+        let ascending = self.true_anomaly(&line);
+        Some((ascending, ascending + core::f64::consts::PI))
+    }
+
+    /// The time, in seconds, to get from true anomaly `from` to `to`, going
+    /// forward.  Only for closed orbits.
+    pub fn time_between(&self, from: f64, to: f64) -> Option<f64> {
+        let e = self.e();
+        if e >= 1.0 {
+            return None;
+        }
+        let a = self.p / (1.0 - e * e);
+        let mean_motion = (self.gm / (a * a * a)).sqrt();
+        let mean = |nu: f64| {
+            let ecc = 2.0 * (((1.0 - e) / (1.0 + e)).sqrt() * (nu / 2.0).tan()).atan();
+            ecc - e * ecc.sin()
+        };
+        let dm = num_traits::Euclid::rem_euclid(&(mean(to) - mean(from)), &core::f64::consts::TAU);
+        Some(dm / mean_motion)
+    }
+}
@@ -0,0 +1,223 @@
+//! Orbit determination: an orbit from a history of noisy position fixes.
+//!
+//! Three fixes give a first guess at the velocity: Gibbs' method, from the
+//! geometry of three positions on one conic, when they are well apart, and
+//! the Herrick-Gibbs series in time, when they are close together, where
+//! Gibbs' method falls apart.  Batch least squares then fits a conic to all
+//! the fixes: the state at the epoch is corrected, again and again, by the
+//! step that best explains what is left over, with the sensitivities of each
+//! fix to the state found by finite differences.
+//!
+//! The model is two-body (see `kepler`), so whatever it leaves out, such as
+//! an oblate body's pull, or a burn, shows in the residuals.
+
+extern crate nalgebra as na;
+use alloc::vec::Vec;
+use na::{SMatrix, SVector, Vector3};
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+use crate::kepler;
+
+type Matrix6 = SMatrix<f64, 6, 6>;
+type Vector6 = SVector<f64, 6>;
+
+/// Below this angle, in radians, between fixes, Gibbs' method gives way to
+/// Herrick-Gibbs.
+const GIBBS_MIN_ANGLE: f64 = 0.05;
+
+/// How far a state is nudged, relative to its size, to find the
+/// sensitivities.
+const NUDGE: f64 = 1.0e-7;
+
+/// A position fix, relative to the central body, in km, at a time, in
+/// seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fix {
+    pub time: f64,
+    pub pos: Vector3<f64>,
+}
+
+/// An orbit fitted to some fixes.
+#[derive(Clone, Debug)]
+pub struct Fit {
+    /// The time of the state, in seconds.
+    pub epoch: f64,
+    /// Relative to the central body, in km and km/s.
+    pub pos: Vector3<f64>,
+    pub vel: Vector3<f64>,
+    pub gm: f64,
+    /// The root mean square distance, in km, of the fixes from the fit.
+    pub rms: f64,
+    /// The covariance of the state, for fixes with the standard deviation
+    /// given to `least_squares`.
+    pub covariance: Matrix6,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+impl Fit {
+    /// The fitted state at `time`, along its conic.
+    pub fn state_at(&self, time: f64) -> (Vector3<f64>, Vector3<f64>) {
+        kepler::propagate(&self.pos, &self.vel, self.gm, time - self.epoch)
+    }
+
+    /// The standard deviation of the position, in km, and of the velocity, in
+    /// km/s, over their three axes, at the epoch.
+    pub fn sigma(&self) -> (f64, f64) {
+        let sum = |start: usize| {
+            (start..start + 3)
+                .map(|i| self.covariance[(i, i)])
+                .sum::<f64>()
+                .sqrt()
+        };
+        (sum(0), sum(3))
+    }
+}
+
+/// The velocity at `r2`, by Gibbs' method, from three positions on one
+/// conic, in order along it.  None if they aren't in one plane, or are too
+/// close together for the geometry to say.
+pub fn gibbs(
+    r1: &Vector3<f64>,
+    r2: &Vector3<f64>,
+    r3: &Vector3<f64>,
+    gm: f64,
+) -> Option<Vector3<f64>> {
+    let (m1, m2, m3) = (r1.norm(), r2.norm(), r3.norm());
+    let z12 = r1.cross(r2);
+    let z23 = r2.cross(r3);
+    let z31 = r3.cross(r1);
+    // Off the plane of the other two, as a fraction.
+    if (r1.dot(&z23) / (m1 * z23.norm())).abs() > 0.01 {
+        return None;
+    }
+    let n = z23 * m1 + z31 * m2 + z12 * m3;
+    let d = z12 + z23 + z31;
+    let s = r1 * (m2 - m3) + r2 * (m3 - m1) + r3 * (m1 - m2);
+    let nd = n.dot(&d);
+    if nd <= 0.0 || d.norm() == 0.0 {
+        return None;
+    }
+    Some(((d.cross(r2) / m2) + s) * (gm / nd).sqrt())
+}
+
+/// The velocity at `f2`, by the Herrick-Gibbs series, from three fixes close
+/// together in time.
+pub fn herrick_gibbs(f1: &Fix, f2: &Fix, f3: &Fix, gm: f64) -> Vector3<f64> {
+    let dt21 = f2.time - f1.time;
+    let dt31 = f3.time - f1.time;
+    let dt32 = f3.time - f2.time;
+    let term = |r: &Vector3<f64>| gm / (12.0 * r.norm().powi(3));
+    f1.pos * (-dt32 * (1.0 / (dt21 * dt31) + term(&f1.pos)))
+        + f2.pos * ((dt32 - dt21) * (1.0 / (dt21 * dt32) + term(&f2.pos)))
+        + f3.pos * (dt21 * (1.0 / (dt32 * dt31) + term(&f3.pos)))
+}
+
+/// The velocity at `f2`, from three fixes in time order: by Gibbs' method,
+/// if they are far enough apart, and by Herrick-Gibbs otherwise.  None if the
+/// times don't increase.
+pub fn three_fixes(f1: &Fix, f2: &Fix, f3: &Fix, gm: f64) -> Option<Vector3<f64>> {
+    if f2.time <= f1.time || f3.time <= f2.time {
+        return None;
+    }
+    let apart = f1.pos.angle(&f2.pos).min(f2.pos.angle(&f3.pos));
+    let gibbs = (apart > GIBBS_MIN_ANGLE)
+        .then(|| gibbs(&f1.pos, &f2.pos, &f3.pos, gm))
+        .flatten();
+    Some(gibbs.unwrap_or_else(|| herrick_gibbs(f1, f2, f3, gm)))
+}
+
+/// Fit a conic to `fixes`, each with a standard deviation of `sigma` km, from
+/// the first guess `pos`, `vel`, at `epoch`.  None if the fixes don't pin
+/// the orbit down, such as with fewer than three.
+pub fn least_squares(
+    fixes: &[Fix],
+    gm: f64,
+    epoch: f64,
+    pos: &Vector3<f64>,
+    vel: &Vector3<f64>,
+    sigma: f64,
+    max_iterations: usize,
+) -> Option<Fit> {
+    if fixes.len() < 3 {
+        return None;
+    }
+    let position = |state: &Vector6, time: f64| {
+        let pos = state.fixed_rows::<3>(0).into_owned();
+        let vel = state.fixed_rows::<3>(3).into_owned();
+        kepler::propagate(&pos, &vel, gm, time - epoch).0
+    };
+    let mut state = Vector6::zeros();
+    state.fixed_rows_mut::<3>(0).copy_from(pos);
+    state.fixed_rows_mut::<3>(3).copy_from(vel);
+
+    let mut iterations = 0;
+    let mut converged = false;
+    let normal = loop {
+        let nudges: Vec<(Vector6, f64)> = (0..6)
+            .map(|i| {
+                let size = if i < 3 { pos.norm() } else { vel.norm() };
+                let h = NUDGE * size.max(1.0e-3);
+                let mut nudged = state;
+                nudged[i] += h;
+                (nudged, h)
+            })
+            .collect();
+        let mut normal = Matrix6::zeros();
+        let mut rhs = Vector6::zeros();
+        for fix in fixes {
+            let predicted = position(&state, fix.time);
+            let mut partials = SMatrix::<f64, 3, 6>::zeros();
+            for (i, (nudged, h)) in nudges.iter().enumerate() {
+                partials.set_column(i, &((position(nudged, fix.time) - predicted) / *h));
+            }
+            normal += partials.transpose() * partials;
+            rhs += partials.transpose() * (fix.pos - predicted);
+        }
+        let step = normal.cholesky()?.solve(&rhs);
+        state += step;
+        iterations += 1;
+        // To a millimeter, and a micrometer a second.
+        if step.fixed_rows::<3>(0).norm() < 1.0e-6 && step.fixed_rows::<3>(3).norm() < 1.0e-9 {
+            converged = true;
+            break normal;
+        }
+        if iterations >= max_iterations {
+            break normal;
+        }
+    };
+
+    let sum: f64 = fixes
+        .iter()
+        .map(|fix| (fix.pos - position(&state, fix.time)).norm_squared())
+        .sum();
+    Some(Fit {
+        epoch,
+        pos: state.fixed_rows::<3>(0).into_owned(),
+        vel: state.fixed_rows::<3>(3).into_owned(),
+        gm,
+        rms: (sum / fixes.len() as f64).sqrt(),
+        covariance: normal.try_inverse()? * (sigma * sigma),
+        iterations,
+        converged,
+    })
+}
+
+/// Fit a conic to `fixes`, in time order, from nothing: a first guess from
+/// the first, middle, and last, and then least squares, with the epoch at
+/// the middle one.
+pub fn determine(fixes: &[Fix], gm: f64, sigma: f64, max_iterations: usize) -> Option<Fit> {
+    let (first, last) = (fixes.first()?, fixes.last()?);
+    let middle = fixes.get(fixes.len() / 2)?;
+    let vel = three_fixes(first, middle, last, gm)?;
+    least_squares(
+        fixes,
+        gm,
+        middle.time,
+        &middle.pos,
+        &vel,
+        sigma,
+        max_iterations,
+    )
+}
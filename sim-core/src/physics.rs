@@ -0,0 +1,224 @@
+//! The physics that moves bodies and crafts.
+//!
+//! Each fixed step, everything with an `OrbitalBody` is moved, and turned by
+//! its `AttitudeState` (see `state`), under the pull of the `MassiveBody`s,
+//! and its own `LinearControl` and `AttitudeControl`.
+//!
+//! The models layered on top (gravity here, drag, collisions, and so on
+//! elsewhere) each have a name in `PhysicsModels`, and can be switched off,
+//! such as to see what a perturbation is doing.  The motion itself is there
+//! too, as `translation` and `attitude`: switching one off holds everything
+//! where it is, or as it is turned, while the rest of the step runs, so that
+//! a controller can be watched at work on the one without the other moving.
+
+extern crate nalgebra as na;
+use bevy::prelude::*;
+use na::Vector3;
+use std::collections::BTreeMap;
+
+use crate::{
+    attitude::{AttitudeIntegrator, NutationDamper, RigidBody},
+    barnes_hut::{GravitySolver, TreeBody},
+    state::{AttitudeControl, AttitudeState, LinearControl, MassiveBody, OrbitalBody},
+    watchdog::Frozen,
+};
+
+/// The set of systems that integrate the physics each fixed step.  Anything
+/// that sets `AttitudeControl` or `LinearControl` during `FixedUpdate` should
+/// run before this.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhysicsSet;
+
+/// The set of systems that check, and correct, the state after each physics
+/// step, such as the watchdog, and collisions.  Anything that just reads the
+/// new state should run after this.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PostPhysicsSet;
+
+/// The physics models, by name, and whether each is on.  A plugin adds its
+/// models with `PhysicsModels::add`, and gates their systems with
+/// `model_enabled`.
+#[derive(Resource, Default)]
+pub struct PhysicsModels(pub BTreeMap<&'static str, bool>);
+
+impl PhysicsModels {
+    /// Add a model to the app, on.
+    pub fn add(app: &mut App, name: &'static str) {
+        app.world_mut()
+            .get_resource_or_init::<PhysicsModels>()
+            .0
+            .insert(name, true);
+    }
+
+    /// Whether the named model is on.  Models nobody added are always on.
+    pub fn enabled(&self, name: &str) -> bool {
+        self.0.get(name).copied().unwrap_or(true)
+    }
+}
+
+/// A run condition for the systems that make up a physics model.
+pub fn model_enabled(name: &'static str) -> impl FnMut(Res<PhysicsModels>) -> bool + Clone {
+    move |models: Res<PhysicsModels>| models.enabled(name)
+}
+
+#[derive(Default)]
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(FixedUpdate, PostPhysicsSet.after(PhysicsSet));
+        PhysicsModels::add(app, "gravity");
+        PhysicsModels::add(app, "third-body");
+        PhysicsModels::add(app, "translation");
+        PhysicsModels::add(app, "attitude");
+        app.init_resource::<AttitudeIntegrator>();
+        app.init_resource::<GravitySolver>();
+        // These all run in a fixed order, so that a replay takes exactly the
+        // same steps.
+        app.add_systems(
+            FixedUpdate,
+            (
+                (linear_accel_step, physics_step)
+                    .chain()
+                    .run_if(model_enabled("translation")),
+                attitude_step.run_if(model_enabled("attitude")),
+            )
+                .chain()
+                .in_set(PhysicsSet),
+        );
+    }
+}
+
+/// The big physics update.  Frozen entities neither move, nor pull on anything.
+/// With gravity off, everything just coasts.
+///
+/// With the third-body model off, a craft is only pulled by its primary, the
+/// body pulling on it hardest, and is carried along with whatever pulls on
+/// the primary, so that all it loses is the difference the other bodies make
+/// between them.  The bodies themselves still pull on each other.
+///
+/// With enough bodies, per the `GravitySolver`, their pull is summed with a
+/// tree.  The primaries are still found directly.
+fn physics_step(
+    mut bodies: Query<(Entity, Option<&MassiveBody>, &mut OrbitalBody), Without<Frozen>>,
+    time: Res<Time>,
+    models: Res<PhysicsModels>,
+    solver: Res<GravitySolver>,
+) {
+    let dt = time.delta_secs_f64();
+    let gravity = models.enabled("gravity");
+    let third_body = models.enabled("third-body");
+
+    let massive: Vec<(Entity, TreeBody)> = bodies
+        .iter()
+        .filter(|_| gravity)
+        .filter_map(|(entity, mb, ob)| Some((entity, TreeBody::fixed(ob.pos, mb?.gm))))
+        .collect();
+    let tree = solver.tree(&massive.iter().map(|(_, b)| b.clone()).collect::<Vec<_>>());
+
+    let mut updates = Vec::new();
+    let mut primaries = Vec::new();
+    let mut index = 0;
+    for (e1, mb1, ob1) in bodies.iter() {
+        let mut total_acceleration = na::Vector3::zeros();
+        let mut primary: Option<(Entity, na::Vector3<f64>)> = None;
+        if let Some(tree) = &tree {
+            let skip = mb1.map(|_| index);
+            index += usize::from(mb1.is_some());
+            total_acceleration = tree.accel(&ob1.pos, 0.0, skip);
+            if mb1.is_none() && !third_body {
+                for (e2, body) in &massive {
+                    let rel_pos = body.pos - ob1.pos;
+                    let distance = rel_pos.norm();
+                    let acceleration = rel_pos * body.gm / (distance * distance * distance);
+                    if primary.is_none_or(|(_, a)| acceleration.norm() > a.norm()) {
+                        primary = Some((*e2, acceleration));
+                    }
+                }
+            }
+        } else {
+            for (e2, mb2, ob2) in bodies.iter() {
+                if e1 == e2 {
+                    continue;
+                }
+
+                if let Some(mb2) = mb2
+                    && gravity
+                {
+                    let rel_pos = ob2.pos - ob1.pos;
+                    let distance = rel_pos.norm();
+                    // Impacts are checked afterwards, in `collision`.
+                    let acceleration = rel_pos * mb2.gm / (distance * distance * distance);
+                    total_acceleration += acceleration;
+                    if primary.is_none_or(|(_, a)| acceleration.norm() > a.norm()) {
+                        primary = Some((e2, acceleration));
+                    }
+                }
+            }
+        }
+        updates.push(total_acceleration);
+        primaries.push(primary.filter(|_| mb1.is_none() && !third_body));
+    }
+
+    if primaries.iter().any(Option::is_some) {
+        let accelerations: BTreeMap<Entity, na::Vector3<f64>> = bodies
+            .iter()
+            .map(|(entity, ..)| entity)
+            .zip(updates.iter().copied())
+            .collect();
+        for (update, primary) in updates.iter_mut().zip(&primaries) {
+            if let Some((entity, acceleration)) = primary {
+                *update = acceleration + accelerations[entity];
+            }
+        }
+    }
+
+    // Now, go through again, iteratively, and apply all of the updates.
+    for ((_, _, mut ob), update) in bodies.iter_mut().zip(updates.iter()) {
+        ob.vel += *update * dt;
+        let delta = ob.vel * dt;
+        ob.pos += delta;
+    }
+}
+
+/// Update the velocity of crafts under their own thrust.
+fn linear_accel_step(
+    mut bodies: Query<(&mut OrbitalBody, &AttitudeState, &LinearControl), Without<Frozen>>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs_f64();
+
+    for (mut orbital, attitude, control) in bodies.iter_mut() {
+        orbital.vel += attitude.q_bw.transform_vector(&control.accel_b) * dt;
+    }
+}
+
+/// Turn everything at its rate, under its own control, by the scheme in the
+/// `AttitudeIntegrator`.
+#[allow(clippy::type_complexity)]
+fn attitude_step(
+    mut bodies: Query<
+        (
+            &mut AttitudeState,
+            Option<&AttitudeControl>,
+            Option<&RigidBody>,
+            Option<&NutationDamper>,
+        ),
+        Without<Frozen>,
+    >,
+    integrator: Res<AttitudeIntegrator>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs_f64();
+
+    for (mut attitude, control, rigid, damper) in bodies.iter_mut() {
+        let alpha_b = control.map_or_else(Vector3::zeros, |c| c.alpha_b);
+        integrator.step(
+            &mut attitude,
+            rigid.map(|r| &r.inertia_b),
+            damper,
+            &alpha_b,
+            dt,
+        );
+    }
+}
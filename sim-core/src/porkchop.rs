@@ -0,0 +1,112 @@
+//! Porkchop plots: transfers between two bodies, over a grid of departure and
+//! arrival times.
+//!
+//! For each pair of times, the transfer is the Lambert conic (see `lambert`)
+//! about the central body, from where the departure body is when leaving, to
+//! where the arrival body is when arriving, going around the same way as the
+//! departure body.  What it costs is in the hyperbolic excess velocities at
+//! each end: the C3 (the excess speed squared) leaving, and the excess speed
+//! arriving.  Contoured over the grid, these make the familiar porkchop
+//! shapes, whose low points are the transfer windows.
+//!
+//! The states are given, rather than looked up, so that this works with any
+//! ephemeris.  Units are whatever the caller uses consistently; the sim uses
+//! km, km/s, and seconds past J2000.
+
+extern crate nalgebra as na;
+use alloc::vec::Vec;
+use na::Vector3;
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+use crate::lambert::lambert;
+
+/// A body's position and velocity relative to the central body, at a time.
+#[derive(Clone, Debug)]
+pub struct Ephemeris {
+    pub time: f64,
+    pub pos: Vector3<f64>,
+    pub vel: Vector3<f64>,
+}
+
+/// One transfer of the grid.
+#[derive(Clone, Debug)]
+pub struct Transfer {
+    pub depart: f64,
+    pub arrive: f64,
+    /// The square of the hyperbolic excess speed leaving, in km^2/s^2.
+    pub c3: f64,
+    /// The hyperbolic excess speed arriving, in km/s.
+    pub v_inf_arrive: f64,
+}
+
+impl Transfer {
+    /// The time of flight.
+    pub fn tof(&self) -> f64 {
+        self.arrive - self.depart
+    }
+
+    pub fn v_inf_depart(&self) -> f64 {
+        self.c3.sqrt()
+    }
+
+    /// Both excess speeds together: the delta-v of the transfer, as two
+    /// impulses, leaving out the climb out of, and fall into, the bodies'
+    /// own wells, which depend on the orbits at each end.
+    pub fn dv(&self) -> f64 {
+        self.v_inf_depart() + self.v_inf_arrive
+    }
+}
+
+/// The transfers over a grid of departure and arrival times.
+#[derive(Clone, Debug)]
+pub struct Porkchop {
+    pub departs: Vec<f64>,
+    pub arrives: Vec<f64>,
+    /// Row by departure, across by arrival.  None where the arrival isn't
+    /// after the departure, or there is no single conic.
+    pub transfers: Vec<Option<Transfer>>,
+}
+
+impl Porkchop {
+    /// Work out the transfer for every pair of `departs` (the departure
+    /// body's states) and `arrives` (the arrival body's), about a central
+    /// body with the given `gm`.
+    pub fn scan(departs: &[Ephemeris], arrives: &[Ephemeris], gm: f64) -> Self {
+        let mut transfers = Vec::with_capacity(departs.len() * arrives.len());
+        for from in departs {
+            let h = from.pos.cross(&from.vel);
+            for to in arrives {
+                let transfer =
+                    lambert(&from.pos, &to.pos, to.time - from.time, gm, &h).map(|(v1, v2)| {
+                        Transfer {
+                            depart: from.time,
+                            arrive: to.time,
+                            c3: (v1 - from.vel).norm_squared(),
+                            v_inf_arrive: (v2 - to.vel).norm(),
+                        }
+                    });
+                transfers.push(transfer);
+            }
+        }
+        Porkchop {
+            departs: departs.iter().map(|e| e.time).collect(),
+            arrives: arrives.iter().map(|e| e.time).collect(),
+            transfers,
+        }
+    }
+
+    /// The transfer for the `i`th departure and `j`th arrival time.
+    pub fn get(&self, i: usize, j: usize) -> Option<&Transfer> {
+        self.transfers.get(i * self.arrives.len() + j)?.as_ref()
+    }
+
+    /// The transfer with the least of `cost`, such as `Transfer::dv`.
+    pub fn best(&self, cost: impl Fn(&Transfer) -> f64) -> Option<&Transfer> {
+        self.transfers
+            .iter()
+            .flatten()
+            .filter(|t| cost(t).is_finite())
+            .min_by(|a, b| cost(a).total_cmp(&cost(b)))
+    }
+}
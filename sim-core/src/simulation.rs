@@ -0,0 +1,443 @@
+//! Assembling and running a world without bevy.
+//!
+//! `SimulationBuilder` puts together bodies and crafts from code, rather than
+//! from a scenario file, and `build`s a `Simulation` that can be stepped and
+//! asked where things are.  This is for tools, tests, and scripts that want
+//! the sim's physics without an `App`.
+//!
+//! The bodies pull on each other, and are stepped together with leapfrog.
+//! Crafts are too small to pull on anything.  Each is advanced about its
+//! primary (the body pulling on it hardest when the simulation is built) by
+//! whichever `integrator::Method` was picked, and the rest is left to the
+//! perturbations that are enabled: without any, a craft follows its two-body
+//! conic.  Within a step, the bodies are taken to move on a parabola from
+//! where they were at the start, which is plenty for steps of minutes.
+//!
+//! With many bodies, such as a belt of asteroids, their pull is summed with a
+//! Barnes-Hut tree (see `barnes_hut`), from the `GravitySolver`'s threshold
+//! on.
+//!
+//! As everywhere in the sim, positions are in km, velocities in km/s, and GMs
+//! in km^3/s^2, relative to the solar system barycenter.
+
+extern crate nalgebra as na;
+use alloc::{format, string::String, vec::Vec};
+use na::{Unit, Vector3};
+#[cfg(not(any(feature = "std", test)))]
+use num_traits::Float;
+
+use crate::{
+    barnes_hut::{GravitySolver, Octree, TreeBody},
+    integrator::{Gravity, Method, Propagator, State},
+    state::{MassiveBody, OrbitalBody},
+};
+
+/// Something pulling on the crafts, other than their primary as a point mass.
+#[derive(Clone, Debug)]
+pub enum Perturbation {
+    /// The pull of every other body, less its pull on the primary.
+    ThirdBody,
+    /// The oblateness of a body, for the crafts that have it as their primary.
+    /// `radius` is the equatorial radius that `j2` goes with, and `pole` is
+    /// the body's spin axis.
+    J2 {
+        body: String,
+        j2: f64,
+        radius: f64,
+        pole: Unit<Vector3<f64>>,
+    },
+}
+
+/// A body, by name.
+#[derive(Clone, Debug)]
+struct SimBody {
+    name: String,
+    gm: f64,
+    state: State,
+}
+
+/// A craft, by name.
+#[derive(Clone, Debug)]
+struct SimCraft {
+    name: String,
+    /// The index of the body it is advanced about.
+    primary: usize,
+    propagator: Propagator,
+}
+
+/// Puts a `Simulation` together.
+#[derive(Clone, Debug)]
+pub struct SimulationBuilder {
+    epoch: f64,
+    method: Method,
+    solver: GravitySolver,
+    perturbations: Vec<Perturbation>,
+    bodies: Vec<SimBody>,
+    crafts: Vec<(String, State)>,
+}
+
+impl Default for SimulationBuilder {
+    fn default() -> Self {
+        SimulationBuilder {
+            epoch: 0.0,
+            method: Method::Rk45 { tolerance: 1.0e-9 },
+            solver: GravitySolver::default(),
+            perturbations: Vec::new(),
+            bodies: Vec::new(),
+            crafts: Vec::new(),
+        }
+    }
+}
+
+impl SimulationBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a body, which pulls on everything else.
+    pub fn add_body(
+        mut self,
+        name: impl Into<String>,
+        massive: MassiveBody,
+        orbital: OrbitalBody,
+    ) -> Self {
+        self.bodies.push(SimBody {
+            name: name.into(),
+            gm: massive.gm,
+            state: State {
+                pos: orbital.pos,
+                vel: orbital.vel,
+            },
+        });
+        self
+    }
+
+    /// Add a craft, which is pulled on, but doesn't pull back.
+    pub fn add_craft(mut self, name: impl Into<String>, orbital: OrbitalBody) -> Self {
+        self.crafts.push((
+            name.into(),
+            State {
+                pos: orbital.pos,
+                vel: orbital.vel,
+            },
+        ));
+        self
+    }
+
+    /// The time the simulation starts at, in seconds past J2000.
+    pub fn set_epoch(mut self, et: f64) -> Self {
+        self.epoch = et;
+        self
+    }
+
+    /// How the crafts are advanced.  RK45, at a tolerance of 1e-9, by default.
+    pub fn set_integrator(mut self, method: Method) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// When the bodies' pull is summed with a tree, rather than directly.
+    pub fn set_gravity_solver(mut self, solver: GravitySolver) -> Self {
+        self.solver = solver;
+        self
+    }
+
+    pub fn enable_perturbation(mut self, perturbation: Perturbation) -> Self {
+        self.perturbations.push(perturbation);
+        self
+    }
+
+    /// The simulation, or why it can't be one: every name must be different,
+    /// and there must be a body for the crafts to be about.
+    pub fn build(self) -> Result<Simulation, String> {
+        let mut names: Vec<&str> = self
+            .bodies
+            .iter()
+            .map(|b| b.name.as_str())
+            .chain(self.crafts.iter().map(|(name, _)| name.as_str()))
+            .collect();
+        names.sort_unstable();
+        if let Some(pair) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(format!("There is more than one {:?}", pair[0]));
+        }
+        for perturbation in &self.perturbations {
+            if let Perturbation::J2 { body, .. } = perturbation
+                && !self.bodies.iter().any(|b| b.name == *body)
+            {
+                return Err(format!("No such body as {:?} for J2", body));
+            }
+        }
+
+        let crafts = self
+            .crafts
+            .into_iter()
+            .map(|(name, state)| {
+                let (primary, body) = self
+                    .bodies
+                    .iter()
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| {
+                        let pull =
+                            |body: &SimBody| body.gm / (body.state.pos - state.pos).norm_squared();
+                        pull(a).total_cmp(&pull(b))
+                    })
+                    .ok_or_else(|| format!("No body for {:?} to be about", name))?;
+                let relative = State {
+                    pos: state.pos - body.state.pos,
+                    vel: state.vel - body.state.vel,
+                };
+                Ok(SimCraft {
+                    name,
+                    primary,
+                    propagator: Propagator::new(self.method, 0.0, relative),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Simulation {
+            epoch: self.epoch,
+            time: 0.0,
+            solver: self.solver,
+            perturbations: self.perturbations,
+            bodies: self.bodies,
+            crafts,
+        })
+    }
+}
+
+/// A running simulation.  See `SimulationBuilder`.
+#[derive(Clone, Debug)]
+pub struct Simulation {
+    epoch: f64,
+    time: f64,
+    solver: GravitySolver,
+    perturbations: Vec<Perturbation>,
+    bodies: Vec<SimBody>,
+    crafts: Vec<SimCraft>,
+}
+
+/// The pull of the bodies on each other, at the given positions.
+fn body_accels(
+    bodies: &[SimBody],
+    pos: &[Vector3<f64>],
+    solver: &GravitySolver,
+) -> Vec<Vector3<f64>> {
+    let tree_bodies: Vec<_> = bodies
+        .iter()
+        .zip(pos)
+        .map(|(body, pos)| TreeBody::fixed(*pos, body.gm))
+        .collect();
+    if let Some(tree) = solver.tree(&tree_bodies) {
+        return pos
+            .iter()
+            .enumerate()
+            .map(|(i, p)| tree.accel(p, 0.0, Some(i)))
+            .collect();
+    }
+    pos.iter()
+        .enumerate()
+        .map(|(i, p1)| {
+            bodies
+                .iter()
+                .zip(pos)
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(_, (body, p2))| {
+                    let rel = p2 - p1;
+                    let d = rel.norm();
+                    rel * (body.gm / (d * d * d))
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// The gravity on a craft, over one step.
+struct CraftGravity<'a> {
+    bodies: &'a [SimBody],
+    /// The bodies' accelerations at the start of the step, and when that was.
+    accels: &'a [Vector3<f64>],
+    start: f64,
+    primary: usize,
+    perturbations: &'a [Perturbation],
+    /// The bodies, in a tree, if there are enough of them.
+    tree: Option<&'a Octree>,
+}
+
+impl CraftGravity<'_> {
+    /// Where body `i` is at `t`.
+    fn body_pos(&self, i: usize, t: f64) -> Vector3<f64> {
+        let dt = t - self.start;
+        let state = &self.bodies[i].state;
+        state.pos + state.vel * dt + self.accels[i] * (dt * dt / 2.0)
+    }
+}
+
+impl Gravity for CraftGravity<'_> {
+    fn gm(&self) -> f64 {
+        self.bodies[self.primary].gm
+    }
+
+    fn perturbation(&self, t: f64, pos: &Vector3<f64>) -> Vector3<f64> {
+        let mut accel = Vector3::zeros();
+        for perturbation in self.perturbations {
+            match perturbation {
+                Perturbation::ThirdBody => {
+                    let primary = self.body_pos(self.primary, t);
+                    if let Some(tree) = self.tree {
+                        let dt = t - self.start;
+                        accel += tree.tide(&(primary + pos), &primary, dt, Some(self.primary));
+                        continue;
+                    }
+                    for (i, body) in self.bodies.iter().enumerate() {
+                        if i == self.primary {
+                            continue;
+                        }
+                        // Both relative to the primary, which is our origin.
+                        let other = self.body_pos(i, t) - primary;
+                        let rel = other - pos;
+                        let d = rel.norm();
+                        let o = other.norm();
+                        accel += rel * (body.gm / (d * d * d)) - other * (body.gm / (o * o * o));
+                    }
+                }
+                Perturbation::J2 {
+                    body,
+                    j2,
+                    radius,
+                    pole,
+                } => {
+                    if self.bodies[self.primary].name != *body {
+                        continue;
+                    }
+                    let r = pos.norm();
+                    let z = pos.dot(pole);
+                    let k = 1.5 * j2 * self.gm() * radius * radius / (r * r * r * r * r);
+                    let zr2 = 5.0 * z * z / (r * r);
+                    accel += (pos * (zr2 - 1.0) - pole.into_inner() * (2.0 * z)) * k;
+                }
+            }
+        }
+        accel
+    }
+}
+
+impl Simulation {
+    /// The time since the start, in seconds.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// The current time, in seconds past J2000.
+    pub fn et(&self) -> f64 {
+        self.epoch + self.time
+    }
+
+    /// The names of the bodies, and then the crafts.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.bodies
+            .iter()
+            .map(|b| b.name.as_str())
+            .chain(self.crafts.iter().map(|c| c.name.as_str()))
+    }
+
+    /// Where a body or craft is, and how fast it is going, relative to the
+    /// barycenter.
+    pub fn state(&self, name: &str) -> Option<OrbitalBody> {
+        if let Some(body) = self.bodies.iter().find(|b| b.name == name) {
+            return Some(OrbitalBody {
+                pos: body.state.pos,
+                vel: body.state.vel,
+            });
+        }
+        let craft = self.crafts.iter().find(|c| c.name == name)?;
+        let primary = &self.bodies[craft.primary];
+        let gravity = CraftGravity {
+            bodies: &self.bodies,
+            accels: &[],
+            start: self.time,
+            primary: craft.primary,
+            perturbations: &[],
+            tree: None,
+        };
+        let state = craft.propagator.state(&gravity);
+        Some(OrbitalBody {
+            pos: primary.state.pos + state.pos,
+            vel: primary.state.vel + state.vel,
+        })
+    }
+
+    /// The state of one body or craft relative to another.
+    pub fn relative(&self, name: &str, center: &str) -> Option<OrbitalBody> {
+        let state = self.state(name)?;
+        let center = self.state(center)?;
+        Some(OrbitalBody {
+            pos: state.pos - center.pos,
+            vel: state.vel - center.vel,
+        })
+    }
+
+    /// The body a craft is advanced about.
+    pub fn primary(&self, craft: &str) -> Option<&str> {
+        let craft = self.crafts.iter().find(|c| c.name == craft)?;
+        Some(&self.bodies[craft.primary].name)
+    }
+
+    /// How many times a craft's acceleration has been evaluated, as a measure
+    /// of what it has cost.
+    pub fn evaluations(&self, craft: &str) -> Option<u64> {
+        let craft = self.crafts.iter().find(|c| c.name == craft)?;
+        Some(craft.propagator.evaluations)
+    }
+
+    /// Advance everything by `dt` seconds.
+    pub fn step(&mut self, dt: f64) {
+        let pos: Vec<_> = self.bodies.iter().map(|b| b.state.pos).collect();
+        let accels = body_accels(&self.bodies, &pos, &self.solver);
+        let tree = self.solver.tree(
+            &self
+                .bodies
+                .iter()
+                .zip(&accels)
+                .map(|(body, accel)| TreeBody {
+                    pos: body.state.pos,
+                    vel: body.state.vel,
+                    accel: *accel,
+                    gm: body.gm,
+                })
+                .collect::<Vec<_>>(),
+        );
+
+        for craft in &mut self.crafts {
+            let gravity = CraftGravity {
+                bodies: &self.bodies,
+                accels: &accels,
+                start: self.time,
+                primary: craft.primary,
+                perturbations: &self.perturbations,
+                tree: tree.as_ref(),
+            };
+            craft.propagator.step(&gravity, dt);
+        }
+
+        // Kick-drift-kick, as in `integrator`.
+        for (body, accel) in self.bodies.iter_mut().zip(&accels) {
+            body.state.vel += accel * (dt / 2.0);
+            body.state.pos += body.state.vel * dt;
+        }
+        let pos: Vec<_> = self.bodies.iter().map(|b| b.state.pos).collect();
+        let accels = body_accels(&self.bodies, &pos, &self.solver);
+        for (body, accel) in self.bodies.iter_mut().zip(&accels) {
+            body.state.vel += accel * (dt / 2.0);
+        }
+        self.time += dt;
+    }
+
+    /// Advance by `duration` seconds, in steps of at most `dt`.
+    pub fn run(&mut self, duration: f64, dt: f64) {
+        let steps = (duration / dt).ceil().max(0.0) as usize;
+        for _ in 0..steps {
+            self.step(duration / steps as f64);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! The state of bodies and crafts.
+//!
+//! Everything that moves has an `OrbitalBody`, and most things also have an
+//! `AttitudeState`.  Bodies with a `MassiveBody` pull on everything else.
+//! Crafts steer themselves by setting their `LinearControl` and
+//! `AttitudeControl`.  With the `bevy` feature, these are the components the
+//! physics (see `physics`) moves each fixed step.
+
+extern crate nalgebra as na;
+#[cfg(feature = "bevy")]
+use bevy::prelude::*;
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+
+/// An object that has sufficient mass to be considered a body for orbital
+/// mechanics.  Units are in km^3/s^2.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Component))]
+pub struct MassiveBody {
+    pub gm: f64, // Gravitational constant * mass, km^3/s^2
+}
+
+/// A celestial object that has a position and velocity in space.  Units are in
+/// km and km/s, with an origin at the solar system barycenter.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Component))]
+pub struct OrbitalBody {
+    pub pos: Vector3<f64>,
+    pub vel: Vector3<f64>,
+}
+
+/// An object that has a meaningful notion of a radius.  Units are in km, and
+/// are along the x, y, and z axes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Component))]
+pub struct SizedBody {
+    pub radii: Vector3<f64>,
+}
+
+/// The AttitudeState represents the current orientation, and angular velocity of the body.
+/// The orientation is a convertion between body to world, and the omega is the angular velocity relative to the body frame.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Component))]
+pub struct AttitudeState {
+    pub q_bw: na::UnitQuaternion<f64>,
+    pub omega_b: Vector3<f64>,
+}
+
+impl AttitudeState {
+    /// The orientation `dt` seconds from now, if the rate stays constant.  This
+    /// is how the `Leapfrog` scheme turns it each step.
+    pub fn q_bw_after(&self, dt: f64) -> na::UnitQuaternion<f64> {
+        let angle = self.omega_b.norm() * dt;
+        if angle.abs() > 1.0e-12 {
+            let axis = na::Unit::new_normalize(self.omega_b);
+            // The rate is in the body frame, so the turn is applied on the
+            // body side.
+            self.q_bw * na::UnitQuaternion::from_axis_angle(&axis, angle)
+        } else {
+            self.q_bw
+        }
+    }
+
+    /// The orientation on the clock, for a state kept `lead` seconds ahead of
+    /// it (see `AttitudeIntegrator::lead`).
+    pub fn q_bw_now(&self, lead: f64) -> na::UnitQuaternion<f64> {
+        self.q_bw_after(-lead)
+    }
+
+    /// Set the rate, BODY frame, from now on.  A state kept `lead` seconds
+    /// ahead of the clock has already turned that far at the old rate, so it
+    /// is turned back, and on again at the new one.
+    pub fn set_omega_b(&mut self, omega_b: &Vector3<f64>, lead: f64) {
+        let q_bw = self.q_bw_now(lead);
+        *self = AttitudeState {
+            q_bw,
+            omega_b: *omega_b,
+        };
+        self.q_bw = self.q_bw_after(lead);
+    }
+
+    /// Set the rate, WORLD frame, from now on, as for `set_omega_b`.
+    pub fn set_omega_w(&mut self, omega_w: &Vector3<f64>, lead: f64) {
+        let omega_b = self.q_bw_now(lead).inverse_transform_vector(omega_w);
+        self.set_omega_b(&omega_b, lead);
+    }
+
+    /// Take an instantaneous angular impulse, BODY frame, in kg*m^2/s, given
+    /// the principal moments of inertia along the BODY axes, in kg*m^2.
+    pub fn apply_angular_impulse_b(
+        &mut self,
+        impulse_b: &Vector3<f64>,
+        inertia_b: &Vector3<f64>,
+        lead: f64,
+    ) {
+        let omega_b = self.omega_b + impulse_b.component_div(inertia_b);
+        self.set_omega_b(&omega_b, lead);
+    }
+
+    /// Take an instantaneous angular impulse, WORLD frame, as for
+    /// `apply_angular_impulse_b`.
+    pub fn apply_angular_impulse_w(
+        &mut self,
+        impulse_w: &Vector3<f64>,
+        inertia_b: &Vector3<f64>,
+        lead: f64,
+    ) {
+        let impulse_b = self.q_bw_now(lead).inverse_transform_vector(impulse_w);
+        self.apply_angular_impulse_b(&impulse_b, inertia_b, lead);
+    }
+
+    /// The kinetic energy of the rotation, in J, given the principal moments
+    /// of inertia along the BODY axes, in kg*m^2.
+    pub fn rotational_energy(&self, inertia_b: &Vector3<f64>) -> f64 {
+        0.5 * inertia_b.dot(&self.omega_b.component_mul(&self.omega_b))
+    }
+
+    /// The magnitude of the angular momentum, in kg*m^2/s, given the
+    /// principal moments of inertia along the BODY axes, in kg*m^2.  With no
+    /// torque, this stays the same, as does the energy.
+    pub fn angular_momentum(&self, inertia_b: &Vector3<f64>) -> f64 {
+        inertia_b.component_mul(&self.omega_b).norm()
+    }
+}
+
+/// The attitude can also be under acceleration (such as by an RCS system). This
+/// is represented here as an angular acceleration in the body frame (with Z
+/// being the axis along which the main engine fires).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Component))]
+pub struct AttitudeControl {
+    pub alpha_b: Vector3<f64>,
+}
+
+/// Similarly, a craft can be under linear acceleration from its own thrusters.
+/// This is in the body frame, in km/s^2, and is rotated into the world by the
+/// craft's AttitudeState.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(Component))]
+pub struct LinearControl {
+    pub accel_b: Vector3<f64>,
+}
@@ -0,0 +1,239 @@
+//! Physics watchdog.
+//!
+//! A single NaN or overflow in one craft doesn't stay put: the next gravity
+//! pass spreads it to everything else.  After each physics step, the watchdog
+//! checks every state.  An entity that has gone bad is put back to its last
+//! valid state and frozen, so that it takes no further part in the physics, and
+//! a `PhysicsFault` is sent describing what happened.  F9 restores the frozen
+//! entities from their snapshots and lets them run again.
+
+use bevy::prelude::*;
+use nalgebra::Vector3;
+
+use crate::{
+    physics::PostPhysicsSet,
+    state::{AttitudeControl, AttitudeState, LinearControl, MassiveBody, OrbitalBody},
+};
+
+/// Positions beyond this, in km, are treated as an overflow.  This is well
+/// outside of the solar system.
+const WATCHDOG_MAX_POS: f64 = 1.0e12;
+
+/// Velocities beyond this, in km/s, are treated as an overflow.  This is the
+/// speed of light.
+const WATCHDOG_MAX_VEL: f64 = 299_792.458;
+
+/// Angular rates beyond this, in rad/s, are treated as an overflow.
+const WATCHDOG_MAX_OMEGA: f64 = 1.0e3;
+
+/// An entity that has been taken out of the physics, by the watchdog, or by a
+/// collision.
+#[derive(Clone, Component, Debug)]
+pub struct Frozen {
+    /// What was wrong with it.
+    pub reason: String,
+}
+
+/// The last state of an entity that passed the watchdog, and the sim time it
+/// was taken.
+#[derive(Clone, Component, Debug)]
+pub struct LastValid {
+    pub time: f64,
+    pub orbital: Option<OrbitalBody>,
+    pub attitude: Option<AttitudeState>,
+}
+
+/// Sent when the watchdog freezes an entity.
+#[derive(Clone, Debug, Message)]
+pub struct PhysicsFault {
+    pub entity: Entity,
+    pub name: String,
+    /// The sim time of the step that went bad.
+    pub time: f64,
+    pub reason: String,
+    /// The state the entity was put back to, if it ever had a valid one.
+    pub last_valid: Option<LastValid>,
+    /// The force models that were acting on the entity at the time.
+    pub forces: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct WatchdogPlugin;
+
+impl Plugin for WatchdogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<PhysicsFault>();
+        app.add_systems(FixedUpdate, physics_watchdog.in_set(PostPhysicsSet));
+        app.add_systems(Update, (restore_keys, log_faults));
+    }
+}
+
+fn all_finite(v: &Vector3<f64>) -> bool {
+    v.iter().all(|x| x.is_finite())
+}
+
+/// What is wrong with the state, if anything.
+fn check_state(orbital: Option<&OrbitalBody>, attitude: Option<&AttitudeState>) -> Option<String> {
+    if let Some(orbital) = orbital {
+        if !all_finite(&orbital.pos) || !all_finite(&orbital.vel) {
+            return Some("position or velocity is not finite".to_string());
+        }
+        if orbital.pos.norm() > WATCHDOG_MAX_POS {
+            return Some(format!("position overflow ({:.3e} km)", orbital.pos.norm()));
+        }
+        if orbital.vel.norm() > WATCHDOG_MAX_VEL {
+            return Some(format!(
+                "velocity overflow ({:.3e} km/s)",
+                orbital.vel.norm()
+            ));
+        }
+    }
+    if let Some(attitude) = attitude {
+        if !attitude.q_bw.coords.iter().all(|x| x.is_finite()) || !all_finite(&attitude.omega_b) {
+            return Some("attitude is not finite".to_string());
+        }
+        if attitude.omega_b.norm() > WATCHDOG_MAX_OMEGA {
+            return Some(format!(
+                "angular rate overflow ({:.3e} rad/s)",
+                attitude.omega_b.norm()
+            ));
+        }
+    }
+    None
+}
+
+#[allow(clippy::type_complexity)]
+pub fn physics_watchdog(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut faults: MessageWriter<PhysicsFault>,
+    mut bodies: Query<
+        (
+            Entity,
+            Option<&Name>,
+            Option<&mut OrbitalBody>,
+            Option<&mut AttitudeState>,
+            Option<&mut LastValid>,
+            Option<&MassiveBody>,
+            Option<&LinearControl>,
+            Option<&AttitudeControl>,
+        ),
+        (
+            Or<(With<OrbitalBody>, With<AttitudeState>)>,
+            Without<Frozen>,
+        ),
+    >,
+) {
+    let now = time.elapsed_secs_f64();
+    for (entity, name, mut orbital, mut attitude, last, massive, linear, angular) in
+        bodies.iter_mut()
+    {
+        let Some(reason) = check_state(orbital.as_deref(), attitude.as_deref()) else {
+            let snapshot = LastValid {
+                time: now,
+                orbital: orbital.as_deref().cloned(),
+                attitude: attitude.as_deref().cloned(),
+            };
+            match last {
+                Some(mut last) => *last = snapshot,
+                None => {
+                    commands.entity(entity).insert(snapshot);
+                }
+            }
+            continue;
+        };
+
+        let mut forces = Vec::new();
+        if orbital.is_some() {
+            forces.push("n-body gravity".to_string());
+        }
+        if let Some(massive) = massive {
+            forces.push(format!(
+                "source of gravity (gm {:.3e} km^3/s^2)",
+                massive.gm
+            ));
+        }
+        if let Some(linear) = linear
+            && linear.accel_b != Vector3::zeros()
+        {
+            forces.push(format!("thrust {:.3e} km/s^2 (body)", linear.accel_b));
+        }
+        if let Some(angular) = angular
+            && angular.alpha_b != Vector3::zeros()
+        {
+            forces.push(format!("torque {:.3e} rad/s^2 (body)", angular.alpha_b));
+        }
+
+        // Put it back, so nothing else picks up the bad values.
+        let last = last.map(|last| last.clone());
+        if let Some(last) = &last {
+            if let (Some(orbital), Some(valid)) = (orbital.as_deref_mut(), &last.orbital) {
+                *orbital = valid.clone();
+            }
+            if let (Some(attitude), Some(valid)) = (attitude.as_deref_mut(), &last.attitude) {
+                *attitude = valid.clone();
+            }
+        }
+
+        commands.entity(entity).insert(Frozen {
+            reason: reason.clone(),
+        });
+        faults.write(PhysicsFault {
+            entity,
+            name: name.map_or_else(|| format!("{}", entity), |n| n.to_string()),
+            time: now,
+            reason,
+            last_valid: last,
+            forces,
+        });
+    }
+}
+
+fn log_faults(mut faults: MessageReader<PhysicsFault>) {
+    for fault in faults.read() {
+        error!(
+            "Physics fault at {:.3} s: {} ({}) {}, frozen.  Forces: {}.",
+            fault.time,
+            fault.name,
+            fault.entity,
+            fault.reason,
+            fault.forces.join(", "),
+        );
+        match &fault.last_valid {
+            Some(last) => error!(
+                "  Last valid state, at {:.3} s: {:?} {:?}",
+                last.time, last.orbital, last.attitude
+            ),
+            None => error!("  No valid state to restore."),
+        }
+    }
+}
+
+/// F9 restores the frozen entities from their last valid state.
+#[allow(clippy::type_complexity)]
+fn restore_keys(
+    mut commands: Commands,
+    kb: Res<ButtonInput<KeyCode>>,
+    mut frozen: Query<
+        (
+            Entity,
+            &LastValid,
+            Option<&mut OrbitalBody>,
+            Option<&mut AttitudeState>,
+        ),
+        With<Frozen>,
+    >,
+) {
+    if !kb.just_pressed(KeyCode::F9) {
+        return;
+    }
+    for (entity, last, orbital, attitude) in frozen.iter_mut() {
+        if let (Some(mut orbital), Some(valid)) = (orbital, &last.orbital) {
+            *orbital = valid.clone();
+        }
+        if let (Some(mut attitude), Some(valid)) = (attitude, &last.attitude) {
+            *attitude = valid.clone();
+        }
+        commands.entity(entity).remove::<Frozen>();
+    }
+}
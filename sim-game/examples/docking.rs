@@ -0,0 +1,169 @@
+//! Check that a docking approach ends in a capture.
+//!
+//! A station is put 30 m ahead of the ship in its orbit, with its port
+//! facing back along the track.  The ship points its own port at the
+//! station (`RcsMode::Target`), and once it is steady, is given the push
+//! that the Clohessy–Wiltshire equations say takes its port to the
+//! station's in `APPROACH` seconds.  Over so short a time, that is close to
+//! a straight line, in the world, so the station is left holding still, as
+//! `station` leaves it, rather than turning with its orbit: its port keeps
+//! facing the way the ship comes in.  The latches should catch by the time
+//! the ship gets there, or soon after.  How the ports stand is printed
+//! every ten seconds, until they do.
+//!
+//! Run with: cargo run --release -p sim-game --example docking
+//!
+//! `cargo test` runs it too, and fails if the ports don't catch.
+
+extern crate nalgebra as na;
+
+use bevy::{input::InputPlugin, prelude::*, time::TimeUpdateStrategy};
+use na::{Matrix3, Vector3};
+use sim_astro::{EarthMarker, SolarState, frames::Axes};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, orbit::clohessy_wiltshire};
+use sim_game::{
+    console::{ConsolePlugin, run_line},
+    ship::{MassProperties, PlayerShip, RcsMode, SasTarget, docking::DockingPort},
+    sim::SimPlugins,
+};
+use std::time::Duration;
+
+/// The length of a frame, in real seconds.
+const FRAME: f64 = 1.0 / 64.0;
+
+/// How much faster than real time it runs.
+const WARP: f64 = 20.0;
+
+/// How long, in seconds, the ship has to point itself at the station.
+const SETTLE: f64 = 15.0;
+
+/// How long, in seconds, the approach is to take.
+const APPROACH: f64 = 300.0;
+
+/// How long, in seconds, past the approach, to wait for the capture.
+const GRACE: f64 = 20.0;
+
+/// Where a craft's port is, in m, relative to the SSB.
+fn port_w(
+    port: &DockingPort,
+    orbital: &OrbitalBody,
+    attitude: &AttitudeState,
+    mass: &MassProperties,
+) -> Vector3<f64> {
+    orbital.pos * 1000.0 + attitude.q_bw.transform_vector(&(port.pos_b - mass.cg_b))
+}
+
+/// Run until the sim time gets to `until`, or the ports catch.  Whether
+/// they did.
+fn run_until(app: &mut App, until: f64) -> bool {
+    while app.world().resource::<Time<Fixed>>().elapsed_secs_f64() < until {
+        app.update();
+        let world = app.world_mut();
+        let port = world
+            .query_filtered::<&DockingPort, With<PlayerShip>>()
+            .single(world)
+            .expect("no ship");
+        if port.docked.is_some() {
+            return true;
+        }
+    }
+    false
+}
+
+/// Push the ship onto the approach.
+fn push(world: &mut World) {
+    let station = world.resource::<SasTarget>().0.expect("no station");
+    let (earth, earth_mass) = world
+        .query_filtered::<(&OrbitalBody, &MassiveBody), With<EarthMarker>>()
+        .single(world)
+        .map(|(orbital, mass)| (orbital.clone(), mass.gm))
+        .expect("no earth");
+    let mut crafts = world.query::<(&DockingPort, &OrbitalBody, &AttitudeState, &MassProperties)>();
+    let (port, orbital, attitude, mass) = crafts.get(world, station).expect("no station port");
+    let (station_pos, station_vel) = (orbital.pos - earth.pos, orbital.vel - earth.vel);
+    let station_port = port_w(port, orbital, attitude, mass);
+    let mut ship = world.query_filtered::<(
+        &DockingPort,
+        &mut OrbitalBody,
+        &AttitudeState,
+        &MassProperties,
+    ), With<PlayerShip>>();
+    let (port, mut orbital, attitude, mass) = ship.single_mut(world).expect("no ship");
+    let ship_port = port_w(port, &orbital, attitude, mass);
+
+    // The ship's port relative to the station's, in the station's LVLH
+    // frame, as in `clohessy_wiltshire`, in m, and m/s as seen turning with
+    // it.
+    let n = (earth_mass / station_pos.norm().powi(3)).sqrt();
+    let axes = Axes::lvlh(&station_pos, &station_vel);
+    let (rel, rel_vel) = axes.to_frame(
+        &(ship_port - station_port),
+        &((orbital.vel - earth.vel - station_vel) * 1000.0),
+    );
+
+    // Where a unit of each, of the position or of the velocity, on its own,
+    // takes the port.
+    let units = |of_pos: bool| {
+        Matrix3::from_columns(&[Vector3::x(), Vector3::y(), Vector3::z()].map(|unit| {
+            let (pos, vel) = if of_pos {
+                (unit, Vector3::zeros())
+            } else {
+                (Vector3::zeros(), unit)
+            };
+            clohessy_wiltshire(&pos, &vel, n, APPROACH).0
+        }))
+    };
+    let (from_pos, from_vel) = (units(true), units(false));
+    let wanted = from_vel.try_inverse().expect("no approach") * -(from_pos * rel);
+    let dv = axes.q_fw.transform_vector(&(wanted - rel_vel));
+    println!("Pushed {:.3} m/s, from {:.2} m", dv.norm(), rel.norm());
+    orbital.vel += dv / 1000.0;
+}
+
+fn main() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin));
+    app.insert_resource(
+        SolarState::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../solar.json"))
+            .expect("Can't load solar.json"),
+    );
+    app.add_plugins(SimPlugins);
+    app.add_plugins(ConsolePlugin);
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        FRAME,
+    )));
+    app.finish();
+    app.cleanup();
+    app.world_mut()
+        .resource_mut::<Time<Virtual>>()
+        .set_relative_speed_f64(WARP);
+
+    app.update();
+    let world = app.world_mut();
+    for line in ["teleport earth 400", "station dock 30"] {
+        run_line(world, line).expect(line);
+    }
+    app.update();
+    app.world_mut().insert_resource(RcsMode::Target);
+
+    let start = app.world().resource::<Time<Fixed>>().elapsed_secs_f64();
+    assert!(
+        !run_until(&mut app, start + SETTLE),
+        "Caught before the approach"
+    );
+    push(app.world_mut());
+    let pushed = app.world().resource::<Time<Fixed>>().elapsed_secs_f64();
+    let mut caught = false;
+    let mut next = pushed;
+    while !caught && next < pushed + APPROACH + GRACE {
+        next += 10.0;
+        caught = run_until(&mut app, next);
+        let reply = run_line(app.world_mut(), "dock");
+        println!(
+            "t = {:5.1} s: {}",
+            app.world().resource::<Time<Fixed>>().elapsed_secs_f64() - pushed,
+            reply.unwrap_or_else(|e| e)
+        );
+    }
+    assert!(caught, "The ports didn't catch");
+}
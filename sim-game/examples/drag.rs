@@ -0,0 +1,158 @@
+//! Check that drag lowers the orbit as much as the atmosphere says it should.
+//!
+//! The ship is flown prograde in a low orbit twice, once with the `drag`
+//! model on and once with it off, and the semi-major axes of the two
+//! compared step by step: everything but the drag is the same in both, so
+//! the difference is the decay.  Along the way, the decay the air should
+//! cause is summed, from Gauss's equation,
+//!
+//!   da/dt = 2 a^2 / GM v·f
+//!
+//! with the drag `f` worked out from the earth's `Atmosphere` where the ship
+//! is, and the area it presents to the wind.  The two should agree to
+//! within `BOUND`.
+//!
+//! Run with: cargo run --release -p sim-game --example drag
+//!
+//! `cargo test` runs it too, and fails if they don't.
+
+use bevy::{input::InputPlugin, prelude::*, time::TimeUpdateStrategy};
+use sim_astro::{EarthMarker, SolarState, atmosphere::Atmosphere, geodesy::Geodetic};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, PostPhysicsSet, SizedBody};
+use sim_game::{
+    console::{ConsolePlugin, run_line},
+    ship::{MassProperties, PlayerShip, RcsMode, aero::Aero},
+    sim::SimPlugins,
+};
+use std::time::Duration;
+
+/// The length of a frame, in real seconds.
+const FRAME: f64 = 1.0 / 64.0;
+
+/// How much faster than real time it runs.
+const WARP: f64 = 500.0;
+
+/// The orbit, as for the `teleport` command.
+const ORBIT: &str = "teleport earth 150";
+
+/// How long, in seconds, each run is.
+const DURATION: f64 = 600.0;
+
+/// How far, as a fraction, the decay can be from what the air should cause.
+const BOUND: f64 = 0.001;
+
+/// The semi-major axis, in km, after each physics step, and the decay the
+/// air should have caused by then.
+#[derive(Resource, Default)]
+struct Track(Vec<(f64, f64)>);
+
+#[allow(clippy::type_complexity)]
+fn record(
+    fixed: Res<Time<Fixed>>,
+    mut track: ResMut<Track>,
+    ship: Query<(&OrbitalBody, &AttitudeState, &Aero, &MassProperties), With<PlayerShip>>,
+    earth: Query<
+        (
+            &OrbitalBody,
+            &AttitudeState,
+            &MassiveBody,
+            &SizedBody,
+            &Atmosphere,
+        ),
+        (With<EarthMarker>, Without<PlayerShip>),
+    >,
+) {
+    let (Ok((orbital, attitude, aero, mass)), Ok((earth, earth_attitude, earth_mass, size, air))) =
+        (ship.single(), earth.single())
+    else {
+        return;
+    };
+    let pos = orbital.pos - earth.pos;
+    let vel = orbital.vel - earth.vel;
+    let a = 1.0 / (2.0 / pos.norm() - vel.norm_squared() / earth_mass.gm);
+
+    let alt = Geodetic::from_world(earth, earth_attitude, &size.radii, &orbital.pos).alt;
+    let wind_w = Atmosphere::wind_relative(earth, earth_attitude, &orbital.pos, &orbital.vel);
+    let area = aero.area(&attitude.q_bw.inverse_transform_vector(&wind_w));
+    // In m/s^2, and then km/s^2.
+    let speed = wind_w.norm() * 1000.0;
+    let drag = 0.5 * air.density(alt) * speed * speed * aero.drag_coefficient * area / mass.mass;
+    let f_w = -wind_w.normalize() * drag / 1000.0;
+    let rate = 2.0 * a * a / earth_mass.gm * vel.dot(&f_w);
+
+    let decay = track.0.last().map_or(0.0, |(_, decay)| *decay);
+    track
+        .0
+        .push((a, decay + rate * fixed.timestep().as_secs_f64()));
+}
+
+/// Fly the orbit, with the drag or without.
+fn fly(drag: bool) -> Vec<(f64, f64)> {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin));
+    app.insert_resource(
+        SolarState::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../solar.json"))
+            .expect("Can't load solar.json"),
+    );
+    app.add_plugins(SimPlugins);
+    app.add_plugins(ConsolePlugin);
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        FRAME,
+    )));
+    app.init_resource::<Track>();
+    app.add_systems(FixedUpdate, record.after(PostPhysicsSet));
+    app.finish();
+    app.cleanup();
+    app.world_mut()
+        .resource_mut::<Time<Virtual>>()
+        .set_relative_speed_f64(WARP);
+
+    app.update();
+    let world = app.world_mut();
+    for line in [
+        ORBIT,
+        if drag {
+            "model drag on"
+        } else {
+            "model drag off"
+        },
+    ] {
+        run_line(world, line).expect(line);
+    }
+    *world.resource_mut::<RcsMode>() = RcsMode::Prograde;
+    world.resource_mut::<Track>().0.clear();
+    while app.world().resource::<Time<Fixed>>().elapsed_secs_f64() < DURATION {
+        app.update();
+    }
+    app.world_mut().remove_resource::<Track>().unwrap().0
+}
+
+fn main() {
+    let with = fly(true);
+    let without = fly(false);
+    let steps = with.len().min(without.len());
+    assert!(steps > 0, "No steps were run");
+
+    // The decay by a step, and what the air says it should be, in km.
+    let decay = |step: usize| {
+        let ((a, expected), (a_free, _)) = (with[step], without[step]);
+        let ((a0, _), (a0_free, _)) = (with[0], without[0]);
+        ((a - a_free) - (a0 - a0_free), expected - with[0].1)
+    };
+    for step in (0..steps).step_by((steps / 6).max(1)).chain([steps - 1]) {
+        let (decay, expected) = decay(step);
+        println!(
+            "step {:6}: decayed {:9.3} m, the air says {:9.3} m",
+            step,
+            decay * 1000.0,
+            expected * 1000.0
+        );
+    }
+    let (decay, expected) = decay(steps - 1);
+    let difference = decay / expected - 1.0;
+    println!("Off by {:.3}%", difference * 100.0);
+    assert!(
+        expected < 0.0 && difference.abs() < BOUND,
+        "The decay doesn't match the air"
+    );
+}
@@ -0,0 +1,139 @@
+//! Check that the attitude hold brings the ship back after a knock.
+//!
+//! The ship is put in `RcsMode::Hold`, which holds the attitude it was in,
+//! and then knocked into a tumble of about 4°/s.  The controller should
+//! stop it, and turn it back to the held attitude, using the thrusters as
+//! they are, with their limits.  The error from the held attitude is
+//! printed every ten seconds, with the largest it got to, and how long it
+//! took to settle (to within a degree, for good).  The ship has to settle
+//! within `SETTLE`, and end up within `ERROR` of the attitude, and still.
+//!
+//! Run with: cargo run --release -p sim-game --example hold
+//!
+//! `cargo test` runs it too, and fails if the hold doesn't.
+
+extern crate nalgebra as na;
+
+use bevy::{input::InputPlugin, prelude::*, time::TimeUpdateStrategy};
+use na::Vector3;
+use sim_astro::SolarState;
+use sim_core::{AttitudeIntegrator, AttitudeState, PostPhysicsSet};
+use sim_game::{
+    console::ConsolePlugin,
+    ship::{HoldAttitude, PlayerShip, RcsMode},
+    sim::SimPlugins,
+};
+use std::time::Duration;
+
+/// The length of a frame, in real seconds.
+const FRAME: f64 = 1.0 / 64.0;
+
+/// How much faster than real time it runs.
+const WARP: f64 = 50.0;
+
+/// The knock, in rad/s, BODY frame.
+const KNOCK: [f64; 3] = [0.05, -0.04, 0.03];
+
+/// How long, in seconds, the ship is watched after the knock.
+const DURATION: f64 = 60.0;
+
+/// How long, in seconds, it may take to settle to within a degree.
+const SETTLE: f64 = 15.0;
+
+/// How far, in degrees, it may end up from the attitude, and how fast, in
+/// rad/s, it may still be turning.
+const ERROR: f64 = 0.05;
+const RATE: f64 = 1.0e-4;
+
+/// The error from the held attitude, in degrees, and the rate, after each
+/// physics step.
+#[derive(Resource, Default)]
+struct Track(Vec<(f64, f64, f64)>);
+
+fn record(
+    time: Res<Time>,
+    mut track: ResMut<Track>,
+    ship: Query<(&AttitudeState, &HoldAttitude), With<PlayerShip>>,
+) {
+    if let Ok((attitude, HoldAttitude(Some(held)))) = ship.single() {
+        let error = held.angle_to(&attitude.q_bw).to_degrees();
+        track
+            .0
+            .push((time.elapsed_secs_f64(), error, attitude.omega_b.norm()));
+    }
+}
+
+/// Run until the sim time gets to `until`.
+fn run_until(app: &mut App, until: f64) {
+    while app.world().resource::<Time<Fixed>>().elapsed_secs_f64() < until {
+        app.update();
+    }
+}
+
+fn main() {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin));
+    app.insert_resource(
+        SolarState::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../solar.json"))
+            .expect("Can't load solar.json"),
+    );
+    app.add_plugins(SimPlugins);
+    app.add_plugins(ConsolePlugin);
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        FRAME,
+    )));
+    app.init_resource::<Track>();
+    app.add_systems(FixedUpdate, record.after(PostPhysicsSet));
+    app.finish();
+    app.cleanup();
+    app.world_mut()
+        .resource_mut::<Time<Virtual>>()
+        .set_relative_speed_f64(WARP);
+
+    run_until(&mut app, 1.0);
+    *app.world_mut().resource_mut::<RcsMode>() = RcsMode::Hold;
+    run_until(&mut app, 2.0);
+
+    let world = app.world_mut();
+    let lead = world
+        .resource::<AttitudeIntegrator>()
+        .lead(world.resource::<Time<Fixed>>().timestep().as_secs_f64());
+    let mut attitude = world
+        .query_filtered::<&mut AttitudeState, With<PlayerShip>>()
+        .single_mut(world)
+        .expect("no ship");
+    let omega_b = attitude.omega_b + Vector3::from(KNOCK);
+    attitude.set_omega_b(&omega_b, lead);
+    let knocked = world.resource::<Time<Fixed>>().elapsed_secs_f64();
+    world.resource_mut::<Track>().0.clear();
+    run_until(&mut app, knocked + DURATION);
+
+    let track = app.world_mut().remove_resource::<Track>().unwrap().0;
+    let mut next = 0.0;
+    for (t, error, rate) in &track {
+        if *t - knocked >= next {
+            println!(
+                "t = {:5.1} s: {:8.3}°, {:.2e} rad/s",
+                t - knocked,
+                error,
+                rate
+            );
+            next += 10.0;
+        }
+    }
+    let peak = track.iter().map(|(_, error, _)| *error).fold(0.0, f64::max);
+    let settled = track
+        .iter()
+        .rfind(|(_, error, _)| *error > 1.0)
+        .map_or(0.0, |(t, _, _)| t - knocked);
+    let (_, error, rate) = *track.last().expect("no steps");
+    println!(
+        "Largest error {:.2}°, settled in {:.1} s, ending {:.4}° off, at {:.2e} rad/s",
+        peak, settled, error, rate
+    );
+    assert!(settled < SETTLE, "Took {:.1} s to settle", settled);
+    assert!(
+        error < ERROR && rate < RATE,
+        "Didn't settle on the attitude"
+    );
+}
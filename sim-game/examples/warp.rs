@@ -0,0 +1,168 @@
+//! Check that the controllers fly the same at any warp.
+//!
+//! The controllers run with the physics, at its fixed step, so the time
+//! warp (how many of those steps are run per frame) shouldn't change what
+//! the craft does at all.  This flies the ship through the same scripted
+//! sequence twice, once at 1x and once at 10x, and compares the two step by
+//! step.  The sequence exercises each kind of loop:
+//!
+//! - Attitude command, slewing with the pitch key held.
+//! - Hold, catching the attitude it was left in.
+//! - Prograde, with the pulsed thrusters.
+//! - Manual, with the stability assist integrating its trim.
+//! - Drag phasing, trimming the attitude to the wind.
+//!
+//! The frames are the same length in real time for both, so the 10x run
+//! takes ten physics steps a frame.  The changes of mode and key are made
+//! between frames, at times both runs stop at, so anything left over from
+//! the controllers being sampled by frame shows up as a difference.  The
+//! runs should agree exactly.
+//!
+//! Run with: cargo run --release -p sim-game --example warp
+//!
+//! `cargo test` runs it too, and fails if the runs part.
+
+use bevy::{input::InputPlugin, prelude::*, time::TimeUpdateStrategy};
+use sim_astro::SolarState;
+use sim_core::{AttitudeState, OrbitalBody, PostPhysicsSet};
+use sim_game::{
+    console::ConsolePlugin,
+    ship::{PlayerShip, RcsMode, rcs::RcsRealism, sas::StabilityAssist},
+    sim::SimPlugins,
+};
+use std::time::Duration;
+
+/// The length of a frame, in real seconds.
+const FRAME: f64 = 1.0 / 64.0;
+
+/// The sequence, as the sim time, in seconds, each part starts.
+const SEQUENCE: [(f64, Part); 6] = [
+    (0.0, Part::Slew),
+    (10.0, Part::Hold),
+    (60.0, Part::Prograde),
+    (150.0, Part::Assist),
+    (240.0, Part::Phasing),
+    (300.0, Part::End),
+];
+
+#[derive(Clone, Copy, Debug)]
+enum Part {
+    Slew,
+    Hold,
+    Prograde,
+    Assist,
+    Phasing,
+    End,
+}
+
+/// The ship's state after each physics step.
+#[derive(Resource, Default)]
+struct Track(Vec<(f64, OrbitalBody, AttitudeState)>);
+
+fn record(
+    time: Res<Time>,
+    mut track: ResMut<Track>,
+    ship: Query<(&OrbitalBody, &AttitudeState), With<PlayerShip>>,
+) {
+    if let Ok((orbital, attitude)) = ship.single() {
+        track
+            .0
+            .push((time.elapsed_secs_f64(), orbital.clone(), attitude.clone()));
+    }
+}
+
+/// Fly the sequence at the given warp.
+fn fly(warp: f64) -> Vec<(f64, OrbitalBody, AttitudeState)> {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin));
+    app.insert_resource(
+        SolarState::load(concat!(env!("CARGO_MANIFEST_DIR"), "/../solar.json"))
+            .expect("Can't load solar.json"),
+    );
+    app.add_plugins(SimPlugins);
+    app.add_plugins(ConsolePlugin);
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        FRAME,
+    )));
+    app.init_resource::<Track>();
+    app.add_systems(FixedUpdate, record.after(PostPhysicsSet));
+    app.finish();
+    app.cleanup();
+    app.world_mut()
+        .resource_mut::<Time<Virtual>>()
+        .set_relative_speed_f64(warp);
+
+    for (start, part) in SEQUENCE {
+        // Run up to the start of this part.
+        while app.world().resource::<Time<Fixed>>().elapsed_secs_f64() < start {
+            app.update();
+        }
+        let world = app.world_mut();
+        let mut keys = world.resource_mut::<ButtonInput<KeyCode>>();
+        keys.release(KeyCode::KeyW);
+        match part {
+            Part::Slew => {
+                keys.press(KeyCode::KeyW);
+                *world.resource_mut::<RcsMode>() = RcsMode::AttitudeCommand;
+            }
+            Part::Hold => *world.resource_mut::<RcsMode>() = RcsMode::Hold,
+            Part::Prograde => {
+                *world.resource_mut::<RcsRealism>() = RcsRealism::pulsed();
+                *world.resource_mut::<RcsMode>() = RcsMode::Prograde;
+            }
+            Part::Assist => {
+                *world.resource_mut::<RcsMode>() = RcsMode::Manual;
+                let mut sas = world
+                    .query_filtered::<&mut StabilityAssist, With<PlayerShip>>()
+                    .single_mut(world)
+                    .unwrap();
+                sas.set(true);
+            }
+            Part::Phasing => *world.resource_mut::<RcsMode>() = RcsMode::DragPhasing,
+            Part::End => {}
+        }
+    }
+    app.world_mut().remove_resource::<Track>().unwrap().0
+}
+
+fn main() {
+    let slow = fly(1.0);
+    let fast = fly(10.0);
+    println!(
+        "1x: {} steps, 10x: {} steps, to t = {} s",
+        slow.len(),
+        fast.len(),
+        SEQUENCE[SEQUENCE.len() - 1].0
+    );
+
+    let (mut pos, mut vel, mut angle, mut rate) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+    let mut first = None;
+    for ((t1, o1, a1), (t2, o2, a2)) in slow.iter().zip(&fast) {
+        assert_eq!(t1, t2, "The steps are out of step");
+        let diffs = (
+            (o1.pos - o2.pos).norm(),
+            (o1.vel - o2.vel).norm(),
+            (a1.q_bw.coords - a2.q_bw.coords).norm(),
+            (a1.omega_b - a2.omega_b).norm(),
+        );
+        pos = pos.max(diffs.0);
+        vel = vel.max(diffs.1);
+        angle = angle.max(diffs.2);
+        rate = rate.max(diffs.3);
+        if first.is_none() && diffs != (0.0, 0.0, 0.0, 0.0) {
+            first = Some(*t1);
+        }
+    }
+    println!(
+        "Largest differences: position {:e} km, velocity {:e} km/s, attitude {:e} (quaternion), \
+         rate {:e} rad/s",
+        pos, vel, angle, rate
+    );
+    match first {
+        None => println!("The runs agree at every step"),
+        Some(t) => {
+            println!("The runs part at t = {} s", t);
+            std::process::exit(1);
+        }
+    }
+}
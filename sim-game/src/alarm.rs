@@ -0,0 +1,310 @@
+//! Alarms on the mission clock.
+//!
+//! An alarm goes off at a time on the sim's clock: an ET, as `at` takes them
+//! (see `sequence`), a mission elapsed time, counted from the epoch the run
+//! started at, or a while before an event coming up (see `events`), such as
+//! five minutes before the burn of a lunar orbit insertion.  An event's time
+//! moves as the forecast does, so it is looked up again every frame, from the
+//! next event coming up that matches.
+//!
+//! Due alarms go off at the start of the first physics step at or after their
+//! time, as queued commands are run, each sending an `AlarmRang` for the UI,
+//! and saying so in the console.  Unless told not to, an alarm also drops out
+//! of warp, the frame's steps stopping at the one it went off in (see
+//! `sim_core::clock`), so that however high the warp, the sim is at the
+//! alarm's time, not past it.  An alarm goes off once, and is then gone.
+//! They are kept in snapshots.
+//!
+//! - `alarm`: the alarms set, numbered, with when each is due.
+//! - `alarm at <time> [<name...>]`: at an ET, an ISO date, or `+<seconds>`
+//!   from now.
+//! - `alarm met <time> [<name...>]`: at a mission elapsed time, in seconds or
+//!   as `[h:]m:s`.
+//! - `alarm before <seconds> <event>[:<text>] [<name...>]`: before the next
+//!   event of that kind (`periapsis`, `burn_start`, and so on, as `events`
+//!   names them), for a body, or a burn's label, with `<text>` in it, as in
+//!   `alarm before 300 burn_start:LOI`.
+//! - `alarm warp <n> on|off`: whether alarm `n` drops out of warp.
+//! - `alarm remove <n>`, `alarm clear`.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use sim_astro::SolarState;
+use sim_core::clock::SimClock;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, log_reply, parse_arg},
+    events::{EventKind, MissionEvents, parse_event},
+    oem::iso_date,
+    sequence::parse_time,
+};
+
+/// When an alarm goes off.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum AlarmTime {
+    /// At an ET.
+    At(f64),
+    /// `lead` seconds before the next event of `kind`, for a body, or a burn,
+    /// with `text` in it.
+    Before {
+        lead: f64,
+        kind: EventKind,
+        text: Option<String>,
+    },
+}
+
+/// An alarm set.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Alarm {
+    pub name: String,
+    pub when: AlarmTime,
+    /// Whether it drops out of warp.
+    pub drop_warp: bool,
+    /// When it is due, in ET, if known: an event's, as last forecast.
+    pub due: Option<f64>,
+}
+
+impl Alarm {
+    /// What it goes off at, for listing.
+    pub fn describe(&self) -> String {
+        match &self.when {
+            AlarmTime::At(et) => iso_date(*et),
+            AlarmTime::Before { lead, kind, text } => {
+                let text = text.as_ref().map_or(String::new(), |t| format!(":{}", t));
+                format!("{:.0} s before {}{}", lead, kind.name(), text)
+            }
+        }
+    }
+}
+
+/// The alarms set, in the order they were set.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Alarms(pub Vec<Alarm>);
+
+impl Alarms {
+    /// The alarms, with when they are due, soonest first, and those not yet
+    /// known last.
+    pub fn upcoming(&self) -> Vec<&Alarm> {
+        let mut alarms: Vec<&Alarm> = self.0.iter().collect();
+        alarms.sort_by(|a, b| {
+            let due = |alarm: &Alarm| alarm.due.unwrap_or(f64::INFINITY);
+            due(a).total_cmp(&due(b))
+        });
+        alarms
+    }
+}
+
+/// An alarm going off.
+#[derive(Clone, Debug, Message)]
+pub struct AlarmRang {
+    pub alarm: Alarm,
+    /// When it went off, in ET.
+    pub et: f64,
+}
+
+#[derive(Default)]
+pub struct AlarmPlugin;
+
+impl Plugin for AlarmPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Alarms>();
+        app.add_message::<AlarmRang>();
+        app.add_systems(FixedPreUpdate, ring_alarms);
+        app.add_systems(Update, forecast_alarms);
+        app.add_console_command(
+            "alarm",
+            "alarm [at <time> | met <time> | before <s> <event>[:<text>]] [<name...>] | warp <n> \
+             on|off | remove <n> | clear   alarms on the mission clock",
+            alarm_command,
+        );
+    }
+}
+
+/// When the alarms set before events are due, from the forecast.
+fn forecast_alarms(mut alarms: ResMut<Alarms>, events: MissionEvents) {
+    if !alarms
+        .0
+        .iter()
+        .any(|alarm| matches!(alarm.when, AlarmTime::Before { .. }))
+    {
+        return;
+    }
+    let forecast = events.forecast();
+    for alarm in alarms.0.iter_mut() {
+        let AlarmTime::Before { lead, kind, text } = &alarm.when else {
+            continue;
+        };
+        let next = forecast
+            .iter()
+            .find(|event| event.matches(*kind, text.as_deref()));
+        // Failing a forecast, as it was, so that one just gone still goes
+        // off.
+        if let Some(next) = next {
+            alarm.due = Some(next.et - lead);
+        }
+    }
+}
+
+/// Set off the alarms due by the start of this step.
+fn ring_alarms(world: &mut World) {
+    let fixed = world.resource::<Time<Fixed>>();
+    let start = fixed.elapsed_secs_f64() - fixed.delta_secs_f64();
+    let et = world.resource::<SolarState>().et + start;
+    let mut alarms = world.resource_mut::<Alarms>();
+    if !alarms
+        .0
+        .iter()
+        .any(|alarm| alarm.due.is_some_and(|due| due <= et))
+    {
+        return;
+    }
+    let (rung, left): (Vec<Alarm>, Vec<Alarm>) = alarms
+        .0
+        .drain(..)
+        .partition(|alarm| alarm.due.is_some_and(|due| due <= et));
+    alarms.0 = left;
+
+    if rung.iter().any(|alarm| alarm.drop_warp) {
+        world
+            .resource_mut::<Time<Virtual>>()
+            .set_relative_speed_f64(1.0);
+        world.resource_mut::<SimClock>().halt();
+    }
+    for alarm in rung {
+        warn!("Alarm: {}", alarm.name);
+        log_reply(
+            world,
+            &format!("alarm {}", alarm.describe()),
+            Ok(format!("ALARM: {}", alarm.name)),
+        );
+        world.write_message(AlarmRang {
+            alarm: alarm.clone(),
+            et,
+        });
+    }
+}
+
+/// A mission elapsed time, in seconds, or as `[h:]m:s`.
+pub fn parse_met(text: &str) -> Result<f64, String> {
+    text.split(':')
+        .try_fold(0.0, |total, part| Ok(total * 60.0 + parse_arg(part)?))
+}
+
+/// A mission elapsed time, as `T+h:mm:ss`.
+pub fn format_met(met: f64) -> String {
+    let sign = if met < 0.0 { "-" } else { "+" };
+    let seconds = met.abs().round() as u64;
+    format!(
+        "T{}{}:{:02}:{:02}",
+        sign,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn alarm_command(
+    In(args): In<Vec<String>>,
+    mut alarms: ResMut<Alarms>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+) -> ConsoleReply {
+    let now = solar.et + fixed.elapsed_secs_f64();
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    let name = |words: &[&str], when: &AlarmTime| {
+        if words.is_empty() {
+            match when {
+                AlarmTime::At(et) => format!("Alarm at {}", format_met(et - solar.et)),
+                AlarmTime::Before { kind, text, .. } => match text {
+                    Some(text) => format!("Alarm before {} {}", text, kind.name()),
+                    None => format!("Alarm before {}", kind.name()),
+                },
+            }
+        } else {
+            words.join(" ")
+        }
+    };
+    let numbered = |alarms: &Alarms, n: &str| -> Result<usize, String> {
+        let n = parse_arg(n)? as usize;
+        if n == 0 || n > alarms.0.len() {
+            return Err(format!("There is no alarm {}", n));
+        }
+        Ok(n - 1)
+    };
+    let (when, rest) = match words.as_slice() {
+        [] => {
+            if alarms.0.is_empty() {
+                return Ok("No alarms".to_string());
+            }
+            return Ok(alarms
+                .0
+                .iter()
+                .enumerate()
+                .map(|(i, alarm)| {
+                    let due = alarm.due.map_or("not forecast".to_string(), |due| {
+                        format!("{}, in {:.0} s", format_met(due - solar.et), due - now)
+                    });
+                    let warp = if alarm.drop_warp { "" } else { ", in warp" };
+                    format!(
+                        "{}: {} ({}: {}{})",
+                        i + 1,
+                        alarm.name,
+                        alarm.describe(),
+                        due,
+                        warp
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+        ["clear"] => {
+            alarms.0.clear();
+            return Ok("ok".to_string());
+        }
+        ["remove", n] => {
+            let i = numbered(&alarms, n)?;
+            let alarm = alarms.0.remove(i);
+            return Ok(format!("removed {}", alarm.name));
+        }
+        ["warp", n, on @ ("on" | "off")] => {
+            let i = numbered(&alarms, n)?;
+            alarms.0[i].drop_warp = *on == "on";
+            let alarm = &alarms.0[i];
+            return Ok(if alarm.drop_warp {
+                format!("{} drops out of warp", alarm.name)
+            } else {
+                format!("{} leaves the warp as it is", alarm.name)
+            });
+        }
+        ["at", time, rest @ ..] => (AlarmTime::At(parse_time(time, now)?), rest),
+        ["met", time, rest @ ..] => (AlarmTime::At(solar.et + parse_met(time)?), rest),
+        ["before", lead, event, rest @ ..] => {
+            let lead = parse_arg(lead)?;
+            let (kind, text) = parse_event(event)?;
+            (AlarmTime::Before { lead, kind, text }, rest)
+        }
+        _ => {
+            return Err(
+                "alarm [at <time> | met <time> | before <s> <event>[:<text>]] \
+                        [<name...>] | warp <n> on|off | remove <n> | clear"
+                    .to_string(),
+            );
+        }
+    };
+    let due = match when {
+        AlarmTime::At(et) => Some(et),
+        AlarmTime::Before { .. } => None,
+    };
+    let alarm = Alarm {
+        name: name(rest, &when),
+        when,
+        drop_warp: true,
+        due,
+    };
+    let reply = match alarm.due {
+        Some(due) => format!("{} at {}, in {:.0} s", alarm.name, iso_date(due), due - now),
+        None => format!("{}, {}, once it is forecast", alarm.name, alarm.describe()),
+    };
+    alarms.0.push(alarm);
+    Ok(reply)
+}
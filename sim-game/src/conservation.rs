@@ -0,0 +1,166 @@
+//! Conservation diagnostics.
+//!
+//! Left to themselves, the massive bodies keep their total energy and angular
+//! momentum; how far those wander shows how well the integrator is doing, so
+//! this is the thing to watch when comparing integrators or step sizes, or
+//! looking for a regression.  The bodies' masses are only known as GMs, so
+//! both are divided through by G: the energy is in km^5/s^4, and the angular
+//! momentum in km^5/s^3.  The drift is the change since the first
+//! measurement, relative to it, and starts again whenever the bodies come or
+//! go.
+//!
+//! The player ship's rotational energy and angular momentum are kept as well,
+//! which should hold still whenever nothing is turning it.
+//!
+//! These are Bevy diagnostics, measured each frame, and can also be written to
+//! a CSV file, one row per frame, with `--conservation <file.csv>`.
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+use na::Vector3;
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, watchdog::Frozen};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::ship::{MassProperties, PlayerShip};
+
+#[derive(Default)]
+pub struct ConservationPlugin {
+    log: Option<File>,
+}
+
+impl ConservationPlugin {
+    /// The bodies' total energy, over G, in km^5/s^4.
+    pub const ENERGY: DiagnosticPath = DiagnosticPath::const_new("sim/energy");
+
+    /// The magnitude of the bodies' total angular momentum, about the
+    /// barycenter, over G, in km^5/s^3.
+    pub const ANGULAR_MOMENTUM: DiagnosticPath = DiagnosticPath::const_new("sim/angular_momentum");
+
+    /// The relative change in the energy since the first measurement.
+    pub const ENERGY_DRIFT: DiagnosticPath = DiagnosticPath::const_new("sim/energy_drift");
+
+    /// The relative change in the angular momentum since the first
+    /// measurement.
+    pub const MOMENTUM_DRIFT: DiagnosticPath = DiagnosticPath::const_new("sim/momentum_drift");
+
+    /// The player ship's rotational energy, in J.
+    pub const SHIP_ROTATIONAL_ENERGY: DiagnosticPath =
+        DiagnosticPath::const_new("sim/ship_rotational_energy");
+
+    /// The magnitude of the player ship's angular momentum, in kg*m^2/s.
+    pub const SHIP_ANGULAR_MOMENTUM: DiagnosticPath =
+        DiagnosticPath::const_new("sim/ship_angular_momentum");
+
+    /// Also write every measurement to a CSV file at `path`.
+    pub fn with_log<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Ok(ConservationPlugin {
+            log: Some(File::create(path)?),
+        })
+    }
+}
+
+/// The first measurement, which the drift is measured from, and how many
+/// bodies it was of.
+#[derive(Resource, Default)]
+struct ConservationBaseline(Option<(usize, f64, f64)>);
+
+#[derive(Resource)]
+struct ConservationLog(BufWriter<File>);
+
+impl Plugin for ConservationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::ENERGY))
+            .register_diagnostic(Diagnostic::new(Self::ANGULAR_MOMENTUM))
+            .register_diagnostic(Diagnostic::new(Self::ENERGY_DRIFT))
+            .register_diagnostic(Diagnostic::new(Self::MOMENTUM_DRIFT))
+            .register_diagnostic(Diagnostic::new(Self::SHIP_ROTATIONAL_ENERGY).with_suffix("J"))
+            .register_diagnostic(Diagnostic::new(Self::SHIP_ANGULAR_MOMENTUM));
+        app.init_resource::<ConservationBaseline>();
+        if let Some(file) = &self.log {
+            let file = file
+                .try_clone()
+                .expect("Unable to use the conservation log");
+            let mut out = BufWriter::new(file);
+            if let Err(e) = writeln!(
+                out,
+                "time_s,energy,angular_momentum,energy_drift,momentum_drift,ship_energy_j,ship_momentum"
+            ) {
+                error!("Unable to write the conservation log: {}", e);
+            }
+            app.insert_resource(ConservationLog(out));
+        }
+        app.add_systems(Last, measure_conservation);
+    }
+}
+
+/// The total energy and angular momentum, both over G, of the bodies.
+pub fn totals<'a>(bodies: impl Iterator<Item = (&'a MassiveBody, &'a OrbitalBody)>) -> (f64, f64) {
+    let bodies: Vec<_> = bodies.collect();
+    let mut energy = 0.0;
+    let mut momentum = Vector3::zeros();
+    for (i, (massive, orbital)) in bodies.iter().enumerate() {
+        energy += 0.5 * massive.gm * orbital.vel.norm_squared();
+        momentum += orbital.pos.cross(&orbital.vel) * massive.gm;
+        for (other, other_orbital) in &bodies[i + 1..] {
+            energy -= massive.gm * other.gm / (orbital.pos - other_orbital.pos).norm();
+        }
+    }
+    (energy, momentum.norm())
+}
+
+fn measure_conservation(
+    time: Res<Time<Fixed>>,
+    mut baseline: ResMut<ConservationBaseline>,
+    log: Option<ResMut<ConservationLog>>,
+    mut diagnostics: Diagnostics,
+    bodies: Query<(&MassiveBody, &OrbitalBody), Without<Frozen>>,
+    ship: Query<(&AttitudeState, &MassProperties), With<PlayerShip>>,
+) {
+    let count = bodies.iter().count();
+    let (energy, momentum) = totals(bodies.iter());
+    let (_, energy_0, momentum_0) = match baseline.0 {
+        Some(first) if first.0 == count => first,
+        _ => *baseline.0.insert((count, energy, momentum)),
+    };
+    let energy_drift = (energy - energy_0) / energy_0.abs().max(f64::MIN_POSITIVE);
+    let momentum_drift = (momentum - momentum_0) / momentum_0.max(f64::MIN_POSITIVE);
+    let (ship_energy, ship_momentum) = ship.single().map_or((0.0, 0.0), |(attitude, mass)| {
+        (
+            attitude.rotational_energy(&mass.inertia_b),
+            attitude.angular_momentum(&mass.inertia_b),
+        )
+    });
+
+    diagnostics.add_measurement(&ConservationPlugin::ENERGY, || energy);
+    diagnostics.add_measurement(&ConservationPlugin::ANGULAR_MOMENTUM, || momentum);
+    diagnostics.add_measurement(&ConservationPlugin::ENERGY_DRIFT, || energy_drift);
+    diagnostics.add_measurement(&ConservationPlugin::MOMENTUM_DRIFT, || momentum_drift);
+    diagnostics.add_measurement(&ConservationPlugin::SHIP_ROTATIONAL_ENERGY, || ship_energy);
+    diagnostics.add_measurement(&ConservationPlugin::SHIP_ANGULAR_MOMENTUM, || ship_momentum);
+
+    if let Some(mut log) = log {
+        let row = [
+            time.elapsed_secs_f64(),
+            energy,
+            momentum,
+            energy_drift,
+            momentum_drift,
+            ship_energy,
+            ship_momentum,
+        ];
+        let line = row
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        if let Err(e) = writeln!(log.0, "{}", line).and_then(|()| log.0.flush()) {
+            error!("Unable to write the conservation log: {}", e);
+        }
+    }
+}
@@ -0,0 +1,502 @@
+//! A command console.
+//!
+//! Lines typed (or piped) into the sim's stdin, or into the overlay (see
+//! `sim_ui`), are run as commands, and the results printed to stdout, one line
+//! each, so that scripts and external tools can drive it.  The results are also
+//! kept in the `ConsoleLog`, for the overlay to show.
+//!
+//! The commands are a registry: a plugin adds its own with
+//! `ConsoleApp::add_console_command`, as a system that takes the words after
+//! the command's name, and gives back its reply.  `help` lists them all.  The
+//! console itself has these:
+//!
+//! - `state <entity> <frame>`: the entity's position, velocity, and attitude in
+//!   a frame, such as `state playership lvlh:moon`.  See `sim_astro::frames`.
+//! - `warp [factor]`: run the sim at `factor` times real time, or show the
+//!   current factor.
+//! - `steps [budget]`: the most physics steps a frame runs, past which the
+//!   sim goes in slow motion, or set it.  See `sim_core::clock`.
+//! - `pause [on|off]`: stop the sim's time, or start it again, or show
+//!   whether it is stopped.
+//! - `step [count]`: pause, and run one physics step, or `count` of them,
+//!   in the next frame.  With the `translation`, `attitude` and `autopilots`
+//!   models, this is for watching the sim a step at a time.
+//! - `teleport <body> <orbit>`: put the ship in an orbit about a body, where
+//!   the orbit is `<periapsis> [apoapsis] [inclination] [raan] [argp]
+//!   [anomaly]`, with the apsides as altitudes above the body's equator, in km,
+//!   and the angles in degrees.  See `ShipOrbit::from_elements`.
+//! - `spawn <name> <gm> <radius> <body> <orbit>`: add a body, with a GM in
+//!   km^3/s^2 and a radius in km, in an orbit, as for `teleport`.
+//! - `model [<name> on|off]`: list the physics models, or switch one on or
+//!   off.  See `sim_core::PhysicsModels`.
+//! - `rotation [leapfrog|pcdm|rk4] [renormalize every|never|<tolerance>]`:
+//!   show, or switch, how the rotation is integrated.  See
+//!   `sim_core::AttitudeIntegrator`.
+//!
+//! The rest are with the things they drive, such as `autopilot` in
+//! `ship::autopilot`, or `dump` in `snapshot`.
+
+use bevy::{ecs::system::SystemId, prelude::*};
+use na::{UnitQuaternion, Vector3};
+use sim_astro::{contact::Landed, frames::Frames};
+use sim_core::{
+    AttitudeIntegrator, AttitudeState, MassiveBody, OrbitalBody, PhysicsModels, Renormalize,
+    RotationScheme, SizedBody, clock::SimClock, watchdog::Frozen,
+};
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Mutex,
+        mpsc::{Receiver, channel},
+    },
+};
+
+use crate::ship::{PlayerShip, ShipOrbit};
+
+/// How many lines the `ConsoleLog` keeps.
+const LOG_LINES: usize = 200;
+
+/// What a command gives back: the text to show (which may be empty, or several
+/// lines), or what went wrong.
+pub type ConsoleReply = Result<String, String>;
+
+/// The system behind a console command.
+pub type ConsoleSystem = SystemId<In<Vec<String>>, ConsoleReply>;
+
+#[derive(Resource)]
+pub struct ConsoleInput(Mutex<Receiver<String>>);
+
+/// The console lines to run this frame.  These are gathered in `PreUpdate`, so
+/// that a recording can capture (or replace) them.
+#[derive(Resource, Default)]
+pub struct ConsoleLines(pub Vec<String>);
+
+/// The commands run, and their replies, most recent last.
+#[derive(Resource, Default)]
+pub struct ConsoleLog(pub Vec<String>);
+
+impl ConsoleLog {
+    fn push(&mut self, line: String) {
+        self.0.push(line);
+        let excess = self.0.len().saturating_sub(LOG_LINES);
+        self.0.drain(..excess);
+    }
+}
+
+/// A registered command.
+pub struct ConsoleCommand {
+    /// The command's line in `help`, such as `warp [factor]`.
+    pub usage: &'static str,
+    pub system: ConsoleSystem,
+}
+
+/// Every command the console knows, by name.
+#[derive(Resource, Default)]
+pub struct ConsoleCommands(pub BTreeMap<&'static str, ConsoleCommand>);
+
+pub trait ConsoleApp {
+    /// Add a command to the console.  The system is given the words after the
+    /// name, and its reply is shown.  A command of the same name is replaced.
+    fn add_console_command<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        system: impl IntoSystem<In<Vec<String>>, ConsoleReply, M> + 'static,
+    ) -> &mut Self;
+}
+
+impl ConsoleApp for App {
+    fn add_console_command<M>(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        system: impl IntoSystem<In<Vec<String>>, ConsoleReply, M> + 'static,
+    ) -> &mut Self {
+        let world = self.world_mut();
+        let system = world.register_system(system);
+        world
+            .get_resource_or_init::<ConsoleCommands>()
+            .0
+            .insert(name, ConsoleCommand { usage, system });
+        self
+    }
+}
+
+/// Parse a number from a command's words.
+pub fn parse_arg(arg: &str) -> Result<f64, String> {
+    arg.parse()
+        .map_err(|e| format!("{:?} is not a number: {}", arg, e))
+}
+
+#[derive(Default)]
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        app.insert_resource(ConsoleInput(Mutex::new(rx)));
+        app.init_resource::<ConsoleLines>();
+        app.init_resource::<ConsoleLog>();
+        app.init_resource::<ConsoleCommands>();
+        app.add_console_command("help", "help   this list", help);
+        app.add_console_command(
+            "state",
+            "state <entity> <kind:center>   kind is j2000, fixed, or lvlh",
+            state,
+        );
+        app.add_console_command("warp", "warp [factor]   run faster than real time", warp);
+        app.add_console_command(
+            "steps",
+            "steps [budget]   the most physics steps a frame runs",
+            steps,
+        );
+        app.add_console_command(
+            "pause",
+            "pause [on|off]   stop, or start, the sim's time",
+            pause,
+        );
+        app.add_console_command(
+            "step",
+            "step [count]   pause, and run a step, or count",
+            step,
+        );
+        app.add_console_command(
+            "teleport",
+            "teleport <body> <peri km> [apo km] [inc] [raan] [argp] [anomaly]   move the ship",
+            teleport,
+        );
+        app.add_console_command(
+            "spawn",
+            "spawn <name> <gm> <radius km> <body> <peri km> [apo km] [inc] [raan] [argp] \
+             [anomaly]   add a body",
+            spawn,
+        );
+        app.add_console_command(
+            "model",
+            "model [<name> on|off]   list, or switch, the physics models",
+            model,
+        );
+        app.add_console_command(
+            "rotation",
+            "rotation [leapfrog|pcdm|rk4] [renormalize every|never|<tolerance>]   show, or \
+             switch, the rotational integrator",
+            rotation,
+        );
+        app.add_systems(PreUpdate, read_console);
+        app.add_systems(Update, run_console);
+    }
+}
+
+pub fn read_console(input: Res<ConsoleInput>, mut lines: ResMut<ConsoleLines>) {
+    if let Ok(rx) = input.0.lock() {
+        lines.0.extend(rx.try_iter());
+    }
+}
+
+/// Run the lines that came in this frame.
+pub fn run_console(world: &mut World) {
+    let lines = std::mem::take(&mut world.resource_mut::<ConsoleLines>().0);
+    for line in lines {
+        if line.split_whitespace().next().is_none() {
+            continue;
+        }
+        let reply = run_line(world, &line);
+        log_reply(world, &line, reply);
+    }
+}
+
+/// Run a command line, as typed.
+pub fn run_line(world: &mut World, line: &str) -> ConsoleReply {
+    let words: Vec<String> = line.split_whitespace().map(String::from).collect();
+    let Some((name, args)) = words.split_first() else {
+        return Ok(String::new());
+    };
+    let system = world
+        .resource::<ConsoleCommands>()
+        .0
+        .get(name.as_str())
+        .map(|command| command.system);
+    match system {
+        Some(system) => world
+            .run_system_with(system, args.to_vec())
+            .unwrap_or_else(|e| Err(e.to_string())),
+        None => Err(format!("unknown command {:?}, try help", line)),
+    }
+}
+
+/// Print a command line's reply, and keep them both in the `ConsoleLog`, if
+/// there is one.
+pub fn log_reply(world: &mut World, line: &str, reply: ConsoleReply) {
+    let mut log = world.get_resource_mut::<ConsoleLog>();
+    if let Some(log) = log.as_mut() {
+        log.push(format!("> {}", line));
+    }
+    let text = reply.unwrap_or_else(|e| format!("error: {}", e));
+    for text in text.lines() {
+        println!("{}", text);
+        if let Some(log) = log.as_mut() {
+            log.push(text.to_string());
+        }
+    }
+}
+
+fn help(_: In<Vec<String>>, commands: Res<ConsoleCommands>) -> ConsoleReply {
+    Ok(commands
+        .0
+        .values()
+        .map(|command| command.usage)
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+fn state(In(args): In<Vec<String>>, frames: Frames) -> ConsoleReply {
+    let [entity, frame] = args.as_slice() else {
+        return Err("state <entity> <kind:center>".to_string());
+    };
+    let entity = frames
+        .find(entity)
+        .ok_or_else(|| format!("No such entity: {:?}", entity))?;
+    let state = frames.state(entity, frames.parse(frame)?)?;
+    let mut text = format!(
+        "pos {} {} {} km vel {} {} {} km/s",
+        state.pos.x, state.pos.y, state.pos.z, state.vel.x, state.vel.y, state.vel.z
+    );
+    if let Some(q) = state.q_bf {
+        text += &format!(" q {} {} {} {}", q.w, q.i, q.j, q.k);
+    }
+    Ok(text)
+}
+
+fn warp(In(args): In<Vec<String>>, mut time: ResMut<Time<Virtual>>) -> ConsoleReply {
+    match args.as_slice() {
+        [] => (),
+        [factor] => {
+            let factor = parse_arg(factor)?;
+            if !factor.is_finite() || factor <= 0.0 {
+                return Err("The warp must be more than zero".to_string());
+            }
+            time.set_relative_speed_f64(factor);
+        }
+        _ => return Err("warp [factor]".to_string()),
+    }
+    Ok(format!("warp {}", time.relative_speed_f64()))
+}
+
+fn steps(In(args): In<Vec<String>>, mut clock: ResMut<SimClock>) -> ConsoleReply {
+    match args.as_slice() {
+        [] => (),
+        [budget] => {
+            let budget = parse_arg(budget)?;
+            if budget < 1.0 || budget.fract() != 0.0 {
+                return Err("The budget must be a whole number of steps".to_string());
+            }
+            clock.budget = budget as u64;
+        }
+        _ => return Err("steps [budget]".to_string()),
+    }
+    Ok(format!(
+        "steps {}, {} dropped last frame",
+        clock.budget, clock.dropped
+    ))
+}
+
+fn pause(In(args): In<Vec<String>>, mut time: ResMut<Time<Virtual>>) -> ConsoleReply {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        [] => (),
+        ["on"] => time.pause(),
+        ["off"] => time.unpause(),
+        _ => return Err("pause [on|off]".to_string()),
+    }
+    Ok(format!(
+        "pause {}",
+        if time.is_paused() { "on" } else { "off" }
+    ))
+}
+
+fn step(
+    In(args): In<Vec<String>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut clock: ResMut<SimClock>,
+) -> ConsoleReply {
+    let count = match args.as_slice() {
+        [] => 1.0,
+        [count] => parse_arg(count)?,
+        _ => return Err("step [count]".to_string()),
+    };
+    if count < 1.0 || count.fract() != 0.0 {
+        return Err("The count must be a whole number of steps".to_string());
+    }
+    // Paused, a frame's own time is nothing, so it runs just these.
+    time.pause();
+    clock.take(count as u64);
+    Ok(format!("step {}, from tick {}", count as u64, clock.ticks))
+}
+
+/// A body to put things in orbit about.
+pub(crate) type Primaries<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Name,
+        &'static OrbitalBody,
+        &'static AttitudeState,
+        &'static SizedBody,
+        &'static MassiveBody,
+    ),
+>;
+
+/// The world-frame state for an orbit given as `<body> <periapsis> [apoapsis]
+/// [inclination] [raan] [argp] [anomaly]`, with the apsides as altitudes above
+/// the body's equator, in km, and the angles in degrees.  A missing apoapsis is
+/// the periapsis, and a missing angle is zero.
+pub(crate) fn orbit_state(primaries: &Primaries, args: &[String]) -> Result<OrbitalBody, String> {
+    let [body, elements @ ..] = args else {
+        return Err("The orbit needs a body".to_string());
+    };
+    if elements.is_empty() || elements.len() > 6 {
+        return Err(
+            "The orbit is <periapsis> [apoapsis] [inc] [raan] [argp] [anomaly]".to_string(),
+        );
+    }
+    let (_, center, attitude, size, massive) = primaries
+        .iter()
+        .find(|(name, ..)| name.as_str().eq_ignore_ascii_case(body))
+        .ok_or_else(|| format!("No such body: {:?}", body))?;
+    let elements = elements
+        .iter()
+        .map(|e| parse_arg(e))
+        .collect::<Result<Vec<_>, _>>()?;
+    let element = |i: usize| elements.get(i).copied().unwrap_or(0.0);
+
+    let radius = size.radii.x;
+    let periapsis = radius + element(0);
+    let apoapsis = elements.get(1).map_or(periapsis, |apo| radius + apo);
+    if periapsis <= 0.0 || apoapsis < periapsis {
+        return Err("The apoapsis must be at or above the periapsis".to_string());
+    }
+    let pole = attitude.q_bw.transform_vector(&Vector3::z());
+    let orbit = ShipOrbit::from_elements(
+        &pole,
+        periapsis,
+        apoapsis,
+        element(2).to_radians(),
+        element(3).to_radians(),
+        element(4).to_radians(),
+        element(5).to_radians(),
+    );
+    let (pos, vel) = orbit.state(massive.gm);
+    Ok(OrbitalBody {
+        pos: center.pos + pos,
+        vel: center.vel + vel,
+    })
+}
+
+#[allow(clippy::type_complexity)]
+fn teleport(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    primaries: Primaries,
+    mut ship: Query<(Entity, &mut OrbitalBody), (With<PlayerShip>, Without<MassiveBody>)>,
+) -> ConsoleReply {
+    let orbital = orbit_state(&primaries, &args)?;
+    let (entity, mut ship) = ship.single_mut().map_err(|_| "no ship".to_string())?;
+    *ship = orbital;
+    commands.entity(entity).remove::<(Landed, Frozen)>();
+    Ok("ok".to_string())
+}
+
+fn spawn(In(args): In<Vec<String>>, mut commands: Commands, primaries: Primaries) -> ConsoleReply {
+    let [name, gm, radius, orbit @ ..] = args.as_slice() else {
+        return Err("spawn <name> <gm> <radius> <body> <orbit>".to_string());
+    };
+    if primaries
+        .iter()
+        .any(|(n, ..)| n.as_str().eq_ignore_ascii_case(name))
+    {
+        return Err(format!("There is already a {:?}", name));
+    }
+    let gm = parse_arg(gm)?;
+    let radius = parse_arg(radius)?;
+    let orbital = orbit_state(&primaries, orbit)?;
+    commands.spawn((
+        Name::new(name.clone()),
+        orbital,
+        AttitudeState {
+            q_bw: UnitQuaternion::identity(),
+            omega_b: Vector3::zeros(),
+        },
+        SizedBody {
+            radii: Vector3::repeat(radius),
+        },
+        MassiveBody { gm },
+    ));
+    Ok("ok".to_string())
+}
+
+fn model(In(args): In<Vec<String>>, mut models: ResMut<PhysicsModels>) -> ConsoleReply {
+    let on = match args.as_slice() {
+        [] => {
+            return Ok(models
+                .0
+                .iter()
+                .map(|(name, on)| format!("{} {}", name, if *on { "on" } else { "off" }))
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+        [_, on] if on == "on" => true,
+        [_, off] if off == "off" => false,
+        _ => return Err("model [<name> on|off]".to_string()),
+    };
+    let enabled = models
+        .0
+        .get_mut(args[0].as_str())
+        .ok_or_else(|| format!("No such model: {:?}, try model", args[0]))?;
+    *enabled = on;
+    Ok("ok".to_string())
+}
+
+fn rotation(In(args): In<Vec<String>>, mut integrator: ResMut<AttitudeIntegrator>) -> ConsoleReply {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (scheme, policy) = match words.as_slice() {
+        [] => (None, None),
+        ["renormalize", policy] => (None, Some(*policy)),
+        [scheme] => (Some(*scheme), None),
+        [scheme, "renormalize", policy] => (Some(*scheme), Some(*policy)),
+        _ => {
+            return Err(
+                "rotation [leapfrog|pcdm|rk4] [renormalize every|never|<tolerance>]".to_string(),
+            );
+        }
+    };
+    if let Some(scheme) = scheme {
+        integrator.scheme = RotationScheme::parse(scheme)?;
+    }
+    if let Some(policy) = policy {
+        integrator.renormalize = match policy {
+            "every" => Renormalize::EveryStep,
+            "never" => Renormalize::Never,
+            tolerance => Renormalize::Beyond(parse_arg(tolerance)?),
+        };
+    }
+    let renormalize = match integrator.renormalize {
+        Renormalize::EveryStep => "every step".to_string(),
+        Renormalize::Beyond(tolerance) => format!("beyond {:e}", tolerance),
+        Renormalize::Never => "never".to_string(),
+    };
+    Ok(format!(
+        "{}, renormalizing {}",
+        integrator.scheme.name(),
+        renormalize
+    ))
+}
@@ -0,0 +1,442 @@
+//! The launch countdown.
+//!
+//! A countdown is a schedule of steps, each at a count from T-0, that runs a
+//! console command when the count reaches it: by default, the engine lit
+//! `IGNITION_LEAD` before T-0, so that it is up to thrust, held by the
+//! clamps, by the time `pad release` lets go at T-0.  A step can instead be a
+//! hold point, where the count stops until it is resumed, as can the count
+//! be held by hand at any time before the engine is lit.  Holding moves T-0
+//! on, by as long as the hold.  Steps are run at the start of the first
+//! physics step at or after their count, as for the command queue (see
+//! `sequence`), so warp and the frame rate don't move them.
+//!
+//! The count is aborted by hand, or by itself if a step's command fails (such
+//! as an ascent the craft hasn't the thrust for), if the engine goes out once
+//! lit while the craft is still held down, or if the craft browns out.
+//! Aborting makes the craft safe: the engine is shut down, any ascent
+//! guidance dropped, and the clamps and umbilical put back on, so long as it
+//! is still on the ground.  Off the ground, there is nothing to abort to, and
+//! the count just stops.
+//!
+//! The countdown, schedule and all, is kept in snapshots, so a scenario can
+//! carry its own.
+//!
+//! - `countdown`: the count, and the schedule.
+//! - `countdown start <time>`: count down to T-0 at `time` (as for `at`).
+//! - `countdown hold`, `countdown resume`: stop the count, and start it again.
+//! - `countdown abort [reason...]`: stop the count, and make the craft safe.
+//! - `countdown step <count> <name> hold | <command...>`: add a step to the
+//!   schedule, or change the one of that name, such as `countdown step T-3
+//!   ignition ascent guided 200 51.6`.  A count is in seconds, or as
+//!   `[h:]m:s`, from T-0, negative before it.
+//! - `countdown remove <name>`: take a step off the schedule.
+//! - `countdown load <file>`: replace the schedule with the steps in a file,
+//!   one to a line, as for `countdown step`.  Blank lines, and those starting
+//!   with `#`, are skipped.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use sim_astro::{
+    SolarState,
+    contact::{Clamped, Landed},
+};
+
+use crate::{
+    alarm::{format_met, parse_met},
+    console::{ConsoleApp, ConsoleReply, log_reply, run_line},
+    oem::iso_date,
+    remote::RemoteControl,
+    sequence::parse_time,
+    ship::{PlayerShip, RcsMode, ascent::Ascent, engine::MainEngine, pad::Umbilical, power::Power},
+};
+
+/// How long before T-0, in seconds, the default schedule lights the engine.
+pub const IGNITION_LEAD: f64 = 3.0;
+
+/// A step in the countdown.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CountdownStep {
+    /// The count it is at, s from T-0, negative before it.
+    pub count: f64,
+    pub name: String,
+    /// The command line to run, as typed, or none for a hold point.
+    pub line: Option<String>,
+}
+
+impl CountdownStep {
+    pub fn describe(&self) -> String {
+        format!(
+            "{} {}: {}",
+            format_met(self.count),
+            self.name,
+            self.line.as_deref().unwrap_or("hold")
+        )
+    }
+}
+
+/// Where the count is.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum CountState {
+    /// Not started.
+    #[default]
+    Idle,
+    /// Counting down to T-0.
+    Counting,
+    /// Held, at the count, s from T-0.
+    Holding(f64),
+    /// Stopped, and the craft made safe, for the reason.
+    Aborted(String),
+    /// Every step has run.
+    Done,
+}
+
+/// The countdown, and its schedule.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct Countdown {
+    /// The steps, in the order of their counts.
+    pub schedule: Vec<CountdownStep>,
+    pub state: CountState,
+    /// T-0, ET, as it stands with the holds so far.
+    pub t0: f64,
+    /// How many of the steps have been run.
+    pub next: usize,
+    /// Whether the engine has been seen burning since the count started.
+    pub lit: bool,
+}
+
+impl Default for Countdown {
+    fn default() -> Self {
+        Countdown {
+            schedule: vec![
+                CountdownStep {
+                    count: -IGNITION_LEAD,
+                    name: "ignition".to_string(),
+                    line: Some("throttle 100".to_string()),
+                },
+                CountdownStep {
+                    count: 0.0,
+                    name: "release".to_string(),
+                    line: Some("pad release".to_string()),
+                },
+            ],
+            state: CountState::Idle,
+            t0: 0.0,
+            next: 0,
+            lit: false,
+        }
+    }
+}
+
+impl Countdown {
+    /// The count at `et`, s from T-0.
+    pub fn count(&self, et: f64) -> f64 {
+        match self.state {
+            CountState::Holding(count) => count,
+            _ => et - self.t0,
+        }
+    }
+
+    /// Whether it is counting, or held.
+    pub fn running(&self) -> bool {
+        matches!(self.state, CountState::Counting | CountState::Holding(_))
+    }
+
+    /// Add a step, or change the one of the same name, keeping the schedule
+    /// in order.
+    pub fn set_step(&mut self, step: CountdownStep) {
+        self.schedule.retain(|s| s.name != step.name);
+        let at = self.schedule.partition_point(|s| s.count <= step.count);
+        self.schedule.insert(at, step);
+    }
+}
+
+#[derive(Default)]
+pub struct CountdownPlugin;
+
+impl Plugin for CountdownPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Countdown>();
+        app.add_systems(FixedPreUpdate, run_countdown);
+        app.add_console_command(
+            "countdown",
+            "countdown [start <time> | hold | resume | abort [reason...] | step <count> <name> \
+             hold|<command...> | remove <name> | load <file>]   the launch countdown",
+            countdown_command,
+        );
+    }
+}
+
+/// Watch the craft, and run the steps due by the start of this step.
+fn run_countdown(world: &mut World) {
+    if world.resource::<Countdown>().state != CountState::Counting {
+        return;
+    }
+    let fixed = world.resource::<Time<Fixed>>();
+    let start = fixed.elapsed_secs_f64() - fixed.delta_secs_f64();
+    let et = world.resource::<SolarState>().et + start;
+
+    let mut ship =
+        world.query_filtered::<(&MainEngine, Option<&Power>, Has<Clamped>), With<PlayerShip>>();
+    let Ok((engine, power, clamped)) = ship.single(world) else {
+        return;
+    };
+    let burning = engine.throttle > 0.0;
+    let brownout = power.is_some_and(|p| p.brownout);
+    let lit = world.resource::<Countdown>().lit;
+    let fault = if brownout {
+        Some("browned out")
+    } else if lit && !burning && clamped {
+        Some("engine out before release")
+    } else {
+        None
+    };
+    if let Some(fault) = fault {
+        abort(world, fault);
+        return;
+    }
+    world.resource_mut::<Countdown>().lit |= burning;
+
+    loop {
+        let countdown = world.resource::<Countdown>();
+        let Some(step) = countdown.schedule.get(countdown.next).cloned() else {
+            world.resource_mut::<Countdown>().state = CountState::Done;
+            return;
+        };
+        if countdown.t0 + step.count > et {
+            return;
+        }
+        world.resource_mut::<Countdown>().next += 1;
+        let Some(line) = &step.line else {
+            world.resource_mut::<Countdown>().state = CountState::Holding(step.count);
+            log_reply(
+                world,
+                &format!("countdown {}", step.describe()),
+                Ok(format!("holding at {}", format_met(step.count))),
+            );
+            return;
+        };
+        let reply = run_line(world, line);
+        let failed = reply.as_ref().err().cloned();
+        log_reply(world, &format!("countdown {}", step.describe()), reply);
+        if let Some(error) = failed {
+            abort(world, &format!("{} failed: {}", step.name, error));
+            return;
+        }
+    }
+}
+
+/// Abort the count, by way of the command, so that the craft is made safe the
+/// one way.
+fn abort(world: &mut World, reason: &str) {
+    warn!("Countdown aborted: {}", reason);
+    let line = format!("countdown abort {}", reason);
+    let reply = run_line(world, &line);
+    log_reply(world, &line, reply);
+}
+
+/// Read a step, as `<count> <name> hold | <command...>`.
+fn parse_step(words: &[&str]) -> Result<CountdownStep, String> {
+    let [count, name, line @ ..] = words else {
+        return Err("countdown step <count> <name> hold | <command...>".to_string());
+    };
+    if line.is_empty() {
+        return Err(format!("The step {} has no command (or hold)", name));
+    }
+    Ok(CountdownStep {
+        count: parse_count(count)?,
+        name: name.to_string(),
+        line: (*line != ["hold"]).then(|| line.join(" ")),
+    })
+}
+
+/// A count from T-0, in seconds or `[h:]m:s`, negative before it, and with a
+/// `T` in front or not.
+fn parse_count(text: &str) -> Result<f64, String> {
+    let text = text.strip_prefix('T').unwrap_or(text);
+    match text.strip_prefix('-') {
+        Some(before) => Ok(-parse_met(before)?),
+        None => parse_met(text.strip_prefix('+').unwrap_or(text)),
+    }
+}
+
+/// Say where the count is, and what is on the schedule.
+fn countdown_status(countdown: &Countdown, now: f64) -> String {
+    let state = match &countdown.state {
+        CountState::Idle => "Not counting".to_string(),
+        CountState::Counting => format!(
+            "{}, T-0 at {}",
+            format_met(countdown.count(now)),
+            iso_date(countdown.t0)
+        ),
+        CountState::Holding(count) => format!("Holding at {}", format_met(*count)),
+        CountState::Aborted(reason) => format!("Aborted: {}", reason),
+        CountState::Done => "Done".to_string(),
+    };
+    let mut lines = vec![state];
+    for (i, step) in countdown.schedule.iter().enumerate() {
+        let done = countdown.state != CountState::Idle && i < countdown.next;
+        lines.push(format!(
+            "{} {}",
+            if done { "  done" } else { "      " },
+            step.describe()
+        ));
+    }
+    lines.join("\n")
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn countdown_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    mut countdown: ResMut<Countdown>,
+    mut mode: ResMut<RcsMode>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    remote: Res<RemoteControl>,
+    mut ship: Query<
+        (
+            Entity,
+            &mut MainEngine,
+            Has<Ascent>,
+            Has<Landed>,
+            Has<Clamped>,
+        ),
+        With<PlayerShip>,
+    >,
+) -> ConsoleReply {
+    let now = solar.et + fixed.elapsed_secs_f64();
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        [] => Ok(countdown_status(&countdown, now)),
+        ["start", time] => {
+            let (_, _, _, _, clamped) = ship.single().map_err(|_| "no ship".to_string())?;
+            if !clamped {
+                return Err("The craft isn't held on the pad (pad hold)".to_string());
+            }
+            if countdown.running() {
+                return Err("The count is already running (countdown abort)".to_string());
+            }
+            let t0 = parse_time(time, now)?;
+            let first = countdown.schedule.first().map_or(0.0, |step| step.count);
+            // As for `at`, flying remotely.
+            let reach = if remote.active() {
+                remote.arrival(now)
+            } else {
+                now
+            };
+            if t0 + first < reach {
+                return Err(format!(
+                    "T-0 is too soon: the first step is at {}",
+                    format_met(first)
+                ));
+            }
+            countdown.t0 = t0;
+            countdown.next = 0;
+            countdown.lit = false;
+            countdown.state = CountState::Counting;
+            Ok(format!("T-0 at {} (in {:.0} s)", iso_date(t0), t0 - now))
+        }
+        ["hold"] => {
+            if countdown.state != CountState::Counting {
+                return Err("The count isn't running".to_string());
+            }
+            if countdown.lit {
+                return Err(
+                    "The engine is lit: it is too late to hold (countdown abort)".to_string(),
+                );
+            }
+            let count = countdown.count(now);
+            countdown.state = CountState::Holding(count);
+            Ok(format!("holding at {}", format_met(count)))
+        }
+        ["resume"] => {
+            let CountState::Holding(count) = countdown.state else {
+                return Err("The count isn't held".to_string());
+            };
+            countdown.t0 = now - count;
+            countdown.state = CountState::Counting;
+            Ok(format!(
+                "resumed at {}, T-0 at {}",
+                format_met(count),
+                iso_date(countdown.t0)
+            ))
+        }
+        ["abort", reason @ ..] => {
+            if !countdown.running() {
+                return Err("The count isn't running".to_string());
+            }
+            let reason = if reason.is_empty() {
+                "by hand".to_string()
+            } else {
+                reason.join(" ")
+            };
+            countdown.state = CountState::Aborted(reason.clone());
+            let (entity, mut engine, flying, landed, clamped) =
+                ship.single_mut().map_err(|_| "no ship".to_string())?;
+            if !landed {
+                return Ok(format!("aborted ({}), with the craft off the pad", reason));
+            }
+            engine.throttle = 0.0;
+            if flying {
+                *mode = RcsMode::Hold;
+                commands.entity(entity).remove::<Ascent>();
+            }
+            if !clamped {
+                commands
+                    .entity(entity)
+                    .insert((Clamped, Umbilical::default()));
+            }
+            Ok(format!("aborted ({}), and the craft made safe", reason))
+        }
+        ["step", step @ ..] => {
+            if countdown.running() {
+                return Err("The schedule can't change with the count running".to_string());
+            }
+            let step = parse_step(step)?;
+            let reply = step.describe();
+            countdown.set_step(step);
+            Ok(reply)
+        }
+        ["remove", name] => {
+            if countdown.running() {
+                return Err("The schedule can't change with the count running".to_string());
+            }
+            let count = countdown.schedule.len();
+            countdown.schedule.retain(|step| step.name != *name);
+            if countdown.schedule.len() == count {
+                return Err(format!("There is no step {}", name));
+            }
+            Ok(format!("removed {}", name))
+        }
+        ["load", path] => {
+            if countdown.running() {
+                return Err("The schedule can't change with the count running".to_string());
+            }
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+            // Check it all before changing any of it.
+            let mut schedule = Countdown {
+                schedule: Vec::new(),
+                ..default()
+            };
+            for (number, line) in text.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let words: Vec<&str> = line.split_whitespace().collect();
+                let step =
+                    parse_step(&words).map_err(|e| format!("{}:{}: {}", path, number + 1, e))?;
+                schedule.set_step(step);
+            }
+            let count = schedule.schedule.len();
+            countdown.schedule = schedule.schedule;
+            Ok(format!("loaded {} steps", count))
+        }
+        _ => Err(
+            "countdown [start <time> | hold | resume | abort [reason...] | step <count> <name> \
+             hold|<command...> | remove <name> | load <file>]"
+                .to_string(),
+        ),
+    }
+}
@@ -0,0 +1,106 @@
+//! Keeping the clock inside the kernels' coverage.
+//!
+//! The solar system was taken from SPICE kernels, which only cover so many
+//! years.  The sim itself runs on without them, but anything looked up in
+//! the kernels past the end (such as `ephem now`) fails, and the ephemeris
+//! the sim has drifted to no longer has anything to agree with.  So, when the
+//! `SolarState` knows its coverage, the time warp is held down on the way to
+//! the end, so that it takes at least `COVERAGE_LEAD` real seconds to get
+//! there, and it says so; past the end, it warns.
+//!
+//! - `coverage`: the span covered, and how long is left.
+
+use bevy::prelude::*;
+use sim_astro::SolarState;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    oem::iso_date,
+};
+
+/// The least time, in real seconds, the warp is let to take to reach the end
+/// of the coverage.
+pub const COVERAGE_LEAD: f64 = 60.0;
+
+#[derive(Default)]
+pub struct CoveragePlugin;
+
+impl Plugin for CoveragePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, hold_in_coverage);
+        app.add_console_command(
+            "coverage",
+            "coverage   the span the kernels cover, and how long is left",
+            coverage_command,
+        );
+    }
+}
+
+/// Whether the clock was last held back, or outside the coverage.
+#[derive(Default, PartialEq)]
+enum Held {
+    #[default]
+    Free,
+    Warp,
+    Outside,
+}
+
+fn hold_in_coverage(
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut held: Local<Held>,
+) {
+    let Some((start, stop)) = solar.coverage else {
+        return;
+    };
+    let et = solar.et + fixed.elapsed_secs_f64();
+    if et < start || et > stop {
+        if *held != Held::Outside {
+            warn!(
+                "{} is outside the kernels' coverage, {} to {}",
+                iso_date(et),
+                iso_date(start),
+                iso_date(stop)
+            );
+            *held = Held::Outside;
+        }
+        return;
+    }
+
+    let most = ((stop - et) / COVERAGE_LEAD).max(1.0);
+    if time.relative_speed_f64() > most {
+        time.set_relative_speed_f64(most);
+        if *held != Held::Warp {
+            warn!(
+                "Holding the warp down, the kernels' coverage ends {}",
+                iso_date(stop)
+            );
+            *held = Held::Warp;
+        }
+    } else if time.relative_speed_f64() < most {
+        *held = Held::Free;
+    }
+}
+
+fn coverage_command(
+    _: In<Vec<String>>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+) -> ConsoleReply {
+    let Some((start, stop)) = solar.coverage else {
+        return Ok("coverage unknown".to_string());
+    };
+    let et = solar.et + fixed.elapsed_secs_f64();
+    let status = if et < start || et > stop {
+        "outside it".to_string()
+    } else {
+        format!("{:.1} days left", (stop - et) / 86400.0)
+    };
+    Ok(format!(
+        "kernels cover {} to {}, {}",
+        iso_date(start),
+        iso_date(stop),
+        status
+    ))
+}
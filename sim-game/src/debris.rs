@@ -0,0 +1,870 @@
+//! Debris clouds, from crafts that break up.
+//!
+//! A craft breaks up when it crashes into a body, when it is pushed harder
+//! than its `Structure` takes, when a fragment hits it hard enough, or when
+//! asked to (`breakup`).  It is frozen, as a crashed craft is, and its mass
+//! goes into a cloud of fragments:
+//!
+//! - The fragments' masses follow a power law, as broken things do: a few big
+//!   pieces, and many small ones.  They add up to the craft's mass.
+//! - They start out around where the craft was, within the sphere that has
+//!   its moments of inertia, and fly apart at up to the breakup's spread.
+//!   Their center of mass, and its velocity, are the craft's, so the
+//!   momentum is kept.
+//! - Each tumbles, and the spin the craft had is kept, too: what the
+//!   fragments' tumbles and their flying apart don't add up to is made up by
+//!   turning the cloud as a whole.  The angular momentum about the center of
+//!   mass is then the craft's.
+//!
+//! A crash is the exception, as the ground takes the momentum going into it.
+//! The cloud starts out at the surface, with what was going down bounced
+//! back up by `RESTITUTION`, and most of it falls right back.
+//!
+//! The fragments are on rails: each goes along its conic about the body that
+//! pulled hardest on the craft, from the breakup on, with nothing else on it.
+//! That keeps thousands of them cheap, and the same from any warp.  One that
+//! goes below its body's surface is gone.
+//!
+//! A cloud can also be a catalog of whole objects, each with a name: the
+//! satellites in a file of two-line element sets (see `sim_astro::tle`),
+//! loaded with `debris tle`, or whatever a scenario lists in its debris.
+//! They are on rails the same way, about the earth.
+//!
+//! The fragments are a hazard to the crafts still flying.  One that goes
+//! through a craft, over a physics step, hits it: the craft takes its
+//! momentum, and if the energy is past `CATASTROPHIC` for the craft's mass,
+//! breaks up too.  The player ship is screened against them, on the sim's
+//! clock, every `SCREEN_INTERVAL` seconds, every `BURN_SCREEN_INTERVAL` as it
+//! burns, and once more as the burn ends, out to `SCREEN_SPAN` seconds ahead
+//! (see `sim_core::conjunction`), and the approaches within
+//! `SCREEN_DISTANCE` are kept in `Conjunctions`, for the HUD.  Only the
+//! clouds about the body pulling hardest on the ship are screened, as its
+//! conic about any other means little.
+//!
+//! - `debris`: the clouds, and the close approaches coming up.
+//! - `debris clear`: get rid of them.
+//! - `debris tle <file> [mass] [radius]`: load a catalog of two-line element
+//!   sets, as one cloud, with each object of `mass` kg (500 by default), and
+//!   `radius` m (1 by default), as the catalog doesn't say.
+//! - `breakup [craft] [spread]`: break a craft (the player ship, by default)
+//!   up, flying apart at up to `spread` m/s (10 by default).
+
+use bevy::prelude::*;
+use na::{Matrix3, Vector3};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use sim_astro::{EarthMarker, SolarState, collision::CollisionEvent, geodesy::Geodetic, tle::Tle};
+use sim_core::{
+    AttitudeState, LinearControl, MassiveBody, OrbitalBody, PhysicsModels, PostPhysicsSet,
+    SizedBody, conjunction::Screen, model_enabled, orbit::propagate, watchdog::Frozen,
+};
+use std::path::Path;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{MassProperties, PlayerShip, propulsion::G0},
+};
+
+/// Seeds the fragments, so that a replay breaks up the same way.
+const DEBRIS_SEED: u64 = 0xdeb7_15c1_0d00_0001;
+
+/// How many fragments a craft breaks into.
+const FRAGMENTS: usize = 96;
+
+/// The power law of the fragments' masses: the number heavier than a mass
+/// goes as that mass to minus this, about as in NASA's breakup model.
+const MASS_POWER: f64 = 0.6;
+
+/// The lightest a fragment is, as a fraction of what the heaviest might be.
+const MASS_RANGE: f64 = 1.0e-6;
+
+/// How fast, in rad/s, a fragment tumbles, at most, on top of the craft's
+/// own spin.
+const TUMBLE: f64 = 1.0;
+
+/// The fragments' density, in kg/m^3, as aluminium.
+const DENSITY: f64 = 2700.0;
+
+/// How much of what was going into the ground a crash bounces back up.
+const RESTITUTION: f64 = 0.2;
+
+/// How fast a crash's fragments fly apart, as a fraction of the impact
+/// speed, and a catastrophic hit's, of the fragment's speed.
+const SPREAD_FRACTION: f64 = 0.1;
+
+/// How fast, in m/s, the fragments of a craft pushed too hard fly apart.
+const STRUCTURAL_SPREAD: f64 = 5.0;
+
+/// The energy of a hit, per mass of the craft hit, in J/kg, from which it
+/// breaks up, rather than just being knocked about.  This is the usual 40
+/// J/g.
+const CATASTROPHIC: f64 = 40.0e3;
+
+/// How often, in seconds of sim time, the player ship is screened, if it
+/// doesn't burn.
+const SCREEN_INTERVAL: f64 = 60.0;
+
+/// How far ahead, in seconds, it is screened, about an orbit, low down, and
+/// how far apart, in seconds, the samples are.
+const SCREEN_SPAN: f64 = 6000.0;
+const SCREEN_STEP: f64 = 30.0;
+
+/// How close, in km, an approach has to be to be kept.
+const SCREEN_DISTANCE: f64 = 10.0;
+
+/// A burn past this, in km/s^2, changes the ship's conic enough to screen it
+/// again, every so often, in seconds of sim time, while it lasts.
+const BURN_ACCEL: f64 = 1.0e-6;
+const BURN_SCREEN_INTERVAL: f64 = 10.0;
+
+/// The most approaches `debris` lists.
+const LISTED: usize = 10;
+
+/// A catalogued object's mass, in kg, and radius, in m, unless told
+/// otherwise: about those of a small satellite.
+const CATALOG_MASS: f64 = 500.0;
+const CATALOG_RADIUS: f64 = 1.0;
+
+/// How far, in days, an element set's epoch can be from now before it is
+/// worth saying that it is stale.
+const STALE_ELEMENTS: f64 = 7.0;
+
+/// A piece of a broken craft, or a catalogued object.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Fragment {
+    /// A catalogued object's name.  A piece of a craft has none.
+    #[serde(default)]
+    pub name: String,
+    /// In kg.
+    pub mass: f64,
+    /// In m, as a ball of `DENSITY`.
+    pub radius: f64,
+    /// The state at the cloud's epoch, relative to its primary, in km and
+    /// km/s.
+    pub pos: Vector3<f64>,
+    pub vel: Vector3<f64>,
+    /// How fast it tumbles, in rad/s, world frame.
+    #[serde(default)]
+    pub spin_w: Vector3<f64>,
+    /// The state now, in the world, once it has been propagated.
+    #[serde(skip)]
+    pub world: Option<OrbitalBody>,
+}
+
+impl Fragment {
+    /// The moment of inertia about its center, in kg*m^2, as a solid ball.
+    pub fn inertia(&self) -> f64 {
+        0.4 * self.mass * self.radius * self.radius
+    }
+
+    /// What to call it, as part of `cloud`.
+    pub fn describe(&self, cloud: &DebrisCloud) -> String {
+        if self.name.is_empty() {
+            format!("a fragment of {}", cloud.source)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+/// The fragments of one craft, or the objects of one catalog.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DebrisCloud {
+    /// The craft it was, or the catalog.
+    pub source: String,
+    /// The body the fragments go around, by name.
+    pub primary: String,
+    /// The time of the breakup, or the loading, in seconds past J2000.
+    pub epoch: f64,
+    pub fragments: Vec<Fragment>,
+}
+
+/// All the debris there is.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Debris(pub Vec<DebrisCloud>);
+
+/// How much a craft takes before it breaks up.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct Structure {
+    /// The most acceleration, in m/s^2, from its own thrust and the air.
+    pub max_load: f64,
+    /// The acceleration, in m/s^2, in the last physics step.
+    pub load: f64,
+}
+
+impl Default for Structure {
+    /// About 10 g.
+    fn default() -> Self {
+        Structure {
+            max_load: 100.0,
+            load: 0.0,
+        }
+    }
+}
+
+/// A close approach of a fragment, or an object, to the player ship.
+#[derive(Clone, Debug)]
+pub struct Conjunction {
+    /// What it is, as `Fragment::describe` has it.
+    pub object: String,
+    /// The time of closest approach, in seconds past J2000.
+    pub time: f64,
+    /// How close it comes, in km.
+    pub miss: f64,
+    /// How fast they pass, in km/s.
+    pub speed: f64,
+}
+
+/// The player ship's close approaches coming up, within `SCREEN_DISTANCE`,
+/// in the order they come.
+#[derive(Resource, Debug, Default)]
+pub struct Conjunctions {
+    /// When it was last screened, in seconds past J2000.
+    pub screened: Option<f64>,
+    /// Whether the ship was burning then, so it is screened again once the
+    /// burn is over.
+    pub burning: bool,
+    pub approaches: Vec<Conjunction>,
+}
+
+impl Conjunctions {
+    /// The next approach to come within `miss` km, after `now`.
+    pub fn next_within(&self, now: f64, miss: f64) -> Option<&Conjunction> {
+        self.approaches
+            .iter()
+            .find(|c| c.time >= now && c.miss < miss)
+    }
+}
+
+/// A request to break a craft up.
+#[derive(Clone, Debug, Message)]
+pub struct Breakup {
+    pub craft: Entity,
+    /// Why, for its `Frozen`.
+    pub reason: String,
+    /// How fast, in m/s, the fragments fly apart, at most.
+    pub spread: f64,
+}
+
+#[derive(Resource)]
+struct DebrisRng(StdRng);
+
+#[derive(Default)]
+pub struct DebrisPlugin;
+
+impl Plugin for DebrisPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Debris>();
+        app.init_resource::<Conjunctions>();
+        app.insert_resource(DebrisRng(StdRng::seed_from_u64(DEBRIS_SEED)));
+        app.add_message::<Breakup>();
+        PhysicsModels::add(app, "debris");
+        app.add_systems(
+            FixedUpdate,
+            (
+                propagate_debris,
+                screen_conjunctions,
+                (check_structure, debris_hits, break_up)
+                    .chain()
+                    .run_if(model_enabled("debris")),
+            )
+                .chain()
+                .after(PostPhysicsSet),
+        );
+        app.add_console_command(
+            "debris",
+            "debris [clear | tle <file> [mass] [radius]]   the debris clouds, and the close approaches",
+            debris_command,
+        );
+        app.add_console_command(
+            "breakup",
+            "breakup [craft] [spread]   break a craft up, flying apart at spread m/s",
+            breakup_command,
+        );
+    }
+}
+
+/// A random unit vector, uniform over the sphere.
+fn random_direction(rng: &mut StdRng) -> Vector3<f64> {
+    let z: f64 = rng.random_range(-1.0..=1.0);
+    let phi = rng.random_range(0.0..std::f64::consts::TAU);
+    let r = (1.0 - z * z).sqrt();
+    Vector3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+/// The radius, in m, of the ball of uniform density with the same moments
+/// of inertia as `mass`.
+fn size(mass: &MassProperties) -> f64 {
+    // A ball's moments add up to 6/5 of its mass times its radius squared.
+    (mass.inertia_b.sum() * 5.0 / (6.0 * mass.mass)).sqrt()
+}
+
+/// Break a craft, with `mass` and `attitude`, into fragments flying apart at
+/// up to `spread` m/s.  Their states are in m and m/s, relative to the
+/// craft's center of mass, and they have its momentum (none), and its
+/// angular momentum.
+fn fragments(
+    rng: &mut StdRng,
+    mass: &MassProperties,
+    attitude: &AttitudeState,
+    spread: f64,
+) -> Vec<Fragment> {
+    let radius = size(mass);
+    let omega_w = attitude.q_bw.transform_vector(&attitude.omega_b);
+    let masses: Vec<f64> = (0..FRAGMENTS)
+        .map(|_| {
+            rng.random_range(MASS_RANGE.powf(MASS_POWER)..1.0)
+                .powf(-1.0 / MASS_POWER)
+        })
+        .collect();
+    let scale = mass.mass / masses.iter().sum::<f64>();
+    let mut fragments: Vec<Fragment> = masses
+        .into_iter()
+        .map(|m| {
+            let m = m * scale;
+            let pos = random_direction(rng) * radius * rng.random::<f64>().cbrt();
+            Fragment {
+                name: String::new(),
+                mass: m,
+                radius: (3.0 * m / (4.0 * std::f64::consts::PI * DENSITY)).cbrt(),
+                pos,
+                vel: random_direction(rng) * spread * rng.random::<f64>(),
+                spin_w: omega_w + random_direction(rng) * TUMBLE * rng.random::<f64>(),
+                world: None,
+            }
+        })
+        .collect();
+
+    // Put the center of mass where the craft's was, and at rest.
+    let weighted = |f: fn(&Fragment) -> Vector3<f64>, fragments: &[Fragment]| {
+        fragments
+            .iter()
+            .map(|fragment| f(fragment) * fragment.mass)
+            .sum::<Vector3<f64>>()
+            / mass.mass
+    };
+    let center = weighted(|f| f.pos, &fragments);
+    let drift = weighted(|f| f.vel, &fragments);
+    for fragment in &mut fragments {
+        fragment.pos -= center;
+        // Each was going around with the craft, as well.
+        fragment.vel += omega_w.cross(&fragment.pos) - drift;
+    }
+
+    // Turn the cloud as a whole by whatever makes the angular momentum the
+    // craft's.  Turning it adds none to the momentum, as the center of mass
+    // is at the origin.
+    let wanted = attitude
+        .q_bw
+        .transform_vector(&mass.inertia_b.component_mul(&attitude.omega_b));
+    let mut have = Vector3::zeros();
+    let mut inertia = Matrix3::zeros();
+    for fragment in &fragments {
+        let r = fragment.pos;
+        have += r.cross(&fragment.vel) * fragment.mass + fragment.spin_w * fragment.inertia();
+        inertia += (Matrix3::identity() * r.norm_squared() - r * r.transpose()) * fragment.mass;
+    }
+    if let Some(turn) = inertia
+        .try_inverse()
+        .map(|inverse| inverse * (wanted - have))
+    {
+        for fragment in &mut fragments {
+            fragment.vel += turn.cross(&fragment.pos);
+        }
+    }
+    fragments
+}
+
+/// The massive body pulling hardest at `pos`.
+fn strongest<'a>(
+    bodies: impl Iterator<Item = (&'a Name, &'a OrbitalBody, &'a MassiveBody)>,
+    pos: &Vector3<f64>,
+) -> Option<(&'a Name, &'a OrbitalBody, &'a MassiveBody)> {
+    bodies.max_by(|a, b| {
+        let pull = |(_, orbital, massive): &(_, &OrbitalBody, &MassiveBody)| {
+            massive.gm / (orbital.pos - pos).norm_squared()
+        };
+        pull(a).total_cmp(&pull(b))
+    })
+}
+
+/// Take each fragment along its conic to now, and get rid of those that
+/// have come down.
+fn propagate_debris(
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    mut debris: ResMut<Debris>,
+    bodies: Query<(
+        &Name,
+        &OrbitalBody,
+        &AttitudeState,
+        &MassiveBody,
+        Option<&SizedBody>,
+    )>,
+) {
+    let now = solar.et + fixed.elapsed_secs_f64();
+    for cloud in &mut debris.0 {
+        let Some((_, primary, attitude, massive, size)) = bodies
+            .iter()
+            .find(|(name, ..)| name.as_str() == cloud.primary)
+        else {
+            continue;
+        };
+        let dt = now - cloud.epoch;
+        cloud.fragments.retain_mut(|fragment| {
+            let (pos, vel) = propagate(&fragment.pos, &fragment.vel, massive.gm, dt);
+            let world = OrbitalBody {
+                pos: primary.pos + pos,
+                vel: primary.vel + vel,
+            };
+            // Only those between the ellipsoid's radii need a closer look.
+            let above = size.is_none_or(|size| {
+                let r = pos.norm();
+                r > size.radii.max()
+                    || (r > size.radii.min()
+                        && Geodetic::from_world(primary, attitude, &size.radii, &world.pos).alt
+                            > 0.0)
+            });
+            fragment.world = Some(world);
+            above
+        });
+    }
+    debris.0.retain(|cloud| !cloud.fragments.is_empty());
+}
+
+/// Break up the crafts pushed past what their structure takes.
+fn check_structure(
+    mut breakups: MessageWriter<Breakup>,
+    mut crafts: Query<(Entity, &LinearControl, &mut Structure), Without<Frozen>>,
+) {
+    for (craft, linear, mut structure) in crafts.iter_mut() {
+        // km/s^2 to m/s^2.
+        structure.load = linear.accel_b.norm() * 1000.0;
+        if structure.load > structure.max_load {
+            breakups.write(Breakup {
+                craft,
+                reason: format!("broke up at {:.1} g", structure.load / G0),
+                spread: STRUCTURAL_SPREAD,
+            });
+        }
+    }
+}
+
+/// Have the fragments that went through a craft in the last step hit it.
+#[allow(clippy::type_complexity)]
+fn debris_hits(
+    time: Res<Time>,
+    mut debris: ResMut<Debris>,
+    mut breakups: MessageWriter<Breakup>,
+    mut crafts: Query<
+        (Entity, &Name, &mut OrbitalBody, &MassProperties),
+        (Without<MassiveBody>, Without<Frozen>),
+    >,
+) {
+    let dt = time.delta_secs_f64();
+    for (craft, name, mut orbital, mass) in crafts.iter_mut() {
+        let reach = size(mass);
+        for cloud in &mut debris.0 {
+            let mut hits = Vec::new();
+            for (i, fragment) in cloud.fragments.iter().enumerate() {
+                let Some(world) = &fragment.world else {
+                    continue;
+                };
+                let rel = world.pos - orbital.pos;
+                let rel_vel = world.vel - orbital.vel;
+                let speed2 = rel_vel.norm_squared();
+                let closest = if speed2 > 0.0 {
+                    -rel.dot(&rel_vel) / speed2
+                } else {
+                    0.0
+                };
+                // Over the last step, which ended now.
+                let swept = rel + rel_vel * closest.clamp(-dt, 0.0);
+                if swept.norm() * 1000.0 < reach + fragment.radius {
+                    hits.push(i);
+                }
+            }
+            for i in hits.into_iter().rev() {
+                let fragment = cloud.fragments.remove(i);
+                let object = fragment.describe(cloud);
+                let world = fragment.world.unwrap();
+                let rel_vel = world.vel - orbital.vel;
+                // The fragment stays in the craft, or what is left of it.
+                orbital.vel += rel_vel * (fragment.mass / (mass.mass + fragment.mass));
+                // km/s to m/s.
+                let speed = rel_vel.norm() * 1000.0;
+                let energy = 0.5 * fragment.mass * speed * speed;
+                info!(
+                    "{} hit by {} ({:.3} kg) at {:.0} m/s",
+                    name, object, fragment.mass, speed
+                );
+                if energy / mass.mass > CATASTROPHIC {
+                    breakups.write(Breakup {
+                        craft,
+                        reason: format!("broken up by {} at {:.0} m/s", object, speed),
+                        spread: speed * SPREAD_FRACTION,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Make the clouds for the crafts breaking up this step, and those that
+/// crashed.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn break_up(
+    mut commands: Commands,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    mut rng: ResMut<DebrisRng>,
+    mut debris: ResMut<Debris>,
+    mut breakups: MessageReader<Breakup>,
+    mut collisions: MessageReader<CollisionEvent>,
+    crafts: Query<(
+        &Name,
+        &OrbitalBody,
+        &AttitudeState,
+        &MassProperties,
+        Has<Frozen>,
+    )>,
+    bodies: Query<(&Name, &OrbitalBody, &AttitudeState, &MassiveBody)>,
+) {
+    let epoch = solar.et + fixed.elapsed_secs_f64();
+    let mut broken = Vec::new();
+
+    for breakup in breakups.read() {
+        let Ok((name, orbital, attitude, mass, frozen)) = crafts.get(breakup.craft) else {
+            continue;
+        };
+        if frozen || broken.contains(&breakup.craft) {
+            continue;
+        }
+        broken.push(breakup.craft);
+        commands.entity(breakup.craft).insert(Frozen {
+            reason: breakup.reason.clone(),
+        });
+        let Some((primary, primary_orbital, _)) = strongest(
+            bodies
+                .iter()
+                .map(|(name, orbital, _, massive)| (name, orbital, massive)),
+            &orbital.pos,
+        ) else {
+            continue;
+        };
+        let center = OrbitalBody {
+            pos: orbital.pos - primary_orbital.pos,
+            vel: orbital.vel - primary_orbital.vel,
+        };
+        debris.0.push(cloud(
+            &mut rng.0,
+            name,
+            primary,
+            epoch,
+            &center,
+            mass,
+            attitude,
+            breakup.spread,
+        ));
+        info!("{} {}, into {} fragments", name, breakup.reason, FRAGMENTS);
+    }
+
+    for hit in collisions.read().filter(|hit| !hit.landed) {
+        let (Ok((name, orbital, attitude, mass, _)), Ok((primary, body, body_attitude, _))) =
+            (crafts.get(hit.craft), bodies.get(hit.body))
+        else {
+            continue;
+        };
+        // Bounce what was going into the ground, relative to the ground.
+        let rel = orbital.pos - body.pos;
+        let up = rel.normalize();
+        let omega_w = body_attitude.q_bw.transform_vector(&body_attitude.omega_b);
+        let ground = omega_w.cross(&rel);
+        let mut vel = orbital.vel - body.vel - ground;
+        let down = vel.dot(&up).min(0.0);
+        vel -= up * (down * (1.0 + RESTITUTION));
+        // Start out above the ground, with the fragments clear of it.
+        let center = OrbitalBody {
+            pos: rel + up * (size(mass) / 1000.0 - hit.location.alt),
+            vel: vel + ground,
+        };
+        // km/s to m/s.
+        let spread = hit.impact_speed * 1000.0 * SPREAD_FRACTION;
+        debris.0.push(cloud(
+            &mut rng.0, name, primary, epoch, &center, mass, attitude, spread,
+        ));
+    }
+}
+
+/// The cloud a craft breaks into, with its center of mass at `center`,
+/// relative to `primary`.
+#[allow(clippy::too_many_arguments)]
+fn cloud(
+    rng: &mut StdRng,
+    source: &Name,
+    primary: &Name,
+    epoch: f64,
+    center: &OrbitalBody,
+    mass: &MassProperties,
+    attitude: &AttitudeState,
+    spread: f64,
+) -> DebrisCloud {
+    let fragments = fragments(rng, mass, attitude, spread)
+        .into_iter()
+        .map(|fragment| Fragment {
+            // m to km.
+            pos: center.pos + fragment.pos / 1000.0,
+            vel: center.vel + fragment.vel / 1000.0,
+            ..fragment
+        })
+        .collect();
+    DebrisCloud {
+        source: source.to_string(),
+        primary: primary.to_string(),
+        epoch,
+        fragments,
+    }
+}
+
+/// Screen the player ship against the clouds about the body pulling hardest
+/// on it, every `SCREEN_INTERVAL`, every `BURN_SCREEN_INTERVAL` as it burns,
+/// and as the burn ends.
+#[allow(clippy::type_complexity)]
+fn screen_conjunctions(
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    debris: Res<Debris>,
+    mut conjunctions: ResMut<Conjunctions>,
+    ship: Query<(&OrbitalBody, Option<&LinearControl>), (With<PlayerShip>, Without<Frozen>)>,
+    bodies: Query<(&Name, &OrbitalBody, &MassiveBody)>,
+) {
+    let now = solar.et + fixed.elapsed_secs_f64();
+    let Ok((orbital, linear)) = ship.single() else {
+        conjunctions.approaches.clear();
+        return;
+    };
+    let burning = linear.is_some_and(|linear| linear.accel_b.norm() > BURN_ACCEL);
+    let interval = if burning {
+        BURN_SCREEN_INTERVAL
+    } else {
+        SCREEN_INTERVAL
+    };
+    let due = conjunctions
+        .screened
+        .is_none_or(|screened| !(screened..screened + interval).contains(&now));
+    let burn_ended = conjunctions.burning && !burning;
+    if !due && !burn_ended {
+        return;
+    }
+    conjunctions.screened = Some(now);
+    conjunctions.burning = burning;
+    conjunctions.approaches.clear();
+    let Some((primary, primary_orbital, massive)) = strongest(bodies.iter(), &orbital.pos) else {
+        return;
+    };
+    let mut clouds = debris
+        .0
+        .iter()
+        .filter(|cloud| cloud.primary == primary.as_str())
+        .peekable();
+    if clouds.peek().is_none() {
+        return;
+    }
+
+    let screen = Screen::new(
+        &(orbital.pos - primary_orbital.pos),
+        &(orbital.vel - primary_orbital.vel),
+        massive.gm,
+        SCREEN_SPAN,
+        SCREEN_STEP,
+    );
+    let mut approaches = Vec::new();
+    for cloud in clouds {
+        for fragment in &cloud.fragments {
+            let Some(world) = &fragment.world else {
+                continue;
+            };
+            for approach in screen.screen(
+                &(world.pos - primary_orbital.pos),
+                &(world.vel - primary_orbital.vel),
+                SCREEN_DISTANCE,
+            ) {
+                approaches.push(Conjunction {
+                    object: fragment.describe(cloud),
+                    time: now + approach.time,
+                    miss: approach.miss,
+                    speed: approach.speed,
+                });
+            }
+        }
+    }
+    approaches.sort_by(|a, b| a.time.total_cmp(&b.time));
+    conjunctions.approaches = approaches;
+}
+
+/// A catalog of two-line element sets as a cloud about the earth, at `now`,
+/// each object with `mass` and `radius`, and how many of its sets are stale.
+fn catalog(
+    path: &str,
+    now: f64,
+    mass: f64,
+    radius: f64,
+    (earth, earth_orbital, earth_mass): (&Name, &OrbitalBody, &MassiveBody),
+) -> Result<(DebrisCloud, usize), String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    let sets = Tle::parse_all(&text).map_err(|e| format!("{}: {}", path, e))?;
+    if sets.is_empty() {
+        return Err(format!("No element sets in {}", path));
+    }
+    let stale = sets
+        .iter()
+        .filter(|set| (set.epoch - now).abs() > STALE_ELEMENTS * 86400.0)
+        .count();
+    let fragments = sets
+        .iter()
+        .map(|set| {
+            let (pos, vel) = set.state(earth_mass.gm);
+            let (pos, vel) = propagate(&pos, &vel, earth_mass.gm, now - set.epoch);
+            Fragment {
+                name: set.name.clone(),
+                mass,
+                radius,
+                pos,
+                vel,
+                spin_w: Vector3::zeros(),
+                // Where it is now, so it is screened before the next step.
+                world: Some(OrbitalBody {
+                    pos: earth_orbital.pos + pos,
+                    vel: earth_orbital.vel + vel,
+                }),
+            }
+        })
+        .collect();
+    let source = Path::new(path)
+        .file_stem()
+        .map_or(path.into(), |stem| stem.to_string_lossy());
+    Ok((
+        DebrisCloud {
+            source: source.to_string(),
+            primary: earth.to_string(),
+            epoch: now,
+            fragments,
+        },
+        stale,
+    ))
+}
+
+fn debris_command(
+    In(args): In<Vec<String>>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    mut debris: ResMut<Debris>,
+    mut conjunctions: ResMut<Conjunctions>,
+    earth: Query<(&Name, &OrbitalBody, &MassiveBody), With<EarthMarker>>,
+) -> ConsoleReply {
+    const USAGE: &str = "debris [clear | tle <file> [mass] [radius]]";
+    let now = solar.et + fixed.elapsed_secs_f64();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let mut lines = Vec::new();
+    match args.as_slice() {
+        [] => {}
+        ["clear"] => {
+            debris.0.clear();
+            *conjunctions = Conjunctions::default();
+        }
+        ["tle", path, rest @ ..] if rest.len() <= 2 => {
+            let arg = |i: usize, default: f64| rest.get(i).map_or(Ok(default), |a| parse_arg(a));
+            let earth = earth
+                .single()
+                .map_err(|_| "There is no earth".to_string())?;
+            let (cloud, stale) = catalog(
+                path,
+                now,
+                arg(0, CATALOG_MASS)?,
+                arg(1, CATALOG_RADIUS)?,
+                earth,
+            )?;
+            lines.push(format!("{} objects from {}", cloud.fragments.len(), path));
+            if stale > 0 {
+                lines.push(format!(
+                    "{} of them with elements more than {:.0} days old, or ahead, and far off",
+                    stale, STALE_ELEMENTS
+                ));
+            }
+            debris.0.push(cloud);
+            // Screen them right away.
+            conjunctions.screened = None;
+        }
+        _ => return Err(USAGE.to_string()),
+    }
+    if debris.0.is_empty() {
+        return Ok("no debris".to_string());
+    }
+    lines.extend(debris.0.iter().map(|cloud| {
+        let catalog = cloud.fragments.iter().any(|f| !f.name.is_empty());
+        format!(
+            "{}: {} {}, {:.0} kg, about {}",
+            cloud.source,
+            cloud.fragments.len(),
+            if catalog { "objects" } else { "fragments" },
+            cloud.fragments.iter().map(|f| f.mass).sum::<f64>(),
+            cloud.primary
+        )
+    }));
+    let coming: Vec<String> = conjunctions
+        .approaches
+        .iter()
+        .filter(|c| c.time >= now)
+        .take(LISTED)
+        .map(|c| {
+            format!(
+                "{} passes {:.3} km off, at {:.2} km/s, in {:.0} s",
+                c.object,
+                c.miss,
+                c.speed,
+                c.time - now
+            )
+        })
+        .collect();
+    if coming.is_empty() {
+        lines.push(format!(
+            "nothing within {:.0} km in the next {:.0} s",
+            SCREEN_DISTANCE, SCREEN_SPAN
+        ));
+    } else {
+        lines.extend(coming);
+    }
+    Ok(lines.join("\n"))
+}
+
+fn breakup_command(
+    In(args): In<Vec<String>>,
+    mut breakups: MessageWriter<Breakup>,
+    ship: Query<Entity, With<PlayerShip>>,
+    crafts: Query<(Entity, &Name), With<MassProperties>>,
+) -> ConsoleReply {
+    let (craft, spread) = match args.as_slice() {
+        [] => (None, None),
+        [arg] => match parse_arg(arg) {
+            Ok(spread) => (None, Some(spread)),
+            Err(_) => (Some(arg), None),
+        },
+        [craft, spread] => (Some(craft), Some(parse_arg(spread)?)),
+        _ => return Err("breakup [craft] [spread]".to_string()),
+    };
+    let craft = match craft {
+        Some(wanted) => crafts
+            .iter()
+            .find(|(_, name)| name.as_str().eq_ignore_ascii_case(wanted))
+            .map(|(entity, _)| entity)
+            .ok_or_else(|| format!("No such craft as {:?}", wanted))?,
+        None => ship.single().map_err(|_| "no ship".to_string())?,
+    };
+    breakups.write(Breakup {
+        craft,
+        reason: "broken up from the console".to_string(),
+        spread: spread.unwrap_or(10.0),
+    });
+    Ok("ok".to_string())
+}
@@ -0,0 +1,249 @@
+//! Training drills.
+//!
+//! A drill template gives the bounds of a kind of practice scenario: what
+//! orbit to start in, how badly the ship is tumbling, whether there is a target
+//! to dock with, and what might be broken.  `scifisim --drill <template>
+//! [seed]` picks a random situation within those bounds and starts the sim in
+//! it.  The same template and seed always give the same drill, so a good one
+//! can be handed around by its seed.
+
+use bevy::prelude::*;
+use na::{Unit, UnitQuaternion, Vector3};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::IndexedRandom};
+use serde::{Deserialize, Serialize};
+use sim_astro::{EarthMarker, loading::SimPhase};
+use sim_core::{AttitudeState, OrbitalBody, orbit::OrbitFrame};
+use std::{f64::consts::TAU, path::Path};
+
+use crate::ship::{
+    PlayerShip, SasTarget, ShipOrbit,
+    engine::MainEngine,
+    rcs::{RcsRealism, RcsThrusters},
+};
+
+/// An inclusive range, `[min, max]`, to pick from.
+pub type Bounds = [f64; 2];
+
+fn pick(rng: &mut StdRng, bounds: Bounds) -> f64 {
+    if bounds[0] < bounds[1] {
+        rng.random_range(bounds[0]..=bounds[1])
+    } else {
+        bounds[0]
+    }
+}
+
+/// A random unit vector, uniform over the sphere.
+fn pick_direction(rng: &mut StdRng) -> Unit<Vector3<f64>> {
+    let z: f64 = rng.random_range(-1.0..=1.0);
+    let phi = rng.random_range(0.0..TAU);
+    let r = (1.0 - z * z).sqrt();
+    Unit::new_normalize(Vector3::new(r * phi.cos(), r * phi.sin(), z))
+}
+
+/// The kinds of failure a drill can include.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum FailureKind {
+    /// One RCS thruster gives no thrust at all.
+    DeadThruster,
+    /// The RCS only gives a fraction of its rated thrust.
+    WeakRcs { thrust_limit: Bounds },
+    /// The main engine won't light.
+    EngineOut,
+}
+
+impl Failure {
+    /// Break the ship.
+    pub fn apply(&self, realism: &mut RcsRealism, rcs: &mut RcsThrusters, engine: &mut MainEngine) {
+        match *self {
+            Failure::DeadThruster(index) => {
+                let count = rcs.thrusters.len();
+                if count > 0 {
+                    rcs.thrusters[index as usize % count].max_thrust = 0.0;
+                }
+            }
+            Failure::WeakRcs(limit) => realism.thrust_limit = limit,
+            Failure::EngineOut => engine.max_thrust = 0.0,
+        }
+    }
+}
+
+/// The bounds of a drill.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DrillTemplate {
+    pub name: String,
+    /// Altitudes, in km above the earth's equatorial radius.
+    pub periapsis_altitude: Bounds,
+    pub apoapsis_altitude: Bounds,
+    /// Inclination to the earth's equator, in degrees.
+    pub inclination: Bounds,
+    /// The tumble rate, in degrees per second, about a random axis.
+    pub tumble_rate: Bounds,
+    /// If present, a target to dock with, at a range (m) from the ship.
+    #[serde(default)]
+    pub target_range: Option<Bounds>,
+    /// The target's speed (m/s) relative to the ship.
+    #[serde(default)]
+    pub target_speed: Bounds,
+    /// The chance (0..=1) that something is broken, and what it might be.
+    #[serde(default)]
+    pub failure_chance: f64,
+    #[serde(default)]
+    pub failures: Vec<FailureKind>,
+}
+
+impl DrillTemplate {
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    /// Pick a drill within these bounds.  The orbit is placed around the earth,
+    /// with the given equatorial radius (km) and pole, in the world frame.
+    pub fn generate(&self, seed: u64, earth_radius: f64, earth_pole: &Vector3<f64>) -> Drill {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        // The orbit: periapsis and apoapsis, then the plane, tilted from the
+        // equator by the inclination about a random line of nodes, then where
+        // periapsis is in that plane, and where we are along it.
+        let periapsis = earth_radius + pick(&mut rng, self.periapsis_altitude);
+        let apoapsis = (earth_radius + pick(&mut rng, self.apoapsis_altitude)).max(periapsis);
+        let raan = rng.random_range(0.0..TAU);
+        let inclination = pick(&mut rng, self.inclination).to_radians();
+        let argp = rng.random_range(0.0..TAU);
+        let orbit = ShipOrbit::from_elements(
+            earth_pole,
+            periapsis,
+            apoapsis,
+            inclination,
+            raan,
+            argp,
+            rng.random_range(0.0..TAU),
+        );
+
+        let q_bw = UnitQuaternion::from_scaled_axis(
+            pick_direction(&mut rng).into_inner() * rng.random_range(0.0..TAU / 2.0),
+        );
+        let omega_b =
+            pick_direction(&mut rng).into_inner() * pick(&mut rng, self.tumble_rate).to_radians();
+
+        let target = self.target_range.map(|range| DrillTarget {
+            offset: pick_direction(&mut rng).into_inner() * pick(&mut rng, range) / 1000.0,
+            velocity: pick_direction(&mut rng).into_inner() * pick(&mut rng, self.target_speed)
+                / 1000.0,
+        });
+
+        let failure = if rng.random_bool(self.failure_chance.clamp(0.0, 1.0)) {
+            self.failures.choose(&mut rng).map(|kind| match kind {
+                FailureKind::DeadThruster => Failure::DeadThruster(rng.random()),
+                FailureKind::WeakRcs { thrust_limit } => {
+                    Failure::WeakRcs(pick(&mut rng, *thrust_limit))
+                }
+                FailureKind::EngineOut => Failure::EngineOut,
+            })
+        } else {
+            None
+        };
+
+        Drill {
+            name: self.name.clone(),
+            seed,
+            orbit,
+            q_bw,
+            omega_b,
+            target,
+            failure,
+        }
+    }
+}
+
+/// A failure picked for a drill.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Failure {
+    /// The thruster, by index, modulo the number of thrusters.
+    DeadThruster(u32),
+    /// The fraction of rated thrust the RCS gives.
+    WeakRcs(f64),
+    EngineOut,
+}
+
+/// A target to dock with, placed relative to the ship, along the ship's
+/// `OrbitFrame` (radial, prograde, normal).  Units are km and km/s.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DrillTarget {
+    pub offset: Vector3<f64>,
+    pub velocity: Vector3<f64>,
+}
+
+/// A drill, picked from a template.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct Drill {
+    pub name: String,
+    pub seed: u64,
+    pub orbit: ShipOrbit,
+    pub q_bw: UnitQuaternion<f64>,
+    pub omega_b: Vector3<f64>,
+    pub target: Option<DrillTarget>,
+    pub failure: Option<Failure>,
+}
+
+/// Sets up the ship for the `Drill` resource.  The drill's orbit has to be
+/// inserted as the `ShipOrbit` before the `ShipPlugin` is added.
+#[derive(Default)]
+pub struct DrillPlugin;
+
+impl Plugin for DrillPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(SimPhase::Running),
+            start_drill.after(crate::ship::setup_ship),
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn start_drill(
+    mut commands: Commands,
+    drill: Res<Drill>,
+    mut realism: ResMut<RcsRealism>,
+    mut sas_target: ResMut<SasTarget>,
+    mut ship: Query<
+        (
+            &OrbitalBody,
+            &mut AttitudeState,
+            &mut RcsThrusters,
+            &mut MainEngine,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<&OrbitalBody, (With<EarthMarker>, Without<PlayerShip>)>,
+) {
+    let Ok((orbital, mut attitude, mut rcs, mut engine)) = ship.single_mut() else {
+        return;
+    };
+    attitude.q_bw = drill.q_bw;
+    attitude.omega_b = drill.omega_b;
+
+    if let Some(failure) = &drill.failure {
+        failure.apply(&mut realism, &mut rcs, &mut engine);
+    }
+
+    if let (Some(target), Ok(earth)) = (&drill.target, earth.single()) {
+        let frame = OrbitFrame::new(&(orbital.pos - earth.pos), &(orbital.vel - earth.vel));
+        let to_world =
+            |v: &Vector3<f64>| frame.radial * v.x + frame.prograde * v.y + frame.normal * v.z;
+        let entity = commands
+            .spawn((
+                Name::new("DrillTarget"),
+                OrbitalBody {
+                    pos: orbital.pos + to_world(&target.offset),
+                    vel: orbital.vel + to_world(&target.velocity),
+                },
+                AttitudeState {
+                    q_bw: UnitQuaternion::identity(),
+                    omega_b: Vector3::zeros(),
+                },
+            ))
+            .id();
+        sas_target.0 = Some(entity);
+    }
+}
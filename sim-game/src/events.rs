@@ -0,0 +1,705 @@
+//! Mission events.
+//!
+//! The sequence of events is what has happened to the player ship, and what is
+//! coming up, in order: burns starting and ending, entering and leaving a
+//! body's sphere of influence, going into and out of a shadow, passing
+//! periapsis and apoapsis, crossing the equator of the body it orbits at the
+//! ascending and descending nodes, and hitting that body.  After each physics
+//! step, `detect_events` looks for these, and keeps them in `EventLog`.  It
+//! also sends each one as a `MissionEvent` message, for whatever has to act on
+//! it.  The ones coming up are forecast the same way, from the ship's two-body
+//! orbit about the body whose sphere it is in, over the next orbit, and the one
+//! after the maneuver node, if there is one.  The node's burn is forecast from
+//! the engine, as the executor will fly it.  The other bodies are held where
+//! they are for the forecast, so it is only good for an orbit or so.  The burns
+//! of a lunar transfer that are not yet in a node are forecast as they are
+//! expected to go.
+//!
+//! Either way, the time of an event is closed in on by halving the interval
+//! it is found in, along the conic: the forecast's samples, or the step just
+//! taken, back from where the ship is.  A burn is timed at the step the engine
+//! was seen to change on.
+//!
+//! `MissionEvents` puts the two together, and the console's `events [file]`
+//! command lists them, or writes them out as JSON, or as CSV if the file name
+//! ends in `.csv`.
+//!
+//! A body's sphere of influence is its Laplace sphere against the body it goes
+//! around (see `sim_astro::hierarchy`): a moon's against its planet, and a
+//! planet's against the sun.  The ship is in the sphere it is in furthest down
+//! the tree, going into a moon's only from its planet's.  The ship
+//! is in a body's shadow from when it first hides any of the sun, in the
+//! penumbra, until none of it is hidden again (see `sim_astro::eclipse`).
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+use sim_astro::{SolarState, eclipse::sunlight, hierarchy::BodyTree};
+use sim_core::{
+    AttitudeState, MassiveBody, OrbitalBody, PostPhysicsSet, SizedBody,
+    orbit::{period, propagate},
+};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    oem::iso_date,
+    ship::{
+        MassProperties, PlayerShip, engine::MainEngine, lunar::LunarTransfer,
+        maneuver::ManeuverNode,
+    },
+};
+
+/// The points checked along each orbit of the forecast.
+const FORECAST_SAMPLES: usize = 720;
+
+/// How far ahead, in seconds, to forecast an orbit that doesn't close.
+const FORECAST_OPEN_SPAN: f64 = 6.0 * 3600.0;
+
+/// Halvings of the interval an event is found in, to pin down its time.
+const FORECAST_REFINE: usize = 30;
+
+/// How many events the console lists.
+const LISTED_EVENTS: usize = 20;
+
+/// The kinds of event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    BurnStart,
+    BurnEnd,
+    SoiEntry,
+    SoiExit,
+    EclipseEntry,
+    EclipseExit,
+    Periapsis,
+    Apoapsis,
+    AscendingNode,
+    DescendingNode,
+    Impact,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 11] = [
+        EventKind::BurnStart,
+        EventKind::BurnEnd,
+        EventKind::SoiEntry,
+        EventKind::SoiExit,
+        EventKind::EclipseEntry,
+        EventKind::EclipseExit,
+        EventKind::Periapsis,
+        EventKind::Apoapsis,
+        EventKind::AscendingNode,
+        EventKind::DescendingNode,
+        EventKind::Impact,
+    ];
+
+    /// The kind with the name `name` gives.
+    pub fn from_name(name: &str) -> Option<EventKind> {
+        EventKind::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::BurnStart => "burn_start",
+            EventKind::BurnEnd => "burn_end",
+            EventKind::SoiEntry => "soi_entry",
+            EventKind::SoiExit => "soi_exit",
+            EventKind::EclipseEntry => "eclipse_entry",
+            EventKind::EclipseExit => "eclipse_exit",
+            EventKind::Periapsis => "periapsis",
+            EventKind::Apoapsis => "apoapsis",
+            EventKind::AscendingNode => "ascending_node",
+            EventKind::DescendingNode => "descending_node",
+            EventKind::Impact => "impact",
+        }
+    }
+}
+
+/// Something that happens to the ship.
+#[derive(Clone, Debug, Message, Serialize)]
+pub struct MissionEvent {
+    /// When, in seconds past J2000.
+    pub et: f64,
+    pub kind: EventKind,
+    /// The body it concerns: the one being orbited, whose sphere is entered or
+    /// left, or that casts the shadow.
+    pub body: String,
+    /// Whether it is forecast, rather than seen.
+    pub planned: bool,
+    /// Anything more, such as a burn's Δv, an apsis' altitude, or the speed
+    /// of an impact.
+    pub detail: String,
+}
+
+impl MissionEvent {
+    /// Whether it is of `kind`, and, with `text`, for a body, or a burn's
+    /// label, with that in it, in any case.
+    pub fn matches(&self, kind: EventKind, text: Option<&str>) -> bool {
+        self.kind == kind
+            && text.is_none_or(|text| {
+                let text = text.to_lowercase();
+                self.body.to_lowercase().contains(&text)
+                    || self.detail.to_lowercase().contains(&text)
+            })
+    }
+}
+
+/// An event to look for, as `<kind>[:<text>]`, such as `periapsis`, or
+/// `burn_start:LOI` (see `MissionEvent::matches`).
+pub fn parse_event(event: &str) -> Result<(EventKind, Option<String>), String> {
+    let (kind, text) = match event.split_once(':') {
+        Some((kind, text)) => (kind, Some(text.to_string())),
+        None => (event, None),
+    };
+    let kind = EventKind::from_name(kind).ok_or_else(|| {
+        let names: Vec<_> = EventKind::ALL.iter().map(EventKind::name).collect();
+        format!("No such event as {:?}: {}", kind, names.join(", "))
+    })?;
+    Ok((kind, text))
+}
+
+/// The events seen so far.
+#[derive(Resource, Default)]
+pub struct EventLog {
+    pub events: Vec<MissionEvent>,
+    /// How things were after the last step.
+    last: Option<Watch>,
+}
+
+/// What is watched for changes, from one moment to the next.
+#[derive(Clone, Copy, Debug)]
+struct Watch {
+    burning: bool,
+    /// The body whose sphere the ship is in.
+    soi: Entity,
+    /// The body whose shadow the ship is in.
+    shadow: Option<Entity>,
+    /// The ship's position, dotted with its velocity, relative to `soi`: this
+    /// goes from negative to positive at periapsis.
+    radial: f64,
+    /// The ship's position, relative to `soi`, along its pole: this goes from
+    /// negative to positive at the ascending node.
+    height: f64,
+    /// Whether the ship is inside `soi`'s radius.
+    inside: bool,
+}
+
+impl Watch {
+    /// The events between `self` and `next`, and the bodies they concern.
+    fn changes(&self, next: &Watch) -> Vec<(EventKind, Entity)> {
+        let mut changes = Vec::new();
+        if self.burning != next.burning {
+            let kind = if next.burning {
+                EventKind::BurnStart
+            } else {
+                EventKind::BurnEnd
+            };
+            changes.push((kind, next.soi));
+        }
+        if self.soi != next.soi {
+            changes.push((EventKind::SoiExit, self.soi));
+            changes.push((EventKind::SoiEntry, next.soi));
+        } else if self.radial < 0.0 && next.radial >= 0.0 {
+            changes.push((EventKind::Periapsis, next.soi));
+        } else if self.radial > 0.0 && next.radial <= 0.0 {
+            changes.push((EventKind::Apoapsis, next.soi));
+        }
+        if self.soi == next.soi {
+            if self.height < 0.0 && next.height >= 0.0 {
+                changes.push((EventKind::AscendingNode, next.soi));
+            } else if self.height > 0.0 && next.height <= 0.0 {
+                changes.push((EventKind::DescendingNode, next.soi));
+            }
+            if !self.inside && next.inside {
+                changes.push((EventKind::Impact, next.soi));
+            }
+        }
+        if self.shadow != next.shadow {
+            if let Some(body) = self.shadow {
+                changes.push((EventKind::EclipseExit, body));
+            }
+            if let Some(body) = next.shadow {
+                changes.push((EventKind::EclipseEntry, body));
+            }
+        }
+        changes
+    }
+}
+
+/// A body, as far as events go.
+struct Surrounding {
+    entity: Entity,
+    name: String,
+    pos: Vector3<f64>,
+    vel: Vector3<f64>,
+    gm: f64,
+    /// Its equatorial radius, km, if it has a size.
+    radius: Option<f64>,
+    /// Its spin axis, or the ecliptic's pole if it has no attitude.
+    pole: Vector3<f64>,
+    /// The radius of its sphere of influence, km.
+    soi: f64,
+    /// The body it goes around, if any.
+    parent: Option<usize>,
+}
+
+/// The bodies, at one moment.
+struct Surroundings {
+    bodies: Vec<Surrounding>,
+    /// The most massive of them, taken as the sun.
+    sun: usize,
+}
+
+impl Surroundings {
+    fn new(bodies: &Bodies, tree: &BodyTree) -> Option<Self> {
+        let mut bodies: Vec<_> = bodies
+            .iter()
+            .map(
+                |(entity, name, orbital, massive, size, attitude)| Surrounding {
+                    entity,
+                    name: name.to_string(),
+                    pos: orbital.pos,
+                    vel: orbital.vel,
+                    gm: massive.gm,
+                    radius: size.map(|size| size.radii.x),
+                    pole: attitude.map_or(Vector3::z(), |attitude| {
+                        attitude.q_bw.transform_vector(&Vector3::z())
+                    }),
+                    soi: f64::INFINITY,
+                    parent: None,
+                },
+            )
+            .collect();
+        let find = |entity: Entity| bodies.iter().position(|body| body.entity == entity);
+        let sun = tree
+            .nodes
+            .get(tree.root)
+            .and_then(|root| find(root.entity?))
+            .or_else(|| (0..bodies.len()).max_by(|a, b| bodies[*a].gm.total_cmp(&bodies[*b].gm)))?;
+        // Up the tree, or for a body it doesn't have, whatever pulls on it
+        // hardest.
+        let parents: Vec<_> = (0..bodies.len())
+            .map(|i| {
+                let body = &bodies[i];
+                if i == sun {
+                    return None;
+                }
+                tree.primary(body.entity).and_then(find).or_else(|| {
+                    (0..bodies.len()).filter(|j| *j != i).max_by(|a, b| {
+                        let pull =
+                            |other: &Surrounding| other.gm / (other.pos - body.pos).norm_squared();
+                        pull(&bodies[*a]).total_cmp(&pull(&bodies[*b]))
+                    })
+                })
+            })
+            .collect();
+        for (i, parent) in parents.into_iter().enumerate() {
+            let Some(parent) = parent else {
+                continue;
+            };
+            let (body, parent_body) = (&bodies[i], &bodies[parent]);
+            bodies[i].soi =
+                (body.pos - parent_body.pos).norm() * (body.gm / parent_body.gm).powf(0.4);
+            bodies[i].parent = Some(parent);
+        }
+        Some(Surroundings { bodies, sun })
+    }
+
+    /// The body whose sphere of influence `pos` is in: down the tree from
+    /// the sun, into whichever sphere under the last it is in.
+    fn soi(&self, pos: &Vector3<f64>) -> usize {
+        let mut center = self.sun;
+        while let Some(inner) = (0..self.bodies.len())
+            .filter(|i| self.bodies[*i].parent == Some(center))
+            .filter(|i| (pos - self.bodies[*i].pos).norm() < self.bodies[*i].soi)
+            .min_by(|a, b| self.bodies[*a].soi.total_cmp(&self.bodies[*b].soi))
+        {
+            center = inner;
+        }
+        center
+    }
+
+    /// The body shadowing `pos` from the sun, if any.
+    fn shadow(&self, pos: &Vector3<f64>) -> Option<usize> {
+        let sun = &self.bodies[self.sun];
+        (0..self.bodies.len()).find(|i| {
+            let body = &self.bodies[*i];
+            let Some(radius) = body.radius.filter(|_| *i != self.sun) else {
+                return false;
+            };
+            sunlight(pos, &sun.pos, sun.radius.unwrap_or(0.0), &body.pos, radius) < 1.0
+        })
+    }
+
+    /// What to watch, for the ship at `pos` and `vel`.
+    fn watch(&self, pos: &Vector3<f64>, vel: &Vector3<f64>, burning: bool) -> Watch {
+        let soi = &self.bodies[self.soi(pos)];
+        let rel = pos - soi.pos;
+        Watch {
+            burning,
+            soi: soi.entity,
+            shadow: self.shadow(pos).map(|i| self.bodies[i].entity),
+            radial: rel.dot(&(vel - soi.vel)),
+            height: rel.dot(&soi.pole),
+            inside: soi.radius.is_some_and(|radius| rel.norm() < radius),
+        }
+    }
+
+    fn name(&self, entity: Entity) -> String {
+        self.bodies
+            .iter()
+            .find(|body| body.entity == entity)
+            .map_or("?".to_string(), |body| body.name.clone())
+    }
+
+    /// The event, with its details, for the ship at `pos` and `vel`.
+    fn event(
+        &self,
+        et: f64,
+        (kind, body): (EventKind, Entity),
+        pos: &Vector3<f64>,
+        vel: &Vector3<f64>,
+        planned: bool,
+    ) -> MissionEvent {
+        let body_at = self.bodies.iter().find(|b| b.entity == body);
+        let detail = match kind {
+            EventKind::Periapsis | EventKind::Apoapsis => body_at.map_or(String::new(), |b| {
+                format!(
+                    "altitude {:.1} km",
+                    (pos - b.pos).norm() - b.radius.unwrap_or(0.0)
+                )
+            }),
+            EventKind::Impact => body_at.map_or(String::new(), |b| {
+                format!("speed {:.1} m/s", (vel - b.vel).norm() * 1000.0)
+            }),
+            _ => String::new(),
+        };
+        MissionEvent {
+            et,
+            kind,
+            body: self.name(body),
+            planned,
+            detail,
+        }
+    }
+}
+
+/// Every body with a mass.
+type Bodies<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Name,
+        &'static OrbitalBody,
+        &'static MassiveBody,
+        Option<&'static SizedBody>,
+        Option<&'static AttitudeState>,
+    ),
+    Without<PlayerShip>,
+>;
+
+/// A request to write out the sequence of events.
+#[derive(Clone, Debug, Message)]
+pub struct ExportEvents {
+    pub path: PathBuf,
+}
+
+#[derive(Default)]
+pub struct EventsPlugin;
+
+impl Plugin for EventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EventLog>();
+        app.add_message::<ExportEvents>();
+        app.add_message::<MissionEvent>();
+        app.add_systems(FixedUpdate, detect_events.after(PostPhysicsSet));
+        app.add_systems(Update, export_events);
+        app.add_console_command(
+            "events",
+            "events [file]   list the sequence of events, or write it as JSON or CSV",
+            events_command,
+        );
+    }
+}
+
+pub(crate) fn detect_events(
+    fixed: Res<Time<Fixed>>,
+    solar: Res<SolarState>,
+    mut log: ResMut<EventLog>,
+    mut messages: MessageWriter<MissionEvent>,
+    ship: Query<(&OrbitalBody, Option<&MainEngine>), With<PlayerShip>>,
+    bodies: Bodies,
+    tree: Res<BodyTree>,
+) {
+    let Ok((orbital, engine)) = ship.single() else {
+        return;
+    };
+    let Some(surroundings) = Surroundings::new(&bodies, &tree) else {
+        return;
+    };
+    let et = solar.et + fixed.elapsed_secs_f64();
+    let burning = engine.is_some_and(|engine| engine.throttle > 0.0);
+    let watch = surroundings.watch(&orbital.pos, &orbital.vel, burning);
+    if let Some(last) = log.last {
+        // Back along the conic about the body whose sphere the ship is in,
+        // over the step, with the engine as it was.
+        let center = &surroundings.bodies[surroundings.soi(&orbital.pos)];
+        let pos = orbital.pos - center.pos;
+        let vel = orbital.vel - center.vel;
+        let state_at = |t: f64| {
+            let (p, v) = propagate(&pos, &vel, center.gm, t);
+            (p + center.pos, v + center.vel)
+        };
+        let watch_at = |t: f64| {
+            let (p, v) = state_at(t);
+            surroundings.watch(&p, &v, last.burning)
+        };
+        for change in last.changes(&watch) {
+            let t = refine(watch_at, &watch, change, -fixed.delta_secs_f64(), 0.0);
+            let (p, v) = state_at(t);
+            let event = surroundings.event(et + t, change, &p, &v, false);
+            messages.write(event.clone());
+            log.events.push(event);
+        }
+    }
+    log.last = Some(watch);
+}
+
+/// When `change`, which happens by `after` (its watch being `next`), happens
+/// after `before`, given the watch at any time between.
+fn refine(
+    watch_at: impl Fn(f64) -> Watch,
+    next: &Watch,
+    change: (EventKind, Entity),
+    mut before: f64,
+    mut after: f64,
+) -> f64 {
+    for _ in 0..FORECAST_REFINE {
+        let mid = (before + after) / 2.0;
+        if watch_at(mid).changes(next).contains(&change) {
+            before = mid;
+        } else {
+            after = mid;
+        }
+    }
+    after
+}
+
+/// The sequence of events, seen and forecast.
+#[derive(SystemParam)]
+#[allow(clippy::type_complexity)]
+pub struct MissionEvents<'w, 's> {
+    log: Res<'w, EventLog>,
+    fixed: Res<'w, Time<Fixed>>,
+    solar: Res<'w, SolarState>,
+    ship: Query<
+        'w,
+        's,
+        (
+            &'static OrbitalBody,
+            &'static MassProperties,
+            Option<&'static MainEngine>,
+            Option<&'static ManeuverNode>,
+            Option<&'static LunarTransfer>,
+        ),
+        With<PlayerShip>,
+    >,
+    bodies: Bodies<'w, 's>,
+    tree: Res<'w, BodyTree>,
+}
+
+impl MissionEvents<'_, '_> {
+    /// The events coming up.
+    pub fn forecast(&self) -> Vec<MissionEvent> {
+        let (Ok((orbital, mass, engine, node, lunar)), Some(surroundings)) = (
+            self.ship.single(),
+            Surroundings::new(&self.bodies, &self.tree),
+        ) else {
+            return Vec::new();
+        };
+        let now = self.fixed.elapsed_secs_f64();
+        let et = self.solar.et + now;
+        let center = &surroundings.bodies[surroundings.soi(&orbital.pos)];
+        let gm = center.gm;
+        let pos = orbital.pos - center.pos;
+        let vel = orbital.vel - center.vel;
+        let mut events = Vec::new();
+
+        // A burn of `dv` m/s centered on `time`, as the executor flies it.
+        let burn = |time: f64, dv: f64, body: &str, label: String| {
+            let burn_time = engine.map_or(0.0, |engine| engine.burn_time(mass.mass, dv));
+            let detail = format!("{}dv {:.1} m/s, {:.0} s", label, dv, burn_time);
+            [
+                (EventKind::BurnStart, time - burn_time / 2.0),
+                (EventKind::BurnEnd, time + burn_time / 2.0),
+            ]
+            .map(|(kind, at)| MissionEvent {
+                et: self.solar.et + at,
+                kind,
+                body: body.to_string(),
+                planned: true,
+                detail: detail.clone(),
+            })
+        };
+        if let Some(lunar) = lunar {
+            for planned in &lunar.burns {
+                let label = format!("{} ", planned.burn);
+                events.extend(burn(planned.time, planned.dv, planned.burn.body(), label));
+            }
+        }
+
+        let Some(node) = node else {
+            forecast_orbit(&surroundings, center, et, &pos, &vel, None, &mut events);
+            events.sort_by(|a, b| a.et.total_cmp(&b.et));
+            return events;
+        };
+
+        // Up to the node, and then on from it, with its Δv as an impulse.
+        let dt = (node.time - now).max(0.0);
+        forecast_orbit(&surroundings, center, et, &pos, &vel, Some(dt), &mut events);
+        let dv_w = node.dv_w(&pos, &vel, gm, now);
+        if node.remaining_w.is_none() {
+            let label = lunar
+                .and_then(LunarTransfer::current)
+                .map_or(String::new(), |current| format!("{} ", current));
+            events.extend(burn(node.time, dv_w.norm(), &center.name, label));
+        }
+        let (node_pos, node_vel) = propagate(&pos, &vel, gm, dt);
+        let node_vel = node_vel + dv_w / 1000.0;
+        forecast_orbit(
+            &surroundings,
+            center,
+            et + dt,
+            &node_pos,
+            &node_vel,
+            None,
+            &mut events,
+        );
+        events.sort_by(|a, b| a.et.total_cmp(&b.et));
+        events
+    }
+
+    /// Everything, seen and forecast, in order.
+    pub fn sequence(&self) -> Vec<MissionEvent> {
+        let mut events = self.log.events.clone();
+        events.extend(self.forecast());
+        events
+    }
+}
+
+/// Forecast the events along the conic through `pos` and `vel`, relative to
+/// `center`, from `et`, for `span` seconds (or one revolution, if `span` is
+/// None).
+fn forecast_orbit(
+    surroundings: &Surroundings,
+    center: &Surrounding,
+    et: f64,
+    pos: &Vector3<f64>,
+    vel: &Vector3<f64>,
+    span: Option<f64>,
+    events: &mut Vec<MissionEvent>,
+) {
+    let span = span.unwrap_or_else(|| period(pos, vel, center.gm).unwrap_or(FORECAST_OPEN_SPAN));
+    let state_at = |t: f64| {
+        let (p, v) = propagate(pos, vel, center.gm, t);
+        (p + center.pos, v + center.vel)
+    };
+    let watch_at = |t: f64| {
+        let (p, v) = state_at(t);
+        surroundings.watch(&p, &v, false)
+    };
+
+    let mut last = watch_at(0.0);
+    for i in 1..=FORECAST_SAMPLES {
+        let t = span * i as f64 / FORECAST_SAMPLES as f64;
+        let next = watch_at(t);
+        for change in last.changes(&next) {
+            // Close in on when it happens.
+            let before = span * (i - 1) as f64 / FORECAST_SAMPLES as f64;
+            let at = refine(watch_at, &next, change, before, t);
+            let (p, v) = state_at(at);
+            events.push(surroundings.event(et + at, change, &p, &v, true));
+        }
+        last = next;
+    }
+}
+
+fn events_command(
+    In(args): In<Vec<String>>,
+    events: MissionEvents,
+    mut exports: MessageWriter<ExportEvents>,
+) -> ConsoleReply {
+    match args.as_slice() {
+        [] => {
+            let sequence = events.sequence();
+            let lines: Vec<_> = sequence[sequence.len().saturating_sub(LISTED_EVENTS)..]
+                .iter()
+                .map(|event| {
+                    format!(
+                        "{} {} {:<13} {} {}",
+                        iso_date(event.et),
+                        if event.planned { "plan" } else { "seen" },
+                        event.kind.name(),
+                        event.body,
+                        event.detail
+                    )
+                })
+                .collect();
+            Ok(lines.join("\n"))
+        }
+        [path] => {
+            exports.write(ExportEvents { path: path.into() });
+            Ok("ok".to_string())
+        }
+        _ => Err("events [file]".to_string()),
+    }
+}
+
+/// Write out a sequence of events, as CSV if the file name ends in `.csv`,
+/// and JSON otherwise.
+pub fn write_events<P: AsRef<Path>>(path: P, events: &[MissionEvent]) -> std::io::Result<()> {
+    let csv = path
+        .as_ref()
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let mut out = BufWriter::new(File::create(path)?);
+    if csv {
+        writeln!(out, "time_tdb,et_s,kind,body,planned,detail")?;
+        for event in events {
+            writeln!(
+                out,
+                "{},{:.3},{},{},{},\"{}\"",
+                iso_date(event.et),
+                event.et,
+                event.kind.name(),
+                event.body,
+                event.planned,
+                event.detail.replace('"', "\"\"")
+            )?;
+        }
+    } else {
+        serde_json::to_writer_pretty(&mut out, events)?;
+        writeln!(out)?;
+    }
+    out.flush()
+}
+
+fn export_events(mut requests: MessageReader<ExportEvents>, events: MissionEvents) {
+    for request in requests.read() {
+        let sequence = events.sequence();
+        match write_events(&request.path, &sequence) {
+            Ok(()) => info!(
+                "Wrote {} events to {}",
+                sequence.len(),
+                request.path.display()
+            ),
+            Err(e) => error!("Unable to write {}: {}", request.path.display(), e),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! Gamepads.
+//!
+//! A gamepad flies the ship alongside the keyboard.  The sticks turn it, in
+//! proportion to how far they are pushed, where the keys are all or nothing:
+//! the left stick pitches, pushed forward as with W, and yaws, pushed left as
+//! with A, and the right stick rolls, pushed left as with Q.  What that does
+//! is up to the mode, as it is for the keys: in `Manual`, it is a torque, and
+//! in `RateCommand`, a rate.  Select switches the sticks to move the craft
+//! instead, as Tab does the keys (see `ControlLayer`).  The triggers move the
+//! throttle, the right one up and the left one down, as fast as the Shift and
+//! Ctrl keys do when pulled all the way, and slower for less.
+//!
+//! A stick never quite comes back to the middle, so anything inside the
+//! dead zone is taken as nothing, and past it, the travel is stretched to go
+//! from nothing at the edge of the dead zone to all at the end.  That is then
+//! raised to the power of the curve, which, above 1, gives a finer touch near
+//! the middle.  The triggers are taken the same way.  Both settings can be
+//! put in the `[gamepad]` table of the bindings file (see `keys`):
+//!
+//! ```toml
+//! [gamepad]
+//! deadzone = 0.15
+//! curve = 2.0
+//! ```
+//!
+//! - `gamepad`: the gamepads connected, and the settings.
+//! - `gamepad deadzone <fraction>`: set the dead zone, 0..1.
+//! - `gamepad curve <power>`: set the curve.
+
+use bevy::prelude::*;
+use na::Vector3;
+use std::path::Path;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    keys::{bindings_file, read_bindings},
+    remote::local_control,
+    ship::{
+        ControlLayer, PlayerShip, RotationKeys,
+        engine::{MainEngine, THROTTLE_RATE},
+        rcs_keys,
+    },
+};
+
+/// How the sticks and triggers are read.
+#[derive(Resource, Clone, Debug)]
+pub struct GamepadTuning {
+    /// The fraction of the travel, from the middle, taken as nothing.
+    pub deadzone: f64,
+    /// The power the travel past the dead zone is raised to.
+    pub curve: f64,
+}
+
+impl Default for GamepadTuning {
+    fn default() -> Self {
+        GamepadTuning {
+            deadzone: 0.1,
+            curve: 2.0,
+        }
+    }
+}
+
+impl GamepadTuning {
+    /// An axis, -1..=1, as it is to be used: nothing in the dead zone, and
+    /// curved past it.
+    pub fn shape(&self, value: f64) -> f64 {
+        let past = ((value.abs() - self.deadzone) / (1.0 - self.deadzone)).clamp(0.0, 1.0);
+        if past == 0.0 {
+            return 0.0;
+        }
+        past.powf(self.curve).copysign(value)
+    }
+
+    /// Take the settings from the `[gamepad]` table of a bindings file.
+    /// Those it doesn't have are left as they are.
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        let document = read_bindings(path)?;
+        let Some(table) = document.get("gamepad") else {
+            return Ok(());
+        };
+        let number = |name: &str| -> Result<Option<f64>, String> {
+            match table.get(name) {
+                None => Ok(None),
+                Some(item) => item
+                    .as_float()
+                    .or_else(|| item.as_integer().map(|i| i as f64))
+                    .map(Some)
+                    .ok_or_else(|| format!("gamepad.{} must be a number", name)),
+            }
+        };
+        let mut tuning = self.clone();
+        if let Some(deadzone) = number("deadzone")? {
+            tuning.deadzone = deadzone;
+        }
+        if let Some(curve) = number("curve")? {
+            tuning.curve = curve;
+        }
+        tuning.check()?;
+        *self = tuning;
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), String> {
+        if !(0.0..1.0).contains(&self.deadzone) {
+            return Err("The dead zone must be from 0 to under 1".to_string());
+        }
+        if !self.curve.is_finite() || self.curve <= 0.0 {
+            return Err("The curve must be more than zero".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct GamepadPlugin;
+
+impl Plugin for GamepadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GamepadTuning>();
+        app.add_systems(
+            Update,
+            (gamepad_attitude.after(rcs_keys), gamepad_throttle).run_if(local_control),
+        );
+        app.add_console_command(
+            "gamepad",
+            "gamepad [deadzone <fraction> | curve <power>]   the gamepads, and how they are read",
+            gamepad_command,
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let world = app.world_mut();
+        let Some(path) = bindings_file(world) else {
+            return;
+        };
+        if let Err(e) = world.resource_mut::<GamepadTuning>().load(&path) {
+            error!("Unable to load {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Add the sticks to the rotation keys, which the keys have just set.
+fn gamepad_attitude(
+    pads: Query<&Gamepad>,
+    tuning: Res<GamepadTuning>,
+    mut layer: ResMut<ControlLayer>,
+    mut keys: ResMut<RotationKeys>,
+) {
+    for pad in pads.iter() {
+        if pad.just_pressed(GamepadButton::Select) {
+            layer.toggle();
+        }
+        let (left, right) = (pad.left_stick(), pad.right_stick());
+        let sticks = Vector3::new(
+            tuning.shape(left.y as f64),
+            tuning.shape(-left.x as f64),
+            tuning.shape(-right.x as f64),
+        );
+        keys.0 = (keys.0 + sticks).map(|value| value.clamp(-1.0, 1.0));
+    }
+}
+
+/// Move the throttle with the triggers.
+fn gamepad_throttle(
+    pads: Query<&Gamepad>,
+    tuning: Res<GamepadTuning>,
+    time: Res<Time>,
+    mut ship: Query<&mut MainEngine, With<PlayerShip>>,
+) {
+    let Ok(mut engine) = ship.single_mut() else {
+        return;
+    };
+    for pad in pads.iter() {
+        let pulled = |trigger| tuning.shape(pad.get(trigger).unwrap_or(0.0) as f64);
+        let rate = pulled(GamepadButton::RightTrigger2) - pulled(GamepadButton::LeftTrigger2);
+        if rate != 0.0 {
+            let step = rate * THROTTLE_RATE * time.delta_secs_f64();
+            engine.throttle = (engine.throttle + step).clamp(0.0, 1.0);
+        }
+    }
+}
+
+fn gamepad_command(
+    In(args): In<Vec<String>>,
+    pads: Query<&Gamepad>,
+    mut tuning: ResMut<GamepadTuning>,
+) -> ConsoleReply {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    let mut wanted = tuning.clone();
+    match words.as_slice() {
+        [] => {}
+        ["deadzone", value] => wanted.deadzone = parse_arg(value)?,
+        ["curve", value] => wanted.curve = parse_arg(value)?,
+        _ => return Err("gamepad [deadzone <fraction> | curve <power>]".to_string()),
+    }
+    wanted.check()?;
+    *tuning = wanted;
+    Ok(format!(
+        "{} connected, deadzone {}, curve {}",
+        pads.iter().count(),
+        tuning.deadzone,
+        tuning.curve
+    ))
+}
@@ -0,0 +1,258 @@
+//! Key bindings.
+//!
+//! The keys are looked up by what they do, an action, in the `InputMap`,
+//! rather than fixed where they are read, so that a layout can move them.  A
+//! plugin adds the actions it reads, with their default keys, through
+//! `KeysApp::add_key_action`, and reads them with `InputMap::pressed` and
+//! `InputMap::just_pressed`.  An action can have several keys, and any of
+//! them does it.
+//!
+//! The bindings file, `keys.toml` in the config directory (see `keys_file`),
+//! or the one given to the game with `--keys`, overrides the defaults.  It
+//! binds an action to a key, or a list of them, by their names in Bevy's
+//! `KeyCode`:
+//!
+//! ```toml
+//! "pitch+" = "KeyI"
+//! "pitch-" = "KeyK"
+//! "rcs-mode" = ["KeyR", "F1"]
+//! pause = []
+//! ```
+//!
+//! Tables in the file are the settings of other inputs, such as `[gamepad]`
+//! (see `gamepad`), and are left to them.
+//!
+//! A recording is of the keys, not the actions, so it is played back on the
+//! bindings it was made with.
+//!
+//! Along with the ship's keys, these run the sim's time:
+//!
+//! - P: pause, or start again (`pause`).
+//! - NumpadAdd/NumpadSubtract: twice, or half, the warp (`warp+`, `warp-`).
+//! - `keys`: list the actions, and their keys.
+//! - `keys <action>`: show an action's keys.
+//! - `keys <action> <key...> | none`: bind an action to the keys, or to none,
+//!   for this run.
+
+use bevy::{
+    prelude::*,
+    reflect::{DynamicEnum, DynamicVariant, Enum, TypeInfo, Typed, VariantInfo},
+};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use crate::console::{ConsoleApp, ConsoleReply};
+
+/// How much each warp key changes the warp by.
+const WARP_STEP: f64 = 2.0;
+
+/// The keys of each action, by its name.
+#[derive(Resource, Default)]
+pub struct InputMap(pub BTreeMap<&'static str, Vec<KeyCode>>);
+
+impl InputMap {
+    /// Whether any of the action's keys is held.
+    pub fn pressed(&self, kb: &ButtonInput<KeyCode>, action: &str) -> bool {
+        self.keys(action).iter().any(|key| kb.pressed(*key))
+    }
+
+    /// Whether any of the action's keys went down this frame.
+    pub fn just_pressed(&self, kb: &ButtonInput<KeyCode>, action: &str) -> bool {
+        self.keys(action).iter().any(|key| kb.just_pressed(*key))
+    }
+
+    /// A pair of actions as a -1..=1 axis: 1 with `pos` held, -1 with `neg`.
+    pub fn axis(&self, kb: &ButtonInput<KeyCode>, pos: &str, neg: &str) -> f64 {
+        let mut value = 0.0;
+        if self.pressed(kb, pos) {
+            value += 1.0;
+        }
+        if self.pressed(kb, neg) {
+            value -= 1.0;
+        }
+        value
+    }
+
+    /// The action's keys, or none for an action no plugin has added.
+    pub fn keys(&self, action: &str) -> &[KeyCode] {
+        self.0.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Bind an action to its keys, by their names.
+    pub fn bind(&mut self, action: &str, names: &[&str]) -> Result<(), String> {
+        let keys = names
+            .iter()
+            .map(|name| key_code(name).ok_or_else(|| format!("Unknown key {:?}", name)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let bound = self
+            .0
+            .get_mut(action)
+            .ok_or_else(|| format!("Unknown action {:?}", action))?;
+        *bound = keys;
+        Ok(())
+    }
+
+    /// Bind the actions in a bindings file.  Those it doesn't have keep
+    /// their keys.
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        let document = read_bindings(path)?;
+        for (action, item) in document.iter().filter(|(_, item)| !item.is_table()) {
+            let names: Vec<&str> = match (item.as_str(), item.as_array()) {
+                (Some(name), _) => vec![name],
+                (_, Some(names)) => names
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| format!("{}: the keys must be names", action))?,
+                _ => return Err(format!("{}: a key, or a list of them", action)),
+            };
+            self.bind(action, &names)?;
+        }
+        Ok(())
+    }
+}
+
+/// A key by its name, such as "KeyW".
+pub fn key_code(name: &str) -> Option<KeyCode> {
+    // Reflection panics on a name the enum doesn't have, so look first.
+    let TypeInfo::Enum(info) = KeyCode::type_info() else {
+        return None;
+    };
+    match info.variant(name) {
+        Some(VariantInfo::Unit(_)) => {
+            KeyCode::from_reflect(&DynamicEnum::new(name, DynamicVariant::Unit))
+        }
+        _ => None,
+    }
+}
+
+/// A key's name, such as "KeyW".
+pub fn key_name(key: &KeyCode) -> &str {
+    key.variant_name()
+}
+
+/// Read a bindings file.
+pub fn read_bindings(path: &Path) -> Result<toml_edit::DocumentMut, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    text.parse().map_err(|e| format!("{}", e))
+}
+
+/// The bindings file to read, if any: the one given, or the one in the
+/// config directory, if it is there.
+pub fn bindings_file(world: &World) -> Option<PathBuf> {
+    match world.get_resource::<KeysFile>() {
+        Some(file) => Some(file.0.clone()),
+        None => Some(keys_file()).filter(|path| path.exists()),
+    }
+}
+
+/// The bindings file to use, if not the one in the config directory.
+#[derive(Resource)]
+pub struct KeysFile(pub PathBuf);
+
+/// Where the bindings are kept: `$XDG_CONFIG_HOME/scifisim/keys.toml`, or
+/// `~/.config/scifisim/keys.toml`, or, without either, `keys.toml` in the
+/// working directory.
+pub fn keys_file() -> PathBuf {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    match config {
+        Some(config) => config.join("scifisim").join("keys.toml"),
+        None => PathBuf::from("keys.toml"),
+    }
+}
+
+pub trait KeysApp {
+    /// Add an action, with the keys it has unless the bindings say
+    /// otherwise.  An action of the same name is replaced.
+    fn add_key_action(&mut self, action: &'static str, keys: &[KeyCode]) -> &mut Self;
+}
+
+impl KeysApp for App {
+    fn add_key_action(&mut self, action: &'static str, keys: &[KeyCode]) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_init::<InputMap>()
+            .0
+            .insert(action, keys.to_vec());
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct KeysPlugin;
+
+impl Plugin for KeysPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_key_action("pause", &[KeyCode::KeyP]);
+        app.add_key_action("warp+", &[KeyCode::NumpadAdd]);
+        app.add_key_action("warp-", &[KeyCode::NumpadSubtract]);
+        app.add_systems(Update, time_keys);
+        app.add_console_command(
+            "keys",
+            "keys [<action> [<key...> | none]]   list the key bindings, or bind an action",
+            keys_command,
+        );
+    }
+
+    /// Once every plugin has added its actions, bind them as the file says.
+    fn finish(&self, app: &mut App) {
+        let world = app.world_mut();
+        let Some(path) = bindings_file(world) else {
+            return;
+        };
+        let mut map = world.get_resource_or_init::<InputMap>();
+        match map.load(&path) {
+            Ok(()) => info!("Loaded key bindings from {}", path.display()),
+            Err(e) => error!("Unable to load {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Pause, and change the warp.
+fn time_keys(kb: Res<ButtonInput<KeyCode>>, map: Res<InputMap>, mut time: ResMut<Time<Virtual>>) {
+    if map.just_pressed(&kb, "pause") {
+        if time.is_paused() {
+            time.unpause();
+        } else {
+            time.pause();
+        }
+    }
+    let step = if map.just_pressed(&kb, "warp+") {
+        WARP_STEP
+    } else if map.just_pressed(&kb, "warp-") {
+        1.0 / WARP_STEP
+    } else {
+        return;
+    };
+    let warp = time.relative_speed_f64() * step;
+    time.set_relative_speed_f64(warp);
+    info!("warp {}", warp);
+}
+
+fn keys_command(In(args): In<Vec<String>>, mut map: ResMut<InputMap>) -> ConsoleReply {
+    let line = |map: &InputMap, action: &str| {
+        let keys: Vec<&str> = map.keys(action).iter().map(key_name).collect();
+        format!("{}: {}", action, keys.join(" "))
+    };
+    match args.as_slice() {
+        [] => Ok(map
+            .0
+            .keys()
+            .map(|action| line(&map, action))
+            .collect::<Vec<_>>()
+            .join("\n")),
+        [action] if map.0.contains_key(action.as_str()) => Ok(line(&map, action)),
+        [action] => Err(format!("Unknown action {:?}", action)),
+        [action, keys @ ..] => {
+            let names: Vec<&str> = match keys {
+                [none] if none == "none" => Vec::new(),
+                _ => keys.iter().map(String::as_str).collect(),
+            };
+            map.bind(action, &names)?;
+            Ok(line(&map, action))
+        }
+    }
+}
@@ -0,0 +1,350 @@
+//! Lagrange points, and the frame turning with a pair of bodies.
+//!
+//! For each pair of bodies chosen (the earth and moon, and the sun and earth,
+//! to start with), the five Lagrange points of the restricted three-body
+//! model (see `sim_core::cr3bp`) are worked out each frame, for the map to
+//! mark.
+//!
+//! The map can also be turned with one of the pairs, so that the two bodies
+//! and their points stand still, which is how halo and other three-body
+//! orbits are looked at.  The ship is then coasted ahead in the model,
+//! rather than along its conic, and its Jacobi constant is given beside those
+//! of L1 and L2: with less than those, the ship has the energy to get out
+//! past them.  That path is only as good as the model, which leaves out the
+//! pair's eccentricity and every other body.
+//!
+//! - `lagrange`: the pairs, and where their points are.
+//! - `lagrange <primary> <secondary>`: mark a pair's points, or stop.
+//! - `lagrange rotating <primary> <secondary> [days]`: turn the map with a
+//!   pair, coasting the ship for that many days (a turn of the pair, by
+//!   default).
+//! - `lagrange rotating off`, `lagrange off`: stop turning, or stop marking
+//!   any points at all.
+
+use bevy::prelude::*;
+use na::{UnitQuaternion, Vector3};
+use sim_astro::{loading::SimPhase, setup_solar};
+use sim_core::{
+    MassiveBody, OrbitalBody, SizedBody,
+    cr3bp::{LAGRANGE_NAMES, RotatingFrame},
+};
+use std::f64::consts::TAU;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::PlayerShip,
+};
+
+/// The pairs marked to start with, primary first.
+const DEFAULT_PAIRS: [(&str, &str); 2] = [("EARTH", "MOON"), ("SUN", "EARTH")];
+
+/// How often, in real seconds, the ship is coasted again in the model.
+const LAGRANGE_INTERVAL: f64 = 1.0;
+
+/// How many points the coasted path is sampled at.
+const PATH_SAMPLES: usize = 1000;
+
+/// The most steps taken coasting the ship.  Close to either body, this is
+/// short of the whole span.
+const PATH_MAX_STEPS: usize = 50_000;
+
+/// Two bodies, the heavier first.
+#[derive(Clone, Debug)]
+pub struct BodyPair {
+    pub primary: Entity,
+    pub secondary: Entity,
+    /// Such as `EARTH-MOON`.
+    pub name: String,
+}
+
+/// One of a pair's Lagrange points.
+#[derive(Clone, Debug)]
+pub struct LagrangePoint {
+    /// The pair's name, and the point's, such as `L1`.
+    pub pair: String,
+    pub name: &'static str,
+    /// Where it is, world frame, in km.
+    pub pos: Vector3<f64>,
+}
+
+/// The map turning with a pair.
+#[derive(Clone, Debug)]
+pub struct RotatingView {
+    pub pair: BodyPair,
+    /// How far ahead, in sim seconds, the ship is coasted, or None for a
+    /// turn of the pair.
+    pub span: Option<f64>,
+    /// The frame, as of `time`, in sim seconds.
+    pub frame: Option<RotatingFrame>,
+    pub time: f64,
+    /// The ship's path, coasted in the model, in its normalized units.
+    pub path: Vec<Vector3<f64>>,
+    /// The ship's Jacobi constant, and those of L1 and L2.
+    pub jacobi: Option<f64>,
+    pub jacobi_l1: f64,
+    pub jacobi_l2: f64,
+}
+
+impl RotatingView {
+    /// A world-frame offset, such as from the earth to a body, at sim time
+    /// `t`, along the axes turning with the pair.
+    pub fn turn(&self, rel: &Vector3<f64>, t: f64) -> Vector3<f64> {
+        match &self.frame {
+            Some(frame) => frame.axes_at(t - self.time).inverse_transform_vector(rel),
+            None => *rel,
+        }
+    }
+
+    /// A body to world orientation, as body to the turning axes, now.
+    pub fn turn_attitude(&self, q_bw: &UnitQuaternion<f64>) -> UnitQuaternion<f64> {
+        match &self.frame {
+            Some(frame) => UnitQuaternion::from_rotation_matrix(&frame.axes).inverse() * q_bw,
+            None => *q_bw,
+        }
+    }
+
+    /// The ship's path, from `center`, a world position, along the turning
+    /// axes, in km.
+    pub fn path_from(&self, center: &Vector3<f64>) -> Vec<Vector3<f64>> {
+        let Some(frame) = &self.frame else {
+            return Vec::new();
+        };
+        let offset = frame
+            .axes
+            .inverse_transform_vector(&(frame.origin - center));
+        self.path
+            .iter()
+            .map(|p| offset + p * frame.separation)
+            .collect()
+    }
+}
+
+/// The pairs whose points are marked, the points, and the pair the map turns
+/// with, if any.
+#[derive(Resource, Default)]
+pub struct Lagrange {
+    pub pairs: Vec<BodyPair>,
+    pub points: Vec<LagrangePoint>,
+    pub rotating: Option<RotatingView>,
+}
+
+#[derive(Default)]
+pub struct LagrangePlugin;
+
+impl Plugin for LagrangePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Lagrange>();
+        app.add_systems(OnEnter(SimPhase::Running), default_pairs.after(setup_solar));
+        app.add_systems(Update, track_lagrange);
+        app.add_console_command(
+            "lagrange",
+            "lagrange [<primary> <secondary> | rotating <primary> <secondary> [days] | rotating off | off]   Lagrange points, and the frame turning with a pair",
+            lagrange_command,
+        );
+    }
+}
+
+/// The bodies that can be paired, by name.
+type Pairable<'w, 's> = Query<'w, 's, (Entity, &'static Name, &'static MassiveBody)>;
+
+/// The pair of the named bodies.
+fn find_pair(bodies: &Pairable, primary: &str, secondary: &str) -> Result<BodyPair, String> {
+    let find = |name: &str| {
+        bodies
+            .iter()
+            .find(|(_, n, _)| n.as_str().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("No such body: {:?}", name))
+    };
+    let (primary, primary_name, primary_mass) = find(primary)?;
+    let (secondary, secondary_name, secondary_mass) = find(secondary)?;
+    if primary == secondary {
+        return Err("A pair needs two bodies".to_string());
+    }
+    if secondary_mass.gm > primary_mass.gm {
+        return Err(format!(
+            "{} is heavier than {}, so goes first",
+            secondary_name, primary_name
+        ));
+    }
+    Ok(BodyPair {
+        primary,
+        secondary,
+        name: format!("{}-{}", primary_name, secondary_name),
+    })
+}
+
+fn default_pairs(mut lagrange: ResMut<Lagrange>, bodies: Pairable) {
+    for (primary, secondary) in DEFAULT_PAIRS {
+        if let Ok(pair) = find_pair(&bodies, primary, secondary) {
+            lagrange.pairs.push(pair);
+        }
+    }
+}
+
+/// The frame turning with a pair, as the bodies are now.
+fn pair_frame(
+    pair: &BodyPair,
+    bodies: &Query<(&OrbitalBody, &MassiveBody, Option<&SizedBody>)>,
+) -> Option<(RotatingFrame, f64, f64)> {
+    let (p1, m1, s1) = bodies.get(pair.primary).ok()?;
+    let (p2, m2, s2) = bodies.get(pair.secondary).ok()?;
+    let frame = RotatingFrame::new((&p1.pos, &p1.vel, m1.gm), (&p2.pos, &p2.vel, m2.gm))?;
+    let radius = |size: Option<&SizedBody>| size.map_or(0.0, |s| s.radii.x) / frame.separation;
+    let (r1, r2) = (radius(s1), radius(s2));
+    Some((frame, r1, r2))
+}
+
+/// Where each pair's points are now, and the turning frame, and, every so
+/// often, the ship's path in it.
+fn track_lagrange(
+    mut lagrange: ResMut<Lagrange>,
+    real: Res<Time<Real>>,
+    mut next: Local<f64>,
+    fixed: Res<Time<Fixed>>,
+    bodies: Query<(&OrbitalBody, &MassiveBody, Option<&SizedBody>)>,
+    ship: Query<&OrbitalBody, With<PlayerShip>>,
+) {
+    let now = real.elapsed_secs_f64();
+    let coast = lagrange.is_changed() || now >= *next;
+    if coast {
+        *next = now + LAGRANGE_INTERVAL;
+    }
+    let lagrange = lagrange.bypass_change_detection();
+
+    lagrange.points.clear();
+    for pair in &lagrange.pairs {
+        let Some((frame, ..)) = pair_frame(pair, &bodies) else {
+            continue;
+        };
+        for (name, pos) in LAGRANGE_NAMES.into_iter().zip(frame.lagrange_points()) {
+            lagrange.points.push(LagrangePoint {
+                pair: pair.name.clone(),
+                name,
+                pos,
+            });
+        }
+    }
+
+    let Some(view) = &mut lagrange.rotating else {
+        return;
+    };
+    let Some((frame, r1, r2)) = pair_frame(&view.pair, &bodies) else {
+        view.frame = None;
+        return;
+    };
+    let model = frame.model;
+    let [l1, l2, ..] = model.lagrange_points();
+    view.jacobi_l1 = model.jacobi(&l1, &Vector3::zeros());
+    view.jacobi_l2 = model.jacobi(&l2, &Vector3::zeros());
+    view.time = fixed.elapsed_secs_f64();
+    match ship.single() {
+        Ok(ship) => {
+            let (pos, vel) = frame.to_model(&ship.pos, &ship.vel);
+            view.jacobi = Some(model.jacobi(&pos, &vel));
+            if coast {
+                let span = view.span.map_or(TAU, |span| span * frame.rate);
+                view.path =
+                    model.propagate(&pos, &vel, span, PATH_SAMPLES, (r1, r2), PATH_MAX_STEPS);
+            }
+        }
+        Err(_) => {
+            view.jacobi = None;
+            view.path.clear();
+        }
+    }
+    view.frame = Some(frame);
+}
+
+fn lagrange_command(
+    In(args): In<Vec<String>>,
+    mut lagrange: ResMut<Lagrange>,
+    bodies: Pairable,
+    ship: Query<&OrbitalBody, With<PlayerShip>>,
+    states: Query<&OrbitalBody>,
+) -> ConsoleReply {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        [] => {
+            let mut lines = Vec::new();
+            for pair in &lagrange.pairs {
+                lines.push(pair.name.clone());
+                let primary = states.get(pair.primary).map_err(|e| e.to_string())?;
+                for point in lagrange.points.iter().filter(|p| p.pair == pair.name) {
+                    let from_ship = ship.single().map_or(String::new(), |ship| {
+                        format!(", {:.0} km from the ship", (point.pos - ship.pos).norm())
+                    });
+                    lines.push(format!(
+                        "  {}: {:.0} km from the primary{}",
+                        point.name,
+                        (point.pos - primary.pos).norm(),
+                        from_ship
+                    ));
+                }
+            }
+            if let Some(view) = &lagrange.rotating {
+                let jacobi = view
+                    .jacobi
+                    .map_or("-".to_string(), |jacobi| format!("{:.6}", jacobi));
+                lines.push(format!(
+                    "Turning with {}: ship's Jacobi constant {} (L1 {:.6}, L2 {:.6})",
+                    view.pair.name, jacobi, view.jacobi_l1, view.jacobi_l2
+                ));
+            }
+            if lines.is_empty() {
+                Ok("No pairs".to_string())
+            } else {
+                Ok(lines.join("\n"))
+            }
+        }
+        ["off"] => {
+            lagrange.pairs.clear();
+            lagrange.rotating = None;
+            Ok("ok".to_string())
+        }
+        ["rotating", "off"] => {
+            lagrange.rotating = None;
+            Ok("ok".to_string())
+        }
+        ["rotating", primary, secondary, rest @ ..] => {
+            let span = match rest {
+                [] => None,
+                [days] => {
+                    let days: f64 = parse_arg(days)?;
+                    if days <= 0.0 {
+                        return Err("The span has to be more than 0 days".to_string());
+                    }
+                    Some(days * 86400.0)
+                }
+                _ => return Err("lagrange rotating <primary> <secondary> [days]".to_string()),
+            };
+            let pair = find_pair(&bodies, primary, secondary)?;
+            if !lagrange.pairs.iter().any(|p| p.name == pair.name) {
+                lagrange.pairs.push(pair.clone());
+            }
+            lagrange.rotating = Some(RotatingView {
+                pair,
+                span,
+                frame: None,
+                time: 0.0,
+                path: Vec::new(),
+                jacobi: None,
+                jacobi_l1: 0.0,
+                jacobi_l2: 0.0,
+            });
+            Ok("ok".to_string())
+        }
+        [primary, secondary] => {
+            let pair = find_pair(&bodies, primary, secondary)?;
+            let before = lagrange.pairs.len();
+            lagrange.pairs.retain(|p| p.name != pair.name);
+            if lagrange.pairs.len() == before {
+                lagrange.pairs.push(pair);
+            }
+            Ok("ok".to_string())
+        }
+        _ => Err(
+            "lagrange [<primary> <secondary> | rotating <primary> <secondary> [days] | rotating off | off]"
+                .to_string(),
+        ),
+    }
+}
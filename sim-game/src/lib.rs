@@ -0,0 +1,35 @@
+//! The game: the player's ship, and everything that can be done with it.
+//!
+//! This sits on the solar system from `sim_astro`.  `sim::SimPlugins` has all
+//! of it that runs without a window, so it can be driven headlessly as well as
+//! from the game; the views are in `sim_render` and `sim_ui`.
+
+// Recommended alias.
+extern crate nalgebra as na;
+
+pub mod alarm;
+pub mod conservation;
+pub mod console;
+pub mod countdown;
+pub mod coverage;
+pub mod debris;
+pub mod drill;
+pub mod events;
+pub mod gamepad;
+pub mod keys;
+pub mod lagrange;
+pub mod observer;
+pub mod oem;
+pub mod preset;
+pub mod promote;
+pub mod range;
+pub mod recording;
+pub mod remote;
+pub mod sequence;
+pub mod ship;
+pub mod sim;
+pub mod snapshot;
+pub mod stats;
+#[cfg(feature = "stream")]
+pub mod stream;
+pub mod telemetry;
@@ -0,0 +1,340 @@
+//! Watching from the ground.
+//!
+//! An `Observer` stands at a latitude and longitude on a body, and watches
+//! some crafts and bodies go over: where each is in the sky now, and its
+//! passes over the next day, when it rises above the horizon, how high it
+//! gets, and when it sets again.  The passes are predicted along each target's
+//! conic about the observer's body (with both their GMs, so that it works for
+//! the moon and the sun, too), with the body turning underneath, as for the
+//! ground track.  That leaves out the drag and the other bodies' pulls, which
+//! is fine for planning a night's watching, but a low orbit drifts from it
+//! over the days.
+//!
+//! - `observer at <lat> <lon> [alt m] [body]`: stand somewhere (on the earth,
+//!   unless another body is named), watching the player ship.
+//! - `observer add <name>`, `observer remove <name>`: watch something else
+//!   too, or stop watching it.
+//! - `observer`: where each target is, and its passes.
+//! - `observer off`.
+//!
+//! The sky view is in `sim_ui`.
+
+use bevy::prelude::*;
+use sim_astro::{
+    EarthMarker, SolarState,
+    geodesy::{Geodetic, LookAngles},
+};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, SizedBody, orbit::propagate};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::PlayerShip,
+};
+
+/// How far ahead, in seconds, passes are predicted.
+pub const OBSERVER_SPAN: f64 = 24.0 * 3600.0;
+
+/// The time, in seconds, between samples of a target's path.  This has to be
+/// well under the shortest pass worth seeing.
+const OBSERVER_STEP: f64 = 20.0;
+
+/// How often, in real seconds, the passes are predicted again.
+const OBSERVER_INTERVAL: f64 = 1.0;
+
+/// How many times a rise or a set is halved down between samples.
+const OBSERVER_REFINE: usize = 20;
+
+/// A target's time above the horizon.  The times are in seconds past J2000.
+#[derive(Clone, Debug)]
+pub struct Pass {
+    /// When it rises, or None if it was already up when the prediction
+    /// started.
+    pub rise: Option<f64>,
+    /// When it is highest, and how high, in radians.
+    pub culmination: f64,
+    pub max_elevation: f64,
+    /// When it sets, or None if it is still up at the end of the prediction.
+    pub set: Option<f64>,
+    /// Where it goes in the sky, sampled from rise to set.
+    pub path: Vec<LookAngles>,
+}
+
+/// Something being watched.
+#[derive(Clone, Debug)]
+pub struct SkyTarget {
+    pub entity: Entity,
+    pub name: String,
+    /// Where it is in the sky now.
+    pub now: Option<LookAngles>,
+    /// Its passes, from now to `OBSERVER_SPAN` ahead.
+    pub passes: Vec<Pass>,
+}
+
+/// Someone on the ground, looking up.
+#[derive(Resource, Clone, Debug)]
+pub struct Observer {
+    /// The body they stand on.
+    pub body: Entity,
+    pub body_name: String,
+    /// Where they stand.  The altitude is above the body's ellipsoid.
+    pub site: Geodetic,
+    pub targets: Vec<SkyTarget>,
+}
+
+impl Observer {
+    fn watch(&mut self, entity: Entity, name: &str) {
+        if self.targets.iter().all(|t| t.entity != entity) {
+            self.targets.push(SkyTarget {
+                entity,
+                name: name.to_string(),
+                now: None,
+                passes: Vec::new(),
+            });
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ObserverPlugin;
+
+impl Plugin for ObserverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, track_sky);
+        app.add_console_command(
+            "observer",
+            "observer [at <lat> <lon> [alt m] [body] | add <name> | remove <name> | off]   watch from the ground",
+            observer_command,
+        );
+    }
+}
+
+/// A time from now, as h:mm:ss.
+fn clock(seconds: f64) -> String {
+    let seconds = seconds.max(0.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn observer_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    observer: Option<ResMut<Observer>>,
+    fixed: Res<Time<Fixed>>,
+    solar: Res<SolarState>,
+    named: Query<(Entity, &Name, Has<SizedBody>)>,
+    ship: Query<(Entity, &Name), With<PlayerShip>>,
+    earth: Query<(Entity, &Name), With<EarthMarker>>,
+) -> ConsoleReply {
+    let find = |name: &str| {
+        named
+            .iter()
+            .find(|(_, n, _)| n.as_str().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("No such body or craft: {:?}", name))
+    };
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match (words.as_slice(), observer) {
+        (["at", lat, lon, rest @ ..], _) => {
+            let (alt, body) = match rest {
+                [] => (0.0, None),
+                [alt] => (parse_arg(alt)?, None),
+                [alt, body] => (parse_arg(alt)?, Some(*body)),
+                _ => return Err("observer at <lat> <lon> [alt m] [body]".to_string()),
+            };
+            let (body, body_name) = match body {
+                Some(body) => {
+                    let (entity, name, sized) = find(body)?;
+                    if !sized {
+                        return Err(format!("{} has no surface to stand on", name));
+                    }
+                    (entity, name.to_string())
+                }
+                None => {
+                    let (entity, name) = earth.single().map_err(|_| "No earth".to_string())?;
+                    (entity, name.to_string())
+                }
+            };
+            let mut observer = Observer {
+                body,
+                body_name,
+                site: Geodetic {
+                    lat: parse_arg(lat)?.to_radians(),
+                    lon: parse_arg(lon)?.to_radians(),
+                    // m to km.
+                    alt: alt / 1000.0,
+                },
+                targets: Vec::new(),
+            };
+            if let Ok((entity, name)) = ship.single() {
+                observer.watch(entity, name);
+            }
+            commands.insert_resource(observer);
+            Ok("ok".to_string())
+        }
+        (["off"], _) => {
+            commands.remove_resource::<Observer>();
+            Ok("ok".to_string())
+        }
+        (["add", name], Some(mut observer)) => {
+            let (entity, name, _) = find(name)?;
+            if entity == observer.body {
+                return Err(format!("{} is underfoot", name));
+            }
+            observer.watch(entity, name);
+            Ok("ok".to_string())
+        }
+        (["remove", name], Some(mut observer)) => {
+            let (entity, ..) = find(name)?;
+            observer.targets.retain(|t| t.entity != entity);
+            Ok("ok".to_string())
+        }
+        ([], Some(observer)) => {
+            let et = solar.et + fixed.elapsed_secs_f64();
+            let mut lines = vec![format!(
+                "at {:.4} {:.4}, {:.0} m on {}",
+                observer.site.lat.to_degrees(),
+                observer.site.lon.to_degrees(),
+                observer.site.alt * 1000.0,
+                observer.body_name
+            )];
+            for target in &observer.targets {
+                if let Some(now) = target.now {
+                    lines.push(format!(
+                        "{}: az {:.1} el {:.1} range {:.0} km",
+                        target.name,
+                        now.azimuth.to_degrees(),
+                        now.elevation.to_degrees(),
+                        now.range
+                    ));
+                }
+                for pass in &target.passes {
+                    let when = |at: Option<f64>| at.map_or("-".to_string(), |at| clock(at - et));
+                    lines.push(format!(
+                        "  rise {}, highest {} at {:.1}, set {}",
+                        when(pass.rise),
+                        clock(pass.culmination - et),
+                        pass.max_elevation.to_degrees(),
+                        when(pass.set)
+                    ));
+                }
+            }
+            Ok(lines.join("\n"))
+        }
+        ([] | ["add" | "remove", _], None) => Err("No observer, try observer at".to_string()),
+        _ => Err(
+            "observer [at <lat> <lon> [alt m] [body] | add <name> | remove <name> | off]"
+                .to_string(),
+        ),
+    }
+}
+
+/// Where each target is in the observer's sky now, and, every so often, its
+/// passes.
+#[allow(clippy::type_complexity)]
+fn track_sky(
+    observer: Option<ResMut<Observer>>,
+    real: Res<Time<Real>>,
+    mut next: Local<f64>,
+    fixed: Res<Time<Fixed>>,
+    solar: Res<SolarState>,
+    bodies: Query<(
+        &OrbitalBody,
+        &AttitudeState,
+        &SizedBody,
+        Option<&MassiveBody>,
+    )>,
+    targets: Query<(&OrbitalBody, Option<&MassiveBody>)>,
+) {
+    let Some(mut observer) = observer else {
+        return;
+    };
+    let Ok((body, attitude, size, massive)) = bodies.get(observer.body) else {
+        return;
+    };
+    let now = real.elapsed_secs_f64();
+    let predict = observer.is_changed() || now >= *next;
+    if predict {
+        *next = now + OBSERVER_INTERVAL;
+    }
+    let et = solar.et + fixed.elapsed_secs_f64();
+    let site = observer.site;
+    let gm = massive.map_or(0.0, |m| m.gm);
+
+    for target in observer.bypass_change_detection().targets.iter_mut() {
+        let Ok((orbital, target_mass)) = targets.get(target.entity) else {
+            target.now = None;
+            target.passes.clear();
+            continue;
+        };
+        let pos = orbital.pos - body.pos;
+        let vel = orbital.vel - body.vel;
+        let gm = gm + target_mass.map_or(0.0, |m| m.gm);
+        let look = |dt: f64| {
+            let (p, _) = propagate(&pos, &vel, gm, dt);
+            let pos_f = attitude.q_bw_after(dt).inverse_transform_vector(&p);
+            site.look_angles(&size.radii, &pos_f)
+        };
+        target.now = Some(look(0.0));
+        if predict {
+            target.passes = passes(look, et);
+        }
+    }
+}
+
+/// The passes along `look`, the look angles `dt` seconds from now, where now
+/// is `et`.
+fn passes(look: impl Fn(f64) -> LookAngles, et: f64) -> Vec<Pass> {
+    // The time the elevation crosses the horizon between two samples.
+    let crossing = |mut before: f64, mut after: f64| {
+        let up = look(after).elevation > 0.0;
+        for _ in 0..OBSERVER_REFINE {
+            let mid = (before + after) / 2.0;
+            if (look(mid).elevation > 0.0) == up {
+                after = mid;
+            } else {
+                before = mid;
+            }
+        }
+        (before + after) / 2.0
+    };
+
+    let samples = (OBSERVER_SPAN / OBSERVER_STEP).ceil() as usize;
+    let mut passes = Vec::new();
+    let mut pass: Option<Pass> = None;
+    let mut last = 0.0;
+    for i in 0..=samples {
+        let dt = i as f64 * OBSERVER_STEP;
+        let angles = look(dt);
+        let up = angles.elevation > 0.0;
+        match (&mut pass, up) {
+            (None, true) => {
+                pass = Some(Pass {
+                    rise: (i > 0).then(|| et + crossing(last, dt)),
+                    culmination: et + dt,
+                    max_elevation: angles.elevation,
+                    set: None,
+                    path: vec![angles],
+                })
+            }
+            (Some(current), true) => {
+                if angles.elevation > current.max_elevation {
+                    current.culmination = et + dt;
+                    current.max_elevation = angles.elevation;
+                }
+                current.path.push(angles);
+            }
+            (Some(current), false) => {
+                current.set = Some(et + crossing(last, dt));
+                passes.extend(pass.take());
+            }
+            (None, false) => (),
+        }
+        last = dt;
+    }
+    passes.extend(pass);
+    passes
+}
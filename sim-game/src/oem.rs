@@ -0,0 +1,248 @@
+//! Trajectory export as a CCSDS Orbit Ephemeris Message.
+//!
+//! The console's `oem <file> [duration] [step]` command propagates the player
+//! ship from where it is now, for `duration` seconds (a day by default), with
+//! a state every `step` seconds (60 by default), and writes it out as an OEM
+//! (CCSDS 502.0-B-3) in the text (KVN) form.  A planned maneuver node is
+//! applied as an impulse, the same as the predicted path, and starts a new
+//! segment, so that nothing interpolates across it.  GMAT, STK, and
+//! cspice's `mkspk` can all read this, for checking the sim against them.
+//!
+//! The propagation is two body, about the earth.  The states are given in
+//! EME2000 (the sim works in ECLIPJ2000, which few tools take for an OEM), on
+//! the TDB time scale that SPICE gives the sim's epoch in.
+
+use bevy::prelude::*;
+use na::Vector3;
+use sim_astro::{EarthMarker, SolarState, frames};
+use sim_core::{MassiveBody, OrbitalBody, orbit::propagate};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{PlayerShip, maneuver::ManeuverNode},
+};
+
+/// A request to export the ship's trajectory.
+#[derive(Clone, Debug, Message)]
+pub struct ExportOem {
+    pub path: PathBuf,
+    /// How long to propagate for, in seconds.
+    pub duration: f64,
+    /// The time between states, in seconds.
+    pub step: f64,
+}
+
+#[derive(Default)]
+pub struct OemPlugin;
+
+impl Plugin for OemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<ExportOem>();
+        app.add_systems(Update, export_oem);
+        app.add_console_command(
+            "oem",
+            "oem <file> [duration] [step]   export the ship's trajectory",
+            oem_command,
+        );
+    }
+}
+
+fn oem_command(In(args): In<Vec<String>>, mut exports: MessageWriter<ExportOem>) -> ConsoleReply {
+    let [path, args @ ..] = args.as_slice() else {
+        return Err("oem <file> [duration] [step]".to_string());
+    };
+    let arg = |i: usize, default: f64| args.get(i).map_or(Ok(default), |a| parse_arg(a));
+    exports.write(ExportOem {
+        path: path.into(),
+        duration: arg(0, 86400.0)?,
+        step: arg(1, 60.0)?,
+    });
+    Ok("ok".to_string())
+}
+
+/// A time, in seconds past J2000, as an ISO 8601 date on the same scale.
+pub(crate) fn iso_date(et: f64) -> String {
+    // Milliseconds from midnight, January 1, 2000 (J2000 is noon).
+    let ms = ((et + 43200.0) * 1000.0).round() as i64;
+    let days = ms.div_euclid(86_400_000);
+    let ms = ms.rem_euclid(86_400_000);
+
+    // Days to a civil date, from Howard Hinnant's algorithm, counting from
+    // 2000-03-01.
+    let z = days - 60;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = 2000 + era * 400 + yoe + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}",
+        year,
+        month,
+        day,
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// An ISO 8601 date, as `iso_date` gives, or a shorter one (down to just the
+/// day), as seconds past J2000 on the same scale.
+pub(crate) fn parse_iso_date(text: &str) -> Option<f64> {
+    let (date, time) = text.split_once('T').unwrap_or((text, ""));
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut seconds = 0.0;
+    for (part, scale) in time
+        .trim_end_matches('Z')
+        .split(':')
+        .zip([3600.0, 60.0, 1.0])
+    {
+        if !part.is_empty() {
+            seconds += part.parse::<f64>().ok()? * scale;
+        }
+    }
+
+    // The other way around from `iso_date`: days from 2000-03-01, for
+    // years starting in March.
+    let year = year - 2000 - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe + 60;
+    Some(days as f64 * 86400.0 + seconds - 43200.0)
+}
+
+/// A state: time past J2000 (s), position (km), and velocity (km/s),
+/// relative to the earth, in ECLIPJ2000.
+pub type State = (f64, Vector3<f64>, Vector3<f64>);
+
+/// Write an OEM with a segment for each run of states.
+pub fn write_oem<P: AsRef<Path>>(
+    path: P,
+    object: &str,
+    segments: &[Vec<State>],
+) -> std::io::Result<()> {
+    let Some(start) = segments.first().and_then(|s| s.first()) else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "No states to export",
+        ));
+    };
+    let to_eme = frames::eme2000_to_ecliptic().inverse();
+
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "CCSDS_OEM_VERS = 3.0")?;
+    writeln!(out, "CREATION_DATE = {}", iso_date(start.0))?;
+    writeln!(out, "ORIGINATOR = scifisim")?;
+    for states in segments {
+        let (Some(first), Some(last)) = (states.first(), states.last()) else {
+            continue;
+        };
+        writeln!(out)?;
+        writeln!(out, "META_START")?;
+        writeln!(out, "OBJECT_NAME = {}", object)?;
+        writeln!(out, "OBJECT_ID = {}", object)?;
+        writeln!(out, "CENTER_NAME = EARTH")?;
+        writeln!(out, "REF_FRAME = EME2000")?;
+        writeln!(out, "TIME_SYSTEM = TDB")?;
+        writeln!(out, "START_TIME = {}", iso_date(first.0))?;
+        writeln!(out, "STOP_TIME = {}", iso_date(last.0))?;
+        writeln!(out, "META_STOP")?;
+        writeln!(out)?;
+        writeln!(out, "COMMENT Two body propagation")?;
+        for (et, pos, vel) in states {
+            let pos = to_eme * pos;
+            let vel = to_eme * vel;
+            writeln!(
+                out,
+                "{} {:.6} {:.6} {:.6} {:.9} {:.9} {:.9}",
+                iso_date(*et),
+                pos.x,
+                pos.y,
+                pos.z,
+                vel.x,
+                vel.y,
+                vel.z
+            )?;
+        }
+    }
+    out.flush()
+}
+
+fn export_oem(
+    mut requests: MessageReader<ExportOem>,
+    fixed: Res<Time<Fixed>>,
+    solar: Res<SolarState>,
+    ship: Query<(&Name, &OrbitalBody, Option<&ManeuverNode>), With<PlayerShip>>,
+    earth: Query<(&OrbitalBody, &MassiveBody), With<EarthMarker>>,
+) {
+    for request in requests.read() {
+        let (Ok((name, orbital, node)), Ok((earth, earth_mass))) = (ship.single(), earth.single())
+        else {
+            continue;
+        };
+        let gm = earth_mass.gm;
+        let now = fixed.elapsed_secs_f64();
+        let mut pos = orbital.pos - earth.pos;
+        let mut vel = orbital.vel - earth.vel;
+        let mut node = node.map(|node| (node.time, node.dv_w(&pos, &vel, gm, now)));
+
+        // Step along, stopping at the node to apply it.
+        let mut t = 0.0;
+        let mut segments = vec![vec![(solar.et + now, pos, vel)]];
+        while t < request.duration {
+            let next = (t + request.step.max(1.0e-3)).min(request.duration);
+            if let Some((time, dv_w)) = node.filter(|(time, _)| *time - now < next) {
+                let at = (time - now).max(t);
+                if at > t {
+                    (pos, vel) = propagate(&pos, &vel, gm, at - t);
+                    t = at;
+                    segments
+                        .last_mut()
+                        .unwrap()
+                        .push((solar.et + now + t, pos, vel));
+                }
+                vel += dv_w / 1000.0;
+                if segments.last().is_some_and(|s| s.len() == 1) {
+                    // The burn is right at the start, so there's nothing
+                    // before it.
+                    segments.pop();
+                }
+                segments.push(vec![(solar.et + now + t, pos, vel)]);
+                node = None;
+                continue;
+            }
+            (pos, vel) = propagate(&pos, &vel, gm, next - t);
+            t = next;
+            segments
+                .last_mut()
+                .unwrap()
+                .push((solar.et + now + t, pos, vel));
+        }
+
+        match write_oem(&request.path, name.as_str(), &segments) {
+            Ok(()) => info!(
+                "Wrote {} states to {}",
+                segments.iter().map(Vec::len).sum::<usize>(),
+                request.path.display()
+            ),
+            Err(e) => error!("Unable to write {}: {}", request.path.display(), e),
+        }
+    }
+}
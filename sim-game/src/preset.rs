@@ -0,0 +1,117 @@
+//! Physics presets.
+//!
+//! The full physics is a lot to take in when learning to fly.  The simple
+//! preset takes the edges off, by setting up the same systems differently
+//! rather than running any others:
+//!
+//! - Only the primary pulls on the ship (the `third-body` model is off), and
+//!   there is no drag or sunlight pressure, so that orbits are the conics the
+//!   prediction draws.
+//! - The RCS has `SIMPLE_RCS_BOOST` times its rated thrust, and is continuous.
+//! - The stability assist is on: with the RCS in manual, it goes to rate
+//!   command, which stops the rotation whenever no key is held.
+//!
+//! A scenario picks its preset, which snapshots keep.  Going back to the full
+//! preset turns the models back on, and the RCS back down to its rating, but
+//! leaves the RCS mode alone.
+//!
+//! - `preset`: the preset in use.
+//! - `preset full|simple`: switch.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use sim_core::PhysicsModels;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    ship::{
+        RcsMode,
+        rcs::{RcsPulsing, RcsRealism},
+    },
+};
+
+/// How many times its rated thrust each RCS thruster has in the simple
+/// preset.
+pub const SIMPLE_RCS_BOOST: f64 = 4.0;
+
+/// The models the simple preset turns off.
+const SIMPLE_MODELS_OFF: [&str; 3] = ["third-body", "drag", "srp"];
+
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PhysicsPreset {
+    /// Everything modeled.
+    #[default]
+    Full,
+    /// For new players.
+    Simple,
+}
+
+impl PhysicsPreset {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "full" => Ok(PhysicsPreset::Full),
+            "simple" => Ok(PhysicsPreset::Simple),
+            _ => Err(format!("Unknown preset {:?}, try full or simple", name)),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PhysicsPreset::Full => "full",
+            PhysicsPreset::Simple => "simple",
+        }
+    }
+
+    /// Set the sim up for this preset.
+    pub fn apply(self, models: &mut PhysicsModels, realism: &mut RcsRealism, mode: &mut RcsMode) {
+        let simple = self == PhysicsPreset::Simple;
+        for name in SIMPLE_MODELS_OFF {
+            if let Some(on) = models.0.get_mut(name) {
+                *on = !simple;
+            }
+        }
+        if simple {
+            *realism = RcsRealism {
+                thrust_limit: SIMPLE_RCS_BOOST,
+                pulsing: RcsPulsing::Continuous,
+            };
+            if matches!(mode, RcsMode::Manual) {
+                *mode = RcsMode::RateCommand;
+            }
+        } else {
+            realism.thrust_limit = realism.thrust_limit.min(1.0);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PresetPlugin;
+
+impl Plugin for PresetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhysicsPreset>();
+        app.add_console_command(
+            "preset",
+            "preset [full|simple]   show or switch the physics preset",
+            preset_command,
+        );
+    }
+}
+
+fn preset_command(
+    In(args): In<Vec<String>>,
+    mut preset: ResMut<PhysicsPreset>,
+    mut models: ResMut<PhysicsModels>,
+    mut realism: ResMut<RcsRealism>,
+    mut mode: ResMut<RcsMode>,
+) -> ConsoleReply {
+    match args.as_slice() {
+        [] => Ok(preset.name().to_string()),
+        [name] => {
+            *preset = PhysicsPreset::parse(name)?;
+            preset.apply(&mut models, &mut realism, &mut mode);
+            Ok("ok".to_string())
+        }
+        _ => Err("preset [full|simple]".to_string()),
+    }
+}
@@ -0,0 +1,169 @@
+//! Crafts into bodies, and bodies into crafts.
+//!
+//! What a thing is comes only from its components: whatever moves has an
+//! `OrbitalBody`, and turns with an `AttitudeState`; a body with a surface
+//! has a `SizedBody`, and one that pulls, a `MassiveBody`; and a craft has
+//! the controls, mass and systems of `craft_bundle`.  So a thing is made into
+//! another by trading its components for those the other is made with, in
+//! the same ways it would have been made to begin with.  It is spawned again,
+//! under the same name, where it was, going and turning as it was, and the
+//! old one goes (with its model).
+//!
+//! - `derelict <craft>`: the craft is left as a small body (see
+//!   `sim_astro::SmallBody`), as big as its mass properties make it, which
+//!   pulls if it is heavy enough to (see `ship::craft_gravity`).  It can no
+//!   longer be flown, but can be targeted, landed on, and captured again.
+//!   Not the craft being flown, nor one docked, or being docked to.
+//! - `capture <body> [kg]`: a small body becomes a craft, to be flown, docked
+//!   with, or towed, with the mass of a rocky ellipsoid its size, unless told.
+//!   It keeps its surface, to be landed on still.
+//!   The massive bodies are out of reach: the solar system hangs on them.
+
+use bevy::prelude::*;
+use na::Vector3;
+use sim_astro::SmallBodyMarker;
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, SizedBody};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{
+        Craft, MassProperties, PlayerShip, craft_bundle,
+        craft_gravity::{CraftGravity, G, ROCK_DENSITY},
+        docking::Docked,
+        rcs::RcsRealism,
+    },
+};
+
+#[derive(Default)]
+pub struct PromotePlugin;
+
+impl Plugin for PromotePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command(
+            "derelict",
+            "derelict <craft>   leave a craft as a small body, that can't be flown",
+            derelict_command,
+        );
+        app.add_console_command(
+            "capture",
+            "capture <body> [kg]   make a small body into a craft",
+            capture_command,
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn derelict_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    settings: Res<CraftGravity>,
+    crafts: Query<
+        (
+            Entity,
+            &Name,
+            &OrbitalBody,
+            &AttitudeState,
+            &MassProperties,
+            Has<PlayerShip>,
+            Has<Docked>,
+        ),
+        With<Craft>,
+    >,
+    docked: Query<&Docked>,
+) -> ConsoleReply {
+    let [name] = args.as_slice() else {
+        return Err("derelict <craft>".to_string());
+    };
+    let (entity, name, orbital, attitude, mass, player, is_docked) = crafts
+        .iter()
+        .find(|(_, craft, ..)| craft.as_str().eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("No such craft as {:?}", name))?;
+    if player {
+        return Err(format!("{} is being flown: focus on another first", name));
+    }
+    if is_docked || docked.iter().any(|docked| docked.host == entity) {
+        return Err(format!("{} is docked: undock it first", name));
+    }
+
+    // m to km.
+    let radii = mass.equivalent_ellipsoid().map(|r| r.max(1.0)) / 1000.0;
+    let mut body = commands.spawn((
+        name.clone(),
+        orbital.clone(),
+        attitude.clone(),
+        SizedBody { radii },
+        SmallBodyMarker,
+    ));
+    let heavy = mass.mass >= settings.threshold;
+    if heavy {
+        body.insert(MassiveBody { gm: G * mass.mass });
+    }
+    commands.entity(entity).despawn();
+    Ok(format!(
+        "{} is a derelict, {:.0} m across{}",
+        name,
+        radii.max() * 2000.0,
+        if heavy { ", and pulls" } else { "" }
+    ))
+}
+
+#[allow(clippy::type_complexity)]
+fn capture_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    realism: Res<RcsRealism>,
+    bodies: Query<
+        (
+            Entity,
+            &Name,
+            &OrbitalBody,
+            &AttitudeState,
+            Option<&SizedBody>,
+            Has<MassiveBody>,
+        ),
+        (With<SmallBodyMarker>, Without<Craft>),
+    >,
+) -> ConsoleReply {
+    let (name, kg) = match args.as_slice() {
+        [name] => (name, None),
+        [name, kg] => (name, Some(parse_arg(kg)?)),
+        _ => return Err("capture <body> [kg]".to_string()),
+    };
+    let (entity, name, orbital, attitude, size, massive) = bodies
+        .iter()
+        .find(|(_, body, ..)| body.as_str().eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("No small body called {:?}", name))?;
+
+    // km to m.
+    let radii = size.map_or_else(|| Vector3::repeat(1.0), |size| size.radii * 1000.0);
+    let kg = match kg {
+        Some(kg) if kg > 0.0 => kg,
+        Some(_) => return Err("The mass must be more than 0 kg".to_string()),
+        None if size.is_some() => {
+            4.0 / 3.0 * std::f64::consts::PI * radii.x * radii.y * radii.z * ROCK_DENSITY
+        }
+        None => {
+            return Err(format!(
+                "{} has no size to weigh it by: give its mass",
+                name
+            ));
+        }
+    };
+    let mut craft = commands.spawn(craft_bundle(
+        name,
+        orbital.clone(),
+        attitude.clone(),
+        &realism,
+    ));
+    craft.insert(MassProperties::ellipsoid(kg, &radii));
+    if let Some(size) = size {
+        craft.insert(size.clone());
+    }
+    commands.entity(entity).despawn();
+    Ok(format!(
+        "{} is a craft, of {:.3e} kg{}",
+        name,
+        kg,
+        if massive { ", and no longer pulls" } else { "" }
+    ))
+}
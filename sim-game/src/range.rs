@@ -0,0 +1,441 @@
+//! Range safety.
+//!
+//! The range is a set of boundaries over the earth that the player ship is
+//! to keep in, or out of: a region of latitude, longitude and altitude, a
+//! sphere about a point (such as a town under the ascent), or a corridor
+//! along the ground track, from a start to an end, and so wide either side.
+//! A corridor holds the craft only as far as its end: past that, it has left
+//! the range, and is on its own.
+//!
+//! Every physics step, the craft is checked against each boundary.  On
+//! crossing one the wrong way, it is violated: a `RangeViolation` is sent,
+//! for a scenario to act on or score, it is said in the console, and the
+//! boundary's command, if it has one, is run, such as `breakup`, for flight
+//! termination, or `countdown abort`.  The craft coming back the right side
+//! of it clears it, to be violated again.  A craft that has broken up, or
+//! crashed, isn't checked.  The boundaries, and how many times each has been
+//! violated, are kept in snapshots.
+//!
+//! Latitudes and longitudes are geodetic, in degrees, altitudes in km, and
+//! distances in km.
+//!
+//! - `range`: the boundaries, and which are violated.
+//! - `range in|out <name> region <lat> <lat> <lon> <lon> <alt> <alt>`: keep
+//!   in, or out of, a region.  A longitude range from east round to west
+//!   goes over the antimeridian.
+//! - `range in|out <name> sphere <lat> <lon> <alt> <radius>`: a sphere.
+//! - `range in|out <name> corridor <lat> <lon> <lat> <lon> <width>`: a
+//!   corridor, from one point to the other, `width` either side.
+//! - `range on <name> [<command...>]`: the command to run on violating the
+//!   boundary, or none.
+//! - `range remove <name>`, `range clear`.
+//! - `range load <file>`: add the boundaries in a file, one to a line, as for
+//!   `range in|out` and `range on`.  Blank lines, and those starting with
+//!   `#`, are skipped.
+
+use bevy::prelude::*;
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+use sim_astro::{
+    EarthMarker, SolarState,
+    geodesy::{Geodetic, world_to_body},
+};
+use sim_core::{AttitudeState, OrbitalBody, PostPhysicsSet, SizedBody, watchdog::Frozen};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, log_reply, parse_arg, run_line},
+    oem::iso_date,
+    ship::PlayerShip,
+};
+
+/// Which side of a boundary the craft is to keep.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Keep {
+    In,
+    Out,
+}
+
+impl Keep {
+    pub fn name(self) -> &'static str {
+        match self {
+            Keep::In => "in",
+            Keep::Out => "out",
+        }
+    }
+}
+
+/// A volume over the earth.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Volume {
+    /// Between the latitudes, longitudes (east from the first to the second)
+    /// and altitudes.
+    Region {
+        lat: [f64; 2],
+        lon: [f64; 2],
+        alt: [f64; 2],
+    },
+    /// Within `radius` of a point.
+    Sphere {
+        lat: f64,
+        lon: f64,
+        alt: f64,
+        radius: f64,
+    },
+    /// Within `width` either side of the ground track from one point, as
+    /// (latitude, longitude), to the other, at any altitude, or anywhere
+    /// past the end.
+    Corridor {
+        from: [f64; 2],
+        to: [f64; 2],
+        width: f64,
+    },
+}
+
+impl Volume {
+    /// Whether `pos_f`, in the earth's fixed frame, is in it, over the
+    /// ellipsoid with `radii`.
+    pub fn contains(&self, pos_f: &Vector3<f64>, radii: &Vector3<f64>) -> bool {
+        let ground = |lat: f64, lon: f64| {
+            Geodetic {
+                lat: lat.to_radians(),
+                lon: lon.to_radians(),
+                alt: 0.0,
+            }
+            .up()
+        };
+        match self {
+            Volume::Region { lat, lon, alt } => {
+                let at = Geodetic::from_body(pos_f, radii);
+                let (at_lat, at_lon) = (at.lat.to_degrees(), at.lon.to_degrees());
+                // East of the first longitude, and no further than the second.
+                let span = (lon[1] - lon[0]).rem_euclid(360.0);
+                (lat[0]..=lat[1]).contains(&at_lat)
+                    && (at_lon - lon[0]).rem_euclid(360.0) <= span
+                    && (alt[0]..=alt[1]).contains(&at.alt)
+            }
+            Volume::Sphere {
+                lat,
+                lon,
+                alt,
+                radius,
+            } => {
+                let center = Geodetic {
+                    lat: lat.to_radians(),
+                    lon: lon.to_radians(),
+                    alt: *alt,
+                }
+                .to_body(radii);
+                (pos_f - center).norm() <= *radius
+            }
+            Volume::Corridor { from, to, width } => {
+                // Along, and across, the great circle from `from` to `to`, as
+                // angles, at the equatorial radius.
+                let (start, end) = (ground(from[0], from[1]), ground(to[0], to[1]));
+                let Some(pole) = start.cross(&end).try_normalize(1.0e-12) else {
+                    return true;
+                };
+                let here = pos_f.normalize();
+                let across = here.dot(&pole).clamp(-1.0, 1.0).asin() * radii.x;
+                let along = here.dot(&pole.cross(&start)).atan2(here.dot(&start)) * radii.x;
+                let length = start.angle(&end) * radii.x;
+                along > length || (along >= -width && across.abs() <= *width)
+            }
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Volume::Region { lat, lon, alt } => format!(
+                "region {:.3}..{:.3} N, {:.3}..{:.3} E, {:.1}..{:.1} km",
+                lat[0], lat[1], lon[0], lon[1], alt[0], alt[1]
+            ),
+            Volume::Sphere {
+                lat,
+                lon,
+                alt,
+                radius,
+            } => format!(
+                "sphere {:.1} km about {:.3} N {:.3} E, {:.1} km",
+                radius, lat, lon, alt
+            ),
+            Volume::Corridor { from, to, width } => format!(
+                "corridor {:.3} N {:.3} E to {:.3} N {:.3} E, {:.1} km either side",
+                from[0], from[1], to[0], to[1], width
+            ),
+        }
+    }
+}
+
+/// A boundary on the range.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Boundary {
+    pub name: String,
+    pub keep: Keep,
+    pub volume: Volume,
+    /// The command line to run on violating it, as typed.
+    pub on: Option<String>,
+    /// Whether the craft is the wrong side of it.
+    pub violated: bool,
+    /// How many times it has been violated.
+    pub violations: u32,
+}
+
+impl Boundary {
+    pub fn describe(&self) -> String {
+        let on = self
+            .on
+            .as_ref()
+            .map_or(String::new(), |line| format!(", on violation: {}", line));
+        format!(
+            "{}: keep {} {}{}",
+            self.name,
+            self.keep.name(),
+            self.volume.describe(),
+            on
+        )
+    }
+}
+
+/// The boundaries on the range, in the order they were set.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct RangeSafety(pub Vec<Boundary>);
+
+impl RangeSafety {
+    fn find(&mut self, name: &str) -> Result<&mut Boundary, String> {
+        self.0
+            .iter_mut()
+            .find(|boundary| boundary.name == name)
+            .ok_or_else(|| format!("There is no boundary {}", name))
+    }
+}
+
+/// A boundary being violated.
+#[derive(Clone, Debug, Message)]
+pub struct RangeViolation {
+    /// When, in ET.
+    pub et: f64,
+    pub boundary: String,
+    pub keep: Keep,
+    /// Where the craft was, geodetic.
+    pub at: Geodetic,
+}
+
+#[derive(Default)]
+pub struct RangePlugin;
+
+impl Plugin for RangePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RangeSafety>();
+        app.add_message::<RangeViolation>();
+        app.add_systems(FixedUpdate, check_range.after(PostPhysicsSet));
+        app.add_console_command(
+            "range",
+            "range [in|out <name> region|sphere|corridor <args...> | on <name> [<command...>] | \
+             remove <name> | clear | load <file>]   range safety boundaries",
+            range_command,
+        );
+    }
+}
+
+/// Check the craft against each boundary, after the step, and act on those
+/// it has just violated.
+fn check_range(world: &mut World) {
+    if world.resource::<RangeSafety>().0.is_empty() {
+        return;
+    }
+    let mut ship = world.query_filtered::<&OrbitalBody, (With<PlayerShip>, Without<Frozen>)>();
+    let Ok(orbital) = ship.single(world) else {
+        return;
+    };
+    let pos = orbital.pos;
+    let mut earth =
+        world.query_filtered::<(&OrbitalBody, &AttitudeState, &SizedBody), With<EarthMarker>>();
+    let Ok((earth, earth_attitude, earth_size)) = earth.single(world) else {
+        return;
+    };
+    let pos_f = world_to_body(earth, earth_attitude, &pos);
+    let radii = earth_size.radii;
+    let fixed = world.resource::<Time<Fixed>>();
+    let et = world.resource::<SolarState>().et + fixed.elapsed_secs_f64();
+
+    let mut violated = Vec::new();
+    for boundary in world.resource_mut::<RangeSafety>().0.iter_mut() {
+        let inside = boundary.volume.contains(&pos_f, &radii);
+        let wrong = inside != (boundary.keep == Keep::In);
+        if wrong && !boundary.violated {
+            boundary.violations += 1;
+            violated.push(boundary.clone());
+        }
+        boundary.violated = wrong;
+    }
+    if violated.is_empty() {
+        return;
+    }
+    let at = Geodetic::from_body(&pos_f, &radii);
+    for boundary in violated {
+        warn!("Range violation: {}", boundary.name);
+        let place = format!(
+            "{:.3} N {:.3} E, {:.1} km",
+            at.lat.to_degrees(),
+            at.lon.to_degrees(),
+            at.alt
+        );
+        log_reply(
+            world,
+            &format!("range {}", boundary.describe()),
+            Ok(format!(
+                "RANGE: {} violated at {}, {}",
+                boundary.name,
+                iso_date(et),
+                place
+            )),
+        );
+        world.write_message(RangeViolation {
+            et,
+            boundary: boundary.name.clone(),
+            keep: boundary.keep,
+            at,
+        });
+        if let Some(line) = &boundary.on {
+            let reply = run_line(world, line);
+            log_reply(world, line, reply);
+        }
+    }
+}
+
+/// Read numbers from words.
+fn numbers<const N: usize>(words: &[&str]) -> Result<[f64; N], String> {
+    if words.len() != N {
+        return Err(format!("Expected {} numbers, not {}", N, words.len()));
+    }
+    let mut values = [0.0; N];
+    for (value, word) in values.iter_mut().zip(words) {
+        *value = parse_arg(word)?;
+    }
+    Ok(values)
+}
+
+/// Read a volume, as `region|sphere|corridor <args...>`.
+fn parse_volume(words: &[&str]) -> Result<Volume, String> {
+    match words {
+        ["region", args @ ..] => {
+            let [lat0, lat1, lon0, lon1, alt0, alt1] = numbers(args)?;
+            Ok(Volume::Region {
+                lat: [lat0.min(lat1), lat0.max(lat1)],
+                lon: [lon0, lon1],
+                alt: [alt0.min(alt1), alt0.max(alt1)],
+            })
+        }
+        ["sphere", args @ ..] => {
+            let [lat, lon, alt, radius] = numbers(args)?;
+            Ok(Volume::Sphere {
+                lat,
+                lon,
+                alt,
+                radius,
+            })
+        }
+        ["corridor", args @ ..] => {
+            let [lat0, lon0, lat1, lon1, width] = numbers(args)?;
+            Ok(Volume::Corridor {
+                from: [lat0, lon0],
+                to: [lat1, lon1],
+                width,
+            })
+        }
+        _ => Err(
+            "region <lat> <lat> <lon> <lon> <alt> <alt> | sphere <lat> <lon> <alt> <radius> | \
+             corridor <lat> <lon> <lat> <lon> <width>"
+                .to_string(),
+        ),
+    }
+}
+
+/// Set a boundary, or what is run on violating one, from `in|out <name>
+/// <volume...>` or `on <name> [<command...>]`.
+fn set_boundary(range: &mut RangeSafety, words: &[&str]) -> Result<String, String> {
+    match words {
+        [keep @ ("in" | "out"), name, volume @ ..] => {
+            let keep = if *keep == "in" { Keep::In } else { Keep::Out };
+            let volume = parse_volume(volume)?;
+            // Changing one keeps its command.
+            let on = range
+                .find(name)
+                .ok()
+                .and_then(|boundary| boundary.on.clone());
+            range.0.retain(|boundary| boundary.name != *name);
+            let boundary = Boundary {
+                name: name.to_string(),
+                keep,
+                volume,
+                on,
+                violated: false,
+                violations: 0,
+            };
+            let reply = boundary.describe();
+            range.0.push(boundary);
+            Ok(reply)
+        }
+        ["on", name, line @ ..] => {
+            let boundary = range.find(name)?;
+            boundary.on = (!line.is_empty()).then(|| line.join(" "));
+            Ok(boundary.describe())
+        }
+        _ => Err(
+            "range in|out <name> region|sphere|corridor <args...> | on <name> [<command...>]"
+                .to_string(),
+        ),
+    }
+}
+
+fn range_command(In(args): In<Vec<String>>, mut range: ResMut<RangeSafety>) -> ConsoleReply {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        [] => {
+            if range.0.is_empty() {
+                return Ok("No boundaries".to_string());
+            }
+            Ok(range
+                .0
+                .iter()
+                .map(|boundary| {
+                    let state = if boundary.violated { "VIOLATED" } else { "ok" };
+                    format!(
+                        "{} ({}, {} violations)",
+                        boundary.describe(),
+                        state,
+                        boundary.violations
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        ["remove", name] => {
+            range.find(name)?;
+            range.0.retain(|boundary| boundary.name != *name);
+            Ok(format!("removed {}", name))
+        }
+        ["clear"] => {
+            range.0.clear();
+            Ok("ok".to_string())
+        }
+        ["load", path] => {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+            // Check it all before changing any of it.
+            let mut loaded = range.clone();
+            for (number, line) in text.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let words: Vec<&str> = line.split_whitespace().collect();
+                set_boundary(&mut loaded, &words)
+                    .map_err(|e| format!("{}:{}: {}", path, number + 1, e))?;
+            }
+            *range = loaded;
+            Ok(format!("{} boundaries", range.0.len()))
+        }
+        words => set_boundary(&mut range, words),
+    }
+}
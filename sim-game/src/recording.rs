@@ -0,0 +1,249 @@
+//! Recording and replay.
+//!
+//! `scifisim --record <file>` writes the state the sim starts in, and then,
+//! for every frame, how much time it covered, how many physics steps it ran,
+//! and what keys and console commands came in.  `scifisim --replay <file>`
+//! starts from that state, and runs each frame for exactly the recorded
+//! steps (see `sim_core::clock`), with the recorded input in place of the
+//! real keyboard and console.  As the physics only runs on the fixed step,
+//! in a fixed order, the replay follows the original step for step, on any
+//! machine, however fast it is, which makes it good for chasing down a bug,
+//! or looking back over a mission.  Once the recording runs out, the sim
+//! carries on live.
+//!
+//! A recording from before the steps were written down is replayed by its
+//! times, which comes to the same steps, as the clock counts them in whole
+//! nanoseconds.
+//!
+//! The file is JSON lines: the start `Snapshot`, then one line per frame.
+//! Each frame is written as it happens, so a recording survives a crash.
+
+use bevy::{
+    app::RunFixedMainLoopSystems,
+    ecs::schedule::{LogLevel, ScheduleBuildSettings},
+    prelude::*,
+    time::TimeUpdateStrategy,
+};
+use serde::{Deserialize, Serialize};
+use sim_astro::loading::SimPhase;
+use sim_core::clock::SimClock;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::Mutex,
+    time::Duration,
+};
+
+use crate::{
+    console::{ConsoleLines, read_console},
+    keys::{key_code, key_name},
+    snapshot::{SimState, Snapshot},
+};
+
+/// Keys are written by name, such as "KeyW".
+mod key_names {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(keys: &[KeyCode], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(keys.iter().map(key_name))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<KeyCode>, D::Error> {
+        let names = Vec::<String>::deserialize(d)?;
+        names
+            .iter()
+            .map(|name| {
+                key_code(name)
+                    .ok_or_else(|| serde::de::Error::custom(format!("Unknown key {:?}", name)))
+            })
+            .collect()
+    }
+}
+
+/// One frame of input.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// The real time the frame covered.
+    pub delta: Duration,
+    /// The physics steps it ran, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steps: Option<u64>,
+    #[serde(default, with = "key_names", skip_serializing_if = "Vec::is_empty")]
+    pub pressed: Vec<KeyCode>,
+    #[serde(default, with = "key_names", skip_serializing_if = "Vec::is_empty")]
+    pub released: Vec<KeyCode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub console: Vec<String>,
+}
+
+/// Where the frames are being written.
+#[derive(Resource)]
+struct Recorder(Mutex<BufWriter<File>>);
+
+/// A recording being played back.
+#[derive(Resource)]
+struct Replay {
+    frames: Vec<RecordedFrame>,
+    next: usize,
+    keys: ButtonInput<KeyCode>,
+}
+
+/// The ways the sim can be recorded or replayed.
+pub enum RecordingPlugin {
+    Record(File),
+    Replay(Box<Snapshot>, Vec<RecordedFrame>),
+}
+
+impl RecordingPlugin {
+    pub fn record<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Ok(RecordingPlugin::Record(File::create(path)?))
+    }
+
+    pub fn replay<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let start: Snapshot = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Empty recording",
+                ));
+            }
+        };
+        let frames = lines
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<std::io::Result<_>>()?;
+        Ok(RecordingPlugin::Replay(Box::new(start), frames))
+    }
+}
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        // Anything left to run in whatever order the scheduler picks would
+        // make the replay drift, so report it.
+        app.edit_schedule(FixedUpdate, |schedule| {
+            schedule.set_build_settings(ScheduleBuildSettings {
+                ambiguity_detection: LogLevel::Warn,
+                ..default()
+            });
+        });
+
+        match self {
+            RecordingPlugin::Record(file) => {
+                let file = file.try_clone().expect("Unable to use the recording file");
+                app.insert_resource(Recorder(Mutex::new(BufWriter::new(file))));
+                // Once everything is set up, which may be after loading (see
+                // `sim_astro::loading`).
+                app.add_systems(
+                    OnEnter(SimPhase::Running),
+                    record_start.after(crate::snapshot::load_at_start),
+                );
+                // Once the frame's steps are run, and before the console
+                // lines are.
+                app.add_systems(
+                    RunFixedMainLoop,
+                    record_frame
+                        .in_set(RunFixedMainLoopSystems::AfterFixedMainLoop)
+                        .run_if(in_state(SimPhase::Running)),
+                );
+            }
+            RecordingPlugin::Replay(start, frames) => {
+                // The start is loaded like any other snapshot.
+                app.insert_resource((**start).clone());
+                if let Some(first) = frames.first() {
+                    app.insert_resource(TimeUpdateStrategy::ManualDuration(first.delta));
+                }
+                app.insert_resource(Replay {
+                    frames: frames.clone(),
+                    next: 0,
+                    keys: ButtonInput::default(),
+                });
+                app.add_systems(
+                    PreUpdate,
+                    replay_frame
+                        .after(bevy::input::InputSystems)
+                        .after(read_console),
+                );
+            }
+        }
+    }
+}
+
+fn record_start(recorder: Res<Recorder>, state: SimState) {
+    let Ok(mut out) = recorder.0.lock() else {
+        return;
+    };
+    let written = serde_json::to_writer(&mut *out, &state.save())
+        .map_err(std::io::Error::from)
+        .and_then(|()| writeln!(out))
+        .and_then(|()| out.flush());
+    if let Err(e) = written {
+        error!("Unable to write the recording: {}", e);
+    }
+}
+
+fn record_frame(
+    recorder: Res<Recorder>,
+    time: Res<Time<Real>>,
+    clock: Res<SimClock>,
+    kb: Res<ButtonInput<KeyCode>>,
+    console: Res<ConsoleLines>,
+) {
+    let Ok(mut out) = recorder.0.lock() else {
+        return;
+    };
+    let frame = RecordedFrame {
+        delta: time.delta(),
+        steps: Some(clock.frame_steps),
+        pressed: kb.get_just_pressed().copied().collect(),
+        released: kb.get_just_released().copied().collect(),
+        console: console.0.clone(),
+    };
+    let written = serde_json::to_writer(&mut *out, &frame)
+        .map_err(std::io::Error::from)
+        .and_then(|()| writeln!(out))
+        .and_then(|()| out.flush());
+    if let Err(e) = written {
+        error!("Unable to write the recording: {}", e);
+    }
+}
+
+/// Put this frame's recorded input in place of the real input, have the clock
+/// take its steps, and set up the time step for the next.
+fn replay_frame(
+    mut commands: Commands,
+    replay: Option<ResMut<Replay>>,
+    mut clock: ResMut<SimClock>,
+    mut kb: ResMut<ButtonInput<KeyCode>>,
+    mut console: ResMut<ConsoleLines>,
+) {
+    let Some(mut replay) = replay else {
+        return;
+    };
+    let replay = &mut *replay;
+    let Some(frame) = replay.frames.get(replay.next) else {
+        info!("Replay finished, now live");
+        commands.insert_resource(TimeUpdateStrategy::Automatic);
+        commands.remove_resource::<Replay>();
+        return;
+    };
+    replay.keys.clear();
+    for key in &frame.pressed {
+        replay.keys.press(*key);
+    }
+    for key in &frame.released {
+        replay.keys.release(*key);
+    }
+    *kb = replay.keys.clone();
+    console.0 = frame.console.clone();
+    if let Some(steps) = frame.steps {
+        clock.take(steps);
+    }
+
+    replay.next += 1;
+    if let Some(next) = replay.frames.get(replay.next) {
+        commands.insert_resource(TimeUpdateStrategy::ManualDuration(next.delta));
+    }
+}
@@ -0,0 +1,217 @@
+//! Flying a distant craft from the ground, at the speed of light.
+//!
+//! With remote control on, the player is at a ground station on a body (the
+//! earth, by default), and the craft is as far from them as it is.  The
+//! commands that fly the craft (`UPLINKED`, such as `throttle` and `mode`)
+//! are sent up, and go in the command queue (see `sequence`) to run when
+//! they get there, the one-way light time later.  Commands queued with `at`
+//! have to be due no sooner than that, and only those can be taken off the
+//! queue again.  The keys that fly the craft do nothing, so that it all goes
+//! through the console, and a burn at Mars is flown as a sequence, sent on
+//! ahead.
+//!
+//! What comes back is as late: the HUD and the navball show the position,
+//! attitude, and attitude mode the craft sent a light time ago (or the first
+//! it sent, until then).
+//!
+//! The light time is from the craft to the middle of the station's body, as
+//! they are at the moment.  About the earth, it is a small fraction of a
+//! second; at Mars, it is from 3 to 22 minutes.
+//!
+//! - `remote`: whether it is on, and the light time.
+//! - `remote on [body]`, `remote off`.
+
+use bevy::prelude::*;
+use sim_astro::SolarState;
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, PostPhysicsSet};
+use std::collections::VecDeque;
+
+use crate::{
+    console::{ConsoleApp, ConsoleLines, ConsoleReply, log_reply},
+    sequence::CommandQueue,
+    ship::{
+        PlayerShip, RcsMode, RotationKeys,
+        nav::{NavSource, Navigation},
+    },
+};
+
+/// In km/s.
+pub const LIGHT_SPEED: f64 = 299_792.458;
+
+/// The commands that fly the craft, and so go up to it.
+pub const UPLINKED: [&str; 12] = [
+    "ascent",
+    "autopilot",
+    "gimbal",
+    "lunar",
+    "mode",
+    "nav",
+    "stage",
+    "target",
+    "throttle",
+    "torch",
+    "transfer",
+    "undock",
+];
+
+/// What the craft sends down, each physics step.
+#[derive(Clone, Debug)]
+pub struct Telemetry {
+    /// The `Time<Fixed>` elapsed when it was sent.
+    pub elapsed: f64,
+    /// As the craft knows it, by its `NavSource`.
+    pub orbital: OrbitalBody,
+    pub attitude: AttitudeState,
+    pub mode: RcsMode,
+}
+
+/// Whether the player is flying from the ground, and how far behind the
+/// craft they are.
+#[derive(Resource, Debug, Default)]
+pub struct RemoteControl {
+    /// The body the ground station is on, if remote control is on.
+    pub station: Option<String>,
+    /// The one-way light time, in seconds.
+    pub delay: f64,
+    /// The telemetry on its way down, from the latest heard.
+    downlink: VecDeque<Telemetry>,
+}
+
+impl RemoteControl {
+    pub fn active(&self) -> bool {
+        self.station.is_some()
+    }
+
+    /// The soonest, in ET, that a command sent at `now` can reach the craft.
+    pub fn arrival(&self, now: f64) -> f64 {
+        if self.active() { now + self.delay } else { now }
+    }
+
+    /// The telemetry heard at `now`, on the `Time<Fixed>` clock, if remote
+    /// control is on.
+    pub fn heard(&self, now: f64) -> Option<&Telemetry> {
+        let sent = self
+            .downlink
+            .partition_point(|t| t.elapsed <= now - self.delay);
+        self.downlink.get(sent.saturating_sub(1))
+    }
+}
+
+/// Whether the player is at the craft's own controls: a run condition for
+/// the keys that fly it.
+pub fn local_control(remote: Option<Res<RemoteControl>>) -> bool {
+    remote.is_none_or(|remote| !remote.active())
+}
+
+#[derive(Default)]
+pub struct RemotePlugin;
+
+impl Plugin for RemotePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RemoteControl>();
+        app.add_systems(FixedUpdate, track_remote.after(PostPhysicsSet));
+        app.add_systems(Update, uplink.before(crate::console::run_console));
+        app.add_console_command(
+            "remote",
+            "remote [on [body] | off]   fly the ship from the ground, a light time behind it",
+            remote_command,
+        );
+    }
+}
+
+/// Work out the light time, and send down this step's telemetry.
+fn track_remote(
+    fixed: Res<Time<Fixed>>,
+    mode: Res<RcsMode>,
+    nav_source: Res<NavSource>,
+    mut remote: ResMut<RemoteControl>,
+    ship: Query<(&OrbitalBody, &AttitudeState, Option<&Navigation>), With<PlayerShip>>,
+    bodies: Query<(&Name, &OrbitalBody), With<MassiveBody>>,
+) {
+    let Some(station) = &remote.station else {
+        return;
+    };
+    let Ok((orbital, attitude, nav)) = ship.single() else {
+        return;
+    };
+    let Some((_, body)) = bodies.iter().find(|(name, _)| name.as_str() == station) else {
+        return;
+    };
+    let now = fixed.elapsed_secs_f64();
+    remote.delay = (orbital.pos - body.pos).norm() / LIGHT_SPEED;
+    let (orbital, attitude) = nav_source.view(nav, orbital, attitude);
+    remote.downlink.push_back(Telemetry {
+        elapsed: now,
+        orbital,
+        attitude,
+        mode: *mode,
+    });
+    // Keep the latest heard, and everything after it.
+    while remote
+        .downlink
+        .get(1)
+        .is_some_and(|t| t.elapsed <= now - remote.delay)
+    {
+        remote.downlink.pop_front();
+    }
+}
+
+/// Send the commands that fly the craft up to it, rather than running them.
+fn uplink(world: &mut World) {
+    if !world.resource::<RemoteControl>().active() {
+        return;
+    }
+    let lines = std::mem::take(&mut world.resource_mut::<ConsoleLines>().0);
+    let (sent, kept): (Vec<_>, Vec<_>) = lines.into_iter().partition(|line| {
+        line.split_whitespace()
+            .next()
+            .is_some_and(|name| UPLINKED.contains(&name))
+    });
+    world.resource_mut::<ConsoleLines>().0 = kept;
+
+    let now =
+        world.resource::<SolarState>().et + world.resource::<Time<Fixed>>().elapsed_secs_f64();
+    let arrival = world.resource::<RemoteControl>().arrival(now);
+    for line in sent {
+        world
+            .resource_mut::<CommandQueue>()
+            .push(arrival, line.clone());
+        let reply = Ok(format!("sent, arriving in {:.1} s", arrival - now));
+        log_reply(world, &line, reply);
+    }
+}
+
+fn remote_command(
+    In(args): In<Vec<String>>,
+    mut remote: ResMut<RemoteControl>,
+    mut keys: ResMut<RotationKeys>,
+    ship: Query<&OrbitalBody, With<PlayerShip>>,
+    bodies: Query<(&Name, &OrbitalBody), With<MassiveBody>>,
+) -> ConsoleReply {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        [] => {}
+        ["on"] | ["on", _] => {
+            let wanted = words.get(1).copied().unwrap_or("EARTH");
+            let (name, body) = bodies
+                .iter()
+                .find(|(name, _)| name.as_str().eq_ignore_ascii_case(wanted))
+                .ok_or_else(|| format!("No such body as {:?}", wanted))?;
+            let ship = ship.single().map_err(|_| "no ship".to_string())?;
+            remote.station = Some(name.to_string());
+            remote.delay = (ship.pos - body.pos).norm() / LIGHT_SPEED;
+            remote.downlink.clear();
+            // Let go of anything held down, as the keys are now ignored.
+            keys.0 = Default::default();
+        }
+        ["off"] => {
+            remote.station = None;
+            remote.downlink.clear();
+        }
+        _ => return Err("remote [on [body] | off]".to_string()),
+    }
+    Ok(match &remote.station {
+        Some(station) => format!("remote from {}, {:.3} s light time", station, remote.delay),
+        None => "remote off".to_string(),
+    })
+}
@@ -0,0 +1,309 @@
+//! Commands run at set times.
+//!
+//! Any console command can be queued to run at a time on the sim's clock
+//! (ET, seconds past J2000), rather than now, so that a sequence of them,
+//! such as a burn's throttle up, attitude mode, and staging, plays out the
+//! same however the sim is flown.  Due commands are run at the start of the
+//! first physics step at or after their time, ahead of everything else in
+//! the step, so warp and the frame rate don't move them.  Ones due at the
+//! same time run in the order they were queued.  The queue is kept in
+//! snapshots, and as it is on the same clock as their epoch, a saved game
+//! picks up its sequence where it left off.
+//!
+//! A time is ET, an ISO 8601 date (as `oem` writes them, on the same scale),
+//! or `+<seconds>` from now, or with `met`, a mission elapsed time, as for
+//! `alarm met`.  A time already gone runs on the next step.
+//!
+//! A command can wait on an event instead (see `events`), such as the next
+//! apoapsis, or going into the moon's sphere of influence, to be queued for
+//! the step after the event is seen, once.  An event is as `alarm before`
+//! takes them, `<kind>[:<text>]`.  Those are kept in snapshots, too, so that
+//! a scenario can carry its whole mission, to be flown unattended, in warp.
+//!
+//! - `at`: the queue, and the commands waiting on events after it.
+//! - `at <time> <command...>`: queue a command, such as `at +60 throttle
+//!   100`.
+//! - `at met <time> <command...>`: queue a command at a mission elapsed time.
+//! - `at on <event> <command...>`: run a command after the next event, such
+//!   as `at on apoapsis circularize`.
+//! - `at load <file>`: queue each line of a file, as `<time> <command...>`,
+//!   `met <time> <command...>`, or `on <event> <command...>`.  Blank lines,
+//!   and those starting with `#`, are skipped, and `+` times are from when the
+//!   file is loaded.
+//! - `at remove <n>`, `at clear`: take a command off the queue, or all of
+//!   them.
+//!
+//! Flying remotely (see `remote`), the queue is on the craft, and the
+//! commands sent up to it wait in it until they get there.  Nothing due
+//! sooner than a command sent now could get there can be queued, or taken
+//! off again.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use sim_astro::SolarState;
+
+use crate::{
+    alarm::parse_met,
+    console::{ConsoleApp, ConsoleReply, log_reply, parse_arg, run_line},
+    events::{EventKind, MissionEvent, detect_events, parse_event},
+    oem::{iso_date, parse_iso_date},
+    remote::RemoteControl,
+};
+
+const USAGE: &str =
+    "at [<time> | met <time> | on <event>] <command...> | load <file> | remove <n> | clear";
+
+/// A command, and when to run it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedCommand {
+    /// In seconds past J2000.
+    pub et: f64,
+    /// The command line, as typed.
+    pub line: String,
+}
+
+/// The commands to run, soonest first.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct CommandQueue(pub Vec<QueuedCommand>);
+
+impl CommandQueue {
+    /// Queue a command line to run at `et`, after any others queued for then.
+    pub fn push(&mut self, et: f64, line: String) {
+        let at = self.0.partition_point(|c| c.et <= et);
+        self.0.insert(at, QueuedCommand { et, line });
+    }
+
+    /// Take the commands due by `et`.
+    fn due(&mut self, et: f64) -> Vec<QueuedCommand> {
+        let due = self.0.partition_point(|c| c.et <= et);
+        self.0.drain(..due).collect()
+    }
+}
+
+/// A command to run after an event.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EventCommand {
+    pub kind: EventKind,
+    /// What the event's body, or burn's label, has in it, if it matters.
+    pub text: Option<String>,
+    /// The command line, as typed.
+    pub line: String,
+}
+
+impl EventCommand {
+    fn describe(&self) -> String {
+        let text = self
+            .text
+            .as_ref()
+            .map_or(String::new(), |t| format!(":{}", t));
+        format!("on {}{} {}", self.kind.name(), text, self.line)
+    }
+}
+
+/// The commands waiting on events, in the order they were set.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct EventCommands(pub Vec<EventCommand>);
+
+/// A command, and when it is to run, as `at` takes them.
+enum Entry {
+    At(f64, String),
+    On(EventCommand),
+}
+
+/// An entry from its words: `<time> <command...>`, `met <time> <command...>`,
+/// or `on <event> <command...>`, with `now` and the run's epoch, in ET.
+fn parse_entry(words: &[&str], now: f64, epoch: f64) -> Result<Entry, String> {
+    match words {
+        ["met", time, command @ ..] if !command.is_empty() => {
+            Ok(Entry::At(epoch + parse_met(time)?, command.join(" ")))
+        }
+        ["on", event, command @ ..] if !command.is_empty() => {
+            let (kind, text) = parse_event(event)?;
+            Ok(Entry::On(EventCommand {
+                kind,
+                text,
+                line: command.join(" "),
+            }))
+        }
+        ["met" | "on", ..] => Err(USAGE.to_string()),
+        [time, command @ ..] if !command.is_empty() => {
+            Ok(Entry::At(parse_time(time, now)?, command.join(" ")))
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+#[derive(Default)]
+pub struct SequencePlugin;
+
+impl Plugin for SequencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CommandQueue>();
+        app.init_resource::<EventCommands>();
+        app.add_systems(FixedPreUpdate, run_queue);
+        app.add_systems(FixedUpdate, queue_on_events.after(detect_events));
+        app.add_console_command(
+            "at",
+            "at [[<time> | met <time> | on <event>] <command...> | load <file> | remove <n> | \
+             clear]   run commands at set times (ET, ISO date, or +seconds), or after events",
+            at_command,
+        );
+    }
+}
+
+/// Run the commands due by the start of this step.
+fn run_queue(world: &mut World) {
+    let fixed = world.resource::<Time<Fixed>>();
+    let start = fixed.elapsed_secs_f64() - fixed.delta_secs_f64();
+    let et = world.resource::<SolarState>().et + start;
+    let due = world.resource_mut::<CommandQueue>().due(et);
+    for command in due {
+        let reply = run_line(world, &command.line);
+        log_reply(
+            world,
+            &format!("at {} {}", iso_date(command.et), command.line),
+            reply,
+        );
+    }
+}
+
+/// Queue the commands waiting on the events just seen, for the next step.
+fn queue_on_events(
+    mut seen: MessageReader<MissionEvent>,
+    mut waiting: ResMut<EventCommands>,
+    mut queue: ResMut<CommandQueue>,
+) {
+    for event in seen.read() {
+        if waiting.0.is_empty() {
+            continue;
+        }
+        let (due, left): (Vec<EventCommand>, Vec<EventCommand>) = waiting
+            .0
+            .drain(..)
+            .partition(|command| event.matches(command.kind, command.text.as_deref()));
+        waiting.0 = left;
+        for command in due {
+            queue.push(event.et, command.line);
+        }
+    }
+}
+
+/// A queue time: ET, an ISO date, or `+<seconds>` from `now`.
+pub fn parse_time(text: &str, now: f64) -> Result<f64, String> {
+    if let Some(delay) = text.strip_prefix('+') {
+        return Ok(now + parse_arg(delay)?);
+    }
+    if let Ok(et) = text.parse::<f64>() {
+        return Ok(et);
+    }
+    parse_iso_date(text)
+        .ok_or_else(|| format!("{:?} is not a time: ET, an ISO date, or +<seconds>", text))
+}
+
+fn at_command(
+    In(args): In<Vec<String>>,
+    mut queue: ResMut<CommandQueue>,
+    mut waiting: ResMut<EventCommands>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    remote: Res<RemoteControl>,
+) -> ConsoleReply {
+    let now = solar.et + fixed.elapsed_secs_f64();
+    // Flying remotely, nothing due before a command sent now would reach the
+    // craft can be queued, or changed.
+    let reach = remote.active().then(|| remote.arrival(now));
+    let check = |et: f64| match reach {
+        Some(reach) if et < reach => Err(format!(
+            "That is due before a command sent now reaches the craft, in {:.1} s",
+            reach - now
+        )),
+        _ => Ok(()),
+    };
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        [] => {
+            if queue.0.is_empty() && waiting.0.is_empty() {
+                return Ok("Nothing queued".to_string());
+            }
+            let timed = queue.0.iter().map(|command| {
+                format!(
+                    "{} (in {:.0} s) {}",
+                    iso_date(command.et),
+                    command.et - now,
+                    command.line
+                )
+            });
+            Ok(timed
+                .chain(waiting.0.iter().map(EventCommand::describe))
+                .enumerate()
+                .map(|(i, line)| format!("{}: {}", i + 1, line))
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        ["clear"] => {
+            waiting.0.clear();
+            queue.0.retain(|c| reach.is_some_and(|reach| c.et < reach));
+            if queue.0.is_empty() {
+                return Ok("ok".to_string());
+            }
+            Ok(format!(
+                "kept {}, due before a command sent now reaches the craft",
+                queue.0.len()
+            ))
+        }
+        ["remove", n] => {
+            let n = parse_arg(n)? as usize;
+            let timed = queue.0.len();
+            if n == 0 || n > timed + waiting.0.len() {
+                return Err(format!("There is no command {} queued", n));
+            }
+            if n > timed {
+                let command = waiting.0.remove(n - 1 - timed);
+                return Ok(format!("removed {}", command.line));
+            }
+            check(queue.0[n - 1].et)?;
+            let command = queue.0.remove(n - 1);
+            Ok(format!("removed {}", command.line))
+        }
+        ["load", path] => {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+            // Check it all before queueing any of it.
+            let mut entries = Vec::new();
+            for (number, line) in text.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let words: Vec<&str> = line.split_whitespace().collect();
+                let entry = parse_entry(&words, now, solar.et)
+                    .and_then(|entry| match entry {
+                        Entry::At(et, _) => check(et).map(|()| entry),
+                        Entry::On(_) => Ok(entry),
+                    })
+                    .map_err(|e| format!("{}:{}: {}", path, number + 1, e))?;
+                entries.push(entry);
+            }
+            let count = entries.len();
+            for entry in entries {
+                match entry {
+                    Entry::At(et, line) => queue.push(et, line),
+                    Entry::On(command) => waiting.0.push(command),
+                }
+            }
+            Ok(format!("queued {} commands", count))
+        }
+        words => match parse_entry(words, now, solar.et)? {
+            Entry::At(et, line) => {
+                check(et)?;
+                queue.push(et, line);
+                Ok(format!("at {} (in {:.0} s)", iso_date(et), et - now))
+            }
+            Entry::On(command) => {
+                let reply = command.describe();
+                waiting.0.push(command);
+                Ok(reply)
+            }
+        },
+    }
+}
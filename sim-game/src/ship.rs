@@ -0,0 +1,997 @@
+//! Simulation of the user's craft.
+//!
+//! Most of the information behind the physics of the ship is in `sim_core`,
+//! including orbital movements. This module manages ship-specific aspects.
+
+use bevy::prelude::*;
+use na::{Unit, UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use sim_astro::{
+    EarthMarker,
+    collision::Terrain,
+    contact::{Clamped, Landed},
+    frames::Axes,
+    geodesy::Geodetic,
+    loading::SimPhase,
+    setup_solar,
+};
+use sim_core::{
+    AttitudeControl, AttitudeController, AttitudeState, LinearControl, MassiveBody, OrbitalBody,
+    PhysicsModels, PhysicsSet, SizedBody, model_enabled, orbit::OrbitFrame,
+};
+
+pub mod aero;
+pub mod ascent;
+pub mod autopilot;
+pub mod craft_gravity;
+pub mod docking;
+pub mod engine;
+pub mod focus;
+pub mod gravity_gradient;
+pub mod ground_track;
+pub mod insertion;
+pub mod lifetime;
+pub mod lunar;
+pub mod magnetorquer;
+pub mod maneuver;
+pub mod nav;
+pub mod orbit_determination;
+pub mod pad;
+pub mod parts;
+pub mod power;
+pub mod predict;
+pub mod propulsion;
+pub mod proximity;
+pub mod radiation;
+pub mod rcs;
+pub mod registry;
+pub mod sas;
+pub mod sensors;
+pub mod staging;
+pub mod sunlight;
+pub mod targeting;
+pub mod tether;
+pub mod torch;
+pub mod trail;
+
+use engine::{FuelTank, MainEngine, engine_fire};
+use maneuver::ManeuverNode;
+use nav::{NavSource, Navigation};
+use rcs::{RcsCommand, RcsRealism, RcsTank, RcsThrusters};
+use sas::StabilityAssist;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    keys::{InputMap, KeysApp},
+    remote::local_control,
+};
+
+/// The craft the player is flying, and the views follow.  It is one of the
+/// `Craft`s, and moves between them (see `focus`).
+#[derive(Component)]
+pub struct PlayerShip;
+
+/// A craft that can be flown.
+#[derive(Component)]
+pub struct Craft;
+
+/// The orientation (body to world) the attitude controller is holding, if any.
+#[derive(Clone, Component, Debug, Default)]
+pub struct HoldAttitude(pub Option<UnitQuaternion<f64>>);
+
+/// The rotation keys, as last read, as a -1..=1 value about each BODY axis.
+/// The keys are read each frame, but the controllers that act on them run
+/// with the physics, however many steps that takes per frame.  In the
+/// translation layer, the same keys move the craft instead (see
+/// `ControlLayer`).
+#[derive(Resource, Debug, Default)]
+pub struct RotationKeys(pub Vector3<f64>);
+
+/// What the rotation keys, and the gamepad's sticks, move: the attitude, or,
+/// for docking, the craft itself.  Translating, W/S move it up and down
+/// (along -X, the craft's top, and back), A/D left and right (+Y and -Y),
+/// and Q/E fore and aft (+Z, the nose, and back), while the attitude is left
+/// to the mode, or the stability assist, to hold.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ControlLayer {
+    #[default]
+    Rotation,
+    Translation,
+}
+
+/// The object the `Target` SAS mode points at.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SasTarget(pub Option<Entity>);
+
+/// The mass properties of a craft.  Unlike the solar system, which works in km,
+/// these are in SI units, as that is what thrusters are specified in.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct MassProperties {
+    /// Total mass, in kg.
+    pub mass: f64,
+    /// The center of mass, in m, relative to the body origin, BODY frame.
+    pub cg_b: Vector3<f64>,
+    /// Principal moments of inertia about the center of mass, in kg*m^2,
+    /// BODY frame.
+    pub inertia_b: Vector3<f64>,
+}
+
+impl MassProperties {
+    /// A solid cylinder of the given mass, radius and length (kg, m), with
+    /// its axis along Z.
+    pub fn cylinder(mass: f64, radius: f64, length: f64) -> Self {
+        let i_xy = mass * (3.0 * radius * radius + length * length) / 12.0;
+        let i_z = mass * radius * radius / 2.0;
+        MassProperties {
+            mass,
+            cg_b: Vector3::zeros(),
+            inertia_b: Vector3::new(i_xy, i_xy, i_z),
+        }
+    }
+
+    /// A uniform solid ellipsoid of the given mass, and semi-axes along X,
+    /// Y and Z (kg, m).
+    pub fn ellipsoid(mass: f64, radii: &Vector3<f64>) -> Self {
+        let sq = radii.component_mul(radii);
+        MassProperties {
+            mass,
+            cg_b: Vector3::zeros(),
+            inertia_b: Vector3::new(sq.y + sq.z, sq.x + sq.z, sq.x + sq.y) * (mass / 5.0),
+        }
+    }
+
+    /// The semi-axes, in m, of the uniform solid ellipsoid with the same mass
+    /// and inertia.  This gives a feel for how the mass is spread out.
+    pub fn equivalent_ellipsoid(&self) -> Vector3<f64> {
+        let i = &self.inertia_b;
+        let k = 5.0 / (2.0 * self.mass);
+        Vector3::new(
+            (k * (i.y + i.z - i.x)).max(0.0).sqrt(),
+            (k * (i.x + i.z - i.y)).max(0.0).sqrt(),
+            (k * (i.x + i.y - i.z)).max(0.0).sqrt(),
+        )
+    }
+}
+
+/// A description of an initial orbit for the ship.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct ShipOrbit {
+    /// The normal vector of the orbital plane.
+    pub plane_normal: Unit<Vector3<f64>>,
+    /// The direction of periapsis within the orbital plane.
+    pub periapsis_direction: Unit<Vector3<f64>>,
+    /// The distance at periapsis, in km from the center of the planet.
+    pub periapsis: f64,
+    /// The distance at apoapsis, in km from the center of the planet.
+    pub apoapsis: f64,
+    /// The current true anomaly, in radians, with 0 being periapsis.
+    pub true_anomaly: f64,
+}
+
+impl ShipOrbit {
+    pub fn new(
+        plane_normal: Unit<Vector3<f64>>,
+        periapsis_direction: Unit<Vector3<f64>>,
+        periapsis: f64,
+        apoapsis: f64,
+        true_anomaly: f64,
+    ) -> Self {
+        ShipOrbit {
+            plane_normal,
+            periapsis_direction,
+            periapsis,
+            apoapsis,
+            true_anomaly,
+        }
+    }
+
+    /// An orbit from its elements, about a planet with the given pole, in the
+    /// world frame.  The apsides are in km from the center of the planet, and
+    /// the angles in radians.  The inclination is from the planet's equator,
+    /// and the ascending node is measured from `pole × x` (or `pole × y`, for
+    /// a pole close to x).
+    pub fn from_elements(
+        pole: &Vector3<f64>,
+        periapsis: f64,
+        apoapsis: f64,
+        inclination: f64,
+        raan: f64,
+        argp: f64,
+        true_anomaly: f64,
+    ) -> Self {
+        let pole = pole.normalize();
+        let across = if pole.x.abs() < 0.9 {
+            Vector3::x()
+        } else {
+            Vector3::y()
+        };
+        let e1 = pole.cross(&across).normalize();
+        let e2 = pole.cross(&e1);
+        let node = e1 * raan.cos() + e2 * raan.sin();
+        let normal =
+            UnitQuaternion::from_axis_angle(&Unit::new_normalize(node), inclination) * pole;
+        let periapsis_direction = node * argp.cos() + normal.cross(&node) * argp.sin();
+        ShipOrbit::new(
+            Unit::new_normalize(normal),
+            Unit::new_normalize(periapsis_direction),
+            periapsis,
+            apoapsis,
+            true_anomaly,
+        )
+    }
+
+    /// The position (km) and velocity (km/s) on this orbit, relative to the
+    /// planet, with the given GM.
+    pub fn state(&self, gm: f64) -> (Vector3<f64>, Vector3<f64>) {
+        // Ensure that the periapsis direction is perpendicular to the plane
+        // normal.
+        assert!(
+            self.plane_normal.dot(&self.periapsis_direction) < 1e-6,
+            "Periapsis direction must be perpendicular to plane normal"
+        );
+
+        let a = (self.periapsis + self.apoapsis) / 2.0;
+        let e = (self.apoapsis - self.periapsis) / (self.apoapsis + self.periapsis);
+        let p = a * (1.0 - e * e);
+
+        let p_hat = self.periapsis_direction.into_inner();
+        let q_hat = self.plane_normal.cross(&p_hat);
+
+        let nu = self.true_anomaly;
+        let r_mag = p / (1.0 + e * nu.cos());
+
+        let r_rel = (p_hat * nu.cos() + q_hat * nu.sin()) * r_mag;
+        let v_rel = (-p_hat * nu.sin() + q_hat * (e + nu.cos())) * (gm / p).sqrt();
+        (r_rel, v_rel)
+    }
+
+    /// Construct a circular LEO orbit on the earth.
+    pub fn new_leo() -> Self {
+        ShipOrbit::new(
+            Unit::new_normalize(Vector3::z()),
+            Unit::new_normalize(Vector3::x()),
+            6578.0, // 200 km altitude
+            6678.0, // 201 km altitude
+            0.0,
+        )
+    }
+}
+
+/// Where the ship starts.
+#[derive(Resource, Clone, Debug, Default, Serialize, Deserialize)]
+pub enum ShipSpawn {
+    /// In the orbit given by the `ShipOrbit`.
+    #[default]
+    Orbit,
+    /// Over the earth, at a geodetic latitude and longitude (degrees) and
+    /// altitude (km, above the ellipsoid), turning with it, and pointing
+    /// straight up.  At or below the ground, the ship starts landed.
+    Surface { lat: f64, lon: f64, alt: f64 },
+}
+
+impl ShipSpawn {
+    /// The position, velocity, and attitude of a ship at a surface spawn, on a
+    /// body with the given state and radii.  The ship's +Z (the engine's
+    /// thrust) is up, and +X is east.
+    pub fn surface_state(
+        location: &Geodetic,
+        body: &OrbitalBody,
+        attitude: &AttitudeState,
+        radii: &Vector3<f64>,
+    ) -> (OrbitalBody, AttitudeState) {
+        let fixed = Axes::body_fixed(attitude);
+        let (r_w, v_w) = fixed.from_frame(&location.to_body(radii), &Vector3::zeros());
+
+        // North, as east turned up, so that it is the way the longitude
+        // points even at a pole.
+        let up = fixed.q_fw.transform_vector(&location.up());
+        let east = fixed.q_fw.transform_vector(&Vector3::new(
+            -location.lon.sin(),
+            location.lon.cos(),
+            0.0,
+        ));
+        let q_bw = Axes::horizon(&up, &up.cross(&east))
+            .expect("east is level")
+            .q_fw;
+
+        (
+            OrbitalBody {
+                pos: body.pos + r_w,
+                vel: body.vel + v_w,
+            },
+            AttitudeState {
+                q_bw,
+                omega_b: q_bw.inverse_transform_vector(&fixed.omega_w),
+            },
+        )
+    }
+}
+
+/// The guidance programs: the autopilot, and what flies a maneuver node, an
+/// ascent, an insertion, a lunar transfer, or drag phasing, by setting the
+/// attitude mode and the throttle.  Switching the `autopilots` model off
+/// holds them all, leaving the craft flying as they last set it, with its
+/// attitude mode still held.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GuidanceSet;
+
+/// Plugin to setup a ship in orbit.
+#[derive(Default)]
+pub struct ShipPlugin;
+
+impl Plugin for ShipPlugin {
+    fn build(&self, app: &mut App) {
+        // A scenario can insert its own orbit before the plugin.
+        if !app.world().contains_resource::<ShipOrbit>() {
+            app.insert_resource(ShipOrbit::new_leo());
+        }
+        app.init_resource::<ShipSpawn>();
+        app.init_resource::<SasTarget>();
+        app.init_resource::<RcsMode>();
+        app.init_resource::<RcsRealism>();
+        app.init_resource::<RotationKeys>();
+        app.init_resource::<ControlLayer>();
+        app.add_systems(OnEnter(SimPhase::Running), setup_ship.after(setup_solar));
+        app.add_systems(Update, rcs_keys.run_if(local_control));
+        for (action, key) in [
+            ("pitch+", KeyCode::KeyW),
+            ("pitch-", KeyCode::KeyS),
+            ("yaw+", KeyCode::KeyA),
+            ("yaw-", KeyCode::KeyD),
+            ("roll+", KeyCode::KeyQ),
+            ("roll-", KeyCode::KeyE),
+            ("rcs-mode", KeyCode::KeyR),
+            ("rcs-pulsed", KeyCode::F2),
+            ("translate", KeyCode::Tab),
+        ] {
+            app.add_key_action(action, &[key]);
+        }
+        for (action, key, _) in POINTING_KEYS {
+            app.add_key_action(action, &[key]);
+        }
+        PhysicsModels::add(app, "autopilots");
+        app.configure_sets(FixedUpdate, GuidanceSet.run_if(model_enabled("autopilots")));
+        // The controllers step with the physics, so that they fly the same
+        // at any warp or frame rate.
+        app.add_systems(
+            FixedUpdate,
+            (
+                retune_controllers,
+                rcs_command,
+                rcs_allocate,
+                rcs_fire,
+                engine_fire,
+            )
+                .chain()
+                .before(PhysicsSet),
+        );
+        app.add_systems(Update, realism_keys);
+        app.add_console_command(
+            "mode",
+            "mode [<mode>]   show or set the attitude mode, such as hold or prograde",
+            mode_command,
+        );
+        app.add_console_command(
+            "layer",
+            "layer [rotation|translation]   show or set what the rotation keys move",
+            layer_command,
+        );
+    }
+}
+
+/// The attitude controller for a craft with the given thrusters.
+fn ship_controller(
+    rcs: &RcsThrusters,
+    mass: &MassProperties,
+    realism: &RcsRealism,
+) -> AttitudeController {
+    let controller = AttitudeController::default()
+        .with_max_rate(0.2)
+        .with_max_torque(rcs.max_torque_b(&mass.cg_b) * realism.thrust_limit);
+    if realism.is_pulsed() {
+        // Don't chase errors smaller than the thrusters can resolve.
+        controller.with_deadband(0.5_f64.to_radians(), 0.1_f64.to_radians())
+    } else {
+        controller
+    }
+}
+
+/// The parts of a craft, 8 m long: the cabin on the nose, the propellant tanks
+/// (full, with the engine's 3500 kg and the RCS's 100 kg) in the middle, and
+/// the engine at the tail.
+fn craft_parts() -> [parts::Part; 3] {
+    [
+        parts::Part::cylinder("cabin", 600.0, 2.0, 1.5).at(Vector3::new(0.0, 0.0, 3.25)),
+        parts::Part::cylinder("tank", 3800.0, 2.0, 5.0),
+        parts::Part::cylinder("engine", 600.0, 1.0, 1.5).at(Vector3::new(0.0, 0.0, -3.25)),
+    ]
+}
+
+/// Everything a craft is made of, with the given name and starting state.
+pub fn craft_bundle(
+    name: &str,
+    start: OrbitalBody,
+    attitude: AttitudeState,
+    realism: &RcsRealism,
+) -> impl Bundle {
+    let mass = MassProperties::from_parts(&craft_parts());
+    // Hypergolic, as the main engine.
+    let rcs = RcsThrusters::quad_pods(2.0, 445.0).with_isp(290.0);
+    let controller = ship_controller(&rcs, &mass, realism);
+    (
+        Name::new(name.to_string()),
+        Transform::default(),
+        start,
+        attitude,
+        AttitudeControl {
+            alpha_b: Vector3::zeros(),
+        },
+        LinearControl::default(),
+        mass,
+        rcs,
+        RcsCommand::default(),
+        // At the tail, on a gimbal.
+        MainEngine::new(20_000.0)
+            .with_isp(320.0)
+            .at(Vector3::new(0.0, 0.0, -4.0))
+            .with_gimbal(5.0_f64.to_radians()),
+        controller,
+        HoldAttitude::default(),
+        StabilityAssist::default(),
+        (
+            predict::Prediction::default(),
+            ground_track::GroundTrack::default(),
+            trail::Trail::default(),
+            autopilot::Autopilot::default(),
+            aero::Aero::cylinder(2.0, 8.0),
+            radiation::Dosimeter::default(),
+            sunlight::Sunlight::default(),
+            power::Power::wings(20.0, 5000.0),
+            // In the tank part, leaving about 3.8 km/s.
+            FuelTank::full(3500.0),
+            RcsTank::full(100.0),
+            staging::Staging::default(),
+            // On the nose, where the engine pushes toward.
+            docking::DockingPort::new(Vector3::new(0.0, 0.0, 4.0), Vector3::z()),
+            sensors::Sensors::default(),
+            nav::Navigation::default(),
+            (
+                crate::debris::Structure::default(),
+                // Rods of 1000 A*m^2 (see `magnetorquer`).
+                magnetorquer::Magnetorquers::new(1000.0),
+                orbit_determination::OrbitDetermination::default(),
+            ),
+        ),
+        Craft,
+    )
+}
+
+#[allow(clippy::type_complexity)]
+pub fn setup_ship(
+    orbit: Res<ShipOrbit>,
+    spawn: Res<ShipSpawn>,
+    realism: Res<RcsRealism>,
+    earth: Query<
+        (
+            Entity,
+            &MassiveBody,
+            &OrbitalBody,
+            &SizedBody,
+            &AttitudeState,
+            Option<&Terrain>,
+        ),
+        With<EarthMarker>,
+    >,
+    mut commands: Commands,
+) {
+    let (earth_entity, mb, ob, earth_size, earth_attitude, terrain) = earth.single().unwrap();
+    let (start, start_attitude, landed) = match *spawn {
+        ShipSpawn::Orbit => {
+            let (r_rel, v_rel) = orbit.state(mb.gm);
+            let start = OrbitalBody {
+                pos: ob.pos + r_rel,
+                vel: ob.vel + v_rel,
+            };
+            let start_attitude = AttitudeState {
+                // q_bw: na::UnitQuaternion::from_axis_angle(
+                //     &Vector3::y_axis(),
+                //     std::f64::consts::FRAC_PI_2,
+                // ),
+                q_bw: na::UnitQuaternion::identity(),
+                // q_bw: na::UnitQuaternion::from_axis_angle(
+                //     &Vector3::y_axis(),
+                //     std::f64::consts::FRAC_PI_2,
+                // ),
+                // omega_b: Vector3::new(1.0, 2.0, 3.0).normalize() * 0.5,
+                omega_b: Vector3::zeros(),
+            };
+            (start, start_attitude, None)
+        }
+        ShipSpawn::Surface { lat, lon, alt } => {
+            let (lat, lon) = (lat.to_radians(), lon.to_radians());
+            let ground = terrain.map_or(0.0, |t| t.0.height(lat, lon));
+            let location = Geodetic {
+                lat,
+                lon,
+                alt: alt.max(ground),
+            };
+            let (start, start_attitude) =
+                ShipSpawn::surface_state(&location, ob, earth_attitude, &earth_size.radii);
+            let landed = (alt <= ground).then(|| {
+                Landed::touch_down(
+                    earth_entity,
+                    &location,
+                    ground,
+                    &earth_size.radii,
+                    &start_attitude,
+                    earth_attitude,
+                )
+            });
+            (start, start_attitude, landed)
+        }
+    };
+
+    // Spawn the ship.
+    let mut ship = commands.spawn((
+        craft_bundle("PlayerShip", start, start_attitude, &realism),
+        PlayerShip,
+    ));
+    // On the ground, it is on the pad, waiting for the countdown.
+    if let Some(landed) = landed {
+        ship.insert((landed, Clamped, pad::Umbilical::default()));
+    }
+
+    /*
+    println!("Spawned ship at pos {:?} vel {:?}", r_rel, v_rel);
+    println!(
+        "  q_bw: {}",
+        na::UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f64::consts::FRAC_PI_2)
+    );
+    println!("  omega_b: {}", Vector3::<f64>::zeros());
+    */
+}
+
+/// The angular accelerations requested by the keyboard, in rad/s^2.  The RCS
+/// will deliver as much of this as the thrusters allow.
+const ACCEL_X: f64 = 0.25;
+const ACCEL_Y: f64 = 0.25;
+const ACCEL_Z: f64 = 0.25;
+
+/// The acceleration, in m/s^2, requested along each BODY axis by the keys in
+/// the translation layer.  Gentle, for docking.
+const TRANSLATE_ACCEL: f64 = 0.1;
+
+/// The body rates, in rad/s, requested by the keyboard in the fly-by-wire
+/// modes.
+const RATE_X: f64 = 0.1;
+const RATE_Y: f64 = 0.1;
+const RATE_Z: f64 = 0.1;
+
+/// The modes `mode` can set.  `Ascent` is left to the ascent and insertion
+/// guidance.
+const MODES: [RcsMode; 13] = [
+    RcsMode::Manual,
+    RcsMode::Hold,
+    RcsMode::RateCommand,
+    RcsMode::AttitudeCommand,
+    RcsMode::Prograde,
+    RcsMode::Retrograde,
+    RcsMode::Normal,
+    RcsMode::AntiNormal,
+    RcsMode::RadialOut,
+    RcsMode::RadialIn,
+    RcsMode::Target,
+    RcsMode::Maneuver,
+    RcsMode::DragPhasing,
+];
+
+#[derive(Resource, Component, Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub enum RcsMode {
+    /// The keys directly command angular acceleration.
+    #[default]
+    Manual,
+    /// Hold the attitude the craft was in when this mode was entered.
+    Hold,
+    /// Fly-by-wire: the keys command a body rate, which the attitude
+    /// controller holds.  With no keys pressed, the rotation is stopped.
+    RateCommand,
+    /// Fly-by-wire: the keys move a target attitude, which the attitude
+    /// controller follows.  With no keys pressed, the attitude is held.
+    AttitudeCommand,
+    // The stability assist pointing modes.  These all point the BODY +Z axis
+    // (the main engine axis) along a direction in the orbital frame of the
+    // central body, leaving the roll alone.
+    Prograde,
+    Retrograde,
+    Normal,
+    AntiNormal,
+    RadialOut,
+    RadialIn,
+    /// Point at the `SasTarget`.
+    Target,
+    /// Point along the burn of the current maneuver node.
+    Maneuver,
+    /// Turn in the air flow for more or less drag, to drift toward the
+    /// `SasTarget`.  See `aero`.
+    DragPhasing,
+    /// Follow the ascent or insertion guidance's steering.  See `ascent` and
+    /// `insertion`.
+    Ascent,
+}
+
+impl RcsMode {
+    /// The world direction this mode points the craft, if it is a pointing
+    /// mode.  `pos` and `vel` are relative to the central body, `target_rel`
+    /// is the target relative to the craft, and `node_w` is the maneuver node
+    /// burn direction.
+    fn pointing_w(
+        &self,
+        pos: &Vector3<f64>,
+        vel: &Vector3<f64>,
+        target_rel: Option<Vector3<f64>>,
+        node_w: Option<Vector3<f64>>,
+    ) -> Option<Vector3<f64>> {
+        let frame = OrbitFrame::new(pos, vel);
+        match self {
+            RcsMode::Prograde => Some(frame.prograde),
+            RcsMode::Retrograde => Some(-frame.prograde),
+            RcsMode::Normal => Some(frame.normal),
+            RcsMode::AntiNormal => Some(-frame.normal),
+            RcsMode::RadialOut => Some(frame.radial),
+            RcsMode::RadialIn => Some(-frame.radial),
+            RcsMode::Target => target_rel.map(|t| t.normalize()),
+            RcsMode::Maneuver => node_w
+                .filter(|n| n.norm_squared() > 0.0)
+                .map(|n| n.normalize()),
+            _ => None,
+        }
+    }
+}
+
+/// The attitude nearest `q_bw` that points the BODY +Z axis along `dir_w`.
+/// This is the smallest rotation that gets there, so roll about the pointing
+/// axis is left as it is.
+fn point_z_at(q_bw: &UnitQuaternion<f64>, dir_w: &Vector3<f64>) -> UnitQuaternion<f64> {
+    point_axis_at(q_bw, &Vector3::z(), dir_w)
+}
+
+/// Like `point_z_at`, but for any BODY axis.
+pub(crate) fn point_axis_at(
+    q_bw: &UnitQuaternion<f64>,
+    axis_b: &Vector3<f64>,
+    dir_w: &Vector3<f64>,
+) -> UnitQuaternion<f64> {
+    let axis_w = q_bw.transform_vector(axis_b);
+    let rotation = UnitQuaternion::rotation_between(&axis_w, dir_w).unwrap_or_else(|| {
+        // Exactly backwards, so flip over about some axis across it.
+        let across = if axis_b.x.abs() < 0.9 {
+            Vector3::x()
+        } else {
+            Vector3::y()
+        };
+        UnitQuaternion::from_axis_angle(
+            &Unit::new_normalize(q_bw.transform_vector(&axis_b.cross(&across))),
+            std::f64::consts::PI,
+        )
+    });
+    rotation * q_bw
+}
+
+/// Read the rotation keys as a -1..=1 value about each BODY axis.
+fn rotation_keys(kb: &ButtonInput<KeyCode>, map: &InputMap) -> Vector3<f64> {
+    Vector3::new(
+        map.axis(kb, "pitch+", "pitch-"),
+        map.axis(kb, "yaw+", "yaw-"),
+        map.axis(kb, "roll+", "roll-"),
+    )
+}
+
+/// The actions that point the craft, with their default keys, and the modes
+/// they set.
+const POINTING_KEYS: [(&str, KeyCode, RcsMode); 9] = [
+    ("point-prograde", KeyCode::Digit1, RcsMode::Prograde),
+    ("point-retrograde", KeyCode::Digit2, RcsMode::Retrograde),
+    ("point-normal", KeyCode::Digit3, RcsMode::Normal),
+    ("point-anti-normal", KeyCode::Digit4, RcsMode::AntiNormal),
+    ("point-radial-out", KeyCode::Digit5, RcsMode::RadialOut),
+    ("point-radial-in", KeyCode::Digit6, RcsMode::RadialIn),
+    ("point-target", KeyCode::Digit7, RcsMode::Target),
+    ("point-maneuver", KeyCode::Digit8, RcsMode::Maneuver),
+    ("point-drag-phasing", KeyCode::Digit9, RcsMode::DragPhasing),
+];
+
+/// Switch modes, and read the rotation keys for the controllers.
+pub(crate) fn rcs_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    map: Res<InputMap>,
+    mut layer: ResMut<ControlLayer>,
+    mut mode: ResMut<RcsMode>,
+    mut keys: ResMut<RotationKeys>,
+) {
+    // TODO: This simple mode switch isn't what we really will want, but I'll
+    // have to come up with what makes sense.  Basically, it shouldn't just go
+    // between the modes as you wouldn't want it to start moving until you
+    // confirm the mode. For now, just cycle through them.
+    if map.just_pressed(&kb, "translate") {
+        layer.toggle();
+    }
+    if map.just_pressed(&kb, "rcs-mode") {
+        *mode = match *mode {
+            RcsMode::Manual => RcsMode::Hold,
+            RcsMode::Hold => RcsMode::RateCommand,
+            RcsMode::RateCommand => RcsMode::AttitudeCommand,
+            _ => RcsMode::Manual,
+        };
+    }
+    for (action, _, pointing) in POINTING_KEYS {
+        if map.just_pressed(&kb, action) {
+            *mode = pointing;
+        }
+    }
+
+    keys.0 = rotation_keys(&kb, &map);
+}
+
+/// The force and torque each craft wants of its thrusters this physics step.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub(crate) fn rcs_command(
+    keys: Res<RotationKeys>,
+    layer: Res<ControlLayer>,
+    time: Res<Time>,
+    mode: Res<RcsMode>,
+    sas_target: Res<SasTarget>,
+    mut query: Query<
+        (
+            &mut RcsCommand,
+            &mut HoldAttitude,
+            &OrbitalBody,
+            &AttitudeState,
+            &MassProperties,
+            &AttitudeController,
+            Option<&ManeuverNode>,
+            Option<&mut StabilityAssist>,
+            Option<&Navigation>,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<(&OrbitalBody, &MassiveBody), (With<EarthMarker>, Without<PlayerShip>)>,
+    targets: Query<&OrbitalBody, Without<PlayerShip>>,
+    nav_source: Res<NavSource>,
+) {
+    // Translating, the keys push the craft, and leave the attitude be.
+    let (keys, push_b) = match *layer {
+        ControlLayer::Rotation => (keys.0, Vector3::zeros()),
+        ControlLayer::Translation => (Vector3::zeros(), ControlLayer::push_b(&keys.0)),
+    };
+    // The physics step.
+    let dt = time.delta_secs_f64();
+    let Ok((earth, earth_mass)) = earth.single() else {
+        return;
+    };
+
+    for (mut command, mut hold, orbital, state, mass, controller, node, mut sas, nav) in
+        query.iter_mut()
+    {
+        command.force_b = push_b * TRANSLATE_ACCEL * mass.mass;
+        // Steer by what the craft believes, if that's what it is flying by.
+        let (ref orbital, ref state) = nav_source.view(nav, orbital, state);
+
+        let pos = orbital.pos - earth.pos;
+        let vel = orbital.vel - earth.vel;
+        let target_rel = sas_target
+            .0
+            .and_then(|e| targets.get(e).ok())
+            .map(|t| t.pos - orbital.pos);
+        let node_w = node.map(|n| n.dv_w(&pos, &vel, earth_mass.gm, time.elapsed_secs_f64()));
+        let pointing = mode.pointing_w(&pos, &vel, target_rel, node_w);
+
+        command.torque_b = match *mode {
+            RcsMode::Manual => {
+                match sas
+                    .as_deref_mut()
+                    .filter(|sas| sas.on && keys == Vector3::zeros())
+                {
+                    // Let go of, with the stability assist on.
+                    Some(sas) => sas.torque_b(&mut hold, state, &mass.inertia_b, controller, dt),
+                    None => {
+                        hold.0 = None;
+                        let alpha_b = keys.component_mul(&Vector3::new(ACCEL_X, ACCEL_Y, ACCEL_Z));
+                        mass.inertia_b.component_mul(&alpha_b)
+                    }
+                }
+            }
+            RcsMode::Hold => {
+                // Hold whatever attitude we were in when hold was engaged.
+                let target = *hold.0.get_or_insert(state.q_bw);
+                controller.torque_b(
+                    &state.q_bw,
+                    &state.omega_b,
+                    &mass.inertia_b,
+                    &target,
+                    &Vector3::zeros(),
+                )
+            }
+            RcsMode::RateCommand => {
+                hold.0 = None;
+                let omega_cmd_b = keys.component_mul(&Vector3::new(RATE_X, RATE_Y, RATE_Z));
+                controller.rate_torque_b(&state.omega_b, &mass.inertia_b, &omega_cmd_b)
+            }
+            RcsMode::AttitudeCommand => {
+                // Slew the target about its own axes at the commanded rate,
+                // and have the controller chase it.
+                let omega_cmd_b = keys.component_mul(&Vector3::new(RATE_X, RATE_Y, RATE_Z));
+                let target = hold.0.get_or_insert(state.q_bw);
+                *target *= UnitQuaternion::from_scaled_axis(omega_cmd_b * dt);
+                controller.torque_b(
+                    &state.q_bw,
+                    &state.omega_b,
+                    &mass.inertia_b,
+                    target,
+                    &omega_cmd_b,
+                )
+            }
+            _ => {
+                // A pointing mode.  Without anything to point at (no target),
+                // just hold where we are.  `DragPhasing` and `Ascent` set
+                // the attitude to hold themselves.
+                let target = match pointing {
+                    Some(dir_w) => {
+                        hold.0 = None;
+                        point_z_at(&state.q_bw, &dir_w)
+                    }
+                    None => *hold.0.get_or_insert(state.q_bw),
+                };
+                controller.torque_b(
+                    &state.q_bw,
+                    &state.omega_b,
+                    &mass.inertia_b,
+                    &target,
+                    &Vector3::zeros(),
+                )
+            }
+        };
+    }
+}
+
+impl ControlLayer {
+    /// Switch to the other layer.
+    pub fn toggle(&mut self) {
+        *self = match *self {
+            ControlLayer::Rotation => ControlLayer::Translation,
+            ControlLayer::Translation => ControlLayer::Rotation,
+        };
+    }
+
+    /// The way, -1..=1 along each BODY axis, the keys push the craft, in the
+    /// translation layer.
+    pub fn push_b(keys: &Vector3<f64>) -> Vector3<f64> {
+        Vector3::new(-keys.x, keys.y, keys.z)
+    }
+}
+
+fn layer_command(In(args): In<Vec<String>>, mut layer: ResMut<ControlLayer>) -> ConsoleReply {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        [] => {}
+        ["rotation"] => *layer = ControlLayer::Rotation,
+        ["translation"] => *layer = ControlLayer::Translation,
+        _ => return Err("layer [rotation|translation]".to_string()),
+    }
+    Ok(format!("layer {:?}", *layer).to_lowercase())
+}
+
+/// `mode` shows the attitude mode, and `mode <mode>` sets it, by its name,
+/// in any case.
+fn mode_command(In(args): In<Vec<String>>, mut mode: ResMut<RcsMode>) -> ConsoleReply {
+    match args.as_slice() {
+        [] => {}
+        [name] => {
+            *mode = MODES
+                .iter()
+                .find(|m| format!("{:?}", m).eq_ignore_ascii_case(name))
+                .copied()
+                .ok_or_else(|| {
+                    let names: Vec<String> = MODES
+                        .iter()
+                        .map(|m| format!("{:?}", m).to_lowercase())
+                        .collect();
+                    format!("No such mode as {:?}, try {}", name, names.join(", "))
+                })?;
+        }
+        _ => return Err("mode [<mode>]".to_string()),
+    }
+    Ok(format!("mode {:?}", *mode))
+}
+
+/// F2 switches between idealized and pulsed thrusters.
+fn realism_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    map: Res<InputMap>,
+    mut realism: ResMut<RcsRealism>,
+) {
+    if map.just_pressed(&kb, "rcs-pulsed") {
+        let thrust_limit = realism.thrust_limit;
+        *realism = if realism.is_pulsed() {
+            RcsRealism::default()
+        } else {
+            RcsRealism::pulsed()
+        };
+        realism.thrust_limit = thrust_limit;
+    }
+}
+
+/// Retune the attitude controllers when the realism settings or the mass
+/// properties change.
+fn retune_controllers(
+    realism: Res<RcsRealism>,
+    mut query: Query<(&RcsThrusters, Ref<MassProperties>, &mut AttitudeController)>,
+) {
+    for (rcs, mass, mut controller) in query.iter_mut() {
+        if realism.is_changed() || mass.is_changed() {
+            *controller = ship_controller(rcs, &mass, &realism);
+        }
+    }
+}
+
+/// Turn the RCS command into thruster duty cycles.
+pub(crate) fn rcs_allocate(
+    mut query: Query<(
+        &RcsCommand,
+        &MassProperties,
+        &mut RcsThrusters,
+        Option<&RcsTank>,
+    )>,
+    realism: Res<RcsRealism>,
+) {
+    // Ask for more from each thruster to make up for the thrust limit.  This
+    // saturates sooner, but gets the direction right.
+    let scale = 1.0 / realism.thrust_limit.max(1.0e-6);
+    for (command, mass, mut rcs, tank) in query.iter_mut() {
+        // With the tank dry, there is no control at all.
+        if tank.is_some_and(RcsTank::is_dry) {
+            rcs.duty.fill(0.0);
+            continue;
+        }
+        rcs.allocate(
+            &mass.cg_b,
+            &(command.force_b * scale),
+            &(command.torque_b * scale),
+        );
+    }
+}
+
+/// Fire the thrusters for this physics step, and turn the force and torque
+/// they produce into the accelerations the physics uses.  The propellant
+/// they burn comes off the craft's mass.
+#[allow(clippy::type_complexity)]
+pub(crate) fn rcs_fire(
+    mut query: Query<(
+        &Name,
+        &mut RcsThrusters,
+        Option<&mut RcsTank>,
+        &mut MassProperties,
+        &mut AttitudeControl,
+        &mut LinearControl,
+    )>,
+    realism: Res<RcsRealism>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_secs_f64();
+
+    for (name, mut rcs, mut tank, mut mass, mut attitude, mut linear) in query.iter_mut() {
+        let burned = rcs.fire(&realism, tank.as_deref_mut(), dt);
+        if burned > 0.0 && mass.mass > burned {
+            if tank.as_ref().is_some_and(|tank| tank.is_dry()) {
+                warn!("{}: RCS out of propellant, attitude control lost", name);
+            }
+            // Taken evenly from everywhere, as `engine_fire` does.
+            let scale = (mass.mass - burned) / mass.mass;
+            mass.mass -= burned;
+            mass.inertia_b *= scale;
+        }
+        let (force_b, torque_b) = rcs.output_b(&mass.cg_b);
+        attitude.alpha_b = torque_b.component_div(&mass.inertia_b);
+        // m/s^2 to km/s^2.
+        linear.accel_b = force_b / mass.mass / 1000.0;
+    }
+}
@@ -0,0 +1,333 @@
+//! Aerodynamic drag and lift, and differential drag phasing.
+//!
+//! A craft low enough to be in a body's `Atmosphere` is slowed by the air
+//! going past it, by an amount that depends on how much of itself it presents
+//! to the flow.  Turning broadside on, or end on, changes that, so a craft
+//! with no propellant to spare can still steer its orbit a little: more drag
+//! lowers the orbit, which makes it go faster, and drift ahead.
+//!
+//! Flown at an angle of attack, off its Z axis, a craft is also pushed
+//! sideways, across the flow, toward the side its nose (or, flown backward,
+//! its tail) is on.  The lift coefficient is taken as `lift_slope * sin(a) *
+//! cos(a)`, on the area along Z, which is right for a slender body, with a
+//! slope of 2, and, with the slope negative, for a blunt capsule, which the
+//! air pushes back along its axis.
+//!
+//! The air's force acts at the center of pressure.  Off the center of mass,
+//! it turns the craft: with the center of pressure behind, the craft weather
+//! vanes into the flow, and a capsule with its center of mass off to one side
+//! trims at an angle of attack, and so flies with lift, which can be rolled
+//! to steer the entry.
+//!
+//! The `RcsMode::DragPhasing` mode (key 9) uses that to close the along-track
+//! distance to the SAS target, flying high drag while it needs to gain on the
+//! target, and low drag otherwise.  With no target, it flies low drag, to make
+//! the orbit last.
+
+use bevy::prelude::*;
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+use sim_astro::{EarthMarker, atmosphere::Atmosphere, geodesy::Geodetic};
+use sim_core::{
+    AttitudeControl, AttitudeState, LinearControl, MassiveBody, OrbitalBody, PhysicsModels,
+    PhysicsSet, SizedBody, model_enabled, orbit::OrbitFrame,
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{
+        GuidanceSet, HoldAttitude, MassProperties, PlayerShip, RcsMode, SasTarget,
+        engine::engine_fire, point_axis_at, rcs_command,
+    },
+};
+
+/// How far ahead, in seconds, phasing projects the drift when deciding which
+/// way to fly.  The differences in drag are small, so the drift takes days to
+/// turn around.
+const PHASING_LEAD: f64 = 86400.0;
+
+/// Phasing doesn't change its mind over less than this, in km, of projected
+/// separation.
+const PHASING_DEADBAND: f64 = 1.0;
+
+/// The drag properties of a craft.  These are SI, like the `MassProperties`.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct Aero {
+    pub drag_coefficient: f64,
+    /// The area, in m^2, presented to a flow along each BODY axis.  A flow in
+    /// between sees a mix of these.
+    pub area_b: Vector3<f64>,
+    /// The drag acceleration in the last physics step, in m/s^2.
+    pub drag: f64,
+    /// The dynamic pressure in the last physics step, in Pa.
+    #[serde(default)]
+    pub dynamic_pressure: f64,
+    /// The Mach number in the last physics step, if the air has a speed of
+    /// sound.
+    #[serde(default)]
+    pub mach: Option<f64>,
+    /// The slope of the lift coefficient, per radian, at no angle of attack.
+    #[serde(default)]
+    pub lift_slope: f64,
+    /// The center of pressure, in m, relative to the body origin, BODY frame.
+    #[serde(default)]
+    pub cp_b: Vector3<f64>,
+    /// The angle of attack, radians, off Z, the lift acceleration, m/s^2,
+    /// and the torque, N*m, BODY frame, in the last physics step.
+    #[serde(default)]
+    pub angle_of_attack: f64,
+    #[serde(default)]
+    pub lift: f64,
+    #[serde(default)]
+    pub torque_b: Vector3<f64>,
+    /// What phasing is doing, while it is flying.
+    pub phasing: Option<Phasing>,
+}
+
+impl Aero {
+    /// A cylinder (m) with its axis along Z, and the center of pressure in
+    /// the middle.
+    pub fn cylinder(radius: f64, length: f64) -> Self {
+        let side = 2.0 * radius * length;
+        Aero {
+            drag_coefficient: 2.2,
+            area_b: Vector3::new(side, side, std::f64::consts::PI * radius * radius),
+            drag: 0.0,
+            dynamic_pressure: 0.0,
+            mach: None,
+            lift_slope: 2.0,
+            cp_b: Vector3::zeros(),
+            angle_of_attack: 0.0,
+            lift: 0.0,
+            torque_b: Vector3::zeros(),
+            phasing: None,
+        }
+    }
+
+    /// The lift acceleration, in m/s^2, BODY frame, for a flow along
+    /// `wind_b`, the craft's velocity through the air, at the given dynamic
+    /// pressure, for a craft of the given mass, and the angle of attack.
+    pub fn lift_b(&self, wind_b: &Vector3<f64>, pressure: f64, mass: f64) -> (Vector3<f64>, f64) {
+        let wind = wind_b.normalize();
+        let cos = wind.z.clamp(-1.0, 1.0);
+        let alpha = cos.acos();
+        // The nose's direction across the flow.
+        let Some(across) = (Vector3::z() - wind * cos).try_normalize(1.0e-9) else {
+            return (Vector3::zeros(), alpha);
+        };
+        let coefficient = self.lift_slope * alpha.sin() * cos;
+        let lift = across * (pressure * coefficient * self.area_b.z / mass);
+        (lift, alpha)
+    }
+
+    /// The area, in m^2, presented to a flow along `flow_b`.
+    pub fn area(&self, flow_b: &Vector3<f64>) -> f64 {
+        flow_b.normalize().abs().dot(&self.area_b)
+    }
+
+    /// The BODY axis presenting the most (`high`), or the least area.
+    fn axis_b(&self, high: bool) -> Vector3<f64> {
+        let index = if high {
+            self.area_b.imax()
+        } else {
+            self.area_b.imin()
+        };
+        let mut axis = Vector3::zeros();
+        axis[index] = 1.0;
+        axis
+    }
+}
+
+/// The state of differential drag phasing.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Phasing {
+    /// How far, in km along track, the craft is ahead of the target.
+    pub separation: f64,
+    /// How fast, in km/s, that is growing, from the difference in the orbits'
+    /// periods.
+    pub drift: f64,
+    /// Whether it is flying for high drag.
+    pub high_drag: bool,
+}
+
+#[derive(Default)]
+pub struct AeroPlugin;
+
+impl Plugin for AeroPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            drag_phasing.in_set(GuidanceSet).before(rcs_command),
+        );
+        PhysicsModels::add(app, "drag");
+        app.add_systems(
+            FixedUpdate,
+            aero_drag
+                .after(engine_fire)
+                .before(PhysicsSet)
+                .run_if(model_enabled("drag")),
+        );
+        app.add_console_command(
+            "aero",
+            "aero [cp <x> <y> <z> | lift <slope>]   the air on the ship, or set its center of pressure (m) or lift",
+            aero_command,
+        );
+    }
+}
+
+/// Add the drag and lift to the craft's linear acceleration, and their
+/// torque to its angular acceleration.  This must run after the thrusters
+/// have set theirs.
+#[allow(clippy::type_complexity)]
+pub(crate) fn aero_drag(
+    mut crafts: Query<(
+        &OrbitalBody,
+        &AttitudeState,
+        &MassProperties,
+        &mut Aero,
+        &mut LinearControl,
+        &mut AttitudeControl,
+    )>,
+    bodies: Query<(&OrbitalBody, &AttitudeState, &SizedBody, &Atmosphere), Without<Aero>>,
+) {
+    for (orbital, attitude, mass, mut aero, mut linear, mut control) in crafts.iter_mut() {
+        let mut accel_b = Vector3::zeros();
+        let mut lift_b = Vector3::zeros();
+        aero.dynamic_pressure = 0.0;
+        aero.mach = None;
+        aero.angle_of_attack = 0.0;
+        for (body, body_attitude, size, atmosphere) in bodies.iter() {
+            if (orbital.pos - body.pos).norm() - size.radii.max() > atmosphere.top {
+                continue;
+            }
+            let alt = Geodetic::from_world(body, body_attitude, &size.radii, &orbital.pos).alt;
+            let density = atmosphere.density(alt);
+            let wind_w = Atmosphere::wind_relative(body, body_attitude, &orbital.pos, &orbital.vel);
+            if density == 0.0 || wind_w.norm_squared() == 0.0 {
+                continue;
+            }
+            let wind_b = attitude.q_bw.inverse_transform_vector(&wind_w);
+            // km/s to m/s.
+            let speed = wind_b.norm() * 1000.0;
+            let pressure = 0.5 * density * speed * speed;
+            let drag = pressure * aero.drag_coefficient * aero.area(&wind_b) / mass.mass;
+            let (lift, alpha) = aero.lift_b(&wind_b, pressure, mass.mass);
+            aero.dynamic_pressure += pressure;
+            aero.mach = atmosphere.speed_of_sound(alt).map(|sound| speed / sound);
+            aero.angle_of_attack = alpha;
+            accel_b -= wind_b.normalize() * drag;
+            lift_b += lift;
+        }
+        aero.drag = accel_b.norm();
+        aero.lift = lift_b.norm();
+        accel_b += lift_b;
+        aero.torque_b = (aero.cp_b - mass.cg_b).cross(&(accel_b * mass.mass));
+        control.alpha_b += aero.torque_b.component_div(&mass.inertia_b);
+        // m/s^2 to km/s^2.
+        linear.accel_b += accel_b / 1000.0;
+    }
+}
+
+fn aero_command(
+    In(args): In<Vec<String>>,
+    mut ship: Query<&mut Aero, With<PlayerShip>>,
+) -> ConsoleReply {
+    let mut aero = ship
+        .single_mut()
+        .map_err(|_| "The ship has no aerodynamics".to_string())?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] => {}
+        ["cp", x, y, z] => aero.cp_b = Vector3::new(parse_arg(x)?, parse_arg(y)?, parse_arg(z)?),
+        ["lift", slope] => aero.lift_slope = parse_arg(slope)?,
+        _ => return Err("aero [cp <x> <y> <z> | lift <slope>]".to_string()),
+    }
+    let torque = aero.torque_b;
+    Ok(format!(
+        "q {:.1} Pa, angle of attack {:.1} deg, drag {:.3} m/s^2, lift {:.3} m/s^2, torque \
+         ({:.1}, {:.1}, {:.1}) N*m, center of pressure ({}, {}, {}) m, lift slope {}",
+        aero.dynamic_pressure,
+        aero.angle_of_attack.to_degrees(),
+        aero.drag,
+        aero.lift,
+        torque.x,
+        torque.y,
+        torque.z,
+        aero.cp_b.x,
+        aero.cp_b.y,
+        aero.cp_b.z,
+        aero.lift_slope,
+    ))
+}
+
+/// In the `DragPhasing` mode, pick high or low drag, and set the attitude to
+/// hold to get it.
+#[allow(clippy::type_complexity)]
+fn drag_phasing(
+    mode: Res<RcsMode>,
+    sas_target: Res<SasTarget>,
+    mut ship: Query<(&OrbitalBody, &AttitudeState, &mut HoldAttitude, &mut Aero), With<PlayerShip>>,
+    earth: Query<
+        (&OrbitalBody, &AttitudeState, &MassiveBody),
+        (With<EarthMarker>, Without<PlayerShip>),
+    >,
+    targets: Query<&OrbitalBody, Without<PlayerShip>>,
+) {
+    let Ok((orbital, attitude, mut hold, mut aero)) = ship.single_mut() else {
+        return;
+    };
+    if !matches!(*mode, RcsMode::DragPhasing) {
+        aero.phasing = None;
+        return;
+    }
+    let Ok((earth, earth_attitude, earth_mass)) = earth.single() else {
+        return;
+    };
+    let gm = earth_mass.gm;
+    let pos = orbital.pos - earth.pos;
+    let vel = orbital.vel - earth.vel;
+
+    let target = sas_target.0.and_then(|e| targets.get(e).ok());
+    aero.phasing = target.map(|target| {
+        let target_pos = target.pos - earth.pos;
+        let target_vel = target.vel - earth.vel;
+
+        // The angle along the orbit from the craft to the target.
+        let frame = OrbitFrame::new(&pos, &vel);
+        let r = pos.normalize();
+        let t = target_pos.normalize();
+        let angle = frame.normal.dot(&r.cross(&t)).atan2(r.dot(&t));
+        let separation = -angle * pos.norm();
+
+        // The lower orbit goes around faster, by 3/2 n per unit of semi-major
+        // axis.
+        let semi_major =
+            |p: &Vector3<f64>, v: &Vector3<f64>| 1.0 / (2.0 / p.norm() - v.norm_squared() / gm);
+        let a = semi_major(&pos, &vel);
+        let n = (gm / (a * a * a)).sqrt();
+        let drift = -1.5 * n * (a - semi_major(&target_pos, &target_vel));
+
+        let projected = separation + drift * PHASING_LEAD;
+        let high_drag = if projected.abs() < PHASING_DEADBAND {
+            aero.phasing.as_ref().is_some_and(|p| p.high_drag)
+        } else {
+            projected < 0.0
+        };
+        Phasing {
+            separation,
+            drift,
+            high_drag,
+        }
+    });
+
+    // Present the chosen face to the wind.
+    let high_drag = aero.phasing.as_ref().is_some_and(|p| p.high_drag);
+    let wind_w = Atmosphere::wind_relative(earth, earth_attitude, &orbital.pos, &orbital.vel);
+    if wind_w.norm_squared() > 0.0 {
+        hold.0 = Some(point_axis_at(
+            &attitude.q_bw,
+            &aero.axis_b(high_drag),
+            &wind_w.normalize(),
+        ));
+    }
+}
@@ -0,0 +1,408 @@
+//! Ascent guidance, from the ground to orbit.
+//!
+//! The craft rises straight up until it is moving, then pitches over toward
+//! its heading, and follows a pitch program down to the horizontal by a set
+//! altitude.  The pitch above the horizon is `90° (1 - f^shape)`, where `f` is
+//! the fraction of the turn altitude climbed, so a shape below 1 turns early
+//! and a shape above 1 late.  The kick at the start of the turn is the pitch
+//! over: the profile is already some degrees off vertical by the time the
+//! craft has the speed to start it.  Should the profile lay the craft down
+//! before it has the speed to stay up, and the climb die away, it pitches up
+//! enough to hold it.
+//!
+//! The heading steers the horizontal velocity toward circular speed in the
+//! target plane, which makes up for the ground's own speed east, and keeps the
+//! track in the plane as it goes downrange.  An inclination lower than the
+//! launch latitude can't be flown directly, and gets the nearest there is,
+//! going due east.
+//!
+//! The engine is throttled back as the apoapsis nears the target, and cut when
+//! it gets there.  Coasting, the craft points prograde, and relights if drag
+//! pulls the apoapsis down again.  Once the apoapsis is a few minutes off, the
+//! autopilot takes over, to circularize there.
+//!
+//! A guided ascent only flies the pitch program through the lower half of the
+//! turn, where the air is thick.  From there, the linear tangent guidance (see
+//! `insertion`) steers it, at full throttle, straight into a circular orbit at
+//! the altitude, with no coast, and no circularizing after.  Should the
+//! guidance fail, as it can with too little thrust, it goes back to the pitch
+//! program.
+//!
+//! The console's `ascent [guided] <apoapsis km> [inclination] [turn km]
+//! [shape]` starts it, and `ascent off` stops it.  Only the craft being flown
+//! is guided.
+
+use bevy::prelude::*;
+use na::Vector3;
+use sim_astro::{EarthMarker, geodesy::Geodetic};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, PhysicsSet, SizedBody, orbit::Conic};
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{
+        GuidanceSet, HoldAttitude, MassProperties, PlayerShip, RcsMode,
+        autopilot::{AUTOPILOT_LEAD, Apsis, Autopilot, Program},
+        engine::{MainEngine, engine_fire},
+        insertion::{LinearTangent, steering_direction},
+        point_axis_at, rcs_command,
+    },
+};
+
+/// The least thrust to weight, at the start, that an ascent will fly with.
+/// Any less, and too much of the burn goes into just holding the craft up.
+const ASCENT_MIN_TWR: f64 = 1.2;
+
+/// The least climb rate, in m/s, the turn will let the craft fall to before it
+/// pitches up to hold it.  Twice this, it starts to.
+const ASCENT_MIN_CLIMB: f64 = 50.0;
+
+/// The throttle comes back over the last this many km short of the target
+/// apoapsis.
+const ASCENT_TAPER: f64 = 10.0;
+
+/// The least throttle used short of the target, so it does get there.
+const ASCENT_MIN_THROTTLE: f64 = 0.05;
+
+/// While coasting, relight if the apoapsis sags this far, in km, below the
+/// target.
+const ASCENT_SAG: f64 = 1.0;
+
+/// Hand over to the autopilot with this long, in seconds, left to apoapsis,
+/// so that it has time to plan the burn, and turn for it.
+const ASCENT_HANDOFF: f64 = AUTOPILOT_LEAD + 60.0;
+
+/// A guided ascent is handed to the guidance this far through the turn's
+/// altitude.
+const ASCENT_GUIDED_FROM: f64 = 0.5;
+
+/// Where an ascent is.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AscentPhase {
+    /// Straight up, until the craft has the speed to turn.
+    #[default]
+    Vertical,
+    /// Following the pitch program, until the apoapsis is high enough.
+    Turn,
+    /// Engine off, waiting to get near the apoapsis.
+    Coast,
+    /// Steered by the linear tangent guidance into orbit.
+    Guided,
+}
+
+impl std::fmt::Display for AscentPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AscentPhase::Vertical => write!(f, "vertical rise"),
+            AscentPhase::Turn => write!(f, "gravity turn"),
+            AscentPhase::Coast => write!(f, "coast"),
+            AscentPhase::Guided => write!(f, "guided insertion"),
+        }
+    }
+}
+
+/// Guidance flying a craft up from the earth.  Altitudes are in km above the
+/// earth's equatorial radius, as for the autopilot, except the turn's, which
+/// is over the ground.
+#[derive(Clone, Component, Debug)]
+pub struct Ascent {
+    /// The apoapsis to aim for.
+    pub apoapsis: f64,
+    /// The inclination of the orbit to the equator, in degrees.
+    pub inclination: f64,
+    /// The speed over the ground, in m/s, at which to start the turn.
+    pub pitch_speed: f64,
+    /// The height over the ground, in km, by which the turn is horizontal.
+    pub turn_altitude: f64,
+    /// The shape of the pitch program.
+    pub turn_shape: f64,
+    pub phase: AscentPhase,
+    /// The apoapsis as of the last step.
+    pub predicted: f64,
+    /// The guidance to take over from the pitch program, for a guided ascent.
+    pub guidance: Option<LinearTangent>,
+    /// The normal of the plane being flown into, found at the start.
+    normal: Option<Vector3<f64>>,
+}
+
+impl Ascent {
+    pub fn new(apoapsis: f64, inclination: f64) -> Self {
+        Ascent {
+            apoapsis,
+            inclination,
+            pitch_speed: 50.0,
+            turn_altitude: 60.0,
+            turn_shape: 0.5,
+            phase: AscentPhase::Vertical,
+            predicted: 0.0,
+            guidance: None,
+            normal: None,
+        }
+    }
+
+    /// The pitch above the horizon, in radians, at a height over the ground,
+    /// in km.
+    pub fn pitch(&self, alt: f64) -> f64 {
+        let f = (alt / self.turn_altitude).clamp(0.0, 1.0);
+        FRAC_PI_2 * (1.0 - f.powf(self.turn_shape))
+    }
+}
+
+#[derive(Default)]
+pub struct AscentPlugin;
+
+impl Plugin for AscentPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            ascent_guidance
+                .in_set(GuidanceSet)
+                .before(rcs_command)
+                .before(engine_fire)
+                .before(PhysicsSet),
+        );
+        app.add_console_command(
+            "ascent",
+            "ascent [guided] <apoapsis km> [inclination] [turn km] [shape]   fly to orbit, or \
+             ascent off",
+            ascent_command,
+        );
+    }
+}
+
+/// The normal of the plane through `up` at an inclination, in degrees, to the
+/// equator of the body with the given `pole`, heading north.
+fn launch_normal(up: &Vector3<f64>, pole: &Vector3<f64>, inclination: f64) -> Vector3<f64> {
+    let east = pole
+        .cross(up)
+        .try_normalize(1.0e-9)
+        .unwrap_or_else(|| up.cross(&Vector3::x()).normalize());
+    let north = up.cross(&east);
+    // The normal lies in the east-north plane, and only its north part, which
+    // is cos(latitude) off the pole, sets the inclination.
+    let y = (inclination.to_radians().cos() / north.dot(pole)).clamp(-1.0, 1.0);
+    let x = -(1.0 - y * y).sqrt();
+    east * x + north * y
+}
+
+/// Set the throttle and the attitude to hold, and hand over to the autopilot
+/// at the end.
+#[allow(clippy::type_complexity)]
+fn ascent_guidance(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut mode: ResMut<RcsMode>,
+    mut ship: Query<
+        (
+            Entity,
+            &mut Ascent,
+            &OrbitalBody,
+            &AttitudeState,
+            &mut HoldAttitude,
+            &MassProperties,
+            &mut MainEngine,
+            &mut Autopilot,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<
+        (&OrbitalBody, &MassiveBody, &SizedBody, &AttitudeState),
+        (With<EarthMarker>, Without<PlayerShip>),
+    >,
+) {
+    let Ok((entity, mut ascent, orbital, attitude, mut hold, mass, mut engine, mut autopilot)) =
+        ship.single_mut()
+    else {
+        return;
+    };
+    let Ok((earth, earth_mass, earth_size, earth_attitude)) = earth.single() else {
+        return;
+    };
+    let gm = earth_mass.gm;
+    let radius = earth_size.radii.x;
+    let pos = orbital.pos - earth.pos;
+    let vel = orbital.vel - earth.vel;
+    let up = pos.normalize();
+    let pole = earth_attitude.q_bw.transform_vector(&Vector3::z());
+    let spin_w = earth_attitude
+        .q_bw
+        .transform_vector(&earth_attitude.omega_b);
+    let ground_speed = (vel - spin_w.cross(&pos)).norm();
+    let alt = Geodetic::from_world(earth, earth_attitude, &earth_size.radii, &orbital.pos).alt;
+    let inclination = ascent.inclination;
+    let normal = *ascent
+        .normal
+        .get_or_insert_with(|| launch_normal(&up, &pole, inclination));
+
+    let conic = Conic::new(&pos, &vel, gm);
+    ascent.predicted = conic
+        .apoapsis()
+        .map_or(f64::INFINITY, |apo| apo.norm() - radius);
+    let short = ascent.apoapsis - ascent.predicted;
+    let throttle = (short / ASCENT_TAPER).clamp(ASCENT_MIN_THROTTLE, 1.0);
+
+    *mode = RcsMode::Ascent;
+    match ascent.phase {
+        AscentPhase::Vertical if ground_speed * 1000.0 >= ascent.pitch_speed => {
+            ascent.phase = AscentPhase::Turn;
+        }
+        AscentPhase::Turn
+            if ascent.guidance.is_some() && alt >= ascent.turn_altitude * ASCENT_GUIDED_FROM =>
+        {
+            ascent.phase = AscentPhase::Guided;
+        }
+        AscentPhase::Turn if short <= 0.0 => {
+            ascent.phase = AscentPhase::Coast;
+        }
+        AscentPhase::Coast => {
+            let to_apoapsis = conic.time_between(conic.true_anomaly(&pos), PI);
+            if to_apoapsis.is_none_or(|dt| dt <= ASCENT_HANDOFF) || pos.dot(&vel) < 0.0 {
+                engine.throttle = 0.0;
+                autopilot
+                    .programs
+                    .push_front(Program::Circularize(Apsis::Apoapsis));
+                *mode = RcsMode::Prograde;
+                commands.entity(entity).remove::<Ascent>();
+                return;
+            }
+        }
+        _ => (),
+    }
+
+    let dir_w = match ascent.phase {
+        AscentPhase::Vertical => {
+            engine.throttle = throttle;
+            up
+        }
+        AscentPhase::Turn => {
+            let v_up = vel.dot(&up);
+            let horizontal = vel - up * v_up;
+            // Steer the horizontal velocity toward circular speed in the
+            // plane.
+            let along = normal.cross(&up);
+            let circular = (gm / (radius + ascent.apoapsis)).sqrt();
+            let to_go = along * circular - horizontal;
+            let heading = (to_go - up * to_go.dot(&up)).normalize();
+            // Never let the climb die away short of orbit: below the least
+            // climb rate, put enough of the thrust up to hold it, against what
+            // gravity the speed over the horizon leaves.
+            let sag = gm / pos.norm_squared() - horizontal.norm_squared() / pos.norm();
+            engine.throttle = throttle;
+            let thrust = engine.thrust(mass.mass) / mass.mass / 1000.0;
+            let share = (2.0 - v_up * 1000.0 / ASCENT_MIN_CLIMB).clamp(0.0, 1.0);
+            let floor = (sag * share / thrust).clamp(0.0, 1.0).asin();
+            let pitch = ascent.pitch(alt).max(floor);
+            let (sin, cos) = pitch.sin_cos();
+            up * sin + heading * cos
+        }
+        AscentPhase::Coast => {
+            let burning = short > ASCENT_SAG || (engine.throttle > 0.0 && short > 0.0);
+            engine.throttle = if burning { throttle } else { 0.0 };
+            vel.normalize()
+        }
+        AscentPhase::Guided => {
+            let Some(guidance) = ascent.guidance.as_mut() else {
+                ascent.phase = AscentPhase::Turn;
+                return;
+            };
+            match guidance.steer(&pos, &vel, gm, &engine, mass.mass, time.delta_secs_f64()) {
+                Ok(steering) if steering.done => {
+                    engine.throttle = 0.0;
+                    *mode = RcsMode::Prograde;
+                    commands.entity(entity).remove::<Ascent>();
+                    return;
+                }
+                Ok(steering) => {
+                    engine.throttle = 1.0;
+                    steering_direction(&pos, &vel, &normal, guidance.speed, steering.sin_pitch)
+                }
+                Err(e) => {
+                    warn!("Ascent: {}, going back to the pitch program", e);
+                    ascent.guidance = None;
+                    ascent.phase = AscentPhase::Turn;
+                    return;
+                }
+            }
+        }
+    };
+    hold.0 = Some(point_axis_at(&attitude.q_bw, &Vector3::z(), &dir_w));
+}
+
+/// `ascent [guided] <apoapsis> [inclination] [turn km] [shape]` starts
+/// guidance, with the rest of the pitch program as it defaults, and `ascent
+/// off` stops it.
+#[allow(clippy::type_complexity)]
+fn ascent_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    mut mode: ResMut<RcsMode>,
+    mut ship: Query<
+        (
+            Entity,
+            &OrbitalBody,
+            &MassProperties,
+            &mut MainEngine,
+            Has<Ascent>,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<
+        (&OrbitalBody, &MassiveBody, &SizedBody),
+        (With<EarthMarker>, Without<PlayerShip>),
+    >,
+) -> ConsoleReply {
+    let (entity, orbital, mass, mut engine, flying) =
+        ship.single_mut().map_err(|_| "no ship".to_string())?;
+    if args == ["off"] {
+        if flying {
+            engine.throttle = 0.0;
+            *mode = RcsMode::Hold;
+            commands.entity(entity).remove::<Ascent>();
+        }
+        return Ok("ok".to_string());
+    }
+    let (guided, args) = match args.split_first() {
+        Some((guided, rest)) if guided == "guided" => (true, rest),
+        _ => (false, args.as_slice()),
+    };
+    let mut ascent = match args {
+        [apoapsis, rest @ ..] if rest.len() <= 3 => {
+            let mut ascent = Ascent::new(
+                parse_arg(apoapsis)?,
+                rest.first().map_or(Ok(0.0), |a| parse_arg(a))?,
+            );
+            if let Some(turn) = rest.get(1) {
+                ascent.turn_altitude = parse_arg(turn)?;
+            }
+            if let Some(shape) = rest.get(2) {
+                ascent.turn_shape = parse_arg(shape)?;
+            }
+            ascent
+        }
+        _ => {
+            return Err(
+                "ascent [guided] <apoapsis km> [inclination] [turn km] [shape]".to_string(),
+            );
+        }
+    };
+    if ascent.turn_altitude <= 0.0 || ascent.turn_shape <= 0.0 {
+        return Err("The turn altitude and shape must be positive".to_string());
+    }
+
+    let (earth, earth_mass, earth_size) = earth.single().map_err(|_| "no earth".to_string())?;
+    // km/s^2 to m/s^2.
+    let gravity = earth_mass.gm / (orbital.pos - earth.pos).norm_squared() * 1000.0;
+    let twr = engine.full_burn(mass.mass, 0.0).1 / gravity;
+    if twr < ASCENT_MIN_TWR {
+        return Err(format!(
+            "Thrust to weight is {:.2}, an ascent needs {:.1}",
+            twr, ASCENT_MIN_TWR
+        ));
+    }
+    if guided {
+        let radius = earth_size.radii.x + ascent.apoapsis;
+        ascent.guidance = Some(LinearTangent::to_orbit(earth_mass.gm, radius, radius));
+    }
+    commands.entity(entity).insert(ascent);
+    Ok("ok".to_string())
+}
@@ -0,0 +1,712 @@
+//! Autopilot programs.
+//!
+//! A program is a canned orbit change, such as circularizing at apoapsis, or a
+//! Hohmann transfer to some altitude.  Programs are queued on the ship, and run
+//! one at a time: each plans an armed maneuver node from the orbit as it is by
+//! then, and waits for the executor to fly it.  Programs that take more than
+//! one burn are made of simpler ones.
+//!
+//! Circularizing takes a burn of a minute or more, not an impulse at the
+//! apsis, and the craft gets lighter as it goes.  So the burn is flown in
+//! advance, the way the executor will fly it, and its Δv corrected until it
+//! ends at circular speed; and the node's time moved until the orbit that
+//! leaves is as round as it can be.
+//!
+//! Changing plane and altitude together is cheaper than one after the other:
+//! the turn can be folded into the burns of the transfer, at the node it
+//! leaves from and at the opposite one, where it arrives.  How much of the turn
+//! to make at each is found for the least Δv, and what it costs is kept to
+//! compare with making it all at either end.
+//!
+//! F4 circularizes at the next apsis, F5/F6 at apoapsis/periapsis, F7 matches
+//! planes with the SAS target, and F8 goes to an equatorial orbit.  The
+//! console's `autopilot` command can queue any of them.
+
+use bevy::prelude::*;
+use na::{Unit, UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use sim_astro::EarthMarker;
+use sim_core::{
+    AttitudeState, MassiveBody, OrbitalBody, PhysicsSet, SizedBody,
+    integrator::{Gravity, Method, Propagator, State},
+    orbit::{Conic, OrbitFrame, period, propagate},
+};
+use std::collections::VecDeque;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    keys::{InputMap, KeysApp},
+    remote::local_control,
+    ship::{
+        GuidanceSet, MassProperties, PlayerShip, SasTarget,
+        engine::MainEngine,
+        maneuver::{ManeuverNode, node_execute},
+    },
+};
+
+/// The least time, in seconds, a program leaves before its burn, so that the
+/// executor has time to turn.
+pub(crate) const AUTOPILOT_LEAD: f64 = 120.0;
+
+/// Plane changes smaller than this, in m/s, aren't worth a burn.
+pub(crate) const AUTOPILOT_MIN_DV: f64 = 0.1;
+
+/// Orbits less eccentric than this have no meaningful apsides, and are burned
+/// on as soon as there is time.
+const AUTOPILOT_CIRCULAR: f64 = 1.0e-3;
+
+/// How closely, relative to the state, a planned burn is flown.
+const BURN_TOLERANCE: f64 = 1.0e-10;
+
+/// How many times a planned burn's Δv is corrected, for each time tried.
+pub(crate) const BURN_CORRECTIONS: usize = 6;
+
+/// How closely, in seconds, the best time for a burn is found.
+const BURN_TIME_TOLERANCE: f64 = 0.1;
+
+/// How closely, in radians, the best split of a plane change is found.
+const SPLIT_TOLERANCE: f64 = 1.0e-6;
+
+/// The golden ratio's conjugate, for golden section searches.
+const GOLDEN: f64 = 0.618_033_988_749_894_9;
+
+/// The `x` in `lo..hi` where `f`, falling and then rising, is least.
+pub(crate) fn golden_section(
+    mut lo: f64,
+    mut hi: f64,
+    tolerance: f64,
+    f: impl Fn(f64) -> f64,
+) -> f64 {
+    while hi - lo > tolerance {
+        let a = hi - GOLDEN * (hi - lo);
+        let b = lo + GOLDEN * (hi - lo);
+        if f(a) < f(b) {
+            hi = b;
+        } else {
+            lo = a;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+/// A plane to turn an orbit into.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Plane {
+    /// At an inclination, in degrees, to the earth's equator, keeping the
+    /// nodes where they are.
+    Inclination(f64),
+    /// The SAS target's.
+    Target,
+}
+
+impl std::fmt::Display for Plane {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Plane::Inclination(inc) => write!(f, "{:.1} deg", inc),
+            Plane::Target => write!(f, "the target's plane"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Apsis {
+    Periapsis,
+    Apoapsis,
+    /// Whichever comes first.
+    Next,
+}
+
+/// An autopilot program.  Altitudes are in km above the earth's equatorial
+/// radius, and angles in degrees.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Program {
+    /// Make the orbit circular, at the height of the given apsis.
+    Circularize(Apsis),
+    /// Burn at the next apsis to put the opposite one at an altitude: at
+    /// periapsis to raise, or at apoapsis to lower.
+    ChangeApsis(f64),
+    /// Change the inclination to the earth's equator, at the next node.
+    Inclination(f64),
+    /// Turn the orbit into the plane of the SAS target's, at the next relative
+    /// node.
+    MatchPlanes,
+    /// A two burn transfer to a circular orbit at an altitude.
+    Hohmann(f64),
+    /// A two burn transfer to a circular orbit at an altitude, in another
+    /// plane, turning in both burns.
+    PlaneTransfer(f64, Plane),
+    /// Circularize at the next apsis, turning into a plane in the same burn.
+    CircularizeInto(Plane),
+}
+
+impl Program {
+    /// Parse a program from console words, such as `hohmann 400`.
+    pub fn parse(words: &[&str]) -> Result<Self, String> {
+        let arg = |i: usize| -> Result<f64, String> {
+            words
+                .get(i)
+                .ok_or_else(|| format!("{} needs a value", words[0]))?
+                .parse()
+                .map_err(|e| format!("{}", e))
+        };
+        match words {
+            ["circularize"] | ["circularize", "apoapsis"] => {
+                Ok(Program::Circularize(Apsis::Apoapsis))
+            }
+            ["circularize", "periapsis"] => Ok(Program::Circularize(Apsis::Periapsis)),
+            ["circularize", "next"] => Ok(Program::Circularize(Apsis::Next)),
+            ["apsis", _] => Ok(Program::ChangeApsis(arg(1)?)),
+            ["inclination", _] => Ok(Program::Inclination(arg(1)?)),
+            ["match-planes"] => Ok(Program::MatchPlanes),
+            ["hohmann", _] => Ok(Program::Hohmann(arg(1)?)),
+            ["plane-transfer", _, "target"] => Ok(Program::PlaneTransfer(arg(1)?, Plane::Target)),
+            ["plane-transfer", _, _] => {
+                Ok(Program::PlaneTransfer(arg(1)?, Plane::Inclination(arg(2)?)))
+            }
+            _ => Err(format!("Unknown program {:?}", words.join(" "))),
+        }
+    }
+}
+
+impl std::fmt::Display for Program {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Program::Circularize(Apsis::Apoapsis) => write!(f, "circularize at apoapsis"),
+            Program::Circularize(Apsis::Periapsis) => write!(f, "circularize at periapsis"),
+            Program::Circularize(Apsis::Next) => write!(f, "circularize at next apsis"),
+            Program::ChangeApsis(alt) => write!(f, "opposite apsis to {:.0} km", alt),
+            Program::Inclination(inc) => write!(f, "inclination to {:.1} deg", inc),
+            Program::MatchPlanes => write!(f, "match planes with target"),
+            Program::Hohmann(alt) => write!(f, "Hohmann transfer to {:.0} km", alt),
+            Program::PlaneTransfer(alt, plane) => {
+                write!(f, "transfer to {:.0} km in {}", alt, plane)
+            }
+            Program::CircularizeInto(plane) => write!(f, "circularize into {}", plane),
+        }
+    }
+}
+
+/// The queue of programs for a craft.
+#[derive(Clone, Component, Debug, Default, Serialize, Deserialize)]
+pub struct Autopilot {
+    /// Programs still to run, next first.
+    pub programs: VecDeque<Program>,
+    /// The program whose node is being flown.
+    pub current: Option<Program>,
+    /// Why the queue was last abandoned, if it was.
+    pub error: Option<String>,
+    /// What the last plane transfer planned costs.
+    #[serde(default)]
+    pub plane_cost: Option<PlaneTransferCost>,
+}
+
+/// What a plane transfer costs, in m/s, depending on where the turn is made.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlaneTransferCost {
+    /// All of it at the node it leaves from.
+    pub departure: f64,
+    /// All of it where it arrives.
+    pub arrival: f64,
+    /// Split between them for the least Δv, with the degrees turned on leaving.
+    pub split: f64,
+    pub split_angle: f64,
+}
+
+/// What a program needs to know to plan.  Everything is relative to the earth.
+struct PlanState {
+    pos: Vector3<f64>,
+    vel: Vector3<f64>,
+    gm: f64,
+    radius: f64,
+    pole: Vector3<f64>,
+    target_normal: Option<Vector3<f64>>,
+    /// The craft's mass, in kg, and engine, for how its burns go.
+    mass: f64,
+    engine: MainEngine,
+}
+
+/// The central body's pull, and the engine's, during a burn.  The time is from
+/// the start of the burn.
+struct Burn<'a> {
+    gm: f64,
+    mass: f64,
+    engine: &'a MainEngine,
+    /// The direction of the thrust, world frame.
+    dir: Vector3<f64>,
+}
+
+impl Gravity for Burn<'_> {
+    fn gm(&self) -> f64 {
+        self.gm
+    }
+
+    fn perturbation(&self, t: f64, _pos: &Vector3<f64>) -> Vector3<f64> {
+        // m/s^2 to km/s^2.
+        let (_, accel) = self.engine.full_burn(self.mass, t);
+        self.dir * (accel / 1000.0)
+    }
+}
+
+/// Where a craft at `pos` and `vel`, about a body with the given `gm`, is
+/// after flying a node `dt` seconds from now, with a Δv of `dv_w` (m/s, world
+/// frame), the way the executor flies it: at full thrust, in a fixed
+/// direction, centered on the node as if the mass didn't change.  The craft
+/// starts out at `mass`, in kg.
+pub(crate) fn fly(
+    pos: &Vector3<f64>,
+    vel: &Vector3<f64>,
+    gm: f64,
+    mass: f64,
+    engine: &MainEngine,
+    dt: f64,
+    dv_w: &Vector3<f64>,
+) -> (Vector3<f64>, Vector3<f64>) {
+    let dv = dv_w.norm();
+    let duration = engine.burn_time(mass, dv);
+    let (pos, vel) = propagate(pos, vel, gm, dt - duration / 2.0);
+    if dv == 0.0 {
+        return (pos, vel);
+    }
+    let burn = Burn {
+        gm,
+        mass,
+        engine,
+        dir: dv_w / dv,
+    };
+    let mut propagator = Propagator::new(
+        Method::Rk45 {
+            tolerance: BURN_TOLERANCE,
+        },
+        0.0,
+        State { pos, vel },
+    );
+    propagator.step(&burn, duration);
+    let state = propagator.state(&burn);
+    (state.pos, state.vel)
+}
+
+impl PlanState {
+    /// A node `dt` seconds from now (`now`), for the velocity change `change`
+    /// makes to the (position, velocity) there.
+    fn node(
+        &self,
+        now: f64,
+        dt: f64,
+        change: impl Fn(&Vector3<f64>, &Vector3<f64>) -> Vector3<f64>,
+    ) -> ManeuverNode {
+        let (pos, vel) = propagate(&self.pos, &self.vel, self.gm, dt);
+        let dv_w = (change(&pos, &vel) - vel) * 1000.0;
+        let frame = OrbitFrame::new(&pos, &vel);
+        let mut node = ManeuverNode::new(now + dt);
+        node.dv = Vector3::new(
+            dv_w.dot(&frame.prograde),
+            dv_w.dot(&frame.normal),
+            dv_w.dot(&frame.radial),
+        );
+        node.armed = true;
+        node
+    }
+
+    /// Where the craft is after flying a node `dt` seconds from now, with a
+    /// Δv of `dv_w` (m/s, world frame).  See `fly`.
+    fn fly(&self, dt: f64, dv_w: &Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+        fly(
+            &self.pos,
+            &self.vel,
+            self.gm,
+            self.mass,
+            &self.engine,
+            dt,
+            dv_w,
+        )
+    }
+
+    /// A node `dt` seconds from now that circularizes the orbit, burned
+    /// prograde, with its Δv corrected for the burn taking time.  Returns the
+    /// node's Δv, m/s, and the eccentricity it leaves.
+    fn circularize_at(&self, dt: f64) -> (f64, f64) {
+        let (pos, vel) = propagate(&self.pos, &self.vel, self.gm, dt);
+        let prograde = vel.normalize();
+        let mut dv = ((self.gm / pos.norm()).sqrt() - vel.norm()) * 1000.0;
+        let mut e = f64::INFINITY;
+        for _ in 0..BURN_CORRECTIONS {
+            let (end_pos, end_vel) = self.fly(dt, &(prograde * dv));
+            // Make up what is missing from the circular speed, where the burn
+            // ends, along the horizon.
+            let h = end_pos.cross(&end_vel).normalize();
+            let along = h.cross(&end_pos.normalize());
+            let circular = (self.gm / end_pos.norm()).sqrt();
+            dv += (circular - end_vel.dot(&along)) * 1000.0;
+            e = Conic::new(&end_pos, &end_vel, self.gm).e();
+        }
+        (dv, e)
+    }
+
+    /// Time until the craft gets to true anomaly `nu`, at least
+    /// `AUTOPILOT_LEAD` away.
+    fn time_to(&self, conic: &Conic, nu: f64) -> Result<f64, String> {
+        let now_nu = conic.true_anomaly(&self.pos);
+        let dt = conic
+            .time_between(now_nu, nu)
+            .ok_or_else(|| "The orbit isn't closed".to_string())?;
+        if dt >= AUTOPILOT_LEAD {
+            Ok(dt)
+        } else {
+            let orbit = period(&self.pos, &self.vel, self.gm)
+                .ok_or_else(|| "The orbit isn't closed".to_string())?;
+            Ok(dt + orbit)
+        }
+    }
+
+    /// The time to the next node relative to the plane with normal `pole`.
+    /// If the orbit is already in that plane, anywhere is a node.
+    fn time_to_node(&self, conic: &Conic, pole: &Vector3<f64>) -> Result<f64, String> {
+        let Some((ascending, descending)) = conic.nodes(pole) else {
+            return Ok(AUTOPILOT_LEAD);
+        };
+        let a = self.time_to(conic, ascending)?;
+        let d = self.time_to(conic, descending)?;
+        Ok(a.min(d))
+    }
+
+    /// The normal of a plane, to find the nodes against.
+    fn plane_normal(&self, plane: &Plane) -> Result<Vector3<f64>, String> {
+        match plane {
+            Plane::Inclination(_) => Ok(self.pole),
+            Plane::Target => self
+                .target_normal
+                .ok_or_else(|| "No SAS target to match planes with".to_string()),
+        }
+    }
+
+    /// The angle to turn the orbit normal `h` about the radius `r`, at a node
+    /// against the plane with normal `normal`, to go into `plane`.
+    fn plane_turn(
+        &self,
+        plane: &Plane,
+        normal: &Vector3<f64>,
+        h: &Vector3<f64>,
+        r: &Vector3<f64>,
+    ) -> f64 {
+        let signed = r.dot(&h.cross(normal)).atan2(h.dot(normal));
+        match plane {
+            // At a node, the radius is square to both the pole and the orbit
+            // normal, so turning about it moves the normal straight toward (or
+            // away from) the pole.
+            Plane::Inclination(inc) => {
+                let inc = inc.to_radians();
+                signed - inc.copysign(signed)
+            }
+            Plane::Target => signed,
+        }
+    }
+
+    /// The time to an apsis, at least `AUTOPILOT_LEAD` away, or soon, if the
+    /// orbit has none to speak of.
+    fn time_to_apsis(&self, conic: &Conic, apsis: Apsis) -> Result<f64, String> {
+        if conic.e() < AUTOPILOT_CIRCULAR {
+            return Ok(AUTOPILOT_LEAD);
+        }
+        match apsis {
+            Apsis::Periapsis => self.time_to(conic, 0.0),
+            Apsis::Apoapsis => self.time_to(conic, std::f64::consts::PI),
+            Apsis::Next => Ok(self
+                .time_to(conic, 0.0)?
+                .min(self.time_to(conic, std::f64::consts::PI)?)),
+        }
+    }
+
+    /// A node at the next crossing of the plane with normal `pole` that turns
+    /// the orbit normal by `turn(h, r)`, the angle to rotate about the radius.
+    /// None if there is no turn to make.
+    fn plane_change(
+        &self,
+        now: f64,
+        pole: &Vector3<f64>,
+        turn: impl Fn(&Vector3<f64>, &Vector3<f64>) -> f64,
+    ) -> Result<Option<ManeuverNode>, String> {
+        let conic = Conic::new(&self.pos, &self.vel, self.gm);
+        let dt = self.time_to_node(&conic, pole)?;
+        let node = self.node(now, dt, |pos, vel| {
+            let r = Unit::new_normalize(*pos);
+            let angle = turn(&pos.cross(vel), &r);
+            UnitQuaternion::from_axis_angle(&r, angle) * vel
+        });
+        Ok((node.dv.norm() > AUTOPILOT_MIN_DV).then_some(node))
+    }
+
+    /// Plan the node for a program.  Programs made of others expand into
+    /// them, instead, and those are put back on the `autopilot`'s queue.
+    fn plan(
+        &self,
+        now: f64,
+        program: &Program,
+        autopilot: &mut Autopilot,
+    ) -> Result<Option<ManeuverNode>, String> {
+        let conic = Conic::new(&self.pos, &self.vel, self.gm);
+        match *program {
+            Program::Circularize(apsis) => {
+                let dt = self.time_to_apsis(&conic, apsis)?;
+                // The impulse, first, for how long the burn is.
+                let impulse = self.node(now, dt, |pos, vel| {
+                    let h = pos.cross(vel).normalize();
+                    h.cross(&pos.normalize()) * (self.gm / pos.norm()).sqrt()
+                });
+                let duration = self.engine.burn_time(self.mass, impulse.dv.norm());
+                // The best time is within a burn of the apsis, and the
+                // eccentricity left falls away from it on either side.
+                let best = golden_section(
+                    (dt - duration).max(AUTOPILOT_LEAD.min(dt)),
+                    dt + duration,
+                    BURN_TIME_TOLERANCE,
+                    |t| self.circularize_at(t).1,
+                );
+                let (dv, _) = self.circularize_at(best);
+                let mut node = ManeuverNode::new(now + best);
+                node.dv = Vector3::new(dv, 0.0, 0.0);
+                node.armed = true;
+                Ok(Some(node))
+            }
+            Program::ChangeApsis(alt) => {
+                let target = self.radius + alt;
+                let a = conic.p / (1.0 - conic.e() * conic.e());
+                let dt = if conic.e() < AUTOPILOT_CIRCULAR {
+                    AUTOPILOT_LEAD
+                } else if target > a {
+                    self.time_to(&conic, 0.0)?
+                } else {
+                    self.time_to(&conic, std::f64::consts::PI)?
+                };
+                Ok(Some(self.node(now, dt, |pos, vel| {
+                    let r = pos.norm();
+                    let h = pos.cross(vel).normalize();
+                    let speed = (2.0 * self.gm * target / (r * (r + target))).sqrt();
+                    h.cross(&pos.normalize()) * speed
+                })))
+            }
+            Program::Inclination(inc) => {
+                let plane = Plane::Inclination(inc);
+                self.plane_change(now, &self.pole, |h, r| {
+                    self.plane_turn(&plane, &self.pole, h, r)
+                })
+            }
+            Program::MatchPlanes => {
+                let normal = self.plane_normal(&Plane::Target)?;
+                self.plane_change(now, &normal, |h, r| {
+                    self.plane_turn(&Plane::Target, &normal, h, r)
+                })
+            }
+            Program::Hohmann(alt) => {
+                let a = conic.p / (1.0 - conic.e() * conic.e());
+                let apsis = if self.radius + alt > a {
+                    Apsis::Apoapsis
+                } else {
+                    Apsis::Periapsis
+                };
+                autopilot.programs.push_front(Program::Circularize(apsis));
+                autopilot.programs.push_front(Program::ChangeApsis(alt));
+                Ok(None)
+            }
+            Program::PlaneTransfer(alt, plane) => {
+                let normal = self.plane_normal(&plane)?;
+                let dt = self.time_to_node(&conic, &normal)?;
+                let (pos, vel) = propagate(&self.pos, &self.vel, self.gm, dt);
+                let r = Unit::new_normalize(pos);
+                let h = pos.cross(&vel);
+                let turn = self.plane_turn(&plane, &normal, &h, &r);
+                let along = h.normalize().cross(&r);
+
+                // Onto the transfer, and then circular, as its far apsis
+                // comes at the opposite node.
+                let (r1, r2) = (pos.norm(), self.radius + alt);
+                let a = (r1 + r2) / 2.0;
+                let leave = (self.gm * (2.0 / r1 - 1.0 / a)).sqrt();
+                let arrive = (self.gm * (2.0 / r2 - 1.0 / a)).sqrt();
+                let circular = (self.gm / r2).sqrt();
+                let leave_w =
+                    |angle: f64| UnitQuaternion::from_axis_angle(&r, angle) * along * leave;
+                let cost = |angle: f64| {
+                    let rest = turn - angle;
+                    (leave_w(angle) - vel).norm()
+                        + (arrive * arrive + circular * circular
+                            - 2.0 * arrive * circular * rest.cos())
+                        .max(0.0)
+                        .sqrt()
+                };
+                let split = golden_section(turn.min(0.0), turn.max(0.0), SPLIT_TOLERANCE, cost);
+                autopilot.plane_cost = Some(PlaneTransferCost {
+                    departure: cost(turn) * 1000.0,
+                    arrival: cost(0.0) * 1000.0,
+                    split: cost(split) * 1000.0,
+                    split_angle: split.to_degrees(),
+                });
+
+                autopilot
+                    .programs
+                    .push_front(Program::CircularizeInto(plane));
+                Ok(Some(self.node(now, dt, |_, _| leave_w(split))))
+            }
+            Program::CircularizeInto(plane) => {
+                let normal = self.plane_normal(&plane)?;
+                let dt = self.time_to_apsis(&conic, Apsis::Next)?;
+                Ok(Some(self.node(now, dt, |pos, vel| {
+                    let r = Unit::new_normalize(*pos);
+                    let h = pos.cross(vel);
+                    let turn = self.plane_turn(&plane, &normal, &h, &r);
+                    UnitQuaternion::from_axis_angle(&r, turn)
+                        * h.normalize().cross(&r)
+                        * (self.gm / pos.norm()).sqrt()
+                })))
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AutopilotPlugin;
+
+impl Plugin for AutopilotPlugin {
+    fn build(&self, app: &mut App) {
+        for (action, key, _) in PROGRAM_KEYS {
+            app.add_key_action(action, &[key]);
+        }
+        app.add_systems(Update, autopilot_keys.run_if(local_control));
+        app.add_systems(
+            FixedUpdate,
+            autopilot_step
+                .in_set(GuidanceSet)
+                .before(node_execute)
+                .before(PhysicsSet),
+        );
+        app.add_console_command(
+            "autopilot",
+            "autopilot <program>   circularize [apoapsis|periapsis|next], apsis <km>, \
+             inclination <deg>, match-planes, hohmann <km>, \
+             plane-transfer <km> <deg|target>, or clear",
+            autopilot_command,
+        );
+    }
+}
+
+/// `autopilot <program>` queues a program, and `autopilot clear` empties the
+/// queue.
+fn autopilot_command(
+    In(args): In<Vec<String>>,
+    mut ship: Query<&mut Autopilot, With<PlayerShip>>,
+) -> ConsoleReply {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    let mut autopilot = ship.single_mut().map_err(|_| "no ship".to_string())?;
+    if words == ["clear"] {
+        autopilot.programs.clear();
+        return Ok("ok".to_string());
+    }
+    let program = Program::parse(&words)?;
+    let reply = format!("queued {}", program);
+    autopilot.programs.push_back(program);
+    Ok(reply)
+}
+
+/// The actions that queue programs, with their default keys, F4-F8.
+const PROGRAM_KEYS: [(&str, KeyCode, Program); 5] = [
+    (
+        "circularize",
+        KeyCode::F4,
+        Program::Circularize(Apsis::Next),
+    ),
+    (
+        "circularize-apoapsis",
+        KeyCode::F5,
+        Program::Circularize(Apsis::Apoapsis),
+    ),
+    (
+        "circularize-periapsis",
+        KeyCode::F6,
+        Program::Circularize(Apsis::Periapsis),
+    ),
+    ("match-planes", KeyCode::F7, Program::MatchPlanes),
+    ("equatorial", KeyCode::F8, Program::Inclination(0.0)),
+];
+
+/// Queue the programs whose keys went down.
+fn autopilot_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    map: Res<InputMap>,
+    mut ship: Query<&mut Autopilot, With<PlayerShip>>,
+) {
+    let Ok(mut autopilot) = ship.single_mut() else {
+        return;
+    };
+    for (action, _, program) in PROGRAM_KEYS {
+        if map.just_pressed(&kb, action) {
+            autopilot.programs.push_back(program);
+        }
+    }
+}
+
+/// Start the next program once the ship has no node to fly.
+#[allow(clippy::type_complexity)]
+fn autopilot_step(
+    mut commands: Commands,
+    time: Res<Time>,
+    sas_target: Res<SasTarget>,
+    mut ship: Query<
+        (
+            Entity,
+            &mut Autopilot,
+            &OrbitalBody,
+            &MassProperties,
+            &MainEngine,
+            Option<&ManeuverNode>,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<
+        (&OrbitalBody, &MassiveBody, &SizedBody, &AttitudeState),
+        (With<EarthMarker>, Without<PlayerShip>),
+    >,
+    targets: Query<&OrbitalBody, Without<PlayerShip>>,
+) {
+    let Ok((entity, mut autopilot, orbital, mass, engine, node)) = ship.single_mut() else {
+        return;
+    };
+    if node.is_some() {
+        return;
+    }
+    autopilot.current = None;
+    let Ok((earth, earth_mass, earth_size, earth_attitude)) = earth.single() else {
+        return;
+    };
+
+    let state = PlanState {
+        pos: orbital.pos - earth.pos,
+        vel: orbital.vel - earth.vel,
+        gm: earth_mass.gm,
+        radius: earth_size.radii.x,
+        pole: earth_attitude.q_bw.transform_vector(&Vector3::z()),
+        target_normal: sas_target
+            .0
+            .and_then(|t| targets.get(t).ok())
+            .map(|t| (t.pos - earth.pos).cross(&(t.vel - earth.vel)).normalize()),
+        mass: mass.mass,
+        engine: engine.clone(),
+    };
+    let now = time.elapsed_secs_f64();
+
+    while let Some(program) = autopilot.programs.pop_front() {
+        match state.plan(now, &program, &mut autopilot) {
+            Ok(Some(node)) => {
+                commands.entity(entity).insert(node);
+                autopilot.current = Some(program);
+                autopilot.error = None;
+                return;
+            }
+            // Expanded, or nothing to do.
+            Ok(None) => (),
+            Err(e) => {
+                autopilot.error = Some(format!("{}: {}", program, e));
+                autopilot.programs.clear();
+                return;
+            }
+        }
+    }
+}
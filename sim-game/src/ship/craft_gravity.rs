@@ -0,0 +1,204 @@
+//! Gravity between crafts.
+//!
+//! A craft is pulled by the bodies, but pulls on nothing, which is as it
+//! should be for a ship: its pull on one alongside is a few billionths of a
+//! m/s^2.  A craft heavy enough to matter, such as a captured asteroid under
+//! tow, or a station the size of a small moon, does pull, though.  With this
+//! model on, each craft of at least `CraftGravity::threshold` kg pulls on the
+//! other crafts, as a uniform ball as big as its moments of inertia make it,
+//! so the pull falls away inside it rather than blowing up.  It is still a
+//! craft, and doesn't pull on the bodies, which wouldn't notice.
+//!
+//! A craft docked to another is part of its host, whose mass has it, so it
+//! neither pulls nor is pulled on its own.
+//!
+//! - `craft-gravity`: the threshold, and the crafts over it.
+//! - `craft-gravity threshold <kg>`.
+//! - `craft-gravity mass <craft> <kg> [radius m]`: make a craft into a ball
+//!   that heavy, as big as given, or as a ball of rock would be.
+
+use bevy::prelude::*;
+use na::Vector3;
+use sim_core::{
+    AttitudeState, LinearControl, MassiveBody, OrbitalBody, PhysicsModels, PhysicsSet,
+    model_enabled,
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{MassProperties, docking::Docked, engine::engine_fire},
+};
+
+/// The gravitational constant, in km^3/(kg s^2).
+pub const G: f64 = 6.6743e-20;
+
+/// The density, in kg/m^3, of a craft made heavy without a size: rock.
+pub const ROCK_DENSITY: f64 = 2000.0;
+
+/// Which crafts pull on the others.
+#[derive(Resource, Clone, Debug)]
+pub struct CraftGravity {
+    /// The mass, in kg, from which a craft pulls.
+    pub threshold: f64,
+}
+
+impl Default for CraftGravity {
+    /// From a billion kg, which is a rocky asteroid some 100 m across, and
+    /// thousands of times any ship.
+    fn default() -> Self {
+        CraftGravity { threshold: 1.0e9 }
+    }
+}
+
+/// A craft heavy enough to pull.
+struct Source {
+    entity: Entity,
+    pos: Vector3<f64>,
+    gm: f64,
+    /// km.
+    radius: f64,
+}
+
+/// The crafts, that can pull and be pulled.
+type Crafts<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Name,
+        &'static OrbitalBody,
+        &'static MassProperties,
+    ),
+    (Without<MassiveBody>, Without<Docked>),
+>;
+
+#[derive(Default)]
+pub struct CraftGravityPlugin;
+
+impl Plugin for CraftGravityPlugin {
+    fn build(&self, app: &mut App) {
+        PhysicsModels::add(app, "craft-gravity");
+        app.init_resource::<CraftGravity>();
+        app.add_systems(
+            FixedUpdate,
+            craft_gravity
+                .after(engine_fire)
+                .before(PhysicsSet)
+                .run_if(model_enabled("craft-gravity")),
+        );
+        app.add_console_command(
+            "craft-gravity",
+            "craft-gravity [threshold <kg> | mass <craft> <kg> [radius m]]   crafts heavy enough to pull on the others",
+            craft_gravity_command,
+        );
+    }
+}
+
+/// The radius, in km, of a uniform ball with the given mass properties: its
+/// largest moment is 2/5 m r^2.
+fn ball_radius(mass: &MassProperties) -> f64 {
+    (2.5 * mass.inertia_b.max() / mass.mass).sqrt() / 1000.0
+}
+
+fn sources(settings: &CraftGravity, crafts: &Crafts) -> Vec<Source> {
+    crafts
+        .iter()
+        .filter(|(.., mass)| mass.mass >= settings.threshold)
+        .map(|(entity, _, orbital, mass)| Source {
+            entity,
+            pos: orbital.pos,
+            gm: G * mass.mass,
+            radius: ball_radius(mass),
+        })
+        .collect()
+}
+
+/// Add the heavy crafts' pull to each craft's linear acceleration.  This must
+/// run after the thrusters have set theirs.
+fn craft_gravity(
+    settings: Res<CraftGravity>,
+    crafts: Crafts,
+    mut controls: Query<(&AttitudeState, &mut LinearControl)>,
+) {
+    let sources = sources(&settings, &crafts);
+    if sources.is_empty() {
+        return;
+    }
+    for (entity, _, orbital, _) in crafts.iter() {
+        let Ok((attitude, mut linear)) = controls.get_mut(entity) else {
+            continue;
+        };
+        let mut accel_w = Vector3::zeros();
+        for source in sources.iter().filter(|source| source.entity != entity) {
+            let rel = source.pos - orbital.pos;
+            // Inside the ball, only what is nearer the middle pulls.
+            let r = rel.norm().max(source.radius);
+            if r > 0.0 {
+                accel_w += rel * (source.gm / (r * r * r));
+            }
+        }
+        linear.accel_b += attitude.q_bw.inverse_transform_vector(&accel_w);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn craft_gravity_command(
+    In(args): In<Vec<String>>,
+    mut settings: ResMut<CraftGravity>,
+    mut crafts: Query<(&Name, &mut MassProperties), (Without<MassiveBody>, Without<Docked>)>,
+) -> ConsoleReply {
+    let usage = "craft-gravity [threshold <kg> | mass <craft> <kg> [radius m]]";
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => {}
+        ["threshold", kg] => {
+            let kg = parse_arg(kg)?;
+            if kg <= 0.0 {
+                return Err("The threshold must be more than 0 kg".to_string());
+            }
+            settings.threshold = kg;
+        }
+        ["mass", name, kg] | ["mass", name, kg, _] => {
+            let kg = parse_arg(kg)?;
+            if kg <= 0.0 {
+                return Err("The mass must be more than 0 kg".to_string());
+            }
+            let radius = match args.get(3) {
+                Some(radius) => parse_arg(radius)?,
+                None => (3.0 * kg / (4.0 * std::f64::consts::PI * ROCK_DENSITY)).cbrt(),
+            };
+            let (_, mut mass) = crafts
+                .iter_mut()
+                .find(|(craft, _)| craft.as_str().eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("No such craft as {:?}", name))?;
+            mass.mass = kg;
+            mass.inertia_b = Vector3::repeat(0.4 * kg * radius * radius);
+        }
+        _ => return Err(usage.to_string()),
+    }
+    let heavy: Vec<String> = crafts
+        .iter()
+        .filter(|(_, mass)| mass.mass >= settings.threshold)
+        .map(|(name, mass)| {
+            format!(
+                "{} {:.3e} kg, {:.0} m",
+                name,
+                mass.mass,
+                ball_radius(mass) * 1000.0
+            )
+        })
+        .collect();
+    Ok(format!(
+        "crafts from {:.3e} kg pull: {}",
+        settings.threshold,
+        if heavy.is_empty() {
+            "none".to_string()
+        } else {
+            heavy.join(", ")
+        }
+    ))
+}
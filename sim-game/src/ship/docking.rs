@@ -0,0 +1,481 @@
+//! Docking.
+//!
+//! A craft can have a `DockingPort`: a point on its hull, with the direction
+//! it faces, and the envelope it can catch another port in.  After each
+//! physics step, every pair of free ports is checked, and when two are close
+//! enough, lined up face to face, and closing gently enough, their latches
+//! catch (a soft capture), and the crafts become one.  The ports are pulled
+//! together, and the smaller craft (or whichever isn't the player ship) is
+//! carried by the other from then on: its mass, center of mass, and inertia
+//! are added to the host's, and their momentum and angular momentum are kept.
+//! `undock` splits them again, pushing them apart at `SEPARATION_SPEED`.
+//!
+//! The products of inertia of the combination are left out, as
+//! `MassProperties` only has principal moments, so it turns a little wrongly
+//! when the crafts aren't lined up along their axes.
+//!
+//! The console's `dock` command shows how the ship's port stands against the
+//! nearest other port, `undock` lets go, and `station <name> <distance>` puts
+//! a station with a port in the ship's orbit, ahead of it, facing back, to
+//! practice on.
+
+use bevy::prelude::*;
+use na::{Matrix3, UnitQuaternion, Vector3};
+use sim_astro::EarthMarker;
+use sim_core::{
+    AttitudeIntegrator, AttitudeState, MassiveBody, OrbitalBody, PhysicsSet, PostPhysicsSet,
+    orbit::propagate,
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{MassProperties, PlayerShip, SasTarget, parts::parallel_axis, point_axis_at},
+};
+
+/// How fast, in m/s, undocking pushes the crafts apart.
+const SEPARATION_SPEED: f64 = 0.05;
+
+/// A docking port.
+#[derive(Clone, Component, Debug)]
+pub struct DockingPort {
+    /// Where the port is, in m, relative to the body origin, BODY frame.
+    pub pos_b: Vector3<f64>,
+    /// The way it faces, BODY frame.
+    pub axis_b: Vector3<f64>,
+    /// How far, in m, the other port can be for the latches to catch it, both
+    /// along the axis and across it.
+    pub capture_range: f64,
+    /// How far off facing the other port it can be, in radians.
+    pub capture_angle: f64,
+    /// How fast, in m/s, the ports can be closing.
+    pub max_closing: f64,
+    /// The craft docked here.
+    pub docked: Option<Entity>,
+}
+
+impl DockingPort {
+    /// A port with a typical envelope: 30 cm, 5 degrees, and 10 cm/s.
+    pub fn new(pos_b: Vector3<f64>, axis_b: Vector3<f64>) -> Self {
+        DockingPort {
+            pos_b,
+            axis_b: axis_b.normalize(),
+            capture_range: 0.3,
+            capture_angle: 5.0_f64.to_radians(),
+            max_closing: 0.1,
+            docked: None,
+        }
+    }
+}
+
+/// A craft being carried by another, through their docked ports.
+#[derive(Clone, Component, Debug)]
+pub struct Docked {
+    pub host: Entity,
+    /// Where this craft's center of mass is, in m, relative to the host's body
+    /// origin, host BODY frame.
+    pub pos_b: Vector3<f64>,
+    /// This craft's orientation relative to the host, body to host body.
+    pub q_bh: UnitQuaternion<f64>,
+    /// The host's own mass properties, for when they part.
+    pub host_mass: MassProperties,
+}
+
+/// A port, where it is, and how it is moving, at one moment.
+struct PortState {
+    /// Position, in m, and velocity, in m/s, relative to the SSB.
+    pos: Vector3<f64>,
+    vel: Vector3<f64>,
+    axis: Vector3<f64>,
+}
+
+impl PortState {
+    fn new(
+        port: &DockingPort,
+        orbital: &OrbitalBody,
+        attitude: &AttitudeState,
+        mass: &MassProperties,
+    ) -> Self {
+        let arm_w = attitude.q_bw.transform_vector(&(port.pos_b - mass.cg_b));
+        let omega_w = attitude.q_bw.transform_vector(&attitude.omega_b);
+        PortState {
+            pos: orbital.pos * 1000.0 + arm_w,
+            vel: orbital.vel * 1000.0 + omega_w.cross(&arm_w),
+            axis: attitude.q_bw.transform_vector(&port.axis_b),
+        }
+    }
+}
+
+/// How one port stands against another.
+#[derive(Clone, Debug)]
+pub struct Approach {
+    /// How far the other port is in front, along this one's axis, and off to
+    /// the side, in m.
+    pub along: f64,
+    pub across: f64,
+    /// How far the ports are from facing each other, in radians.
+    pub angle: f64,
+    /// How fast, in m/s, the other port is closing along the axis.
+    pub closing: f64,
+}
+
+impl Approach {
+    fn new(a: &PortState, b: &PortState) -> Self {
+        let rel = b.pos - a.pos;
+        let along = rel.dot(&a.axis);
+        Approach {
+            along,
+            across: (rel - a.axis * along).norm(),
+            angle: a.axis.angle(&-b.axis),
+            closing: -(b.vel - a.vel).dot(&a.axis),
+        }
+    }
+
+    /// Whether this is within both ports' envelopes.
+    fn captures(&self, a: &DockingPort, b: &DockingPort) -> bool {
+        let range = a.capture_range.min(b.capture_range);
+        self.along.abs() < range
+            && self.across < range
+            && self.angle < a.capture_angle.min(b.capture_angle)
+            && self.closing > 0.0
+            && self.closing < a.max_closing.min(b.max_closing)
+    }
+}
+
+#[derive(Default)]
+pub struct DockingPlugin;
+
+impl Plugin for DockingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (soft_capture, carry_docked)
+                .chain()
+                .after(PhysicsSet)
+                .before(PostPhysicsSet),
+        );
+        app.add_console_command(
+            "dock",
+            "dock   how the ship's port stands against the nearest other one",
+            dock_command,
+        );
+        app.add_console_command(
+            "undock",
+            "undock   let go of the docked craft",
+            undock_command,
+        );
+        app.add_console_command(
+            "station",
+            "station <name> <distance m>   put a station with a port ahead of the ship",
+            station_command,
+        );
+    }
+}
+
+/// The crafts that can dock.
+type Crafts<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut DockingPort,
+        &'static mut OrbitalBody,
+        &'static mut AttitudeState,
+        &'static mut MassProperties,
+        Has<PlayerShip>,
+    ),
+    Without<Docked>,
+>;
+
+/// The mass properties of two crafts together, in the frame of the first,
+/// with the second's center of mass at `pos_b` (from the first's body
+/// origin), turned by `q_bh` (its body to the first's).  Only the principal
+/// moments are kept.
+fn combine(
+    host: &MassProperties,
+    other: &MassProperties,
+    pos_b: &Vector3<f64>,
+    q_bh: &UnitQuaternion<f64>,
+) -> MassProperties {
+    let mass = host.mass + other.mass;
+    let cg_b = (host.cg_b * host.mass + pos_b * other.mass) / mass;
+    let rotation = q_bh.to_rotation_matrix();
+    let inertia = Matrix3::from_diagonal(&host.inertia_b)
+        + parallel_axis(&(host.cg_b - cg_b), host.mass)
+        + rotation.matrix()
+            * Matrix3::from_diagonal(&other.inertia_b)
+            * rotation.matrix().transpose()
+        + parallel_axis(&(pos_b - cg_b), other.mass);
+    MassProperties {
+        mass,
+        cg_b,
+        inertia_b: inertia.diagonal(),
+    }
+}
+
+/// Catch any pair of free ports that are within their envelopes, and merge
+/// their crafts.
+fn soft_capture(
+    mut commands: Commands,
+    mut crafts: Crafts,
+    names: Query<&Name>,
+    integrator: Res<AttitudeIntegrator>,
+    time: Res<Time>,
+) {
+    let lead = integrator.lead(time.delta_secs_f64());
+    let free: Vec<_> = crafts
+        .iter()
+        .filter(|(_, port, ..)| port.docked.is_none())
+        .map(|(entity, port, orbital, attitude, mass, player)| {
+            (
+                entity,
+                PortState::new(port, orbital, attitude, mass),
+                port.clone(),
+                mass.mass,
+                player,
+            )
+        })
+        .collect();
+
+    let mut caught = Vec::new();
+    for (i, (a, a_state, a_port, a_mass, a_player)) in free.iter().enumerate() {
+        for (b, b_state, b_port, b_mass, b_player) in &free[i + 1..] {
+            if caught
+                .iter()
+                .any(|(h, o)| [a, b].contains(&h) || [a, b].contains(&o))
+            {
+                continue;
+            }
+            if !Approach::new(a_state, b_state).captures(a_port, b_port) {
+                continue;
+            }
+            // The player ship, or else the heavier, carries the other.
+            let a_hosts = if *a_player != *b_player {
+                *a_player
+            } else {
+                a_mass >= b_mass
+            };
+            caught.push(if a_hosts { (*a, *b) } else { (*b, *a) });
+        }
+    }
+
+    for (host, other) in caught {
+        let Ok([h, o]) = crafts.get_many_mut([host, other]) else {
+            continue;
+        };
+        let (_, mut h_port, mut h_orbital, mut h_attitude, mut h_mass, _) = h;
+        let (_, mut o_port, mut o_orbital, mut o_attitude, o_mass, _) = o;
+
+        // Pull the other craft in, so the ports meet face to face.
+        let h_port_w = PortState::new(&h_port, &h_orbital, &h_attitude, &h_mass);
+        let q_bw = point_axis_at(&o_attitude.q_bw, &o_port.axis_b, &-h_port_w.axis);
+        let o_pos = h_port_w.pos - q_bw.transform_vector(&(o_port.pos_b - o_mass.cg_b));
+        let pos_b = h_attitude
+            .q_bw
+            .inverse_transform_vector(&(o_pos - h_orbital.pos * 1000.0))
+            + h_mass.cg_b;
+        let q_bh = h_attitude.q_bw.inverse() * q_bw;
+        let combined = combine(&h_mass, &o_mass, &pos_b, &q_bh);
+
+        // Momentum, and angular momentum about the combined center of mass,
+        // world frame, in SI.
+        let total = combined.mass;
+        let cg_w = h_orbital.pos * 1000.0
+            + h_attitude
+                .q_bw
+                .transform_vector(&(combined.cg_b - h_mass.cg_b));
+        let vel = (h_orbital.vel * h_mass.mass + o_orbital.vel * o_mass.mass) * 1000.0 / total;
+        let spin = |attitude: &AttitudeState, mass: &MassProperties| {
+            attitude
+                .q_bw
+                .transform_vector(&mass.inertia_b.component_mul(&attitude.omega_b))
+        };
+        let momentum = spin(&h_attitude, &h_mass)
+            + spin(&o_attitude, &o_mass)
+            + (h_orbital.pos * 1000.0 - cg_w).cross(&(h_orbital.vel * 1000.0 - vel)) * h_mass.mass
+            + (o_pos - cg_w).cross(&(o_orbital.vel * 1000.0 - vel)) * o_mass.mass;
+
+        commands.entity(other).insert(Docked {
+            host,
+            pos_b,
+            q_bh,
+            host_mass: h_mass.clone(),
+        });
+        h_port.docked = Some(other);
+        o_port.docked = Some(host);
+        h_orbital.pos = cg_w / 1000.0;
+        h_orbital.vel = vel / 1000.0;
+        let momentum_b = h_attitude
+            .q_bw_now(lead)
+            .inverse_transform_vector(&momentum);
+        h_attitude.set_omega_b(&momentum_b.component_div(&combined.inertia_b), lead);
+        o_orbital.pos = o_pos / 1000.0;
+        o_attitude.q_bw = q_bw;
+        *h_mass = combined;
+
+        let name = |entity| names.get(entity).map_or("?".to_string(), |n| n.to_string());
+        info!("{} docked with {}", name(other), name(host));
+    }
+}
+
+/// Move the docked crafts along with their hosts.
+fn carry_docked(
+    mut docked: Query<(&Docked, &mut OrbitalBody, &mut AttitudeState)>,
+    hosts: Query<(&OrbitalBody, &AttitudeState, &MassProperties), Without<Docked>>,
+) {
+    for (docked, mut orbital, mut attitude) in docked.iter_mut() {
+        let Ok((h_orbital, h_attitude, h_mass)) = hosts.get(docked.host) else {
+            continue;
+        };
+        let arm_w = h_attitude
+            .q_bw
+            .transform_vector(&(docked.pos_b - h_mass.cg_b));
+        let omega_w = h_attitude.q_bw.transform_vector(&h_attitude.omega_b);
+        orbital.pos = h_orbital.pos + arm_w / 1000.0;
+        orbital.vel = h_orbital.vel + omega_w.cross(&arm_w) / 1000.0;
+        attitude.q_bw = h_attitude.q_bw * docked.q_bh;
+        attitude.omega_b = docked.q_bh.inverse_transform_vector(&h_attitude.omega_b);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn dock_command(
+    In(_): In<Vec<String>>,
+    crafts: Query<(
+        Entity,
+        &DockingPort,
+        &OrbitalBody,
+        &AttitudeState,
+        &MassProperties,
+        Has<PlayerShip>,
+    )>,
+    names: Query<&Name>,
+) -> ConsoleReply {
+    let (ship, port, orbital, attitude, mass, _) = crafts
+        .iter()
+        .find(|(.., player)| *player)
+        .ok_or_else(|| "The ship has no docking port".to_string())?;
+    if let Some(other) = port.docked {
+        let name = names.get(other).map_or("?".to_string(), |n| n.to_string());
+        return Ok(format!("Docked with {}", name));
+    }
+    let state = PortState::new(port, orbital, attitude, mass);
+    let (other, other_port, approach) = crafts
+        .iter()
+        .filter(|(entity, other, ..)| *entity != ship && other.docked.is_none())
+        .map(|(entity, other, orbital, attitude, mass, _)| {
+            let other_state = PortState::new(other, orbital, attitude, mass);
+            (entity, other, Approach::new(&state, &other_state))
+        })
+        .min_by(|a, b| {
+            let distance = |approach: &Approach| approach.along.hypot(approach.across);
+            distance(&a.2).total_cmp(&distance(&b.2))
+        })
+        .ok_or_else(|| "No other port".to_string())?;
+    let name = names.get(other).map_or("?".to_string(), |n| n.to_string());
+    Ok(format!(
+        "{}: {:.2} m ahead, {:.2} m across, {:.1} deg off, closing at {:.3} m/s ({})",
+        name,
+        approach.along,
+        approach.across,
+        approach.angle.to_degrees(),
+        approach.closing,
+        if approach.captures(port, other_port) {
+            "capture"
+        } else {
+            "outside the envelope"
+        }
+    ))
+}
+
+#[allow(clippy::type_complexity)]
+fn undock_command(
+    In(_): In<Vec<String>>,
+    mut commands: Commands,
+    mut ship: Query<
+        (
+            &mut DockingPort,
+            &mut OrbitalBody,
+            &AttitudeState,
+            &mut MassProperties,
+        ),
+        (With<PlayerShip>, Without<Docked>),
+    >,
+    mut docked: Query<(&Docked, &mut DockingPort, &mut OrbitalBody), Without<PlayerShip>>,
+) -> ConsoleReply {
+    let (mut port, mut orbital, attitude, mut mass) = ship
+        .single_mut()
+        .map_err(|_| "The ship has no docking port".to_string())?;
+    let other = port.docked.ok_or_else(|| "Not docked".to_string())?;
+    let (dock, mut other_port, mut other_orbital) = docked
+        .get_mut(other)
+        .map_err(|_| "The docked craft is gone".to_string())?;
+
+    // The ship's own center of mass, and how fast that point is moving.
+    let own = &dock.host_mass;
+    let arm_w = attitude.q_bw.transform_vector(&(own.cg_b - mass.cg_b));
+    let omega_w = attitude.q_bw.transform_vector(&attitude.omega_b);
+    let total = mass.mass;
+    orbital.pos += arm_w / 1000.0;
+    orbital.vel += omega_w.cross(&arm_w) / 1000.0;
+
+    // Push apart along the ship's port, sharing the impulse by mass.
+    let axis_w = attitude.q_bw.transform_vector(&port.axis_b);
+    let other_mass = total - own.mass;
+    orbital.vel -= axis_w * SEPARATION_SPEED * other_mass / total / 1000.0;
+    other_orbital.vel += axis_w * SEPARATION_SPEED * own.mass / total / 1000.0;
+
+    *mass = own.clone();
+    port.docked = None;
+    other_port.docked = None;
+    commands.entity(other).remove::<Docked>();
+    Ok("ok".to_string())
+}
+
+/// `station <name> <distance>` puts a station in the ship's orbit about the
+/// earth, `distance` m ahead, with its port facing back along the orbit, and
+/// makes it the SAS target.
+fn station_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    mut sas_target: ResMut<SasTarget>,
+    ship: Query<&OrbitalBody, With<PlayerShip>>,
+    earth: Query<(&OrbitalBody, &MassiveBody), With<EarthMarker>>,
+    names: Query<&Name>,
+) -> ConsoleReply {
+    let [name, distance] = args.as_slice() else {
+        return Err("station <name> <distance m>".to_string());
+    };
+    if names.iter().any(|n| n.as_str().eq_ignore_ascii_case(name)) {
+        return Err(format!("There is already a {:?}", name));
+    }
+    let distance = parse_arg(distance)?;
+    let ship = ship.single().map_err(|_| "no ship".to_string())?;
+    let (earth, earth_mass) = earth.single().map_err(|_| "no earth".to_string())?;
+
+    let (pos, vel) = propagate(
+        &(ship.pos - earth.pos),
+        &(ship.vel - earth.vel),
+        earth_mass.gm,
+        distance / 1000.0 / (ship.vel - earth.vel).norm(),
+    );
+    // A station is a bigger cylinder, with its port on the end.
+    let port = DockingPort::new(Vector3::new(0.0, 0.0, 5.0), Vector3::z());
+    let q_bw = point_axis_at(&UnitQuaternion::identity(), &port.axis_b, &-vel);
+    let entity = commands
+        .spawn((
+            Name::new(name.clone()),
+            OrbitalBody {
+                pos: earth.pos + pos,
+                vel: earth.vel + vel,
+            },
+            AttitudeState {
+                q_bw,
+                omega_b: Vector3::zeros(),
+            },
+            MassProperties::cylinder(20_000.0, 2.0, 10.0),
+            port,
+        ))
+        .id();
+    sas_target.0 = Some(entity);
+    Ok("ok".to_string())
+}
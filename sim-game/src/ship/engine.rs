@@ -0,0 +1,439 @@
+//! The main engine.
+//!
+//! The engine fires along the BODY +Z axis, from where it is mounted.  An
+//! engine with a specific impulse uses up the craft's mass as it burns, drawn
+//! from its `FuelTank`, and flames out when that is dry.  A craft with no tank
+//! has as much as it likes.
+//!
+//! Thrust that doesn't pass through the center of mass turns the craft, as
+//! the RCS does.  An engine on a gimbal can be swung off the axis, and while
+//! it burns, the gimbal is trimmed to point the thrust back through the
+//! center of mass, at the rate the actuators can move it.  That keeps the
+//! craft from being turned as its center of mass moves (after docking, say),
+//! within the gimbal's range; past that, the RCS has to hold it.
+//!
+//! The autothrottle holds the craft to an acceleration, as a crew's g limit
+//! has it: however far the throttle is open, the thrust is cut back to no
+//! more than that acceleration gives the craft's mass, so that as the
+//! propellant burns off, a burn at full throttle keeps on at the same
+//! acceleration, rather than pushing harder and harder.  That also makes a
+//! long burn go the way it was planned, as the planner knows to fly it so
+//! (see `MainEngine::full_burn`).
+//!
+//! - Shift/Ctrl: throttle up/down, a half per second, when held on their own.
+//! - `throttle [percent]`: show or set the throttle.
+//! - `throttle auto <m/s^2 | g's>g | off`: hold the acceleration to that, as
+//!   `throttle auto 2g`, or stop.
+//! - `gimbal`: the gimbal's angles.
+//! - `gimbal <pitch> <yaw>`: set them, in degrees, and stop trimming.
+//! - `gimbal trim`: trim again.
+
+use bevy::prelude::*;
+use na::{Vector2, Vector3};
+use serde::{Deserialize, Serialize};
+use sim_core::{AttitudeControl, LinearControl};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    remote::local_control,
+    ship::{MassProperties, PlayerShip, propulsion::G0},
+};
+
+/// How fast, per second, the throttle keys move the throttle.
+pub(crate) const THROTTLE_RATE: f64 = 0.5;
+
+/// How fast, in radians per second, the gimbal's actuators swing it.
+const GIMBAL_RATE: f64 = 0.1;
+
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct MainEngine {
+    /// Thrust at full throttle, in Newtons.
+    pub max_thrust: f64,
+    /// Current throttle setting, 0..=1.
+    pub throttle: f64,
+    /// Specific impulse, in seconds, or None for an engine that burns nothing.
+    #[serde(default)]
+    pub isp: Option<f64>,
+    /// Where the thrust acts, in m, relative to the body origin, BODY frame.
+    #[serde(default)]
+    pub pos_b: Vector3<f64>,
+    /// The gimbal's angles, in radians: the pitch about BODY X, then the yaw
+    /// about BODY Y, which swing the thrust off +Z.
+    #[serde(default)]
+    pub gimbal: Vector2<f64>,
+    /// How far, in radians, the gimbal swings either way.
+    #[serde(default)]
+    pub max_gimbal: f64,
+    /// Whether the gimbal is being held where it was put, rather than
+    /// trimmed.
+    #[serde(default)]
+    pub gimbal_held: bool,
+    /// The acceleration, in m/s^2, the autothrottle holds the thrust to, if
+    /// it is on.
+    #[serde(default)]
+    pub autothrottle: Option<f64>,
+}
+
+impl MainEngine {
+    pub fn new(max_thrust: f64) -> Self {
+        MainEngine {
+            max_thrust,
+            throttle: 0.0,
+            isp: None,
+            pos_b: Vector3::zeros(),
+            gimbal: Vector2::zeros(),
+            max_gimbal: 0.0,
+            gimbal_held: false,
+            autothrottle: None,
+        }
+    }
+
+    /// The engine, mounted at `pos_b`.
+    pub fn at(self, pos_b: Vector3<f64>) -> Self {
+        MainEngine { pos_b, ..self }
+    }
+
+    /// The engine, on a gimbal that swings `max_gimbal` radians either way.
+    pub fn with_gimbal(self, max_gimbal: f64) -> Self {
+        MainEngine { max_gimbal, ..self }
+    }
+
+    pub fn with_isp(self, isp: f64) -> Self {
+        MainEngine {
+            isp: Some(isp),
+            ..self
+        }
+    }
+
+    /// The mass, in kg/s, burned at full throttle.
+    pub fn mass_flow(&self) -> f64 {
+        self.isp.map_or(0.0, |isp| self.max_thrust / (isp * G0))
+    }
+
+    /// The time, in seconds, to deliver `dv` (m/s) at full throttle to a craft
+    /// of the given `mass` (kg), as it gets lighter, and with the autothrottle
+    /// holding it back, once it is light enough to.
+    pub fn burn_time(&self, mass: f64, dv: f64) -> f64 {
+        let full = |dv: f64| match self.isp {
+            Some(isp) => mass * (1.0 - (-dv / (isp * G0)).exp()) / self.mass_flow(),
+            None => dv * mass / self.max_thrust,
+        };
+        let Some(accel) = self.autothrottle else {
+            return full(dv);
+        };
+        let light = self.max_thrust / accel;
+        if mass <= light {
+            return dv / accel;
+        }
+        // The Δv at full throttle, before it is light enough.
+        let before = self
+            .isp
+            .map_or(f64::INFINITY, |isp| isp * G0 * (mass / light).ln());
+        if dv <= before {
+            full(dv)
+        } else {
+            full(before) + (dv - before) / accel
+        }
+    }
+
+    /// The craft's mass (kg), and its acceleration (m/s^2), `t` seconds into
+    /// a burn at full throttle, starting at `mass`, as the autothrottle, if
+    /// on, holds it back.
+    pub fn full_burn(&self, mass: f64, t: f64) -> (f64, f64) {
+        let flow = self.mass_flow();
+        let light = self
+            .autothrottle
+            .map_or(0.0, |accel| self.max_thrust / accel);
+        // At full throttle, until it is light enough for the autothrottle.
+        let held = if mass <= light {
+            0.0
+        } else if flow > 0.0 {
+            (mass - light) / flow
+        } else {
+            f64::INFINITY
+        };
+        match (self.autothrottle, self.isp) {
+            (Some(accel), Some(isp)) if t > held => {
+                let mass = mass.min(light) * (-accel * (t - held) / (isp * G0)).exp();
+                (mass, accel)
+            }
+            (Some(accel), None) if t > held => (mass, accel),
+            _ => {
+                let mass = mass - flow * t;
+                (mass, self.max_thrust / mass)
+            }
+        }
+    }
+
+    /// The thrust, in N, at the current throttle, on a craft of `mass` kg, as
+    /// the autothrottle, if on, cuts it back.
+    pub fn thrust(&self, mass: f64) -> f64 {
+        let thrust = self.max_thrust * self.throttle;
+        self.autothrottle
+            .map_or(thrust, |accel| thrust.min(accel * mass))
+    }
+
+    /// The throttle the engine is really at, on a craft of `mass` kg, with the
+    /// autothrottle.
+    pub fn held_throttle(&self, mass: f64) -> f64 {
+        if self.max_thrust > 0.0 {
+            self.thrust(mass) / self.max_thrust
+        } else {
+            0.0
+        }
+    }
+
+    /// The direction of the thrust, BODY frame, through the gimbal.
+    pub fn direction_b(&self) -> Vector3<f64> {
+        let (pitch, yaw) = (self.gimbal.x, self.gimbal.y);
+        Vector3::new(
+            pitch.cos() * yaw.sin(),
+            -pitch.sin(),
+            pitch.cos() * yaw.cos(),
+        )
+    }
+
+    /// The gimbal's angles to thrust along `direction_b`, as near as its
+    /// range lets it.
+    pub fn gimbal_for(&self, direction_b: &Vector3<f64>) -> Vector2<f64> {
+        let d = direction_b.normalize();
+        let limit = |angle: f64| angle.clamp(-self.max_gimbal, self.max_gimbal);
+        Vector2::new(limit((-d.y).asin()), limit(d.x.atan2(d.z)))
+    }
+
+    /// The gimbal's angles to thrust through `cg_b`, the center of mass.
+    pub fn trim(&self, cg_b: &Vector3<f64>) -> Vector2<f64> {
+        self.gimbal_for(&(cg_b - self.pos_b))
+    }
+
+    /// The thrust, in N, BODY frame, at the current throttle, on a craft of
+    /// `mass` kg.
+    pub fn force_b(&self, mass: f64) -> Vector3<f64> {
+        self.direction_b() * self.thrust(mass)
+    }
+
+    /// The torque, in N*m, BODY frame, the thrust makes about `cg_b`, on a
+    /// craft of `mass` kg.
+    pub fn torque_b(&self, cg_b: &Vector3<f64>, mass: f64) -> Vector3<f64> {
+        (self.pos_b - cg_b).cross(&self.force_b(mass))
+    }
+
+    /// The acceleration, in m/s^2, BODY frame, at the current throttle.
+    pub fn accel_b(&self, mass: f64) -> Vector3<f64> {
+        self.force_b(mass) / mass
+    }
+}
+
+/// The propellant the main engine burns.  Its mass is part of the craft's.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct FuelTank {
+    /// Propellant left, in kg.
+    pub propellant: f64,
+    /// Propellant when full, in kg.
+    pub capacity: f64,
+}
+
+impl FuelTank {
+    pub fn full(capacity: f64) -> Self {
+        FuelTank {
+            propellant: capacity,
+            capacity,
+        }
+    }
+
+    /// How full it is, 0..=1.
+    pub fn fraction(&self) -> f64 {
+        if self.capacity > 0.0 {
+            self.propellant / self.capacity
+        } else {
+            0.0
+        }
+    }
+
+    /// The Δv, in m/s, left in the tank for `engine` on a craft of `mass` kg.
+    pub fn delta_v(&self, engine: &MainEngine, mass: f64) -> f64 {
+        engine.isp.map_or(f64::INFINITY, |isp| {
+            isp * G0 * (mass / (mass - self.propellant.min(mass)).max(f64::MIN_POSITIVE)).ln()
+        })
+    }
+}
+
+/// Add the engine's thrust, and its torque, to the craft's accelerations, and
+/// take what it burns off the mass and out of the tank.  This must run after
+/// the RCS has set its part.
+#[allow(clippy::type_complexity)]
+pub fn engine_fire(
+    time: Res<Time>,
+    mut query: Query<(
+        &Name,
+        &mut MainEngine,
+        Option<&mut FuelTank>,
+        &mut MassProperties,
+        &mut LinearControl,
+        Option<&mut AttitudeControl>,
+    )>,
+) {
+    for (name, mut engine, tank, mut mass, mut linear, attitude) in query.iter_mut() {
+        let accel_b = engine.accel_b(mass.mass);
+        let alpha_b = engine
+            .torque_b(&mass.cg_b, mass.mass)
+            .component_div(&mass.inertia_b);
+        let mut burned =
+            engine.mass_flow() * engine.held_throttle(mass.mass) * time.delta_secs_f64();
+        // The share of the step it burns for, if the tank runs dry partway.
+        let mut share = 1.0;
+        if let Some(mut tank) = tank
+            && burned > 0.0
+        {
+            if burned >= tank.propellant {
+                share = tank.propellant / burned;
+                burned = tank.propellant;
+                engine.throttle = 0.0;
+                warn!("{}: flameout, out of propellant", name);
+            }
+            tank.propellant -= burned;
+        }
+        // m/s^2 to km/s^2.
+        linear.accel_b += accel_b * share / 1000.0;
+        if let Some(mut attitude) = attitude {
+            attitude.alpha_b += alpha_b * share;
+        }
+        if burned > 0.0 {
+            // Taken evenly from everywhere, so the inertia goes down with it.
+            let scale = (mass.mass - burned) / mass.mass;
+            mass.mass -= burned;
+            mass.inertia_b *= scale;
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct EnginePlugin;
+
+impl Plugin for EnginePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, throttle_keys.run_if(local_control));
+        app.add_systems(FixedUpdate, gimbal_trim.before(engine_fire));
+        app.add_console_command(
+            "throttle",
+            "throttle [percent | auto <m/s^2 | g's>g | auto off]   show or set the main engine's \
+             throttle, or hold the acceleration",
+            throttle_command,
+        );
+        app.add_console_command(
+            "gimbal",
+            "gimbal [<pitch> <yaw> | trim]   show or set the engine's gimbal, in degrees",
+            gimbal_command,
+        );
+    }
+}
+
+/// Swing each burning engine's gimbal toward the trim, unless it is held.
+fn gimbal_trim(time: Res<Time>, mut query: Query<(&mut MainEngine, &MassProperties)>) {
+    let step = GIMBAL_RATE * time.delta_secs_f64();
+    for (mut engine, mass) in query.iter_mut() {
+        if engine.max_gimbal <= 0.0 || engine.gimbal_held || engine.throttle <= 0.0 {
+            continue;
+        }
+        let error = engine.trim(&mass.cg_b) - engine.gimbal;
+        if error.norm() > 0.0 {
+            let swing = error * (step / error.norm()).min(1.0);
+            engine.gimbal += swing;
+        }
+    }
+}
+
+fn gimbal_command(
+    In(args): In<Vec<String>>,
+    mut ship: Query<(&mut MainEngine, &MassProperties), With<PlayerShip>>,
+) -> ConsoleReply {
+    let Ok((mut engine, mass)) = ship.single_mut() else {
+        return Err("no ship".to_string());
+    };
+    if engine.max_gimbal <= 0.0 {
+        return Err("The engine has no gimbal".to_string());
+    }
+    match args.as_slice() {
+        [] => {}
+        [trim] if trim == "trim" => engine.gimbal_held = false,
+        [pitch, yaw] => {
+            let limit = |angle: f64| angle.clamp(-engine.max_gimbal, engine.max_gimbal);
+            engine.gimbal = Vector2::new(
+                limit(parse_arg(pitch)?.to_radians()),
+                limit(parse_arg(yaw)?.to_radians()),
+            );
+            engine.gimbal_held = true;
+        }
+        _ => return Err("gimbal [<pitch> <yaw> | trim]".to_string()),
+    }
+    let trim = engine.trim(&mass.cg_b);
+    Ok(format!(
+        "pitch {:.2} yaw {:.2}{}, trim {:.2} {:.2}, of {:.1} either way",
+        engine.gimbal.x.to_degrees(),
+        engine.gimbal.y.to_degrees(),
+        if engine.gimbal_held { " (held)" } else { "" },
+        trim.x.to_degrees(),
+        trim.y.to_degrees(),
+        engine.max_gimbal.to_degrees()
+    ))
+}
+
+/// `throttle` shows the player ship's throttle, `throttle <percent>` sets
+/// it, and `throttle auto <accel>` holds the acceleration, in m/s^2, or in
+/// g's, as `2g`.
+fn throttle_command(
+    In(args): In<Vec<String>>,
+    mut ship: Query<(&mut MainEngine, &MassProperties), With<PlayerShip>>,
+) -> ConsoleReply {
+    let Ok((mut engine, mass)) = ship.single_mut() else {
+        return Err("no ship".to_string());
+    };
+    match args.as_slice() {
+        [] => {}
+        [auto, off] if auto == "auto" && off == "off" => engine.autothrottle = None,
+        [auto, accel] if auto == "auto" => {
+            let accel = match accel.strip_suffix('g') {
+                Some(gs) => parse_arg(gs)? * G0,
+                None => parse_arg(accel)?,
+            };
+            if accel <= 0.0 {
+                return Err("The acceleration to hold must be more than none".to_string());
+            }
+            engine.autothrottle = Some(accel);
+        }
+        [percent] => engine.throttle = (parse_arg(percent)? / 100.0).clamp(0.0, 1.0),
+        _ => return Err("throttle [percent | auto <m/s^2 | g's>g | auto off]".to_string()),
+    }
+    let held = match engine.autothrottle {
+        Some(accel) => format!(
+            ", held to {:.2} m/s^2 ({:.2} g), at {:.0}% now",
+            accel,
+            accel / G0,
+            engine.held_throttle(mass.mass) * 100.0
+        ),
+        None => String::new(),
+    };
+    Ok(format!("throttle {:.0}%{}", engine.throttle * 100.0, held))
+}
+
+/// Shift and Ctrl move the player ship's throttle.  Shift is also the key to
+/// go faster elsewhere, so only on its own does it throttle up.
+fn throttle_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    mut ship: Query<&mut MainEngine, With<PlayerShip>>,
+) {
+    let Ok(mut engine) = ship.single_mut() else {
+        return;
+    };
+    let shift = [KeyCode::ShiftLeft, KeyCode::ShiftRight];
+    let ctrl = [KeyCode::ControlLeft, KeyCode::ControlRight];
+    let held: Vec<KeyCode> = kb.get_pressed().copied().collect();
+    let only = |keys: &[KeyCode]| !held.is_empty() && held.iter().all(|k| keys.contains(k));
+    let step = THROTTLE_RATE * time.delta_secs_f64();
+    if only(&shift) {
+        engine.throttle = (engine.throttle + step).min(1.0);
+    } else if only(&ctrl) {
+        engine.throttle = (engine.throttle - step).max(0.0);
+    }
+}
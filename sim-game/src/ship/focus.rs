@@ -0,0 +1,187 @@
+//! Flying more than one craft.
+//!
+//! Any number of `Craft`s can be out at once, but only the one with the
+//! `PlayerShip` marker takes the keys, and is followed by the views.  V moves
+//! the marker to the next craft, by name (with shift, the one before), and the
+//! console's `focus <name>` to a particular one, by its name or id (see
+//! `registry`).  A craft docked to another is
+//! carried by it, so it can't be flown on its own until it undocks.
+//!
+//! The RCS mode goes with the craft: the one left keeps its mode, to pick up
+//! again when it is flown next, but its thrusters are stopped, and it drifts
+//! meanwhile.  Its engine keeps the throttle it was left at.
+//!
+//! `craft <name> <body> <orbit>` puts a new craft in an orbit, as for
+//! `teleport`.
+
+use bevy::prelude::*;
+use na::{UnitQuaternion, Vector3};
+use sim_core::AttitudeState;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, Primaries, orbit_state},
+    ship::{
+        Craft, PlayerShip, RcsMode, SasTarget, craft_bundle,
+        docking::Docked,
+        rcs::{RcsCommand, RcsRealism},
+        registry::{CraftId, VesselRegistry, named},
+    },
+};
+
+/// A request to fly a craft.
+#[derive(Clone, Debug, Message)]
+pub struct Focus(pub Entity);
+
+#[derive(Default)]
+pub struct FocusPlugin;
+
+impl Plugin for FocusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<Focus>();
+        app.add_systems(
+            Update,
+            (focus_keys, switch_focus)
+                .chain()
+                .before(crate::ship::rcs_keys),
+        );
+        app.add_console_command(
+            "focus",
+            "focus [name]   list the crafts, or fly one",
+            focus_command,
+        );
+        app.add_console_command(
+            "craft",
+            "craft <name> <body> <peri km> [apo km] [inc] [raan] [argp] [anomaly]   add a craft",
+            craft_command,
+        );
+    }
+}
+
+/// The crafts that can be flown, by name, with their ids, and whether each is
+/// the one being flown.
+type Crafts<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Name,
+        Option<&'static CraftId>,
+        Has<PlayerShip>,
+    ),
+    (With<Craft>, Without<Docked>),
+>;
+
+fn sorted_crafts(crafts: &Crafts) -> Vec<(Entity, String, Option<CraftId>, bool)> {
+    let mut list = crafts
+        .iter()
+        .map(|(entity, name, id, player)| (entity, name.to_string(), id.copied(), player))
+        .collect::<Vec<_>>();
+    list.sort_by(|a, b| a.1.cmp(&b.1));
+    list
+}
+
+fn focus_keys(kb: Res<ButtonInput<KeyCode>>, crafts: Crafts, mut focus: MessageWriter<Focus>) {
+    if !kb.just_pressed(KeyCode::KeyV) {
+        return;
+    }
+    let step = if kb.pressed(KeyCode::ShiftLeft) || kb.pressed(KeyCode::ShiftRight) {
+        -1
+    } else {
+        1
+    };
+    let list = sorted_crafts(&crafts);
+    let Some(current) = list.iter().position(|(.., player)| *player) else {
+        return;
+    };
+    let next = (current as isize + step).rem_euclid(list.len() as isize) as usize;
+    if next != current {
+        focus.write(Focus(list[next].0));
+    }
+}
+
+/// Move the `PlayerShip` marker, and the RCS mode with it.
+#[allow(clippy::type_complexity)]
+fn switch_focus(
+    mut commands: Commands,
+    mut requests: MessageReader<Focus>,
+    registry: Res<VesselRegistry>,
+    mut mode: ResMut<RcsMode>,
+    mut sas_target: ResMut<SasTarget>,
+    mut current: Query<(Entity, &mut RcsCommand), With<PlayerShip>>,
+    modes: Query<Option<&RcsMode>, (With<Craft>, Without<PlayerShip>)>,
+) {
+    let Some(Focus(next)) = requests.read().last().cloned() else {
+        return;
+    };
+    let Ok(next_mode) = modes.get(next) else {
+        return;
+    };
+    if let Ok((entity, mut command)) = current.single_mut() {
+        *command = RcsCommand::default();
+        commands.entity(entity).remove::<PlayerShip>().insert(*mode);
+        // Keep the craft just left in sight.
+        if sas_target.0 == Some(next) {
+            sas_target.0 = Some(entity);
+        }
+    }
+    *mode = next_mode.copied().unwrap_or_default();
+    commands.entity(next).insert(PlayerShip);
+    if let Some(label) = registry.label(next) {
+        info!("Flying {}", label);
+    }
+}
+
+/// `focus` lists the crafts, and `focus <name>` flies one, by its name or id.
+fn focus_command(
+    In(args): In<Vec<String>>,
+    crafts: Crafts,
+    mut focus: MessageWriter<Focus>,
+) -> ConsoleReply {
+    let list = sorted_crafts(&crafts);
+    match args.as_slice() {
+        [] => Ok(list
+            .iter()
+            .map(|(_, name, id, player)| {
+                let id = id.map_or(String::new(), |id| format!("{} ", id));
+                format!("{} {}{}", if *player { "*" } else { " " }, id, name)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        [name] => {
+            let (entity, ..) = list
+                .iter()
+                .find(|(_, n, id, _)| named(name, n, id.as_ref()))
+                .ok_or_else(|| format!("No such craft: {:?}, try focus", name))?;
+            focus.write(Focus(*entity));
+            Ok("ok".to_string())
+        }
+        _ => Err("focus [name]".to_string()),
+    }
+}
+
+/// `craft <name> <body> <orbit>` adds a craft, in an orbit as for `teleport`,
+/// with its axes lined up with the world's.
+fn craft_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    realism: Res<RcsRealism>,
+    primaries: Primaries,
+    names: Query<&Name>,
+) -> ConsoleReply {
+    let [name, orbit @ ..] = args.as_slice() else {
+        return Err("craft <name> <body> <orbit>".to_string());
+    };
+    if names.iter().any(|n| n.as_str().eq_ignore_ascii_case(name)) {
+        return Err(format!("There is already a {:?}", name));
+    }
+    if CraftId::parse(name).is_some() {
+        return Err(format!("{:?} would read as an id", name));
+    }
+    let orbital = orbit_state(&primaries, orbit)?;
+    let attitude = AttitudeState {
+        q_bw: UnitQuaternion::identity(),
+        omega_b: Vector3::zeros(),
+    };
+    commands.spawn(craft_bundle(name, orbital, attitude, &realism));
+    Ok("ok".to_string())
+}
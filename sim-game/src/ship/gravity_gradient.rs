@@ -0,0 +1,68 @@
+//! The gravity gradient torque.
+//!
+//! The near end of a long craft is pulled a little harder than the far end,
+//! which turns it to hang lengthwise toward the body it orbits.  Only the
+//! primary (the body pulling on it hardest) is counted.  For the ship in low
+//! orbit, this is a few hundredths of a N*m, which is nothing to the RCS, but
+//! is always there, for the stability assist to trim out.
+
+use bevy::prelude::*;
+use na::Vector3;
+use sim_core::{
+    AttitudeControl, AttitudeState, MassiveBody, OrbitalBody, PhysicsModels, PhysicsSet,
+    model_enabled,
+};
+
+use crate::ship::{MassProperties, engine::engine_fire};
+
+#[derive(Default)]
+pub struct GravityGradientPlugin;
+
+impl Plugin for GravityGradientPlugin {
+    fn build(&self, app: &mut App) {
+        PhysicsModels::add(app, "gravity-gradient");
+        app.add_systems(
+            FixedUpdate,
+            gravity_gradient
+                .after(engine_fire)
+                .before(PhysicsSet)
+                .run_if(model_enabled("gravity-gradient")),
+        );
+    }
+}
+
+/// The gravity gradient torque, in N*m, BODY frame, on a craft with the
+/// principal moments `inertia_b`, in kg*m^2, at `r_b`, km, BODY frame, from a
+/// body with the given GM, in km^3/s^2.
+pub fn torque_b(gm: f64, r_b: &Vector3<f64>, inertia_b: &Vector3<f64>) -> Vector3<f64> {
+    let r = r_b.norm();
+    let r_hat = r_b / r;
+    r_hat.cross(&inertia_b.component_mul(&r_hat)) * (3.0 * gm / (r * r * r))
+}
+
+/// Add the torque to the craft's angular acceleration.  This must run after
+/// the thrusters have set theirs.
+fn gravity_gradient(
+    mut crafts: Query<(
+        &OrbitalBody,
+        &AttitudeState,
+        &MassProperties,
+        &mut AttitudeControl,
+    )>,
+    bodies: Query<(&OrbitalBody, &MassiveBody)>,
+) {
+    for (orbital, attitude, mass, mut control) in crafts.iter_mut() {
+        let primary = bodies.iter().max_by(|(a, a_mass), (b, b_mass)| {
+            let pull = |body: &OrbitalBody, gm: f64| gm / (orbital.pos - body.pos).norm_squared();
+            pull(a, a_mass.gm).total_cmp(&pull(b, b_mass.gm))
+        });
+        let Some((body, body_mass)) = primary else {
+            continue;
+        };
+        let r_b = attitude
+            .q_bw
+            .inverse_transform_vector(&(orbital.pos - body.pos));
+        control.alpha_b +=
+            torque_b(body_mass.gm, &r_b, &mass.inertia_b).component_div(&mass.inertia_b);
+    }
+}
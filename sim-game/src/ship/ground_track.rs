@@ -0,0 +1,131 @@
+//! Ground track.
+//!
+//! The craft's position is converted into latitude and longitude over the
+//! rotating earth.  Where the craft has been is sampled as the sim runs, and
+//! where it will be is predicted over the next few orbits, along the current
+//! conic, with the earth turning underneath.
+
+use bevy::prelude::*;
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+use sim_astro::{
+    EarthMarker,
+    geodesy::{Geodetic, world_to_body},
+};
+use sim_core::{
+    AttitudeState, MassiveBody, OrbitalBody, PostPhysicsSet, SizedBody,
+    orbit::{period, propagate},
+};
+use std::collections::VecDeque;
+
+use crate::ship::PlayerShip;
+
+/// How often, in sim seconds, to sample the track history.
+const GROUND_TRACK_INTERVAL: f64 = 10.0;
+
+/// How many history samples to keep.
+const GROUND_TRACK_HISTORY: usize = 1000;
+
+/// How many orbits ahead to predict.
+const GROUND_TRACK_ORBITS: usize = 3;
+
+/// Points sampled per predicted orbit.
+const GROUND_TRACK_SAMPLES: usize = 128;
+
+/// For orbits that don't close, how far ahead, in seconds, counts as an
+/// orbit.
+const GROUND_TRACK_OPEN_SPAN: f64 = 2.0 * 3600.0;
+
+/// The ground track of a craft, as (latitude, longitude) pairs, in degrees.
+#[derive(Clone, Component, Debug, Default, Serialize, Deserialize)]
+pub struct GroundTrack {
+    /// Where the craft has been, oldest first.
+    pub history: VecDeque<(f64, f64)>,
+    /// Where the craft will be over the next few orbits.
+    pub predicted: Vec<(f64, f64)>,
+    /// The sim time of the last history sample.
+    last_sample: Option<f64>,
+}
+
+/// The geodetic (latitude, longitude), in degrees, of a position in a
+/// body-fixed frame.
+pub fn lat_lon(pos_f: &Vector3<f64>, radii: &Vector3<f64>) -> (f64, f64) {
+    let geodetic = Geodetic::from_body(pos_f, radii);
+    (geodetic.lat.to_degrees(), geodetic.lon.to_degrees())
+}
+
+#[derive(Default)]
+pub struct GroundTrackPlugin;
+
+impl Plugin for GroundTrackPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, record_ground_track.after(PostPhysicsSet));
+        app.add_systems(Update, predict_ground_track);
+    }
+}
+
+/// Sample where the ship is now, every so often.
+#[allow(clippy::type_complexity)]
+fn record_ground_track(
+    time: Res<Time>,
+    mut ship: Query<(&OrbitalBody, &mut GroundTrack), With<PlayerShip>>,
+    earth: Query<
+        (&OrbitalBody, &SizedBody, &AttitudeState),
+        (With<EarthMarker>, Without<PlayerShip>),
+    >,
+) {
+    let Ok((orbital, mut track)) = ship.single_mut() else {
+        return;
+    };
+    let Ok((earth, earth_size, earth_attitude)) = earth.single() else {
+        return;
+    };
+
+    let now = time.elapsed_secs_f64();
+    if let Some(last) = track.last_sample
+        && now - last < GROUND_TRACK_INTERVAL
+    {
+        return;
+    }
+    track.last_sample = Some(now);
+
+    let pos_f = world_to_body(earth, earth_attitude, &orbital.pos);
+    track.history.push_back(lat_lon(&pos_f, &earth_size.radii));
+    while track.history.len() > GROUND_TRACK_HISTORY {
+        track.history.pop_front();
+    }
+}
+
+/// Predict the track over the next few orbits.
+#[allow(clippy::type_complexity)]
+fn predict_ground_track(
+    mut ship: Query<(&OrbitalBody, &mut GroundTrack), With<PlayerShip>>,
+    earth: Query<
+        (&OrbitalBody, &MassiveBody, &SizedBody, &AttitudeState),
+        (With<EarthMarker>, Without<PlayerShip>),
+    >,
+) {
+    let Ok((orbital, mut track)) = ship.single_mut() else {
+        return;
+    };
+    let Ok((earth, earth_mass, earth_size, earth_attitude)) = earth.single() else {
+        return;
+    };
+
+    let gm = earth_mass.gm;
+    let pos = orbital.pos - earth.pos;
+    let vel = orbital.vel - earth.vel;
+    let orbit = period(&pos, &vel, gm).unwrap_or(GROUND_TRACK_OPEN_SPAN);
+    let samples = GROUND_TRACK_ORBITS * GROUND_TRACK_SAMPLES;
+
+    track.predicted.clear();
+    for i in 0..=samples {
+        let dt = orbit * i as f64 / GROUND_TRACK_SAMPLES as f64;
+        let (p, _) = propagate(&pos, &vel, gm, dt);
+        if p.norm() < earth_size.radii.z {
+            break;
+        }
+        let pos_f = earth_attitude.q_bw_after(dt).inverse_transform_vector(&p);
+        track.predicted.push(lat_lon(&pos_f, &earth_size.radii));
+    }
+}
@@ -0,0 +1,423 @@
+//! Guided insertion into orbit, on a long burn.
+//!
+//! An impulse is the best way to spend Δv, but a burn of minutes, as an upper
+//! stage's is, can't be one.  The least propellant then goes with the thrust
+//! turning so that the tangent of its pitch changes linearly in time, and this
+//! flies an approximation of that, as the Shuttle's powered explicit guidance
+//! (PEG) did: the sine of the pitch above the horizon is `A + B t`, plus what
+//! it takes to hold up against gravity, less what the speed across carries,
+//! at the present and the end.  `A` and `B` are solved for each step, so that
+//! the burn ends at the target radius, with no climb left, from the time to
+//! go, which is found from the angular momentum still to be gained, and the
+//! rocket equation, as the engine gets lighter.  A few passes each step keep
+//! it converged, and over the last seconds it is left alone, as it comes apart
+//! as the time to go runs out.  The engine is cut once the angular momentum is
+//! there, with the craft at the periapsis of the orbit wanted.
+//!
+//! The heading steers the horizontal velocity into the plane, as the ascent's
+//! does, so that the plane comes right with the rest.
+//!
+//! The ascent flies this for the upper part of a guided ascent (see
+//! `ascent`).  The console's `insert <periapsis km> [apoapsis km]` flies it
+//! from wherever the craft is, in the plane it is in, as an upper stage would
+//! from a suborbital staging, and `insert off` stops it.  Only the craft being
+//! flown is guided, about the earth, with the engine at full throttle.
+
+use bevy::prelude::*;
+use na::Vector3;
+use sim_astro::EarthMarker;
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, PhysicsSet, SizedBody};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{
+        GuidanceSet, HoldAttitude, MassProperties, PlayerShip, RcsMode,
+        engine::{MainEngine, engine_fire},
+        maneuver::EXECUTE_POINTING,
+        point_axis_at,
+        propulsion::G0,
+        rcs_command,
+    },
+};
+
+/// The passes over the steering each step.
+const GUIDANCE_PASSES: usize = 3;
+
+/// Over the last this many seconds, the steering is held as it was.
+const GUIDANCE_HOLD: f64 = 2.0;
+
+/// Once burning, the craft may fall this far behind the steering, in
+/// degrees, as it moves, before the engine is cut.
+const INSERTION_DRIFT: f64 = 10.0;
+
+/// Steering toward a radius, and a speed across it, with no climb, by the
+/// linear tangent law.  Radii are in km from the body's center, and speeds in
+/// km/s.
+#[derive(Clone, Debug)]
+pub struct LinearTangent {
+    pub radius: f64,
+    pub speed: f64,
+    /// The time to go, in seconds, as last found.
+    pub t_go: Option<f64>,
+    /// The sine of the pitch, less gravity's part, is `a + b t`.
+    a: f64,
+    b: f64,
+}
+
+/// Where to point, and for how long yet.
+#[derive(Clone, Copy, Debug)]
+pub struct Steering {
+    /// The sine of the pitch above the horizon.
+    pub sin_pitch: f64,
+    pub t_go: f64,
+    /// Whether the target has been reached, and the engine should be cut.
+    pub done: bool,
+}
+
+/// The thrust, as the guidance takes it: the acceleration now, in km/s^2,
+/// and the exhaust speed, in km/s, if the engine uses propellant at all.
+struct Thrust {
+    accel: f64,
+    exhaust: Option<f64>,
+}
+
+impl Thrust {
+    fn new(engine: &MainEngine, mass: f64) -> Self {
+        Thrust {
+            accel: engine.full_burn(mass, 0.0).1 / 1000.0,
+            exhaust: engine.isp.map(|isp| isp * G0 / 1000.0),
+        }
+    }
+
+    /// The time to make `dv`.
+    fn time_for(&self, dv: f64) -> f64 {
+        match self.exhaust {
+            Some(ve) => ve / self.accel * (1.0 - (-dv / ve).exp()),
+            None => dv / self.accel,
+        }
+    }
+
+    /// The acceleration `t` seconds from now.
+    fn accel_at(&self, t: f64) -> f64 {
+        match self.exhaust {
+            Some(ve) => self.accel / (1.0 - t * self.accel / ve),
+            None => self.accel,
+        }
+    }
+
+    /// The Δv over the next `t` seconds, and its moment in time, and the
+    /// distance it makes, and its moment.
+    fn integrals(&self, t: f64) -> (f64, f64, f64, f64) {
+        let a = self.accel;
+        match self.exhaust {
+            Some(ve) => {
+                let tau = ve / a;
+                let b0 = -ve * (1.0 - t / tau).ln();
+                let b1 = b0 * tau - ve * t;
+                let c0 = b0 * t - b1;
+                let c1 = c0 * tau - ve * t * t / 2.0;
+                (b0, b1, c0, c1)
+            }
+            None => (a * t, a * t * t / 2.0, a * t * t / 2.0, a * t * t * t / 6.0),
+        }
+    }
+}
+
+impl LinearTangent {
+    pub fn new(radius: f64, speed: f64) -> Self {
+        LinearTangent {
+            radius,
+            speed,
+            t_go: None,
+            a: 0.0,
+            b: 0.0,
+        }
+    }
+
+    /// Into the orbit with the given periapsis and apoapsis radii, about a
+    /// body with the given `gm`, at its periapsis.
+    pub fn to_orbit(gm: f64, periapsis: f64, apoapsis: f64) -> Self {
+        let speed = (2.0 * gm * apoapsis / (periapsis * (periapsis + apoapsis))).sqrt();
+        Self::new(periapsis, speed)
+    }
+
+    /// Steer a craft at `pos` and `vel`, about a body with the given `gm`,
+    /// with `engine` on it at full throttle, and weighing `mass`, kg, `dt`
+    /// seconds on from the last step.
+    pub fn steer(
+        &mut self,
+        pos: &Vector3<f64>,
+        vel: &Vector3<f64>,
+        gm: f64,
+        engine: &MainEngine,
+        mass: f64,
+        dt: f64,
+    ) -> Result<Steering, String> {
+        let thrust = Thrust::new(engine, mass);
+        if thrust.accel <= 0.0 {
+            return Err("There is no thrust to steer".to_string());
+        }
+        let r = pos.norm();
+        let up = pos / r;
+        let v_up = vel.dot(&up);
+        let across = (vel - up * v_up).norm();
+        let h_to_go = self.radius * self.speed - r * across;
+        // Holding up against gravity, less what the speed across carries, as
+        // a part of the thrust.
+        let lift = |r: f64, across: f64, accel: f64| (gm / (r * r) - across * across / r) / accel;
+
+        let held = self.t_go.filter(|&t_go| t_go < GUIDANCE_HOLD);
+        let t_go = match held {
+            Some(t_go) => {
+                self.a += self.b * dt;
+                t_go - dt
+            }
+            None => {
+                let mut t_go = self.t_go.map_or_else(
+                    || thrust.time_for(h_to_go / ((r + self.radius) / 2.0)),
+                    |t_go| t_go - dt,
+                );
+                for _ in 0..GUIDANCE_PASSES {
+                    t_go = self.solve(&thrust, r, v_up, across, h_to_go, t_go, &lift);
+                    if !(t_go.is_finite() && t_go > 0.0) {
+                        self.t_go = None;
+                        return Err("The guidance can't find a way there".to_string());
+                    }
+                }
+                t_go
+            }
+        };
+        self.t_go = Some(t_go);
+        Ok(Steering {
+            sin_pitch: (self.a + lift(r, across, thrust.accel)).clamp(-1.0, 1.0),
+            t_go,
+            done: h_to_go <= 0.0 || t_go <= 0.0,
+        })
+    }
+
+    /// One pass: `A` and `B` for the time to go, and from them, the time to
+    /// go again.
+    #[allow(clippy::too_many_arguments)]
+    fn solve(
+        &mut self,
+        thrust: &Thrust,
+        r: f64,
+        v_up: f64,
+        across: f64,
+        h_to_go: f64,
+        t_go: f64,
+        lift: &impl Fn(f64, f64, f64) -> f64,
+    ) -> f64 {
+        let (b0, b1, c0, c1) = thrust.integrals(t_go);
+        let climb = -v_up;
+        let rise = self.radius - r - v_up * t_go;
+        let det = b0 * c1 - b1 * c0;
+        self.a = (climb * c1 - b1 * rise) / det;
+        self.b = (b0 * rise - c0 * climb) / det;
+
+        // The pitch's sine now, and at the end, and its rate between them.
+        let now = self.a + lift(r, across, thrust.accel);
+        let end = self.a + self.b * t_go + lift(self.radius, self.speed, thrust.accel_at(t_go));
+        let rate = (end - now) / t_go;
+        // How much of the thrust goes across, on average, as the cosine of
+        // the pitch, taken to second order.
+        let cos = 1.0 - now * now / 2.0;
+        let cos_rate = -now * rate;
+        let cos_accel = -rate * rate / 2.0;
+        let mean = cos + cos_rate * t_go / 2.0 + cos_accel * t_go * t_go / 3.0;
+        let dv = h_to_go / ((r + self.radius) / 2.0) / mean;
+        thrust.time_for(dv)
+    }
+}
+
+/// The direction to thrust, steering at `sin_pitch` above the horizon, and
+/// across, to bring the horizontal velocity to `speed`, km/s, in the plane
+/// with the given `normal`.
+pub fn steering_direction(
+    pos: &Vector3<f64>,
+    vel: &Vector3<f64>,
+    normal: &Vector3<f64>,
+    speed: f64,
+    sin_pitch: f64,
+) -> Vector3<f64> {
+    let up = pos.normalize();
+    let horizontal = vel - up * vel.dot(&up);
+    let to_go = normal.cross(&up) * speed - horizontal;
+    let heading = (to_go - up * to_go.dot(&up))
+        .try_normalize(1.0e-12)
+        .unwrap_or_else(|| normal.cross(&up).normalize());
+    up * sin_pitch + heading * (1.0 - sin_pitch * sin_pitch).sqrt()
+}
+
+/// Full throttle, once the craft is pointed along `dir_w`, as near as a
+/// maneuver node's burn would be, and then for as long as it keeps up.
+fn throttle_for(attitude: &AttitudeState, dir_w: &Vector3<f64>, burning: bool) -> f64 {
+    let z_w = attitude.q_bw.transform_vector(&Vector3::z());
+    let limit = if burning {
+        INSERTION_DRIFT
+    } else {
+        EXECUTE_POINTING
+    };
+    if z_w.angle(dir_w) > limit.to_radians() {
+        0.0
+    } else {
+        1.0
+    }
+}
+
+/// Guidance flying a craft into orbit about the earth.  Altitudes are in km
+/// above the earth's equatorial radius.
+#[derive(Clone, Component, Debug)]
+pub struct Insertion {
+    pub periapsis: f64,
+    pub apoapsis: f64,
+    pub guidance: LinearTangent,
+    /// The normal of the plane being flown in, found at the start.
+    pub normal: Vector3<f64>,
+}
+
+#[derive(Default)]
+pub struct InsertionPlugin;
+
+impl Plugin for InsertionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            insertion_guidance
+                .in_set(GuidanceSet)
+                .before(rcs_command)
+                .before(engine_fire)
+                .before(PhysicsSet),
+        );
+        app.add_console_command(
+            "insert",
+            "insert <periapsis km> [apoapsis km]   burn into orbit, steering, or insert off",
+            insert_command,
+        );
+    }
+}
+
+/// Steer, at full throttle, until in orbit.
+#[allow(clippy::type_complexity)]
+fn insertion_guidance(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut mode: ResMut<RcsMode>,
+    mut ship: Query<
+        (
+            Entity,
+            &mut Insertion,
+            &OrbitalBody,
+            &AttitudeState,
+            &mut HoldAttitude,
+            &MassProperties,
+            &mut MainEngine,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<(&OrbitalBody, &MassiveBody), (With<EarthMarker>, Without<PlayerShip>)>,
+) {
+    let Ok((entity, mut insertion, orbital, attitude, mut hold, mass, mut engine)) =
+        ship.single_mut()
+    else {
+        return;
+    };
+    let Ok((earth, earth_mass)) = earth.single() else {
+        return;
+    };
+    let pos = orbital.pos - earth.pos;
+    let vel = orbital.vel - earth.vel;
+    let normal = insertion.normal;
+    let steering = insertion.guidance.steer(
+        &pos,
+        &vel,
+        earth_mass.gm,
+        &engine,
+        mass.mass,
+        // Only the time spent burning counts down.
+        time.delta_secs_f64() * engine.throttle,
+    );
+    match steering {
+        Ok(steering) if !steering.done => {
+            *mode = RcsMode::Ascent;
+            let dir_w = steering_direction(
+                &pos,
+                &vel,
+                &normal,
+                insertion.guidance.speed,
+                steering.sin_pitch,
+            );
+            engine.throttle = throttle_for(attitude, &dir_w, engine.throttle > 0.0);
+            hold.0 = Some(point_axis_at(&attitude.q_bw, &Vector3::z(), &dir_w));
+        }
+        done => {
+            if let Err(e) = done {
+                warn!("Insertion: {}", e);
+            }
+            engine.throttle = 0.0;
+            *mode = RcsMode::Prograde;
+            commands.entity(entity).remove::<Insertion>();
+        }
+    }
+}
+
+/// `insert <periapsis km> [apoapsis km]` starts guidance into an orbit, a
+/// circular one unless told, and `insert off` stops it.
+#[allow(clippy::type_complexity)]
+fn insert_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    mut mode: ResMut<RcsMode>,
+    mut ship: Query<
+        (
+            Entity,
+            &OrbitalBody,
+            &MassProperties,
+            &mut MainEngine,
+            Has<Insertion>,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<
+        (&OrbitalBody, &MassiveBody, &SizedBody),
+        (With<EarthMarker>, Without<PlayerShip>),
+    >,
+) -> ConsoleReply {
+    let (entity, orbital, mass, mut engine, flying) =
+        ship.single_mut().map_err(|_| "no ship".to_string())?;
+    if args == ["off"] {
+        if flying {
+            engine.throttle = 0.0;
+            *mode = RcsMode::Hold;
+            commands.entity(entity).remove::<Insertion>();
+        }
+        return Ok("ok".to_string());
+    }
+    let (periapsis, apoapsis) = match args.as_slice() {
+        [periapsis] => (parse_arg(periapsis)?, parse_arg(periapsis)?),
+        [periapsis, apoapsis] => (parse_arg(periapsis)?, parse_arg(apoapsis)?),
+        _ => return Err("insert <periapsis km> [apoapsis km]".to_string()),
+    };
+    if apoapsis < periapsis {
+        return Err("The apoapsis can't be below the periapsis".to_string());
+    }
+
+    let (earth, earth_mass, earth_size) = earth.single().map_err(|_| "no earth".to_string())?;
+    let radius = earth_size.radii.x;
+    let pos = orbital.pos - earth.pos;
+    let vel = orbital.vel - earth.vel;
+    let normal = pos
+        .cross(&vel)
+        .try_normalize(1.0e-12)
+        .ok_or_else(|| "Going straight up, there is no plane to fly in".to_string())?;
+    let mut guidance =
+        LinearTangent::to_orbit(earth_mass.gm, radius + periapsis, radius + apoapsis);
+    let steering = guidance.steer(&pos, &vel, earth_mass.gm, &engine, mass.mass, 0.0)?;
+    commands.entity(entity).insert(Insertion {
+        periapsis,
+        apoapsis,
+        guidance,
+        normal,
+    });
+    Ok(format!("Burning about {:.0} s", steering.t_go))
+}
@@ -0,0 +1,351 @@
+//! Orbital lifetime.
+//!
+//! How long until the player ship's orbit decays and it reenters, from the
+//! drag (the same atmosphere and `Aero` as the sim flies), and from solar
+//! radiation pressure, which can pump up the eccentricity of a light craft
+//! with a lot of area until its perigee dips into the air.  That is modeled
+//! here as on a cannonball, with the earth's shadow as a cylinder, rather than
+//! as the sim pushes (see `ship::sunlight`), which depends on the way the craft
+//! is facing.
+//!
+//! Years of decay can't be flown step by step, so the orbit is propagated on
+//! its averages instead: each step, the forces are sampled all the way around
+//! the current (osculating) orbit, and their average effect on its angular
+//! momentum and eccentricity vectors is taken as the rate those change at.
+//! The steps are then as long as the decay allows, often days.  Neither vector
+//! has trouble with circular or equatorial orbits, as the classical elements
+//! do.  The craft is taken to be tumbling, so it shows its average area.  The
+//! moon's and sun's gravity are left out, so this will be off for orbits they
+//! work on, such as a GTO.
+//!
+//! The estimate is kept up to date in the map (M), and the console's
+//! `lifetime` command gives it.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use na::{UnitQuaternion, Vector3};
+use sim_astro::{EarthMarker, atmosphere::Atmosphere, eclipse::pressure, geodesy::Geodetic};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, SizedBody};
+use std::{f64::consts::TAU, fmt};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    ship::{MassProperties, PlayerShip, aero::Aero, sunlight::REFLECTIVITY},
+};
+
+/// How far ahead to look, in seconds: 100 years.
+const LIFETIME_HORIZON: f64 = 100.0 * 365.25 * 86400.0;
+
+/// The perigee altitude, km, at which the craft counts as reentered.  Below
+/// this, the orbit is gone within a few revolutions.
+const LIFETIME_REENTRY: f64 = 120.0;
+
+/// The points sampled around the orbit, for the averages.
+const LIFETIME_SAMPLES: usize = 72;
+
+/// Each step can take this fraction off the perigee's height above
+/// `LIFETIME_REENTRY`.
+const LIFETIME_STEP_FRACTION: f64 = 0.02;
+
+/// The longest step, in seconds.  The sun moves about 5 degrees in this time,
+/// which is as far as the radiation pressure should be let go without a look.
+const LIFETIME_MAX_STEP: f64 = 5.0 * 86400.0;
+
+/// How often, in real seconds, the estimate is brought up to date.
+const LIFETIME_INTERVAL: f64 = 5.0;
+
+/// How fast the sun goes around the ecliptic, as seen from the earth, rad/s.
+const SUN_RATE: f64 = TAU / (365.256_363 * 86400.0);
+
+/// How long an orbit lasts.
+#[derive(Clone, Copy, Debug)]
+pub enum Lifetime {
+    /// It reenters after this many seconds.
+    Reentry(f64),
+    /// It is still up at the end of the horizon, in seconds.
+    Beyond(f64),
+    /// It isn't in a closed orbit, so there's nothing to decay.
+    Unbound,
+}
+
+/// A length of time, in the largest units that make sense.
+fn span(seconds: f64) -> String {
+    let days = seconds / 86400.0;
+    if days >= 365.25 {
+        format!("{:.1} years", days / 365.25)
+    } else if days >= 1.0 {
+        format!("{:.1} days", days)
+    } else {
+        format!("{:.1} hours", seconds / 3600.0)
+    }
+}
+
+impl fmt::Display for Lifetime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Lifetime::Reentry(t) => write!(f, "reentry in {}", span(*t)),
+            Lifetime::Beyond(t) => write!(f, "more than {}", span(*t)),
+            Lifetime::Unbound => write!(f, "not in orbit"),
+        }
+    }
+}
+
+/// The lifetime of the player ship's orbit, as of the last estimate.
+#[derive(Clone, Component, Debug)]
+pub struct OrbitLifetime(pub Lifetime);
+
+/// What the orbit is propagated in.  Everything is relative to the earth, in
+/// km and km/s, except as noted.
+struct Model<'a> {
+    gm: f64,
+    radii: Vector3<f64>,
+    attitude: &'a AttitudeState,
+    atmosphere: &'a Atmosphere,
+    /// The craft's drag coefficient and radiation pressure coefficient, each
+    /// times its area over its mass, m^2/kg.
+    drag: f64,
+    srp: f64,
+    /// Where the sun is at the start.
+    sun: Vector3<f64>,
+}
+
+impl Model<'_> {
+    /// The acceleration, km/s^2, on the craft at `pos` and `vel`, `t` seconds
+    /// from the start.
+    fn accel(&self, t: f64, pos: &Vector3<f64>, vel: &Vector3<f64>) -> Vector3<f64> {
+        // The earth is at the origin here.
+        let earth = OrbitalBody {
+            pos: Vector3::zeros(),
+            vel: Vector3::zeros(),
+        };
+        let mut accel = Vector3::zeros();
+
+        if pos.norm() - self.radii.max() < self.atmosphere.top {
+            let alt = Geodetic::from_world(&earth, self.attitude, &self.radii, pos).alt;
+            let density = self.atmosphere.density(alt);
+            let wind = Atmosphere::wind_relative(&earth, self.attitude, pos, vel);
+            // km/s to m/s, and the result back to km/s^2.
+            let speed = wind.norm() * 1000.0;
+            if speed > 0.0 {
+                accel -= wind / wind.norm() * 0.5 * density * speed * speed * self.drag / 1000.0;
+            }
+        }
+
+        let sun = UnitQuaternion::from_axis_angle(&Vector3::z_axis(), SUN_RATE * t) * self.sun;
+        let sun_dir = sun.normalize();
+        let along = pos.dot(&sun_dir);
+        let shadowed = along < 0.0 && (pos - sun_dir * along).norm() < self.radii.x;
+        if !shadowed {
+            let to_sun = sun - pos;
+            accel -= to_sun.normalize() * pressure(to_sun.norm(), 1.0) * self.srp / 1000.0;
+        }
+        accel
+    }
+
+    /// The average rates of change of the angular momentum and eccentricity
+    /// vectors over the orbit they describe, at `t` seconds from the start.
+    fn rates(&self, t: f64, h: &Vector3<f64>, e: &Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+        let p = h.norm_squared() / self.gm;
+        let ecc = e.norm();
+        // Periapsis, or anywhere in the plane for a circle.
+        let p_hat = if ecc > 1.0e-9 {
+            e / ecc
+        } else {
+            let other = if h.x.abs() < h.z.abs() {
+                Vector3::x()
+            } else {
+                Vector3::z()
+            };
+            h.cross(&other).normalize()
+        };
+        let q_hat = h.normalize().cross(&p_hat);
+        let speed = (self.gm / p).sqrt();
+
+        // Sampled evenly in true anomaly, each sample is weighted by the time
+        // spent there.
+        let mut h_dot = Vector3::zeros();
+        let mut e_dot = Vector3::zeros();
+        let mut weights = 0.0;
+        for i in 0..LIFETIME_SAMPLES {
+            let nu = TAU * (i as f64 + 0.5) / LIFETIME_SAMPLES as f64;
+            let (sin, cos) = nu.sin_cos();
+            let denom = 1.0 + ecc * cos;
+            let pos = (p_hat * cos + q_hat * sin) * (p / denom);
+            let vel = (-p_hat * sin + q_hat * (ecc + cos)) * speed;
+            let accel = self.accel(t, &pos, &vel);
+            let torque = pos.cross(&accel);
+            let weight = 1.0 / (denom * denom);
+            h_dot += torque * weight;
+            e_dot += (accel.cross(h) + vel.cross(&torque)) / self.gm * weight;
+            weights += weight;
+        }
+        (h_dot / weights, e_dot / weights)
+    }
+
+    /// The perigee altitude, km, over the equator.
+    fn perigee(&self, h: &Vector3<f64>, e: &Vector3<f64>) -> f64 {
+        h.norm_squared() / self.gm / (1.0 + e.norm()) - self.radii.x
+    }
+
+    /// How long the orbit through `pos` and `vel` lasts.
+    fn lifetime(&self, pos: &Vector3<f64>, vel: &Vector3<f64>) -> Lifetime {
+        let mut h = pos.cross(vel);
+        let mut e = vel.cross(&h) / self.gm - pos.normalize();
+        let mut t = 0.0;
+        let mut perigee = self.perigee(&h, &e);
+        if perigee < LIFETIME_REENTRY {
+            return Lifetime::Reentry(0.0);
+        }
+
+        while t < LIFETIME_HORIZON {
+            let ecc = e.norm();
+            if ecc >= 1.0 {
+                return Lifetime::Unbound;
+            }
+            let (h_dot, e_dot) = self.rates(t, &h, &e);
+
+            // How fast the perigee is coming down.
+            let p = h.norm_squared() / self.gm;
+            let ecc_dot = if ecc > 1.0e-9 {
+                e.dot(&e_dot) / ecc
+            } else {
+                e_dot.norm()
+            };
+            let perigee_dot =
+                2.0 * h.dot(&h_dot) / self.gm / (1.0 + ecc) - p * ecc_dot / (1.0 + ecc).powi(2);
+            let a = p / (1.0 - ecc * ecc);
+            let period = TAU * (a * a * a / self.gm).sqrt();
+            let height = perigee - LIFETIME_REENTRY;
+            if perigee_dot < 0.0 && height / -perigee_dot < period {
+                // It is down within the revolution.
+                return Lifetime::Reentry(t + height / -perigee_dot);
+            }
+            let dt = if perigee_dot < 0.0 {
+                LIFETIME_STEP_FRACTION * height / -perigee_dot
+            } else {
+                LIFETIME_MAX_STEP
+            }
+            .clamp(period, LIFETIME_MAX_STEP.max(period))
+            .min(LIFETIME_HORIZON - t);
+
+            // The midpoint method.
+            let (h_mid, e_mid) = (h + h_dot * dt / 2.0, e + e_dot * dt / 2.0);
+            let (h_dot, e_dot) = self.rates(t + dt / 2.0, &h_mid, &e_mid);
+            h += h_dot * dt;
+            e += e_dot * dt;
+            t += dt;
+
+            let next = self.perigee(&h, &e);
+            if next < LIFETIME_REENTRY {
+                // Back up to where it crossed.
+                return Lifetime::Reentry(t - dt * (LIFETIME_REENTRY - next) / (perigee - next));
+            }
+            perigee = next;
+        }
+        Lifetime::Beyond(LIFETIME_HORIZON)
+    }
+}
+
+#[derive(Default)]
+pub struct LifetimePlugin;
+
+impl Plugin for LifetimePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, estimate_lifetime);
+        app.add_console_command(
+            "lifetime",
+            "lifetime   estimate the ship's orbital lifetime",
+            lifetime_command,
+        );
+    }
+}
+
+/// What the estimate is made from, as a system parameter.
+#[derive(SystemParam)]
+#[allow(clippy::type_complexity)]
+struct LifetimeInputs<'w, 's> {
+    ship: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static OrbitalBody,
+            &'static MassProperties,
+            &'static Aero,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<
+        'w,
+        's,
+        (
+            &'static OrbitalBody,
+            &'static MassiveBody,
+            &'static SizedBody,
+            &'static AttitudeState,
+            &'static Atmosphere,
+        ),
+        With<EarthMarker>,
+    >,
+    bodies: Query<'w, 's, (&'static MassiveBody, &'static OrbitalBody)>,
+}
+
+impl LifetimeInputs<'_, '_> {
+    /// The player ship, and its orbital lifetime as it is now.
+    fn estimate(&self) -> Result<(Entity, Lifetime), String> {
+        let (
+            Ok((entity, orbital, mass, aero)),
+            Ok((earth, earth_mass, size, attitude, atmosphere)),
+        ) = (self.ship.single(), self.earth.single())
+        else {
+            return Err("no ship".to_string());
+        };
+        // The sun is the most massive body.
+        let (_, sun) = self
+            .bodies
+            .iter()
+            .max_by(|a, b| a.0.gm.total_cmp(&b.0.gm))
+            .ok_or_else(|| "no sun".to_string())?;
+
+        // A tumbling box shows half the sum of its faces' areas, on average.
+        let area = aero.area_b.sum() / 2.0;
+        let model = Model {
+            gm: earth_mass.gm,
+            radii: size.radii,
+            attitude,
+            atmosphere,
+            drag: aero.drag_coefficient * area / mass.mass,
+            srp: REFLECTIVITY * area / mass.mass,
+            sun: sun.pos - earth.pos,
+        };
+        let lifetime = model.lifetime(&(orbital.pos - earth.pos), &(orbital.vel - earth.vel));
+        Ok((entity, lifetime))
+    }
+}
+
+/// `lifetime` estimates it afresh, and keeps that.
+fn lifetime_command(
+    _: In<Vec<String>>,
+    mut commands: Commands,
+    inputs: LifetimeInputs,
+) -> ConsoleReply {
+    let (entity, lifetime) = inputs.estimate()?;
+    commands.entity(entity).insert(OrbitLifetime(lifetime));
+    Ok(format!("lifetime: {}", lifetime))
+}
+
+/// Keep the estimate up to date.
+fn estimate_lifetime(
+    mut commands: Commands,
+    real: Res<Time<Real>>,
+    mut next: Local<f64>,
+    inputs: LifetimeInputs,
+) {
+    let now = real.elapsed_secs_f64();
+    if now < *next {
+        return;
+    }
+    *next = now + LIFETIME_INTERVAL;
+    if let Ok((entity, lifetime)) = inputs.estimate() {
+        commands.entity(entity).insert(OrbitLifetime(lifetime));
+    }
+}
@@ -0,0 +1,831 @@
+//! Lunar transfers.
+//!
+//! Getting from a low earth orbit into orbit about the Moon takes three burns:
+//! trans-lunar injection (TLI) onto the transfer, a mid-course correction (MCC)
+//! halfway there, and lunar orbit insertion (LOI) at the closest approach.
+//!
+//! The transfer is Lambert's problem about the earth, solved as the targeting
+//! module does, to a point beside where the Moon will be on arrival, on its
+//! two-body orbit from where the ephemeris has it now.  The point is off to the
+//! side by the miss distance that the Moon's pull bends into the closest
+//! approach wanted, for the speed the craft arrives at, and on the side that
+//! leaves the craft going around the Moon the same way as it went around the
+//! earth.  Of the departures over the next orbit, the one needing the least Δv
+//! is taken.
+//!
+//! The injection goes in a maneuver node at once, with its Δv corrected, as
+//! the autopilot's are, for the burn taking minutes rather than being an
+//! impulse.  The correction is solved the same way, from the craft's orbit as
+//! it is by then, which takes out what the burn, and the Moon's orbit not
+//! quite being a conic, have put off.  Inside the Moon's sphere of influence,
+//! the insertion is planned at the closest approach, from the craft's orbit
+//! about the Moon, to circularize there.  The burns not yet in a node are
+//! forecast with the mission events, so the whole sequence is on the
+//! timeline.
+//!
+//! A free return goes around the far side of the Moon instead, which bends it
+//! back to the earth with no burn at all, as the Apollo flights went out.  The
+//! Lambert transfer only starts that off: the Moon's pull along the way, and
+//! what the flyby does to the return, take flying the whole thing through both
+//! bodies' pulls.  The departure, and the injection's prograde and normal Δv,
+//! are corrected until the flyby's B-plane has the closest approach wanted, in
+//! the Moon's orbit plane, and the craft comes back down to the perigee
+//! wanted.  The mid-course correction is found the same way, from where the
+//! craft is, on all three of its components.  There is no insertion, and the
+//! transfer is over once the craft leaves the Moon's sphere of influence.
+//!
+//! The console's `lunar <altitude km> [duration s]` plans a transfer to a
+//! circular orbit at an altitude above the Moon, taking three days unless told
+//! otherwise, `lunar free-return <altitude km> <perigee km> [duration s]` a
+//! free return around it, and `lunar off` drops either, and its node.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use na::Vector3;
+use sim_astro::EarthMarker;
+use sim_core::{
+    MassiveBody, OrbitalBody, PhysicsSet, SizedBody,
+    bplane::{BPlane, miss_distance},
+    correction::{Correction, Corrector},
+    integrator::{Gravity, Method, Propagator, State},
+    lambert::lambert,
+    orbit::{Conic, OrbitFrame, period, propagate},
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{
+        GuidanceSet, MassProperties, PlayerShip,
+        autopilot::{AUTOPILOT_LEAD, AUTOPILOT_MIN_DV, BURN_CORRECTIONS, fly, golden_section},
+        engine::MainEngine,
+        maneuver::{ManeuverNode, node_execute},
+        targeting::lambert_burn,
+    },
+};
+
+/// The name of the body to go to.
+pub const MOON: &str = "MOON";
+
+/// How long a transfer takes, in seconds, unless told otherwise.
+const LUNAR_DURATION: f64 = 3.0 * 86400.0;
+
+/// The correction comes this far along the transfer.
+const LUNAR_CORRECTION: f64 = 0.5;
+
+/// Departures tried over the parking orbit, before closing in on the best.
+const LUNAR_SAMPLES: usize = 180;
+
+/// How closely, in seconds, the best departure is found.
+const LUNAR_TIME_TOLERANCE: f64 = 1.0;
+
+/// Times the aim point is moved for the speed the craft arrives at.
+const LUNAR_AIM_ITERATIONS: usize = 3;
+
+/// A free return is flown in steps of this many seconds, to find the closest
+/// approach, and its integration kept to this relative tolerance.
+const FREE_RETURN_STEP: f64 = 600.0;
+const FREE_RETURN_TOLERANCE: f64 = 1.0e-11;
+
+/// How long after the arrival, as a fraction of the transfer, a free return is
+/// flown for, to get back out of the Moon's sphere of influence.
+const FREE_RETURN_SPAN: f64 = 1.0;
+
+/// How closely B·T and B·R, and the perigee, km, are corrected.
+const FREE_RETURN_TOLERANCE_KM: f64 = 1.0;
+
+/// Iterations of the correction.
+const FREE_RETURN_ITERATIONS: usize = 20;
+
+/// The burns of a lunar transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LunarBurn {
+    Injection,
+    Correction,
+    Insertion,
+}
+
+impl std::fmt::Display for LunarBurn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LunarBurn::Injection => write!(f, "TLI"),
+            LunarBurn::Correction => write!(f, "MCC"),
+            LunarBurn::Insertion => write!(f, "LOI"),
+        }
+    }
+}
+
+impl LunarBurn {
+    /// The body the burn is made about.
+    pub fn body(&self) -> &'static str {
+        match self {
+            LunarBurn::Injection | LunarBurn::Correction => "EARTH",
+            LunarBurn::Insertion => MOON,
+        }
+    }
+}
+
+/// A burn still to be planned, as it is expected to go.
+#[derive(Clone, Debug)]
+pub struct PlannedBurn {
+    pub burn: LunarBurn,
+    /// When, as measured by `Time<Fixed>`.
+    pub time: f64,
+    /// How big, in m/s.
+    pub dv: f64,
+}
+
+/// Where a lunar transfer is.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LunarPhase {
+    /// Waiting for the injection node to be flown.
+    #[default]
+    Injection,
+    /// On the way out, before the correction.
+    Coast,
+    /// Flying the correction node.
+    Correction,
+    /// On the way in, before the Moon's sphere of influence.
+    Approach,
+    /// Flying the insertion node.
+    Insertion,
+    /// Going around the Moon, on a free return.
+    Flyby,
+}
+
+impl std::fmt::Display for LunarPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LunarPhase::Injection => write!(f, "injection"),
+            LunarPhase::Coast => write!(f, "coast"),
+            LunarPhase::Correction => write!(f, "mid-course correction"),
+            LunarPhase::Approach => write!(f, "approach"),
+            LunarPhase::Insertion => write!(f, "orbit insertion"),
+            LunarPhase::Flyby => write!(f, "flyby"),
+        }
+    }
+}
+
+/// A transfer from earth orbit into orbit about the Moon, or around it and
+/// back.
+#[derive(Clone, Component, Debug)]
+pub struct LunarTransfer {
+    /// The altitude of the lunar orbit, or of the closest approach on a free
+    /// return, in km above the Moon's radius.
+    pub altitude: f64,
+    /// On a free return, the altitude of the perigee it comes back to, in km
+    /// above the earth's radius.
+    pub perigee: Option<f64>,
+    /// When the transfer leaves, and gets to the closest approach, as
+    /// measured by `Time<Fixed>`.
+    pub departure: f64,
+    pub arrival: f64,
+    pub phase: LunarPhase,
+    /// The burns not yet in a node, next first.
+    pub burns: Vec<PlannedBurn>,
+}
+
+impl LunarTransfer {
+    /// The burn in the node, if it is one of the transfer's.
+    pub fn current(&self) -> Option<LunarBurn> {
+        match self.phase {
+            LunarPhase::Injection => Some(LunarBurn::Injection),
+            LunarPhase::Correction => Some(LunarBurn::Correction),
+            LunarPhase::Insertion => Some(LunarBurn::Insertion),
+            LunarPhase::Coast | LunarPhase::Approach | LunarPhase::Flyby => None,
+        }
+    }
+}
+
+/// One way to the Moon.
+struct Leg {
+    /// The burn onto it, m/s, as (prograde, normal, radial).
+    dv: Vector3<f64>,
+    /// The speed it arrives at the Moon with, relative to it, km/s.
+    v_inf: f64,
+    /// Where it is aimed, km, relative to the Moon on arrival.
+    aim: Vector3<f64>,
+}
+
+/// What a transfer is planned from.  Everything is relative to the earth.
+struct LunarState {
+    pos: Vector3<f64>,
+    vel: Vector3<f64>,
+    gm: f64,
+    moon_pos: Vector3<f64>,
+    moon_vel: Vector3<f64>,
+    moon_gm: f64,
+    moon_radius: f64,
+    radius: f64,
+}
+
+impl LunarState {
+    /// The Moon's state `dt` seconds from now, on its two-body orbit.
+    fn moon_at(&self, dt: f64) -> (Vector3<f64>, Vector3<f64>) {
+        propagate(&self.moon_pos, &self.moon_vel, self.gm + self.moon_gm, dt)
+    }
+
+    /// The radius of the Moon's sphere of influence, km.
+    fn moon_soi(&self) -> f64 {
+        self.moon_pos.norm() * (self.moon_gm / self.gm).powf(0.4)
+    }
+
+    /// The transfer leaving `dt` seconds from now, taking `duration`, to a
+    /// closest approach at `altitude` above the Moon, on the far side of it for
+    /// a free return.
+    fn leg(&self, dt: f64, duration: f64, altitude: f64, free_return: bool) -> Option<Leg> {
+        let (pos, vel) = propagate(&self.pos, &self.vel, self.gm, dt);
+        let (moon, moon_vel) = self.moon_at(dt + duration);
+        let rp = self.moon_radius + altitude;
+        let h = pos.cross(&vel);
+
+        // Aim at the Moon itself first, for the speed it arrives at.
+        let mut aim = Vector3::zeros();
+        let mut leg = None;
+        for _ in 0..LUNAR_AIM_ITERATIONS {
+            let (dv, v2) = lambert_burn(&pos, &vel, &(moon + aim), duration, self.gm)?;
+            let v_inf = v2 - moon_vel;
+            // The miss distance that a hyperbola with this speed at infinity
+            // bends into the closest approach, across the way it comes in.
+            let miss = miss_distance(rp, v_inf.norm(), self.moon_gm);
+            leg = Some(Leg {
+                dv,
+                v_inf: v_inf.norm(),
+                aim,
+            });
+            let side = if free_return {
+                h.cross(&v_inf)
+            } else {
+                v_inf.cross(&h)
+            };
+            aim = side.try_normalize(1.0e-12)? * miss;
+        }
+        leg
+    }
+
+    /// The leg `dt` seconds from now, with its burn corrected for being flown
+    /// by an engine, for a craft of `mass` kg, rather than as an impulse.
+    /// What is missing at the end of the burn, from the velocity that goes
+    /// from there to the aim point, is added to it, a few times over.
+    fn flown(
+        &self,
+        dt: f64,
+        duration: f64,
+        altitude: f64,
+        free_return: bool,
+        mass: f64,
+        engine: &MainEngine,
+    ) -> Option<Leg> {
+        let mut leg = self.leg(dt, duration, altitude, free_return)?;
+        let (pos, vel) = propagate(&self.pos, &self.vel, self.gm, dt);
+        let frame = OrbitFrame::new(&pos, &vel);
+        let (moon, _) = self.moon_at(dt + duration);
+        let mut dv_w = frame.to_world(&leg.dv);
+        for _ in 0..BURN_CORRECTIONS {
+            let end = dt + engine.burn_time(mass, dv_w.norm()) / 2.0;
+            let (end_pos, end_vel) = fly(&self.pos, &self.vel, self.gm, mass, engine, dt, &dv_w);
+            let h = end_pos.cross(&end_vel);
+            let (needed, _) = lambert(
+                &end_pos,
+                &(moon + leg.aim),
+                dt + duration - end,
+                self.gm,
+                &h,
+            )?;
+            dv_w += (needed - end_vel) * 1000.0;
+        }
+        leg.dv = Vector3::new(
+            dv_w.dot(&frame.prograde),
+            dv_w.dot(&frame.normal),
+            dv_w.dot(&frame.radial),
+        );
+        Some(leg)
+    }
+
+    /// The Δv, m/s, to circularize at the closest approach, coming in at
+    /// `v_inf`.
+    fn insertion_dv(&self, v_inf: f64, altitude: f64) -> f64 {
+        let rp = self.moon_radius + altitude;
+        let periapsis = (v_inf * v_inf + 2.0 * self.moon_gm / rp).sqrt();
+        (periapsis - (self.moon_gm / rp).sqrt()) * 1000.0
+    }
+
+    /// The best departure, in seconds from now, between `lead` seconds from
+    /// now and an orbit later.
+    fn departure(&self, lead: f64, duration: f64, altitude: f64, free_return: bool) -> Option<f64> {
+        let orbit = period(&self.pos, &self.vel, self.gm)?;
+        let cost = |dt: f64| {
+            self.leg(dt, duration, altitude, free_return)
+                .map_or(f64::INFINITY, |leg| leg.dv.norm())
+        };
+        let step = orbit / LUNAR_SAMPLES as f64;
+        let best = (0..=LUNAR_SAMPLES)
+            .map(|i| lead + step * i as f64)
+            .min_by(|a, b| cost(*a).total_cmp(&cost(*b)))?;
+        Some(golden_section(
+            (best - step).max(lead),
+            best + step,
+            LUNAR_TIME_TOLERANCE,
+            cost,
+        ))
+    }
+
+    /// Fly a burn `dt` seconds from now, of `dv` (m/s, as a node's), and
+    /// coast on through both the earth's and the Moon's pulls, around the
+    /// Moon and back out of its sphere of influence, for up to `span` seconds
+    /// from now.  The flyby's B-plane, with T in the Moon's orbit plane, and
+    /// the radius of the perigee the craft comes back to, in km.  None if it
+    /// doesn't go around the Moon in time.
+    fn flyby(
+        &self,
+        dt: f64,
+        dv: &Vector3<f64>,
+        mass: f64,
+        engine: &MainEngine,
+        span: f64,
+    ) -> Option<(BPlane, f64)> {
+        let (pos, vel) = propagate(&self.pos, &self.vel, self.gm, dt);
+        let dv_w = OrbitFrame::new(&pos, &vel).to_world(dv);
+        let (pos, vel) = fly(&self.pos, &self.vel, self.gm, mass, engine, dt, &dv_w);
+        let start = dt + engine.burn_time(mass, dv_w.norm()) / 2.0;
+        let pull = MoonPull(self);
+        let mut propagator = Propagator::new(
+            Method::Rk45 {
+                tolerance: FREE_RETURN_TOLERANCE,
+            },
+            start,
+            State { pos, vel },
+        );
+        let pole = self.moon_pos.cross(&self.moon_vel);
+        let soi = self.moon_soi();
+        let mut closest = f64::INFINITY;
+        let mut nearest = None;
+        let mut flyby = None;
+        while propagator.time < span {
+            propagator.step(&pull, FREE_RETURN_STEP);
+            let state = propagator.state(&pull);
+            let (moon, moon_vel) = self.moon_at(propagator.time);
+            let rel = state.pos - moon;
+            let inside = rel.norm() < soi;
+            match &flyby {
+                // The B-plane is taken from the osculating hyperbola nearest
+                // the Moon, where the earth's pull counts least.
+                None if inside && rel.norm() < closest => {
+                    closest = rel.norm();
+                    nearest = BPlane::new(&rel, &(state.vel - moon_vel), self.moon_gm, &pole);
+                }
+                None if nearest.is_some() => flyby = nearest.take(),
+                Some(_) if !inside => {
+                    let conic = Conic::new(&state.pos, &state.vel, self.gm);
+                    return Some((flyby?, conic.p / (1.0 + conic.e())));
+                }
+                _ => (),
+            }
+        }
+        None
+    }
+
+    /// Correct a burn, as `burn` makes it of some controls (when, in seconds
+    /// from now, and its Δv, m/s, as a node's), from the controls `guess`,
+    /// until it flies a free return: a closest approach at `altitude` above
+    /// the Moon, on the far side of it, in its orbit plane, and back to a
+    /// perigee at `perigee` above the earth.  `perturbation` and `max_step`
+    /// are the corrector's, for the controls.
+    #[allow(clippy::too_many_arguments)]
+    fn free_return(
+        &self,
+        guess: Vector3<f64>,
+        burn: impl Fn(&Vector3<f64>) -> (f64, Vector3<f64>),
+        perturbation: Vector3<f64>,
+        max_step: Vector3<f64>,
+        altitude: f64,
+        perigee: f64,
+        span: f64,
+        mass: f64,
+        engine: &MainEngine,
+    ) -> Option<Correction<3, 3>> {
+        let flight = |controls: &Vector3<f64>| {
+            let (dt, dv) = burn(controls);
+            self.flyby(dt, &dv, mass, engine, span)
+        };
+        let corrector = Corrector {
+            perturbation,
+            max_step,
+            tolerance: Vector3::repeat(FREE_RETURN_TOLERANCE_KM),
+            max_iterations: FREE_RETURN_ITERATIONS,
+        };
+        // Going around the far side is going around the Moon backwards, which
+        // puts B on the far side of T.  How far depends on the speed at
+        // infinity, which the correction changes a little, so it is done again.
+        let rp = self.moon_radius + altitude;
+        let mut controls = guess;
+        let mut correction = None;
+        for _ in 0..LUNAR_AIM_ITERATIONS {
+            let (flyby, _) = flight(&controls)?;
+            let wanted = Vector3::new(
+                -miss_distance(rp, flyby.v_inf, self.moon_gm),
+                0.0,
+                self.radius + perigee,
+            );
+            let corrected = corrector.correct(controls, &wanted, |controls| {
+                let (flyby, perigee) = flight(controls)?;
+                Some(Vector3::new(flyby.b_dot_t(), flyby.b_dot_r(), perigee))
+            })?;
+            controls = corrected.controls;
+            correction = Some(corrected);
+        }
+        correction
+    }
+
+    /// The insertion: when, in seconds from now, and the Δv, in m/s, world
+    /// frame, to circularize at the closest approach.  None if the craft is
+    /// on its way out.
+    fn insertion(&self) -> Option<(f64, Vector3<f64>)> {
+        let pos = self.pos - self.moon_pos;
+        let vel = self.vel - self.moon_vel;
+        let dt = time_to_periapsis(&pos, &vel, self.moon_gm)?;
+        let (pos, vel) = propagate(&pos, &vel, self.moon_gm, dt);
+        let along = pos.cross(&vel).cross(&pos).normalize();
+        let circular = (self.moon_gm / pos.norm()).sqrt();
+        Some((dt, (along * circular - vel) * 1000.0))
+    }
+
+    /// A Δv `dv_w` (m/s, world frame) `dt` seconds from now, as a node's
+    /// (prograde, normal, radial), which are relative to the earth.
+    fn node_dv(&self, dt: f64, dv_w: &Vector3<f64>) -> Vector3<f64> {
+        let (pos, vel) = propagate(&self.pos, &self.vel, self.gm, dt);
+        let frame = OrbitFrame::new(&pos, &vel);
+        Vector3::new(
+            dv_w.dot(&frame.prograde),
+            dv_w.dot(&frame.normal),
+            dv_w.dot(&frame.radial),
+        )
+    }
+}
+
+/// The Moon's pull on a craft about the earth, less its pull on the earth,
+/// with the Moon on its two-body orbit, `t` seconds from when the state was
+/// taken.
+struct MoonPull<'a>(&'a LunarState);
+
+impl Gravity for MoonPull<'_> {
+    fn gm(&self) -> f64 {
+        self.0.gm
+    }
+
+    fn perturbation(&self, t: f64, pos: &Vector3<f64>) -> Vector3<f64> {
+        let (moon, _) = self.0.moon_at(t);
+        let rel = moon - pos;
+        let d = rel.norm();
+        let o = moon.norm();
+        rel * (self.0.moon_gm / (d * d * d)) - moon * (self.0.moon_gm / (o * o * o))
+    }
+}
+
+/// The time, in seconds, to the next periapsis, or None if the orbit is open
+/// and it has gone by.
+fn time_to_periapsis(pos: &Vector3<f64>, vel: &Vector3<f64>, gm: f64) -> Option<f64> {
+    let conic = Conic::new(pos, vel, gm);
+    let nu = conic.true_anomaly(pos);
+    let e = conic.e();
+    if e < 1.0 {
+        return conic.time_between(nu, 0.0);
+    }
+    if nu >= 0.0 {
+        return None;
+    }
+    // Kepler's equation for the hyperbola.
+    let a = conic.p / (e * e - 1.0);
+    let f = 2.0 * (((e - 1.0) / (e + 1.0)).sqrt() * (nu / 2.0).tan()).atanh();
+    let mean = e * f.sinh() - f;
+    Some(-mean / (gm / (a * a * a)).sqrt())
+}
+
+/// The earth and the Moon, for planning.
+#[derive(SystemParam)]
+#[allow(clippy::type_complexity)]
+struct LunarBodies<'w, 's> {
+    earth: Query<
+        'w,
+        's,
+        (
+            &'static OrbitalBody,
+            &'static MassiveBody,
+            &'static SizedBody,
+        ),
+        (With<EarthMarker>, Without<PlayerShip>),
+    >,
+    bodies: Query<
+        'w,
+        's,
+        (
+            &'static Name,
+            &'static OrbitalBody,
+            &'static MassiveBody,
+            &'static SizedBody,
+        ),
+        Without<PlayerShip>,
+    >,
+}
+
+impl LunarBodies<'_, '_> {
+    fn state(&self, ship: &OrbitalBody) -> Result<LunarState, String> {
+        let (earth, earth_mass, earth_size) =
+            self.earth.single().map_err(|_| "no earth".to_string())?;
+        let (_, moon, moon_mass, moon_size) = self
+            .bodies
+            .iter()
+            .find(|(name, ..)| name.as_str() == MOON)
+            .ok_or_else(|| "no Moon".to_string())?;
+        Ok(LunarState {
+            pos: ship.pos - earth.pos,
+            vel: ship.vel - earth.vel,
+            gm: earth_mass.gm,
+            moon_pos: moon.pos - earth.pos,
+            moon_vel: moon.vel - earth.vel,
+            moon_gm: moon_mass.gm,
+            moon_radius: moon_size.radii.x,
+            radius: earth_size.radii.x,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct LunarPlugin;
+
+impl Plugin for LunarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            lunar_guidance
+                .in_set(GuidanceSet)
+                .before(node_execute)
+                .before(PhysicsSet),
+        );
+        app.add_console_command(
+            "lunar",
+            "lunar [free-return] <altitude km> [perigee km] [duration s]   go to the Moon, or lunar off",
+            lunar_command,
+        );
+    }
+}
+
+/// Plan each burn of the transfer, as the one before it is done.
+#[allow(clippy::type_complexity)]
+fn lunar_guidance(
+    time: Res<Time>,
+    mut commands: Commands,
+    mut ship: Query<
+        (
+            Entity,
+            &mut LunarTransfer,
+            &OrbitalBody,
+            &MassProperties,
+            &MainEngine,
+            Option<&mut ManeuverNode>,
+        ),
+        With<PlayerShip>,
+    >,
+    bodies: LunarBodies,
+) {
+    let Ok((entity, mut transfer, orbital, mass, engine, node)) = ship.single_mut() else {
+        return;
+    };
+    let Ok(state) = bodies.state(orbital) else {
+        return;
+    };
+    let now = time.elapsed_secs_f64();
+
+    match (transfer.phase, node) {
+        (LunarPhase::Injection, None) => transfer.phase = LunarPhase::Coast,
+        (LunarPhase::Coast, _) => {
+            let Some(at) = transfer
+                .burns
+                .first()
+                .filter(|burn| burn.burn == LunarBurn::Correction)
+                .map(|burn| burn.time)
+            else {
+                transfer.phase = LunarPhase::Approach;
+                return;
+            };
+            if at - now > AUTOPILOT_LEAD {
+                return;
+            }
+            transfer.burns.remove(0);
+            let duration = transfer.arrival - at;
+            let dv = match transfer.perigee {
+                Some(perigee) => state
+                    .free_return(
+                        Vector3::zeros(),
+                        |dv| (at - now, *dv),
+                        Vector3::repeat(0.1),
+                        Vector3::repeat(5.0),
+                        transfer.altitude,
+                        perigee,
+                        transfer.arrival - now + duration * FREE_RETURN_SPAN,
+                        mass.mass,
+                        engine,
+                    )
+                    .map(|correction| correction.controls),
+                None => state
+                    .leg(at - now, duration, transfer.altitude, false)
+                    .map(|leg| leg.dv),
+            };
+            match dv.filter(|dv| dv.norm() > AUTOPILOT_MIN_DV) {
+                Some(dv) => {
+                    let mut node = ManeuverNode::new(at);
+                    node.dv = dv;
+                    node.armed = true;
+                    commands.entity(entity).insert(node);
+                    transfer.phase = LunarPhase::Correction;
+                }
+                None => transfer.phase = LunarPhase::Approach,
+            }
+        }
+        (LunarPhase::Correction, None) => transfer.phase = LunarPhase::Approach,
+        (LunarPhase::Approach, _) => {
+            let rel = state.pos - state.moon_pos;
+            if rel.norm() > state.moon_soi() {
+                return;
+            }
+            if transfer.perigee.is_some() {
+                transfer.phase = LunarPhase::Flyby;
+                return;
+            }
+            let Some((dt, dv_w)) = state.insertion() else {
+                return;
+            };
+            transfer
+                .burns
+                .retain(|burn| burn.burn != LunarBurn::Insertion);
+            let mut node = ManeuverNode::new(now + dt);
+            node.dv = state.node_dv(dt, &dv_w);
+            node.armed = true;
+            commands.entity(entity).insert(node);
+            transfer.phase = LunarPhase::Insertion;
+        }
+        (LunarPhase::Insertion, Some(mut node)) => {
+            // Nodes are in the orbital frame about the earth, which the
+            // Moon's pull turns away from as the craft comes in; so the burn
+            // is worked out again about the Moon, until it starts.
+            if node.remaining_w.is_none()
+                && let Some((dt, dv_w)) = state.insertion()
+            {
+                node.time = now + dt;
+                node.dv = state.node_dv(dt, &dv_w);
+            }
+        }
+        (LunarPhase::Insertion, None) => {
+            commands.entity(entity).remove::<LunarTransfer>();
+        }
+        (LunarPhase::Flyby, _) => {
+            let rel = state.pos - state.moon_pos;
+            if rel.norm() > state.moon_soi() {
+                commands.entity(entity).remove::<LunarTransfer>();
+            }
+        }
+        _ => (),
+    }
+}
+
+/// `lunar <altitude> [duration]` plans a transfer into a circular orbit at an
+/// altitude above the Moon, and `lunar free-return <altitude> <perigee>
+/// [duration]` a free return around it, and either puts its injection in the
+/// ship's node.  `lunar off` drops it.
+#[allow(clippy::type_complexity)]
+fn lunar_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    fixed: Res<Time<Fixed>>,
+    ship: Query<
+        (
+            Entity,
+            &OrbitalBody,
+            &MassProperties,
+            &MainEngine,
+            Has<LunarTransfer>,
+        ),
+        With<PlayerShip>,
+    >,
+    bodies: LunarBodies,
+) -> ConsoleReply {
+    let (entity, orbital, mass, engine, planned) =
+        ship.single().map_err(|_| "no ship".to_string())?;
+    if args == ["off"] {
+        if planned {
+            commands
+                .entity(entity)
+                .remove::<(LunarTransfer, ManeuverNode)>();
+        }
+        return Ok("ok".to_string());
+    }
+    let (altitude, perigee, duration) = match args.as_slice() {
+        [altitude] => (parse_arg(altitude)?, None, LUNAR_DURATION),
+        [altitude, duration] => (parse_arg(altitude)?, None, parse_arg(duration)?),
+        [free, altitude, perigee] if free == "free-return" => (
+            parse_arg(altitude)?,
+            Some(parse_arg(perigee)?),
+            LUNAR_DURATION,
+        ),
+        [free, altitude, perigee, duration] if free == "free-return" => (
+            parse_arg(altitude)?,
+            Some(parse_arg(perigee)?),
+            parse_arg(duration)?,
+        ),
+        _ => {
+            return Err(
+                "lunar <altitude km> [duration s], or lunar free-return <altitude km> <perigee km> [duration s]"
+                    .to_string(),
+            );
+        }
+    };
+    if altitude <= 0.0 || duration <= 0.0 || perigee.is_some_and(|perigee: f64| perigee <= 0.0) {
+        return Err("The altitudes and duration must be positive".to_string());
+    }
+
+    let state = bodies.state(orbital)?;
+    // Leaving takes about what escape does, and half the burn comes before
+    // the node.
+    let escape = (2.0f64.sqrt() - 1.0) * state.vel.norm() * 1000.0;
+    let lead = AUTOPILOT_LEAD + engine.burn_time(mass.mass, escape) / 2.0;
+    let none = || "No transfer to the Moon from this orbit".to_string();
+    let dt = state
+        .departure(lead, duration, altitude, perigee.is_some())
+        .ok_or_else(none)?;
+
+    let now = fixed.elapsed_secs_f64();
+    let (dt, dv, mut burns, reply) = match perigee {
+        Some(perigee) => {
+            // The departure, and the injection's prograde and normal Δv, are
+            // corrected, from the Lambert transfer's.
+            let leg = state
+                .flown(dt, duration, altitude, true, mass.mass, engine)
+                .ok_or_else(none)?;
+            let radial = leg.dv.z;
+            let correction = state
+                .free_return(
+                    Vector3::new(dt, leg.dv.x, leg.dv.y),
+                    |controls| (controls.x, Vector3::new(controls.y, controls.z, radial)),
+                    Vector3::new(10.0, 0.1, 0.1),
+                    Vector3::new(600.0, 20.0, 20.0),
+                    altitude,
+                    perigee,
+                    dt + duration * (1.0 + FREE_RETURN_SPAN),
+                    mass.mass,
+                    engine,
+                )
+                .filter(|correction| correction.converged)
+                .ok_or_else(|| "No free return from this orbit".to_string())?;
+            let controls = correction.controls;
+            let dv = Vector3::new(controls.y, controls.z, radial);
+            let reply = format!(
+                "TLI T-{:.0} s, {:.1} m/s; free return to a {:.0} km perigee",
+                controls.x,
+                dv.norm(),
+                correction.targets.z - state.radius
+            );
+            (controls.x, dv, Vec::new(), reply)
+        }
+        None => {
+            let leg = state
+                .flown(dt, duration, altitude, false, mass.mass, engine)
+                .ok_or_else(none)?;
+            let loi = state.insertion_dv(leg.v_inf, altitude);
+            let reply = format!(
+                "TLI T-{:.0} s, {:.1} m/s; LOI {:.1} m/s",
+                dt,
+                leg.dv.norm(),
+                loi
+            );
+            let burns = vec![PlannedBurn {
+                burn: LunarBurn::Insertion,
+                time: now + dt + duration,
+                dv: loi,
+            }];
+            (dt, leg.dv, burns, reply)
+        }
+    };
+    let departure = now + dt;
+    let arrival = departure + duration;
+    burns.insert(
+        0,
+        PlannedBurn {
+            burn: LunarBurn::Correction,
+            time: departure + duration * LUNAR_CORRECTION,
+            dv: 0.0,
+        },
+    );
+    let mut node = ManeuverNode::new(departure);
+    node.dv = dv;
+    node.armed = true;
+    commands.entity(entity).insert((
+        node,
+        LunarTransfer {
+            altitude,
+            perigee,
+            departure,
+            arrival,
+            phase: LunarPhase::Injection,
+            burns,
+        },
+    ));
+    Ok(reply)
+}
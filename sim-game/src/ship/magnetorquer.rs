@@ -0,0 +1,231 @@
+//! Magnetorquers.
+//!
+//! Three coils, one along each BODY axis, make a magnetic dipole, which the
+//! body's field (see `sim_astro::radiation::Magnetosphere`) turns with a
+//! torque of m × B.  That is only ever across the field, and small, a few
+//! hundredths of a N*m in low orbit, but it takes no propellant, and goes
+//! away with the field, further out.
+//!
+//! In `Bdot`, the coils detumble the craft: they push back against the
+//! change in the field the craft sees, which, while it tumbles, is nearly all
+//! its own turning, and so brake it.  The field's own turn along the orbit
+//! leaves it spinning at about twice the orbital rate.  In `Manual`, they
+//! hold the dipole given.
+//!
+//! A craft that has browned out (see `power`) can't drive its coils.
+//!
+//! - `torquer`: the mode, the field, and the dipole and torque.
+//! - `torquer off|bdot`: switch the mode.
+//! - `torquer <x> <y> <z>`: hold a dipole, A*m^2, BODY frame.
+//! - `magfield <g10> <g11> <h11>`: set the earth's dipole from the Gauss
+//!   coefficients of a field model, in nT.
+
+use bevy::prelude::*;
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+use sim_astro::{EarthMarker, radiation::Magnetosphere};
+use sim_core::{
+    AttitudeControl, AttitudeState, OrbitalBody, PhysicsModels, PhysicsSet, SizedBody,
+    model_enabled,
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{MassProperties, PlayerShip, engine::engine_fire, power::Power},
+};
+
+/// The B-dot gain, A*m^2 per T/s.  The coils are full on for a tumble of a
+/// few mrad/s, and the loop is stable for steps up to a few minutes.
+const BDOT_GAIN: f64 = 1.0e10;
+
+/// What the coils do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum TorquerMode {
+    #[default]
+    Off,
+    Bdot,
+    Manual,
+}
+
+/// A craft's magnetorquers.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct Magnetorquers {
+    /// The most dipole each coil makes, A*m^2.
+    pub max_dipole: f64,
+    pub mode: TorquerMode,
+    /// The dipole the coils are making, or, in `Manual`, to make, A*m^2, BODY
+    /// frame.
+    pub dipole_b: Vector3<f64>,
+    /// The field, T, BODY frame, as measured in the last physics step.
+    pub field_b: Option<Vector3<f64>>,
+}
+
+impl Magnetorquers {
+    pub fn new(max_dipole: f64) -> Self {
+        Magnetorquers {
+            max_dipole,
+            mode: TorquerMode::Off,
+            dipole_b: Vector3::zeros(),
+            field_b: None,
+        }
+    }
+
+    /// The torque, N*m, BODY frame, in the last physics step.
+    pub fn torque_b(&self) -> Vector3<f64> {
+        match self.field_b {
+            Some(field_b) => self.dipole_b.cross(&field_b),
+            None => Vector3::zeros(),
+        }
+    }
+
+    pub fn status(&self) -> String {
+        let field = self.field_b.unwrap_or_else(Vector3::zeros) * 1.0e9;
+        let torque = self.torque_b();
+        format!(
+            "{:?}, field ({:.0}, {:.0}, {:.0}) nT, dipole ({:.0}, {:.0}, {:.0}) of {:.0} \
+             A*m^2, torque ({:.4}, {:.4}, {:.4}) N*m",
+            self.mode,
+            field.x,
+            field.y,
+            field.z,
+            self.dipole_b.x,
+            self.dipole_b.y,
+            self.dipole_b.z,
+            self.max_dipole,
+            torque.x,
+            torque.y,
+            torque.z,
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct MagnetorquerPlugin;
+
+impl Plugin for MagnetorquerPlugin {
+    fn build(&self, app: &mut App) {
+        PhysicsModels::add(app, "magnetorquer");
+        app.add_systems(
+            FixedUpdate,
+            magnetorquers
+                .after(engine_fire)
+                .before(PhysicsSet)
+                .run_if(model_enabled("magnetorquer")),
+        );
+        app.add_console_command(
+            "torquer",
+            "torquer [off | bdot | <x> <y> <z>]   the magnetorquers, and their mode or dipole",
+            torquer_command,
+        );
+        app.add_console_command(
+            "magfield",
+            "magfield [<g10> <g11> <h11>]   the earth's dipole, or set it from Gauss coefficients, nT",
+            magfield_command,
+        );
+    }
+}
+
+/// The field at `pos` (world frame, km), in T, world frame.
+pub fn field_w<'a>(
+    pos: &Vector3<f64>,
+    magnetospheres: impl Iterator<Item = (&'a Magnetosphere, &'a OrbitalBody, &'a AttitudeState, f64)>,
+) -> Vector3<f64> {
+    magnetospheres
+        .map(|(magnetosphere, body, attitude, radius)| {
+            let pos_b = attitude.q_bw.inverse_transform_vector(&(pos - body.pos));
+            attitude.q_bw * magnetosphere.field_b(&pos_b, radius)
+        })
+        .sum()
+}
+
+/// Measure the field, set the dipole, and add its torque to the craft's
+/// angular acceleration.  This must run after the thrusters have set theirs.
+#[allow(clippy::type_complexity)]
+fn magnetorquers(
+    time: Res<Time>,
+    mut crafts: Query<(
+        &OrbitalBody,
+        &AttitudeState,
+        &MassProperties,
+        &mut AttitudeControl,
+        &mut Magnetorquers,
+        Option<&Power>,
+    )>,
+    magnetospheres: Query<(&Magnetosphere, &OrbitalBody, &AttitudeState, &SizedBody)>,
+) {
+    let dt = time.delta_secs_f64();
+    for (orbital, attitude, mass, mut control, mut torquers, power) in crafts.iter_mut() {
+        let field = field_w(
+            &orbital.pos,
+            magnetospheres
+                .iter()
+                .map(|(m, body, attitude, size)| (m, body, attitude, size.radii.x)),
+        );
+        let field_b = attitude.q_bw.inverse_transform_vector(&field);
+        let last_b = torquers.field_b.replace(field_b);
+        let wanted_b = match torquers.mode {
+            TorquerMode::Off => Vector3::zeros(),
+            TorquerMode::Manual => torquers.dipole_b,
+            TorquerMode::Bdot => match last_b {
+                Some(last_b) if dt > 0.0 => (field_b - last_b) * (-BDOT_GAIN / dt),
+                _ => Vector3::zeros(),
+            },
+        };
+        let max = torquers.max_dipole;
+        let dipole_b = if power.is_some_and(|power| power.brownout) {
+            Vector3::zeros()
+        } else {
+            wanted_b.map(|m| m.clamp(-max, max))
+        };
+        if torquers.mode != TorquerMode::Manual {
+            torquers.dipole_b = dipole_b;
+        }
+        control.alpha_b += dipole_b.cross(&field_b).component_div(&mass.inertia_b);
+    }
+}
+
+fn torquer_command(
+    In(args): In<Vec<String>>,
+    mut torquers: Query<&mut Magnetorquers, With<PlayerShip>>,
+) -> ConsoleReply {
+    let mut torquers = torquers
+        .single_mut()
+        .map_err(|_| "The ship has no magnetorquers".to_string())?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] => {}
+        ["off"] => torquers.mode = TorquerMode::Off,
+        ["bdot"] => torquers.mode = TorquerMode::Bdot,
+        [x, y, z] => {
+            let dipole_b = Vector3::new(parse_arg(x)?, parse_arg(y)?, parse_arg(z)?);
+            let max = torquers.max_dipole;
+            torquers.dipole_b = dipole_b.map(|m| m.clamp(-max, max));
+            torquers.mode = TorquerMode::Manual;
+        }
+        _ => return Err("torquer [off | bdot | <x> <y> <z>]".to_string()),
+    }
+    Ok(torquers.status())
+}
+
+fn magfield_command(
+    In(args): In<Vec<String>>,
+    mut earth: Query<&mut Magnetosphere, With<EarthMarker>>,
+) -> ConsoleReply {
+    let mut magnetosphere = earth
+        .single_mut()
+        .map_err(|_| "The earth has no magnetic field".to_string())?;
+    match args.as_slice() {
+        [] => {}
+        [g10, g11, h11] => {
+            magnetosphere.set_dipole(parse_arg(g10)?, parse_arg(g11)?, parse_arg(h11)?)
+        }
+        _ => return Err("magfield [<g10> <g11> <h11>]".to_string()),
+    }
+    let axis = magnetosphere.axis_b;
+    Ok(format!(
+        "pole {:.1} {:.1}, {:.0} nT at the equator",
+        axis.z.asin().to_degrees(),
+        axis.y.atan2(axis.x).to_degrees(),
+        magnetosphere.strength * 1.0e9,
+    ))
+}
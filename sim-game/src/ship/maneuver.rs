@@ -0,0 +1,249 @@
+//! Maneuver nodes.
+//!
+//! A maneuver node is a planned change in velocity at a future time, expressed
+//! in the orbital frame at that time (prograde, normal, radial).  The node can
+//! be edited from the keyboard, and, once armed, the executor turns the craft
+//! to the burn direction, and fires the main engine centered on the node time.
+
+use bevy::prelude::*;
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+use sim_astro::EarthMarker;
+use sim_core::{
+    AttitudeState, MassiveBody, OrbitalBody, PhysicsSet,
+    orbit::{OrbitFrame, propagate},
+};
+
+use crate::{
+    remote::local_control,
+    ship::{
+        GuidanceSet, MassProperties, PlayerShip, RcsMode, engine::MainEngine, engine::engine_fire,
+        rcs_command,
+    },
+};
+
+/// A planned burn.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct ManeuverNode {
+    /// The simulation time of the node, in seconds (as measured by
+    /// `Time<Fixed>`).
+    pub time: f64,
+    /// The change in velocity, in m/s, as (prograde, normal, radial) at the
+    /// node.
+    pub dv: Vector3<f64>,
+    /// Whether the executor should fly this node.
+    pub armed: bool,
+    /// Once the burn has started, the Δv still to go, in m/s, world frame.
+    pub remaining_w: Option<Vector3<f64>>,
+}
+
+impl ManeuverNode {
+    pub fn new(time: f64) -> Self {
+        ManeuverNode {
+            time,
+            dv: Vector3::zeros(),
+            armed: false,
+            remaining_w: None,
+        }
+    }
+
+    /// The Δv of the node, in m/s, world frame.  `pos` and `vel` are the
+    /// craft's current state relative to the central body, and `now` is the
+    /// current simulation time.  Once the burn is underway, this is what is
+    /// left of it.
+    pub fn dv_w(&self, pos: &Vector3<f64>, vel: &Vector3<f64>, gm: f64, now: f64) -> Vector3<f64> {
+        if let Some(remaining) = self.remaining_w {
+            return remaining;
+        }
+        let (node_pos, node_vel) = propagate(pos, vel, gm, self.time - now);
+        OrbitFrame::new(&node_pos, &node_vel).to_world(&self.dv)
+    }
+}
+
+/// How long before the burn starts that the executor turns to the burn
+/// attitude, in seconds.
+const EXECUTE_LEAD: f64 = 60.0;
+
+/// How close, in degrees, the craft has to be pointed to the burn direction
+/// before the engine is lit.
+pub(crate) const EXECUTE_POINTING: f64 = 2.0;
+
+/// The burn is done when this little Δv (m/s) is left.
+const EXECUTE_DONE: f64 = 0.05;
+
+/// Default time from now, in seconds, to place a new node.
+const NEW_NODE_LEAD: f64 = 300.0;
+
+#[derive(Default)]
+pub struct ManeuverPlugin;
+
+impl Plugin for ManeuverPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, node_keys.run_if(local_control));
+        app.add_systems(
+            FixedUpdate,
+            node_execute
+                .in_set(GuidanceSet)
+                .before(rcs_command)
+                .before(engine_fire)
+                .before(PhysicsSet),
+        );
+    }
+}
+
+/// Keyboard editing of the node:
+///
+/// - N: create a node a few minutes ahead, or delete the existing one.
+/// - I/K: prograde/retrograde.
+/// - U/O: normal/anti-normal.
+/// - L/J: radial out/in.
+/// - Period/Comma: move the node later/earlier.
+/// - X: arm or disarm the executor.
+///
+/// Holding shift makes the adjustments ten times faster.
+fn node_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    fixed: Res<Time<Fixed>>,
+    mut commands: Commands,
+    mut ship: Query<(Entity, Option<&mut ManeuverNode>), With<PlayerShip>>,
+) {
+    let Ok((entity, node)) = ship.single_mut() else {
+        return;
+    };
+    let now = fixed.elapsed_secs_f64();
+
+    let Some(mut node) = node else {
+        if kb.just_pressed(KeyCode::KeyN) {
+            commands
+                .entity(entity)
+                .insert(ManeuverNode::new(now + NEW_NODE_LEAD));
+        }
+        return;
+    };
+
+    if kb.just_pressed(KeyCode::KeyN) {
+        commands.entity(entity).remove::<ManeuverNode>();
+        return;
+    }
+    if kb.just_pressed(KeyCode::KeyX) {
+        node.armed = !node.armed;
+    }
+
+    // Don't edit a burn in progress.
+    if node.remaining_w.is_some() {
+        return;
+    }
+
+    let dt = time.delta_secs_f64();
+    let fast = if kb.pressed(KeyCode::ShiftLeft) || kb.pressed(KeyCode::ShiftRight) {
+        10.0
+    } else {
+        1.0
+    };
+    let axis = |pos, neg| {
+        let mut value = 0.0;
+        if kb.pressed(pos) {
+            value += 1.0;
+        }
+        if kb.pressed(neg) {
+            value -= 1.0;
+        }
+        value
+    };
+
+    // 10 m/s per second, and one minute per second.
+    let ddv = Vector3::new(
+        axis(KeyCode::KeyI, KeyCode::KeyK),
+        axis(KeyCode::KeyU, KeyCode::KeyO),
+        axis(KeyCode::KeyL, KeyCode::KeyJ),
+    );
+    if ddv != Vector3::zeros() {
+        node.dv += ddv * (10.0 * fast * dt);
+    }
+    let dtime = axis(KeyCode::Period, KeyCode::Comma);
+    if dtime != 0.0 {
+        node.time = (node.time + dtime * 60.0 * fast * dt).max(now);
+    }
+}
+
+/// Fly an armed node: turn to it, and burn until the Δv is delivered.
+#[allow(clippy::type_complexity)]
+pub(crate) fn node_execute(
+    time: Res<Time>,
+    mut mode: ResMut<RcsMode>,
+    mut commands: Commands,
+    mut ship: Query<
+        (
+            Entity,
+            &mut ManeuverNode,
+            &mut MainEngine,
+            &OrbitalBody,
+            &AttitudeState,
+            &MassProperties,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<(&OrbitalBody, &MassiveBody), (With<EarthMarker>, Without<PlayerShip>)>,
+) {
+    let Ok((entity, mut node, mut engine, orbital, attitude, mass)) = ship.single_mut() else {
+        return;
+    };
+    let Ok((earth, earth_mass)) = earth.single() else {
+        return;
+    };
+    if !node.armed {
+        // Disarming mid-burn pauses it; the remaining Δv is kept.
+        if node.remaining_w.is_some() {
+            engine.throttle = 0.0;
+        }
+        return;
+    }
+
+    let now = time.elapsed_secs_f64();
+    let dt = time.delta_secs_f64();
+    let dv_w = node.dv_w(
+        &(orbital.pos - earth.pos),
+        &(orbital.vel - earth.vel),
+        earth_mass.gm,
+        now,
+    );
+    let burn_time = engine.burn_time(mass.mass, dv_w.norm());
+    let start = node.time - burn_time / 2.0;
+
+    if node.remaining_w.is_none() {
+        if now < start - EXECUTE_LEAD {
+            return;
+        }
+        *mode = RcsMode::Maneuver;
+        if now < start {
+            return;
+        }
+    }
+
+    // The burn is fixed in inertial space from here on.
+    let remaining = *node.remaining_w.get_or_insert(dv_w);
+    if remaining.norm() < EXECUTE_DONE {
+        engine.throttle = 0.0;
+        *mode = RcsMode::Hold;
+        commands.entity(entity).remove::<ManeuverNode>();
+        return;
+    }
+
+    let z_w = attitude.q_bw.transform_vector(&Vector3::z());
+    if z_w.angle(&remaining) > EXECUTE_POINTING.to_radians() {
+        engine.throttle = 0.0;
+        return;
+    }
+
+    // Taper off over the last second.
+    engine.throttle = (remaining.norm() * mass.mass / engine.max_thrust).clamp(0.0, 1.0);
+    let delivered = z_w * (engine.thrust(mass.mass) / mass.mass * dt);
+    let remaining = remaining - delivered;
+    if remaining.dot(&dv_w) <= 0.0 {
+        // Overshot, which means we are done.
+        node.remaining_w = Some(Vector3::zeros());
+    } else {
+        node.remaining_w = Some(remaining);
+    }
+}
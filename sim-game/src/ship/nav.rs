@@ -0,0 +1,394 @@
+//! Navigation: what a craft makes of its sensors.
+//!
+//! Each craft with `Sensors` and `Navigation` keeps an estimate of its
+//! attitude and orbit, from its sensors alone (see `sensors`), with a
+//! multiplicative extended Kalman filter.  Between readings, the estimate is
+//! carried along by the gyros and the accelerometers, and the gravity of the
+//! bodies where the craft thinks it is.  The star tracker, and the position
+//! fix, then pull it back.  Along the way, the filter works out the biases of
+//! the gyros and the accelerometers, which is most of what keeps it close
+//! between readings.
+//!
+//! The attitude is kept as a quaternion, and its error as a small rotation,
+//! BODY frame, so that the error state is 15 long: the attitude, the gyro
+//! bias, the position, the velocity, and the accelerometer bias.
+//!
+//! A reading too far from what the filter expects, such as after a teleport,
+//! or a snapshot being loaded, throws the estimate out, and the filter starts
+//! over from the next readings.
+//!
+//! The `NavSource` says whether the player's craft is flown, and shown, by
+//! the truth, or by the estimate.  With the estimate, the attitude controller
+//! steers by what the craft believes, and the ship view and navball show it.
+//! The rest of the sim, such as the autopilot, still knows the truth.
+//!
+//! - F12: switch between the truth and the estimate.
+//! - `nav`: which is in use, and how far off the estimate is.
+//! - `nav truth|estimate`: switch.
+
+use bevy::prelude::*;
+use na::{Matrix3, SMatrix, SVector, UnitQuaternion, Vector3};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    keys::{InputMap, KeysApp},
+    ship::{
+        PlayerShip,
+        sensors::{Sensors, sense},
+    },
+};
+
+type Matrix15 = SMatrix<f64, 15, 15>;
+
+/// Where each part of the error state starts.
+const ATTITUDE: usize = 0;
+const GYRO_BIAS: usize = 3;
+const POSITION: usize = 6;
+const VELOCITY: usize = 9;
+const ACCEL_BIAS: usize = 12;
+
+/// The standard deviation of the gyro bias, in rad/s, and accelerometer
+/// bias, in m/s^2, before the filter has learned anything of them.
+const GYRO_BIAS_SIGMA: f64 = 2.0e-4;
+const ACCEL_BIAS_SIGMA: f64 = 5.0e-3;
+
+/// The process noise on the velocity, km/s per root second, for what the
+/// filter doesn't model, such as the bodies' pull being worked out from
+/// where the craft thinks it is.
+const UNMODELED_ACCEL: f64 = 1.0e-8;
+
+/// A reading whose squared Mahalanobis distance from what the filter expects
+/// is over this throws the estimate out.  Well beyond the noise, for any
+/// number of dimensions read.
+const GATE: f64 = 200.0;
+
+/// Whether the player's craft is flown, and shown, by the truth, or by its
+/// estimate.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NavSource {
+    #[default]
+    Truth,
+    Estimate,
+}
+
+impl NavSource {
+    /// The state of a craft as seen from this source: its estimate, if it has
+    /// one, and the truth otherwise.
+    pub fn view(
+        self,
+        nav: Option<&Navigation>,
+        orbital: &OrbitalBody,
+        attitude: &AttitudeState,
+    ) -> (OrbitalBody, AttitudeState) {
+        match (self, nav.and_then(|n| n.estimate.as_ref())) {
+            (NavSource::Estimate, Some(estimate)) => (estimate.orbital(), estimate.attitude()),
+            _ => (orbital.clone(), attitude.clone()),
+        }
+    }
+}
+
+/// A craft's estimate of its own state.
+#[derive(Clone, Debug)]
+pub struct NavEstimate {
+    /// Body to world.
+    pub q_bw: UnitQuaternion<f64>,
+    /// The body rate, rad/s, BODY frame, the gyros less their bias.
+    pub omega_b: Vector3<f64>,
+    /// In km and km/s, like `OrbitalBody`.
+    pub pos: Vector3<f64>,
+    pub vel: Vector3<f64>,
+    /// In rad/s, and m/s^2, BODY frame.
+    pub gyro_bias_b: Vector3<f64>,
+    pub accel_bias_b: Vector3<f64>,
+    /// The covariance of the error state.
+    pub covariance: Matrix15,
+}
+
+impl NavEstimate {
+    /// Start from the star tracker and the fix, knowing nothing yet of the
+    /// biases.
+    fn new(
+        sensors: &Sensors,
+        q_bw: UnitQuaternion<f64>,
+        pos: Vector3<f64>,
+        vel: Vector3<f64>,
+    ) -> Self {
+        let mut variance = SVector::<f64, 15>::zeros();
+        for (start, sigma) in [
+            (ATTITUDE, sensors.star_tracker.sigma),
+            (GYRO_BIAS, GYRO_BIAS_SIGMA),
+            (POSITION, sensors.fix.sigma),
+            (VELOCITY, sensors.fix_velocity_sigma),
+            (ACCEL_BIAS, ACCEL_BIAS_SIGMA),
+        ] {
+            variance.fixed_rows_mut::<3>(start).fill(sigma * sigma);
+        }
+        NavEstimate {
+            q_bw,
+            omega_b: sensors.readings.omega_b,
+            pos,
+            vel,
+            gyro_bias_b: Vector3::zeros(),
+            accel_bias_b: Vector3::zeros(),
+            covariance: Matrix15::from_diagonal(&variance),
+        }
+    }
+
+    pub fn orbital(&self) -> OrbitalBody {
+        OrbitalBody {
+            pos: self.pos,
+            vel: self.vel,
+        }
+    }
+
+    pub fn attitude(&self) -> AttitudeState {
+        AttitudeState {
+            q_bw: self.q_bw,
+            omega_b: self.omega_b,
+        }
+    }
+
+    /// The standard deviation of one part of the error state, over its three
+    /// axes.
+    pub fn sigma(&self, start: usize) -> f64 {
+        (0..3)
+            .map(|i| self.covariance[(start + i, start + i)])
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    /// Carry the estimate on by `dt`, with the gyros and accelerometers, and
+    /// the pull of the bodies, given where each was at the start of the step,
+    /// and its GM.
+    fn propagate(&mut self, sensors: &Sensors, bodies: &[(Vector3<f64>, f64)], dt: f64) {
+        let omega_b = sensors.readings.omega_b - self.gyro_bias_b;
+        let accel_b = sensors.readings.accel_b - self.accel_bias_b;
+        let rotation = *self.q_bw.to_rotation_matrix().matrix();
+
+        // The pull of the bodies, and its gradient, which only the strongest
+        // is worth counting in.
+        let mut gravity = Vector3::zeros();
+        let mut gradient = Matrix3::zeros();
+        let mut strongest = 0.0;
+        for &(body_pos, gm) in bodies {
+            let rel = body_pos - self.pos;
+            let r = rel.norm();
+            let pull = rel * gm / (r * r * r);
+            gravity += pull;
+            if pull.norm() > strongest {
+                strongest = pull.norm();
+                let r_hat = rel / r;
+                gradient =
+                    (r_hat * r_hat.transpose() * 3.0 - Matrix3::identity()) * gm / (r * r * r);
+            }
+        }
+
+        // Like the physics: thrust, then gravity, on the velocity, then the
+        // velocity on the position.
+        self.vel += (self.q_bw.transform_vector(&accel_b) / 1000.0 + gravity) * dt;
+        self.pos += self.vel * dt;
+        self.q_bw *= UnitQuaternion::from_scaled_axis(omega_b * dt);
+        self.omega_b = omega_b;
+
+        let mut f = Matrix15::zeros();
+        let identity = Matrix3::identity();
+        f.fixed_view_mut::<3, 3>(ATTITUDE, ATTITUDE)
+            .copy_from(&-omega_b.cross_matrix());
+        f.fixed_view_mut::<3, 3>(ATTITUDE, GYRO_BIAS)
+            .copy_from(&-identity);
+        f.fixed_view_mut::<3, 3>(POSITION, VELOCITY)
+            .copy_from(&identity);
+        f.fixed_view_mut::<3, 3>(VELOCITY, ATTITUDE)
+            .copy_from(&(-rotation * accel_b.cross_matrix() / 1000.0));
+        f.fixed_view_mut::<3, 3>(VELOCITY, POSITION)
+            .copy_from(&gradient);
+        f.fixed_view_mut::<3, 3>(VELOCITY, ACCEL_BIAS)
+            .copy_from(&(-rotation / 1000.0));
+        let phi = Matrix15::identity() + f * dt;
+
+        let accel_noise = sensors.accelerometer.noise / 1000.0;
+        let mut q = SVector::<f64, 15>::zeros();
+        for (start, density) in [
+            (ATTITUDE, sensors.gyro.noise.powi(2)),
+            (GYRO_BIAS, sensors.gyro.bias_walk.powi(2)),
+            (VELOCITY, accel_noise.powi(2) + UNMODELED_ACCEL.powi(2)),
+            (ACCEL_BIAS, sensors.accelerometer.bias_walk.powi(2)),
+        ] {
+            q.fixed_rows_mut::<3>(start).fill(density * dt);
+        }
+        self.covariance = phi * self.covariance * phi.transpose() + Matrix15::from_diagonal(&q);
+    }
+
+    /// Take in a reading, `residual` off from what the estimate expects, by
+    /// `h`, with the noise covariance `noise`.  False, leaving the estimate
+    /// be, if the reading is too far off to believe.
+    fn update<const M: usize>(
+        &mut self,
+        h: &SMatrix<f64, M, 15>,
+        residual: &SVector<f64, M>,
+        noise: &SMatrix<f64, M, M>,
+    ) -> bool {
+        let s = h * self.covariance * h.transpose() + noise;
+        let Some(s_inv) = s.cholesky().map(|c| c.inverse()) else {
+            return false;
+        };
+        if (residual.transpose() * s_inv * residual)[(0, 0)] > GATE {
+            return false;
+        }
+        let k = self.covariance * h.transpose() * s_inv;
+        let dx = k * residual;
+
+        // The Joseph form, which keeps the covariance symmetric, and
+        // positive, whatever rounding does.
+        let i_kh = Matrix15::identity() - k * h;
+        self.covariance = i_kh * self.covariance * i_kh.transpose() + k * noise * k.transpose();
+
+        self.q_bw *= UnitQuaternion::from_scaled_axis(dx.fixed_rows::<3>(ATTITUDE).into_owned());
+        self.gyro_bias_b += dx.fixed_rows::<3>(GYRO_BIAS);
+        self.pos += dx.fixed_rows::<3>(POSITION);
+        self.vel += dx.fixed_rows::<3>(VELOCITY);
+        self.accel_bias_b += dx.fixed_rows::<3>(ACCEL_BIAS);
+        true
+    }
+
+    /// Take in this step's star tracker reading and fix, if there were any.
+    /// False if either was too far off.
+    fn correct(&mut self, sensors: &Sensors) -> bool {
+        let mut good = true;
+        if let Some(q_bw) = sensors.readings.q_bw {
+            let mut h = SMatrix::<f64, 3, 15>::zeros();
+            h.fixed_view_mut::<3, 3>(0, ATTITUDE).fill_with_identity();
+            let residual = (self.q_bw.inverse() * q_bw).scaled_axis();
+            let noise = Matrix3::identity() * sensors.star_tracker.sigma.powi(2);
+            good &= self.update(&h, &residual, &noise);
+        }
+        if let Some((pos, vel)) = sensors.readings.fix {
+            let mut h = SMatrix::<f64, 6, 15>::zeros();
+            h.fixed_view_mut::<3, 3>(0, POSITION).fill_with_identity();
+            h.fixed_view_mut::<3, 3>(3, VELOCITY).fill_with_identity();
+            let mut residual = SVector::<f64, 6>::zeros();
+            residual.fixed_rows_mut::<3>(0).copy_from(&(pos - self.pos));
+            residual.fixed_rows_mut::<3>(3).copy_from(&(vel - self.vel));
+            let mut noise = SVector::<f64, 6>::zeros();
+            noise.fixed_rows_mut::<3>(0).fill(sensors.fix.sigma.powi(2));
+            noise
+                .fixed_rows_mut::<3>(3)
+                .fill(sensors.fix_velocity_sigma.powi(2));
+            good &= self.update(&h, &residual, &SMatrix::from_diagonal(&noise));
+        }
+        self.omega_b = sensors.readings.omega_b - self.gyro_bias_b;
+        good
+    }
+}
+
+/// A craft's navigation filter.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Navigation {
+    /// None until the first readings of both the star tracker and the fix.
+    pub estimate: Option<NavEstimate>,
+    /// How many times the estimate has been thrown out.
+    pub resets: u32,
+}
+
+impl Navigation {
+    /// How far the estimate is off the truth, and how far the filter thinks
+    /// it may be, for the HUD and the console.
+    pub fn status(&self, orbital: &OrbitalBody, attitude: &AttitudeState) -> String {
+        let Some(estimate) = &self.estimate else {
+            return format!("waiting for readings, {} resets", self.resets);
+        };
+        format!(
+            "off {:.1}\" (sigma {:.1}\"), {:.1} m ({:.1} m), {:.3} m/s ({:.3} m/s), {} resets",
+            estimate.q_bw.angle_to(&attitude.q_bw).to_degrees() * 3600.0,
+            estimate.sigma(ATTITUDE).to_degrees() * 3600.0,
+            (estimate.pos - orbital.pos).norm() * 1000.0,
+            estimate.sigma(POSITION) * 1000.0,
+            (estimate.vel - orbital.vel).norm() * 1000.0,
+            estimate.sigma(VELOCITY) * 1000.0,
+            self.resets
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct NavPlugin;
+
+impl Plugin for NavPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavSource>();
+        app.add_systems(FixedUpdate, navigate.after(sense));
+        app.add_key_action("nav-source", &[KeyCode::F12]);
+        app.add_systems(Update, nav_keys);
+        app.add_console_command(
+            "nav",
+            "nav [truth|estimate]   fly by the truth, or by the navigation filter",
+            nav_command,
+        );
+    }
+}
+
+/// Run each craft's filter on its sensors' readings.
+fn navigate(
+    time: Res<Time>,
+    mut crafts: Query<(&Sensors, &mut Navigation)>,
+    bodies: Query<(&OrbitalBody, &MassiveBody)>,
+) {
+    let dt = time.delta_secs_f64();
+    if dt <= 0.0 {
+        return;
+    }
+    // The physics has already moved the bodies on, but pulled with where
+    // they were at the start of the step.
+    let bodies: Vec<_> = bodies
+        .iter()
+        .map(|(o, m)| (o.pos - o.vel * dt, m.gm))
+        .collect();
+    for (sensors, mut nav) in crafts.iter_mut() {
+        let good = match nav.estimate.as_mut() {
+            Some(estimate) => {
+                estimate.propagate(sensors, &bodies, dt);
+                estimate.correct(sensors)
+            }
+            None => {
+                if let (Some(q_bw), Some((pos, vel))) =
+                    (sensors.readings.q_bw, sensors.readings.fix)
+                {
+                    nav.estimate = Some(NavEstimate::new(sensors, q_bw, pos, vel));
+                }
+                true
+            }
+        };
+        if !good {
+            nav.estimate = None;
+            nav.resets += 1;
+        }
+    }
+}
+
+fn nav_keys(kb: Res<ButtonInput<KeyCode>>, map: Res<InputMap>, mut source: ResMut<NavSource>) {
+    if map.just_pressed(&kb, "nav-source") {
+        *source = match *source {
+            NavSource::Truth => NavSource::Estimate,
+            NavSource::Estimate => NavSource::Truth,
+        };
+    }
+}
+
+fn nav_command(
+    In(args): In<Vec<String>>,
+    mut source: ResMut<NavSource>,
+    ship: Query<(&OrbitalBody, &AttitudeState, Option<&Navigation>), With<PlayerShip>>,
+) -> ConsoleReply {
+    match args.as_slice() {
+        [] => {}
+        [truth] if truth == "truth" => *source = NavSource::Truth,
+        [estimate] if estimate == "estimate" => *source = NavSource::Estimate,
+        _ => return Err("nav [truth|estimate]".to_string()),
+    }
+    let (orbital, attitude, nav) = ship.single().map_err(|_| "There is no ship".to_string())?;
+    match nav {
+        Some(nav) => Ok(format!("{:?}, {}", *source, nav.status(orbital, attitude))),
+        None => Ok(format!("{:?}, the ship has no navigation", *source)),
+    }
+}
@@ -0,0 +1,245 @@
+//! Orbit determination: the orbit as the ground would know it, from the
+//! craft's position fixes.
+//!
+//! Each craft with `Sensors` and `OrbitDetermination` keeps its position
+//! fixes (see `sensors`), one every so often, relative to the earth, and
+//! fits a two-body orbit to them each time one comes in (see
+//! `sim_core::orbit_determination`).  The fit is the "known" orbit, as
+//! against the truth, and its residuals show how well a conic explains the
+//! fixes: their noise, and what the conic leaves out, such as the earth's
+//! oblateness, and drag.
+//!
+//! A burn, or a fix far off the fit (such as after a teleport, or a snapshot
+//! being loaded), starts the history over.
+//!
+//! - `od`: the fit, its residuals, and how far off the truth it is.
+//! - `od clear`: start the history over.
+//! - `od <interval> <count>`: keep a fix every `interval` seconds, and the
+//!   last `count` of them.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use na::Vector3;
+use sim_astro::EarthMarker;
+use sim_core::{
+    LinearControl, MassiveBody, OrbitalBody,
+    orbit::Conic,
+    orbit_determination::{self, Fit, Fix},
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{
+        PlayerShip,
+        sensors::{Sensors, sense},
+    },
+};
+
+/// Thrust past this, in km/s^2, is a burn, which the history can't span.
+const BURN_ACCEL: f64 = 1.0e-6;
+
+/// A fix further than this, in km, from where the fit has the craft, starts
+/// the history over.
+const OUTLIER: f64 = 1.0;
+
+/// The most least squares iterations a fit gets.  Each fit starts from the
+/// last, so it seldom needs more than two.
+const MAX_ITERATIONS: usize = 8;
+
+/// A craft's history of position fixes, and the orbit fitted to them.
+#[derive(Clone, Component, Debug)]
+pub struct OrbitDetermination {
+    /// The time, in seconds, between the fixes kept.
+    pub interval: f64,
+    /// The most fixes kept.  The oldest go first.
+    pub capacity: usize,
+    /// Relative to the earth.
+    pub fixes: VecDeque<Fix>,
+    pub fit: Option<Fit>,
+    /// How many times the history has been started over.
+    pub restarts: u32,
+}
+
+impl Default for OrbitDetermination {
+    /// A fix every 10 s, for the last 10 minutes, about a tenth of a low
+    /// orbit.
+    fn default() -> Self {
+        OrbitDetermination {
+            interval: 10.0,
+            capacity: 60,
+            fixes: VecDeque::new(),
+            fit: None,
+            restarts: 0,
+        }
+    }
+}
+
+impl OrbitDetermination {
+    fn clear(&mut self) {
+        if !self.fixes.is_empty() {
+            self.restarts += 1;
+        }
+        self.fixes.clear();
+        self.fit = None;
+    }
+
+    /// Keep a fix, and fit the orbit again.
+    fn record(&mut self, fix: Fix, gm: f64, sigma: f64) {
+        let outlier = self
+            .fit
+            .as_ref()
+            .is_some_and(|fit| (fit.state_at(fix.time).0 - fix.pos).norm() > OUTLIER);
+        let backward = self.fixes.back().is_some_and(|last| fix.time <= last.time);
+        if outlier || backward {
+            self.clear();
+        }
+        self.fixes.push_back(fix);
+        while self.fixes.len() > self.capacity {
+            self.fixes.pop_front();
+        }
+
+        let fixes = self.fixes.make_contiguous();
+        let middle = fixes[fixes.len() / 2].time;
+        self.fit = match &self.fit {
+            Some(fit) => {
+                let (pos, vel) = fit.state_at(middle);
+                orbit_determination::least_squares(
+                    fixes,
+                    gm,
+                    middle,
+                    &pos,
+                    &vel,
+                    sigma,
+                    MAX_ITERATIONS,
+                )
+            }
+            None => orbit_determination::determine(fixes, gm, sigma, MAX_ITERATIONS),
+        };
+    }
+
+    /// The fit, against the truth, relative to the earth, at `now`.
+    pub fn status(&self, now: f64, pos: &Vector3<f64>, vel: &Vector3<f64>) -> String {
+        let (Some(fit), Some(first), Some(last)) =
+            (&self.fit, self.fixes.front(), self.fixes.back())
+        else {
+            return format!(
+                "{} fixes, no fit yet, {} restarts",
+                self.fixes.len(),
+                self.restarts
+            );
+        };
+        let (fit_pos, fit_vel) = fit.state_at(now);
+        let known = Conic::new(&fit_pos, &fit_vel, fit.gm);
+        let truth = Conic::new(pos, vel, fit.gm);
+        let axis = |conic: &Conic| conic.p / (1.0 - conic.e() * conic.e());
+        format!(
+            "{} fixes over {:.0} s, residuals {:.1} m rms, off {:.1} m, {:.3} m/s, \
+             a {:.3} km (truth {:.3}), e {:.5} ({:.5}), {} restarts",
+            self.fixes.len(),
+            last.time - first.time,
+            fit.rms * 1000.0,
+            (fit_pos - pos).norm() * 1000.0,
+            (fit_vel - vel).norm() * 1000.0,
+            axis(&known),
+            axis(&truth),
+            known.e(),
+            truth.e(),
+            self.restarts
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct OrbitDeterminationPlugin;
+
+impl Plugin for OrbitDeterminationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, record_fixes.after(sense));
+        app.add_console_command(
+            "od",
+            "od [clear | <interval> <count>]   the orbit fitted to the position fixes",
+            od_command,
+        );
+    }
+}
+
+/// Keep each craft's fixes, every so often, and fit them.
+fn record_fixes(
+    time: Res<Time>,
+    mut crafts: Query<(&Sensors, Option<&LinearControl>, &mut OrbitDetermination)>,
+    earth: Query<(&OrbitalBody, &MassiveBody), With<EarthMarker>>,
+) {
+    let Ok((earth, earth_mass)) = earth.single() else {
+        return;
+    };
+    let now = time.elapsed_secs_f64();
+    for (sensors, linear, mut od) in crafts.iter_mut() {
+        if linear.is_some_and(|linear| linear.accel_b.norm() > BURN_ACCEL) {
+            od.clear();
+            continue;
+        }
+        let Some((pos, _)) = sensors.readings.fix else {
+            continue;
+        };
+        if od
+            .fixes
+            .back()
+            .is_some_and(|last| now > last.time && now - last.time < od.interval)
+        {
+            continue;
+        }
+        let fix = Fix {
+            time: now,
+            pos: pos - earth.pos,
+        };
+        od.record(fix, earth_mass.gm, sensors.fix.sigma);
+    }
+}
+
+fn od_command(
+    In(args): In<Vec<String>>,
+    fixed: Res<Time<Fixed>>,
+    mut ship: Query<(&OrbitalBody, &mut OrbitDetermination), With<PlayerShip>>,
+    earth: Query<&OrbitalBody, (With<EarthMarker>, Without<PlayerShip>)>,
+) -> ConsoleReply {
+    let (orbital, mut od) = ship
+        .single_mut()
+        .map_err(|_| "The ship has no orbit determination".to_string())?;
+    let earth = earth
+        .single()
+        .map_err(|_| "There is no earth".to_string())?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] => {}
+        ["clear"] => od.clear(),
+        [interval, count] => {
+            let interval = parse_arg(interval)?;
+            let count = parse_arg(count)?;
+            if interval <= 0.0 || count < 3.0 {
+                return Err("It takes at least 3 fixes, some time apart".to_string());
+            }
+            od.interval = interval;
+            od.capacity = count as usize;
+            od.clear();
+        }
+        _ => return Err("od [clear | <interval> <count>]".to_string()),
+    }
+    let mut reply = od.status(
+        fixed.elapsed_secs_f64(),
+        &(orbital.pos - earth.pos),
+        &(orbital.vel - earth.vel),
+    );
+    if let Some(fit) = &od.fit {
+        let (pos_sigma, vel_sigma) = fit.sigma();
+        reply += &format!(
+            "\nsigma {:.1} m, {:.3} m/s at {:.0} s, {} iterations{}",
+            pos_sigma * 1000.0,
+            vel_sigma * 1000.0,
+            fit.epoch,
+            fit.iterations,
+            if fit.converged { "" } else { ", not converged" }
+        );
+    }
+    Ok(reply)
+}
@@ -0,0 +1,136 @@
+//! Ground support, on the launch pad.
+//!
+//! A craft on the pad is held down by the launch clamps (see
+//! `sim_astro::contact::Clamped`), rigid to the ground as the body turns,
+//! and fed through its `Umbilical`: ground power, so that the battery is full
+//! however long the hold, and propellant, to top the tank off as the engine
+//! burns it before release.  Both let go at T-0, with `pad release`, and the
+//! craft is on its own.
+//!
+//! The launch itself is run by the countdown (see `countdown`), which lights
+//! the engine while the clamps still hold, and lets go at T-0.  A craft
+//! started on the ground, with `--launch`, is already on the pad.
+//!
+//! - `pad`: what is holding and feeding the craft.
+//! - `pad hold`: clamp a landed craft down, and connect the umbilical.
+//! - `pad release`: let go of it.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use sim_astro::contact::{Clamped, Landed};
+use sim_core::PhysicsSet;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    ship::{
+        MassProperties, PlayerShip,
+        engine::{FuelTank, engine_fire},
+        power::Power,
+    },
+};
+
+/// The ground's feed to a craft on the pad.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct Umbilical {
+    /// The power, W, it charges the battery with.
+    pub power: f64,
+    /// The propellant, kg/s, it tops the tank off with.
+    pub flow: f64,
+}
+
+impl Default for Umbilical {
+    fn default() -> Self {
+        Umbilical {
+            power: 5000.0,
+            flow: 50.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct PadPlugin;
+
+impl Plugin for PadPlugin {
+    fn build(&self, app: &mut App) {
+        // After the engine has drawn on the tank, so that it is full again
+        // for the step.
+        app.add_systems(
+            FixedUpdate,
+            umbilical_feed.after(engine_fire).before(PhysicsSet),
+        );
+        app.add_console_command(
+            "pad",
+            "pad [hold | release]   the launch clamps and umbilical",
+            pad_command,
+        );
+    }
+}
+
+/// Charge the battery and top off the tank of each craft on its umbilical.
+fn umbilical_feed(
+    time: Res<Time>,
+    mut crafts: Query<(
+        &Umbilical,
+        &mut MassProperties,
+        Option<&mut FuelTank>,
+        Option<&mut Power>,
+    )>,
+) {
+    let dt = time.delta_secs_f64();
+    for (umbilical, mut mass, tank, power) in crafts.iter_mut() {
+        if let Some(mut power) = power {
+            // W to Wh.
+            power.charge = (power.charge + umbilical.power * dt / 3600.0).min(power.capacity);
+        }
+        let Some(mut tank) = tank else {
+            continue;
+        };
+        let added = (umbilical.flow * dt).min(tank.capacity - tank.propellant);
+        if added > 0.0 && mass.mass > 0.0 {
+            tank.propellant += added;
+            // The inertia goes with the mass, as `engine_fire` takes it off.
+            let scale = (mass.mass + added) / mass.mass;
+            mass.mass += added;
+            mass.inertia_b *= scale;
+        }
+    }
+}
+
+/// Say what is holding and feeding the craft.
+fn pad_status(clamped: bool, umbilical: Option<&Umbilical>) -> String {
+    format!(
+        "clamps {}, umbilical {}",
+        if clamped { "holding" } else { "off" },
+        match umbilical {
+            Some(u) => format!("on, {:.0} W, {:.0} kg/s", u.power, u.flow),
+            None => "off".to_string(),
+        }
+    )
+}
+
+#[allow(clippy::type_complexity)]
+fn pad_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    ship: Query<(Entity, Has<Landed>, Has<Clamped>, Option<&Umbilical>), With<PlayerShip>>,
+) -> ConsoleReply {
+    let (entity, landed, clamped, umbilical) = ship.single().map_err(|_| "no ship".to_string())?;
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        [] => Ok(pad_status(clamped, umbilical)),
+        ["hold"] => {
+            if !landed {
+                return Err("The craft isn't on the ground".to_string());
+            }
+            commands
+                .entity(entity)
+                .insert((Clamped, Umbilical::default()));
+            Ok(pad_status(true, Some(&Umbilical::default())))
+        }
+        ["release"] => {
+            commands.entity(entity).remove::<(Clamped, Umbilical)>();
+            Ok("released".to_string())
+        }
+        _ => Err("pad [hold | release]".to_string()),
+    }
+}
@@ -0,0 +1,164 @@
+//! Mass properties from the parts a craft is built of.
+//!
+//! Each part is a simple solid, a cylinder along BODY Z, a block, or a point,
+//! with its mass and where its center is.  Together they give the craft's
+//! mass, its center of mass, and its moments of inertia about that, by the
+//! parallel axis theorem.  As elsewhere, only the principal moments along the
+//! BODY axes are kept, so a layout far from symmetric loses its products of
+//! inertia.
+//!
+//! The moments can also be had from the craft's model, with `mass model`: its
+//! meshes' bounding boxes are taken as solid blocks, sharing the craft's mass
+//! by their volumes.  The model is only loaded with a window, so that is done
+//! in `sim_render`, and nothing comes of it headless.
+//!
+//! - `mass`: the player ship's mass properties.
+//! - `mass model`: work them out again from its model.
+
+use bevy::prelude::*;
+use na::{Matrix3, Vector3};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    ship::{MassProperties, PlayerShip},
+};
+
+/// The shape of a part, about its own center.
+#[derive(Clone, Debug)]
+pub enum PartShape {
+    /// A solid cylinder, with its axis along Z.  In m.
+    Cylinder { radius: f64, length: f64 },
+    /// A solid block, with its half widths along each axis, in m.
+    Block { half_extents: Vector3<f64> },
+    /// Small enough for its own moments not to matter.
+    Point,
+}
+
+/// A part of a craft.
+#[derive(Clone, Debug)]
+pub struct Part {
+    pub name: String,
+    /// In kg.
+    pub mass: f64,
+    /// Its center, in m, relative to the body origin, BODY frame.
+    pub pos_b: Vector3<f64>,
+    pub shape: PartShape,
+}
+
+impl Part {
+    fn new(name: &str, mass: f64, shape: PartShape) -> Self {
+        Part {
+            name: name.to_string(),
+            mass,
+            pos_b: Vector3::zeros(),
+            shape,
+        }
+    }
+
+    pub fn cylinder(name: &str, mass: f64, radius: f64, length: f64) -> Self {
+        Part::new(name, mass, PartShape::Cylinder { radius, length })
+    }
+
+    pub fn block(name: &str, mass: f64, half_extents: Vector3<f64>) -> Self {
+        Part::new(name, mass, PartShape::Block { half_extents })
+    }
+
+    pub fn point(name: &str, mass: f64) -> Self {
+        Part::new(name, mass, PartShape::Point)
+    }
+
+    /// The part, with its center at `pos_b`.
+    pub fn at(self, pos_b: Vector3<f64>) -> Self {
+        Part { pos_b, ..self }
+    }
+
+    /// The part's principal moments of inertia about its own center, in
+    /// kg*m^2.
+    pub fn inertia(&self) -> Vector3<f64> {
+        let m = self.mass;
+        match self.shape {
+            PartShape::Cylinder { radius, length } => {
+                let i_xy = m * (3.0 * radius * radius + length * length) / 12.0;
+                Vector3::new(i_xy, i_xy, m * radius * radius / 2.0)
+            }
+            PartShape::Block { half_extents: h } => {
+                // With the widths twice the half widths.
+                let (x, y, z) = (h.x * h.x, h.y * h.y, h.z * h.z);
+                Vector3::new(y + z, x + z, x + y) * (m / 3.0)
+            }
+            PartShape::Point => Vector3::zeros(),
+        }
+    }
+}
+
+/// The inertia, in kg*m^2, of a point mass `m` offset by `d` from the axes:
+/// the parallel axis theorem's term.
+pub(crate) fn parallel_axis(d: &Vector3<f64>, m: f64) -> Matrix3<f64> {
+    (Matrix3::identity() * d.norm_squared() - d * d.transpose()) * m
+}
+
+impl MassProperties {
+    /// The mass properties of the parts together.
+    pub fn from_parts(parts: &[Part]) -> Self {
+        let mass: f64 = parts.iter().map(|part| part.mass).sum();
+        let cg_b = parts
+            .iter()
+            .map(|part| part.pos_b * part.mass)
+            .sum::<Vector3<f64>>()
+            / mass;
+        let inertia = parts
+            .iter()
+            .map(|part| {
+                Matrix3::from_diagonal(&part.inertia())
+                    + parallel_axis(&(part.pos_b - cg_b), part.mass)
+            })
+            .sum::<Matrix3<f64>>();
+        MassProperties {
+            mass,
+            cg_b,
+            inertia_b: inertia.diagonal(),
+        }
+    }
+}
+
+/// On a craft, to have its mass properties worked out from its model, once
+/// that has loaded.  Its mass stays as it is.
+#[derive(Component, Debug)]
+pub struct MassFromModel;
+
+#[derive(Default)]
+pub struct PartsPlugin;
+
+impl Plugin for PartsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command(
+            "mass",
+            "mass [model]   show the ship's mass properties, or take them from its model",
+            mass_command,
+        );
+    }
+}
+
+fn mass_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    ship: Query<(Entity, &MassProperties), With<PlayerShip>>,
+) -> ConsoleReply {
+    let Ok((entity, mass)) = ship.single() else {
+        return Err("no ship".to_string());
+    };
+    match args.as_slice() {
+        [] => {
+            let (cg, i) = (&mass.cg_b, &mass.inertia_b);
+            Ok(format!(
+                "{:.0} kg, cg {:.3} {:.3} {:.3} m, inertia {:.0} {:.0} {:.0} kg m^2",
+                mass.mass, cg.x, cg.y, cg.z, i.x, i.y, i.z
+            ))
+        }
+        [word] if word == "model" => {
+            commands.entity(entity).insert(MassFromModel);
+            Ok("ok, once the model has loaded".to_string())
+        }
+        _ => Err("mass [model]".to_string()),
+    }
+}
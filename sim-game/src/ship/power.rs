@@ -0,0 +1,211 @@
+//! Electrical power.
+//!
+//! A craft with `Power` runs on its solar panels, with a battery for when they
+//! can't keep up: in a shadow (see `sunlight`), or turned away from the sun.
+//! The panels are fixed to the craft, and give power by the cosine of the
+//! sun's angle off their faces.  What they give beyond the loads charges the
+//! battery, and anything more than it can take is thrown away.
+//!
+//! The loads are the avionics, which are always on, the RCS valves while they
+//! fire, and the main engine's valves and igniter while it burns.  When the
+//! battery runs flat with the panels short, the craft browns out: the
+//! avionics drop out, and nothing fires until the panels have charged the
+//! battery back enough to start them again.
+
+use bevy::prelude::*;
+use na::{Unit, Vector3};
+use serde::{Deserialize, Serialize};
+use sim_astro::eclipse::flux;
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, PhysicsModels, model_enabled};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{
+        PlayerShip, engine::MainEngine, rcs::RcsThrusters, rcs_allocate, rcs_fire,
+        sunlight::Sunlight,
+    },
+};
+
+/// The fraction of the battery's capacity it has to be back to, after a
+/// brown-out, before the avionics start again.
+pub const RESTART_CHARGE: f64 = 0.1;
+
+/// A solar panel, fixed to the craft.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SolarPanel {
+    pub name: String,
+    /// The area of the cells, m^2.
+    pub area: f64,
+    /// The direction the cells face, BODY frame.
+    pub normal_b: Unit<Vector3<f64>>,
+}
+
+/// A craft's electrical power system.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct Power {
+    pub panels: Vec<SolarPanel>,
+    /// The fraction of the sunlight on the panels that they turn into power.
+    pub efficiency: f64,
+    /// The battery's capacity, and how much is in it, Wh.
+    pub capacity: f64,
+    pub charge: f64,
+    /// The loads, W: the avionics, each RCS thruster at full thrust, and the
+    /// main engine while it burns.
+    pub avionics: f64,
+    pub rcs: f64,
+    pub engine: f64,
+    /// What the panels gave, and the loads drew, in the last physics step, W.
+    pub generated: f64,
+    pub load: f64,
+    /// Whether the craft is browned out, and waiting for the battery.
+    pub brownout: bool,
+}
+
+impl Power {
+    /// A pair of wings, of `area` m^2 between them, facing BODY +X, and a
+    /// battery of `capacity` Wh, full.
+    pub fn wings(area: f64, capacity: f64) -> Self {
+        Power {
+            panels: vec![SolarPanel {
+                name: "wings".to_string(),
+                area,
+                normal_b: Vector3::x_axis(),
+            }],
+            efficiency: 0.3,
+            capacity,
+            charge: capacity,
+            avionics: 1500.0,
+            rcs: 40.0,
+            engine: 500.0,
+            generated: 0.0,
+            load: 0.0,
+            brownout: false,
+        }
+    }
+
+    /// The battery's charge, as a fraction of its capacity.
+    pub fn state_of_charge(&self) -> f64 {
+        if self.capacity > 0.0 {
+            self.charge / self.capacity
+        } else {
+            0.0
+        }
+    }
+
+    /// The power, W, the panels give with the sun along `sun_b` (BODY frame,
+    /// a unit vector), and a flux of `flux` W/m^2.
+    pub fn generation(&self, sun_b: &Vector3<f64>, flux: f64) -> f64 {
+        self.panels
+            .iter()
+            .map(|panel| panel.area * panel.normal_b.dot(sun_b).max(0.0))
+            .sum::<f64>()
+            * flux
+            * self.efficiency
+    }
+}
+
+#[derive(Default)]
+pub struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        PhysicsModels::add(app, "power");
+        app.add_systems(
+            FixedUpdate,
+            power_step
+                .after(rcs_allocate)
+                .before(rcs_fire)
+                .run_if(model_enabled("power")),
+        );
+        app.add_console_command(
+            "battery",
+            "battery [percent]   show the ship's power, or set its battery's charge",
+            battery_command,
+        );
+    }
+}
+
+/// `battery` shows the player ship's power, and `battery <percent>` sets its
+/// battery's charge.
+fn battery_command(
+    In(args): In<Vec<String>>,
+    mut ship: Query<&mut Power, With<PlayerShip>>,
+) -> ConsoleReply {
+    let Ok(mut power) = ship.single_mut() else {
+        return Err("no ship".to_string());
+    };
+    match args.as_slice() {
+        [] => {}
+        [percent] => {
+            let percent: f64 = parse_arg(percent)?;
+            power.charge = power.capacity * (percent / 100.0).clamp(0.0, 1.0);
+        }
+        _ => return Err("battery [percent]".to_string()),
+    }
+    Ok(format!(
+        "battery {:.0} of {:.0} Wh, panels {:.0} W, loads {:.0} W{}",
+        power.charge,
+        power.capacity,
+        power.generated,
+        power.load,
+        if power.brownout { ", browned out" } else { "" }
+    ))
+}
+
+/// Charge and drain each craft's battery over the last step, and keep a
+/// browned out craft from firing anything.  The loads are those of the last
+/// step, so this must run before the thrusters fire for this one.
+#[allow(clippy::type_complexity)]
+fn power_step(
+    time: Res<Time>,
+    mut crafts: Query<(
+        &Name,
+        &OrbitalBody,
+        &AttitudeState,
+        &Sunlight,
+        &mut Power,
+        Option<&mut RcsThrusters>,
+        Option<&mut MainEngine>,
+    )>,
+    bodies: Query<(&MassiveBody, &OrbitalBody), Without<Power>>,
+) {
+    let dt = time.delta_secs_f64();
+    // The sun is the most massive body.
+    let Some((_, sun)) = bodies.iter().max_by(|a, b| a.0.gm.total_cmp(&b.0.gm)) else {
+        return;
+    };
+    for (name, orbital, attitude, light, mut power, rcs, engine) in crafts.iter_mut() {
+        let sun_b = attitude.q_bw.inverse_transform_vector(&light.sun_w);
+        let distance = (sun.pos - orbital.pos).norm();
+        power.generated = power.generation(&sun_b, flux(distance, light.fraction));
+        power.load = if power.brownout {
+            0.0
+        } else {
+            let rcs = rcs
+                .as_ref()
+                .map_or(0.0, |rcs| rcs.level.iter().sum::<f64>());
+            let burning = engine.as_ref().is_some_and(|engine| engine.throttle > 0.0);
+            power.avionics + power.rcs * rcs + if burning { power.engine } else { 0.0 }
+        };
+
+        // W to Wh.
+        let net = power.generated - power.load;
+        power.charge = (power.charge + net * dt / 3600.0).clamp(0.0, power.capacity);
+        if !power.brownout && power.charge <= 0.0 && net < 0.0 {
+            power.brownout = true;
+            warn!("{}: battery flat, browned out", name);
+        } else if power.brownout && power.state_of_charge() >= RESTART_CHARGE {
+            power.brownout = false;
+            info!("{}: power back, avionics restarted", name);
+        }
+
+        if power.brownout {
+            if let Some(mut rcs) = rcs {
+                rcs.duty.fill(0.0);
+            }
+            if let Some(mut engine) = engine {
+                engine.throttle = 0.0;
+            }
+        }
+    }
+}
@@ -0,0 +1,505 @@
+//! Trajectory prediction.
+//!
+//! The ship's path is predicted analytically, one conic at a time: from the
+//! current state up to the maneuver node (if there is one), then, with the
+//! node's Δv applied as an impulse, on along the new conic.  This is redone
+//! every frame, so the prediction follows along as nodes are edited and as the
+//! engine and thrusters change the orbit.
+//!
+//! The conics are only the ideal: the sim also pulls the craft toward the
+//! moon and the sun, and drags it through the air.  So the path it will
+//! really coast along is integrated, too, with those forces, for a few
+//! revolutions, to show how far reality bends away from the conic.  Nodes
+//! aren't flown on that path.
+//!
+//! The paths are kept as sampled points relative to the central body, so that
+//! any view can draw them at whatever scale it likes (see `sim_render`, for the
+//! 3D scene, and the map in `sim_ui`).
+//!
+//! Where the conics come down, they give the footprint: when, and where, the
+//! craft reaches the top of the atmosphere, and the ground, and how steeply
+//! it is coming down there.  The impact is the vacuum one, where the conic
+//! meets the ellipsoid; the air brings a craft down short of it.
+//!
+//! - `impact`: the entry and impact, if the path comes down.
+
+use bevy::{diagnostic::Diagnostics, prelude::*};
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+use sim_astro::{EarthMarker, atmosphere::Atmosphere, contact::Landed, geodesy::Geodetic};
+use sim_core::{
+    AttitudeState, MassiveBody, OrbitalBody, PhysicsModels, SizedBody,
+    integrator::{Gravity, Method, Propagator, State},
+    orbit::{Conic, period, propagate},
+};
+use std::{fmt, time::Instant};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    ship::{MassProperties, PlayerShip, aero::Aero, maneuver::ManeuverNode},
+    stats::SimStatsPlugin,
+};
+
+/// Number of points sampled along each conic.
+const PREDICT_SAMPLES: usize = 256;
+
+/// How far ahead, in seconds, to follow an orbit that doesn't close.
+const PREDICT_OPEN_SPAN: f64 = 6.0 * 3600.0;
+
+/// How many revolutions the perturbed path is followed for.
+const PERTURBED_REVOLUTIONS: f64 = 3.0;
+
+/// How closely, relative to the state, the perturbed path is integrated.
+const PERTURBED_TOLERANCE: f64 = 1.0e-10;
+
+/// How many times the time where a conic comes down through an altitude is
+/// halved, from between two samples.
+const CROSSING_BISECTIONS: usize = 40;
+
+/// Where, and when, the path comes down through an altitude.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Footprint {
+    /// Seconds from now.
+    pub time: f64,
+    /// Geodetic, in degrees.
+    pub lat: f64,
+    pub lon: f64,
+    /// The angle, in radians, of the velocity relative to the air, above the
+    /// local horizontal: negative, coming down.
+    pub flight_path_angle: f64,
+    /// Relative to the air, km/s.
+    pub speed: f64,
+}
+
+impl fmt::Display for Footprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = self.time.round() as u64;
+        write!(
+            f,
+            "in {}:{:02}:{:02} at {:.2} {}, {:.2} {}, {:.2} deg at {:.3} km/s",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            self.lat.abs(),
+            if self.lat >= 0.0 { "N" } else { "S" },
+            self.lon.abs(),
+            if self.lon >= 0.0 { "E" } else { "W" },
+            self.flight_path_angle.to_degrees(),
+            self.speed
+        )
+    }
+}
+
+/// The predicted path of a craft.
+#[derive(Clone, Component, Debug, Default, Serialize, Deserialize)]
+pub struct Prediction {
+    /// The sampled positions along each conic, in km, world frame, relative
+    /// to the central body.  The first conic starts at the craft's current
+    /// position, and each one after that starts at a maneuver node.
+    pub conics: Vec<Vec<Vector3<f64>>>,
+    /// The path the craft will really coast along, with the other bodies'
+    /// pull and drag, sampled the same way, over `PERTURBED_REVOLUTIONS`.
+    pub perturbed: Vec<Vector3<f64>>,
+    /// How far, in km, the end of the perturbed path is from where the first
+    /// conic puts the craft at that time.
+    pub divergence: f64,
+    /// Where the conics first come down to the top of the atmosphere (see
+    /// `Atmosphere::thickness`), if the craft is above it now.
+    #[serde(default)]
+    pub entry: Option<Footprint>,
+    /// Where the conics first meet the ground.
+    #[serde(default)]
+    pub impact: Option<Footprint>,
+}
+
+#[derive(Default)]
+pub struct PredictPlugin;
+
+impl Plugin for PredictPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, predict);
+        app.add_console_command(
+            "impact",
+            "impact   when and where the predicted path enters the air, and meets the ground",
+            impact_command,
+        );
+    }
+}
+
+/// Sample the conic through `pos` and `vel` from now until `span` seconds
+/// ahead (or one revolution, if `span` is None), stopping early if it meets
+/// the surface.  Returns the points, and whether the surface was hit.
+fn sample_conic(
+    pos: &Vector3<f64>,
+    vel: &Vector3<f64>,
+    gm: f64,
+    span: Option<f64>,
+    surface: f64,
+) -> (Vec<Vector3<f64>>, bool) {
+    let span = span.unwrap_or_else(|| period(pos, vel, gm).unwrap_or(PREDICT_OPEN_SPAN));
+    let mut points = vec![*pos];
+    for i in 1..=PREDICT_SAMPLES {
+        let dt = span * i as f64 / PREDICT_SAMPLES as f64;
+        let (p, _) = propagate(pos, vel, gm, dt);
+        points.push(p);
+        if p.norm() < surface {
+            return (points, true);
+        }
+    }
+    (points, false)
+}
+
+/// A conic the path follows: when it starts, in seconds from now, the state
+/// there, and how long it is followed for (or one revolution, if None).
+struct Leg {
+    start: f64,
+    pos: Vector3<f64>,
+    vel: Vector3<f64>,
+    span: Option<f64>,
+}
+
+/// The conics from the current state, through the node, if any.
+fn legs(
+    pos: &Vector3<f64>,
+    vel: &Vector3<f64>,
+    gm: f64,
+    node: Option<&ManeuverNode>,
+    now: f64,
+) -> Vec<Leg> {
+    let Some(node) = node else {
+        return vec![Leg {
+            start: 0.0,
+            pos: *pos,
+            vel: *vel,
+            span: None,
+        }];
+    };
+
+    // A burn in progress is treated as though the rest of it happens now.
+    let dt = (node.time - now).max(0.0);
+    let (node_pos, node_vel) = propagate(pos, vel, gm, dt);
+    // m/s to km/s.
+    let node_vel = node_vel + node.dv_w(pos, vel, gm, now) / 1000.0;
+    vec![
+        Leg {
+            start: 0.0,
+            pos: *pos,
+            vel: *vel,
+            span: Some(dt),
+        },
+        Leg {
+            start: dt,
+            pos: node_pos,
+            vel: node_vel,
+            span: None,
+        },
+    ]
+}
+
+/// The conics from the current state, sampled, up to where they meet the
+/// surface.
+fn predict_conics(legs: &[Leg], gm: f64, surface: f64) -> Vec<Vec<Vector3<f64>>> {
+    let mut conics = Vec::new();
+    for leg in legs {
+        let (points, hit) = sample_conic(&leg.pos, &leg.vel, gm, leg.span, surface);
+        conics.push(points);
+        if hit {
+            break;
+        }
+    }
+    conics
+}
+
+/// Where the conics first come down through `alt` km over the central body's
+/// ellipsoid, if they do.  `attitude` is the body's, now.
+fn crossing(
+    legs: &[Leg],
+    gm: f64,
+    attitude: &AttitudeState,
+    radii: &Vector3<f64>,
+    alt: f64,
+) -> Option<Footprint> {
+    let body = OrbitalBody {
+        pos: Vector3::zeros(),
+        vel: Vector3::zeros(),
+    };
+    for leg in legs {
+        let fixed = |t: f64, pos: &Vector3<f64>| {
+            attitude
+                .q_bw_after(leg.start + t)
+                .inverse_transform_vector(pos)
+        };
+        let above = |t: f64| {
+            let (pos, _) = propagate(&leg.pos, &leg.vel, gm, t);
+            Geodetic::from_body(&fixed(t, &pos), radii).alt > alt
+        };
+        // Nothing to find if it can't get that low, or is there already.
+        let conic = Conic::new(&leg.pos, &leg.vel, gm);
+        if conic.p / (1.0 + conic.e()) > radii.max() + alt || !above(0.0) {
+            continue;
+        }
+        let span = leg
+            .span
+            .unwrap_or_else(|| period(&leg.pos, &leg.vel, gm).unwrap_or(PREDICT_OPEN_SPAN));
+        let Some(i) =
+            (1..=PREDICT_SAMPLES).find(|i| !above(span * *i as f64 / PREDICT_SAMPLES as f64))
+        else {
+            continue;
+        };
+        let mut before = span * (i - 1) as f64 / PREDICT_SAMPLES as f64;
+        let mut after = span * i as f64 / PREDICT_SAMPLES as f64;
+        for _ in 0..CROSSING_BISECTIONS {
+            let mid = (before + after) / 2.0;
+            if above(mid) {
+                before = mid;
+            } else {
+                after = mid;
+            }
+        }
+        let (pos, vel) = propagate(&leg.pos, &leg.vel, gm, after);
+        let geodetic = Geodetic::from_body(&fixed(after, &pos), radii);
+        let up = attitude.q_bw_after(leg.start + after) * geodetic.up();
+        let wind = Atmosphere::wind_relative(&body, attitude, &pos, &vel);
+        return Some(Footprint {
+            time: leg.start + after,
+            lat: geodetic.lat.to_degrees(),
+            lon: geodetic.lon.to_degrees(),
+            flight_path_angle: (wind.dot(&up) / wind.norm()).asin(),
+            speed: wind.norm(),
+        });
+    }
+    None
+}
+
+/// Drag, for the perturbed path.
+struct Drag<'a> {
+    /// The central body, at the origin, and its air.
+    body: OrbitalBody,
+    attitude: &'a AttitudeState,
+    radii: Vector3<f64>,
+    atmosphere: &'a Atmosphere,
+    /// The craft's drag coefficient times its area, over its mass, in m^2/kg.
+    /// It is taken to keep showing the flow the area it shows now.
+    ballistic: f64,
+}
+
+/// Another body pulling on the craft, relative to the central body.  Over a
+/// few revolutions of the craft, it is taken to move on a parabola.
+struct Other {
+    gm: f64,
+    pos: Vector3<f64>,
+    vel: Vector3<f64>,
+    accel: Vector3<f64>,
+}
+
+/// What the sim puts on the craft, besides the central body's pull as a
+/// point mass.
+struct Perturbations<'a> {
+    gm: f64,
+    others: Vec<Other>,
+    drag: Option<Drag<'a>>,
+}
+
+impl Gravity for Perturbations<'_> {
+    fn gm(&self) -> f64 {
+        self.gm
+    }
+
+    fn perturbation(&self, t: f64, pos: &Vector3<f64>) -> Vector3<f64> {
+        let mut accel = Vector3::zeros();
+        for other in &self.others {
+            // Less the pull on the central body, which is our origin.
+            let at = other.pos + other.vel * t + other.accel * (t * t / 2.0);
+            let rel = at - pos;
+            let d = rel.norm();
+            let o = at.norm();
+            accel += rel * (other.gm / (d * d * d)) - at * (other.gm / (o * o * o));
+        }
+        accel
+    }
+
+    fn drag(&self, _t: f64, pos: &Vector3<f64>, vel: &Vector3<f64>) -> Vector3<f64> {
+        let Some(drag) = &self.drag else {
+            return Vector3::zeros();
+        };
+        if pos.norm() - drag.radii.max() > drag.atmosphere.top {
+            return Vector3::zeros();
+        }
+        // The ellipsoid is the same all the way around, so it doesn't matter
+        // that the body will have turned.
+        let alt = Geodetic::from_world(&drag.body, drag.attitude, &drag.radii, pos).alt;
+        let density = drag.atmosphere.density(alt);
+        let wind = Atmosphere::wind_relative(&drag.body, drag.attitude, pos, vel);
+        if density == 0.0 || wind.norm_squared() == 0.0 {
+            return Vector3::zeros();
+        }
+        // km/s to m/s, and back to km/s^2.
+        let speed = wind.norm() * 1000.0;
+        -wind.normalize() * (0.5 * density * speed * speed * drag.ballistic / 1000.0)
+    }
+}
+
+/// Integrate the path the craft will coast along, from `pos` and `vel`, over
+/// `PERTURBED_REVOLUTIONS`, stopping early if it meets the surface.  Returns
+/// the points, and how far the last is from the conic.
+fn predict_perturbed(
+    pos: &Vector3<f64>,
+    vel: &Vector3<f64>,
+    perturbations: &Perturbations,
+    surface: f64,
+) -> (Vec<Vector3<f64>>, f64) {
+    let gm = perturbations.gm;
+    let span = period(pos, vel, gm).map_or(PREDICT_OPEN_SPAN, |p| p * PERTURBED_REVOLUTIONS);
+    let step = span / PREDICT_SAMPLES as f64;
+    let mut propagator = Propagator::new(
+        Method::Rk45 {
+            tolerance: PERTURBED_TOLERANCE,
+        },
+        0.0,
+        State {
+            pos: *pos,
+            vel: *vel,
+        },
+    );
+    let mut points = vec![*pos];
+    for _ in 0..PREDICT_SAMPLES {
+        propagator.step(perturbations, step);
+        let p = propagator.state(perturbations).pos;
+        points.push(p);
+        if p.norm() < surface {
+            break;
+        }
+    }
+    let (ideal, _) = propagate(pos, vel, gm, propagator.time);
+    let divergence = (points[points.len() - 1] - ideal).norm();
+    (points, divergence)
+}
+
+/// Recompute the prediction for the player ship.
+#[allow(clippy::type_complexity)]
+pub fn predict(
+    fixed: Res<Time<Fixed>>,
+    models: Res<PhysicsModels>,
+    mut diagnostics: Diagnostics,
+    mut ship: Query<
+        (
+            &OrbitalBody,
+            &AttitudeState,
+            &MassProperties,
+            &mut Prediction,
+            Option<&ManeuverNode>,
+            Option<&Aero>,
+            Has<Landed>,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<
+        (
+            Entity,
+            &OrbitalBody,
+            &AttitudeState,
+            &MassiveBody,
+            &SizedBody,
+            Option<&Atmosphere>,
+        ),
+        (With<EarthMarker>, Without<PlayerShip>),
+    >,
+    others: Query<(Entity, &OrbitalBody, &MassiveBody), Without<PlayerShip>>,
+) {
+    let Ok((orbital, attitude, mass, mut prediction, node, aero, landed)) = ship.single_mut()
+    else {
+        return;
+    };
+    let Ok((earth_entity, earth, earth_attitude, earth_mass, earth_size, atmosphere)) =
+        earth.single()
+    else {
+        return;
+    };
+
+    let start = Instant::now();
+    let pos = orbital.pos - earth.pos;
+    let vel = orbital.vel - earth.vel;
+    let legs = legs(&pos, &vel, earth_mass.gm, node, fixed.elapsed_secs_f64());
+    prediction.conics = predict_conics(&legs, earth_mass.gm, earth_size.radii.z);
+    (prediction.entry, prediction.impact) = if landed {
+        (None, None)
+    } else {
+        let at = |alt| crossing(&legs, earth_mass.gm, earth_attitude, &earth_size.radii, alt);
+        (atmosphere.and_then(|air| at(air.thickness())), at(0.0))
+    };
+
+    let others = if models.enabled("gravity") && models.enabled("third-body") {
+        others
+            .iter()
+            .filter(|(entity, ..)| *entity != earth_entity)
+            .map(|(_, other, other_mass)| {
+                let rel = other.pos - earth.pos;
+                let r = rel.norm();
+                let accel = -rel * ((earth_mass.gm + other_mass.gm) / (r * r * r));
+                Other {
+                    gm: other_mass.gm,
+                    pos: rel,
+                    vel: other.vel - earth.vel,
+                    accel,
+                }
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let body = OrbitalBody {
+        pos: Vector3::zeros(),
+        vel: Vector3::zeros(),
+    };
+    let drag = match (aero, atmosphere) {
+        (Some(aero), Some(atmosphere)) if models.enabled("drag") => {
+            let wind = Atmosphere::wind_relative(&body, earth_attitude, &pos, &vel);
+            let area = if wind.norm_squared() > 0.0 {
+                aero.area(&attitude.q_bw.inverse_transform_vector(&wind))
+            } else {
+                aero.area_b.mean()
+            };
+            Some(Drag {
+                body: body.clone(),
+                attitude: earth_attitude,
+                radii: earth_size.radii,
+                atmosphere,
+                ballistic: aero.drag_coefficient * area / mass.mass,
+            })
+        }
+        _ => None,
+    };
+    let perturbations = Perturbations {
+        gm: earth_mass.gm,
+        others,
+        drag,
+    };
+    (prediction.perturbed, prediction.divergence) =
+        predict_perturbed(&pos, &vel, &perturbations, earth_size.radii.z);
+    diagnostics.add_measurement(&SimStatsPlugin::PREDICT_TIME, || {
+        start.elapsed().as_secs_f64() * 1000.0
+    });
+}
+
+fn impact_command(
+    In(_): In<Vec<String>>,
+    ship: Query<&Prediction, With<PlayerShip>>,
+) -> ConsoleReply {
+    let prediction = ship.single().map_err(|_| "There is no ship".to_string())?;
+    if prediction.impact.is_none() {
+        return Ok("The path doesn't come down".to_string());
+    }
+    let line = |name: &str, footprint: Option<Footprint>| {
+        footprint.map_or(String::new(), |footprint| {
+            format!("{}: {}", name, footprint)
+        })
+    };
+    Ok([
+        line("entry", prediction.entry),
+        line("impact", prediction.impact),
+    ]
+    .into_iter()
+    .filter(|line| !line.is_empty())
+    .collect::<Vec<_>>()
+    .join("\n"))
+}
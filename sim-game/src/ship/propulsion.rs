@@ -0,0 +1,346 @@
+//! Fictional propulsion.
+//!
+//! A `PropulsionModel` is a drive that doesn't have to obey the rocket
+//! equation: a torchship that holds a steady 1 g for days, or a jump drive
+//! that moves a craft from one state to another outright.  Models only ever
+//! ask for things; the sim does them, the same way for every model, so that
+//! the rest of the sim can still account for where the momentum and energy
+//! came from:
+//!
+//! - An acceleration goes in through the craft's `LinearControl`, like any
+//!   other thrust, and is integrated by the physics.
+//! - A jump has to be confirmed by the pilot (Y, or H to call it off), and
+//!   then replaces the craft's state.
+//!
+//! Either way, the `PropulsionLedger` keeps a tally of the Δv and the energy
+//! (per unit mass) the drive has put in, as nothing else in the sim will show
+//! where it came from.  Nothing else is touched: a drive doesn't push back on
+//! anything.
+//!
+//! Two example drives are here: `Brachistochrone`, an autopilot that flies a
+//! constant acceleration torchship to the SAS target, turning over halfway to
+//! brake, and `JumpDrive`, which offers to jump alongside it.  The console's
+//! `drive` command fits them.
+
+use bevy::prelude::*;
+use na::{UnitQuaternion, Vector3};
+use sim_astro::EarthMarker;
+use sim_core::{AttitudeState, LinearControl, MassiveBody, OrbitalBody, PhysicsSet};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    keys::{InputMap, KeysApp},
+    remote::local_control,
+    ship::{MassProperties, PlayerShip, SasTarget, aero::aero_drag},
+};
+
+/// Standard gravity, m/s^2.
+pub const G0: f64 = 9.806_65;
+
+/// What a drive can see.  Positions and velocities are relative to the
+/// earth, in km and km/s.
+#[allow(dead_code)]
+pub struct PropulsionContext {
+    /// The sim time, and the length of this step, in seconds.
+    pub time: f64,
+    pub dt: f64,
+    pub pos: Vector3<f64>,
+    pub vel: Vector3<f64>,
+    pub q_bw: UnitQuaternion<f64>,
+    /// The craft's mass, in kg.
+    pub mass: f64,
+    /// The earth's GM, km^3/s^2.
+    pub gm: f64,
+    /// The SAS target's position and velocity, if there is one.
+    pub target: Option<(Vector3<f64>, Vector3<f64>)>,
+}
+
+/// What a drive asks for, each step.
+#[derive(Clone, Debug)]
+pub enum PropulsionCommand {
+    Idle,
+    /// Accelerate, in m/s^2, world frame, whatever the craft's attitude.
+    Accel(Vector3<f64>),
+    /// Jump to a new position and velocity, relative to the earth, once the
+    /// pilot confirms.  Until then (or until it is called off), the drive
+    /// isn't asked again.
+    Jump {
+        pos: Vector3<f64>,
+        vel: Vector3<f64>,
+    },
+}
+
+/// A drive.
+pub trait PropulsionModel: Send + Sync {
+    /// What to call the drive, on the screen.
+    fn name(&self) -> &str;
+
+    /// What the drive does this step.
+    fn step(&mut self, context: &PropulsionContext) -> PropulsionCommand;
+}
+
+/// The drive of a craft.
+#[derive(Component)]
+#[require(PropulsionLedger)]
+pub struct Propulsion(pub Box<dyn PropulsionModel>);
+
+/// A tally of what a craft's drive has added.
+#[derive(Clone, Component, Debug, Default)]
+pub struct PropulsionLedger {
+    /// The total Δv, m/s.
+    pub dv: f64,
+    /// The change in orbital energy, J/kg, about the earth.
+    pub energy: f64,
+    pub jumps: u32,
+}
+
+/// A jump waiting for the pilot to confirm it.
+#[derive(Clone, Component, Debug)]
+pub struct PendingJump {
+    pub pos: Vector3<f64>,
+    pub vel: Vector3<f64>,
+}
+
+/// The specific orbital energy, J/kg, of a state in km and km/s.
+fn energy(pos: &Vector3<f64>, vel: &Vector3<f64>, gm: f64) -> f64 {
+    (vel.norm_squared() / 2.0 - gm / pos.norm()) * 1.0e6
+}
+
+#[derive(Default)]
+pub struct PropulsionPlugin;
+
+impl Plugin for PropulsionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_key_action("jump", &[KeyCode::KeyY]);
+        app.add_key_action("jump-cancel", &[KeyCode::KeyH]);
+        app.add_systems(Update, jump_keys.run_if(local_control));
+        app.add_systems(
+            FixedUpdate,
+            propulsion_step.after(aero_drag).before(PhysicsSet),
+        );
+        app.add_console_command(
+            "drive",
+            "drive torch [gees] | jump | off   fit a fictional drive",
+            drive_command,
+        );
+    }
+}
+
+/// `drive torch [gees]`, `drive jump`, or `drive off`: fit the ship with a
+/// drive, a 1 g torchship by default, or take it out.
+fn drive_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    ship: Query<Entity, With<PlayerShip>>,
+) -> ConsoleReply {
+    let entity = ship.single().map_err(|_| "no ship".to_string())?;
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    let drive = match words.as_slice() {
+        ["torch"] => Some(Propulsion(Box::new(Brachistochrone::new(1.0)))),
+        ["torch", gees] => Some(Propulsion(Box::new(Brachistochrone::new(parse_arg(gees)?)))),
+        ["jump"] => Some(Propulsion(Box::new(JumpDrive::default()))),
+        ["off"] => None,
+        _ => return Err(format!("Unknown drive {:?}", args.join(" "))),
+    };
+    match drive {
+        Some(drive) => {
+            commands
+                .entity(entity)
+                .insert(drive)
+                .remove::<PendingJump>();
+        }
+        None => {
+            commands
+                .entity(entity)
+                .remove::<(Propulsion, PendingJump)>();
+        }
+    }
+    Ok("ok".to_string())
+}
+
+#[allow(clippy::type_complexity)]
+fn propulsion_step(
+    mut commands: Commands,
+    time: Res<Time>,
+    sas_target: Res<SasTarget>,
+    mut crafts: Query<
+        (
+            Entity,
+            &OrbitalBody,
+            &AttitudeState,
+            &MassProperties,
+            &mut Propulsion,
+            &mut LinearControl,
+            &mut PropulsionLedger,
+        ),
+        Without<PendingJump>,
+    >,
+    earth: Query<(&OrbitalBody, &MassiveBody), With<EarthMarker>>,
+    targets: Query<&OrbitalBody>,
+) {
+    let Ok((earth, earth_mass)) = earth.single() else {
+        return;
+    };
+    let target = sas_target
+        .0
+        .and_then(|e| targets.get(e).ok())
+        .map(|t| (t.pos - earth.pos, t.vel - earth.vel));
+
+    for (entity, orbital, attitude, mass, mut drive, mut linear, mut ledger) in crafts.iter_mut() {
+        let context = PropulsionContext {
+            time: time.elapsed_secs_f64(),
+            dt: time.delta_secs_f64(),
+            pos: orbital.pos - earth.pos,
+            vel: orbital.vel - earth.vel,
+            q_bw: attitude.q_bw,
+            mass: mass.mass,
+            gm: earth_mass.gm,
+            target,
+        };
+        match drive.0.step(&context) {
+            PropulsionCommand::Idle => (),
+            PropulsionCommand::Accel(accel_w) => {
+                // m/s^2 to km/s^2.
+                linear.accel_b += attitude.q_bw.inverse_transform_vector(&accel_w) / 1000.0;
+                // The work done, per unit mass, is the power times the step.
+                ledger.dv += accel_w.norm() * context.dt;
+                ledger.energy += context.vel.dot(&accel_w) * 1000.0 * context.dt;
+            }
+            PropulsionCommand::Jump { pos, vel } => {
+                info!(
+                    "{} offers a jump of {:.1} km, {:.1} m/s: Y to go, H to call it off",
+                    drive.0.name(),
+                    (pos - context.pos).norm(),
+                    (vel - context.vel).norm() * 1000.0
+                );
+                commands.entity(entity).insert(PendingJump { pos, vel });
+            }
+        }
+    }
+}
+
+/// Y makes the pending jump, and H calls it off.
+#[allow(clippy::type_complexity)]
+fn jump_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    map: Res<InputMap>,
+    mut commands: Commands,
+    mut ship: Query<
+        (
+            Entity,
+            &mut OrbitalBody,
+            &PendingJump,
+            &mut PropulsionLedger,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<(&OrbitalBody, &MassiveBody), (With<EarthMarker>, Without<PlayerShip>)>,
+) {
+    let Ok((entity, mut orbital, jump, mut ledger)) = ship.single_mut() else {
+        return;
+    };
+    if map.just_pressed(&kb, "jump-cancel") {
+        commands.entity(entity).remove::<PendingJump>();
+        return;
+    }
+    if !map.just_pressed(&kb, "jump") {
+        return;
+    }
+    let Ok((earth, earth_mass)) = earth.single() else {
+        return;
+    };
+
+    let gm = earth_mass.gm;
+    let from_pos = orbital.pos - earth.pos;
+    let from_vel = orbital.vel - earth.vel;
+    let dv = (jump.vel - from_vel).norm() * 1000.0;
+    let de = energy(&jump.pos, &jump.vel, gm) - energy(&from_pos, &from_vel, gm);
+    orbital.pos = earth.pos + jump.pos;
+    orbital.vel = earth.vel + jump.vel;
+    ledger.dv += dv;
+    ledger.energy += de;
+    ledger.jumps += 1;
+    info!(
+        "Jumped, {:.1} m/s, {:.3e} J/kg; the drive has now put in {:.1} m/s over {} jumps",
+        dv, de, ledger.dv, ledger.jumps
+    );
+    commands.entity(entity).remove::<PendingJump>();
+}
+
+/// A torchship autopilot: a constant acceleration to the SAS target, turning
+/// over to brake so as to arrive at rest next to it.  This is flown as a
+/// guidance law rather than a fixed plan: the drive always heads for the
+/// speed, toward the target, that it could still brake from in the distance
+/// left, so it copes with the target moving, and with gravity.
+pub struct Brachistochrone {
+    /// The acceleration, m/s^2.
+    pub accel: f64,
+    /// Within this distance (km) and relative speed (m/s), it has arrived, and
+    /// the drive shuts down.
+    pub arrival: (f64, f64),
+}
+
+impl Brachistochrone {
+    pub fn new(gees: f64) -> Self {
+        Brachistochrone {
+            accel: gees * G0,
+            arrival: (1.0, 1.0),
+        }
+    }
+}
+
+impl PropulsionModel for Brachistochrone {
+    fn name(&self) -> &str {
+        "Torch drive"
+    }
+
+    fn step(&mut self, context: &PropulsionContext) -> PropulsionCommand {
+        let Some((target_pos, target_vel)) = context.target else {
+            return PropulsionCommand::Idle;
+        };
+        // In m and m/s.
+        let rel_pos = (target_pos - context.pos) * 1000.0;
+        let rel_vel = (context.vel - target_vel) * 1000.0;
+        let distance = rel_pos.norm();
+        if distance < self.arrival.0 * 1000.0 && rel_vel.norm() < self.arrival.1 {
+            return PropulsionCommand::Idle;
+        }
+
+        // Gravity pulls on both about the same, so this is all relative.
+        let wanted = rel_pos / distance.max(1.0e-9) * (2.0 * self.accel * distance).sqrt();
+        let error = wanted - rel_vel;
+        let error_norm = error.norm();
+        if error_norm == 0.0 {
+            return PropulsionCommand::Idle;
+        }
+        // Don't overshoot the wanted speed in a single step.
+        let accel = self.accel.min(error_norm / context.dt.max(1.0e-9));
+        PropulsionCommand::Accel(error / error_norm * accel)
+    }
+}
+
+/// A jump drive that offers, once, to jump the craft to 1 km above the SAS
+/// target, matching its velocity.
+#[derive(Default)]
+pub struct JumpDrive {
+    pub used: bool,
+}
+
+impl PropulsionModel for JumpDrive {
+    fn name(&self) -> &str {
+        "Jump drive"
+    }
+
+    fn step(&mut self, context: &PropulsionContext) -> PropulsionCommand {
+        match context.target {
+            Some((pos, vel)) if !self.used => {
+                self.used = true;
+                PropulsionCommand::Jump {
+                    pos: pos + pos.normalize(),
+                    vel,
+                }
+            }
+            _ => PropulsionCommand::Idle,
+        }
+    }
+}
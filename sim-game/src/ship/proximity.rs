@@ -0,0 +1,158 @@
+//! Proximity operations.
+//!
+//! Close to a target craft, what matters is where the ship is relative to it,
+//! in the target's local vertical, local horizontal frame (see
+//! `sim_astro::frames`): how far above it (R-bar), ahead of it (V-bar), and
+//! off to the side of its orbit (H-bar).  In that frame, drifting motion
+//! follows the Clohessy–Wiltshire equations, so a burn toward the target
+//! doesn't simply close the distance.
+//!
+//! When the SAS target is within `PROXIMITY_RANGE` (of its center, so it
+//! can't be a planet or a moon), the ship's state relative to it is kept in
+//! `Proximity`, along with the closest approach if nothing is done.  The
+//! console's `proximity` command prints it.
+
+use bevy::prelude::*;
+use na::{UnitQuaternion, Vector3};
+use sim_astro::frames::{Frame, Frames};
+use sim_core::orbit::clohessy_wiltshire;
+use std::f64::consts::TAU;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    ship::{PlayerShip, SasTarget},
+};
+
+/// How close, in km, a target must be for proximity operations.
+pub const PROXIMITY_RANGE: f64 = 50.0;
+
+/// The points sampled over one orbit, looking for the closest approach.
+const APPROACH_SAMPLES: usize = 360;
+
+/// The ship's state relative to a nearby target.
+#[derive(Clone, Debug)]
+pub struct RelativeState {
+    pub target: Entity,
+    /// The target's frame, frame to world, and how fast it turns, rad/s.
+    pub q_fw: UnitQuaternion<f64>,
+    pub rate: f64,
+    /// Where the ship is from the target, in m, as (radial, along, cross).
+    pub pos: Vector3<f64>,
+    /// How fast the ship is moving in the target's frame, in m/s, as (radial,
+    /// along, cross).
+    pub vel: Vector3<f64>,
+    /// The distance, in m, and how fast it is changing, in m/s.
+    pub range: f64,
+    pub range_rate: f64,
+    /// When, in seconds from now, the ship comes closest to the target over
+    /// the next orbit, drifting, and how close, in m.
+    pub closest: (f64, f64),
+}
+
+impl RelativeState {
+    /// Where the ship will be from the target, in m, after `t` seconds of
+    /// drifting.
+    pub fn drift(&self, t: f64) -> Vector3<f64> {
+        clohessy_wiltshire(&self.pos, &self.vel, self.rate, t).0
+    }
+}
+
+/// The ship's state relative to the SAS target, when it is close enough.
+#[derive(Resource, Default)]
+pub struct Proximity(pub Option<RelativeState>);
+
+#[derive(Default)]
+pub struct ProximityPlugin;
+
+impl Plugin for ProximityPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Proximity>();
+        app.add_systems(Update, update_proximity);
+        app.add_console_command(
+            "proximity",
+            "proximity   the ship's state relative to a nearby target",
+            proximity_command,
+        );
+    }
+}
+
+/// The ship's state relative to a target, if it is within range.
+fn relative_state(frames: &Frames, ship: Entity, target: Entity) -> Option<RelativeState> {
+    let frame = Frame::Lvlh(target);
+    let state = frames.state(ship, frame).ok()?;
+    if state.pos.norm() > PROXIMITY_RANGE {
+        return None;
+    }
+    let axes = frames.axes(frame).ok()?;
+
+    let (pos, vel) = (state.pos * 1000.0, state.vel * 1000.0);
+    let range = pos.norm();
+    let mut state = RelativeState {
+        target,
+        q_fw: axes.q_fw,
+        rate: axes.omega_w.norm(),
+        pos,
+        vel,
+        range,
+        range_rate: if range > 0.0 {
+            pos.dot(&vel) / range
+        } else {
+            0.0
+        },
+        closest: (0.0, range),
+    };
+
+    let period = TAU / state.rate;
+    for i in 1..=APPROACH_SAMPLES {
+        let t = period * i as f64 / APPROACH_SAMPLES as f64;
+        let distance = state.drift(t).norm();
+        if distance < state.closest.1 {
+            state.closest = (t, distance);
+        }
+    }
+    Some(state)
+}
+
+fn update_proximity(
+    sas_target: Res<SasTarget>,
+    mut proximity: ResMut<Proximity>,
+    frames: Frames,
+    ship: Query<Entity, With<PlayerShip>>,
+) {
+    proximity.0 = match (sas_target.0, ship.single()) {
+        (Some(target), Ok(ship)) => relative_state(&frames, ship, target),
+        _ => None,
+    };
+}
+
+fn proximity_command(
+    In(_): In<Vec<String>>,
+    proximity: Res<Proximity>,
+    names: Query<&Name>,
+) -> ConsoleReply {
+    let state = proximity
+        .0
+        .as_ref()
+        .ok_or_else(|| format!("The target must be a craft within {} km", PROXIMITY_RANGE))?;
+    let name = names
+        .get(state.target)
+        .map_or("target".to_string(), |name| name.to_string());
+    Ok(format!(
+        "{}: {:.1} m, {:+.2} m/s\n\
+         R {:+10.1} m {:+8.3} m/s\n\
+         V {:+10.1} m {:+8.3} m/s\n\
+         H {:+10.1} m {:+8.3} m/s\n\
+         closest {:.1} m in {:.0} s",
+        name,
+        state.range,
+        state.range_rate,
+        state.pos.x,
+        state.vel.x,
+        state.pos.y,
+        state.vel.y,
+        state.pos.z,
+        state.vel.z,
+        state.closest.1,
+        state.closest.0,
+    ))
+}
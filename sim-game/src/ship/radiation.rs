@@ -0,0 +1,186 @@
+//! Radiation dose, and upsets.
+//!
+//! A craft with a `Dosimeter` tallies the dose it takes from the radiation
+//! environment (see `sim_astro::radiation`): the absorbed dose at its
+//! electronics, and the dose equivalent to its crew, who are a little better
+//! shielded, but are harmed more by some kinds of particle than others.
+//!
+//! Energetic particles also upset electronics, by flipping a bit somewhere.
+//! Most upsets are caught and put right, and are only counted, but some leave
+//! a part of the craft broken, in the same ways a drill can start it broken (a
+//! dead thruster, or the engine out).  The upsets are random, but from a fixed
+//! seed, so that a run always goes the same way.
+//!
+//! Solar particle events are scripted with the console's `spe` command, or
+//! by a scenario.
+
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use serde::{Deserialize, Serialize};
+use sim_astro::radiation::{
+    DoseRate, Magnetosphere, SolarParticleEvent, SolarParticleEvents, dose_rate,
+};
+use sim_core::{
+    AttitudeState, MassiveBody, OrbitalBody, PhysicsModels, PostPhysicsSet, SizedBody,
+    model_enabled,
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    drill::Failure,
+    ship::{
+        engine::MainEngine,
+        rcs::{RcsRealism, RcsThrusters},
+    },
+};
+
+/// The crew's dose equivalent, in Sv, for each Gy at the electronics, by
+/// source: the cabin's extra shielding, times the particles' quality factor.
+/// The heavy ions in the cosmic rays do the most harm for their dose.
+const CREW_BELTS: f64 = 0.7 * 1.5;
+const CREW_COSMIC: f64 = 0.9 * 3.0;
+const CREW_SOLAR: f64 = 0.7 * 1.5;
+
+/// Upsets, per Gy at the electronics, by source.  Again, the heavy ions upset
+/// far more for the dose they leave.
+const UPSETS_BELTS: f64 = 2.0;
+const UPSETS_COSMIC: f64 = 50.0;
+const UPSETS_SOLAR: f64 = 2.0;
+
+/// The fraction of upsets that break something.
+const UPSET_FAILURE: f64 = 0.2;
+
+/// The seed the upsets are drawn from.
+const UPSET_SEED: u64 = 0x5eed;
+
+/// How long, in seconds, a solar particle event from the console takes to
+/// build to its peak.
+const SPE_RISE: f64 = 3600.0;
+
+/// The radiation a craft has taken.
+#[derive(Clone, Component, Debug, Default, Serialize, Deserialize)]
+pub struct Dosimeter {
+    /// The absorbed dose at the electronics, Gy.
+    pub dose: f64,
+    /// The crew's dose equivalent, Sv.
+    pub crew: f64,
+    /// The dose rate, Gy/s, in the last physics step.
+    pub rate: DoseRate,
+    /// The upsets so far, and how many of them broke something.
+    pub upsets: u32,
+    pub failures: u32,
+}
+
+#[derive(Resource)]
+struct UpsetRng(StdRng);
+
+#[derive(Default)]
+pub struct RadiationPlugin;
+
+impl Plugin for RadiationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SolarParticleEvents>();
+        app.insert_resource(UpsetRng(StdRng::seed_from_u64(UPSET_SEED)));
+        PhysicsModels::add(app, "radiation");
+        app.add_systems(
+            FixedUpdate,
+            radiation_step
+                .in_set(PostPhysicsSet)
+                .run_if(model_enabled("radiation")),
+        );
+        app.add_console_command(
+            "spe",
+            "spe <mGy/h> [decay h] [delay h]   start a solar particle event",
+            spe_command,
+        );
+    }
+}
+
+/// `spe <peak> [decay] [delay]`: a solar particle event, peaking at `peak`
+/// mGy/h at 1 AU, and dying away over `decay` hours (6 by default), starting
+/// `delay` hours from now.
+fn spe_command(
+    In(args): In<Vec<String>>,
+    fixed: Res<Time<Fixed>>,
+    mut events: ResMut<SolarParticleEvents>,
+) -> ConsoleReply {
+    let [peak, args @ ..] = args.as_slice() else {
+        return Err("spe <mGy/h> [decay h] [delay h]".to_string());
+    };
+    let arg = |i: usize, default: f64| args.get(i).map_or(Ok(default), |a| parse_arg(a));
+    let (peak, decay, delay) = (parse_arg(peak)?, arg(0, 6.0)?, arg(1, 0.0)?);
+    events.0.push(SolarParticleEvent {
+        start: fixed.elapsed_secs_f64() + delay * 3600.0,
+        rise: SPE_RISE,
+        decay: decay * 3600.0,
+        peak: peak * 1.0e-3 / 3600.0,
+    });
+    Ok("ok".to_string())
+}
+
+#[allow(clippy::type_complexity)]
+fn radiation_step(
+    time: Res<Time>,
+    mut events: ResMut<SolarParticleEvents>,
+    mut rng: ResMut<UpsetRng>,
+    mut realism: ResMut<RcsRealism>,
+    mut crafts: Query<(
+        &Name,
+        &OrbitalBody,
+        &mut Dosimeter,
+        Option<&mut RcsThrusters>,
+        Option<&mut MainEngine>,
+    )>,
+    magnetospheres: Query<(&Magnetosphere, &OrbitalBody, &AttitudeState, &SizedBody)>,
+    bodies: Query<(&MassiveBody, &OrbitalBody)>,
+) {
+    let now = time.elapsed_secs_f64();
+    let dt = time.delta_secs_f64();
+    if events.0.iter().any(|event| event.is_over(now)) {
+        events.0.retain(|event| !event.is_over(now));
+    }
+    // The sun is the most massive body.
+    let Some((_, sun)) = bodies.iter().max_by(|a, b| a.0.gm.total_cmp(&b.0.gm)) else {
+        return;
+    };
+
+    for (name, orbital, mut dosimeter, rcs, engine) in crafts.iter_mut() {
+        let rate = dose_rate(
+            &orbital.pos,
+            now,
+            &sun.pos,
+            &events,
+            magnetospheres
+                .iter()
+                .map(|(m, body, attitude, size)| (m, body, attitude, size.radii.x)),
+        );
+        dosimeter.dose += rate.total() * dt;
+        dosimeter.crew +=
+            (rate.belts * CREW_BELTS + rate.cosmic * CREW_COSMIC + rate.solar * CREW_SOLAR) * dt;
+
+        let upsets =
+            (rate.belts * UPSETS_BELTS + rate.cosmic * UPSETS_COSMIC + rate.solar * UPSETS_SOLAR)
+                * dt;
+        dosimeter.rate = rate;
+        if rng.0.random::<f64>() >= upsets {
+            continue;
+        }
+        dosimeter.upsets += 1;
+        let (Some(mut rcs), Some(mut engine)) = (rcs, engine) else {
+            info!("{}: radiation upset, corrected", name);
+            continue;
+        };
+        if !rng.0.random_bool(UPSET_FAILURE) {
+            info!("{}: radiation upset, corrected", name);
+            continue;
+        }
+        let failure = if rng.0.random_bool(0.8) {
+            Failure::DeadThruster(rng.0.random_range(0..rcs.thrusters.len().max(1) as u32))
+        } else {
+            Failure::EngineOut
+        };
+        warn!("{}: radiation upset, {:?}", name, failure);
+        failure.apply(&mut realism, &mut rcs, &mut engine);
+        dosimeter.failures += 1;
+    }
+}
@@ -0,0 +1,419 @@
+//! Reaction control system thrusters.
+//!
+//! Rather than directly commanding an angular acceleration, the ship carries a
+//! set of discrete thrusters, each with a fixed position and direction in the
+//! body frame. A commanded body force and torque are turned into per-thruster
+//! duty cycles by a small bounded least-squares allocator, and the resulting
+//! (achievable) force and torque are what actually act on the ship.
+//!
+//! How faithfully the thrusters follow those duty cycles is set by
+//! [`RcsRealism`]: either they throttle continuously (idealized), or they are
+//! on/off valves pulsed within a fixed PWM period, with a minimum on time.
+//!
+//! Thrusters with a specific impulse burn propellant from the craft's
+//! `RcsTank`, which is kept apart from the main engine's.  What each one has
+//! burned is kept, and the craft's mass goes down with it.  When the tank
+//! runs dry, the thrusters stop, and with them the attitude control: the
+//! craft keeps turning as it was.
+
+use bevy::prelude::*;
+use na::{Unit, Vector3, Vector6};
+use serde::{Deserialize, Serialize};
+
+use crate::ship::propulsion::G0;
+
+/// A single RCS thruster, fixed to the ship.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RcsThruster {
+    /// A short name, for display and debugging.
+    pub name: String,
+    /// Position of the nozzle relative to the body origin, in meters, BODY
+    /// frame.
+    pub position_b: Vector3<f64>,
+    /// Direction of the force applied to the ship (opposite the exhaust), BODY
+    /// frame.
+    pub direction_b: Unit<Vector3<f64>>,
+    /// Maximum thrust, in Newtons.
+    pub max_thrust: f64,
+}
+
+impl RcsThruster {
+    pub fn new(
+        name: impl Into<String>,
+        position_b: Vector3<f64>,
+        direction_b: Vector3<f64>,
+        max_thrust: f64,
+    ) -> Self {
+        RcsThruster {
+            name: name.into(),
+            position_b,
+            direction_b: Unit::new_normalize(direction_b),
+            max_thrust,
+        }
+    }
+
+    /// The force, in N, BODY frame, produced at full thrust.
+    pub fn force_b(&self) -> Vector3<f64> {
+        self.direction_b.into_inner() * self.max_thrust
+    }
+
+    /// The torque about the center of mass `cg_b`, in N*m, BODY frame,
+    /// produced at full thrust.
+    pub fn torque_b(&self, cg_b: &Vector3<f64>) -> Vector3<f64> {
+        (self.position_b - cg_b).cross(&self.force_b())
+    }
+}
+
+/// How the thrusters turn a duty cycle into thrust.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum RcsPulsing {
+    /// Thrust is continuously variable, and exactly follows the duty cycle.
+    #[default]
+    Continuous,
+    /// Thrusters are either off or at full thrust.  Each `period` seconds, a
+    /// thruster fires for `duty * period`, rounded to either nothing or at
+    /// least `min_on_time` (the minimum impulse bit).
+    Pwm { period: f64, min_on_time: f64 },
+}
+
+/// RCS realism settings.  A scenario can insert this before the `ShipPlugin`
+/// to override the defaults.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct RcsRealism {
+    /// Fraction of each thruster's rated thrust that is available.  Above 1,
+    /// the thrusters are boosted past their rating, as in the simple preset.
+    pub thrust_limit: f64,
+    pub pulsing: RcsPulsing,
+}
+
+impl Default for RcsRealism {
+    fn default() -> Self {
+        RcsRealism {
+            thrust_limit: 1.0,
+            pulsing: RcsPulsing::Continuous,
+        }
+    }
+}
+
+impl RcsRealism {
+    /// Typical of small bipropellant thrusters: 10 Hz PWM, 20 ms minimum
+    /// pulse.
+    pub fn pulsed() -> Self {
+        RcsRealism {
+            thrust_limit: 1.0,
+            pulsing: RcsPulsing::Pwm {
+                period: 0.1,
+                min_on_time: 0.02,
+            },
+        }
+    }
+
+    pub fn is_pulsed(&self) -> bool {
+        matches!(self.pulsing, RcsPulsing::Pwm { .. })
+    }
+}
+
+/// The full set of thrusters on a craft, along with their current duty cycles.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct RcsThrusters {
+    pub thrusters: Vec<RcsThruster>,
+    /// The commanded duty cycle (0..=1) of each thruster, parallel to
+    /// `thrusters`.
+    pub duty: Vec<f64>,
+    /// The thrust level (0..=1) each thruster actually produced over the last
+    /// physics step, parallel to `thrusters`.  This is the same as `duty` when
+    /// thrusters are continuous.
+    pub level: Vec<f64>,
+    /// Accumulated full-thrust-equivalent firing time of each thruster, in
+    /// seconds, parallel to `thrusters`.
+    pub on_time: Vec<f64>,
+    /// Specific impulse, in seconds, or None for thrusters that burn nothing.
+    #[serde(default)]
+    pub isp: Option<f64>,
+    /// The propellant each thruster has burned, in kg, parallel to
+    /// `thrusters`.
+    #[serde(default)]
+    pub used: Vec<f64>,
+    /// Time into the current PWM period, in seconds.
+    pwm_phase: f64,
+    /// The on time of each thruster for the current PWM period, in seconds,
+    /// or none before the first.
+    pwm_pulse: Vec<f64>,
+}
+
+impl RcsThrusters {
+    pub fn new(thrusters: Vec<RcsThruster>) -> Self {
+        let n = thrusters.len();
+        RcsThrusters {
+            thrusters,
+            duty: vec![0.0; n],
+            level: vec![0.0; n],
+            on_time: vec![0.0; n],
+            isp: None,
+            used: vec![0.0; n],
+            pwm_phase: 0.0,
+            pwm_pulse: Vec::new(),
+        }
+    }
+
+    /// A set of four pods, in the plane of the body origin, at a distance
+    /// `radius` (m) from the Z axis on +X, +Y, -X, -Y. Each pod has four
+    /// thrusters: two tangential (for roll and lateral translation), and two
+    /// axial (for pitch, yaw, and Z translation). This is similar to the
+    /// Apollo service module layout, and gives full six degree of freedom
+    /// control.
+    pub fn quad_pods(radius: f64, max_thrust: f64) -> Self {
+        let mut thrusters = Vec::new();
+        for (pod, radial) in [
+            ("+X", Vector3::x()),
+            ("+Y", Vector3::y()),
+            ("-X", -Vector3::x()),
+            ("-Y", -Vector3::y()),
+        ] {
+            let position = radial * radius;
+            let tangent = Vector3::z().cross(&radial);
+            for (dir, direction) in [
+                ("cw", -tangent),
+                ("ccw", tangent),
+                ("fwd", Vector3::z()),
+                ("aft", -Vector3::z()),
+            ] {
+                thrusters.push(RcsThruster::new(
+                    format!("{} {}", pod, dir),
+                    position,
+                    direction,
+                    max_thrust,
+                ));
+            }
+        }
+        RcsThrusters::new(thrusters)
+    }
+
+    pub fn with_isp(mut self, isp: f64) -> Self {
+        self.isp = Some(isp);
+        self
+    }
+
+    /// The propellant burned, in kg/s, by each thruster at full thrust.
+    pub fn mass_flow(&self, thruster: &RcsThruster) -> f64 {
+        self.isp.map_or(0.0, |isp| thruster.max_thrust / (isp * G0))
+    }
+
+    /// Latch the pulse widths for a new PWM period from the duty cycles.
+    fn latch_pulses(&mut self, period: f64, min_on_time: f64) {
+        self.pwm_pulse = self
+            .duty
+            .iter()
+            .map(|duty| {
+                let on = duty * period;
+                if on < min_on_time * 0.5 {
+                    0.0
+                } else {
+                    on.max(min_on_time)
+                }
+            })
+            .collect();
+    }
+
+    /// The total force and torque (N, N*m about `cg_b`, BODY frame) produced
+    /// over the last physics step.
+    pub fn output_b(&self, cg_b: &Vector3<f64>) -> (Vector3<f64>, Vector3<f64>) {
+        let mut force = Vector3::zeros();
+        let mut torque = Vector3::zeros();
+        for (thruster, level) in self.thrusters.iter().zip(self.level.iter()) {
+            force += thruster.force_b() * *level;
+            torque += thruster.torque_b(cg_b) * *level;
+        }
+        (force, torque)
+    }
+
+    /// Advance the thrusters by one physics step of `dt` seconds, turning the
+    /// commanded duty cycles into actual thrust `level`s, and accounting for
+    /// the firing time.  The propellant is drawn from `tank`, if there is
+    /// one, and if it runs dry partway, the levels are cut back to what it
+    /// had.  Returns the propellant burned, in kg.
+    pub fn fire(&mut self, realism: &RcsRealism, tank: Option<&mut RcsTank>, dt: f64) -> f64 {
+        match realism.pulsing {
+            RcsPulsing::Continuous => {
+                self.level.copy_from_slice(&self.duty);
+            }
+            RcsPulsing::Pwm {
+                period,
+                min_on_time,
+            } => {
+                // The first period starts with the first step.
+                if self.pwm_pulse.len() != self.duty.len() {
+                    self.latch_pulses(period, min_on_time);
+                }
+                // The fraction of this step each thruster is open: to the end
+                // of this period, and, if it ends within the step, into the
+                // next, with the pulse widths latched as it starts.
+                let start = self.pwm_phase;
+                let end = start + dt;
+                for (level, pulse) in self.level.iter_mut().zip(self.pwm_pulse.iter()) {
+                    *level = (pulse.min(end.min(period)) - start).max(0.0) / dt;
+                }
+                if end >= period {
+                    self.latch_pulses(period, min_on_time);
+                    for (level, pulse) in self.level.iter_mut().zip(self.pwm_pulse.iter()) {
+                        *level += pulse.min(end - period) / dt;
+                    }
+                }
+                self.pwm_phase = end % period;
+            }
+        }
+
+        for level in self.level.iter_mut() {
+            *level *= realism.thrust_limit;
+        }
+
+        // Saves from before the propellant was kept have nothing burned.
+        self.used.resize(self.thrusters.len(), 0.0);
+        let flows: Vec<f64> = self.thrusters.iter().map(|t| self.mass_flow(t)).collect();
+        let mut burned: f64 = flows.iter().zip(&self.level).map(|(f, l)| f * l * dt).sum();
+        if let Some(tank) = tank
+            && burned > 0.0
+        {
+            if burned > tank.propellant {
+                let share = tank.propellant / burned;
+                for level in self.level.iter_mut() {
+                    *level *= share;
+                }
+                burned = tank.propellant;
+            }
+            tank.propellant -= burned;
+        }
+        for (i, level) in self.level.iter().enumerate() {
+            self.on_time[i] += level * dt;
+            self.used[i] += flows[i] * level * dt;
+        }
+        burned
+    }
+
+    /// The largest torque (N*m, BODY frame) that can be produced about each
+    /// axis through `cg_b`, in either direction.
+    pub fn max_torque_b(&self, cg_b: &Vector3<f64>) -> Vector3<f64> {
+        let mut positive = Vector3::zeros();
+        let mut negative = Vector3::zeros();
+        for thruster in &self.thrusters {
+            let torque = thruster.torque_b(cg_b);
+            positive += torque.map(|t| t.max(0.0));
+            negative -= torque.map(|t| t.min(0.0));
+        }
+        positive.zip_map(&negative, f64::min)
+    }
+
+    /// The number of thrusters currently firing at all.
+    pub fn firing(&self) -> usize {
+        self.level.iter().filter(|l| **l > 1.0e-6).count()
+    }
+
+    /// Compute duty cycles that best produce the requested force and torque
+    /// (BODY frame, torque about `cg_b`). See [`allocate`].
+    pub fn allocate(
+        &mut self,
+        cg_b: &Vector3<f64>,
+        force_b: &Vector3<f64>,
+        torque_b: &Vector3<f64>,
+    ) {
+        allocate(&self.thrusters, cg_b, force_b, torque_b, &mut self.duty);
+    }
+}
+
+/// The RCS's own propellant, apart from the main engine's `FuelTank`.
+#[derive(Clone, Component, Debug, Serialize, Deserialize)]
+pub struct RcsTank {
+    /// The propellant left, in kg.
+    pub propellant: f64,
+    /// The most it holds, in kg.
+    pub capacity: f64,
+}
+
+impl RcsTank {
+    pub fn full(capacity: f64) -> Self {
+        RcsTank {
+            propellant: capacity,
+            capacity,
+        }
+    }
+
+    /// How full it is, 0..=1.
+    pub fn fraction(&self) -> f64 {
+        if self.capacity > 0.0 {
+            self.propellant / self.capacity
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_dry(&self) -> bool {
+        self.propellant <= 0.0
+    }
+}
+
+/// The force and torque the pilot (or an autopilot) is asking of the RCS.
+/// Units are N and N*m, in the BODY frame.
+#[derive(Clone, Component, Debug, Default)]
+pub struct RcsCommand {
+    pub force_b: Vector3<f64>,
+    pub torque_b: Vector3<f64>,
+}
+
+/// Number of Gauss-Seidel sweeps made by the allocator.
+const ALLOCATE_SWEEPS: usize = 32;
+
+/// Lever arm, in meters, used to weight force errors against torque errors.
+const ALLOCATE_LEVER: f64 = 1.0;
+
+/// A small penalty on each unit of duty, so that opposing thrusters aren't
+/// fired against each other.
+const ALLOCATE_FUEL_WEIGHT: f64 = 1.0e-3;
+
+/// Solve for per-thruster duty cycles `u` in 0..=1 minimizing
+/// `|A u - w|^2 + λ Σu`, where each column of `A` is a thruster's
+/// (force, torque) wrench, and `w` is the requested (force, torque).  Torques
+/// are about the center of mass `cg_b`.
+///
+/// This uses projected coordinate descent, which is simple, deterministic, and
+/// converges quickly for the small number of thrusters we have. When the
+/// request can't be met (saturated thrusters, or a direction the layout can't
+/// produce), the result is the nearest achievable wrench.
+pub fn allocate(
+    thrusters: &[RcsThruster],
+    cg_b: &Vector3<f64>,
+    force_b: &Vector3<f64>,
+    torque_b: &Vector3<f64>,
+    duty: &mut [f64],
+) {
+    assert_eq!(thrusters.len(), duty.len());
+
+    let wrench = |f: &Vector3<f64>, t: &Vector3<f64>| {
+        let f = f * ALLOCATE_LEVER;
+        Vector6::new(f.x, f.y, f.z, t.x, t.y, t.z)
+    };
+    let columns: Vec<Vector6<f64>> = thrusters
+        .iter()
+        .map(|t| wrench(&t.force_b(), &t.torque_b(cg_b)))
+        .collect();
+    let scale = columns.iter().map(|c| c.norm()).fold(0.0, f64::max);
+
+    duty.fill(0.0);
+    let target = wrench(force_b, torque_b);
+    if scale == 0.0 || target.norm() == 0.0 {
+        return;
+    }
+    let penalty = ALLOCATE_FUEL_WEIGHT * scale * scale;
+
+    let mut residual = target;
+    for _ in 0..ALLOCATE_SWEEPS {
+        for (column, u) in columns.iter().zip(duty.iter_mut()) {
+            let norm2 = column.norm_squared();
+            if norm2 == 0.0 {
+                continue;
+            }
+            let next = (*u + (column.dot(&residual) - penalty) / norm2).clamp(0.0, 1.0);
+            residual -= column * (next - *u);
+            *u = next;
+        }
+    }
+}
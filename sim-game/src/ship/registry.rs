@@ -0,0 +1,219 @@
+//! The crafts' names and ids.
+//!
+//! Every craft gets a `CraftId` when it first appears, numbered from 1 in the
+//! order they do, and keeps it for as long as it lasts: through renaming,
+//! switching, docking, and saving and loading, as snapshots keep the ids along
+//! with the names.  The `VesselRegistry` has each craft by its id, with its
+//! name, and its entity while it is still about; a craft that is gone keeps
+//! its entry, so that what was said of it can still be put to a name.
+//!
+//! Wherever a craft is named, as in `focus`, `target` or `rename`, it can be
+//! by its name, or by its id, as `#2`.
+//!
+//! - `crafts`: every craft there has been, by id.
+//! - `rename <craft> <name>`: give a craft a new name.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    ship::Craft,
+};
+
+/// A craft's id, kept for its life.
+#[derive(Clone, Copy, Component, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CraftId(pub u64);
+
+impl std::fmt::Display for CraftId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl CraftId {
+    /// The id in `text`, if it is one, as `#2`.
+    pub fn parse(text: &str) -> Option<Self> {
+        text.strip_prefix('#')?.parse().ok().map(CraftId)
+    }
+}
+
+/// A craft, as the registry has it.
+#[derive(Clone, Debug)]
+pub struct Vessel {
+    pub name: String,
+    /// The craft, unless it is gone.
+    pub entity: Option<Entity>,
+}
+
+/// Every craft there has been, by id.
+#[derive(Resource, Clone, Debug)]
+pub struct VesselRegistry {
+    pub vessels: BTreeMap<CraftId, Vessel>,
+    /// The id the next new craft gets.
+    next: u64,
+}
+
+impl Default for VesselRegistry {
+    fn default() -> Self {
+        VesselRegistry {
+            vessels: BTreeMap::new(),
+            next: 1,
+        }
+    }
+}
+
+impl VesselRegistry {
+    /// The craft `text` names, by id or by name, if it is still about.
+    pub fn find(&self, text: &str) -> Option<(CraftId, Entity)> {
+        let found = match CraftId::parse(text) {
+            Some(id) => self.vessels.get_key_value(&id),
+            None => self
+                .vessels
+                .iter()
+                .find(|(_, v)| v.entity.is_some() && v.name.eq_ignore_ascii_case(text)),
+        };
+        found.and_then(|(id, vessel)| Some((*id, vessel.entity?)))
+    }
+
+    /// The id of the craft `entity`, if it is one.
+    pub fn id_of(&self, entity: Entity) -> Option<CraftId> {
+        self.vessels
+            .iter()
+            .find(|(_, v)| v.entity == Some(entity))
+            .map(|(id, _)| *id)
+    }
+
+    /// The craft `entity` as `#2 Name`, if it is one.
+    pub fn label(&self, entity: Entity) -> Option<String> {
+        let id = self.id_of(entity)?;
+        Some(format!("{} {}", id, self.vessels[&id].name))
+    }
+
+    /// Put `entity` down as the craft `id`, named `name`.
+    fn enter(&mut self, id: CraftId, name: &str, entity: Entity) {
+        // A craft given another id, as by a snapshot, leaves its old one.
+        self.vessels
+            .retain(|other, v| *other == id || v.entity != Some(entity));
+        self.vessels.insert(
+            id,
+            Vessel {
+                name: name.to_string(),
+                entity: Some(entity),
+            },
+        );
+        self.next = self.next.max(id.0 + 1);
+    }
+}
+
+/// Whether `text` names the body `name`, with the craft id `id`, if any: by
+/// the id, as `#2`, or by the name, in any case.
+pub fn named(text: &str, name: &str, id: Option<&CraftId>) -> bool {
+    match CraftId::parse(text) {
+        Some(wanted) => id == Some(&wanted),
+        None => name.eq_ignore_ascii_case(text),
+    }
+}
+
+#[derive(Default)]
+pub struct RegistryPlugin;
+
+impl Plugin for RegistryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VesselRegistry>();
+        // Last, so that crafts spawned anywhere in a frame have their ids
+        // before the next one's physics.
+        app.add_systems(Last, register_crafts);
+        app.add_console_command(
+            "crafts",
+            "crafts   every craft there has been, by id",
+            crafts_command,
+        );
+        app.add_console_command(
+            "rename",
+            "rename <craft> <name>   rename a craft",
+            rename_command,
+        );
+    }
+}
+
+/// Give new crafts ids, and keep the registry up with the crafts' names, and
+/// ids from snapshots, and those gone.
+#[allow(clippy::type_complexity)]
+fn register_crafts(
+    mut commands: Commands,
+    mut registry: ResMut<VesselRegistry>,
+    new: Query<(Entity, &Name), (With<Craft>, Without<CraftId>)>,
+    changed: Query<(Entity, &CraftId, &Name), Or<(Changed<CraftId>, Changed<Name>)>>,
+    mut removed: RemovedComponents<CraftId>,
+) {
+    for entity in removed.read() {
+        for vessel in registry.vessels.values_mut() {
+            if vessel.entity == Some(entity) {
+                vessel.entity = None;
+            }
+        }
+    }
+    for (entity, id, name) in changed.iter() {
+        registry.enter(*id, name, entity);
+    }
+    // In the order they were spawned, so that a run numbers them the same way
+    // every time.
+    let mut new: Vec<_> = new.iter().collect();
+    new.sort_by_key(|(entity, _)| *entity);
+    for (entity, name) in new {
+        let id = CraftId(registry.next);
+        registry.enter(id, name, entity);
+        commands.entity(entity).insert(id);
+    }
+}
+
+fn crafts_command(In(args): In<Vec<String>>, registry: Res<VesselRegistry>) -> ConsoleReply {
+    if !args.is_empty() {
+        return Err("crafts".to_string());
+    }
+    if registry.vessels.is_empty() {
+        return Ok("No crafts".to_string());
+    }
+    Ok(registry
+        .vessels
+        .iter()
+        .map(|(id, vessel)| {
+            let gone = if vessel.entity.is_some() {
+                ""
+            } else {
+                " (gone)"
+            };
+            format!("{} {}{}", id, vessel.name, gone)
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// `rename <craft> <name>` renames a craft, by its name or id.
+fn rename_command(
+    In(args): In<Vec<String>>,
+    mut registry: ResMut<VesselRegistry>,
+    mut names: Query<&mut Name>,
+) -> ConsoleReply {
+    let [craft, name] = args.as_slice() else {
+        return Err("rename <craft> <name>".to_string());
+    };
+    let (id, entity) = registry
+        .find(craft)
+        .ok_or_else(|| format!("No such craft: {:?}, try crafts", craft))?;
+    if CraftId::parse(name).is_some() {
+        return Err(format!("{:?} would read as an id", name));
+    }
+    if names.iter().any(|n| n.as_str().eq_ignore_ascii_case(name)) {
+        return Err(format!("There is already a {:?}", name));
+    }
+    let mut current = names
+        .get_mut(entity)
+        .map_err(|_| format!("No such craft: {:?}", craft))?;
+    let old = current.to_string();
+    *current = Name::new(name.clone());
+    registry.enter(id, name, entity);
+    Ok(format!("{} {} is now {}", id, old, name))
+}
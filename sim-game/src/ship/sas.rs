@@ -0,0 +1,140 @@
+//! The stability assist (SAS).
+//!
+//! With the RCS in manual, the stability assist holds the craft still
+//! whenever no rotation key is held.  On letting go, it stops the turn, and
+//! holds the attitude it comes to rest at, until the keys turn it again.  The
+//! pointing modes, hold, and fly-by-wire are separate from this, and go on
+//! working as before.
+//!
+//! A steady torque, such as the gravity gradient (see `gravity_gradient`),
+//! or an engine pushing off its center of mass, leaves the controller
+//! standing a little off the attitude it is holding, or in the pulsed
+//! thrusters' deadband, firing to get back to it.  So the error is also
+//! integrated into a trim, a torque that takes the disturbance's place.  The
+//! trim is kept while the keys are turning the craft, as the disturbance
+//! likely still is there, and dropped when the assist is turned off.
+//!
+//! - F: toggle the assist on the player's craft.
+//! - `sas`: whether it is on, and the trim.
+//! - `sas on|off`: switch it.
+
+use bevy::prelude::*;
+use na::Vector3;
+use serde::{Deserialize, Serialize};
+use sim_core::{AttitudeController, AttitudeState};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply},
+    keys::{InputMap, KeysApp},
+    remote::local_control,
+    ship::{HoldAttitude, PlayerShip, rcs_keys},
+};
+
+/// Below this rate, in rad/s, the turn has stopped, and the attitude is
+/// captured to be held.
+const CAPTURE_RATE: f64 = 0.005;
+
+/// The integral gain of the trim, (rad/s^2) / (rad*s).  The PD gains of the
+/// ship's controller take this up to a little under 0.225 before the loop
+/// goes unstable.
+const TRIM_GAIN: f64 = 0.05;
+
+/// The largest trim, as a share of the controller's torque about each axis,
+/// so that there is always some left to hold with.
+const TRIM_LIMIT: f64 = 0.5;
+
+/// The stability assist, and the trim it has built up, in N*m, BODY frame.
+#[derive(Clone, Component, Debug, Default, Serialize, Deserialize)]
+pub struct StabilityAssist {
+    pub on: bool,
+    pub trim_b: Vector3<f64>,
+}
+
+impl StabilityAssist {
+    pub fn set(&mut self, on: bool) {
+        self.on = on;
+        if !on {
+            self.trim_b = Vector3::zeros();
+        }
+    }
+
+    /// The torque, BODY frame, to hold the craft, with no keys held: first
+    /// stop it turning, then hold where it came to rest, trimmed.  `dt` is
+    /// how long, in seconds, the last error was there for.
+    pub fn torque_b(
+        &mut self,
+        hold: &mut HoldAttitude,
+        state: &AttitudeState,
+        inertia_b: &Vector3<f64>,
+        controller: &AttitudeController,
+        dt: f64,
+    ) -> Vector3<f64> {
+        if hold.0.is_none() && state.omega_b.norm() > CAPTURE_RATE {
+            return controller.rate_torque_b(&state.omega_b, inertia_b, &Vector3::zeros());
+        }
+        let target = *hold.0.get_or_insert(state.q_bw);
+        let error_b = AttitudeController::error_b(&state.q_bw, &target);
+        let limit = controller.max_torque * TRIM_LIMIT;
+        self.trim_b = (self.trim_b + inertia_b.component_mul(&error_b) * (TRIM_GAIN * dt))
+            .zip_map(&limit, |trim, limit| trim.clamp(-limit, limit));
+        controller.torque_b(
+            &state.q_bw,
+            &state.omega_b,
+            inertia_b,
+            &target,
+            &Vector3::zeros(),
+        ) + self.trim_b
+    }
+
+    /// A line for the HUD.
+    pub fn status(&self) -> String {
+        if self.on {
+            format!(
+                "SAS: on, trim {:.2} {:.2} {:.2} N*m",
+                self.trim_b.x, self.trim_b.y, self.trim_b.z
+            )
+        } else {
+            "SAS: off".to_string()
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SasPlugin;
+
+impl Plugin for SasPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_key_action("sas", &[KeyCode::KeyF]);
+        app.add_systems(Update, sas_keys.before(rcs_keys).run_if(local_control));
+        app.add_console_command("sas", "sas [on|off]   the stability assist", sas_command);
+    }
+}
+
+fn sas_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    map: Res<InputMap>,
+    mut sas: Query<&mut StabilityAssist, With<PlayerShip>>,
+) {
+    if map.just_pressed(&kb, "sas")
+        && let Ok(mut sas) = sas.single_mut()
+    {
+        let on = !sas.on;
+        sas.set(on);
+    }
+}
+
+fn sas_command(
+    In(args): In<Vec<String>>,
+    mut sas: Query<&mut StabilityAssist, With<PlayerShip>>,
+) -> ConsoleReply {
+    let mut sas = sas
+        .single_mut()
+        .map_err(|_| "The ship has no stability assist".to_string())?;
+    match args.as_slice() {
+        [] => {}
+        [on] if on == "on" => sas.set(true),
+        [off] if off == "off" => sas.set(false),
+        _ => return Err("sas [on|off]".to_string()),
+    }
+    Ok(sas.status())
+}
@@ -0,0 +1,188 @@
+//! The craft's sensors.
+//!
+//! The sim knows exactly where everything is, and which way it points, but a
+//! craft only knows what its sensors tell it, which the navigation filter
+//! (see `nav`) makes the best of.  Each physics step, every craft with
+//! `Sensors` takes its readings from the true state:
+//!
+//! - The gyros measure the body rate, with a bias that wanders, and noise.
+//! - The accelerometers measure the acceleration from everything but gravity
+//!   (thrust, drag, sunlight), which a craft in free fall can't feel, also
+//!   with a wandering bias, and noise.
+//! - The star tracker gives the attitude, every so often, to a few arc
+//!   seconds.
+//! - The position fix, like GPS, gives the position and velocity, every so
+//!   often, to some meters, and a fraction of a meter per second.
+//!
+//! The noise is random, but from a fixed seed, so that a run always goes the
+//! same way.
+
+use bevy::prelude::*;
+use na::{UnitQuaternion, Vector3};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use sim_core::{AttitudeState, LinearControl, OrbitalBody, PostPhysicsSet};
+
+/// The seed the sensor noise is drawn from.
+const SENSOR_SEED: u64 = 0x5e_75_04;
+
+/// A gyro, or an accelerometer: noise on each sample, and a bias that
+/// wanders.
+#[derive(Clone, Debug)]
+pub struct Inertial {
+    /// The white noise density, in units/s^0.5 (rad/s, or m/s^2, per root
+    /// Hz).  Each sample's noise is this over the root of the step.
+    pub noise: f64,
+    /// How fast the bias wanders, in units/s^0.5: its random walk.
+    pub bias_walk: f64,
+    /// The bias now, BODY frame.  The filter doesn't get to see this.
+    pub bias_b: Vector3<f64>,
+}
+
+/// A sensor that gives a reading every so often.
+#[derive(Clone, Debug)]
+pub struct Periodic {
+    /// The time, in seconds, between readings.
+    pub interval: f64,
+    /// The standard deviation of each reading's error, for each axis.
+    pub sigma: f64,
+    /// The sim time of the last reading.
+    pub last: Option<f64>,
+}
+
+impl Periodic {
+    fn due(&mut self, now: f64) -> bool {
+        if self.last.is_some_and(|last| now - last < self.interval) {
+            return false;
+        }
+        self.last = Some(now);
+        true
+    }
+}
+
+/// What the sensors read in the last physics step.
+#[derive(Clone, Debug, Default)]
+pub struct Readings {
+    /// The body rate, rad/s, BODY frame.
+    pub omega_b: Vector3<f64>,
+    /// The acceleration, in m/s^2, BODY frame, less gravity.
+    pub accel_b: Vector3<f64>,
+    /// The attitude, body to world, if the star tracker gave one.
+    pub q_bw: Option<UnitQuaternion<f64>>,
+    /// The position, km, and velocity, km/s, world frame, if there was a
+    /// fix.
+    pub fix: Option<(Vector3<f64>, Vector3<f64>)>,
+}
+
+/// A craft's sensors, and what they read last.
+#[derive(Clone, Component, Debug)]
+pub struct Sensors {
+    pub gyro: Inertial,
+    pub accelerometer: Inertial,
+    /// The error is in radians, about each axis.
+    pub star_tracker: Periodic,
+    /// The error is in km; the velocity's is `fix_velocity_sigma`.
+    pub fix: Periodic,
+    /// The standard deviation of the fix's velocity, in km/s.
+    pub fix_velocity_sigma: f64,
+    pub readings: Readings,
+}
+
+impl Default for Sensors {
+    /// Sensors like a small craft's: tactical grade gyros, about 20 deg/hr of
+    /// bias, a star tracker good to 4 arc seconds, and a GPS receiver.
+    fn default() -> Self {
+        Sensors {
+            gyro: Inertial {
+                noise: 3.0e-5,
+                bias_walk: 1.0e-7,
+                bias_b: Vector3::new(1.0e-4, -0.6e-4, 0.4e-4),
+            },
+            accelerometer: Inertial {
+                noise: 1.0e-4,
+                bias_walk: 1.0e-6,
+                bias_b: Vector3::new(-1.0e-3, 0.5e-3, 2.0e-3),
+            },
+            star_tracker: Periodic {
+                interval: 1.0,
+                sigma: 2.0e-5,
+                last: None,
+            },
+            fix: Periodic {
+                interval: 1.0,
+                sigma: 0.01,
+                last: None,
+            },
+            fix_velocity_sigma: 1.0e-4,
+            readings: Readings::default(),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub(crate) struct SensorRng(StdRng);
+
+impl SensorRng {
+    /// A standard normal draw, by Box-Muller.
+    fn normal(&mut self) -> f64 {
+        let u: f64 = 1.0 - self.0.random::<f64>();
+        let v: f64 = self.0.random();
+        (-2.0 * u.ln()).sqrt() * (std::f64::consts::TAU * v).cos()
+    }
+
+    /// Standard normal draws about each axis, times `sigma`.
+    fn vector(&mut self, sigma: f64) -> Vector3<f64> {
+        Vector3::new(self.normal(), self.normal(), self.normal()) * sigma
+    }
+}
+
+#[derive(Default)]
+pub struct SensorsPlugin;
+
+impl Plugin for SensorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SensorRng(StdRng::seed_from_u64(SENSOR_SEED)));
+        app.add_systems(FixedUpdate, sense.after(PostPhysicsSet));
+    }
+}
+
+/// Take this step's readings from the true state.
+pub(crate) fn sense(
+    time: Res<Time>,
+    mut rng: ResMut<SensorRng>,
+    mut crafts: Query<(
+        &OrbitalBody,
+        &AttitudeState,
+        Option<&LinearControl>,
+        &mut Sensors,
+    )>,
+) {
+    let dt = time.delta_secs_f64();
+    if dt <= 0.0 {
+        return;
+    }
+    let now = time.elapsed_secs_f64();
+    for (orbital, attitude, linear, mut sensors) in crafts.iter_mut() {
+        let sensors = &mut *sensors;
+        for inertial in [&mut sensors.gyro, &mut sensors.accelerometer] {
+            inertial.bias_b += rng.vector(inertial.bias_walk * dt.sqrt());
+        }
+
+        sensors.readings.omega_b =
+            attitude.omega_b + sensors.gyro.bias_b + rng.vector(sensors.gyro.noise / dt.sqrt());
+        // km/s^2 to m/s^2.
+        let accel_b = linear.map_or(Vector3::zeros(), |l| l.accel_b * 1000.0);
+        sensors.readings.accel_b = accel_b
+            + sensors.accelerometer.bias_b
+            + rng.vector(sensors.accelerometer.noise / dt.sqrt());
+
+        sensors.readings.q_bw = sensors.star_tracker.due(now).then(|| {
+            attitude.q_bw * UnitQuaternion::from_scaled_axis(rng.vector(sensors.star_tracker.sigma))
+        });
+        sensors.readings.fix = sensors.fix.due(now).then(|| {
+            (
+                orbital.pos + rng.vector(sensors.fix.sigma),
+                orbital.vel + rng.vector(sensors.fix_velocity_sigma),
+            )
+        });
+    }
+}
@@ -0,0 +1,229 @@
+//! Staging, and the Δv budget.
+//!
+//! A craft can carry more than one stage.  The stage firing now is the craft's
+//! own `MainEngine` and `FuelTank`; the ones still to come are in its
+//! `Staging`, next first, each with its engine, its tank, and the dry mass
+//! that goes with it.  Staging drops the spent stage, its dry mass and
+//! whatever propellant is left in it, and brings up the next one at the same
+//! throttle.  The last stage is never dropped.
+//!
+//! The Δv budget is the rocket equation for each stage in turn, from the mass
+//! the craft will have when that stage lights.
+//!
+//! - Space: stage.
+//! - `stage`: the stages, and the Δv in each.
+//! - `stage now`: stage.
+//! - `stage add <dry kg> <propellant kg> <thrust kN> <isp s>`: put a new
+//!   stage under the present one, to fire first.
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    keys::{InputMap, KeysApp},
+    remote::local_control,
+    ship::{
+        MassProperties, PlayerShip,
+        engine::{FuelTank, MainEngine},
+    },
+};
+
+/// A stage waiting its turn.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Stage {
+    /// The mass, in kg, dropped along with its tank when it is spent.
+    pub dry_mass: f64,
+    pub engine: MainEngine,
+    pub tank: FuelTank,
+}
+
+/// The stages under a craft's present one.
+#[derive(Clone, Component, Debug, Default, Serialize, Deserialize)]
+pub struct Staging {
+    /// The present stage's dry mass, in kg, dropped with it.  Nothing is
+    /// dropped from the last stage, so this is only used while there are
+    /// more to come.
+    pub dry_mass: f64,
+    /// The stages to come, next first.
+    pub next: Vec<Stage>,
+}
+
+/// The Δv, in m/s, in each of a craft's stages, the present one first, for a
+/// craft of `mass` kg.  A stage with no tank has as much as it likes.
+pub fn budget(
+    engine: &MainEngine,
+    tank: Option<&FuelTank>,
+    staging: Option<&Staging>,
+    mass: f64,
+) -> Vec<f64> {
+    let mut budget = vec![tank.map_or(f64::INFINITY, |tank| tank.delta_v(engine, mass))];
+    let Some(staging) = staging else {
+        return budget;
+    };
+    let mut mass = mass - tank.map_or(0.0, |tank| tank.propellant) - staging.dry_mass;
+    for stage in &staging.next {
+        budget.push(stage.tank.delta_v(&stage.engine, mass));
+        mass -= stage.tank.propellant + stage.dry_mass;
+    }
+    budget
+}
+
+impl Staging {
+    /// Drop the present stage, and bring up the next.  Returns the mass
+    /// dropped, in kg.
+    pub fn stage(
+        &mut self,
+        engine: &mut MainEngine,
+        tank: &mut FuelTank,
+        mass: &mut MassProperties,
+    ) -> Result<f64, String> {
+        if self.next.is_empty() {
+            return Err("No more stages".to_string());
+        }
+        let next = self.next.remove(0);
+        let dropped = self.dry_mass + tank.propellant;
+        // Taken evenly from everywhere, as the engine burns it.
+        let scale = (mass.mass - dropped) / mass.mass;
+        mass.mass -= dropped;
+        mass.inertia_b *= scale;
+        *engine = MainEngine {
+            throttle: engine.throttle,
+            autothrottle: engine.autothrottle,
+            ..next.engine
+        };
+        *tank = next.tank;
+        self.dry_mass = next.dry_mass;
+        Ok(dropped)
+    }
+
+    /// Put a new stage under the present one, adding its mass to the craft.
+    pub fn add_under(
+        &mut self,
+        stage: Stage,
+        engine: &mut MainEngine,
+        tank: &mut FuelTank,
+        mass: &mut MassProperties,
+    ) {
+        let added = stage.dry_mass + stage.tank.propellant;
+        let scale = (mass.mass + added) / mass.mass;
+        mass.mass += added;
+        mass.inertia_b *= scale;
+        self.next.insert(
+            0,
+            Stage {
+                dry_mass: self.dry_mass,
+                engine: engine.clone(),
+                tank: tank.clone(),
+            },
+        );
+        *engine = stage.engine;
+        *tank = stage.tank;
+        self.dry_mass = stage.dry_mass;
+    }
+}
+
+#[derive(Default)]
+pub struct StagingPlugin;
+
+impl Plugin for StagingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_key_action("stage", &[KeyCode::Space]);
+        app.add_systems(Update, staging_keys.run_if(local_control));
+        app.add_console_command(
+            "stage",
+            "stage [now | add <dry kg> <propellant kg> <thrust kN> <isp s>]   show or change the ship's stages",
+            stage_command,
+        );
+    }
+}
+
+fn staging_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    map: Res<InputMap>,
+    mut ship: Query<
+        (
+            &Name,
+            &mut Staging,
+            &mut MainEngine,
+            &mut FuelTank,
+            &mut MassProperties,
+        ),
+        With<PlayerShip>,
+    >,
+) {
+    if !map.just_pressed(&kb, "stage") {
+        return;
+    }
+    let Ok((name, mut staging, mut engine, mut tank, mut mass)) = ship.single_mut() else {
+        return;
+    };
+    match staging.stage(&mut engine, &mut tank, &mut mass) {
+        Ok(dropped) => info!("{}: staged, dropping {:.0} kg", name, dropped),
+        Err(e) => warn!("{}: {}", name, e),
+    }
+}
+
+fn stage_command(
+    In(args): In<Vec<String>>,
+    mut ship: Query<
+        (
+            &mut Staging,
+            &mut MainEngine,
+            &mut FuelTank,
+            &mut MassProperties,
+        ),
+        With<PlayerShip>,
+    >,
+) -> ConsoleReply {
+    let Ok((mut staging, mut engine, mut tank, mut mass)) = ship.single_mut() else {
+        return Err("no ship".to_string());
+    };
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    match words.as_slice() {
+        [] => {}
+        ["now"] => {
+            let dropped = staging.stage(&mut engine, &mut tank, &mut mass)?;
+            return Ok(format!("dropped {:.0} kg", dropped));
+        }
+        ["add", dry, propellant, thrust, isp] => {
+            let stage = Stage {
+                dry_mass: parse_arg(dry)?,
+                // kN to N.
+                engine: MainEngine::new(parse_arg(thrust)? * 1000.0).with_isp(parse_arg(isp)?),
+                tank: FuelTank::full(parse_arg(propellant)?),
+            };
+            staging.add_under(stage, &mut engine, &mut tank, &mut mass);
+        }
+        _ => {
+            return Err(
+                "stage [now | add <dry kg> <propellant kg> <thrust kN> <isp s>]".to_string(),
+            );
+        }
+    }
+
+    let budget = budget(&engine, Some(&tank), Some(&staging), mass.mass);
+    let stages = std::iter::once((&*engine, &*tank)).chain(
+        staging
+            .next
+            .iter()
+            .map(|stage| (&stage.engine, &stage.tank)),
+    );
+    let mut lines = Vec::new();
+    for (i, ((engine, tank), dv)) in stages.zip(&budget).enumerate() {
+        lines.push(format!(
+            "{}: {:.0} kN, isp {:.0} s, {:.0} kg propellant, {:.0} m/s",
+            i + 1,
+            engine.max_thrust / 1000.0,
+            engine.isp.unwrap_or(0.0),
+            tank.propellant,
+            dv
+        ));
+    }
+    lines.push(format!(
+        "total {:.0} m/s, {:.0} kg",
+        budget.iter().sum::<f64>(),
+        mass.mass
+    ));
+    Ok(lines.join("\n"))
+}
@@ -0,0 +1,130 @@
+//! Sunlight: eclipses, and solar radiation pressure.
+//!
+//! Each step, every craft with a `Sunlight` works out how much of the sun it
+//! can see past the bodies in the way (see `sim_astro::eclipse`), which the
+//! ship view lights it by, and the events log as it goes into and out of a
+//! shadow.  The light that gets through pushes on it, by the area it presents
+//! to the sun (its `Aero` areas, as with the air), and how much of the light
+//! it throws back.  That is tiny, but adds up over the days for a light craft
+//! with a lot of area.
+
+use bevy::prelude::*;
+use na::Vector3;
+use sim_astro::eclipse::{Shadow, pressure, sunlight};
+use sim_core::{
+    AttitudeState, LinearControl, MassiveBody, OrbitalBody, PhysicsModels, PhysicsSet, SizedBody,
+    model_enabled,
+};
+
+use crate::ship::{MassProperties, aero::Aero, engine::engine_fire};
+
+/// The reflectivity coefficient of a craft, unless told otherwise: 1 absorbs
+/// everything, and 2 is a mirror.
+pub const REFLECTIVITY: f64 = 1.3;
+
+/// How a craft is lit by the sun.
+#[derive(Clone, Component, Debug)]
+pub struct Sunlight {
+    pub reflectivity: f64,
+    /// The fraction of the sun's disk it sees, from 0 to 1.
+    pub fraction: f64,
+    pub shadow: Shadow,
+    /// The body whose shadow it is in, if any.
+    pub body: Option<Entity>,
+    /// The direction to the sun, world frame.
+    pub sun_w: Vector3<f64>,
+    /// The radiation pressure acceleration in the last physics step, m/s^2.
+    pub pressure: f64,
+}
+
+impl Default for Sunlight {
+    fn default() -> Self {
+        Sunlight {
+            reflectivity: REFLECTIVITY,
+            fraction: 1.0,
+            shadow: Shadow::Sunlit,
+            body: None,
+            sun_w: Vector3::x(),
+            pressure: 0.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SunlightPlugin;
+
+impl Plugin for SunlightPlugin {
+    fn build(&self, app: &mut App) {
+        PhysicsModels::add(app, "srp");
+        app.add_systems(
+            FixedUpdate,
+            (sunlight_step, solar_pressure.run_if(model_enabled("srp")))
+                .chain()
+                .after(engine_fire)
+                .before(PhysicsSet),
+        );
+    }
+}
+
+/// Work out how much of the sun each craft sees, and past which body.  The
+/// sun is the most massive body.
+fn sunlight_step(
+    mut crafts: Query<(&OrbitalBody, &mut Sunlight)>,
+    bodies: Query<(Entity, &OrbitalBody, &MassiveBody, &SizedBody), Without<Sunlight>>,
+) {
+    let Some((sun_entity, sun, _, sun_size)) =
+        bodies.iter().max_by(|a, b| a.2.gm.total_cmp(&b.2.gm))
+    else {
+        return;
+    };
+    for (orbital, mut light) in crafts.iter_mut() {
+        let (body, fraction) = bodies
+            .iter()
+            .filter(|(entity, ..)| *entity != sun_entity)
+            .map(|(entity, body, _, size)| {
+                let fraction = sunlight(
+                    &orbital.pos,
+                    &sun.pos,
+                    sun_size.radii.x,
+                    &body.pos,
+                    size.radii.x,
+                );
+                (Some(entity), fraction)
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .filter(|(_, fraction)| *fraction < 1.0)
+            .unwrap_or((None, 1.0));
+        light.fraction = fraction;
+        light.shadow = Shadow::of(fraction);
+        light.body = body;
+        light.sun_w = (sun.pos - orbital.pos).normalize();
+    }
+}
+
+/// Add the radiation pressure to each craft's linear acceleration.  This must
+/// run after the thrusters have set theirs.
+#[allow(clippy::type_complexity)]
+fn solar_pressure(
+    mut crafts: Query<(
+        &OrbitalBody,
+        &AttitudeState,
+        &MassProperties,
+        &Aero,
+        &mut Sunlight,
+        &mut LinearControl,
+    )>,
+    bodies: Query<(&OrbitalBody, &MassiveBody), Without<Sunlight>>,
+) {
+    let Some((sun, _)) = bodies.iter().max_by(|a, b| a.1.gm.total_cmp(&b.1.gm)) else {
+        return;
+    };
+    for (orbital, attitude, mass, aero, mut light, mut linear) in crafts.iter_mut() {
+        let sun_b = attitude.q_bw.inverse_transform_vector(&light.sun_w);
+        let distance = (sun.pos - orbital.pos).norm();
+        let accel =
+            pressure(distance, light.fraction) * light.reflectivity * aero.area(&sun_b) / mass.mass;
+        light.pressure = accel;
+        // Away from the sun, and m/s^2 to km/s^2.
+        linear.accel_b -= sun_b * accel / 1000.0;
+    }
+}
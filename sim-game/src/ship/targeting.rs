@@ -0,0 +1,334 @@
+//! Rendezvous targeting.
+//!
+//! The planner finds the transfer to the SAS target that leaves at a chosen
+//! time, and arrives a chosen time later, at where the target will be by then.
+//! That is Lambert's problem (see `sim_core::lambert`), about the earth, with
+//! the ship and the target both on their two-body orbits.  The Δv to leave on
+//! the transfer can be put in a maneuver node, to be flown like any other; the
+//! Δv to match the target on arrival is left to the pilot.
+//!
+//! - T: pick the next craft or body as the SAS target (with shift, the one
+//!   before).
+//! - BracketRight/BracketLeft: a longer/shorter trip.
+//! - Quote/Semicolon: leave later/earlier.
+//! - Enter: put the departure burn in a maneuver node, replacing any there.
+//!
+//! Holding shift makes the adjustments ten times bigger.  The console's
+//! `target` and `transfer` commands do the same; `target` takes a craft by
+//! its name or its id (see `registry`).
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use na::Vector3;
+use sim_astro::EarthMarker;
+use sim_core::{
+    MassiveBody, OrbitalBody,
+    lambert::lambert,
+    orbit::{OrbitFrame, propagate},
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    remote::local_control,
+    ship::{
+        PlayerShip, SasTarget,
+        maneuver::ManeuverNode,
+        registry::{CraftId, named},
+    },
+};
+
+/// How far ahead, in seconds, a new transfer leaves, and how long it takes.
+const TRANSFER_LEAD: f64 = 300.0;
+const TRANSFER_DURATION: f64 = 3600.0;
+
+/// How much each press moves the departure, in seconds, and scales the trip.
+const DEPARTURE_STEP: f64 = 60.0;
+const DURATION_STEP: f64 = 1.05;
+
+/// A transfer to the SAS target.
+#[derive(Clone, Debug)]
+pub struct Transfer {
+    /// The burn to leave on it, in m/s, as (prograde, normal, radial) at the
+    /// departure, as for a `ManeuverNode`.
+    pub departure_dv: Vector3<f64>,
+    /// The burn to match the target's velocity on arrival, in m/s, world
+    /// frame.
+    pub arrival_dv: Vector3<f64>,
+}
+
+/// The transfer being planned.
+#[derive(Resource, Clone, Debug)]
+pub struct TransferPlanner {
+    /// When the transfer leaves, as measured by `Time<Fixed>`.
+    pub departure: f64,
+    /// How long it takes, in seconds.
+    pub duration: f64,
+    /// The transfer, if there is a target, and a way to it.
+    pub transfer: Option<Transfer>,
+}
+
+impl Default for TransferPlanner {
+    fn default() -> Self {
+        TransferPlanner {
+            departure: TRANSFER_LEAD,
+            duration: TRANSFER_DURATION,
+            transfer: None,
+        }
+    }
+}
+
+impl TransferPlanner {
+    /// The departure burn as a node, armed, if there is a transfer.
+    pub fn node(&self) -> Option<ManeuverNode> {
+        self.transfer.as_ref().map(|transfer| {
+            let mut node = ManeuverNode::new(self.departure);
+            node.dv = transfer.departure_dv;
+            node.armed = true;
+            node
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct TargetingPlugin;
+
+impl Plugin for TargetingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TransferPlanner>();
+        app.add_systems(
+            Update,
+            (targeting_keys.run_if(local_control), plan_transfer).chain(),
+        );
+        app.add_console_command(
+            "target",
+            "target [name|#id|none]   show, or pick, the SAS target",
+            target_command,
+        );
+        app.add_console_command(
+            "transfer",
+            "transfer <duration s> [departure s]   plan a transfer to the target, as a node",
+            transfer_command,
+        );
+    }
+}
+
+/// Everything that can be a target, with the crafts' ids.
+type Candidates<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static Name, Option<&'static CraftId>),
+    (With<OrbitalBody>, Without<PlayerShip>),
+>;
+
+/// The candidates, in a fixed order.
+fn targets(candidates: &Candidates) -> Vec<(Entity, String)> {
+    let mut targets: Vec<_> = candidates
+        .iter()
+        .map(|(entity, name, _)| (entity, name.to_string()))
+        .collect();
+    targets.sort_by(|a, b| a.1.cmp(&b.1));
+    targets
+}
+
+fn targeting_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    fixed: Res<Time<Fixed>>,
+    mut sas_target: ResMut<SasTarget>,
+    mut planner: ResMut<TransferPlanner>,
+    mut commands: Commands,
+    ship: Query<Entity, With<PlayerShip>>,
+    candidates: Candidates,
+) {
+    let now = fixed.elapsed_secs_f64();
+    let fast = kb.pressed(KeyCode::ShiftLeft) || kb.pressed(KeyCode::ShiftRight);
+
+    if kb.just_pressed(KeyCode::KeyT) {
+        let targets = targets(&candidates);
+        let current = sas_target
+            .0
+            .and_then(|target| targets.iter().position(|(e, _)| *e == target));
+        let next = match (current, fast) {
+            (None, false) => 0,
+            (None, true) => targets.len().saturating_sub(1),
+            (Some(i), false) => (i + 1) % targets.len(),
+            (Some(i), true) => (i + targets.len() - 1) % targets.len(),
+        };
+        sas_target.0 = targets.get(next).map(|(e, _)| *e);
+    }
+
+    let step = if fast { 10.0 } else { 1.0 };
+    if kb.just_pressed(KeyCode::BracketRight) {
+        planner.duration *= DURATION_STEP.powf(step);
+    }
+    if kb.just_pressed(KeyCode::BracketLeft) {
+        planner.duration /= DURATION_STEP.powf(step);
+    }
+    if kb.just_pressed(KeyCode::Quote) {
+        planner.departure += DEPARTURE_STEP * step;
+    }
+    if kb.just_pressed(KeyCode::Semicolon) {
+        planner.departure -= DEPARTURE_STEP * step;
+    }
+    // A departure that has gone by leaves as soon as it can instead.
+    if planner.departure < now {
+        planner.departure = now + TRANSFER_LEAD;
+    }
+
+    if kb.just_pressed(KeyCode::Enter)
+        && let (Ok(entity), Some(node)) = (ship.single(), planner.node())
+    {
+        commands.entity(entity).insert(node);
+    }
+}
+
+/// The transfer from the ship to the target, leaving `departure` seconds from
+/// now, and taking `duration`, about the earth.
+fn solve(
+    departure: f64,
+    duration: f64,
+    ship: &OrbitalBody,
+    target: &OrbitalBody,
+    earth: &OrbitalBody,
+    gm: f64,
+) -> Option<Transfer> {
+    let (pos1, vel1) = propagate(
+        &(ship.pos - earth.pos),
+        &(ship.vel - earth.vel),
+        gm,
+        departure,
+    );
+    let (pos2, vel2) = propagate(
+        &(target.pos - earth.pos),
+        &(target.vel - earth.vel),
+        gm,
+        departure + duration,
+    );
+    let (departure_dv, v2) = lambert_burn(&pos1, &vel1, &pos2, duration, gm)?;
+    Some(Transfer {
+        departure_dv,
+        arrival_dv: (vel2 - v2) * 1000.0,
+    })
+}
+
+/// The burn, in m/s as (prograde, normal, radial), that takes a craft at `pos`
+/// and `vel` to `target` in `duration` seconds, about a body with the given
+/// `gm`, going around the way it already does; and the velocity it gets there
+/// with, km/s.
+pub(crate) fn lambert_burn(
+    pos: &Vector3<f64>,
+    vel: &Vector3<f64>,
+    target: &Vector3<f64>,
+    duration: f64,
+    gm: f64,
+) -> Option<(Vector3<f64>, Vector3<f64>)> {
+    let (v1, v2) = lambert(pos, target, duration, gm, &pos.cross(vel))?;
+    let dv_w = (v1 - vel) * 1000.0;
+    let frame = OrbitFrame::new(pos, vel);
+    let dv = Vector3::new(
+        dv_w.dot(&frame.prograde),
+        dv_w.dot(&frame.normal),
+        dv_w.dot(&frame.radial),
+    );
+    Some((dv, v2))
+}
+
+/// The ship, the target, and the earth, for planning.
+#[derive(SystemParam)]
+#[allow(clippy::type_complexity)]
+struct TransferBodies<'w, 's> {
+    sas_target: Res<'w, SasTarget>,
+    ship: Query<'w, 's, &'static OrbitalBody, With<PlayerShip>>,
+    bodies: Query<'w, 's, &'static OrbitalBody, Without<PlayerShip>>,
+    earth: Query<'w, 's, (Entity, &'static OrbitalBody, &'static MassiveBody), With<EarthMarker>>,
+}
+
+impl TransferBodies<'_, '_> {
+    /// The transfer the planner has set up, if there is a target other than
+    /// the earth, and a way to it.
+    fn solve(&self, planner: &TransferPlanner, now: f64) -> Option<Transfer> {
+        let ship = self.ship.single().ok()?;
+        let (earth_entity, earth, earth_mass) = self.earth.single().ok()?;
+        let target = self.sas_target.0.filter(|target| *target != earth_entity)?;
+        let target = self.bodies.get(target).ok()?;
+        solve(
+            planner.departure - now,
+            planner.duration,
+            ship,
+            target,
+            earth,
+            earth_mass.gm,
+        )
+    }
+}
+
+/// Solve the transfer for the planner as it is.
+fn plan_transfer(
+    fixed: Res<Time<Fixed>>,
+    mut planner: ResMut<TransferPlanner>,
+    bodies: TransferBodies,
+) {
+    planner.transfer = bodies.solve(&planner, fixed.elapsed_secs_f64());
+}
+
+fn target_command(
+    In(args): In<Vec<String>>,
+    mut sas_target: ResMut<SasTarget>,
+    candidates: Candidates,
+) -> ConsoleReply {
+    match args.as_slice() {
+        [] => Ok(sas_target
+            .0
+            .and_then(|target| candidates.get(target).ok())
+            .map_or("none".to_string(), |(_, name, id)| match id {
+                Some(id) => format!("{} {}", id, name),
+                None => name.to_string(),
+            })),
+        [none] if none == "none" => {
+            sas_target.0 = None;
+            Ok("ok".to_string())
+        }
+        [name] => {
+            let (entity, ..) = candidates
+                .iter()
+                .find(|(_, n, id)| named(name, n, *id))
+                .ok_or_else(|| format!("No such target: {:?}", name))?;
+            sas_target.0 = Some(entity);
+            Ok("ok".to_string())
+        }
+        _ => Err("target [name|#id|none]".to_string()),
+    }
+}
+
+/// `transfer <duration> [departure]` plans a transfer taking `duration`
+/// seconds, leaving `departure` seconds from now (five minutes by default),
+/// and puts it in the ship's node.
+fn transfer_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    fixed: Res<Time<Fixed>>,
+    mut planner: ResMut<TransferPlanner>,
+    ship: Query<Entity, With<PlayerShip>>,
+    bodies: TransferBodies,
+) -> ConsoleReply {
+    let (duration, lead) = match args.as_slice() {
+        [duration] => (parse_arg(duration)?, TRANSFER_LEAD),
+        [duration, departure] => (parse_arg(duration)?, parse_arg(departure)?),
+        _ => return Err("transfer <duration s> [departure s]".to_string()),
+    };
+    if duration <= 0.0 || lead < 0.0 {
+        return Err("The transfer must take some time, and leave from now on".to_string());
+    }
+    let now = fixed.elapsed_secs_f64();
+    planner.departure = now + lead;
+    planner.duration = duration;
+    planner.transfer = bodies.solve(&planner, now);
+    let node = planner
+        .node()
+        .ok_or_else(|| "No transfer to the target".to_string())?;
+    let entity = ship.single().map_err(|_| "no ship".to_string())?;
+    let dv = node.dv;
+    commands.entity(entity).insert(node);
+    Ok(format!(
+        "node {:.1} pro, {:.1} nor, {:.1} rad m/s",
+        dv.x, dv.y, dv.z
+    ))
+}
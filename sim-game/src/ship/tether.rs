@@ -0,0 +1,207 @@
+//! Momentum exchange.
+//!
+//! A mass driver or a spinning tether can throw a payload without any
+//! propellant, by handing it some of the thrower's own momentum: the payload
+//! gains a Δv, and the thrower is pushed back by the same impulse, at the point
+//! the payload leaves from, which also sets it turning.  A catch is the same in
+//! reverse: the payload is brought to the speed of that point, and the thrower
+//! takes up the difference.  Together these make skyhook style scenarios,
+//! where a station throws and catches crafts, and pays for it in its own orbit.
+//!
+//! A craft without `MassProperties` is taken to be too small to matter as a
+//! payload, or too big to notice as a thrower.
+//!
+//! The console's `throw` and `catch` commands send a `MomentumExchange`.
+
+use bevy::prelude::*;
+use na::Vector3;
+use sim_astro::{EarthMarker, frames::Frames};
+use sim_core::{
+    AttitudeIntegrator, AttitudeState, OrbitalBody, PhysicsSet, PostPhysicsSet, orbit::OrbitFrame,
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::MassProperties,
+};
+
+/// How the payload is exchanged.
+#[derive(Clone, Debug)]
+pub enum Exchange {
+    /// Throw the payload, with the Δv in m/s as (prograde, normal, radial) in
+    /// its orbit about the earth.
+    Throw(Vector3<f64>),
+    /// Catch the payload.
+    Catch,
+}
+
+/// A request to exchange momentum between two crafts.
+#[derive(Clone, Debug, Message)]
+pub struct MomentumExchange {
+    pub thrower: Entity,
+    pub payload: Entity,
+    pub exchange: Exchange,
+    /// Where, in m, on the thrower, the payload leaves or arrives, BODY frame.
+    pub arm_b: Vector3<f64>,
+}
+
+#[derive(Default)]
+pub struct TetherPlugin;
+
+impl Plugin for TetherPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_message::<MomentumExchange>();
+        app.add_systems(
+            FixedUpdate,
+            momentum_exchange.after(PhysicsSet).before(PostPhysicsSet),
+        );
+        app.add_console_command(
+            "throw",
+            "throw <thrower> <payload> <prograde> <normal> <radial> [<x> <y> <z>]",
+            throw_command,
+        );
+        app.add_console_command(
+            "catch",
+            "catch <thrower> <payload> [<x> <y> <z>]",
+            catch_command,
+        );
+    }
+}
+
+/// `throw <thrower> <payload> <prograde> <normal> <radial> [<x> <y> <z>]`
+/// throws the payload with a Δv, in m/s, from a point on the thrower, in m,
+/// BODY frame.
+fn throw_command(
+    In(args): In<Vec<String>>,
+    frames: Frames,
+    exchanges: MessageWriter<MomentumExchange>,
+) -> ConsoleReply {
+    exchange_command("throw", &args, &frames, exchanges)
+}
+
+/// `catch <thrower> <payload> [<x> <y> <z>]` catches it.
+fn catch_command(
+    In(args): In<Vec<String>>,
+    frames: Frames,
+    exchanges: MessageWriter<MomentumExchange>,
+) -> ConsoleReply {
+    exchange_command("catch", &args, &frames, exchanges)
+}
+
+fn exchange_command(
+    kind: &str,
+    args: &[String],
+    frames: &Frames,
+    mut exchanges: MessageWriter<MomentumExchange>,
+) -> ConsoleReply {
+    let [thrower, payload, args @ ..] = args else {
+        return Err(format!("{} needs a thrower and a payload", kind));
+    };
+    let find = |name: &String| {
+        frames
+            .find(name)
+            .ok_or_else(|| format!("No such entity: {:?}", name))
+    };
+    let values = args
+        .iter()
+        .map(|a| parse_arg(a))
+        .collect::<Result<Vec<_>, _>>()?;
+    let (exchange, arm) = match (kind, values.as_slice()) {
+        ("throw", [p, n, r, arm @ ..]) => (Exchange::Throw(Vector3::new(*p, *n, *r)), arm),
+        ("catch", arm) => (Exchange::Catch, arm),
+        _ => return Err("throw needs a prograde, normal, and radial Δv".to_string()),
+    };
+    let arm_b = match arm {
+        [] => Vector3::zeros(),
+        [x, y, z] => Vector3::new(*x, *y, *z),
+        _ => return Err("The arm needs x, y, and z".to_string()),
+    };
+    exchanges.write(MomentumExchange {
+        thrower: find(thrower)?,
+        payload: find(payload)?,
+        exchange,
+        arm_b,
+    });
+    Ok("ok".to_string())
+}
+
+#[allow(clippy::type_complexity)]
+fn momentum_exchange(
+    mut exchanges: MessageReader<MomentumExchange>,
+    mut crafts: Query<
+        (
+            &Name,
+            &mut OrbitalBody,
+            &mut AttitudeState,
+            Option<&MassProperties>,
+        ),
+        Without<EarthMarker>,
+    >,
+    earth: Query<&OrbitalBody, With<EarthMarker>>,
+    integrator: Res<AttitudeIntegrator>,
+    time: Res<Time>,
+) {
+    let earth = earth.single().ok().cloned();
+    let lead = integrator.lead(time.delta_secs_f64());
+    for exchange in exchanges.read() {
+        let Ok([thrower, payload]) = crafts.get_many_mut([exchange.thrower, exchange.payload])
+        else {
+            warn!("Momentum exchange between missing crafts");
+            continue;
+        };
+        let (thrower_name, mut thrower, mut thrower_attitude, thrower_mass) = thrower;
+        let (payload_name, mut payload, _, payload_mass) = payload;
+        let payload_mass = payload_mass.map_or(0.0, |m| m.mass);
+
+        // Where the payload leaves from, relative to the center of mass, and
+        // how fast that point is moving, in m/s.
+        let lever_b = exchange.arm_b - thrower_mass.map_or(Vector3::zeros(), |m| m.cg_b);
+        let q_bw = thrower_attitude.q_bw_now(lead);
+        let arm_w = q_bw.transform_vector(&lever_b);
+        let tip_vel = thrower.vel * 1000.0
+            + q_bw
+                .transform_vector(&thrower_attitude.omega_b)
+                .cross(&arm_w);
+
+        // The Δv, m/s world, given to the payload.
+        let dv_w = match &exchange.exchange {
+            Exchange::Throw(dv) => {
+                let Some(earth) = &earth else {
+                    continue;
+                };
+                OrbitFrame::new(&(payload.pos - earth.pos), &(payload.vel - earth.vel)).to_world(dv)
+            }
+            Exchange::Catch => {
+                // An inelastic collision at the tip, leaving aside the turn
+                // it gives the thrower.
+                let reduced = match thrower_mass {
+                    Some(m) => m.mass / (m.mass + payload_mass),
+                    None => 1.0,
+                };
+                (tip_vel - payload.vel * 1000.0) * reduced
+            }
+        };
+        payload.vel += dv_w / 1000.0;
+
+        // The thrower takes the opposite impulse, N*s.
+        if let Some(mass) = thrower_mass {
+            let impulse_w = -dv_w * payload_mass;
+            thrower.vel += impulse_w / mass.mass / 1000.0;
+            thrower_attitude.apply_angular_impulse_w(
+                &arm_w.cross(&impulse_w),
+                &mass.inertia_b,
+                lead,
+            );
+        }
+        info!(
+            "{} {} {}, {:.3} m/s",
+            thrower_name,
+            match exchange.exchange {
+                Exchange::Throw(_) => "threw",
+                Exchange::Catch => "caught",
+            },
+            payload_name,
+            dv_w.norm()
+        );
+    }
+}
@@ -0,0 +1,631 @@
+//! Flip-and-burn planning.
+//!
+//! A torchship doesn't coast on conics: it burns toward the target for the
+//! first half of the trip, flips over, and burns to brake for the second,
+//! arriving matched to the target's position and velocity.  With a constant
+//! acceleration, the two burns come out of the kinematics directly, for any
+//! trip time `T`: with `s = T / 2`, the burns `a1` and `a2` must satisfy
+//!
+//!   r_t(T) = r0 + v0 T + (3/2) a1 s^2 + (1/2) a2 s^2
+//!   v_t(T) = v0 + (a1 + a2) s
+//!
+//! The planner looks for the shortest `T` where neither burn needs more than
+//! the ship can give.  Where the target is at `T` comes from integrating the
+//! sim's own bodies forward, so the intercept is against the same ephemeris
+//! the sim will follow, moons and all.  Gravity on the ship is then put back
+//! by shooting: the plan is flown through the same bodies, and the burns are
+//! corrected, by Newton's method, until the ship arrives, with the trip time
+//! adjusted to keep the harder burn at full throttle.
+//!
+//! The ship arrives a few radii out from the target, on the near side, and
+//! further if need be to keep the target's pull well below the drive's, so
+//! that braking isn't a fight against the target's gravity.
+//!
+//! The console's `torch <body> [gees] [fly]` command plans a trip, replying
+//! with the attitude and throttle schedule, and the trip time against a range
+//! of accelerations.  With `fly`, the ship is fitted with a `FlipAndBurn`
+//! drive that follows the schedule.  See `propulsion`.
+
+use bevy::prelude::*;
+use na::{Matrix6, UnitQuaternion, Vector3, Vector6};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, SizedBody, watchdog::Frozen};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{
+        PlayerShip, point_axis_at,
+        propulsion::{
+            G0, PendingJump, Propulsion, PropulsionCommand, PropulsionContext, PropulsionModel,
+        },
+    },
+};
+
+/// The step, in seconds, the bodies are integrated with, and the shortest
+/// the ship is.
+const TORCH_STEP: f64 = 60.0;
+
+/// The ship's step, as a fraction of the free-fall time to the body that
+/// dominates where it is.  Away from everything, this is limited to
+/// `TORCH_SAMPLE`.
+const TORCH_FREE_FALL: f64 = 0.01;
+
+/// How often, in seconds, the bodies' states are kept, to be interpolated
+/// between.
+const TORCH_SAMPLE: f64 = 600.0;
+
+/// The longest trip, in seconds, that will be planned.
+const TORCH_HORIZON: f64 = 365.0 * 86400.0;
+
+/// The accelerations, in g, the trip time is shown against.
+const TORCH_TRADE_GEES: [f64; 6] = [0.01, 0.03, 0.1, 0.3, 1.0, 3.0];
+
+/// How far out, in radii of the target, the ship arrives, at least.
+const TORCH_STANDOFF: f64 = 3.0;
+
+/// How hard, as a fraction of the drive, the target may pull where the ship
+/// arrives.
+const TORCH_PULL: f64 = 0.1;
+
+/// How closely, in km and km/s, the ship must arrive for the shooting to be
+/// done.
+const TORCH_MISS: (f64, f64) = (1.0, 1.0e-3);
+
+/// One burn of a plan.
+#[derive(Clone, Debug)]
+pub struct TorchBurn {
+    /// When the burn starts and ends, in `Time<Fixed>` seconds.
+    pub start: f64,
+    pub end: f64,
+    /// The acceleration, in m/s^2, world frame.
+    pub accel_w: Vector3<f64>,
+    /// The fraction of the ship's acceleration used.
+    pub throttle: f64,
+    /// The attitude that points the engine (BODY +Z) along the burn.
+    pub q_bw: UnitQuaternion<f64>,
+}
+
+/// A planned trip.
+#[derive(Clone, Component, Debug)]
+pub struct TorchPlan {
+    pub target: String,
+    /// The ship's acceleration, in m/s^2.
+    pub accel: f64,
+    pub burns: Vec<TorchBurn>,
+    /// The total Δv, in m/s.
+    pub dv: f64,
+}
+
+impl TorchPlan {
+    pub fn arrival(&self) -> f64 {
+        self.burns.last().map_or(0.0, |b| b.end)
+    }
+}
+
+/// A drive that flies a `TorchPlan`'s schedule, open loop.
+pub struct FlipAndBurn {
+    pub burns: Vec<TorchBurn>,
+}
+
+impl PropulsionModel for FlipAndBurn {
+    fn name(&self) -> &str {
+        "Flip and burn"
+    }
+
+    fn step(&mut self, context: &PropulsionContext) -> PropulsionCommand {
+        match self
+            .burns
+            .iter()
+            .find(|b| b.start <= context.time && context.time < b.end)
+        {
+            Some(burn) => PropulsionCommand::Accel(burn.accel_w),
+            None => PropulsionCommand::Idle,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TorchPlugin;
+
+impl Plugin for TorchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command(
+            "torch",
+            "torch <body> [gees] [fly]   plan a flip-and-burn trip",
+            torch_command,
+        );
+    }
+}
+
+/// A gravitating body, for the integration.
+#[derive(Clone, Debug)]
+struct Point {
+    pos: Vector3<f64>,
+    vel: Vector3<f64>,
+    gm: f64,
+}
+
+/// The gravity, km/s^2, at `pos`, from bodies at `positions`, but `skip`.
+fn gravity(
+    positions: impl Iterator<Item = (Vector3<f64>, f64)>,
+    pos: &Vector3<f64>,
+    skip: Option<usize>,
+) -> Vector3<f64> {
+    let mut accel = Vector3::zeros();
+    for (i, (body, gm)) in positions.enumerate() {
+        if Some(i) == skip {
+            continue;
+        }
+        let rel = body - pos;
+        let r = rel.norm();
+        accel += rel * (gm / (r * r * r));
+    }
+    accel
+}
+
+/// Advance the bodies by one leapfrog step.
+fn leapfrog(bodies: &mut [Point], dt: f64) {
+    let kick = |bodies: &mut [Point]| {
+        let accels: Vec<_> = (0..bodies.len())
+            .map(|i| {
+                gravity(
+                    bodies.iter().map(|b| (b.pos, b.gm)),
+                    &bodies[i].pos,
+                    Some(i),
+                )
+            })
+            .collect();
+        for (body, a) in bodies.iter_mut().zip(accels) {
+            body.vel += a * (dt / 2.0);
+        }
+    };
+    kick(bodies);
+    for body in bodies.iter_mut() {
+        body.pos += body.vel * dt;
+    }
+    kick(bodies);
+}
+
+/// Cubic Hermite interpolation, `u` of the way through a span of `h` seconds.
+fn hermite(
+    (p0, v0): &(Vector3<f64>, Vector3<f64>),
+    (p1, v1): &(Vector3<f64>, Vector3<f64>),
+    h: f64,
+    u: f64,
+) -> (Vector3<f64>, Vector3<f64>) {
+    let (u2, u3) = (u * u, u * u * u);
+    let pos = p0 * (2.0 * u3 - 3.0 * u2 + 1.0)
+        + v0 * (h * (u3 - 2.0 * u2 + u))
+        + p1 * (-2.0 * u3 + 3.0 * u2)
+        + v1 * (h * (u3 - u2));
+    let vel = p0 * ((6.0 * u2 - 6.0 * u) / h)
+        + v0 * (3.0 * u2 - 4.0 * u + 1.0)
+        + p1 * ((-6.0 * u2 + 6.0 * u) / h)
+        + v1 * (3.0 * u2 - 2.0 * u);
+    (pos, vel)
+}
+
+/// The bodies, integrated forward as far as has been asked for so far, and
+/// where on the target the ship is to arrive.
+struct Ephemeris {
+    bodies: Vec<Point>,
+    target: usize,
+    /// Where to arrive, km, relative to the target.
+    offset: Vector3<f64>,
+    /// The bodies' states every `TORCH_SAMPLE` seconds from the start.
+    samples: Vec<Vec<(Vector3<f64>, Vector3<f64>)>>,
+}
+
+impl Ephemeris {
+    fn new(bodies: Vec<Point>, target: usize, offset: Vector3<f64>) -> Self {
+        let first = bodies.iter().map(|b| (b.pos, b.vel)).collect();
+        Ephemeris {
+            bodies,
+            target,
+            offset,
+            samples: vec![first],
+        }
+    }
+
+    /// Integrate far enough ahead to cover `t` seconds from the start.
+    fn extend(&mut self, t: f64) {
+        let index = (t / TORCH_SAMPLE).floor().max(0.0) as usize;
+        while self.samples.len() < index + 2 {
+            let steps = (TORCH_SAMPLE / TORCH_STEP).round() as usize;
+            for _ in 0..steps {
+                leapfrog(&mut self.bodies, TORCH_SAMPLE / steps as f64);
+            }
+            self.samples
+                .push(self.bodies.iter().map(|b| (b.pos, b.vel)).collect());
+        }
+    }
+
+    /// A body's state `t` seconds from the start, once extended that far.
+    fn body(&self, body: usize, t: f64) -> (Vector3<f64>, Vector3<f64>) {
+        let index = ((t / TORCH_SAMPLE).floor().max(0.0) as usize).min(self.samples.len() - 2);
+        hermite(
+            &self.samples[index][body],
+            &self.samples[index + 1][body],
+            TORCH_SAMPLE,
+            t / TORCH_SAMPLE - index as f64,
+        )
+    }
+
+    /// The gravity, km/s^2, at `pos`, `t` seconds from the start, and the
+    /// shortest free-fall time, in seconds, to any of the bodies.
+    fn gravity(&self, t: f64, pos: &Vector3<f64>) -> (Vector3<f64>, f64) {
+        let positions: Vec<_> = self
+            .bodies
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (self.body(i, t).0, b.gm))
+            .collect();
+        let free_fall = positions
+            .iter()
+            .map(|(body, gm)| ((body - pos).norm().powi(3) / gm).sqrt())
+            .fold(f64::INFINITY, f64::min);
+        (gravity(positions.into_iter(), pos, None), free_fall)
+    }
+
+    /// The arrival state `t` seconds from the start.
+    fn state(&mut self, t: f64) -> (Vector3<f64>, Vector3<f64>) {
+        self.extend(t);
+        let (pos, vel) = self.body(self.target, t);
+        (pos + self.offset, vel)
+    }
+}
+
+/// The two burns, km/s^2, that take the ship from `(r0, v0)` to `(r1, v1)`
+/// in `t` seconds, leaving aside gravity.
+fn burns(
+    r0: &Vector3<f64>,
+    v0: &Vector3<f64>,
+    r1: &Vector3<f64>,
+    v1: &Vector3<f64>,
+    t: f64,
+) -> (Vector3<f64>, Vector3<f64>) {
+    let s = t / 2.0;
+    let d = (r1 - r0 - v0 * t) / (s * s);
+    let w = (v1 - v0) / s;
+    (d - w / 2.0, w * 1.5 - d)
+}
+
+/// The shortest trip, in seconds, with no burn over `accel` (km/s^2), and its
+/// burns, leaving aside gravity on the ship.
+fn shortest(
+    ephemeris: &mut Ephemeris,
+    r0: &Vector3<f64>,
+    v0: &Vector3<f64>,
+    accel: f64,
+) -> Option<(f64, Vector3<f64>, Vector3<f64>)> {
+    let mut need = |t: f64| {
+        let (r1, v1) = ephemeris.state(t);
+        let (a1, a2) = burns(r0, v0, &r1, &v1, t);
+        a1.norm().max(a2.norm())
+    };
+
+    // Step out until the trip is possible, then narrow it down.
+    let mut low = TORCH_STEP;
+    let mut high = low;
+    while need(high) > accel {
+        low = high;
+        high *= 1.25;
+        if high > TORCH_HORIZON {
+            return None;
+        }
+    }
+    for _ in 0..50 {
+        let mid = (low + high) / 2.0;
+        if need(mid) > accel {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    let (r1, v1) = ephemeris.state(high);
+    let (a1, a2) = burns(r0, v0, &r1, &v1, high);
+    Some((high, a1, a2))
+}
+
+/// Fly the burns through the bodies, returning the ship's position and
+/// velocity at the end.  The ephemeris must already reach that far.
+fn fly(
+    ephemeris: &Ephemeris,
+    ship: &Point,
+    t: f64,
+    a1: &Vector3<f64>,
+    a2: &Vector3<f64>,
+) -> (Vector3<f64>, Vector3<f64>) {
+    let (mut pos, mut vel) = (ship.pos, ship.vel);
+    let (mut g, mut free_fall) = ephemeris.gravity(0.0, &pos);
+    let mut time = 0.0;
+    // Each burn ends exactly on a step.
+    for (thrust, end) in [(a1, t / 2.0), (a2, t)] {
+        while time < end {
+            let dt = (free_fall * TORCH_FREE_FALL)
+                .clamp(TORCH_STEP, TORCH_SAMPLE)
+                .min(end - time);
+            vel += (g + thrust) * (dt / 2.0);
+            pos += vel * dt;
+            time += dt;
+            (g, free_fall) = ephemeris.gravity(time, &pos);
+            vel += (g + thrust) * (dt / 2.0);
+        }
+    }
+    (pos, vel)
+}
+
+/// The burns, as one vector, a1 then a2.
+fn split(a: &Vector6<f64>) -> (Vector3<f64>, Vector3<f64>) {
+    (a.fixed_rows::<3>(0).into(), a.fixed_rows::<3>(3).into())
+}
+
+/// Find the burns, starting from `a`, that arrive in `t` seconds, with gravity.
+/// This is Newton's method, with the Jacobian from finite differences the
+/// first time, and kept up to date with Broyden's method after that, as each
+/// evaluation is a whole trip.  Returns the burns, and the miss, in km and
+/// km/s.
+fn shoot(
+    ephemeris: &mut Ephemeris,
+    ship: &Point,
+    t: f64,
+    mut a: Vector6<f64>,
+    jacobian: &mut Option<Matrix6<f64>>,
+) -> (Vector6<f64>, (f64, f64)) {
+    let goal = ephemeris.state(t);
+    let ephemeris = &*ephemeris;
+
+    // How far off the arrival is, with the velocity scaled by the trip time,
+    // so that both are in km.
+    let residual = |a: &Vector6<f64>| {
+        let (a1, a2) = split(a);
+        let (arrived_pos, arrived_vel) = fly(ephemeris, ship, t, &a1, &a2);
+        let pos = goal.0 - arrived_pos;
+        let vel = goal.1 - arrived_vel;
+        let r = Vector6::new(pos.x, pos.y, pos.z, vel.x * t, vel.y * t, vel.z * t);
+        (r, (pos.norm(), vel.norm()))
+    };
+    let differences = |a: &Vector6<f64>, r: &Vector6<f64>| {
+        let h = a.norm().max(1.0e-12) * 1.0e-6;
+        let mut jacobian = Matrix6::zeros();
+        for i in 0..6 {
+            let mut nudged = *a;
+            nudged[i] += h;
+            jacobian.set_column(i, &((residual(&nudged).0 - r) / h));
+        }
+        jacobian
+    };
+
+    let (mut r, mut miss) = residual(&a);
+    // Whether the Jacobian is fresh from finite differences.
+    let mut fresh = false;
+    for _ in 0..30 {
+        if miss.0 < TORCH_MISS.0 && miss.1 < TORCH_MISS.1 {
+            break;
+        }
+        let j = match *jacobian {
+            Some(j) => j,
+            None => {
+                fresh = true;
+                *jacobian.insert(differences(&a, &r))
+            }
+        };
+        let Some(mut step) = j.lu().solve(&-r) else {
+            break;
+        };
+
+        // Close to a body, the full step can be far too long, so back off
+        // until it gets closer.
+        let mut next = None;
+        for _ in 0..10 {
+            let (next_r, next_miss) = residual(&(a + step));
+            if next_r.norm() < r.norm() {
+                next = Some((next_r, next_miss));
+                break;
+            }
+            step /= 2.0;
+        }
+        let Some((next_r, next_miss)) = next else {
+            if fresh {
+                // Even a fresh Jacobian doesn't help, so this is as close as
+                // it gets.
+                break;
+            }
+            *jacobian = None;
+            continue;
+        };
+        let change = next_r - r - j * step;
+        *jacobian = Some(j + change * step.transpose() / step.norm_squared());
+        fresh = false;
+        a += step;
+        (r, miss) = (next_r, next_miss);
+    }
+    (a, miss)
+}
+
+/// Plan a trip to the ephemeris' target, at `accel` (km/s^2).  Returns the
+/// trip time, the burns, and the miss, in km and km/s.
+#[allow(clippy::type_complexity)]
+fn plan(
+    ephemeris: &mut Ephemeris,
+    ship: &Point,
+    accel: f64,
+) -> Option<(f64, Vector3<f64>, Vector3<f64>, (f64, f64))> {
+    let (mut t, a1, a2) = shortest(ephemeris, &ship.pos, &ship.vel, accel)?;
+
+    // Put gravity back in, and then, as that changes what the burns need,
+    // stretch or shrink the trip until the harder burn takes all the ship
+    // has.  As the burns go roughly as 1 / t^2, this settles quickly.
+    let mut a = Vector6::new(a1.x, a1.y, a1.z, a2.x, a2.y, a2.z);
+    let mut jacobian = None;
+    let mut miss;
+    for _ in 0..20 {
+        (a, miss) = shoot(ephemeris, ship, t, a, &mut jacobian);
+        let (a1, a2) = split(&a);
+        let ratio = a1.norm().max(a2.norm()) / accel;
+        if (ratio - 1.0).abs() < 1.0e-3 {
+            return Some((t, a1, a2, miss));
+        }
+
+        // What gravity added, by where the burns alone would have arrived,
+        // is much the same for a slightly different trip, so it makes a good
+        // start for the next.
+        let s = t / 2.0;
+        let (goal_pos, goal_vel) = ephemeris.state(t);
+        let gravity_pos =
+            goal_pos - (ship.pos + ship.vel * t + a1 * (1.5 * s * s) + a2 * (0.5 * s * s));
+        let gravity_vel = goal_vel - (ship.vel + (a1 + a2) * s);
+        t = (t * ratio.sqrt()).min(TORCH_HORIZON);
+        let (goal_pos, goal_vel) = ephemeris.state(t);
+        let (a1, a2) = burns(
+            &ship.pos,
+            &ship.vel,
+            &(goal_pos - gravity_pos),
+            &(goal_vel - gravity_vel),
+            t,
+        );
+        a = Vector6::new(a1.x, a1.y, a1.z, a2.x, a2.y, a2.z);
+    }
+    (a, miss) = shoot(ephemeris, ship, t, a, &mut jacobian);
+    let (a1, a2) = split(&a);
+    Some((t, a1, a2, miss))
+}
+
+/// A time span, in the largest unit that reads easily.
+fn span(seconds: f64) -> String {
+    if seconds >= 2.0 * 86400.0 {
+        format!("{:.2} d", seconds / 86400.0)
+    } else if seconds >= 2.0 * 3600.0 {
+        format!("{:.2} h", seconds / 3600.0)
+    } else {
+        format!("{:.0} s", seconds)
+    }
+}
+
+/// `torch <body> [gees] [fly]` plans a trip, at 1 g by default, and replies
+/// with the plan.
+#[allow(clippy::type_complexity)]
+fn torch_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    fixed: Res<Time<Fixed>>,
+    ship: Query<(Entity, &OrbitalBody, &AttitudeState), With<PlayerShip>>,
+    bodies: Query<
+        (&Name, &OrbitalBody, &MassiveBody, Option<&SizedBody>),
+        (Without<PlayerShip>, Without<Frozen>),
+    >,
+) -> ConsoleReply {
+    let words: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (name, gees, fly) = match words.as_slice() {
+        [name] => (*name, 1.0, false),
+        [name, "fly"] => (*name, 1.0, true),
+        [name, gees] => (*name, parse_arg(gees)?, false),
+        [name, gees, "fly"] => (*name, parse_arg(gees)?, true),
+        _ => return Err("torch <body> [gees] [fly]".to_string()),
+    };
+    let (entity, orbital, attitude) = ship.single().map_err(|_| "no ship".to_string())?;
+    let mut target = None;
+    let mut points = Vec::new();
+    for (body_name, body, massive, size) in bodies.iter() {
+        if body_name.as_str().eq_ignore_ascii_case(name) {
+            target = Some((points.len(), size.map_or(0.0, |s| s.radii.max())));
+        }
+        points.push(Point {
+            pos: body.pos,
+            vel: body.vel,
+            gm: massive.gm,
+        });
+    }
+    let (target, radius) = target.ok_or_else(|| format!("No such body: {:?}", name))?;
+    let ship_point = Point {
+        pos: orbital.pos,
+        vel: orbital.vel,
+        gm: 0.0,
+    };
+    // Arrive on the near side, far enough out that the drive isn't fighting
+    // the target's gravity.
+    let accel = gees * G0 / 1000.0;
+    let standoff = (radius * TORCH_STANDOFF).max((points[target].gm / (accel * TORCH_PULL)).sqrt());
+    let offset = (orbital.pos - points[target].pos).normalize() * standoff;
+    let mut ephemeris = Ephemeris::new(points.clone(), target, offset);
+
+    let mut lines = vec![format!("Trip time to {}, leaving aside gravity:", name)];
+    for trade in TORCH_TRADE_GEES {
+        lines.push(
+            match shortest(
+                &mut ephemeris,
+                &ship_point.pos,
+                &ship_point.vel,
+                trade * G0 / 1000.0,
+            ) {
+                Some((t, a1, a2)) => format!(
+                    "  {:5.2} g: {:>9}, Δv {:.1} km/s",
+                    trade,
+                    span(t),
+                    (a1.norm() + a2.norm()) * t / 2.0
+                ),
+                None => format!("  {:5.2} g: over a year", trade),
+            },
+        );
+    }
+
+    let (t, a1, a2, miss) = plan(&mut ephemeris, &ship_point, accel)
+        .ok_or_else(|| format!("{} is more than a year away at {} g", name, gees))?;
+    let now = fixed.elapsed_secs_f64();
+    let q_1 = point_axis_at(&attitude.q_bw, &Vector3::z(), &a1);
+    let q_2 = point_axis_at(&q_1, &Vector3::z(), &a2);
+    let burn = |start: f64, a: &Vector3<f64>, q_bw| TorchBurn {
+        start: now + start,
+        end: now + start + t / 2.0,
+        // km/s^2 to m/s^2.
+        accel_w: a * 1000.0,
+        throttle: a.norm() / accel,
+        q_bw,
+    };
+    let plan = TorchPlan {
+        target: name.to_string(),
+        accel: accel * 1000.0,
+        burns: vec![burn(0.0, &a1, q_1), burn(t / 2.0, &a2, q_2)],
+        dv: (a1.norm() + a2.norm()) * t / 2.0 * 1000.0,
+    };
+
+    lines.push(format!(
+        "To {} at {} g: {}, Δv {:.1} km/s, arriving {:.0} km out, within {:.3} km and \
+         {:.3} m/s",
+        plan.target,
+        gees,
+        span(t),
+        plan.dv / 1000.0,
+        standoff,
+        miss.0,
+        miss.1 * 1000.0
+    ));
+    for (i, burn) in plan.burns.iter().enumerate() {
+        let dir = burn.accel_w.normalize();
+        let q = burn.q_bw;
+        lines.push(format!(
+            "  {} at T+{}: throttle {:.3}, toward ({:.4}, {:.4}, {:.4}), \
+             q_bw ({:.4}, {:.4}, {:.4}, {:.4})",
+            if i == 0 { "Burn" } else { "Flip" },
+            span(burn.start - now),
+            burn.throttle,
+            dir.x,
+            dir.y,
+            dir.z,
+            q.w,
+            q.i,
+            q.j,
+            q.k
+        ));
+    }
+
+    if fly {
+        commands
+            .entity(entity)
+            .insert(Propulsion(Box::new(FlipAndBurn {
+                burns: plan.burns.clone(),
+            })))
+            .remove::<PendingJump>();
+    }
+    commands.entity(entity).insert(plan);
+    Ok(lines.join("\n"))
+}
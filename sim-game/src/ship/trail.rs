@@ -0,0 +1,145 @@
+//! The trail of breadcrumbs behind a craft.
+//!
+//! Where each craft has been is sampled as the sim runs, relative to the
+//! earth, for the ship view and the map to draw behind it, fading with age.
+//! Set against the predicted path ahead, it shows straight away what a burn
+//! or a perturbation has done.
+//!
+//! The `TrailSettings` say how far back the trail goes, and how often it is
+//! sampled, which is what keeps a long trail cheap to draw.
+//!
+//! A craft that jumps, such as by a teleport, or a snapshot being loaded,
+//! starts its trail over, so it isn't drawn straight across.
+//!
+//! - `trail`: the settings.
+//! - `trail <length s> [interval s]`: change them.  A length of 0 turns the
+//!   trail off.
+
+use bevy::prelude::*;
+use na::Vector3;
+use sim_astro::EarthMarker;
+use sim_core::{OrbitalBody, PostPhysicsSet};
+use std::collections::VecDeque;
+
+use crate::console::{ConsoleApp, ConsoleReply, parse_arg};
+
+/// How long, in sim seconds, a trail is, unless told otherwise.  About an
+/// orbit in low earth orbit.
+const TRAIL_LENGTH: f64 = 90.0 * 60.0;
+
+/// How often, in sim seconds, a trail is sampled, unless told otherwise.
+const TRAIL_INTERVAL: f64 = 10.0;
+
+/// A craft that has moved further than this many times its speed allows
+/// since the last sample has jumped, and its trail starts over.
+const JUMP_SLACK: f64 = 2.0;
+
+#[derive(Resource, Clone, Debug)]
+pub struct TrailSettings {
+    /// How far back, in sim seconds, the trail goes.
+    pub length: f64,
+    /// The time, in sim seconds, between samples.
+    pub interval: f64,
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        TrailSettings {
+            length: TRAIL_LENGTH,
+            interval: TRAIL_INTERVAL,
+        }
+    }
+}
+
+/// Where a craft has been.
+#[derive(Clone, Component, Debug, Default)]
+pub struct Trail {
+    /// The samples, oldest first: the sim time, and the position, in km,
+    /// relative to the earth.
+    pub points: VecDeque<(f64, Vector3<f64>)>,
+}
+
+impl Trail {
+    /// The samples, with how far along from the oldest end of the trail each
+    /// is, 0 to 1 at `now`, for fading them out.
+    pub fn faded(&self, now: f64, length: f64) -> impl Iterator<Item = (f64, &Vector3<f64>)> {
+        self.points
+            .iter()
+            .map(move |(t, pos)| ((1.0 - (now - t) / length).clamp(0.0, 1.0), pos))
+    }
+}
+
+#[derive(Default)]
+pub struct TrailPlugin;
+
+impl Plugin for TrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrailSettings>();
+        app.add_systems(FixedUpdate, record_trails.after(PostPhysicsSet));
+        app.add_console_command(
+            "trail",
+            "trail [<length s> [interval s]]   show, or set, the breadcrumb trail",
+            trail_command,
+        );
+    }
+}
+
+/// Sample where each craft is now, every so often, and drop what has fallen
+/// off the end.
+fn record_trails(
+    time: Res<Time>,
+    settings: Res<TrailSettings>,
+    mut crafts: Query<(&OrbitalBody, &mut Trail)>,
+    earth: Query<&OrbitalBody, (With<EarthMarker>, Without<Trail>)>,
+) {
+    let Ok(earth) = earth.single() else {
+        return;
+    };
+    let now = time.elapsed_secs_f64();
+    for (orbital, mut trail) in crafts.iter_mut() {
+        if settings.length <= 0.0 {
+            trail.points.clear();
+            continue;
+        }
+        let pos = orbital.pos - earth.pos;
+        let speed = (orbital.vel - earth.vel).norm();
+        match trail.points.back() {
+            Some((last, _)) if now - last < settings.interval => {}
+            // A jump, such as a teleport, or a snapshot being loaded.
+            Some((last, last_pos))
+                if (pos - last_pos).norm() > JUMP_SLACK * speed * (now - last) + 1.0 =>
+            {
+                trail.points.clear();
+                trail.points.push_back((now, pos));
+            }
+            _ => trail.points.push_back((now, pos)),
+        }
+        while trail
+            .points
+            .front()
+            .is_some_and(|(t, _)| now - t > settings.length)
+        {
+            trail.points.pop_front();
+        }
+    }
+}
+
+fn trail_command(In(args): In<Vec<String>>, mut settings: ResMut<TrailSettings>) -> ConsoleReply {
+    match args.as_slice() {
+        [] => {}
+        [length] => settings.length = parse_arg(length)?,
+        [length, interval] => {
+            let interval = parse_arg(interval)?;
+            if interval <= 0.0 {
+                return Err("The interval has to be more than 0".to_string());
+            }
+            settings.length = parse_arg(length)?;
+            settings.interval = interval;
+        }
+        _ => return Err("trail [<length s> [interval s]]".to_string()),
+    }
+    Ok(format!(
+        "{} s, every {} s",
+        settings.length, settings.interval
+    ))
+}
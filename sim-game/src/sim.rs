@@ -0,0 +1,77 @@
+//! The simulation, without anything to look at.
+//!
+//! `SimPlugins` is everything that moves the sim along: the solar system, the
+//! ship and its systems, and the things that can be asked of them from the
+//! console.  None of it needs a window or a GPU, so the same plugins run the
+//! game and the headless modes (such as `scifisim propagate`).  It does need
+//! the input plugin, as the keyboard controls are part of the ship, but
+//! headless they just never see a key.
+
+use bevy::{app::PluginGroupBuilder, prelude::*};
+use sim_astro::{SolarPlugin, collision::CollisionPlugin};
+use sim_core::{clock::ClockPlugin, watchdog::WatchdogPlugin};
+
+use crate::{
+    alarm, countdown, coverage, debris, events, gamepad, keys, lagrange, observer, oem, preset,
+    promote, range, remote, sequence, ship, snapshot,
+};
+
+pub struct SimPlugins;
+
+impl PluginGroup for SimPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(SolarPlugin)
+            .add(ClockPlugin)
+            .add(keys::KeysPlugin)
+            .add(WatchdogPlugin)
+            .add(coverage::CoveragePlugin)
+            .add(CollisionPlugin)
+            .add(debris::DebrisPlugin)
+            .add(promote::PromotePlugin)
+            .add(ship::ShipPlugin)
+            .add(ship::engine::EnginePlugin)
+            .add(gamepad::GamepadPlugin)
+            .add(ship::registry::RegistryPlugin)
+            .add(ship::focus::FocusPlugin)
+            .add(ship::maneuver::ManeuverPlugin)
+            .add(ship::autopilot::AutopilotPlugin)
+            .add(ship::ascent::AscentPlugin)
+            .add(ship::pad::PadPlugin)
+            .add(ship::insertion::InsertionPlugin)
+            .add(ship::targeting::TargetingPlugin)
+            .add(ship::lunar::LunarPlugin)
+            .add(ship::proximity::ProximityPlugin)
+            .add(ship::docking::DockingPlugin)
+            .add(ship::aero::AeroPlugin)
+            .add(ship::gravity_gradient::GravityGradientPlugin)
+            .add(ship::magnetorquer::MagnetorquerPlugin)
+            .add(ship::craft_gravity::CraftGravityPlugin)
+            .add(ship::sas::SasPlugin)
+            .add(ship::sunlight::SunlightPlugin)
+            .add(ship::power::PowerPlugin)
+            .add(ship::staging::StagingPlugin)
+            .add(ship::parts::PartsPlugin)
+            .add(ship::tether::TetherPlugin)
+            .add(ship::propulsion::PropulsionPlugin)
+            .add(ship::torch::TorchPlugin)
+            .add(ship::radiation::RadiationPlugin)
+            .add(ship::lifetime::LifetimePlugin)
+            .add(ship::ground_track::GroundTrackPlugin)
+            .add(ship::trail::TrailPlugin)
+            .add(ship::sensors::SensorsPlugin)
+            .add(ship::nav::NavPlugin)
+            .add(ship::orbit_determination::OrbitDeterminationPlugin)
+            .add(oem::OemPlugin)
+            .add(observer::ObserverPlugin)
+            .add(lagrange::LagrangePlugin)
+            .add(events::EventsPlugin)
+            .add(preset::PresetPlugin)
+            .add(snapshot::SnapshotPlugin)
+            .add(sequence::SequencePlugin)
+            .add(alarm::AlarmPlugin)
+            .add(countdown::CountdownPlugin)
+            .add(range::RangePlugin)
+            .add(remote::RemotePlugin)
+    }
+}
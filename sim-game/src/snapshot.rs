@@ -0,0 +1,619 @@
+//! Saving and loading the state of the sim.
+//!
+//! A snapshot holds the epoch, every named body and craft's state, the
+//! player ship's own components and settings, the commands queued with `at`,
+//! and those waiting on events, the alarms set, the launch countdown, the
+//! range safety boundaries, and the debris clouds, as JSON.  F10 saves a
+//! quicksave, and F11 loads it back.  `scifisim --load <file>` starts from a
+//! snapshot, and a snapshot is also the scenario for `scifisim propagate`.
+//! Those can be RON, too, for scenarios written by hand.
+//!
+//! Entities are matched up by name when loading, and crafts by their ids (see
+//! `ship::registry`) first, so that one renamed since is still found, and
+//! keeps its id.  Anything in the snapshot that isn't in the sim (such as a
+//! drill's target) is spawned; anything in the sim but not in the snapshot
+//! is left alone.
+//!
+//! A snapshot can also carry overrides for the solar system's bodies (see
+//! `sim_astro::overrides`), for a scenario set somewhere fictional.  The ones
+//! a run started with are merged in before the bodies are spawned, and are
+//! kept in every snapshot saved from it.  When loading, their GMs and radii
+//! win over the snapshot's, but the snapshot's states, being for its own time,
+//! win over theirs.
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use na::{UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use sim_astro::{
+    SolarState,
+    contact::{Clamped, Landed},
+    loading::SimPhase,
+    overrides::BodyOverride,
+    radiation::{SolarParticleEvent, SolarParticleEvents},
+};
+use sim_core::{
+    AttitudeState, MassiveBody, OrbitalBody, PhysicsModels, SizedBody, watchdog::Frozen,
+};
+use std::path::Path;
+
+use crate::{
+    alarm::{Alarm, Alarms},
+    console::{ConsoleApp, ConsoleReply},
+    countdown::Countdown,
+    debris::{Debris, DebrisCloud},
+    keys::{InputMap, KeysApp},
+    preset::PhysicsPreset,
+    range::{Boundary, RangeSafety},
+    sequence::{CommandQueue, EventCommand, EventCommands, QueuedCommand},
+    ship::{
+        MassProperties, PlayerShip, RcsMode, SasTarget,
+        aero::Aero,
+        autopilot::Autopilot,
+        engine::{FuelTank, MainEngine},
+        magnetorquer::Magnetorquers,
+        maneuver::ManeuverNode,
+        pad::Umbilical,
+        power::Power,
+        radiation::Dosimeter,
+        rcs::{RcsRealism, RcsTank, RcsThrusters},
+        registry::CraftId,
+        sas::StabilityAssist,
+        staging::Staging,
+    },
+};
+
+/// Where F10 and F11 save and load.
+const QUICKSAVE: &str = "quicksave.json";
+
+/// The state of a named body or craft.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BodySnapshot {
+    pub name: String,
+    /// The craft's id, if it is one.
+    #[serde(default)]
+    pub craft: Option<CraftId>,
+    pub orbital: OrbitalBody,
+    pub attitude: AttitudeState,
+    pub massive: Option<MassiveBody>,
+}
+
+/// A `Landed`, with the body it is on by name.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LandedSnapshot {
+    pub body: String,
+    pub pos_f: Vector3<f64>,
+    pub q_bf: UnitQuaternion<f64>,
+    pub slide_f: Vector3<f64>,
+}
+
+/// The player ship's own components.  Its orbit and attitude are with the
+/// other bodies.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ShipSnapshot {
+    pub mass: MassProperties,
+    pub rcs: RcsThrusters,
+    pub engine: MainEngine,
+    pub tank: Option<FuelTank>,
+    pub staging: Option<Staging>,
+    pub node: Option<ManeuverNode>,
+    pub autopilot: Autopilot,
+    pub aero: Option<Aero>,
+    pub landed: Option<LandedSnapshot>,
+    pub dosimeter: Option<Dosimeter>,
+    pub power: Option<Power>,
+    #[serde(default)]
+    pub sas: Option<StabilityAssist>,
+    /// Whether it is held on the pad, and fed from the ground.
+    #[serde(default)]
+    pub clamped: bool,
+    #[serde(default)]
+    pub umbilical: Option<Umbilical>,
+    #[serde(default)]
+    pub rcs_tank: Option<RcsTank>,
+    #[serde(default)]
+    pub torquers: Option<Magnetorquers>,
+}
+
+/// The whole state of the sim.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// The time of the snapshot, in seconds past J2000.
+    pub et: f64,
+    /// The `Time<Fixed>` elapsed when it was taken.  The times in the
+    /// snapshot, such as a maneuver node's, are on this clock.
+    pub elapsed: f64,
+    pub bodies: Vec<BodySnapshot>,
+    pub ship: Option<ShipSnapshot>,
+    pub rcs_mode: RcsMode,
+    pub realism: RcsRealism,
+    /// The SAS target, by name, or by id if it is a craft.
+    pub sas_target: Option<String>,
+    /// The solar particle events to come, or still going.
+    #[serde(default)]
+    pub solar_events: Vec<SolarParticleEvent>,
+    /// Changes to the solar system's bodies, and bodies of its own.
+    #[serde(default)]
+    pub overrides: Vec<BodyOverride>,
+    #[serde(default)]
+    pub preset: PhysicsPreset,
+    /// The commands queued to run, at times that are ET, so they need no
+    /// moving.
+    #[serde(default)]
+    pub queue: Vec<QueuedCommand>,
+    /// The commands waiting on events.
+    #[serde(default)]
+    pub on_events: Vec<EventCommand>,
+    /// The debris clouds, with their epochs in ET, too.
+    #[serde(default)]
+    pub debris: Vec<DebrisCloud>,
+    /// The alarms set, at times in ET as well.
+    #[serde(default)]
+    pub alarms: Vec<Alarm>,
+    /// The launch countdown, with its T-0 in ET, and its schedule.
+    #[serde(default)]
+    pub countdown: Countdown,
+    /// The range safety boundaries.
+    #[serde(default)]
+    pub range: Vec<Boundary>,
+}
+
+/// The overrides the run started with.
+#[derive(Resource, Default)]
+struct ScenarioOverrides(Vec<BodyOverride>);
+
+impl Snapshot {
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        Ok(serde_json::to_writer_pretty(file, self)?)
+    }
+
+    /// Load a snapshot, from JSON, or from RON if the file name ends in
+    /// `.ron` (which is nicer for writing scenarios by hand).
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)?;
+        if path.extension().is_some_and(|e| e == "ron") {
+            return ron::de::from_reader(file).map_err(std::io::Error::other);
+        }
+        Ok(serde_json::from_reader(file)?)
+    }
+}
+
+/// Everything a snapshot is taken from, and put back into.
+#[derive(SystemParam)]
+#[allow(clippy::type_complexity)]
+pub struct SimState<'w, 's> {
+    commands: Commands<'w, 's>,
+    fixed: Res<'w, Time<Fixed>>,
+    solar: ResMut<'w, SolarState>,
+    mode: ResMut<'w, RcsMode>,
+    realism: ResMut<'w, RcsRealism>,
+    sas_target: ResMut<'w, SasTarget>,
+    solar_events: ResMut<'w, SolarParticleEvents>,
+    overrides: Res<'w, ScenarioOverrides>,
+    preset: ResMut<'w, PhysicsPreset>,
+    models: ResMut<'w, PhysicsModels>,
+    queue: ResMut<'w, CommandQueue>,
+    on_events: ResMut<'w, EventCommands>,
+    debris: ResMut<'w, Debris>,
+    alarms: ResMut<'w, Alarms>,
+    countdown: ResMut<'w, Countdown>,
+    range: ResMut<'w, RangeSafety>,
+    bodies: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static Name,
+            &'static mut OrbitalBody,
+            &'static mut AttitudeState,
+            Option<&'static mut MassiveBody>,
+            Option<&'static CraftId>,
+        ),
+    >,
+    ship: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut MassProperties,
+            &'static mut RcsThrusters,
+            &'static mut MainEngine,
+            (Option<&'static mut FuelTank>, Option<&'static mut RcsTank>),
+            Option<&'static mut Staging>,
+            Option<&'static ManeuverNode>,
+            &'static mut Autopilot,
+            Option<&'static mut Aero>,
+            Option<&'static Landed>,
+            Option<&'static mut Dosimeter>,
+            Option<&'static mut Power>,
+            (
+                Option<&'static mut StabilityAssist>,
+                Option<&'static mut Magnetorquers>,
+            ),
+            Has<Clamped>,
+            Option<&'static Umbilical>,
+        ),
+        With<PlayerShip>,
+    >,
+    frozen: Query<'w, 's, Entity, With<Frozen>>,
+}
+
+impl SimState<'_, '_> {
+    fn name_of(&self, entity: Entity) -> Option<String> {
+        self.bodies.get(entity).ok().map(|b| b.1.to_string())
+    }
+
+    /// The body by name, or the craft by name or id, as `#2`.
+    fn find(&self, name: &str) -> Option<Entity> {
+        let id = CraftId::parse(name);
+        self.bodies
+            .iter()
+            .find(|b| match id {
+                Some(id) => b.5 == Some(&id),
+                None => b.1.as_str() == name,
+            })
+            .map(|b| b.0)
+    }
+
+    /// How a snapshot refers to `entity`: by id if it is a craft, and
+    /// otherwise by name.
+    fn key_of(&self, entity: Entity) -> Option<String> {
+        let body = self.bodies.get(entity).ok()?;
+        Some(body.5.map_or(body.1.to_string(), CraftId::to_string))
+    }
+
+    pub fn save(&self) -> Snapshot {
+        let elapsed = self.fixed.elapsed_secs_f64();
+        let bodies = self
+            .bodies
+            .iter()
+            .map(
+                |(_, name, orbital, attitude, massive, craft)| BodySnapshot {
+                    name: name.to_string(),
+                    craft: craft.copied(),
+                    orbital: orbital.clone(),
+                    attitude: attitude.clone(),
+                    massive: massive.cloned(),
+                },
+            )
+            .collect();
+        let ship = self.ship.single().ok().map(|ship| {
+            let (
+                _,
+                mass,
+                rcs,
+                engine,
+                (tank, rcs_tank),
+                staging,
+                node,
+                autopilot,
+                aero,
+                landed,
+                dosimeter,
+                power,
+                (sas, torquers),
+                clamped,
+                umbilical,
+            ) = ship;
+            ShipSnapshot {
+                mass: mass.clone(),
+                rcs: rcs.clone(),
+                engine: engine.clone(),
+                tank: tank.cloned(),
+                staging: staging.cloned(),
+                node: node.cloned(),
+                autopilot: autopilot.clone(),
+                aero: aero.cloned(),
+                landed: landed.and_then(|landed| {
+                    Some(LandedSnapshot {
+                        body: self.name_of(landed.body)?,
+                        pos_f: landed.pos_f,
+                        q_bf: landed.q_bf,
+                        slide_f: landed.slide_f,
+                    })
+                }),
+                dosimeter: dosimeter.cloned(),
+                power: power.cloned(),
+                sas: sas.cloned(),
+                clamped,
+                umbilical: umbilical.cloned(),
+                rcs_tank: rcs_tank.cloned(),
+                torquers: torquers.cloned(),
+            }
+        });
+        Snapshot {
+            et: self.solar.et + elapsed,
+            elapsed,
+            bodies,
+            ship,
+            rcs_mode: *self.mode,
+            realism: self.realism.clone(),
+            sas_target: self.sas_target.0.and_then(|e| self.key_of(e)),
+            solar_events: self.solar_events.0.clone(),
+            overrides: self.overrides.0.clone(),
+            preset: *self.preset,
+            queue: self.queue.0.clone(),
+            on_events: self.on_events.0.clone(),
+            debris: self.debris.0.clone(),
+            alarms: self.alarms.0.clone(),
+            countdown: self.countdown.clone(),
+            range: self.range.0.clone(),
+        }
+    }
+
+    pub fn load(&mut self, snapshot: &Snapshot) {
+        // Keep the fixed clock running, and move the epoch, and the times on
+        // it, to match.
+        let now = self.fixed.elapsed_secs_f64();
+        self.solar.et = snapshot.et - now;
+
+        for body in &snapshot.bodies {
+            // The scenario's overrides win over the saved GM and radii, but not
+            // over the state, which is for the snapshot's own time.
+            let overridden = snapshot
+                .overrides
+                .iter()
+                .rev()
+                .find(|o| o.name == body.name);
+            let massive = match overridden.and_then(|o| o.gm) {
+                Some(gm) => Some(MassiveBody { gm }),
+                None => body.massive.clone(),
+            };
+            let found = body
+                .craft
+                .and_then(|id| self.find(&id.to_string()))
+                .or_else(|| self.find(&body.name));
+            let entity = match found {
+                Some(entity) => {
+                    let (_, _, mut orbital, mut attitude, current, _) =
+                        self.bodies.get_mut(entity).unwrap();
+                    *orbital = body.orbital.clone();
+                    *attitude = body.attitude.clone();
+                    if let (Some(mut current), Some(massive)) = (current, massive) {
+                        *current = massive;
+                    }
+                    entity
+                }
+                None => {
+                    let mut entity = self.commands.spawn((
+                        Name::new(body.name.clone()),
+                        body.orbital.clone(),
+                        body.attitude.clone(),
+                    ));
+                    if let Some(massive) = massive {
+                        entity.insert(massive);
+                    }
+                    entity.id()
+                }
+            };
+            if let Some(radii) = overridden.and_then(|o| o.radii) {
+                self.commands.entity(entity).insert(SizedBody { radii });
+            }
+            // A craft takes back its id, and the name it had then.
+            if let Some(id) = body.craft {
+                self.commands
+                    .entity(entity)
+                    .insert((id, Name::new(body.name.clone())));
+            }
+        }
+        for entity in self.frozen.iter() {
+            self.commands.entity(entity).remove::<Frozen>();
+        }
+
+        *self.mode = snapshot.rcs_mode;
+        *self.realism = snapshot.realism.clone();
+        // A scenario's preset wins over its RCS settings.
+        if snapshot.preset != *self.preset {
+            *self.preset = snapshot.preset;
+            snapshot
+                .preset
+                .apply(&mut self.models, &mut self.realism, &mut self.mode);
+        }
+        self.sas_target.0 = snapshot.sas_target.as_deref().and_then(|n| self.find(n));
+        self.solar_events.0 = snapshot
+            .solar_events
+            .iter()
+            .map(|event| SolarParticleEvent {
+                start: event.start + now - snapshot.elapsed,
+                ..event.clone()
+            })
+            .collect();
+        self.queue.0 = snapshot.queue.clone();
+        self.on_events.0 = snapshot.on_events.clone();
+        self.debris.0 = snapshot.debris.clone();
+        self.alarms.0 = snapshot.alarms.clone();
+        *self.countdown = snapshot.countdown.clone();
+        self.range.0 = snapshot.range.clone();
+
+        let Some(saved) = &snapshot.ship else {
+            return;
+        };
+        let landed = saved.landed.as_ref().and_then(|landed| {
+            Some(Landed {
+                body: self.find(&landed.body)?,
+                pos_f: landed.pos_f,
+                q_bf: landed.q_bf,
+                slide_f: landed.slide_f,
+            })
+        });
+        let Ok((
+            entity,
+            mut mass,
+            mut rcs,
+            mut engine,
+            (tank, rcs_tank),
+            staging,
+            _,
+            mut autopilot,
+            aero,
+            _,
+            dosimeter,
+            power,
+            (sas, torquers),
+            _,
+            _,
+        )) = self.ship.single_mut()
+        else {
+            return;
+        };
+        *mass = saved.mass.clone();
+        *rcs = saved.rcs.clone();
+        *engine = saved.engine.clone();
+        if let (Some(mut tank), Some(saved)) = (tank, &saved.tank) {
+            *tank = saved.clone();
+        }
+        if let (Some(mut tank), Some(saved)) = (rcs_tank, &saved.rcs_tank) {
+            *tank = saved.clone();
+        }
+        if let (Some(mut staging), Some(saved)) = (staging, &saved.staging) {
+            *staging = saved.clone();
+        }
+        *autopilot = saved.autopilot.clone();
+        if let (Some(mut aero), Some(saved)) = (aero, &saved.aero) {
+            *aero = saved.clone();
+        }
+        if let (Some(mut dosimeter), Some(saved)) = (dosimeter, &saved.dosimeter) {
+            *dosimeter = saved.clone();
+        }
+        if let (Some(mut power), Some(saved)) = (power, &saved.power) {
+            *power = saved.clone();
+        }
+        if let Some(mut sas) = sas {
+            *sas = saved.sas.clone().unwrap_or_default();
+        }
+        if let (Some(mut torquers), Some(saved)) = (torquers, &saved.torquers) {
+            *torquers = saved.clone();
+        }
+
+        let mut ship = self.commands.entity(entity);
+        match &saved.node {
+            Some(node) => {
+                let mut node = node.clone();
+                node.time += now - snapshot.elapsed;
+                ship.insert(node);
+            }
+            None => {
+                ship.remove::<ManeuverNode>();
+            }
+        }
+        match landed {
+            Some(landed) => {
+                ship.insert(landed);
+            }
+            None => {
+                ship.remove::<Landed>();
+            }
+        }
+        if saved.clamped {
+            ship.insert(Clamped);
+        } else {
+            ship.remove::<Clamped>();
+        }
+        match &saved.umbilical {
+            Some(umbilical) => {
+                ship.insert(umbilical.clone());
+            }
+            None => {
+                ship.remove::<Umbilical>();
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScenarioOverrides>();
+        app.add_systems(
+            OnEnter(SimPhase::Running),
+            merge_overrides.before(sim_astro::setup_solar),
+        );
+        app.add_systems(
+            OnEnter(SimPhase::Running),
+            load_at_start.after(crate::ship::setup_ship),
+        );
+        app.add_key_action("quicksave", &[KeyCode::F10]);
+        app.add_key_action("quickload", &[KeyCode::F11]);
+        app.add_systems(Update, snapshot_keys);
+        app.add_console_command(
+            "dump",
+            "dump [file]   show the state of every body, or save it as a snapshot",
+            dump_command,
+        );
+    }
+}
+
+/// `dump` shows every body's state, and `dump <file>` saves a snapshot.
+fn dump_command(In(args): In<Vec<String>>, state: SimState) -> ConsoleReply {
+    let snapshot = state.save();
+    match args.as_slice() {
+        [] => {
+            let mut lines = vec![format!("et {}", snapshot.et)];
+            for body in &snapshot.bodies {
+                let (pos, vel) = (body.orbital.pos, body.orbital.vel);
+                lines.push(format!(
+                    "{} pos {} {} {} km vel {} {} {} km/s",
+                    body.name, pos.x, pos.y, pos.z, vel.x, vel.y, vel.z
+                ));
+            }
+            Ok(lines.join("\n"))
+        }
+        [path] => snapshot
+            .save(path)
+            .map(|()| format!("saved {}", path))
+            .map_err(|e| format!("Unable to save {}: {}", path, e)),
+        _ => Err("dump [file]".to_string()),
+    }
+}
+
+/// Merge the overrides from the snapshot given on the command line, if there
+/// was one, into the solar system.
+fn merge_overrides(
+    snapshot: Option<Res<Snapshot>>,
+    mut solar: ResMut<SolarState>,
+    mut overrides: ResMut<ScenarioOverrides>,
+) {
+    let Some(snapshot) = snapshot else {
+        return;
+    };
+    if let Err(errors) = solar.merge(&snapshot.overrides) {
+        for e in errors {
+            error!("{}", e);
+        }
+    }
+    overrides.0 = snapshot.overrides.clone();
+}
+
+/// Load the snapshot given on the command line, if there was one.
+pub(crate) fn load_at_start(
+    mut commands: Commands,
+    snapshot: Option<Res<Snapshot>>,
+    mut state: SimState,
+) {
+    if let Some(snapshot) = snapshot {
+        state.load(&snapshot);
+        commands.remove_resource::<Snapshot>();
+    }
+}
+
+/// F10 saves the sim, and F11 loads it back.
+fn snapshot_keys(kb: Res<ButtonInput<KeyCode>>, map: Res<InputMap>, mut state: SimState) {
+    if map.just_pressed(&kb, "quicksave") {
+        match state.save().save(QUICKSAVE) {
+            Ok(()) => info!("Saved {}", QUICKSAVE),
+            Err(e) => error!("Unable to save {}: {}", QUICKSAVE, e),
+        }
+    }
+    if map.just_pressed(&kb, "quickload") {
+        match Snapshot::load(QUICKSAVE) {
+            Ok(snapshot) => {
+                state.load(&snapshot);
+                info!("Loaded {}", QUICKSAVE);
+            }
+            Err(e) => error!("Unable to load {}: {}", QUICKSAVE, e),
+        }
+    }
+}
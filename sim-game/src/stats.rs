@@ -0,0 +1,90 @@
+//! Simulation statistics.
+//!
+//! These are kept as Bevy diagnostics, so they show up in the overlay, and can
+//! also be read by anything else with access to the `DiagnosticsStore`.
+
+use bevy::{
+    diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic},
+    prelude::*,
+};
+use sim_core::{MassiveBody, OrbitalBody};
+use std::time::Instant;
+
+#[derive(Default)]
+pub struct SimStatsPlugin;
+
+impl SimStatsPlugin {
+    /// Physics (fixed) steps run in each frame.
+    pub const STEPS_PER_FRAME: DiagnosticPath = DiagnosticPath::const_new("sim/steps_per_frame");
+
+    /// The time taken by each physics step, in ms.
+    pub const STEP_TIME: DiagnosticPath = DiagnosticPath::const_new("sim/step_time");
+
+    /// The number of gravitating bodies that pull on each craft.
+    pub const GRAVITY_BODIES: DiagnosticPath = DiagnosticPath::const_new("sim/gravity_bodies");
+
+    /// The time taken to predict the ship's trajectory, in ms.
+    pub const PREDICT_TIME: DiagnosticPath = DiagnosticPath::const_new("sim/predict_time");
+
+    /// Crafts that are following their conic analytically.
+    pub const CRAFTS_ON_RAILS: DiagnosticPath = DiagnosticPath::const_new("sim/crafts_on_rails");
+
+    /// Crafts that are being integrated numerically.
+    pub const CRAFTS_NUMERICAL: DiagnosticPath = DiagnosticPath::const_new("sim/crafts_numerical");
+}
+
+/// Bookkeeping for the physics step measurements.
+#[derive(Resource, Default)]
+struct StepTimer {
+    /// When the current step started.
+    start: Option<Instant>,
+    /// Steps run so far this frame.
+    steps: u32,
+}
+
+impl Plugin for SimStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::STEPS_PER_FRAME))
+            .register_diagnostic(Diagnostic::new(Self::STEP_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::GRAVITY_BODIES))
+            .register_diagnostic(Diagnostic::new(Self::PREDICT_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::CRAFTS_ON_RAILS))
+            .register_diagnostic(Diagnostic::new(Self::CRAFTS_NUMERICAL));
+        app.init_resource::<StepTimer>();
+        app.add_systems(FixedFirst, step_start);
+        app.add_systems(FixedLast, step_end);
+        app.add_systems(Last, frame_stats);
+    }
+}
+
+fn step_start(mut timer: ResMut<StepTimer>) {
+    timer.start = Some(Instant::now());
+}
+
+fn step_end(mut timer: ResMut<StepTimer>, mut diagnostics: Diagnostics) {
+    if let Some(start) = timer.start.take() {
+        diagnostics.add_measurement(&SimStatsPlugin::STEP_TIME, || {
+            start.elapsed().as_secs_f64() * 1000.0
+        });
+    }
+    timer.steps += 1;
+}
+
+fn frame_stats(
+    mut timer: ResMut<StepTimer>,
+    mut diagnostics: Diagnostics,
+    bodies: Query<(), With<MassiveBody>>,
+    crafts: Query<(), (With<OrbitalBody>, Without<MassiveBody>)>,
+) {
+    let steps = std::mem::take(&mut timer.steps);
+    diagnostics.add_measurement(&SimStatsPlugin::STEPS_PER_FRAME, || steps as f64);
+
+    // Every craft is pulled on by every massive body.
+    let bodies = bodies.iter().count();
+    diagnostics.add_measurement(&SimStatsPlugin::GRAVITY_BODIES, || bodies as f64);
+
+    // Nothing is on rails yet; every craft is integrated with the bodies.
+    let crafts = crafts.iter().count();
+    diagnostics.add_measurement(&SimStatsPlugin::CRAFTS_ON_RAILS, || 0.0);
+    diagnostics.add_measurement(&SimStatsPlugin::CRAFTS_NUMERICAL, || crafts as f64);
+}
@@ -0,0 +1,477 @@
+//! Live telemetry, over the network.
+//!
+//! Built with the `stream` feature, `scifisim --stream <address> [rate]
+//! [channels] [json|binary]` sends the telemetry channels (see `telemetry`)
+//! out as the sim runs, for a dashboard, such as Grafana, or a ground
+//! station's own, to show.  The address is either `udp://<host>:<port>`, to
+//! send each sample there as a datagram, or `ws://<host>:<port>`, to listen
+//! there for WebSocket clients, and send each sample to all of them as a
+//! message.  The rate is in samples per second of real time (10 by default),
+//! however fast the sim is warping, as that is what a dashboard keeps up
+//! with.
+//!
+//! A sample, as JSON, is an object of the time, the craft's id (or null),
+//! and the values by their CSV column names:
+//!
+//! ```json
+//! {"time_s":3600.0,"craft_id":1,"x_km":6778.1,"y_km":...}
+//! ```
+//!
+//! In binary, it is, little-endian, the time as an f64, the craft's id as a
+//! u64 (0 for none), the number of values as a u16, and the values, as f64s,
+//! in the order of the columns `stream` lists.
+//!
+//! A WebSocket client that can't keep up is dropped.  Nothing the clients
+//! send is read.
+//!
+//! - `stream`: where it goes, the format, rate and columns, and the clients.
+//! - `stream rate <per s>`: change the rate.
+//! - `stream format json|binary`: change the format.
+//! - `stream channels <channel,...>`: change the channels.
+
+use bevy::prelude::*;
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket},
+};
+
+use crate::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::registry::CraftId,
+    telemetry::{Channel, TelemetrySource},
+};
+
+/// The most a WebSocket client's request can be, in bytes, before it is
+/// taken as not one.
+const MAX_REQUEST: usize = 8192;
+
+/// Added to a client's key, for the handshake (RFC 6455).
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// How a sample is sent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Binary,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "json" => Ok(Format::Json),
+            "binary" => Ok(Format::Binary),
+            _ => Err(format!("Unknown stream format {:?}", name)),
+        }
+    }
+}
+
+/// Where the samples go.
+enum Transport {
+    Udp {
+        socket: UdpSocket,
+        to: SocketAddr,
+    },
+    WebSocket {
+        listener: TcpListener,
+        clients: Vec<Client>,
+    },
+}
+
+impl Transport {
+    fn open(address: &str) -> io::Result<Self> {
+        let invalid = |e: &str| io::Error::new(ErrorKind::InvalidInput, e.to_string());
+        if let Some(host) = address.strip_prefix("udp://") {
+            let to = host
+                .to_socket_addrs()?
+                .next()
+                .ok_or_else(|| invalid("No such host"))?;
+            let local = if to.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+            let socket = UdpSocket::bind(local)?;
+            socket.set_nonblocking(true)?;
+            Ok(Transport::Udp { socket, to })
+        } else if let Some(host) = address.strip_prefix("ws://") {
+            let listener = TcpListener::bind(host.trim_end_matches('/'))?;
+            listener.set_nonblocking(true)?;
+            Ok(Transport::WebSocket {
+                listener,
+                clients: Vec::new(),
+            })
+        } else {
+            Err(invalid(
+                "The address must be udp://<host>:<port> or ws://<host>:<port>",
+            ))
+        }
+    }
+
+    /// The same sockets, for another handle, with no clients yet.
+    fn try_clone(&self) -> io::Result<Self> {
+        match self {
+            Transport::Udp { socket, to } => Ok(Transport::Udp {
+                socket: socket.try_clone()?,
+                to: *to,
+            }),
+            Transport::WebSocket { listener, .. } => Ok(Transport::WebSocket {
+                listener: listener.try_clone()?,
+                clients: Vec::new(),
+            }),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Transport::Udp { to, .. } => format!("udp://{}", to),
+            Transport::WebSocket { listener, clients } => format!(
+                "ws://{}, {} clients",
+                listener
+                    .local_addr()
+                    .map_or("?".to_string(), |a| a.to_string()),
+                clients.iter().filter(|c| c.open).count()
+            ),
+        }
+    }
+
+    /// Take on new WebSocket clients, and answer their handshakes.
+    fn accept(&mut self) {
+        let Transport::WebSocket { listener, clients } = self else {
+            return;
+        };
+        loop {
+            match listener.accept() {
+                Ok((stream, peer)) => match stream.set_nonblocking(true) {
+                    Ok(()) => clients.push(Client {
+                        stream,
+                        peer,
+                        request: Vec::new(),
+                        open: false,
+                    }),
+                    Err(e) => warn!("Unable to stream to {}: {}", peer, e),
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    warn!("Unable to take a stream client: {}", e);
+                    break;
+                }
+            }
+        }
+        clients.retain_mut(|client| match client.handshake() {
+            Ok(()) => true,
+            Err(e) => {
+                info!("Stream client {} gone: {}", client.peer, e);
+                false
+            }
+        });
+    }
+
+    /// Send a message to wherever it goes.
+    fn send(&mut self, message: &[u8], format: Format) {
+        match self {
+            // A datagram that can't be sent is lost, as one can be anyway.
+            Transport::Udp { socket, to } => {
+                let _ = socket.send_to(message, *to);
+            }
+            Transport::WebSocket { clients, .. } => {
+                let frame = websocket_frame(message, format);
+                clients.retain_mut(|client| {
+                    if !client.open {
+                        return true;
+                    }
+                    match client.stream.write_all(&frame) {
+                        Ok(()) => true,
+                        Err(e) => {
+                            info!("Stream client {} gone: {}", client.peer, e);
+                            false
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// A WebSocket client, as it connects, and once it has.
+struct Client {
+    stream: TcpStream,
+    peer: SocketAddr,
+    /// The handshake request, as far as it has come.
+    request: Vec<u8>,
+    open: bool,
+}
+
+impl Client {
+    /// Read as much of the handshake as has come, and answer it once it has
+    /// all come.  An error drops the client.
+    fn handshake(&mut self) -> io::Result<()> {
+        if self.open {
+            return Ok(());
+        }
+        let mut buffer = [0; 1024];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                Ok(n) => self.request.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e),
+            }
+        }
+        if !self.request.windows(4).any(|w| w == b"\r\n\r\n") {
+            if self.request.len() > MAX_REQUEST {
+                return Err(io::Error::new(ErrorKind::InvalidData, "request too long"));
+            }
+            return Ok(());
+        }
+        let request = String::from_utf8_lossy(&self.request);
+        let key = request.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("sec-websocket-key")
+                .then(|| value.trim().to_string())
+        });
+        let Some(key) = key else {
+            self.stream
+                .write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+            return Err(io::Error::new(ErrorKind::InvalidData, "not a WebSocket"));
+        };
+        let accept = base64(&sha1(format!("{}{}", key, WEBSOCKET_GUID).as_bytes()));
+        write!(
+            self.stream,
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+             Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            accept
+        )?;
+        info!("Streaming to {}", self.peer);
+        self.open = true;
+        Ok(())
+    }
+}
+
+/// A message as one WebSocket frame, from the server, so unmasked.
+fn websocket_frame(message: &[u8], format: Format) -> Vec<u8> {
+    let opcode = match format {
+        Format::Json => 0x1,
+        Format::Binary => 0x2,
+    };
+    let mut frame = vec![0x80 | opcode];
+    match message.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend((len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend((len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(message);
+    frame
+}
+
+/// The SHA-1 digest, which the WebSocket handshake needs, and nothing else.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend((data.len() as u64 * 8).to_be_bytes());
+    for block in padded.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..20 => ((b & c) | (!b & d), 0x5A827999),
+                20..40 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..60 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, t);
+        }
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+    let mut digest = [0; 20];
+    for (out, h) in digest.chunks_mut(4).zip(h) {
+        out.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard, padded base64.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[derive(Resource)]
+struct TelemetryStream {
+    transport: Transport,
+    channels: Vec<Channel>,
+    format: Format,
+    /// Seconds of real time between samples.
+    interval: f64,
+    /// When the next sample is due.
+    next: f64,
+}
+
+impl TelemetryStream {
+    fn columns(&self) -> Vec<&'static str> {
+        self.channels
+            .iter()
+            .flat_map(|channel| channel.columns().iter().copied())
+            .collect()
+    }
+
+    /// A sample, as a message in the format.
+    fn message(&self, time: f64, craft: Option<CraftId>, values: &[f64]) -> Vec<u8> {
+        match self.format {
+            Format::Json => {
+                let mut object = serde_json::Map::new();
+                object.insert("time_s".to_string(), time.into());
+                object.insert("craft_id".to_string(), craft.map(|id| id.0).into());
+                for (column, value) in self.columns().into_iter().zip(values) {
+                    object.insert(column.to_string(), (*value).into());
+                }
+                serde_json::Value::Object(object).to_string().into_bytes()
+            }
+            Format::Binary => {
+                let mut message = Vec::with_capacity(18 + 8 * values.len());
+                message.extend(time.to_le_bytes());
+                message.extend(craft.map_or(0, |id| id.0).to_le_bytes());
+                message.extend((values.len() as u16).to_le_bytes());
+                for value in values {
+                    message.extend(value.to_le_bytes());
+                }
+                message
+            }
+        }
+    }
+}
+
+pub struct StreamPlugin {
+    transport: Transport,
+    rate: f64,
+    channels: Vec<Channel>,
+    format: Format,
+}
+
+impl StreamPlugin {
+    pub fn new(
+        address: &str,
+        rate: f64,
+        channels: Vec<Channel>,
+        format: Format,
+    ) -> io::Result<Self> {
+        Ok(StreamPlugin {
+            transport: Transport::open(address)?,
+            rate,
+            channels,
+            format,
+        })
+    }
+}
+
+impl Plugin for StreamPlugin {
+    fn build(&self, app: &mut App) {
+        let transport = self
+            .transport
+            .try_clone()
+            .expect("Unable to use the stream's socket");
+        info!("Streaming telemetry to {}", transport.describe());
+        app.insert_resource(TelemetryStream {
+            transport,
+            channels: self.channels.clone(),
+            format: self.format,
+            interval: 1.0 / self.rate.max(1.0e-6),
+            next: 0.0,
+        });
+        app.add_systems(Update, stream_telemetry);
+        app.add_console_command(
+            "stream",
+            "stream [rate <per s> | format json|binary | channels <channel,...>]   the live telemetry",
+            stream_command,
+        );
+    }
+}
+
+fn stream_telemetry(
+    real: Res<Time<Real>>,
+    fixed: Res<Time<Fixed>>,
+    mut stream: ResMut<TelemetryStream>,
+    source: TelemetrySource,
+) {
+    stream.transport.accept();
+    let now = real.elapsed_secs_f64();
+    if now < stream.next {
+        return;
+    }
+    stream.next = (stream.next + stream.interval).max(now);
+    let Some((id, values)) = source.sample(&stream.channels) else {
+        return;
+    };
+    let message = stream.message(fixed.elapsed_secs_f64(), id, &values);
+    let format = stream.format;
+    stream.transport.send(&message, format);
+}
+
+fn stream_command(In(args): In<Vec<String>>, mut stream: ResMut<TelemetryStream>) -> ConsoleReply {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] => {}
+        ["rate", rate] => {
+            let rate: f64 = parse_arg(rate)?;
+            if rate <= 0.0 {
+                return Err("The rate must be more than zero".to_string());
+            }
+            stream.interval = 1.0 / rate;
+        }
+        ["format", format] => stream.format = Format::parse(format)?,
+        ["channels", names] => {
+            stream.channels = names
+                .split(',')
+                .map(Channel::parse)
+                .collect::<Result<_, _>>()?;
+        }
+        _ => {
+            return Err(
+                "stream [rate <per s> | format json|binary | channels <channel,...>]".to_string(),
+            );
+        }
+    }
+    Ok(format!(
+        "{}, {:?}, {} per s: {}",
+        stream.transport.describe(),
+        stream.format,
+        1.0 / stream.interval,
+        stream.columns().join(" ")
+    ))
+}
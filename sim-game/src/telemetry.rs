@@ -0,0 +1,239 @@
+//! Telemetry logging.
+//!
+//! `scifisim --telemetry <file.csv> [rate] [channels]` samples the player
+//! ship at `rate` samples per second of sim time (1 by default), and writes
+//! one CSV row per sample, for looking over afterwards in something like
+//! Python or Matlab.  The channels are a comma separated list, such as
+//! `position,altitude,attitude`, and default to all of them.  Everything is
+//! relative to the earth, in the inertial frame, in the sim's units: km, km/s,
+//! radians, and SI for the craft itself.  Each row starts with the time, and
+//! the id of the craft flown then (see `ship::registry`), as that can change.
+//!
+//! The same channels can be streamed live, with the `stream` feature (see
+//! `stream`).
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use sim_astro::{EarthMarker, geodesy::Geodetic};
+use sim_core::{
+    AttitudeControl, AttitudeState, LinearControl, OrbitalBody, PostPhysicsSet, SizedBody,
+};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use crate::ship::{MassProperties, PlayerShip, registry::CraftId};
+
+/// The quantities that can be logged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Channel {
+    /// Position, km.
+    Position,
+    /// Velocity, km/s.
+    Velocity,
+    /// Geodetic altitude, km.
+    Altitude,
+    /// The body to world quaternion.
+    Attitude,
+    /// Angular rate, rad/s, BODY frame.
+    Rate,
+    /// The craft's mass, kg.  There is no separate propellant yet, so this is
+    /// where it would show.
+    Mass,
+    /// The torque applied by the thrusters, N*m, BODY frame.
+    Torque,
+    /// The acceleration from the craft's own thrust and drag, m/s^2, BODY
+    /// frame.
+    Accel,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 8] = [
+        Channel::Position,
+        Channel::Velocity,
+        Channel::Altitude,
+        Channel::Attitude,
+        Channel::Rate,
+        Channel::Mass,
+        Channel::Torque,
+        Channel::Accel,
+    ];
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "position" => Ok(Channel::Position),
+            "velocity" => Ok(Channel::Velocity),
+            "altitude" => Ok(Channel::Altitude),
+            "attitude" => Ok(Channel::Attitude),
+            "rate" => Ok(Channel::Rate),
+            "mass" | "propellant" => Ok(Channel::Mass),
+            "torque" => Ok(Channel::Torque),
+            "accel" => Ok(Channel::Accel),
+            _ => Err(format!("Unknown telemetry channel {:?}", name)),
+        }
+    }
+
+    /// The CSV column names.
+    pub fn columns(&self) -> &'static [&'static str] {
+        match self {
+            Channel::Position => &["x_km", "y_km", "z_km"],
+            Channel::Velocity => &["vx_km_s", "vy_km_s", "vz_km_s"],
+            Channel::Altitude => &["alt_km"],
+            Channel::Attitude => &["qw", "qx", "qy", "qz"],
+            Channel::Rate => &["wx_rad_s", "wy_rad_s", "wz_rad_s"],
+            Channel::Mass => &["mass_kg"],
+            Channel::Torque => &["tx_nm", "ty_nm", "tz_nm"],
+            Channel::Accel => &["ax_m_s2", "ay_m_s2", "az_m_s2"],
+        }
+    }
+}
+
+#[derive(Resource)]
+struct TelemetryLog {
+    out: BufWriter<File>,
+    channels: Vec<Channel>,
+    /// Seconds between samples.
+    interval: f64,
+    /// When the next sample is due.
+    next: f64,
+}
+
+pub struct TelemetryPlugin {
+    file: File,
+    rate: f64,
+    channels: Vec<Channel>,
+}
+
+impl TelemetryPlugin {
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        rate: f64,
+        channels: Vec<Channel>,
+    ) -> std::io::Result<Self> {
+        Ok(TelemetryPlugin {
+            file: File::create(path)?,
+            rate,
+            channels,
+        })
+    }
+}
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        let file = self
+            .file
+            .try_clone()
+            .expect("Unable to use the telemetry file");
+        let mut out = BufWriter::new(file);
+        let mut header = vec!["time_s", "craft_id"];
+        for channel in &self.channels {
+            header.extend(channel.columns());
+        }
+        if let Err(e) = writeln!(out, "{}", header.join(",")) {
+            error!("Unable to write telemetry: {}", e);
+        }
+        app.insert_resource(TelemetryLog {
+            out,
+            channels: self.channels.clone(),
+            interval: 1.0 / self.rate.max(1.0e-6),
+            next: 0.0,
+        });
+        app.add_systems(FixedUpdate, sample_telemetry.after(PostPhysicsSet));
+    }
+}
+
+/// The player ship, and the earth, as the channels are sampled from.
+#[allow(clippy::type_complexity)]
+#[derive(SystemParam)]
+pub struct TelemetrySource<'w, 's> {
+    ship: Query<
+        'w,
+        's,
+        (
+            &'static OrbitalBody,
+            &'static AttitudeState,
+            &'static MassProperties,
+            Option<&'static AttitudeControl>,
+            Option<&'static LinearControl>,
+            Option<&'static CraftId>,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<
+        'w,
+        's,
+        (
+            &'static OrbitalBody,
+            &'static AttitudeState,
+            &'static SizedBody,
+        ),
+        With<EarthMarker>,
+    >,
+}
+
+impl TelemetrySource<'_, '_> {
+    /// The id of the craft flown, and the channels' values, in the order of
+    /// their columns, if there is a ship.
+    pub fn sample(&self, channels: &[Channel]) -> Option<(Option<CraftId>, Vec<f64>)> {
+        let (
+            Ok((orbital, attitude, mass, control, linear, id)),
+            Ok((earth, earth_attitude, earth_size)),
+        ) = (self.ship.single(), self.earth.single())
+        else {
+            return None;
+        };
+        let mut row = vec![];
+        for channel in channels {
+            match channel {
+                Channel::Position => row.extend((orbital.pos - earth.pos).iter()),
+                Channel::Velocity => row.extend((orbital.vel - earth.vel).iter()),
+                Channel::Altitude => row.push(
+                    Geodetic::from_world(earth, earth_attitude, &earth_size.radii, &orbital.pos)
+                        .alt,
+                ),
+                Channel::Attitude => {
+                    let q = attitude.q_bw;
+                    row.extend([q.w, q.i, q.j, q.k]);
+                }
+                Channel::Rate => row.extend(attitude.omega_b.iter()),
+                Channel::Mass => row.push(mass.mass),
+                Channel::Torque => {
+                    let torque = control.map_or(Default::default(), |c| {
+                        c.alpha_b.component_mul(&mass.inertia_b)
+                    });
+                    row.extend(torque.iter());
+                }
+                Channel::Accel => {
+                    // km/s^2 to m/s^2.
+                    let accel = linear.map_or(Default::default(), |l| l.accel_b * 1000.0);
+                    row.extend(accel.iter());
+                }
+            }
+        }
+        Some((id.copied(), row))
+    }
+}
+
+fn sample_telemetry(time: Res<Time>, mut log: ResMut<TelemetryLog>, source: TelemetrySource) {
+    let now = time.elapsed_secs_f64();
+    if now < log.next {
+        return;
+    }
+    while log.next <= now {
+        log.next += log.interval;
+    }
+    let Some((id, row)) = source.sample(&log.channels) else {
+        return;
+    };
+
+    let id = id.map_or(String::new(), |id| id.0.to_string());
+    let line = [now.to_string(), id]
+        .into_iter()
+        .chain(row.iter().map(|v| v.to_string()))
+        .collect::<Vec<_>>()
+        .join(",");
+    if let Err(e) = writeln!(log.out, "{}", line).and_then(|()| log.out.flush()) {
+        error!("Unable to write telemetry: {}", e);
+    }
+}
@@ -1,5 +0,0 @@
-//! Physics simulation library for rigid body dynamics.
-
-mod attitude;
-
-pub use attitude::AttitudeState;
@@ -0,0 +1,62 @@
+//! Check that drawn rotations turn things the same way the sim does.
+//!
+//! A body's or craft's attitude is drawn with `sim_quat_to_bevy`, and its
+//! position placed with `sim_to_bevy`, so the two have to agree: turning a
+//! point in Bevy's axes has to land where turning it in the sim's, and then
+//! moving it to Bevy's, does.  A craft's model is read into the sim's axes
+//! with `bevy_to_sim` (for its mass properties), so a point on the model has
+//! to be drawn where its attitude puts it in the sim.  The rotations are
+//! about each axis, and one about a skew axis, as a tilted pole is, and a
+//! point off by more than a part in a hundred thousand fails.
+//!
+//! Run with: cargo run -p sim-render --example rotations
+//!
+//! `cargo test` runs it too, and fails if a point is drawn out of place.
+
+extern crate nalgebra as na;
+
+use bevy::math::Vec3;
+use na::{UnitQuaternion, Vector3};
+use sim_render::{bevy_to_sim, sim_quat_to_bevy, sim_to_bevy};
+
+/// The most a turned unit vector may be off by.
+const ERROR: f32 = 1.0e-5;
+
+fn main() -> Result<(), String> {
+    let rotations = [
+        ("about X", Vector3::x(), 0.7),
+        ("about Y", Vector3::y(), -1.2),
+        ("about Z", Vector3::z(), 2.5),
+        ("about a skew axis", Vector3::new(0.3, -0.5, 0.8), 0.41),
+    ];
+    let points = [
+        Vector3::x(),
+        Vector3::y(),
+        Vector3::z(),
+        Vector3::new(0.6, -0.48, 0.64),
+    ];
+    let models = [Vec3::X, Vec3::Y, Vec3::Z, Vec3::new(0.48, 0.8, -0.36)];
+
+    let mut failed = Vec::new();
+    for (name, axis, angle) in rotations {
+        let q = UnitQuaternion::from_axis_angle(&na::Unit::new_normalize(axis), angle);
+        let drawn = sim_quat_to_bevy(&q);
+        let mut worst: f32 = 0.0;
+        for point in &points {
+            worst = worst.max((drawn * sim_to_bevy(point) - sim_to_bevy(&(q * point))).length());
+        }
+        for model in models {
+            worst = worst.max((drawn * model - sim_to_bevy(&(q * bevy_to_sim(&model)))).length());
+        }
+        println!("{}: off by {:.2e}", name, worst);
+        if worst > ERROR {
+            failed.push(name);
+        }
+    }
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Drawn out of place: {}", failed.join(", ")))
+    }
+}
@@ -0,0 +1,137 @@
+//! The bodies, in the 3D scene.
+//!
+//! Each body gets a sphere of its own, placed from the floating origin, and
+//! brought in to its shell if it is far (see `origin`).  The earth has its
+//! day and night maps (see `earth`), the sun is drawn unlit, and the rest are
+//! plain, lit by the ship's light, which comes from the sun.
+
+use bevy::{
+    light::{NotShadowCaster, NotShadowReceiver},
+    prelude::*,
+};
+use sim_astro::{EarthMarker, SmallBodyMarker};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, SizedBody};
+use sim_game::ship::{PlayerShip, sunlight::Sunlight};
+
+use crate::{
+    earth::{EarthMaterial, body_sphere, earth_material},
+    interpolate::{Interpolation, LastStep},
+    origin::FloatingOrigin,
+    sim_quat_to_bevy, sim_to_bevy,
+};
+
+/// The color of the bodies that are neither the earth nor the sun.
+const BODY_COLOR: Color = Color::srgb(0.6, 0.6, 0.6);
+
+/// The sun's color.
+const SUN_COLOR: Color = Color::srgb(1.0, 0.95, 0.85);
+
+/// The scene's stand-in for a body.
+#[derive(Component)]
+pub struct BodyView(pub Entity);
+
+#[derive(Default)]
+pub struct BodyViewPlugin;
+
+impl Plugin for BodyViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (spawn_body_views, update_body_views).chain());
+    }
+}
+
+/// Give each body a sphere.  The sun is the most massive body.  The small
+/// bodies are drawn as balls of their own (see `small_bodies`).
+#[allow(clippy::type_complexity)]
+fn spawn_body_views(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut earth_materials: ResMut<Assets<EarthMaterial>>,
+    bodies: Query<(Entity, Has<EarthMarker>), (Added<SizedBody>, Without<SmallBodyMarker>)>,
+    massive: Query<(Entity, &MassiveBody)>,
+) {
+    if bodies.is_empty() {
+        return;
+    }
+    let sun = massive
+        .iter()
+        .max_by(|a, b| a.1.gm.total_cmp(&b.1.gm))
+        .map(|(entity, _)| entity);
+    let sphere = meshes.add(body_sphere());
+    for (entity, is_earth) in bodies.iter() {
+        let view = (
+            Mesh3d(sphere.clone()),
+            Transform::default(),
+            NotShadowCaster,
+            NotShadowReceiver,
+            BodyView(entity),
+        );
+        if is_earth {
+            let material = earth_materials.add(earth_material(&asset_server));
+            commands.spawn((view, MeshMaterial3d(material)));
+            continue;
+        }
+        // Seen from under a sky, the other bodies are overhead, out of the
+        // haze along the ground.
+        let material = if Some(entity) == sun {
+            StandardMaterial {
+                base_color: SUN_COLOR,
+                unlit: true,
+                fog_enabled: false,
+                ..default()
+            }
+        } else {
+            StandardMaterial {
+                base_color: BODY_COLOR,
+                perceptual_roughness: 1.0,
+                fog_enabled: false,
+                ..default()
+            }
+        };
+        commands.spawn((view, MeshMaterial3d(materials.add(material))));
+    }
+}
+
+/// Place the bodies about the floating origin, and light the earth's night
+/// side from where the sun is.
+fn update_body_views(
+    origin: Res<FloatingOrigin>,
+    interpolation: Res<Interpolation>,
+    ship: Query<&Sunlight, With<PlayerShip>>,
+    mut views: Query<(
+        Entity,
+        &BodyView,
+        &mut Transform,
+        Option<&MeshMaterial3d<EarthMaterial>>,
+    )>,
+    bodies: Query<(&OrbitalBody, &SizedBody, &AttitudeState, Option<&LastStep>)>,
+    mut commands: Commands,
+    mut earth_materials: ResMut<Assets<EarthMaterial>>,
+) {
+    let sun = ship
+        .single()
+        .ok()
+        .map(|sunlight| sim_to_bevy(&sunlight.sun_w));
+    for (entity, view, mut transform, earth) in views.iter_mut() {
+        let Ok((orbital, size, attitude, last)) = bodies.get(view.0) else {
+            // The body is gone.
+            commands.entity(entity).despawn();
+            continue;
+        };
+        // km to m.
+        let radii = size.radii * 1000.0;
+        let pos = interpolation.pos(orbital, last);
+        let (place, shrunk) = origin.on_shell(&pos, radii.max());
+        transform.translation = place;
+        transform.rotation = sim_quat_to_bevy(&interpolation.q_bw(attitude, last));
+        // Sim X, Y, Z are bevy X, -Z, Y.
+        transform.scale = Vec3::new(radii.x as f32, radii.z as f32, radii.y as f32) * shrunk;
+        if let Some((material, sun)) = earth
+            .and_then(|earth| earth_materials.get_mut(earth))
+            .zip(sun)
+        {
+            material.extension.set_sun(sun);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! The debris clouds, in the 3D scene.
+
+use bevy::{color::palettes::css::ORANGE, prelude::*};
+use sim_game::debris::Debris;
+
+use crate::origin::FloatingOrigin;
+
+/// The color of the fragments.
+pub const DEBRIS_COLOR: Srgba = ORANGE;
+
+/// How big a fragment is drawn, at least, as a fraction of its distance, so
+/// that far off ones are still specks, rather than nothing.
+const SPECK: f64 = 0.002;
+
+#[derive(Default)]
+pub struct DebrisViewPlugin;
+
+impl Plugin for DebrisViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, draw_debris);
+    }
+}
+
+/// Draw each fragment as a little ball, around the ship at the bevy origin,
+/// in meters.
+fn draw_debris(mut gizmos: Gizmos, debris: Res<Debris>, origin: Res<FloatingOrigin>) {
+    for fragment in debris.0.iter().flat_map(|cloud| &cloud.fragments) {
+        let Some(world) = &fragment.world else {
+            continue;
+        };
+        let rel = origin.offset(&world.pos);
+        let radius = fragment.radius.max(rel.norm() * SPECK);
+        gizmos.sphere(
+            Isometry3d::from_translation(origin.place(&world.pos)),
+            radius as f32,
+            DEBRIS_COLOR,
+        );
+    }
+}
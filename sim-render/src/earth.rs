@@ -0,0 +1,133 @@
+//! The earth's material: its day map, lit by the sun, with its night map, of
+//! the city lights, showing through on the side turned away.
+//!
+//! Both maps are equirectangular, west to east from longitude -180, north at
+//! the top, as NASA's Blue Marble and Black Marble are, and go in
+//! `assets/tex` (see `EARTH_DAY` and `EARTH_NIGHT`).  They aren't kept in the
+//! repo: `assets/tex/fetch.sh` downloads them from NASA Visible Earth, and
+//! makes PNGs of them, with ImageMagick.  Without the day map, the earth is
+//! its plain color, and without the night map, its night side is just dark.
+
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::render_resource::AsBindGroup,
+    shader::ShaderRef,
+};
+
+/// The earth's day and night maps.
+pub const EARTH_DAY: &str = "tex/earth_day.png";
+pub const EARTH_NIGHT: &str = "tex/earth_night.png";
+
+/// The earth's color, without the day map.
+pub const EARTH_COLOR: Color = Color::srgb(0.2, 0.4, 0.8);
+
+const SHADER_ASSET_PATH: &str = "shaders/earth.wgsl";
+
+/// The earth's material.
+pub type EarthMaterial = ExtendedMaterial<StandardMaterial, EarthExtension>;
+
+/// What the earth's material adds to the standard one: the night map, and
+/// where the sun is.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub struct EarthExtension {
+    /// The direction to the sun, in Bevy's axes, and in `w`, how bright the
+    /// night map is (0 without one).
+    #[uniform(100)]
+    pub sun: Vec4,
+    #[texture(101)]
+    #[sampler(102)]
+    pub night: Option<Handle<Image>>,
+}
+
+impl MaterialExtension for EarthExtension {
+    fn fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+
+    fn deferred_fragment_shader() -> ShaderRef {
+        SHADER_ASSET_PATH.into()
+    }
+}
+
+impl EarthExtension {
+    /// Point the material's sun along `sun`, in Bevy's axes.
+    pub fn set_sun(&mut self, sun: Vec3) {
+        let brightness = if self.night.is_some() { 1.0 } else { 0.0 };
+        self.sun = sun.normalize_or_zero().extend(brightness);
+    }
+}
+
+/// The earth's material, with its maps.
+pub fn earth_material(asset_server: &AssetServer) -> EarthMaterial {
+    ExtendedMaterial {
+        base: StandardMaterial {
+            base_color_texture: Some(asset_server.load(EARTH_DAY)),
+            perceptual_roughness: 0.8,
+            ..default()
+        },
+        extension: EarthExtension {
+            sun: Vec4::new(1.0, 0.0, 0.0, 1.0),
+            night: Some(asset_server.load(EARTH_NIGHT)),
+        },
+    }
+}
+
+/// A unit sphere for a body, in Bevy's axes, with the maps' longitudes and
+/// latitudes on it: its pole along Y, and the prime meridian on X.
+pub fn body_sphere() -> Mesh {
+    // Bevy's UV sphere has its pole on Z, with U going east from X.  Turned
+    // half way about the pole, U starts from longitude -180, and turned
+    // down, the pole is on Y, and the sim's Y on -Z.
+    Sphere::new(1.0).mesh().uv(96, 48).rotated_by(
+        Quat::from_rotation_x(-std::f32::consts::FRAC_PI_2)
+            * Quat::from_rotation_z(std::f32::consts::PI),
+    )
+}
+
+#[derive(Default)]
+pub struct EarthMaterialPlugin;
+
+impl Plugin for EarthMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MaterialPlugin::<EarthMaterial>::default());
+        app.add_systems(Update, drop_missing_maps);
+    }
+}
+
+/// Go without whichever maps can't be loaded, as they aren't in the repo
+/// until fetched.
+fn drop_missing_maps(asset_server: Res<AssetServer>, mut materials: ResMut<Assets<EarthMaterial>>) {
+    let failed = |handle: &Option<Handle<Image>>| {
+        handle
+            .as_ref()
+            .is_some_and(|handle| asset_server.load_state(handle).is_failed())
+    };
+    // Only looking, so that the materials aren't all marked changed.
+    let missing: Vec<_> = materials
+        .iter()
+        .filter(|(_, m)| failed(&m.base.base_color_texture) || failed(&m.extension.night))
+        .map(|(id, _)| id)
+        .collect();
+    for id in missing {
+        let Some(material) = materials.get_mut(id) else {
+            continue;
+        };
+        if failed(&material.base.base_color_texture) {
+            warn!(
+                "No {}, the earth is its plain color (run assets/tex/fetch.sh)",
+                EARTH_DAY
+            );
+            material.base.base_color_texture = None;
+            material.base.base_color = EARTH_COLOR;
+        }
+        if failed(&material.extension.night) {
+            warn!(
+                "No {}, the earth's night side is dark (run assets/tex/fetch.sh)",
+                EARTH_NIGHT
+            );
+            material.extension.night = None;
+            material.extension.sun.w = 0.0;
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Drawing between the physics steps.
+//!
+//! The physics moves on the fixed step, and the frames come when they come,
+//! so drawn straight from the state, the craft, and the navball with it,
+//! would move in jerks: some frames a step on, some two, some none.  Instead,
+//! everything that moves keeps where it was before the last step
+//! (`LastStep`), and is drawn as far from there to where it is now as the
+//! clock is into the next step (see `SimClock::fraction`): its position along
+//! the straight line between them, and its attitude slerped.  What is drawn
+//! is then up to a step behind the physics, which at a step's length can't
+//! be seen.
+//!
+//! Whatever has jumped in the last step, rather than moved, such as on being
+//! put back from a snapshot, is drawn where it is.
+
+use bevy::{app::RunFixedMainLoopSystems, prelude::*};
+use sim_core::{AttitudeState, OrbitalBody, clock::SimClock};
+
+/// How much further than its speed would take it, km, something can move in
+/// a step before it counts as having jumped.
+const JUMP_SLACK: f64 = 1.0;
+
+/// How much further than its rate would turn it, rad, likewise.
+const TURN_SLACK: f64 = 0.1;
+
+/// Where something was before the last fixed step, km, world frame, and how
+/// it was turned, body to world, if it has an attitude.
+#[derive(Component, Clone, Debug)]
+pub struct LastStep {
+    pub pos: na::Vector3<f64>,
+    pub q_bw: Option<na::UnitQuaternion<f64>>,
+}
+
+/// How far the frame is between the last step and the next.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Interpolation {
+    /// From 0, at the last step, to 1, at the next.
+    pub fraction: f64,
+    /// The fixed step, s.
+    pub step: f64,
+}
+
+impl Interpolation {
+    /// Where to draw something, km, world frame.
+    pub fn pos(&self, orbital: &OrbitalBody, last: Option<&LastStep>) -> na::Vector3<f64> {
+        let reach = orbital.vel.norm() * self.step * 2.0 + JUMP_SLACK;
+        match last.filter(|last| (orbital.pos - last.pos).norm() <= reach) {
+            Some(last) => last.pos.lerp(&orbital.pos, self.fraction),
+            None => orbital.pos,
+        }
+    }
+
+    /// How to draw something turned, body to world.
+    pub fn q_bw(
+        &self,
+        attitude: &AttitudeState,
+        last: Option<&LastStep>,
+    ) -> na::UnitQuaternion<f64> {
+        let reach = attitude.omega_b.norm() * self.step * 2.0 + TURN_SLACK;
+        last.and_then(|last| last.q_bw)
+            .filter(|q_bw| q_bw.angle_to(&attitude.q_bw) <= reach)
+            .and_then(|q_bw| q_bw.try_slerp(&attitude.q_bw, self.fraction, 1.0e-9))
+            .unwrap_or(attitude.q_bw)
+    }
+
+    /// The attitude to draw something with, at its rate now.
+    pub fn attitude(&self, attitude: &AttitudeState, last: Option<&LastStep>) -> AttitudeState {
+        AttitudeState {
+            q_bw: self.q_bw(attitude, last),
+            omega_b: attitude.omega_b,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Interpolation>();
+        app.add_systems(FixedFirst, remember_last_step);
+        // Before the origin is placed, as it is placed on the craft as drawn.
+        app.add_systems(
+            RunFixedMainLoop,
+            update_interpolation
+                .in_set(RunFixedMainLoopSystems::AfterFixedMainLoop)
+                .before(crate::origin::follow_craft),
+        );
+    }
+}
+
+/// Before each step, keep where everything is, to draw from.
+fn remember_last_step(
+    mut commands: Commands,
+    mut kept: Query<(&OrbitalBody, Option<&AttitudeState>, &mut LastStep)>,
+    new: Query<(Entity, &OrbitalBody, Option<&AttitudeState>), Without<LastStep>>,
+) {
+    for (orbital, attitude, mut last) in kept.iter_mut() {
+        last.pos = orbital.pos;
+        last.q_bw = attitude.map(|a| a.q_bw);
+    }
+    for (entity, orbital, attitude) in new.iter() {
+        commands.entity(entity).insert(LastStep {
+            pos: orbital.pos,
+            q_bw: attitude.map(|a| a.q_bw),
+        });
+    }
+}
+
+/// See how far the clock is into the next step, once this frame's have run.
+fn update_interpolation(
+    clock: Res<SimClock>,
+    fixed: Res<Time<Fixed>>,
+    mut interpolation: ResMut<Interpolation>,
+) {
+    let step = fixed.timestep();
+    interpolation.fraction = clock.fraction(step).clamp(0.0, 1.0);
+    interpolation.step = step.as_secs_f64();
+}
@@ -0,0 +1,62 @@
+//! Drawing the sim.
+//!
+//! The sim is Z-up, in km, relative to the solar system barycenter.  Bevy is
+//! Y-up, and the scene is drawn around the ship, so everything passes through
+//! `sim_to_bevy` and `sim_quat_to_bevy` on its way to the screen, placed
+//! from the floating origin (see `origin`), as it is between the physics
+//! steps (see `interpolate`).  The plugins here put the sim's things in the
+//! 3D scene; the overlays are in `sim_ui`.
+
+// Recommended alias.
+extern crate nalgebra as na;
+
+use bevy::prelude::*;
+
+mod bodies;
+mod debris;
+pub mod earth;
+pub mod interpolate;
+pub mod origin;
+mod predict;
+mod ship;
+mod small_bodies;
+pub mod trail;
+
+pub use bodies::BodyViewPlugin;
+pub use debris::DebrisViewPlugin;
+pub use earth::EarthMaterialPlugin;
+pub use interpolate::InterpolationPlugin;
+pub use origin::FloatingOriginPlugin;
+pub use predict::PredictionViewPlugin;
+pub use ship::ShipViewPlugin;
+pub use small_bodies::SmallBodyViewPlugin;
+pub use trail::TrailViewPlugin;
+
+pub fn sim_to_bevy(v: &na::Vector3<f64>) -> Vec3 {
+    Vec3::new(v.x as f32, v.z as f32, -v.y as f32)
+}
+
+/// The other way, from Bevy's axes back to the sim's.
+pub fn bevy_to_sim(v: &Vec3) -> na::Vector3<f64> {
+    na::Vector3::new(v.x as f64, -v.z as f64, v.y as f64)
+}
+
+/// A rotation in the sim's axes, as the same rotation in Bevy's, so that it
+/// takes `sim_to_bevy(v)` to `sim_to_bevy(q * v)`.
+pub fn sim_quat_to_bevy(q: &na::UnitQuaternion<f64>) -> Quat {
+    // `r` turns the sim's axes to Bevy's, as `sim_to_bevy` does.
+    let r =
+        na::UnitQuaternion::from_axis_angle(&na::Vector3::x_axis(), -std::f64::consts::FRAC_PI_2);
+    let q = r * q * r.conjugate();
+    Quat::from_array([q.i as f32, q.j as f32, q.k as f32, q.w as f32])
+}
+
+/// The other way, from a Bevy rotation back to the sim's axes.
+pub fn bevy_quat_to_sim(q: &Quat) -> na::UnitQuaternion<f64> {
+    let r =
+        na::UnitQuaternion::from_axis_angle(&na::Vector3::x_axis(), -std::f64::consts::FRAC_PI_2);
+    let q = na::UnitQuaternion::from_quaternion(na::Quaternion::new(
+        q.w as f64, q.x as f64, q.y as f64, q.z as f64,
+    ));
+    r.conjugate() * q * r
+}
@@ -0,0 +1,94 @@
+//! The floating origin.
+//!
+//! The sim's positions are km from the solar system barycenter, some 1e8 km
+//! out, which an f32 can't hold to better than tens of km.  So nothing in the
+//! 3D scene is placed where it is, only where it is from the `FloatingOrigin`,
+//! the craft being flown, worked out in f64 and only then cut down to f32, in
+//! meters.  The craft sits at the Bevy origin, and whatever is near it is as
+//! exact as it can be.
+//!
+//! The bodies are too big and far for that, even so: the sun would be 1.5e11
+//! m off.  Whatever is further than `SHELL` is drawn nearer, on a shell whose
+//! distance goes with the log of the true one, and shrunk by as much, so it
+//! looks the same size, in the same direction, and in the same order, near to
+//! far (see `FloatingOrigin::on_shell`).  Nothing is then further off than
+//! `VIEW_DISTANCE`, which is where the camera's far plane can be.
+
+use bevy::{app::RunFixedMainLoopSystems, prelude::*};
+use sim_core::OrbitalBody;
+use sim_game::ship::PlayerShip;
+
+use crate::{
+    interpolate::{Interpolation, LastStep},
+    sim_to_bevy,
+};
+
+/// Out to here, m, things are drawn at their true distance.
+pub const SHELL: f64 = 1.0e5;
+
+/// The furthest anything is drawn, m, the sun from past Neptune with room to
+/// spare.
+pub const VIEW_DISTANCE: f32 = 1.0e7;
+
+/// Where the Bevy origin is, in the sim.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct FloatingOrigin {
+    /// km, world frame.
+    pub pos: na::Vector3<f64>,
+}
+
+impl FloatingOrigin {
+    /// Where `pos`, km, world frame, is in the scene, m.
+    pub fn place(&self, pos: &na::Vector3<f64>) -> Vec3 {
+        sim_to_bevy(&self.offset(pos))
+    }
+
+    /// How far `pos`, km, world frame, is from the origin, m, in the sim's
+    /// axes.
+    pub fn offset(&self, pos: &na::Vector3<f64>) -> na::Vector3<f64> {
+        // km to m.
+        (pos - self.pos) * 1000.0
+    }
+
+    /// Where a ball at `pos`, km, world frame, `radius` m across, is drawn,
+    /// and how much it is shrunk.  Out to `SHELL` from its near side, it is
+    /// where it is; past that, it is brought in to the shell, and shrunk as
+    /// much as it was brought in.
+    pub fn on_shell(&self, pos: &na::Vector3<f64>, radius: f64) -> (Vec3, f32) {
+        let offset = self.offset(pos);
+        let near = offset.norm() - radius;
+        let scale = if near > SHELL {
+            SHELL * (1.0 + (near / SHELL).ln()) / near
+        } else {
+            1.0
+        };
+        (sim_to_bevy(&(offset * scale)), scale as f32)
+    }
+}
+
+#[derive(Default)]
+pub struct FloatingOriginPlugin;
+
+impl Plugin for FloatingOriginPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FloatingOrigin>();
+        // As soon as the physics has stepped, so that everything drawn this
+        // frame is placed from where the craft is now.
+        app.add_systems(
+            RunFixedMainLoop,
+            follow_craft.in_set(RunFixedMainLoopSystems::AfterFixedMainLoop),
+        );
+    }
+}
+
+/// Keep the origin on the craft being flown, where it is drawn (see
+/// `interpolate`).
+pub(crate) fn follow_craft(
+    ship: Query<(&OrbitalBody, Option<&LastStep>), With<PlayerShip>>,
+    interpolation: Res<Interpolation>,
+    mut origin: ResMut<FloatingOrigin>,
+) {
+    if let Ok((ship, last)) = ship.single() {
+        origin.pos = interpolation.pos(ship, last);
+    }
+}
@@ -0,0 +1,54 @@
+//! The predicted path, in the 3D scene.
+
+use bevy::{color::palettes::css::GOLD, prelude::*};
+use sim_astro::EarthMarker;
+use sim_core::OrbitalBody;
+use sim_game::ship::{
+    PlayerShip,
+    predict::{Prediction, predict},
+};
+
+use crate::{
+    interpolate::{Interpolation, LastStep},
+    origin::FloatingOrigin,
+};
+
+/// The color of the path after the maneuver node.  This matches the node
+/// marker on the navball.
+const PREDICT_NODE_COLOR: Color = Color::srgb(0.2, 0.5, 1.0);
+
+#[derive(Default)]
+pub struct PredictionViewPlugin;
+
+impl Plugin for PredictionViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, draw_prediction.after(predict));
+    }
+}
+
+/// Draw the prediction in the 3D scene.  The ship sits at the bevy origin, in
+/// meters, so only the nearby part of the path will be within view.
+fn draw_prediction(
+    mut gizmos: Gizmos,
+    origin: Res<FloatingOrigin>,
+    interpolation: Res<Interpolation>,
+    ship: Query<&Prediction, With<PlayerShip>>,
+    earth: Query<(&OrbitalBody, Option<&LastStep>), (With<EarthMarker>, Without<PlayerShip>)>,
+) {
+    let Ok(prediction) = ship.single() else {
+        return;
+    };
+    let Ok((earth, last)) = earth.single() else {
+        return;
+    };
+    let earth = interpolation.pos(earth, last);
+
+    for (i, conic) in prediction.conics.iter().enumerate() {
+        let color = if i == 0 {
+            Color::from(GOLD)
+        } else {
+            PREDICT_NODE_COLOR
+        };
+        gizmos.linestrip(conic.iter().map(|p| origin.place(&(earth + p))), color);
+    }
+}
@@ -0,0 +1,109 @@
+//! The crafts' models, on the screen.
+
+use bevy::{asset, camera::primitives::Aabb, prelude::*};
+use sim_core::{AttitudeState, OrbitalBody};
+use sim_game::ship::{
+    Craft, MassProperties,
+    parts::{MassFromModel, Part},
+};
+
+use crate::{
+    bevy_to_sim,
+    interpolate::{Interpolation, LastStep},
+    origin::FloatingOrigin,
+    sim_quat_to_bevy,
+};
+
+#[derive(Default)]
+pub struct ShipViewPlugin;
+
+impl Plugin for ShipViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (add_ship_model, update_ship).chain());
+        app.add_systems(Update, mass_from_model);
+    }
+}
+
+fn add_ship_model(
+    mut commands: Commands,
+    asset_server: Res<asset::AssetServer>,
+    ships: Query<Entity, Added<Craft>>,
+) {
+    for entity in ships.iter() {
+        commands.entity(entity).insert(SceneRoot(
+            asset_server.load(GltfAssetLabel::Scene(0).from_asset("models/output.gltf")),
+        ));
+    }
+}
+
+// Update the crafts' transforms. We are built around 0,0,0 in bevy space as the center of the ship being flown (the floating origin), so this is its orientation, and the others' places, in m, around it.
+fn update_ship(
+    origin: Res<FloatingOrigin>,
+    interpolation: Res<Interpolation>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &OrbitalBody,
+            &AttitudeState,
+            Option<&LastStep>,
+        ),
+        With<Craft>,
+    >,
+) {
+    for (mut transform, orbital, state, last) in query.iter_mut() {
+        transform.translation = origin.place(&interpolation.pos(orbital, last));
+        transform.rotation = sim_quat_to_bevy(&interpolation.q_bw(state, last));
+    }
+}
+
+/// Work out a craft's mass properties from its model, taking each mesh's
+/// bounding box as a solid block, with the craft's mass shared out by volume.
+/// This waits for the meshes' bounds, which come a frame or so after the
+/// model loads.
+fn mass_from_model(
+    mut commands: Commands,
+    mut crafts: Query<(Entity, &Name, &GlobalTransform, &mut MassProperties), With<MassFromModel>>,
+    children: Query<&Children>,
+    meshes: Query<(&Aabb, &GlobalTransform), With<Mesh3d>>,
+) {
+    for (entity, name, root, mut mass) in crafts.iter_mut() {
+        let to_root = root.affine().inverse();
+        let blocks: Vec<(Vec3, Vec3)> = children
+            .iter_descendants(entity)
+            .filter_map(|child| meshes.get(child).ok())
+            .map(|(aabb, transform)| {
+                let to_craft = to_root * transform.affine();
+                let m = to_craft.matrix3;
+                let half = m.x_axis.abs() * aabb.half_extents.x
+                    + m.y_axis.abs() * aabb.half_extents.y
+                    + m.z_axis.abs() * aabb.half_extents.z;
+                (to_craft.transform_point3a(aabb.center).into(), half.into())
+            })
+            .collect();
+        let volume = |half: &Vec3| 8.0 * half.x as f64 * half.y as f64 * half.z as f64;
+        let total: f64 = blocks.iter().map(|(_, half)| volume(half)).sum();
+        if total <= 0.0 {
+            continue;
+        }
+        let parts: Vec<Part> = blocks
+            .iter()
+            .map(|(center, half)| {
+                // Bevy's axes are the sim's in another order, so the half
+                // widths just swap.
+                let half_b = bevy_to_sim(half).abs();
+                Part::block("mesh", mass.mass * volume(half) / total, half_b)
+                    .at(bevy_to_sim(center))
+            })
+            .collect();
+        *mass = MassProperties::from_parts(&parts);
+        info!(
+            "{}: mass properties from {} meshes, inertia {:.0} {:.0} {:.0} kg m^2",
+            name,
+            parts.len(),
+            mass.inertia_b.x,
+            mass.inertia_b.y,
+            mass.inertia_b.z
+        );
+        commands.entity(entity).remove::<MassFromModel>();
+    }
+}
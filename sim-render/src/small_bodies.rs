@@ -0,0 +1,47 @@
+//! The small bodies, in the 3D scene.
+
+use bevy::{color::palettes::css::TAN, prelude::*};
+use sim_astro::SmallBodyMarker;
+use sim_core::{OrbitalBody, SizedBody};
+
+use crate::{
+    interpolate::{Interpolation, LastStep},
+    origin::FloatingOrigin,
+};
+
+/// The color of the small bodies.
+pub const SMALL_BODY_COLOR: Srgba = TAN;
+
+/// How big a small body is drawn, at least, as a fraction of its distance,
+/// so that it can be found from afar.
+const SPECK: f64 = 0.005;
+
+#[derive(Default)]
+pub struct SmallBodyViewPlugin;
+
+impl Plugin for SmallBodyViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, draw_small_bodies);
+    }
+}
+
+/// Draw each small body as a ball as big as its longest radius, around the
+/// ship at the bevy origin, in meters.  One without radii is just a speck.
+fn draw_small_bodies(
+    mut gizmos: Gizmos,
+    origin: Res<FloatingOrigin>,
+    interpolation: Res<Interpolation>,
+    bodies: Query<(&OrbitalBody, Option<&SizedBody>, Option<&LastStep>), With<SmallBodyMarker>>,
+) {
+    for (orbital, size, last) in bodies.iter() {
+        let pos = interpolation.pos(orbital, last);
+        let rel = origin.offset(&pos);
+        // km to m.
+        let radius = size.map_or(0.0, |size| size.radii.max() * 1000.0);
+        gizmos.sphere(
+            Isometry3d::from_translation(origin.place(&pos)),
+            radius.max(rel.norm() * SPECK) as f32,
+            SMALL_BODY_COLOR,
+        );
+    }
+}
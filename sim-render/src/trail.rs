@@ -0,0 +1,60 @@
+//! The breadcrumb trail, in the 3D scene.
+
+use bevy::{color::palettes::css::AQUA, prelude::*};
+use sim_astro::EarthMarker;
+use sim_core::OrbitalBody;
+use sim_game::ship::{
+    PlayerShip,
+    trail::{Trail, TrailSettings},
+};
+
+use crate::{
+    interpolate::{Interpolation, LastStep},
+    origin::FloatingOrigin,
+};
+
+/// The color of the trail, where it is newest.  It fades out to nothing at
+/// the oldest end.
+pub const TRAIL_COLOR: Srgba = AQUA;
+
+#[derive(Default)]
+pub struct TrailViewPlugin;
+
+impl Plugin for TrailViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, draw_trail);
+    }
+}
+
+/// Draw the trail behind the ship, up to where it is now.  As with the
+/// prediction, the ship is at the bevy origin, in meters.
+fn draw_trail(
+    mut gizmos: Gizmos,
+    fixed: Res<Time<Fixed>>,
+    settings: Res<TrailSettings>,
+    origin: Res<FloatingOrigin>,
+    interpolation: Res<Interpolation>,
+    ship: Query<&Trail, With<PlayerShip>>,
+    earth: Query<(&OrbitalBody, Option<&LastStep>), (With<EarthMarker>, Without<PlayerShip>)>,
+) {
+    let (Ok(trail), Ok((earth, last))) = (ship.single(), earth.single()) else {
+        return;
+    };
+    let earth = interpolation.pos(earth, last);
+    if trail.points.is_empty() {
+        return;
+    }
+
+    let now = fixed.elapsed_secs_f64();
+    gizmos.linestrip_gradient(
+        trail
+            .faded(now, settings.length)
+            .map(|(fade, pos)| {
+                (
+                    origin.place(&(earth + pos)),
+                    Color::from(TRAIL_COLOR.with_alpha(fade as f32)),
+                )
+            })
+            .chain([(Vec3::ZERO, Color::from(TRAIL_COLOR))]),
+    );
+}
@@ -0,0 +1,83 @@
+//! Filling the ephemeris cache from the kernels.
+//!
+//! Each body in a `SolarState`, the small ones too, is sampled over the span
+//! asked for, in the frame the sim uses, and fitted (see
+//! `sim_astro::ephemeris`).  Each body's intervals are a small part of its
+//! orbit about its primary, and no more than a few days, so the fit holds to
+//! well under a metre.  A body's samples are all taken in one call, under
+//! one lock.
+
+use sim_astro::{
+    SolarState,
+    ephemeris::{BodyEphemeris, DEFAULT_DEGREE, EphemerisCache},
+    hierarchy::{SUN_ID, naif_parent},
+};
+
+use crate::get_instance;
+
+/// The parts of its orbit each of a body's intervals covers.
+const INTERVALS_PER_ORBIT: f64 = 32.0;
+
+/// The shortest and longest intervals, in s.
+const MIN_INTERVAL: f64 = 600.0;
+const MAX_INTERVAL: f64 = 4.0 * 86400.0;
+
+/// The interval for the body with NAIF id `id`, from its period about the
+/// nearest body above it.
+fn interval(solar: &SolarState, id: i32, pos: &na::Vector3<f64>) -> f64 {
+    let mut parent = naif_parent(id).unwrap_or(SUN_ID);
+    let primary = loop {
+        if let Some(body) = solar.bodies.iter().find(|b| b.id.0 == parent) {
+            break Some(body);
+        }
+        match naif_parent(parent) {
+            Some(next) => parent = next,
+            None if parent != SUN_ID => parent = SUN_ID,
+            None => break None,
+        }
+    };
+    let Some(primary) = primary.filter(|primary| primary.id.0 != id) else {
+        return MAX_INTERVAL;
+    };
+    let r = (pos - primary.orbital.pos).norm();
+    let period = 2.0 * std::f64::consts::PI * (r.powi(3) / primary.massive.gm).sqrt();
+    (period / INTERVALS_PER_ORBIT).clamp(MIN_INTERVAL, MAX_INTERVAL)
+}
+
+/// The cache for every body in `solar`, from `start` to `stop`, in seconds
+/// past J2000.  The kernels have to cover that span (see `coverage`).
+pub fn ephemeris_cache(
+    solar: &SolarState,
+    start: f64,
+    stop: f64,
+) -> Result<EphemerisCache, String> {
+    let sl = get_instance();
+    let ids = solar
+        .bodies
+        .iter()
+        .map(|b| (b.id.0, b.orbital.pos))
+        .chain(solar.small_bodies.iter().map(|b| (b.id.0, b.orbital.pos)));
+
+    let mut cache = EphemerisCache::default();
+    for (id, pos) in ids {
+        let name = id.to_string();
+        let body = BodyEphemeris::fit(
+            id,
+            start,
+            stop,
+            interval(solar, id, &pos),
+            DEFAULT_DEGREE,
+            |times| {
+                let states = sl
+                    .spkezr_many(&[name.as_str()], times, "ECLIPJ2000", "NONE", "SSB")
+                    .map_err(|e| format!("Can't sample {}: {}", id, e))?;
+                Ok(states
+                    .column_iter()
+                    .map(|state| [state[0], state[1], state[2], state[3], state[4], state[5]])
+                    .collect())
+            },
+        )?;
+        cache.insert(body);
+    }
+    Ok(cache)
+}
@@ -0,0 +1,134 @@
+//! Body constants, cached.
+//!
+//! The GMs, radii and rotation constants are in the text kernels, and asking
+//! the kernel pool for each body's is hundreds of queries.  They only change
+//! when the kernels do, so they are taken out once into `CONSTANTS_CACHE`, with
+//! the size and time of each text kernel, and read back from there while those
+//! still match.  Changing, adding or removing a text kernel makes the next
+//! load take them out again.
+
+use serde::{Deserialize, Serialize};
+use std::{path::Path, time::UNIX_EPOCH};
+
+use crate::{SpiceError, get_instance, kernels};
+
+/// Where the cache is kept.
+pub const CONSTANTS_CACHE: &str = "assets/spice/constants.json";
+
+/// A body's constants, as the kernels have them.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BodyConstants {
+    /// The NAIF id.
+    pub id: i32,
+    pub name: String,
+    /// km^3/s^2.
+    pub gm: f64,
+    /// The ellipsoid's radii, in km, if it has one.
+    pub radii: Option<[f64; 3]>,
+    /// The IAU rotation model's polynomials, in degrees and days: the pole's
+    /// right ascension and declination, and the prime meridian.  Empty for a
+    /// body without one.
+    pub pole_ra: Vec<f64>,
+    pub pole_dec: Vec<f64>,
+    pub pm: Vec<f64>,
+}
+
+/// A kernel file, as it was when the constants were taken out.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct KernelStamp {
+    path: String,
+    /// Its size, and when it was last changed (seconds past the Unix epoch),
+    /// or None if it wasn't there.
+    len: Option<u64>,
+    modified: Option<u64>,
+}
+
+impl KernelStamp {
+    fn of(path: &Path) -> Self {
+        let metadata = path.metadata().ok();
+        KernelStamp {
+            path: path.display().to_string(),
+            len: metadata.as_ref().map(|m| m.len()),
+            modified: metadata
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs()),
+        }
+    }
+}
+
+/// Every body with a GM in the kernels.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BodyConstantsCache {
+    kernels: Vec<KernelStamp>,
+    pub bodies: Vec<BodyConstants>,
+}
+
+impl BodyConstantsCache {
+    /// The constants, from the cache if it is still good, or else taken out
+    /// of the kernels again, and saved for next time.
+    pub fn load() -> Result<Self, SpiceError> {
+        let text = kernels::chosen().map_err(SpiceError)?.text_paths();
+        let stamps: Vec<KernelStamp> = text.iter().map(|k| KernelStamp::of(k)).collect();
+        let cached = std::fs::File::open(CONSTANTS_CACHE)
+            .ok()
+            .and_then(|file| serde_json::from_reader::<_, Self>(file).ok());
+        if let Some(cache) = cached.filter(|cache| cache.kernels == stamps) {
+            return Ok(cache);
+        }
+
+        println!("Taking the body constants out of the kernels");
+        let cache = BodyConstantsCache {
+            kernels: stamps,
+            bodies: extract()?,
+        };
+        let saved = std::fs::File::create(CONSTANTS_CACHE)
+            .map_err(|e| e.to_string())
+            .and_then(|file| serde_json::to_writer(file, &cache).map_err(|e| e.to_string()));
+        if let Err(e) = saved {
+            eprintln!("Can't save {}: {}", CONSTANTS_CACHE, e);
+        }
+        Ok(cache)
+    }
+
+    pub fn get(&self, id: i32) -> Option<&BodyConstants> {
+        self.bodies.iter().find(|body| body.id == id)
+    }
+}
+
+/// The constants of every named body with a GM in the kernel pool.
+fn extract() -> Result<Vec<BodyConstants>, SpiceError> {
+    let sl = get_instance();
+    let mut bodies = Vec::new();
+    let mut start = 0;
+    loop {
+        let limit = 500;
+        let names = sl.gnpool("BODY*_GM", start, limit)?;
+        for gm_name in &names {
+            let Ok(id) = gm_name[4..gm_name.len() - 3].parse::<i32>() else {
+                continue;
+            };
+            let Some(name) = sl.bodc2n(id) else {
+                continue;
+            };
+            let item = |item: &str, room| sl.gdpool(&format!("BODY{}_{}", id, item), 0, room);
+            let Some(&gm) = item("GM", 1)?.first() else {
+                continue;
+            };
+            bodies.push(BodyConstants {
+                id,
+                name,
+                gm,
+                radii: item("RADII", 3)?.try_into().ok(),
+                pole_ra: item("POLE_RA", 3)?,
+                pole_dec: item("POLE_DEC", 3)?,
+                pm: item("PM", 3)?,
+            });
+        }
+        if names.len() < limit {
+            break;
+        }
+        start += names.len();
+    }
+    Ok(bodies)
+}
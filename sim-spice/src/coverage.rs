@@ -0,0 +1,31 @@
+//! How far the kernels go.
+//!
+//! Each SPK kernel has each body over some span of time, and nothing outside
+//! it: asking for a state there is a SPICE error.  These find the spans, so
+//! that the sim can keep to them (see `SolarState::coverage`).
+
+use crate::{SpiceError, Window, get_instance};
+
+/// The times the kernels have the body with NAIF id `id` for.  This is just
+/// the body's own segments: one given relative to a barycenter needs the
+/// barycenter's too.
+pub fn coverage(id: i32) -> Result<Window, SpiceError> {
+    get_instance().spkcov(id)
+}
+
+/// The span around `et` that the kernels have all of the bodies with the
+/// NAIF ids `ids` for, or None if some body isn't covered at `et`.  A body
+/// the kernels can't be asked about is left out.
+pub fn common_span(ids: &[i32], et: f64) -> Option<(f64, f64)> {
+    let mut span = (f64::NEG_INFINITY, f64::INFINITY);
+    for &id in ids {
+        let Ok(window) = coverage(id) else {
+            continue;
+        };
+        let &(start, stop) = window
+            .iter()
+            .find(|(start, stop)| *start <= et && et <= *stop)?;
+        span = (span.0.max(start), span.1.min(stop));
+    }
+    (span.0.is_finite() && span.1.is_finite()).then_some(span)
+}
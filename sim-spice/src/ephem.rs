@@ -0,0 +1,231 @@
+//! Ephemeris lookups.
+//!
+//! Where a body is, as seen from another, at any time the kernels cover: its
+//! state, and the angles a planetarium would give, such as where it is in the
+//! sky, how far it is from the sun there, and how much of it is lit.  This is
+//! behind the `ephem` command, on the console and on the command line, so that
+//! the sim doubles as a quick ephemeris lookup:
+//!
+//! ```text
+//! ephem MARS 2026-03-01T00:00Z --frame J2000 --center EARTH
+//! ```
+//!
+//! The options are `--frame` (J2000, by default), `--center` (EARTH), and
+//! `--abcorr` (the aberration correction, NONE, for the geometric state; LT+S
+//! gives where it appears).  Names are whatever SPICE knows them as.
+
+use nalgebra::Vector3;
+use std::fmt;
+
+use crate::{SpiceError, get_instance};
+
+/// What to look up.
+#[derive(Clone, Debug)]
+pub struct EphemQuery {
+    pub target: String,
+    /// The time, in any form SPICE takes.
+    pub time: String,
+    pub frame: String,
+    pub center: String,
+    pub abcorr: String,
+}
+
+impl EphemQuery {
+    /// `<target> <time> [--frame <frame>] [--center <body>] [--abcorr <correction>]`.
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let usage = || {
+            "ephem <target> <time> [--frame <frame>] [--center <body>] [--abcorr <correction>]"
+                .to_string()
+        };
+        let [target, time, options @ ..] = args else {
+            return Err(usage());
+        };
+        let mut query = EphemQuery {
+            target: target.to_uppercase(),
+            time: time.clone(),
+            frame: "J2000".to_string(),
+            center: "EARTH".to_string(),
+            abcorr: "NONE".to_string(),
+        };
+        for pair in options.chunks(2) {
+            let [option, value] = pair else {
+                return Err(usage());
+            };
+            let field = match option.as_str() {
+                "--frame" => &mut query.frame,
+                "--center" => &mut query.center,
+                "--abcorr" => &mut query.abcorr,
+                _ => return Err(format!("No such option as {:?}: {}", option, usage())),
+            };
+            *field = value.to_uppercase();
+        }
+        Ok(query)
+    }
+
+    /// The query's time, in seconds past J2000.  SPICE doesn't take the `Z`
+    /// on the end of an ISO time, and doesn't need it, as it reads times as
+    /// UTC anyway.
+    pub fn et(&self) -> Result<f64, SpiceError> {
+        get_instance().str2et(self.time.strip_suffix(['Z', 'z']).unwrap_or(&self.time))
+    }
+}
+
+/// Where a target is, as seen from a center.
+#[derive(Clone, Debug)]
+pub struct Ephemeris {
+    pub query: EphemQuery,
+    pub et: f64,
+    /// The time, as UTC.
+    pub utc: String,
+    /// The target's position (km) and velocity (km/s) relative to the center,
+    /// in the frame.
+    pub pos: Vector3<f64>,
+    pub vel: Vector3<f64>,
+    /// The one-way light time, in seconds.
+    pub light_time: f64,
+    /// The sun's position relative to the center, in the frame.
+    pub sun: Vector3<f64>,
+    /// The target's equatorial radius, in km, if it has one.
+    pub radius: Option<f64>,
+}
+
+impl Ephemeris {
+    /// Look up `query` at `et`.
+    pub fn at(query: &EphemQuery, et: f64) -> Result<Self, SpiceError> {
+        let spice = get_instance();
+        let (state, light_time) = spice.spkezr(
+            &query.target,
+            et,
+            &query.frame,
+            &query.abcorr,
+            &query.center,
+        )?;
+        let (sun, _) = spice.spkezr("SUN", et, &query.frame, &query.abcorr, &query.center)?;
+        Ok(Ephemeris {
+            query: query.clone(),
+            et,
+            utc: spice.et2utc(et, "ISOC", 3)?,
+            pos: Vector3::new(state[0], state[1], state[2]),
+            vel: Vector3::new(state[3], state[4], state[5]),
+            light_time,
+            sun: Vector3::new(sun[0], sun[1], sun[2]),
+            radius: spice
+                .bodvrd(&query.target, "RADII", 3)
+                .ok()
+                .map(|radii| radii[0]),
+        })
+    }
+
+    /// Look up `query` at its own time.
+    pub fn lookup(query: &EphemQuery) -> Result<Self, SpiceError> {
+        Ephemeris::at(query, query.et()?)
+    }
+
+    pub fn range(&self) -> f64 {
+        self.pos.norm()
+    }
+
+    /// How fast the range is opening, km/s.
+    pub fn range_rate(&self) -> f64 {
+        self.pos.dot(&self.vel) / self.range()
+    }
+
+    /// The longitude and latitude of the direction to the target, in radians,
+    /// in the frame.  In J2000, these are the right ascension and declination.
+    pub fn lon_lat(&self) -> (f64, f64) {
+        let lon = self
+            .pos
+            .y
+            .atan2(self.pos.x)
+            .rem_euclid(std::f64::consts::TAU);
+        (lon, (self.pos.z / self.range()).clamp(-1.0, 1.0).asin())
+    }
+
+    /// The angle, in radians, between the sun and the target, from the
+    /// center.  None when either is the sun.
+    pub fn elongation(&self) -> Option<f64> {
+        (self.sun.norm() > 0.0 && (self.sun - self.pos).norm() > 0.0)
+            .then(|| self.sun.angle(&self.pos))
+    }
+
+    /// The angle, in radians, between the sun and the center, from the target:
+    /// 0 is full, and pi is new.  None when either is the sun.
+    pub fn phase(&self) -> Option<f64> {
+        let to_sun = self.sun - self.pos;
+        (self.sun.norm() > 0.0 && to_sun.norm() > 0.0).then(|| to_sun.angle(&-self.pos))
+    }
+
+    /// How wide the target looks, in radians.
+    pub fn angular_diameter(&self) -> Option<f64> {
+        self.radius
+            .filter(|radius| *radius < self.range())
+            .map(|radius| 2.0 * (radius / self.range()).asin())
+    }
+}
+
+/// An angle, in degrees, as sexagesimal `d:mm:ss.s`, with a sign if `signed`.
+fn sexagesimal(degrees: f64, signed: bool) -> String {
+    let sign = if degrees < 0.0 {
+        "-"
+    } else if signed {
+        "+"
+    } else {
+        ""
+    };
+    let tenths = (degrees.abs() * 36000.0).round() as u64;
+    format!(
+        "{}{}:{:02}:{:02}.{}",
+        sign,
+        tenths / 36000,
+        tenths / 600 % 60,
+        tenths / 10 % 60,
+        tenths % 10
+    )
+}
+
+impl fmt::Display for Ephemeris {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let query = &self.query;
+        writeln!(
+            f,
+            "{} from {}, {} ({:.3} s past J2000), {}, {}",
+            query.target, query.center, self.utc, self.et, query.frame, query.abcorr
+        )?;
+        let (pos, vel) = (&self.pos, &self.vel);
+        writeln!(f, "pos {:.3} {:.3} {:.3} km", pos.x, pos.y, pos.z)?;
+        writeln!(f, "vel {:.6} {:.6} {:.6} km/s", vel.x, vel.y, vel.z)?;
+        writeln!(
+            f,
+            "range {:.3} km, rate {:.6} km/s, light time {:.3} s",
+            self.range(),
+            self.range_rate(),
+            self.light_time
+        )?;
+        let (lon, lat) = self.lon_lat();
+        if query.frame == "J2000" {
+            write!(
+                f,
+                "RA {} Dec {}",
+                sexagesimal(lon.to_degrees() / 15.0, false),
+                sexagesimal(lat.to_degrees(), true)
+            )?;
+        } else {
+            write!(f, "lon {:.4} lat {:.4}", lon.to_degrees(), lat.to_degrees())?;
+        }
+        if let Some(elongation) = self.elongation() {
+            write!(f, ", elongation {:.2}", elongation.to_degrees())?;
+        }
+        if let Some(phase) = self.phase() {
+            write!(
+                f,
+                ", phase {:.2} ({:.0}% lit)",
+                phase.to_degrees(),
+                (1.0 + phase.cos()) / 2.0 * 100.0
+            )?;
+        }
+        if let Some(diameter) = self.angular_diameter() {
+            write!(f, ", diameter {:.2}\"", diameter.to_degrees() * 3600.0)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+//! Geometry searches.
+//!
+//! SPICE's geometry finder looks for when something happens over a span of
+//! time, straight from the kernels: when one body hides another, or when two
+//! come within some angle of each other in the sky.  It steps along, and
+//! homes in on each change it sees, so the step has to be shorter than the
+//! shortest event worth finding (or the gap between two), but it needn't be
+//! much shorter than that, which is what makes it so much quicker than
+//! sampling.
+//!
+//! The times here are in seconds past J2000, as windows: disjoint intervals,
+//! in order.  The names are whatever SPICE knows the bodies as, and they are
+//! taken as ellipsoids (or spheres, for the angles), with light time and
+//! stellar aberration, as they appear.
+
+use crate::{SpiceError, Window, get_instance};
+
+/// How much of the back body is hidden.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Occultation {
+    /// All of it.
+    Full,
+    /// The front body is inside the back one's disk, leaving a ring.
+    Annular,
+    /// Some of it, but neither of the above.
+    Partial,
+    /// Any of it.
+    Any,
+}
+
+impl Occultation {
+    fn spice_name(self) -> &'static str {
+        match self {
+            Occultation::Full => "FULL",
+            Occultation::Annular => "ANNULAR",
+            Occultation::Partial => "PARTIAL",
+            Occultation::Any => "ANY",
+        }
+    }
+}
+
+/// What to look for in an angle, in radians.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Relation {
+    Below(f64),
+    Above(f64),
+    Equals(f64),
+    /// The least, or the greatest, over the whole span.
+    Min,
+    Max,
+    /// Each dip, or each peak.
+    LocalMin,
+    LocalMax,
+}
+
+impl Relation {
+    /// The relation and the reference value, as `gfsep_c` takes them.
+    fn spice_args(self) -> (&'static str, f64) {
+        match self {
+            Relation::Below(angle) => ("<", angle),
+            Relation::Above(angle) => (">", angle),
+            Relation::Equals(angle) => ("=", angle),
+            Relation::Min => ("ABSMIN", 0.0),
+            Relation::Max => ("ABSMAX", 0.0),
+            Relation::LocalMin => ("LOCMIN", 0.0),
+            Relation::LocalMax => ("LOCMAX", 0.0),
+        }
+    }
+}
+
+/// When `front` hides `back`, by `kind`, as seen from `observer`, within
+/// `confine`.
+pub fn occultations(
+    kind: Occultation,
+    front: &str,
+    back: &str,
+    observer: &str,
+    step: f64,
+    confine: &[(f64, f64)],
+) -> Result<Window, SpiceError> {
+    get_instance().gfoclt(
+        kind.spice_name(),
+        front,
+        "ELLIPSOID",
+        &format!("IAU_{}", front),
+        back,
+        "ELLIPSOID",
+        &format!("IAU_{}", back),
+        "LT",
+        observer,
+        step,
+        confine,
+    )
+}
+
+/// When `observer` is in `body`'s shadow, in its umbra or its penumbra,
+/// within `confine`: when the body hides any of the sun.
+pub fn eclipses(
+    observer: &str,
+    body: &str,
+    step: f64,
+    confine: &[(f64, f64)],
+) -> Result<Window, SpiceError> {
+    occultations(Occultation::Any, body, "SUN", observer, step, confine)
+}
+
+/// When the angle between `a` and `b`, as seen from `observer`, meets
+/// `relation`, within `confine`.  The extremes come back as intervals with no
+/// length.
+pub fn separation(
+    a: &str,
+    b: &str,
+    observer: &str,
+    relation: Relation,
+    step: f64,
+    confine: &[(f64, f64)],
+) -> Result<Window, SpiceError> {
+    let (relate, refval) = relation.spice_args();
+    get_instance().gfsep(
+        a, "SPHERE", "NULL", b, "SPHERE", "NULL", "LT+S", observer, relate, refval, 0.0, step,
+        confine,
+    )
+}
+
+/// The times `a` and `b` are closest in `observer`'s sky, each time they
+/// come within `within` radians of each other, within `confine`.
+pub fn conjunctions(
+    a: &str,
+    b: &str,
+    observer: &str,
+    within: f64,
+    step: f64,
+    confine: &[(f64, f64)],
+) -> Result<Vec<f64>, SpiceError> {
+    let close = separation(a, b, observer, Relation::Below(within), step, confine)?;
+    if close.is_empty() {
+        return Ok(Vec::new());
+    }
+    let closest = separation(a, b, observer, Relation::LocalMin, step, &close)?;
+    Ok(closest.into_iter().map(|(at, _)| at).collect())
+}
@@ -0,0 +1,176 @@
+//! Which kernels to load, and from where.
+//!
+//! SPICE is started with a `KernelSet`: the SPK kernels, with the
+//! ephemerides, and the text kernels, with the leap seconds and the bodies'
+//! constants.  Without anything said, that is the set `fetch.sh` downloads
+//! into `assets/spice`.  A different collection, or a smaller download, is
+//! described in `KERNEL_SET`, or passed to `configure` (made with the builder
+//! methods, or `KernelSet::load_from`) before SPICE is first used.  For
+//! example, for the planets only, kept elsewhere:
+//!
+//! ```json
+//! {
+//!   "dir": "/data/naif",
+//!   "spk": ["de440s.bsp"],
+//!   "text": ["naif0012.tls", "pck00011.tpc", "gm_de440.tpc"]
+//! }
+//! ```
+//!
+//! Paths that aren't absolute are taken from `dir`, if it is given, and from
+//! the working directory otherwise.  Every kernel in the set has to be there:
+//! if any aren't, SPICE isn't started, and the error lists them all.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Where the kernel set is described, if anywhere.
+pub const KERNEL_SET: &str = "assets/spice/kernels.json";
+
+/// The SPK kernels `fetch.sh` downloads, which hold the ephemerides.
+pub const SPK_KERNELS: [&str; 9] = [
+    "assets/spice/de440s.bsp",
+    "assets/spice/jup365.bsp",
+    "assets/spice/mar099.bsp",
+    "assets/spice/nep095.bsp",
+    "assets/spice/plu060.bsp",
+    "assets/spice/sat441.bsp",
+    "assets/spice/ura184_part-1.bsp",
+    "assets/spice/ura184_part-2.bsp",
+    "assets/spice/ura184_part-3.bsp",
+];
+
+/// The text kernels `fetch.sh` downloads: the leap seconds, and the bodies'
+/// constants.
+pub const TEXT_KERNELS: [&str; 3] = [
+    "assets/spice/naif0012.tls",
+    "assets/spice/pck00011.tpc",
+    "assets/spice/gm_de440.tpc",
+];
+
+/// The set chosen, once SPICE has started, or `configure` has been called.
+static CHOSEN: OnceLock<KernelSet> = OnceLock::new();
+
+/// The kernels to load.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KernelSet {
+    /// Where the kernels not given with an absolute path are.
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+    #[serde(default)]
+    pub spk: Vec<PathBuf>,
+    #[serde(default)]
+    pub text: Vec<PathBuf>,
+}
+
+impl Default for KernelSet {
+    /// The kernels `fetch.sh` downloads.
+    fn default() -> Self {
+        KernelSet {
+            dir: None,
+            spk: SPK_KERNELS.iter().map(PathBuf::from).collect(),
+            text: TEXT_KERNELS.iter().map(PathBuf::from).collect(),
+        }
+    }
+}
+
+impl KernelSet {
+    /// An empty set, with its kernels in `dir`.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        KernelSet {
+            dir: Some(dir.as_ref().to_path_buf()),
+            spk: Vec::new(),
+            text: Vec::new(),
+        }
+    }
+
+    /// With another SPK kernel.
+    pub fn with_spk(mut self, path: impl AsRef<Path>) -> Self {
+        self.spk.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// With another text kernel.
+    pub fn with_text(mut self, path: impl AsRef<Path>) -> Self {
+        self.text.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// The set described in `path`.
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("Can't open {}: {}", path.display(), e))?;
+        serde_json::from_reader(file).map_err(|e| format!("Can't read {}: {}", path.display(), e))
+    }
+
+    /// The set in `KERNEL_SET`, or the default, if there isn't one.
+    pub fn load() -> Result<Self, String> {
+        if Path::new(KERNEL_SET).exists() {
+            Self::load_from(KERNEL_SET)
+        } else {
+            Ok(KernelSet::default())
+        }
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        match &self.dir {
+            Some(dir) if path.is_relative() => dir.join(path),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// Where the SPK kernels are.
+    pub fn spk_paths(&self) -> Vec<PathBuf> {
+        self.spk.iter().map(|path| self.resolve(path)).collect()
+    }
+
+    /// Where the text kernels are.
+    pub fn text_paths(&self) -> Vec<PathBuf> {
+        self.text.iter().map(|path| self.resolve(path)).collect()
+    }
+
+    /// Every kernel in the set that isn't there, or an error if the set
+    /// can't work at all.
+    pub fn check(&self) -> Result<(), String> {
+        if self.spk.is_empty() {
+            return Err("The kernel set has no SPK kernels".to_string());
+        }
+        let missing: Vec<String> = self
+            .spk_paths()
+            .into_iter()
+            .chain(self.text_paths())
+            .filter(|path| !path.is_file())
+            .map(|path| path.display().to_string())
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Missing SPICE kernels (run assets/spice/fetch.sh, or list the ones \
+                 there are in {}): {}",
+                KERNEL_SET,
+                missing.join(", ")
+            ))
+        }
+    }
+}
+
+/// Use `set`, rather than the one in `KERNEL_SET`.  This has to be before
+/// SPICE is first used.
+pub fn configure(set: KernelSet) -> Result<(), String> {
+    CHOSEN
+        .set(set)
+        .map_err(|_| "SPICE has already been started with its kernels".to_string())
+}
+
+/// The set SPICE is started with: as configured, or else as loaded.
+pub fn chosen() -> Result<&'static KernelSet, String> {
+    if let Some(set) = CHOSEN.get() {
+        return Ok(set);
+    }
+    let set = KernelSet::load()?;
+    Ok(CHOSEN.get_or_init(|| set))
+}
@@ -0,0 +1,165 @@
+//! Taking the solar system from SPICE.
+//!
+//! The sim itself runs from a `SolarState` saved to a file, so that normal
+//! gameplay doesn't need the kernels (or the CSPICE library).  This is what
+//! makes that file, by reading every body that has a GM from the kernels (the
+//! ones in `assets/spice`, unless told otherwise; see `kernels`), with their
+//! constants cached (see `constants`), the names of their systems'
+//! barycenters, and the small bodies listed for it (see `small_bodies`).  It
+//! also looks up any body's state at any time the kernels cover, for the
+//! `ephem` command (see `ephem`), and searches them for eclipses,
+//! occultations and conjunctions (see `gf`).  The kernels only go so far
+//! either way; `coverage` finds how far.  It also fits the bodies'
+//! ephemerides, for the sim to look up without the kernels (see `cache`), and
+//! keeps the bodies turned as their IAU frames have them (see `orientation`).
+
+// The rust-spice crate has a locking mechanism to ensure single threaded
+// access. However, it only implements a handeful of the SPICE functions, and
+// when enabled, makes the raw versions of the functions inaccessible. In order
+// for spice to actually be useful, we'll need to use our own lock, and just
+// make sure we only use the API while holding the lock.
+
+// Recommended alias.
+extern crate nalgebra as na;
+
+use bevy::prelude::*;
+use nalgebra::{Matrix3, Vector3};
+use sim_astro::{
+    Body, SolarState, SpiceId,
+    hierarchy::{Barycenter, BodyKind},
+    loading::LoadMessage,
+};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, SizedBody};
+
+use crate::constants::{BodyConstants, BodyConstantsCache};
+
+pub mod cache;
+pub mod constants;
+pub mod coverage;
+pub mod ephem;
+pub mod gf;
+pub mod kernels;
+pub mod orientation;
+pub mod small_bodies;
+mod wrappers;
+
+pub use kernels::KernelSet;
+pub use wrappers::{MAX_INTERVALS, Spice, SpiceError, Window, get_instance, init};
+
+/// The body with the given constants, at `et`, or None if it isn't one the
+/// sim wants.
+pub fn body(constants: &BodyConstants, et: f64) -> Option<Body> {
+    let sl = get_instance();
+    let name = &constants.name;
+
+    // Barycenters aren't bodies, but points (see `barycenter`).
+    if name.ends_with(" BARYCENTER") {
+        return None;
+    }
+
+    // Reject "small" bodies, and those that don't have a radius.
+    if constants.gm < 1.0 {
+        return None;
+    }
+    let radii = Vector3::from(constants.radii?);
+
+    let attitude = attitude(name, et)?;
+
+    let (state, _) = sl.spkezr(name, et, "ECLIPJ2000", "NONE", "SSB").ok()?;
+
+    let pos = Vector3::new(state[0], state[1], state[2]);
+    let vel = Vector3::new(state[3], state[4], state[5]);
+
+    Some(Body {
+        id: SpiceId(constants.id),
+        orbital: OrbitalBody { pos, vel },
+        size: SizedBody { radii },
+        attitude,
+        massive: MassiveBody { gm: constants.gm },
+        name: Name::new(name.clone()),
+        // Its id says.
+        parent: None,
+    })
+}
+
+/// The barycenter of a planet's system, with the given constants, or None if
+/// it isn't one.  These only give the names to the tree the bodies make (see
+/// `sim_astro::hierarchy`).
+pub fn barycenter(constants: &BodyConstants) -> Option<Barycenter> {
+    (BodyKind::of(constants.id) == BodyKind::Barycenter).then(|| Barycenter {
+        id: constants.id,
+        name: constants.name.clone(),
+    })
+}
+
+/// The attitude, at `et`, of the body with the given name, from its IAU
+/// frame, or None if it hasn't one.
+pub fn attitude(name: &str, et: f64) -> Option<AttitudeState> {
+    let sl = get_instance();
+    let xform = sl.sxform(&format!("IAU_{}", name), "ECLIPJ2000", et).ok()?;
+    let (rot, av) = sl.xf2rav(&xform).ok()?;
+    let rot = Matrix3::from_row_slice(&[
+        rot[0][0], rot[0][1], rot[0][2], // Row 0
+        rot[1][0], rot[1][1], rot[1][2], // Row 1
+        rot[2][0], rot[2][1], rot[2][2], // Row 2
+    ]);
+
+    let av = Vector3::new(av[0], av[1], av[2]);
+    let r_bw = na::Rotation3::from_matrix(&rot);
+    let q_bw = na::UnitQuaternion::from_rotation_matrix(&r_bw);
+
+    // Invert the angular velocity as spice is returning a frame rotation, not the earth's rotation.
+    let omega_b = -av;
+    Some(AttitudeState { q_bw, omega_b })
+}
+
+/// The whole solar system, as SPICE has it, with the small bodies listed in
+/// `small_bodies::SMALL_BODIES`.
+pub fn solar_state() -> Option<SolarState> {
+    solar_state_with(&mut |_| {}).ok()
+}
+
+/// The whole solar system, as `solar_state` has it, saying what it is doing,
+/// and giving each body as it is read, to `progress` (see
+/// `sim_astro::loading`).
+pub fn solar_state_with(progress: &mut dyn FnMut(LoadMessage)) -> Result<SolarState, String> {
+    progress(LoadMessage::Stage("Loading the SPICE kernels".to_string()));
+    let sl = init()?;
+    // TODO: Better start date.
+    let time = "2024-01-01T00:00:00";
+    let et = sl.str2et(time).map_err(|e| e.to_string())?;
+    progress(LoadMessage::Stage(
+        "Reading the bodies' constants".to_string(),
+    ));
+    let constants = BodyConstantsCache::load().map_err(|e| e.to_string())?;
+    progress(LoadMessage::Stage("Reading the bodies".to_string()));
+    let mut bodies = Vec::new();
+    for body in constants
+        .bodies
+        .iter()
+        .filter_map(|constants| body(constants, et))
+    {
+        progress(LoadMessage::Body(body.clone()));
+        bodies.push(body);
+    }
+    bodies.sort_by(|a, b| b.massive.gm.partial_cmp(&a.massive.gm).unwrap());
+    let ids: Vec<i32> = bodies.iter().map(|body| body.id.0).collect();
+    // The small bodies' kernels are only loaded now, so that none of theirs
+    // with a GM and radii is taken for a massive body.
+    progress(LoadMessage::Stage("Reading the small bodies".to_string()));
+    let small_bodies = small_bodies::small_bodies(et, &ids).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        Vec::new()
+    });
+    progress(LoadMessage::Stage(
+        "Finding the kernels' coverage".to_string(),
+    ));
+    Ok(SolarState {
+        et,
+        time: time.to_string(),
+        bodies,
+        coverage: coverage::common_span(&ids, et),
+        small_bodies,
+        barycenters: constants.bodies.iter().filter_map(barycenter).collect(),
+    })
+}
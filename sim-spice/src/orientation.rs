@@ -0,0 +1,80 @@
+//! The bodies' orientations, from their IAU frames.
+//!
+//! The bodies are read with their attitudes at the start (see `attitude`),
+//! and from then on the physics only turns them at the rate they had, about
+//! the pole they had.  That is close, but it drifts: the poles precess, and a
+//! rate taken at one moment isn't the mean one.  With this, once the physics
+//! has stepped each frame, every body with an IAU frame in the kernels (the
+//! PCK) is set to its orientation at the sim's time, so that a textured earth
+//! turns at its true sidereal rate, about its true pole, and the ground
+//! tracks and landing sites, which are all taken from the bodies' attitudes,
+//! line up with it.
+//!
+//! The rate is set as well, from the same frame, so that the steps in between
+//! turn the bodies as the kernels would.  Bodies without an IAU frame are
+//! left to the physics.  Without the kernels, it does nothing.
+
+use std::collections::HashSet;
+
+use bevy::{app::RunFixedMainLoopSystems, prelude::*};
+use sim_astro::{SolarState, SpiceId};
+use sim_core::{AttitudeIntegrator, AttitudeState};
+
+use crate::{attitude, init};
+
+#[derive(Default)]
+pub struct OrientationPlugin;
+
+impl Plugin for OrientationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            RunFixedMainLoop,
+            orient_bodies.in_set(RunFixedMainLoopSystems::AfterFixedMainLoop),
+        );
+    }
+}
+
+/// What is known of the kernels: whether they could be loaded, and the
+/// bodies they have no frame for.
+#[derive(Default)]
+struct Frames {
+    loaded: Option<bool>,
+    missing: HashSet<Entity>,
+}
+
+/// Set every body's attitude from its IAU frame, at the sim's time.
+fn orient_bodies(
+    mut frames: Local<Frames>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    integrator: Res<AttitudeIntegrator>,
+    mut bodies: Query<(Entity, &Name, &mut AttitudeState), With<SpiceId>>,
+) {
+    let loaded = *frames.loaded.get_or_insert_with(|| match init() {
+        Ok(_) => true,
+        Err(e) => {
+            warn!(
+                "The bodies turn as they were read, without the kernels: {}",
+                e
+            );
+            false
+        }
+    });
+    if !loaded {
+        return;
+    }
+    // The states are kept as far ahead of the clock as the physics keeps
+    // them.
+    let et = solar.et + fixed.elapsed_secs_f64() + integrator.lead(fixed.timestep().as_secs_f64());
+    for (entity, name, mut state) in bodies.iter_mut() {
+        if frames.missing.contains(&entity) {
+            continue;
+        }
+        match attitude(name.as_str(), et) {
+            Some(oriented) => *state = oriented,
+            None => {
+                frames.missing.insert(entity);
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Small bodies: asteroids and comets.
+//!
+//! The planetary kernels don't have these; each comes in an SPK of its own
+//! (from JPL's Horizons, or NAIF's collection of the biggest asteroids), and
+//! there are far too many to take them all.  The ones wanted are listed in
+//! `SMALL_BODIES`, by NAIF id, with the kernels to find them in, which are
+//! only loaded for this.  Without the list, there are none.  For example:
+//!
+//! ```json
+//! {
+//!   "kernels": ["assets/spice/codes_300ast_20100725.bsp",
+//!               "assets/spice/codes_300ast_20100725.tf"],
+//!   "bodies": [
+//!     { "id": 2000016, "radii": [139.5, 116.0, 94.5] },
+//!     { "id": 1000093, "name": "HALLEY" }
+//!   ]
+//! }
+//! ```
+//!
+//! Each is taken at the ephemeris' time.  The name is SPICE's, unless the
+//! list gives one, and the radii are the kernel pool's, unless the list
+//! gives them; a body with neither is a point.  The attitude is the body's
+//! IAU frame, if there is one, and otherwise lined up with the world, not
+//! turning.  A body already in the ephemeris, as a massive one, is left out.
+
+use serde::{Deserialize, Serialize};
+use sim_astro::{SmallBody, SpiceId};
+use sim_core::{AttitudeState, OrbitalBody, SizedBody};
+
+use crate::{attitude, get_instance};
+
+/// Where the list of small bodies is kept.
+pub const SMALL_BODIES: &str = "assets/spice/small_bodies.json";
+
+/// A small body to take from the kernels.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SmallBodyEntry {
+    /// The NAIF id: 2000000 plus the number for an asteroid, and 1000000 up
+    /// for a comet.
+    pub id: i32,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// In km.
+    #[serde(default)]
+    pub radii: Option<[f64; 3]>,
+}
+
+/// The small bodies to take, and the kernels they are in.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SmallBodyList {
+    pub kernels: Vec<String>,
+    pub bodies: Vec<SmallBodyEntry>,
+}
+
+impl SmallBodyList {
+    /// The list in `SMALL_BODIES`, or an empty one if there isn't one.
+    pub fn load() -> Result<Self, String> {
+        match std::fs::File::open(SMALL_BODIES) {
+            Ok(file) => serde_json::from_reader(file)
+                .map_err(|e| format!("Can't read {}: {}", SMALL_BODIES, e)),
+            Err(_) => Ok(SmallBodyList::default()),
+        }
+    }
+}
+
+/// The small body `entry`, at `et`, or None if the kernels don't have it
+/// then.
+pub fn small_body(entry: &SmallBodyEntry, et: f64) -> Option<SmallBody> {
+    let sl = get_instance();
+    let name = entry
+        .name
+        .clone()
+        .or_else(|| sl.bodc2n(entry.id))
+        .unwrap_or_else(|| entry.id.to_string());
+    let (state, _) = sl
+        .spkezr(&entry.id.to_string(), et, "ECLIPJ2000", "NONE", "SSB")
+        .ok()?;
+    let radii = entry.radii.or_else(|| {
+        sl.bodvrd(&entry.id.to_string(), "RADII", 3)
+            .ok()?
+            .try_into()
+            .ok()
+    });
+    Some(SmallBody {
+        id: SpiceId(entry.id),
+        name: name.as_str().into(),
+        orbital: OrbitalBody {
+            pos: na::Vector3::new(state[0], state[1], state[2]),
+            vel: na::Vector3::new(state[3], state[4], state[5]),
+        },
+        size: radii.map(|radii| SizedBody {
+            radii: na::Vector3::from(radii),
+        }),
+        attitude: attitude(&name, et).unwrap_or(AttitudeState {
+            q_bw: na::UnitQuaternion::identity(),
+            omega_b: na::Vector3::zeros(),
+        }),
+    })
+}
+
+/// The listed small bodies at `et`, loading their kernels, less any with an
+/// id in `skip`.  One the kernels don't have then is left out, with a note.
+pub fn small_bodies(et: f64, skip: &[i32]) -> Result<Vec<SmallBody>, String> {
+    let list = SmallBodyList::load()?;
+    let sl = get_instance();
+    for kernel in &list.kernels {
+        sl.furnsh(kernel)
+            .map_err(|e| format!("Can't load {}: {}", kernel, e))?;
+    }
+    Ok(list
+        .bodies
+        .iter()
+        .filter(|entry| !skip.contains(&entry.id))
+        .filter_map(|entry| {
+            let body = small_body(entry, et);
+            if body.is_none() {
+                eprintln!("No small body {} in the kernels at {}", entry.id, et);
+            }
+            body
+        })
+        .collect())
+}
@@ -0,0 +1,446 @@
+/// Spice wrappers.
+use nalgebra::Matrix6xX;
+use std::{
+    ffi::{CStr, CString},
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use crate::kernels;
+
+/// The single global SPICE instance, or why it couldn't be started.
+static SPICE: LazyLock<std::result::Result<Spice, String>> = LazyLock::new(Spice::new);
+
+/// An error from SPICE.
+pub struct SpiceError(pub(crate) String);
+
+impl std::fmt::Display for SpiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SPICE error: {}", self.0)
+    }
+}
+
+impl std::fmt::Debug for SpiceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SpiceError").field(&self.0).finish()
+    }
+}
+
+impl std::error::Error for SpiceError {}
+
+type Result<T> = std::result::Result<T, SpiceError>;
+
+/// A set of disjoint time intervals, `(start, stop)` in seconds past J2000, in
+/// order: a SPICE window.
+pub type Window = Vec<(f64, f64)>;
+
+/// The most intervals a geometry search keeps.  The searches' workspaces, and
+/// their results, are sized from this.
+pub const MAX_INTERVALS: usize = 10_000;
+
+/// A double precision SPICE cell, such as a window.  The cell points into the
+/// Vec's storage, which stays put when this moves.
+struct DoubleCell {
+    _buf: Vec<f64>,
+    cell: spice::c::SpiceCell,
+}
+
+impl DoubleCell {
+    fn new(size: usize) -> Self {
+        let ctrl = spice::c::SPICE_CELL_CTRLSZ as usize;
+        let mut buf = vec![0.0; ctrl + size];
+        let base = buf.as_mut_ptr();
+        let cell = spice::c::SpiceCell {
+            dtype: spice::c::_SpiceDataType_SPICE_DP,
+            length: 0,
+            size: size as i32,
+            card: 0,
+            isSet: 1,
+            adjust: 0,
+            init: 0,
+            base: base as *mut _,
+            data: base.wrapping_add(ctrl) as *mut _,
+        };
+        DoubleCell { _buf: buf, cell }
+    }
+
+    /// A window cell holding `window`.  This assumes the lock is already held.
+    fn window(window: &[(f64, f64)]) -> Self {
+        let mut cell = DoubleCell::new(2 * window.len().max(1));
+        for &(start, stop) in window {
+            unsafe {
+                spice::c::wninsd_c(start, stop, &mut cell.cell);
+            }
+        }
+        cell
+    }
+
+    /// The intervals in a window cell.  This assumes the lock is already held.
+    fn intervals(&mut self) -> Window {
+        let count = unsafe { spice::c::wncard_c(&mut self.cell) };
+        (0..count)
+            .map(|i| {
+                let (mut start, mut stop) = (0.0, 0.0);
+                unsafe {
+                    spice::c::wnfetd_c(&mut self.cell, i, &mut start, &mut stop);
+                }
+                (start, stop)
+            })
+            .collect()
+    }
+}
+
+/// Start SPICE, with the chosen kernels (see `kernels`), if it hasn't been
+/// already.
+pub fn init() -> std::result::Result<Spice, String> {
+    SPICE.clone()
+}
+
+/// SPICE, which must be able to start.  Anything that can do without it
+/// should call `init` first, to have the reason it can't.
+pub fn get_instance() -> Spice {
+    init().unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// A wrapped SPICE interface.  Internally cares for its own locking.
+#[derive(Clone)]
+pub struct Spice(Arc<Mutex<()>>);
+
+impl Spice {
+    fn new() -> std::result::Result<Self, String> {
+        let set = kernels::chosen()?;
+        set.check()?;
+
+        // Set the error handling to return errors, and to not print them out.
+        unsafe {
+            spice::c::erract_c(c"SET".as_ptr() as *mut _, 0, c"RETURN".as_ptr() as *mut _);
+            spice::c::errprt_c(c"SET".as_ptr() as *mut _, 0, c"NONE".as_ptr() as *mut _);
+        }
+        let sl = Spice(Arc::new(Mutex::new(())));
+
+        // Load the SPICE kernels for use.
+        for kernel in set.spk_paths().into_iter().chain(set.text_paths()) {
+            sl.furnsh(&kernel.to_string_lossy())
+                .map_err(|e| format!("Can't load {}: {}", kernel.display(), e))?;
+        }
+        Ok(sl)
+    }
+
+    /// Check if the last call returned an error, if so, clear it, and return the error.  Otherwise return Ok(()).
+    ///
+    /// This assumes the lock is already held.
+    pub fn chkerr(&self) -> Result<()> {
+        let is_err = unsafe { spice::c::failed_c() };
+        if is_err != 0 {
+            let mut msg = [0u8; 26];
+            unsafe {
+                spice::c::getmsg_c(c"SHORT".as_ptr() as *mut _, 26, msg.as_mut_ptr() as *mut _);
+                spice::c::reset_c();
+            }
+            let msg = CStr::from_bytes_until_nul(&msg)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+            Err(SpiceError(msg))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Load another kernel, on top of those loaded at the start.
+    pub fn furnsh(&self, path: &str) -> Result<()> {
+        let _lock = self.0.lock().unwrap();
+        spice::furnsh(path);
+        self.chkerr()
+    }
+
+    pub fn str2et(&self, time: &str) -> Result<f64> {
+        let _lock = self.0.lock().unwrap();
+        let result = spice::str2et(time);
+        self.chkerr()?;
+        Ok(result)
+    }
+
+    pub fn spkezr(
+        &self,
+        target: &str,
+        et: f64,
+        ref_frame: &str,
+        abcorr: &str,
+        observer: &str,
+    ) -> Result<([f64; 6], f64)> {
+        let _lock = self.0.lock().unwrap();
+        let result = spice::spkezr(target, et, ref_frame, abcorr, observer);
+        self.chkerr()?;
+        Ok(result)
+    }
+
+    /// The states of each of `targets` at each of `times`, as `spkezr` gives
+    /// them, taking the lock, and making the names into C strings, only once.
+    /// Column `i * times.len() + j` is target `i` at time `j`: its position
+    /// (km) over its velocity (km/s).  The light times are left out.
+    pub fn spkezr_many(
+        &self,
+        targets: &[&str],
+        times: &[f64],
+        ref_frame: &str,
+        abcorr: &str,
+        observer: &str,
+    ) -> Result<Matrix6xX<f64>> {
+        let _lock = self.0.lock().unwrap();
+        let ref_frame = CString::new(ref_frame).unwrap();
+        let abcorr = CString::new(abcorr).unwrap();
+        let observer = CString::new(observer).unwrap();
+        let mut states = Matrix6xX::zeros(targets.len() * times.len());
+        for (i, target) in targets.iter().enumerate() {
+            let target = CString::new(*target).unwrap();
+            for (j, &et) in times.iter().enumerate() {
+                let mut state = [0.0; 6];
+                let mut light_time = 0.0;
+                unsafe {
+                    spice::c::spkezr_c(
+                        target.as_ptr() as *mut _,
+                        et,
+                        ref_frame.as_ptr() as *mut _,
+                        abcorr.as_ptr() as *mut _,
+                        observer.as_ptr() as *mut _,
+                        state.as_mut_ptr(),
+                        &mut light_time,
+                    );
+                }
+                self.chkerr()?;
+                states
+                    .column_mut(i * times.len() + j)
+                    .copy_from_slice(&state);
+            }
+        }
+        Ok(states)
+    }
+
+    /// `et` as a UTC string, in one of SPICE's formats (such as `ISOC`), with
+    /// `prec` decimal places on the seconds.
+    pub fn et2utc(&self, et: f64, format: &str, prec: i32) -> Result<String> {
+        let _lock = self.0.lock().unwrap();
+        let mut buf = [0u8; 64];
+        let format = CString::new(format).unwrap();
+        unsafe {
+            spice::c::et2utc_c(
+                et,
+                format.as_ptr() as *mut _,
+                prec,
+                buf.len() as i32,
+                buf.as_mut_ptr() as *mut _,
+            );
+        }
+        self.chkerr()?;
+        Ok(CStr::from_bytes_until_nul(&buf)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string())
+    }
+
+    pub fn bodvrd(&self, body: &str, item: &str, maxn: usize) -> Result<Vec<f64>> {
+        let _lock = self.0.lock().unwrap();
+        let result = spice::bodvrd(body, item, maxn);
+        self.chkerr()?;
+        Ok(result)
+    }
+
+    #[allow(dead_code)]
+    pub fn bodfnd(&self, body: i32, item: &str) -> bool {
+        let _lock = self.0.lock().unwrap();
+        spice::bodfnd(body, item)
+    }
+
+    #[allow(dead_code)]
+    pub fn pxform(&self, from: &str, to: &str, et: f64) -> Result<[[f64; 3]; 3]> {
+        let _lock = self.0.lock().unwrap();
+        let result = spice::pxform(from, to, et);
+        self.chkerr()?;
+        Ok(result)
+    }
+
+    pub fn sxform(&self, from: &str, to: &str, et: f64) -> Result<[[f64; 6]; 6]> {
+        let _lock = self.0.lock().unwrap();
+        let mut result = [[0.0; 6]; 6];
+        let from = CString::new(from).unwrap();
+        let to = CString::new(to).unwrap();
+        unsafe {
+            spice::c::sxform_c(
+                from.as_ptr() as *mut _,
+                to.as_ptr() as *mut _,
+                et,
+                &mut result as *mut _,
+            );
+        }
+        self.chkerr()?;
+        Ok(result)
+    }
+
+    pub fn xf2rav(&self, xform: &[[f64; 6]; 6]) -> Result<([[f64; 3]; 3], [f64; 3])> {
+        let _lock = self.0.lock().unwrap();
+        let mut rot = [[0.0; 3]; 3];
+        let mut av = [0.0; 3];
+        unsafe {
+            spice::c::xf2rav_c(xform.as_ptr() as *mut _, rot.as_mut_ptr(), av.as_mut_ptr());
+        }
+        self.chkerr()?;
+        Ok((rot, av))
+    }
+
+    pub fn gnpool(&self, name: &str, start: usize, room: usize) -> Result<Vec<String>> {
+        let _lock = self.0.lock().unwrap();
+        let mut buf = vec![[0u8; 33]; room];
+        let mut n = 0;
+        let mut found = 0;
+
+        unsafe {
+            spice::c::gnpool_c(
+                CString::new(name).unwrap().as_ptr() as *mut _,
+                start as i32,
+                room as i32,
+                33,
+                &mut n,
+                buf.as_mut_ptr() as *mut _,
+                &mut found,
+            );
+        }
+
+        let mut result = Vec::new();
+        for i in 0..n {
+            let str = CStr::from_bytes_until_nul(&buf[i as usize]).unwrap();
+            let str = str.to_str().unwrap().to_string();
+            result.push(str);
+        }
+        self.chkerr()?;
+        Ok(result)
+    }
+
+    pub fn gdpool(&self, name: &str, start: usize, room: usize) -> Result<Vec<f64>> {
+        let _lock = self.0.lock().unwrap();
+        let result = spice::gdpool(name, start, room);
+        self.chkerr()?;
+        Ok(result)
+    }
+
+    /// When `front` blocks `back` as seen from `obsrvr`, within the `confine`
+    /// window, stepping `step` seconds: `occtyp` is `FULL`, `ANNULAR`,
+    /// `PARTIAL` or `ANY`.  Each shape is `ELLIPSOID`, with its body-fixed
+    /// frame, or `POINT`, with a blank one.  See `gfoclt_c`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn gfoclt(
+        &self,
+        occtyp: &str,
+        front: &str,
+        fshape: &str,
+        fframe: &str,
+        back: &str,
+        bshape: &str,
+        bframe: &str,
+        abcorr: &str,
+        obsrvr: &str,
+        step: f64,
+        confine: &[(f64, f64)],
+    ) -> Result<Window> {
+        let _lock = self.0.lock().unwrap();
+        let strings = [
+            occtyp, front, fshape, fframe, back, bshape, bframe, abcorr, obsrvr,
+        ]
+        .map(|s| CString::new(s).unwrap());
+        let mut cnfine = DoubleCell::window(confine);
+        let mut result = DoubleCell::new(2 * MAX_INTERVALS);
+        unsafe {
+            spice::c::gfoclt_c(
+                strings[0].as_ptr() as *mut _,
+                strings[1].as_ptr() as *mut _,
+                strings[2].as_ptr() as *mut _,
+                strings[3].as_ptr() as *mut _,
+                strings[4].as_ptr() as *mut _,
+                strings[5].as_ptr() as *mut _,
+                strings[6].as_ptr() as *mut _,
+                strings[7].as_ptr() as *mut _,
+                strings[8].as_ptr() as *mut _,
+                step,
+                &mut cnfine.cell,
+                &mut result.cell,
+            );
+        }
+        self.chkerr()?;
+        Ok(result.intervals())
+    }
+
+    /// When the angle between `targ1` and `targ2`, as seen from `obsrvr`, is
+    /// `relate` (`>`, `=`, `<`, `ABSMAX`, `ABSMIN`, `LOCMAX` or `LOCMIN`) to
+    /// `refval` radians, within the `confine` window, stepping `step` seconds.
+    /// Each shape is `SPHERE` or `POINT`, and the frames are `NULL`.  For
+    /// the absolute extremes, `adjust` widens the match.  See `gfsep_c`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn gfsep(
+        &self,
+        targ1: &str,
+        shape1: &str,
+        frame1: &str,
+        targ2: &str,
+        shape2: &str,
+        frame2: &str,
+        abcorr: &str,
+        obsrvr: &str,
+        relate: &str,
+        refval: f64,
+        adjust: f64,
+        step: f64,
+        confine: &[(f64, f64)],
+    ) -> Result<Window> {
+        let _lock = self.0.lock().unwrap();
+        let strings = [
+            targ1, shape1, frame1, targ2, shape2, frame2, abcorr, obsrvr, relate,
+        ]
+        .map(|s| CString::new(s).unwrap());
+        let mut cnfine = DoubleCell::window(confine);
+        let mut result = DoubleCell::new(2 * MAX_INTERVALS);
+        unsafe {
+            spice::c::gfsep_c(
+                strings[0].as_ptr() as *mut _,
+                strings[1].as_ptr() as *mut _,
+                strings[2].as_ptr() as *mut _,
+                strings[3].as_ptr() as *mut _,
+                strings[4].as_ptr() as *mut _,
+                strings[5].as_ptr() as *mut _,
+                strings[6].as_ptr() as *mut _,
+                strings[7].as_ptr() as *mut _,
+                strings[8].as_ptr() as *mut _,
+                refval,
+                adjust,
+                step,
+                MAX_INTERVALS as i32,
+                &mut cnfine.cell,
+                &mut result.cell,
+            );
+        }
+        self.chkerr()?;
+        Ok(result.intervals())
+    }
+
+    /// The times the SPK kernels have the body with NAIF id `idcode` for,
+    /// across all of them.  See `spkcov_c`.
+    pub fn spkcov(&self, idcode: i32) -> Result<Window> {
+        let _lock = self.0.lock().unwrap();
+        let mut cover = DoubleCell::new(2 * MAX_INTERVALS);
+        // Each adds its own coverage to the window.
+        for kernel in kernels::chosen().map_err(SpiceError)?.spk_paths() {
+            let kernel = CString::new(kernel.to_string_lossy().as_bytes()).unwrap();
+            unsafe {
+                spice::c::spkcov_c(kernel.as_ptr() as *mut _, idcode, &mut cover.cell);
+            }
+            self.chkerr()?;
+        }
+        Ok(cover.intervals())
+    }
+
+    pub fn bodc2n(&self, code: i32) -> Option<String> {
+        let _lock = self.0.lock().unwrap();
+        let (name, found) = spice::bodc2n(code);
+        if found { Some(name) } else { None }
+    }
+}
@@ -0,0 +1,136 @@
+//! The alarm panel.
+//!
+//! Shows the next alarm set (see `sim_game::alarm`), counting down on the
+//! mission clock, and when one goes off, says so in red for a while, with a
+//! few beeps.
+
+use bevy::{audio::Pitch, color::palettes::css::RED, prelude::*};
+use sim_astro::SolarState;
+use sim_game::alarm::{AlarmRang, Alarms, format_met};
+use std::time::Duration;
+
+use crate::{UI_LAYER, layout::HudPanel};
+
+/// How long an alarm going off is shown, in real seconds.
+const RINGING_TIME: f32 = 10.0;
+
+/// The beeps, Hz, and how long each is.
+const BEEP_PITCH: f32 = 880.0;
+const BEEP_LENGTH: Duration = Duration::from_millis(150);
+const BEEPS: u32 = 3;
+
+#[derive(Component)]
+struct AlarmText;
+
+/// The alarm last gone off, and how long ago, in real seconds.
+#[derive(Resource, Default)]
+struct Ringing(Option<(String, f32)>);
+
+/// The beep, and how many are still to play.
+#[derive(Resource, Default)]
+struct Beeper {
+    beep: Option<Handle<Pitch>>,
+    left: u32,
+}
+
+#[derive(Default)]
+pub struct AlarmViewPlugin;
+
+impl Plugin for AlarmViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Ringing>();
+        app.init_resource::<Beeper>();
+        app.add_systems(Startup, setup_alarm);
+        app.add_systems(Update, (alarm_rang, beep, update_alarm_text).chain());
+    }
+}
+
+fn setup_alarm(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut pitches: ResMut<Assets<Pitch>>,
+    mut beeper: ResMut<Beeper>,
+) {
+    beeper.beep = Some(pitches.add(Pitch::new(BEEP_PITCH, BEEP_LENGTH)));
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 18.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("Alarm Text"),
+        HudPanel("alarm"),
+        AlarmText,
+    ));
+}
+
+fn alarm_rang(
+    mut rang: MessageReader<AlarmRang>,
+    mut ringing: ResMut<Ringing>,
+    mut beeper: ResMut<Beeper>,
+    time: Res<Time<Real>>,
+) {
+    for rang in rang.read() {
+        ringing.0 = Some((rang.alarm.name.clone(), 0.0));
+        beeper.left = BEEPS;
+    }
+    if let Some((_, age)) = ringing.0.as_mut() {
+        *age += time.delta_secs();
+        if *age > RINGING_TIME {
+            ringing.0 = None;
+        }
+    }
+}
+
+/// Play the beeps one after another.
+fn beep(
+    mut commands: Commands,
+    mut beeper: ResMut<Beeper>,
+    playing: Query<(), With<AudioPlayer<Pitch>>>,
+) {
+    if beeper.left == 0 || !playing.is_empty() {
+        return;
+    }
+    let Some(beep) = beeper.beep.clone() else {
+        return;
+    };
+    beeper.left -= 1;
+    commands.spawn((AudioPlayer(beep), PlaybackSettings::DESPAWN));
+}
+
+fn update_alarm_text(
+    alarms: Res<Alarms>,
+    ringing: Res<Ringing>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    mut text: Query<(&mut Text, &mut TextColor), With<AlarmText>>,
+) {
+    let Ok((mut text, mut color)) = text.single_mut() else {
+        return;
+    };
+    if let Some((name, _)) = &ringing.0 {
+        **text = format!("ALARM: {}", name);
+        *color = TextColor(RED.into());
+        return;
+    }
+    let now = solar.et + fixed.elapsed_secs_f64();
+    **text = match alarms.upcoming().first() {
+        Some(alarm) => match alarm.due {
+            Some(due) => format!(
+                "Next alarm: {} at {}, in {:.0} s",
+                alarm.name,
+                format_met(due - solar.et),
+                due - now
+            ),
+            None => format!("Next alarm: {}, {}", alarm.name, alarm.describe()),
+        },
+        None => String::new(),
+    };
+    *color = TextColor::WHITE;
+}
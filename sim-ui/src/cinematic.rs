@@ -0,0 +1,537 @@
+//! Cinematic camera paths.
+//!
+//! The main camera can be flown along a path of keys, each a pose (where the
+//! camera is, and which way it looks) at a time on the sim's clock, held in a
+//! frame: the craft being flown, or any other craft or body, by name, moving
+//! and turning with it, so that a key on the earth stays over the same
+//! ground.  Playing, the camera goes through the keys at their times, on a
+//! Catmull-Rom spline through where they are now, and turns smoothly from one
+//! to the next.  It is held at the first key until its time, and given back
+//! after the last.  As the path is on the sim's clock, it plays the same at
+//! any warp, and in a replay (see `sim_game::recording`), so a shot can be set
+//! up, and then recorded.
+//!
+//! F9 opens the editor panel, with the keys.  While it is open, right drag
+//! turns the camera about the craft, and scrolling moves it in and out.
+//! Insert puts down a key where the camera is, now, in the craft's frame,
+//! Delete takes the last one off, and Home plays the path.
+//!
+//! - `camera`: the keys.
+//! - `camera key [<time> [<frame>]]`: put down a key where the camera is, at
+//!   a time (now by default), in a frame (`ship`, the default, or a craft or
+//!   body).  A time is as for `at` (see `sim_game::sequence`).
+//! - `camera pose <time> <frame> <x> <y> <z> <at x> <at y> <at z>`: put down
+//!   a key at a point, in m, in the frame's axes, looking at another, with the
+//!   frame's Z up.
+//! - `camera play`, `camera stop`.
+//! - `camera remove <n>`, `camera clear`: take a key off, or all of them.
+//! - `camera save <file>`, `camera load <file>`: the keys, as RON.
+
+use bevy::{
+    input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use na::{UnitQuaternion, Vector3};
+use serde::{Deserialize, Serialize};
+use sim_astro::SolarState;
+use sim_core::{AttitudeState, OrbitalBody};
+use sim_game::{
+    alarm::format_met,
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    sequence::parse_time,
+    ship::{
+        PlayerShip,
+        registry::{CraftId, named},
+    },
+};
+use sim_render::{
+    bevy_quat_to_sim, bevy_to_sim,
+    interpolate::{Interpolation, LastStep},
+    origin::FloatingOrigin,
+    sim_quat_to_bevy, sim_to_bevy,
+};
+
+use crate::{
+    MainCameraMarker, UI_LAYER, layout::HudPanel, proximity::CwCamera, windows::target_window,
+};
+
+/// Each notch of the scroll wheel moves the camera in or out by this much.
+const ZOOM_STEP: f32 = 1.1;
+
+/// The nearest the editor brings the camera to the craft, m.
+const NEAREST: f32 = 1.0;
+
+/// A pose of the camera, at a time, in a frame.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraKey {
+    /// In seconds past J2000.
+    pub et: f64,
+    /// The craft or body the pose is held in, or None for the craft being
+    /// flown.
+    pub frame: Option<String>,
+    /// Where the camera is, m from the frame's middle, in its axes.
+    pub pos: Vector3<f64>,
+    /// Camera to frame.
+    pub rot: UnitQuaternion<f64>,
+}
+
+impl CameraKey {
+    fn frame_name(&self) -> &str {
+        self.frame.as_deref().unwrap_or("ship")
+    }
+}
+
+/// The keys, soonest first, and whether the camera is on them.
+#[derive(Resource, Default)]
+pub struct CameraPath {
+    pub keys: Vec<CameraKey>,
+    pub playing: bool,
+    /// Where the camera was when the path started playing.
+    saved: Option<Transform>,
+}
+
+impl CameraPath {
+    /// Put down a key, after any others at its time.
+    pub fn push(&mut self, key: CameraKey) {
+        let at = self.keys.partition_point(|k| k.et <= key.et);
+        self.keys.insert(at, key);
+    }
+}
+
+/// Whether the editor panel is open.
+#[derive(Resource, Default)]
+pub struct CameraEditorOpen(pub bool);
+
+#[derive(Component)]
+struct CameraText;
+
+/// The crafts and bodies the keys can be held in.
+type Frames<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Name,
+        Option<&'static CraftId>,
+        &'static OrbitalBody,
+        &'static AttitudeState,
+        Option<&'static LastStep>,
+    ),
+>;
+
+#[derive(Default)]
+pub struct CinematicPlugin;
+
+impl Plugin for CinematicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraPath>();
+        app.init_resource::<CameraEditorOpen>();
+        app.add_systems(Startup, setup_camera_text);
+        app.add_systems(
+            Update,
+            (camera_keys, edit_camera, play_path, update_camera_text).chain(),
+        );
+        app.add_console_command(
+            "camera",
+            "camera [key [<time> [<frame>]] | pose <time> <frame> <x> <y> <z> <at x> <at y> <at z> | play | stop | remove <n> | clear | save <file> | load <file>]   cinematic camera path",
+            camera_command,
+        );
+    }
+}
+
+fn setup_camera_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("Camera Path Text"),
+        HudPanel("camera"),
+        CameraText,
+    ));
+}
+
+/// The sim's time now, in seconds past J2000.
+fn now(solar: &SolarState, fixed: &Time<Fixed>) -> f64 {
+    solar.et + fixed.elapsed_secs_f64()
+}
+
+/// The frame `name` names, or the craft being flown for None: where it is
+/// drawn, km, world frame, and its attitude, body to world.
+fn find_frame(
+    frames: &Frames,
+    interpolation: &Interpolation,
+    ship: Option<Entity>,
+    name: Option<&str>,
+) -> Option<(Vector3<f64>, UnitQuaternion<f64>)> {
+    frames
+        .iter()
+        .find(|(entity, frame_name, id, ..)| match name {
+            Some(name) => named(name, frame_name.as_str(), *id),
+            None => Some(*entity) == ship,
+        })
+        .map(|(_, _, _, orbital, attitude, last)| {
+            (
+                interpolation.pos(orbital, last),
+                interpolation.q_bw(attitude, last),
+            )
+        })
+}
+
+/// A key, in `frame`, for the camera where `transform` has it.
+fn key_here(
+    transform: &Transform,
+    origin: &FloatingOrigin,
+    frame: (Vector3<f64>, UnitQuaternion<f64>),
+    et: f64,
+    name: Option<String>,
+) -> CameraKey {
+    let (pos, q_bw) = frame;
+    let from_frame = bevy_to_sim(&transform.translation) - origin.offset(&pos);
+    CameraKey {
+        et,
+        frame: name,
+        pos: q_bw.inverse_transform_vector(&from_frame),
+        rot: q_bw.inverse() * bevy_quat_to_sim(&transform.rotation),
+    }
+}
+
+/// Where a key puts the camera now, m from the origin, and its attitude, in
+/// the sim's axes.
+fn key_pose(
+    key: &CameraKey,
+    frames: &Frames,
+    interpolation: &Interpolation,
+    ship: Option<Entity>,
+    origin: &FloatingOrigin,
+) -> Option<(Vector3<f64>, UnitQuaternion<f64>)> {
+    let (pos, q_bw) = find_frame(frames, interpolation, ship, key.frame.as_deref())?;
+    Some((
+        origin.offset(&pos) + q_bw.transform_vector(&key.pos),
+        q_bw * key.rot,
+    ))
+}
+
+/// The point `u` of the way from `p1` to `p2`, on the Catmull-Rom spline
+/// through `p0` to `p3`.
+fn catmull_rom(
+    p0: &Vector3<f64>,
+    p1: &Vector3<f64>,
+    p2: &Vector3<f64>,
+    p3: &Vector3<f64>,
+    u: f64,
+) -> Vector3<f64> {
+    let u2 = u * u;
+    let u3 = u2 * u;
+    (p1 * 2.0
+        + (p2 - p0) * u
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * u2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * u3)
+        * 0.5
+}
+
+/// The editor's keys:
+///
+/// - F9: open or close the editor.
+/// - Insert: put down a key where the camera is, now.
+/// - Delete: take the last key off.
+/// - Home: play the path.
+#[allow(clippy::too_many_arguments)]
+fn camera_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    mut open: ResMut<CameraEditorOpen>,
+    mut path: ResMut<CameraPath>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    origin: Res<FloatingOrigin>,
+    interpolation: Res<Interpolation>,
+    frames: Frames,
+    ship: Query<Entity, With<PlayerShip>>,
+    camera: Query<&Transform, With<MainCameraMarker>>,
+) {
+    if kb.just_pressed(KeyCode::F9) {
+        open.0 = !open.0;
+    }
+    if !open.0 {
+        return;
+    }
+    if kb.just_pressed(KeyCode::Insert) {
+        let frame = find_frame(&frames, &interpolation, ship.single().ok(), None);
+        if let (Some(frame), Ok(transform)) = (frame, camera.single()) {
+            let et = now(&solar, &fixed);
+            path.push(key_here(transform, &origin, frame, et, None));
+        }
+    }
+    if kb.just_pressed(KeyCode::Delete) {
+        path.keys.pop();
+    }
+    if kb.just_pressed(KeyCode::Home) {
+        path.playing = true;
+    }
+}
+
+/// With the editor open, right drag turns the camera about the craft, and
+/// scrolling moves it in and out.
+#[allow(clippy::too_many_arguments)]
+fn edit_camera(
+    open: Res<CameraEditorOpen>,
+    path: Res<CameraPath>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    motion: Res<AccumulatedMouseMotion>,
+    scroll: Res<AccumulatedMouseScroll>,
+    mut camera: Query<(&mut Transform, &Camera), With<MainCameraMarker>>,
+    windows: Query<&Window>,
+    primary: Query<Entity, With<PrimaryWindow>>,
+) {
+    if !open.0 || path.playing {
+        return;
+    }
+    let Ok((mut transform, view)) = camera.single_mut() else {
+        return;
+    };
+    // Only with the mouse over the ship view.
+    let window = target_window(view, primary.single().ok()).and_then(|w| windows.get(w).ok());
+    if !view.is_active || !window.is_some_and(|w| w.cursor_position().is_some()) {
+        return;
+    }
+    if buttons.pressed(MouseButton::Right) {
+        let turn = Quat::from_axis_angle(*transform.up(), -motion.delta.x * 0.005)
+            * Quat::from_axis_angle(*transform.right(), -motion.delta.y * 0.005);
+        transform.rotate_around(Vec3::ZERO, turn);
+    }
+    let notches = match scroll.unit {
+        MouseScrollUnit::Line => scroll.delta.y,
+        MouseScrollUnit::Pixel => scroll.delta.y / 32.0,
+    };
+    if notches != 0.0 {
+        let distance = (transform.translation.length() * ZOOM_STEP.powf(-notches)).max(NEAREST);
+        transform.translation = transform.translation.normalize_or_zero() * distance;
+    }
+}
+
+/// Put the camera on the path, where it is now, or give it back once the
+/// path is done.  The proximity camera, when it is on, comes first.
+#[allow(clippy::too_many_arguments)]
+fn play_path(
+    mut path: ResMut<CameraPath>,
+    cw: Res<CwCamera>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    origin: Res<FloatingOrigin>,
+    interpolation: Res<Interpolation>,
+    frames: Frames,
+    ship: Query<Entity, With<PlayerShip>>,
+    mut camera: Query<&mut Transform, With<MainCameraMarker>>,
+) {
+    let Ok(mut transform) = camera.single_mut() else {
+        return;
+    };
+    let now = now(&solar, &fixed);
+    let done = path.keys.last().is_none_or(|last| now >= last.et);
+    if !path.playing || done || cw.on {
+        path.playing = false;
+        if let Some(saved) = path.saved.take() {
+            *transform = saved;
+        }
+        return;
+    }
+    if path.saved.is_none() {
+        path.saved = Some(*transform);
+    }
+
+    // The keys either side of now, and the ones either side of those.
+    let keys = &path.keys;
+    let next = keys.partition_point(|k| k.et <= now).max(1);
+    let last = keys.len() - 1;
+    let (i0, i1, i2, i3) = (
+        next.saturating_sub(2),
+        next - 1,
+        next.min(last),
+        (next + 1).min(last),
+    );
+    let span = keys[i2].et - keys[i1].et;
+    let u = if span > 0.0 {
+        ((now - keys[i1].et) / span).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    let ship = ship.single().ok();
+    let poses: Option<Vec<_>> = [i0, i1, i2, i3]
+        .iter()
+        .map(|&i| key_pose(&keys[i], &frames, &interpolation, ship, &origin))
+        .collect();
+    let Some(poses) = poses else {
+        warn!("A camera key's frame is gone, so the path stops");
+        path.playing = false;
+        return;
+    };
+    let pos = catmull_rom(&poses[0].0, &poses[1].0, &poses[2].0, &poses[3].0, u);
+    // Eased, so that it turns from rest at each key.
+    let rot = poses[1].1.slerp(&poses[2].1, u * u * (3.0 - 2.0 * u));
+    transform.translation = sim_to_bevy(&pos);
+    transform.rotation = sim_quat_to_bevy(&rot);
+}
+
+fn update_camera_text(
+    open: Res<CameraEditorOpen>,
+    path: Res<CameraPath>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    mut text: Query<&mut Text, With<CameraText>>,
+) {
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    if !open.0 {
+        text.clear();
+        return;
+    }
+    let now = now(&solar, &fixed);
+    let mut lines = vec![format!(
+        "Camera path (F9): {}",
+        if path.playing { "playing" } else { "stopped" }
+    )];
+    for (i, key) in path.keys.iter().enumerate() {
+        lines.push(format!(
+            "{:>2}  T{:+.1} s  {}",
+            i + 1,
+            key.et - now,
+            key.frame_name()
+        ));
+    }
+    lines.push("Right drag: turn  Scroll: zoom".to_string());
+    lines.push("Insert: key  Delete: unkey  Home: play".to_string());
+    **text = lines.join("\n");
+}
+
+/// The frame a `camera` command names: `ship` is the craft being flown,
+/// whatever it is called.
+fn frame_arg(text: &str) -> Option<String> {
+    (!text.eq_ignore_ascii_case("ship")).then(|| text.to_string())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn camera_command(
+    In(args): In<Vec<String>>,
+    mut path: ResMut<CameraPath>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    origin: Res<FloatingOrigin>,
+    interpolation: Res<Interpolation>,
+    frames: Frames,
+    ship: Query<Entity, With<PlayerShip>>,
+    camera: Query<&Transform, With<MainCameraMarker>>,
+) -> ConsoleReply {
+    let now = now(&solar, &fixed);
+    let ship = ship.single().ok();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] => {
+            if path.keys.is_empty() {
+                return Ok("No camera keys".to_string());
+            }
+            Ok(path
+                .keys
+                .iter()
+                .enumerate()
+                .map(|(i, key)| {
+                    format!(
+                        "{}: at {}, in {}, ({:.1}, {:.1}, {:.1}) m",
+                        i + 1,
+                        format_met(key.et - solar.et),
+                        key.frame_name(),
+                        key.pos.x,
+                        key.pos.y,
+                        key.pos.z
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"))
+        }
+        ["key", rest @ ..] if rest.len() <= 2 => {
+            let et = match rest.first() {
+                Some(time) => parse_time(time, now)?,
+                None => now,
+            };
+            let name = rest.get(1).and_then(|text| frame_arg(text));
+            let frame = find_frame(&frames, &interpolation, ship, name.as_deref())
+                .ok_or_else(|| format!("No such frame: {}", rest.get(1).unwrap_or(&"ship")))?;
+            let transform = camera.single().map_err(|_| "No camera".to_string())?;
+            path.push(key_here(transform, &origin, frame, et, name));
+            Ok(format!("{} camera keys", path.keys.len()))
+        }
+        ["pose", time, frame, coords @ ..] if coords.len() == 6 => {
+            let et = parse_time(time, now)?;
+            let name = frame_arg(frame);
+            find_frame(&frames, &interpolation, ship, name.as_deref())
+                .ok_or_else(|| format!("No such frame: {}", frame))?;
+            let coords = coords
+                .iter()
+                .map(|c| parse_arg(c))
+                .collect::<Result<Vec<_>, _>>()?;
+            let pos = Vector3::new(coords[0], coords[1], coords[2]);
+            let look = Vector3::new(coords[3], coords[4], coords[5]) - pos;
+            if look.norm() == 0.0 {
+                return Err("The camera can't look at itself".to_string());
+            }
+            // The camera looks along Bevy's -Z, with its Y up, which in the
+            // sim's axes are Y and Z.
+            let rot = UnitQuaternion::face_towards(&look, &-Vector3::z())
+                * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f64::consts::FRAC_PI_2);
+            path.push(CameraKey {
+                et,
+                frame: name,
+                pos,
+                rot,
+            });
+            Ok(format!("{} camera keys", path.keys.len()))
+        }
+        ["play"] => {
+            if path.keys.is_empty() {
+                return Err("No camera keys".to_string());
+            }
+            path.playing = true;
+            Ok("Playing the camera path".to_string())
+        }
+        ["stop"] => {
+            path.playing = false;
+            Ok("Stopped the camera path".to_string())
+        }
+        ["remove", n] => {
+            let n: usize = n.parse().map_err(|_| format!("No such key: {}", n))?;
+            if n == 0 || n > path.keys.len() {
+                return Err(format!("No such key: {}", n));
+            }
+            path.keys.remove(n - 1);
+            Ok(format!("{} camera keys", path.keys.len()))
+        }
+        ["clear"] => {
+            path.keys.clear();
+            path.playing = false;
+            Ok("No camera keys".to_string())
+        }
+        ["save", file] => {
+            let text = ron::ser::to_string_pretty(&path.keys, Default::default())
+                .map_err(|e| e.to_string())?;
+            std::fs::write(file, text).map_err(|e| format!("{}: {}", file, e))?;
+            Ok(format!("Saved {} camera keys to {}", path.keys.len(), file))
+        }
+        ["load", file] => {
+            let text = std::fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+            let mut keys: Vec<CameraKey> =
+                ron::from_str(&text).map_err(|e| format!("{}: {}", file, e))?;
+            keys.sort_by(|a, b| a.et.total_cmp(&b.et));
+            path.keys = keys;
+            Ok(format!("Loaded {} camera keys", path.keys.len()))
+        }
+        _ => Err("camera [key [<time> [<frame>]] | pose <time> <frame> <x> <y> <z> <at x> <at y> <at z> | play | stop | remove <n> | clear | save <file> | load <file>]".to_string()),
+    }
+}
@@ -0,0 +1,154 @@
+//! The console overlay.
+//!
+//! The backtick key drops down the console (see `sim_game::console`) over the
+//! top of the screen, with the last few commands and their replies, and a line
+//! to type into.  Enter runs the line, and backtick or Escape puts the console
+//! away.  While it is open, the keys go to the console, and not to the ship.
+//!
+//! Typed lines go in with the ones from stdin, so a recording captures them
+//! the same way.
+
+use bevy::{
+    input::{
+        InputSystems,
+        keyboard::{Key, KeyboardInput},
+    },
+    prelude::*,
+};
+use sim_game::console::{ConsoleLines, ConsoleLog, read_console};
+
+use crate::UI_LAYER;
+
+/// How many lines of the log the overlay shows.
+const SHOWN_LINES: usize = 16;
+
+/// Whether the console is showing, and the line being typed.
+#[derive(Resource, Default)]
+pub struct ConsoleOverlay {
+    pub open: bool,
+    pub input: String,
+}
+
+#[derive(Component)]
+struct ConsolePanel;
+
+#[derive(Component)]
+struct ConsoleText;
+
+#[derive(Default)]
+pub struct ConsoleOverlayPlugin;
+
+impl Plugin for ConsoleOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConsoleOverlay>();
+        app.init_resource::<ConsoleLines>();
+        app.init_resource::<ConsoleLog>();
+        app.add_systems(Startup, setup_console);
+        // The keys are taken after bevy has seen them, but before the ship's
+        // controls, or a recording, do.
+        app.add_systems(
+            PreUpdate,
+            console_keys.after(InputSystems).before(read_console),
+        );
+        app.add_systems(Update, update_console);
+    }
+}
+
+fn setup_console(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(0.0),
+                left: Val::Px(0.0),
+                width: Val::Percent(100.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.8)),
+            GlobalZIndex(10),
+            Visibility::Hidden,
+            UI_LAYER,
+            Name::new("Console"),
+            ConsolePanel,
+        ))
+        .with_child((
+            Text::new(""),
+            TextFont {
+                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 16.0,
+                ..default()
+            },
+            UI_LAYER,
+            ConsoleText,
+        ));
+}
+
+/// Backtick opens and closes the console.  While it is open, every key is
+/// typed into it, and then taken away from everything else.
+fn console_keys(
+    mut keys: MessageReader<KeyboardInput>,
+    mut kb: ResMut<ButtonInput<KeyCode>>,
+    mut overlay: ResMut<ConsoleOverlay>,
+    mut lines: ResMut<ConsoleLines>,
+) {
+    let was_open = overlay.open;
+    for key in keys.read() {
+        if !key.state.is_pressed() {
+            continue;
+        }
+        if key.key_code == KeyCode::Backquote {
+            overlay.open = !overlay.open;
+            continue;
+        }
+        if !overlay.open {
+            continue;
+        }
+        match &key.logical_key {
+            Key::Enter => {
+                let line = std::mem::take(&mut overlay.input);
+                if !line.trim().is_empty() {
+                    lines.0.push(line);
+                }
+            }
+            Key::Escape => overlay.open = false,
+            Key::Backspace => {
+                overlay.input.pop();
+            }
+            Key::Space => overlay.input.push(' '),
+            Key::Character(text) => overlay.input.push_str(text),
+            _ => (),
+        }
+    }
+    if overlay.open || was_open {
+        kb.reset_all();
+    }
+}
+
+fn update_console(
+    overlay: Res<ConsoleOverlay>,
+    log: Res<ConsoleLog>,
+    mut panel: Query<&mut Visibility, With<ConsolePanel>>,
+    mut text: Query<&mut Text, With<ConsoleText>>,
+) {
+    if let Ok(mut visibility) = panel.single_mut() {
+        *visibility = if overlay.open {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+    if !overlay.open {
+        return;
+    }
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    let shown = &log.0[log.0.len().saturating_sub(SHOWN_LINES)..];
+    let mut message = shown.join("\n");
+    if !message.is_empty() {
+        message.push('\n');
+    }
+    message += &format!("> {}_", overlay.input);
+    text.0 = message;
+}
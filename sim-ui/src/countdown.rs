@@ -0,0 +1,92 @@
+//! The countdown panel.
+//!
+//! Shows the launch countdown (see `sim_game::countdown`) as a timeline: the
+//! count, in yellow while held and red once aborted, and the schedule under
+//! it, with the steps run so far ticked off and the next one marked.
+
+use bevy::{
+    color::palettes::css::{RED, YELLOW},
+    prelude::*,
+};
+use sim_astro::SolarState;
+use sim_game::{
+    alarm::format_met,
+    countdown::{CountState, Countdown},
+};
+use std::fmt::Write;
+
+use crate::{UI_LAYER, layout::HudPanel};
+
+#[derive(Component)]
+struct CountdownText;
+
+#[derive(Default)]
+pub struct CountdownViewPlugin;
+
+impl Plugin for CountdownViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_countdown);
+        app.add_systems(Update, update_countdown_text);
+    }
+}
+
+fn setup_countdown(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("Countdown Text"),
+        HudPanel("countdown"),
+        CountdownText,
+    ));
+}
+
+fn update_countdown_text(
+    countdown: Res<Countdown>,
+    solar: Res<SolarState>,
+    fixed: Res<Time<Fixed>>,
+    mut text: Query<(&mut Text, &mut TextColor), With<CountdownText>>,
+) {
+    let Ok((mut text, mut color)) = text.single_mut() else {
+        return;
+    };
+    let now = solar.et + fixed.elapsed_secs_f64();
+    let (heading, tint) = match &countdown.state {
+        // Nothing to show until it is started.
+        CountState::Idle => {
+            text.clear();
+            return;
+        }
+        CountState::Counting => (
+            format!("Countdown {}", format_met(countdown.count(now))),
+            Color::WHITE,
+        ),
+        CountState::Holding(count) => (
+            format!("Countdown {} HOLDING", format_met(*count)),
+            YELLOW.into(),
+        ),
+        CountState::Aborted(reason) => (format!("Countdown ABORTED: {}", reason), RED.into()),
+        CountState::Done => ("Countdown done".to_string(), Color::WHITE),
+    };
+    let mut message = heading;
+    for (i, step) in countdown.schedule.iter().enumerate() {
+        let mark = if i < countdown.next {
+            "x"
+        } else if i == countdown.next && countdown.running() {
+            ">"
+        } else {
+            " "
+        };
+        write!(message, "\n{} {}", mark, step.describe()).unwrap();
+    }
+    **text = message;
+    *color = TextColor(tint);
+}
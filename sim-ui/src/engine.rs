@@ -0,0 +1,55 @@
+//! The main engine, in the info text.
+//!
+//! The throttle, as the autothrottle holds it, and the propellant show as
+//! gauges, with the Δv left in the tank, and, with stages to come, in each of
+//! them.
+
+use sim_game::ship::{
+    MassProperties,
+    engine::{FuelTank, MainEngine},
+    propulsion::G0,
+    staging::{Staging, budget},
+};
+
+use crate::power::gauge;
+
+/// The line for the info text.
+pub fn engine_line(
+    engine: &MainEngine,
+    tank: Option<&FuelTank>,
+    staging: Option<&Staging>,
+    mass: &MassProperties,
+) -> String {
+    let budget = budget(engine, tank, staging, mass.mass);
+    let mut propellant = match tank {
+        Some(tank) => format!(
+            ", propellant {} {:.0} kg, {:.0} m/s left",
+            gauge(tank.fraction()),
+            tank.propellant,
+            budget[0]
+        ),
+        None => String::new(),
+    };
+    if budget.len() > 1 {
+        let stages: Vec<String> = budget.iter().map(|dv| format!("{:.0}", dv)).collect();
+        propellant += &format!(
+            "\n Stages: {} m/s, {:.0} m/s in all",
+            stages.join(" + "),
+            budget.iter().sum::<f64>()
+        );
+    }
+    // With the autothrottle, the throttle it is held back to, and what to.
+    let throttle = engine.held_throttle(mass.mass);
+    let held = match engine.autothrottle {
+        Some(accel) => format!(", held to {:.2} g", accel / G0),
+        None => String::new(),
+    };
+    format!(
+        "Engine: {} {:.0}% of {:.0} kN{}{}",
+        gauge(throttle),
+        throttle * 100.0,
+        engine.max_thrust / 1000.0,
+        held,
+        propellant
+    )
+}
@@ -0,0 +1,259 @@
+//! The flight HUD.
+//!
+//! The numbers for flying low over a body, each in a widget of its own: an
+//! altitude tape, with the altitude in the middle and marks above and below
+//! it, spaced to suit the height, and under it, the vertical speed, the
+//! orbital and surface speeds, the g-load, and, in the air, the dynamic
+//! pressure, Mach number and angle of attack.  The g-load and dynamic
+//! pressure go yellow, and then red, as they get high.
+//!
+//! As for the info text, it all shows the craft as it believes itself to be,
+//! if that's what it is flown by, and as last heard, flown from the ground.
+//! The g-load is what the craft feels: its thrust, drag and so on, or on the
+//! ground, the ground holding it up.
+
+use bevy::{
+    color::palettes::css::{GOLD, RED, YELLOW},
+    prelude::*,
+};
+use sim_astro::{EarthMarker, atmosphere::Atmosphere, contact::Landed, geodesy::Geodetic};
+use sim_core::{AttitudeState, LinearControl, MassiveBody, OrbitalBody, SizedBody};
+use sim_game::{
+    remote::RemoteControl,
+    ship::{
+        PlayerShip,
+        aero::Aero,
+        nav::{NavSource, Navigation},
+        propulsion::G0,
+    },
+};
+
+use crate::{UI_LAYER, layout::HudPanel};
+
+/// The marks on the tape either side of the altitude.
+const TAPE_MARKS: usize = 4;
+
+/// How many marks the tape shows an altitude's worth of, at most, so that
+/// the spacing grows with the height.
+const TAPE_SPAN: f64 = 40.0;
+
+/// The finest spacing of the marks, km.
+const TAPE_FINEST: f64 = 0.01;
+
+/// The g-loads, and dynamic pressures (Pa), past which they show yellow,
+/// and red.
+const G_WARNING: [f64; 2] = [3.0, 6.0];
+const Q_WARNING: [f64; 2] = [20.0e3, 35.0e3];
+
+/// A row of the altitude tape: the altitude itself at 0, marks above it
+/// positive, and below negative.
+#[derive(Component)]
+struct TapeRow(i32);
+
+/// A readout under the tape.
+#[derive(Component, Clone, Copy)]
+enum Readout {
+    VerticalSpeed,
+    Speed,
+    GLoad,
+    DynamicPressure,
+    Mach,
+    AngleOfAttack,
+}
+
+#[derive(Default)]
+pub struct FlightHudPlugin;
+
+impl Plugin for FlightHudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_flight_hud);
+        app.add_systems(Update, update_flight_hud);
+    }
+}
+
+fn setup_flight_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let font = TextFont {
+        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+        font_size: 16.0,
+        ..default()
+    };
+    let marks = TAPE_MARKS as i32;
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(6.0)),
+                row_gap: Val::Px(2.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.5)),
+            UI_LAYER,
+            Name::new("Flight HUD"),
+            HudPanel("flight"),
+        ))
+        .with_children(|hud| {
+            for row in (-marks..=marks).rev() {
+                let (color, border) = if row == 0 {
+                    (GOLD.into(), Color::from(GOLD))
+                } else {
+                    (Color::srgb(0.7, 0.7, 0.7), Color::NONE)
+                };
+                hud.spawn((
+                    Text::new(""),
+                    font.clone(),
+                    TextColor(color),
+                    Node {
+                        border: UiRect::all(Val::Px(1.0)),
+                        ..default()
+                    },
+                    BorderColor::all(border),
+                    TapeRow(row),
+                ));
+            }
+            for readout in [
+                Readout::VerticalSpeed,
+                Readout::Speed,
+                Readout::GLoad,
+                Readout::DynamicPressure,
+                Readout::Mach,
+                Readout::AngleOfAttack,
+            ] {
+                hud.spawn((Text::new(""), font.clone(), readout));
+            }
+        });
+}
+
+/// The spacing, km, of the marks for an altitude: 1, 2 or 5 times a power
+/// of ten, so that the tape spans about `TAPE_SPAN` of them.
+fn tape_spacing(altitude: f64) -> f64 {
+    let rough = (altitude.abs() / TAPE_SPAN).max(TAPE_FINEST);
+    let decade = 10f64.powf(rough.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|step| step * decade)
+        .find(|spacing| *spacing >= rough)
+        .unwrap_or(10.0 * decade)
+}
+
+/// An altitude, km, to as many places as the spacing of the marks needs.
+fn format_altitude(altitude: f64, spacing: f64) -> String {
+    let places = (-spacing.log10().floor()).max(0.0) as usize;
+    format!("{:>10.*} km", places, altitude)
+}
+
+/// Yellow, or red, past the warnings.
+fn warning_color(value: f64, warning: [f64; 2]) -> Color {
+    if value >= warning[1] {
+        RED.into()
+    } else if value >= warning[0] {
+        YELLOW.into()
+    } else {
+        Color::WHITE
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_flight_hud(
+    fixed: Res<Time<Fixed>>,
+    remote: Res<RemoteControl>,
+    nav_source: Res<NavSource>,
+    ship: Query<
+        (
+            &OrbitalBody,
+            &AttitudeState,
+            Option<&LinearControl>,
+            Option<&Aero>,
+            Option<&Navigation>,
+            Has<Landed>,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<
+        (&OrbitalBody, &AttitudeState, &SizedBody, &MassiveBody),
+        (With<EarthMarker>, Without<PlayerShip>),
+    >,
+    mut rows: Query<(&TapeRow, &mut Text), Without<Readout>>,
+    mut readouts: Query<(&Readout, &mut Text, &mut TextColor), Without<TapeRow>>,
+) {
+    let (
+        Ok((truth, truth_attitude, linear, aero, nav, landed)),
+        Ok((earth, earth_attitude, earth_size, earth_mass)),
+    ) = (ship.single(), earth.single())
+    else {
+        return;
+    };
+    let (ship, _) = match remote.heard(fixed.elapsed_secs_f64()) {
+        Some(heard) => (heard.orbital.clone(), heard.attitude.clone()),
+        None => nav_source.view(nav, truth, truth_attitude),
+    };
+
+    let rel = ship.pos - earth.pos;
+    let up = rel.normalize();
+    let surface = Atmosphere::wind_relative(earth, earth_attitude, &ship.pos, &ship.vel);
+    let altitude = Geodetic::from_world(earth, earth_attitude, &earth_size.radii, &ship.pos).alt;
+
+    let spacing = tape_spacing(altitude);
+    for (row, mut text) in rows.iter_mut() {
+        **text = match row.0 {
+            0 => format_altitude(altitude, spacing),
+            // The marks, from the first past the altitude each way.
+            above if above > 0 => {
+                let mark = ((altitude / spacing).floor() + above as f64) * spacing;
+                format_altitude(mark, spacing)
+            }
+            below => {
+                let mark = ((altitude / spacing).ceil() + below as f64) * spacing;
+                format_altitude(mark, spacing)
+            }
+        };
+    }
+
+    // km/s^2 to g.
+    let g_load = if landed {
+        earth_mass.gm / rel.norm_squared() * 1000.0 / G0
+    } else {
+        linear.map_or(0.0, |linear| linear.accel_b.norm() * 1000.0 / G0)
+    };
+    let air = aero.filter(|aero| aero.dynamic_pressure > 0.0);
+    for (readout, mut text, mut color) in readouts.iter_mut() {
+        let (line, tint) = match readout {
+            Readout::VerticalSpeed => (
+                format!("V/S {:+9.1} m/s", surface.dot(&up) * 1000.0),
+                Color::WHITE,
+            ),
+            Readout::Speed => (
+                format!(
+                    "ORB {:.3} km/s  SRF {:.3} km/s",
+                    (ship.vel - earth.vel).norm(),
+                    surface.norm()
+                ),
+                Color::WHITE,
+            ),
+            Readout::GLoad => (
+                format!("G   {:9.2}", g_load),
+                warning_color(g_load, G_WARNING),
+            ),
+            Readout::DynamicPressure => match air {
+                Some(aero) => (
+                    format!("Q   {:9.2} kPa", aero.dynamic_pressure / 1000.0),
+                    warning_color(aero.dynamic_pressure, Q_WARNING),
+                ),
+                None => ("Q         ---".to_string(), Color::WHITE),
+            },
+            Readout::Mach => match air.and_then(|aero| aero.mach) {
+                Some(mach) => (format!("M   {:9.2}", mach), Color::WHITE),
+                None => ("M         ---".to_string(), Color::WHITE),
+            },
+            Readout::AngleOfAttack => match air {
+                Some(aero) => (
+                    format!("AOA {:9.1} deg", aero.angle_of_attack.to_degrees()),
+                    Color::WHITE,
+                ),
+                None => ("AOA       ---".to_string(), Color::WHITE),
+            },
+        };
+        **text = line;
+        *color = TextColor(tint);
+    }
+}
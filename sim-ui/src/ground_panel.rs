@@ -0,0 +1,170 @@
+//! The ground track panel.
+//!
+//! G toggles a small equirectangular map of the earth, showing where the ship
+//! has been, and where it will be over the next few orbits, and, if the path
+//! comes down, where it enters the air, and where it hits the ground.  The
+//! panel has its own 2D camera, drawn into a viewport wherever the HUD layout
+//! puts it (at the top of the window, to start with).  See `layout`.
+
+use bevy::{
+    camera::visibility::RenderLayers,
+    color::palettes::css::{GOLD, GRAY, ORANGE, RED, WHITE},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use sim_game::ship::{PlayerShip, ground_track::GroundTrack, predict::Prediction};
+
+use crate::{
+    layout::HudPanel,
+    windows::{Detachable, DetachedPanels},
+};
+
+pub const GROUND_LAYER: RenderLayers = RenderLayers::layer(5);
+
+/// The size of the panel, in logical pixels.  Longitude runs across, and
+/// latitude up.
+const PANEL_SIZE: Vec2 = Vec2::new(480.0, 240.0);
+
+/// Gizmos drawn only in the ground track panel.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct GroundGizmos;
+
+#[derive(Component)]
+struct GroundCamera;
+
+#[derive(Default)]
+pub struct GroundPanelPlugin;
+
+impl Plugin for GroundPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_gizmo_group::<GroundGizmos>();
+        app.add_systems(Startup, setup_ground_panel);
+        app.add_systems(Update, (ground_panel_keys, draw_ground_track).chain());
+    }
+}
+
+fn setup_ground_panel(mut commands: Commands, mut config_store: ResMut<GizmoConfigStore>) {
+    let (config, _) = config_store.config_mut::<GroundGizmos>();
+    config.render_layers = GROUND_LAYER;
+
+    commands.spawn((
+        Camera2d,
+        Camera {
+            order: 6,
+            is_active: false,
+            clear_color: ClearColorConfig::Custom(Color::srgb(0.02, 0.05, 0.1)),
+            ..default()
+        },
+        GROUND_LAYER,
+        Name::new("Ground Track Camera"),
+        GroundCamera,
+        HudPanel("ground"),
+        Detachable {
+            name: "ground",
+            size: PANEL_SIZE.as_uvec2(),
+        },
+    ));
+}
+
+/// G toggles the panel.  The viewport's size follows the window's scale, and
+/// the layout moves it.  In a window of its own, it is always showing.
+fn ground_panel_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    detached: Res<DetachedPanels>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut camera: Query<&mut Camera, With<GroundCamera>>,
+) {
+    let Ok(mut camera) = camera.single_mut() else {
+        return;
+    };
+    if detached.contains("ground") {
+        camera.is_active = true;
+        return;
+    }
+    if kb.just_pressed(KeyCode::KeyG) {
+        camera.is_active = !camera.is_active;
+    }
+    let Ok(window) = window.single() else {
+        return;
+    };
+
+    camera.viewport.get_or_insert_default().physical_size =
+        (PANEL_SIZE * window.scale_factor()).as_uvec2();
+}
+
+/// Where a (latitude, longitude), in degrees, goes on the panel.
+fn panel_point((lat, lon): (f64, f64)) -> Vec2 {
+    Vec2::new(
+        (lon / 360.0) as f32 * PANEL_SIZE.x,
+        (lat / 180.0) as f32 * PANEL_SIZE.y,
+    )
+}
+
+/// Draw a track, breaking it where it wraps around in longitude.
+fn draw_track<'a>(
+    gizmos: &mut Gizmos<GroundGizmos>,
+    track: impl IntoIterator<Item = &'a (f64, f64)>,
+    color: impl Into<Color> + Copy,
+) {
+    let mut segment: Vec<Vec2> = Vec::new();
+    let mut last_lon: Option<f64> = None;
+    for point in track {
+        if let Some(last) = last_lon
+            && (point.1 - last).abs() > 180.0
+        {
+            gizmos.linestrip_2d(segment.drain(..), color);
+        }
+        segment.push(panel_point(*point));
+        last_lon = Some(point.1);
+    }
+    gizmos.linestrip_2d(segment, color);
+}
+
+fn draw_ground_track(
+    mut gizmos: Gizmos<GroundGizmos>,
+    camera: Query<&Camera, With<GroundCamera>>,
+    ship: Query<(&GroundTrack, &Prediction), With<PlayerShip>>,
+) {
+    let Ok(camera) = camera.single() else {
+        return;
+    };
+    if !camera.is_active {
+        return;
+    }
+
+    // A grid every 30 degrees, with the equator and prime meridian brighter.
+    let grid = Color::srgb(0.2, 0.25, 0.3);
+    for lon in (-180..=180).step_by(30) {
+        let color = if lon == 0 { Color::from(GRAY) } else { grid };
+        gizmos.line_2d(
+            panel_point((-90.0, lon as f64)),
+            panel_point((90.0, lon as f64)),
+            color,
+        );
+    }
+    for lat in (-90..=90).step_by(30) {
+        let color = if lat == 0 { Color::from(GRAY) } else { grid };
+        gizmos.line_2d(
+            panel_point((lat as f64, -180.0)),
+            panel_point((lat as f64, 180.0)),
+            color,
+        );
+    }
+
+    let Ok((track, prediction)) = ship.single() else {
+        return;
+    };
+    draw_track(&mut gizmos, &track.predicted, WHITE);
+    draw_track(&mut gizmos, &track.history, GOLD);
+    if let Some(now) = track.predicted.first() {
+        gizmos.circle_2d(Isometry2d::from_translation(panel_point(*now)), 4.0, GOLD);
+    }
+    if let Some(entry) = prediction.entry {
+        let at = panel_point((entry.lat, entry.lon));
+        gizmos.circle_2d(Isometry2d::from_translation(at), 4.0, ORANGE);
+    }
+    if let Some(impact) = prediction.impact {
+        let at = panel_point((impact.lat, impact.lon));
+        gizmos.cross_2d(Isometry2d::from_translation(at), 5.0, RED);
+    }
+}
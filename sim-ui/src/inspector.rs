@@ -0,0 +1,199 @@
+//! The mass-property inspector.
+//!
+//! F3 toggles a panel showing the player ship's mass, center of mass, and
+//! moments of inertia.  While it is open, the mass properties can be edited
+//! from the keyboard, and the equivalent inertia ellipsoid is drawn around the
+//! ship, centered on the center of mass.  Everything is read live from
+//! `MassProperties`, so it follows along as the craft changes.
+
+use bevy::{color::palettes::css::ORANGE, prelude::*};
+use na::Vector3;
+use std::io::Write;
+
+use sim_core::AttitudeState;
+use sim_game::ship::{MassProperties, PlayerShip};
+use sim_render::{sim_quat_to_bevy, sim_to_bevy};
+
+use crate::{UI_LAYER, layout::HudPanel};
+
+/// How far each press moves the center of mass, in meters.
+const CG_STEP: f64 = 0.1;
+
+/// Each press scales the mass by this much.
+const MASS_STEP: f64 = 1.1;
+
+/// Whether the inspector is showing.
+#[derive(Resource, Default)]
+pub struct InspectorOpen(pub bool);
+
+#[derive(Component)]
+struct InspectorText;
+
+#[derive(Default)]
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InspectorOpen>();
+        app.add_systems(Startup, setup_inspector);
+        app.add_systems(
+            Update,
+            (inspector_keys, update_inspector, draw_inertia).chain(),
+        );
+    }
+}
+
+fn setup_inspector(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 18.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("Inspector Text"),
+        HudPanel("inspector"),
+        InspectorText,
+    ));
+}
+
+/// Keyboard editing of the mass properties, while the inspector is open:
+///
+/// - F3: open or close the inspector.
+/// - Equal/Minus: scale the mass (and the inertia along with it).
+/// - Arrows: move the center of mass along X (left/right) and Y (up/down).
+/// - PageUp/PageDown: move the center of mass along Z.
+/// - Backspace: put the center of mass back at the body origin.
+fn inspector_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    mut open: ResMut<InspectorOpen>,
+    mut ship: Query<&mut MassProperties, With<PlayerShip>>,
+) {
+    if kb.just_pressed(KeyCode::F3) {
+        open.0 = !open.0;
+    }
+    if !open.0 {
+        return;
+    }
+    let Ok(mut mass) = ship.single_mut() else {
+        return;
+    };
+
+    let axis = |pos, neg| {
+        let mut value = 0.0;
+        if kb.just_pressed(pos) {
+            value += 1.0;
+        }
+        if kb.just_pressed(neg) {
+            value -= 1.0;
+        }
+        value
+    };
+
+    let scale = axis(KeyCode::Equal, KeyCode::Minus);
+    if scale != 0.0 {
+        let scale = MASS_STEP.powf(scale);
+        mass.mass *= scale;
+        mass.inertia_b *= scale;
+    }
+
+    let dcg = Vector3::new(
+        axis(KeyCode::ArrowRight, KeyCode::ArrowLeft),
+        axis(KeyCode::ArrowUp, KeyCode::ArrowDown),
+        axis(KeyCode::PageUp, KeyCode::PageDown),
+    );
+    if dcg != Vector3::zeros() {
+        mass.cg_b += dcg * CG_STEP;
+    }
+    if kb.just_pressed(KeyCode::Backspace) {
+        mass.cg_b = Vector3::zeros();
+    }
+}
+
+fn update_inspector(
+    open: Res<InspectorOpen>,
+    mut text: Query<&mut Text, With<InspectorText>>,
+    ship: Query<&MassProperties, With<PlayerShip>>,
+) {
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    let Ok(mass) = ship.single() else {
+        text.clear();
+        return;
+    };
+    if !open.0 {
+        text.clear();
+        return;
+    }
+
+    let axes = mass.equivalent_ellipsoid();
+    let mut message = Vec::new();
+    writeln!(message, "Mass: {:.1} kg", mass.mass).unwrap();
+    writeln!(
+        message,
+        " CG: {:.2}, {:.2}, {:.2} m",
+        mass.cg_b.x, mass.cg_b.y, mass.cg_b.z
+    )
+    .unwrap();
+    writeln!(
+        message,
+        " I: {:.0}, {:.0}, {:.0} kg m^2",
+        mass.inertia_b.x, mass.inertia_b.y, mass.inertia_b.z
+    )
+    .unwrap();
+    writeln!(
+        message,
+        " Ellipsoid: {:.2}, {:.2}, {:.2} m",
+        axes.x, axes.y, axes.z
+    )
+    .unwrap();
+    **text = String::from_utf8(message).unwrap();
+}
+
+/// Draw the inertia ellipsoid as its three principal sections, along with a
+/// cross at the center of mass.  The ship sits at the bevy origin, in meters.
+fn draw_inertia(
+    open: Res<InspectorOpen>,
+    mut gizmos: Gizmos,
+    ship: Query<(&MassProperties, &AttitudeState), With<PlayerShip>>,
+) {
+    if !open.0 {
+        return;
+    }
+    let Ok((mass, attitude)) = ship.single() else {
+        return;
+    };
+
+    let center = sim_to_bevy(&attitude.q_bw.transform_vector(&mass.cg_b));
+    let axes = mass.equivalent_ellipsoid();
+    let half = |a: f64, b: f64| Vec2::new(a as f32, b as f32);
+
+    // Each section is drawn in the XY plane of its own bevy frame, which is
+    // the XZ plane of the sim frame, so rotate that onto each body plane.
+    let sections = [
+        (na::UnitQuaternion::identity(), half(axes.x, axes.z)),
+        (
+            na::UnitQuaternion::from_axis_angle(&Vector3::x_axis(), -std::f64::consts::FRAC_PI_2),
+            half(axes.x, axes.y),
+        ),
+        (
+            na::UnitQuaternion::from_axis_angle(&Vector3::z_axis(), std::f64::consts::FRAC_PI_2),
+            half(axes.y, axes.z),
+        ),
+    ];
+    for (q_section, half_size) in sections {
+        let rotation = sim_quat_to_bevy(&(attitude.q_bw * q_section));
+        gizmos.ellipse(Isometry3d::new(center, rotation), half_size, ORANGE);
+    }
+    gizmos.cross(
+        Isometry3d::new(center, sim_quat_to_bevy(&attitude.q_bw)),
+        0.5,
+        ORANGE,
+    );
+}
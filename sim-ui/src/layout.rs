@@ -0,0 +1,515 @@
+//! The HUD layout.
+//!
+//! Each panel of the HUD (the navball, the text readouts, the ground track) is
+//! tagged with a `HudPanel` naming it, and is put wherever the current
+//! `HudLayout` says: anchored to a corner, an edge, or the middle of the
+//! window, some logical pixels in from it, or hidden.  The navball, the
+//! ground track, and the sky view are cameras, drawn into viewports, and are
+//! moved the same way.  A panel opened in a window of its own (see
+//! `windows`) is left out of the layout until it is put back.
+//!
+//! There is a layout for each `HudProfile`: launch, orbit, docking, and
+//! landing.  The profile follows what the ship is doing, unless one is picked
+//! with `hud profile`.  Layouts are edited from the console, and `hud save`
+//! keeps the current one for this user, as RON, in `layout_dir()`.  Panels a
+//! saved layout doesn't mention (such as ones added since) stay where the
+//! defaults put them.
+//!
+//! - `hud`: the profile, and where each panel is.
+//! - `hud profile <launch|orbit|docking|landing|auto>`: pick a profile, or go
+//!   back to following the ship.
+//! - `hud move <panel> <anchor> <x> <y>`: move a panel, where the anchor is one
+//!   of `top-left`, `top`, `top-right`, `left`, `center`, `right`,
+//!   `bottom-left`, `bottom`, or `bottom-right`.
+//! - `hud show <panel>`, `hud hide <panel>`.
+//! - `hud save`, `hud reset`: save the profile's layout, or go back to its
+//!   defaults.
+
+use bevy::{prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+use sim_astro::contact::Landed;
+use sim_core::{OrbitalBody, SizedBody};
+use sim_game::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    ship::{PlayerShip, docking::DockingPort, proximity::Proximity},
+};
+use std::{collections::BTreeMap, path::PathBuf};
+
+use crate::windows::DetachedPanels;
+
+/// Below this altitude, in km above the nearest body's equator, the ship is
+/// launching (going up) or landing (coming down).
+const LOW_ALTITUDE: f64 = 100.0;
+
+/// A panel of the HUD, by the name layouts know it by.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct HudPanel(pub &'static str);
+
+/// What a panel is placed relative to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+/// Where a panel sits along one axis of the window.
+#[derive(Clone, Copy)]
+enum Side {
+    Start,
+    Middle,
+    End,
+}
+
+impl Anchor {
+    const NAMES: [(&'static str, Anchor); 9] = [
+        ("top-left", Anchor::TopLeft),
+        ("top", Anchor::Top),
+        ("top-right", Anchor::TopRight),
+        ("left", Anchor::Left),
+        ("center", Anchor::Center),
+        ("right", Anchor::Right),
+        ("bottom-left", Anchor::BottomLeft),
+        ("bottom", Anchor::Bottom),
+        ("bottom-right", Anchor::BottomRight),
+    ];
+
+    fn parse(name: &str) -> Result<Anchor, String> {
+        Self::NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, anchor)| *anchor)
+            .ok_or_else(|| format!("No such anchor as {:?}", name))
+    }
+
+    fn name(self) -> &'static str {
+        Self::NAMES.iter().find(|(_, a)| *a == self).unwrap().0
+    }
+
+    /// The horizontal and vertical sides.
+    fn sides(self) -> (Side, Side) {
+        match self {
+            Anchor::TopLeft => (Side::Start, Side::Start),
+            Anchor::Top => (Side::Middle, Side::Start),
+            Anchor::TopRight => (Side::End, Side::Start),
+            Anchor::Left => (Side::Start, Side::Middle),
+            Anchor::Center => (Side::Middle, Side::Middle),
+            Anchor::Right => (Side::End, Side::Middle),
+            Anchor::BottomLeft => (Side::Start, Side::End),
+            Anchor::Bottom => (Side::Middle, Side::End),
+            Anchor::BottomRight => (Side::End, Side::End),
+        }
+    }
+}
+
+impl Side {
+    /// Where a panel `size` long starts, `offset` in from this side of a
+    /// window `window` long.  From the middle, the offset is toward the end.
+    fn start(self, offset: f32, size: f32, window: f32) -> f32 {
+        match self {
+            Side::Start => offset,
+            Side::Middle => (window - size) / 2.0 + offset,
+            Side::End => window - size - offset,
+        }
+    }
+
+    /// The UI node's position from the start and the end.  The ends are set
+    /// directly, so a panel that grows stays put against its side.
+    fn vals(self, offset: f32, size: f32, window: f32) -> (Val, Val) {
+        match self {
+            Side::End => (Val::Auto, Val::Px(offset)),
+            _ => (Val::Px(self.start(offset, size, window)), Val::Auto),
+        }
+    }
+}
+
+/// Where a panel goes.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Placement {
+    pub anchor: Anchor,
+    /// How far in from the anchor, in logical pixels, across and down.
+    pub x: f32,
+    pub y: f32,
+    pub visible: bool,
+}
+
+impl Placement {
+    fn new(anchor: Anchor, x: f32, y: f32) -> Self {
+        Placement {
+            anchor,
+            x,
+            y,
+            visible: true,
+        }
+    }
+
+    /// The top left corner of a panel of `size`, in a window of `window`.
+    fn origin(&self, size: Vec2, window: Vec2) -> Vec2 {
+        let (across, down) = self.anchor.sides();
+        Vec2::new(
+            across.start(self.x, size.x, window.x),
+            down.start(self.y, size.y, window.y),
+        )
+    }
+}
+
+/// Where each panel goes, by name.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HudLayout(pub BTreeMap<String, Placement>);
+
+/// The kinds of flying, each with its own layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HudProfile {
+    Launch,
+    Orbit,
+    Docking,
+    Landing,
+}
+
+impl HudProfile {
+    const ALL: [HudProfile; 4] = [
+        HudProfile::Launch,
+        HudProfile::Orbit,
+        HudProfile::Docking,
+        HudProfile::Landing,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            HudProfile::Launch => "launch",
+            HudProfile::Orbit => "orbit",
+            HudProfile::Docking => "docking",
+            HudProfile::Landing => "landing",
+        }
+    }
+
+    fn parse(name: &str) -> Result<HudProfile, String> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.name() == name)
+            .ok_or_else(|| format!("No such profile as {:?}", name))
+    }
+
+    /// Where its layout is saved.
+    fn path(self) -> PathBuf {
+        layout_dir().join(format!("{}.ron", self.name()))
+    }
+
+    /// The built in layout.  These are where the panels have always been,
+    /// with the node readout out of the way when there's no orbit to plan,
+    /// and the proximity panel up front, in place of the flight HUD, when
+    /// docking.
+    fn defaults(self) -> HudLayout {
+        let mut panels = BTreeMap::from([
+            ("navball", Placement::new(Anchor::TopLeft, 10.0, 10.0)),
+            ("sas", Placement::new(Anchor::TopLeft, 220.0, 10.0)),
+            ("fps", Placement::new(Anchor::TopRight, 5.0, 5.0)),
+            ("info", Placement::new(Anchor::BottomLeft, 5.0, 5.0)),
+            ("attitude", Placement::new(Anchor::TopLeft, 10.0, 214.0)),
+            ("node", Placement::new(Anchor::TopLeft, 10.0, 240.0)),
+            ("inspector", Placement::new(Anchor::TopRight, 10.0, 160.0)),
+            ("map", Placement::new(Anchor::BottomRight, 10.0, 5.0)),
+            ("proximity", Placement::new(Anchor::Left, 5.0, 0.0)),
+            ("ground", Placement::new(Anchor::Top, 0.0, 10.0)),
+            ("sky", Placement::new(Anchor::Right, 10.0, 0.0)),
+            ("alarm", Placement::new(Anchor::Bottom, 0.0, 10.0)),
+            ("countdown", Placement::new(Anchor::Bottom, 0.0, 40.0)),
+            ("camera", Placement::new(Anchor::TopRight, 10.0, 300.0)),
+            ("view", Placement::new(Anchor::TopRight, 10.0, 30.0)),
+            ("flight", Placement::new(Anchor::Left, 5.0, 0.0)),
+        ]);
+        let hide = |panels: &mut BTreeMap<_, Placement>, name| {
+            if let Some(placement) = panels.get_mut(name) {
+                placement.visible = false;
+            }
+        };
+        match self {
+            HudProfile::Launch | HudProfile::Landing => {
+                hide(&mut panels, "node");
+                hide(&mut panels, "proximity");
+            }
+            HudProfile::Orbit => (),
+            HudProfile::Docking => {
+                hide(&mut panels, "node");
+                hide(&mut panels, "flight");
+                panels.insert("proximity", Placement::new(Anchor::TopLeft, 10.0, 240.0));
+            }
+        }
+        HudLayout(
+            panels
+                .into_iter()
+                .map(|(name, placement)| (name.to_string(), placement))
+                .collect(),
+        )
+    }
+
+    /// The defaults, with whatever this user has saved over them.
+    fn load(self) -> HudLayout {
+        let mut layout = self.defaults();
+        let path = self.path();
+        if !path.exists() {
+            return layout;
+        }
+        let saved = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| ron::from_str::<HudLayout>(&text).map_err(|e| e.to_string()));
+        match saved {
+            Ok(saved) => layout.0.extend(saved.0),
+            Err(e) => error!("Unable to load {}: {}", path.display(), e),
+        }
+        layout
+    }
+}
+
+/// Where the layouts are saved: `$XDG_CONFIG_HOME/scifisim/hud`, or
+/// `~/.config/scifisim/hud`, or, without either, `hud` in the working
+/// directory.
+pub fn layout_dir() -> PathBuf {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    match config {
+        Some(config) => config.join("scifisim").join("hud"),
+        None => PathBuf::from("hud"),
+    }
+}
+
+/// The layouts, and which is in use.
+#[derive(Resource)]
+pub struct Hud {
+    pub profile: HudProfile,
+    /// Whether the profile was picked with `hud profile`, rather than
+    /// following the ship.
+    pub pinned: bool,
+    pub layouts: BTreeMap<HudProfile, HudLayout>,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Hud {
+            profile: HudProfile::Orbit,
+            pinned: false,
+            layouts: HudProfile::ALL.into_iter().map(|p| (p, p.load())).collect(),
+        }
+    }
+}
+
+impl Hud {
+    pub fn layout(&self) -> Option<&HudLayout> {
+        self.layouts.get(&self.profile)
+    }
+
+    fn layout_mut(&mut self) -> &mut HudLayout {
+        self.layouts.entry(self.profile).or_default()
+    }
+
+    fn placement_mut(&mut self, panel: &str) -> Result<&mut Placement, String> {
+        self.layout_mut()
+            .0
+            .get_mut(panel)
+            .ok_or_else(|| format!("No such panel as {:?}, try hud", panel))
+    }
+}
+
+#[derive(Default)]
+pub struct HudLayoutPlugin;
+
+impl Plugin for HudLayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Hud>();
+        app.add_systems(Update, (pick_profile, place_panels).chain());
+        app.add_console_command(
+            "hud",
+            "hud [profile|move|show|hide|save|reset ...]   the HUD layout, see sim_ui::layout",
+            hud_command,
+        );
+    }
+}
+
+/// Follow what the ship is doing: docking when it is near its target (or
+/// docked), launching when it is on the ground or climbing low over it,
+/// landing when it is coming down, and otherwise in orbit.
+#[allow(clippy::type_complexity)]
+fn pick_profile(
+    mut hud: ResMut<Hud>,
+    proximity: Res<Proximity>,
+    ship: Query<(&OrbitalBody, Has<Landed>, Option<&DockingPort>), With<PlayerShip>>,
+    bodies: Query<(&OrbitalBody, &SizedBody), Without<PlayerShip>>,
+) {
+    if hud.pinned {
+        return;
+    }
+    let Ok((orbital, landed, port)) = ship.single() else {
+        return;
+    };
+    let docked = port.is_some_and(|port| port.docked.is_some());
+    let profile = if docked || proximity.0.is_some() {
+        HudProfile::Docking
+    } else if landed {
+        HudProfile::Launch
+    } else {
+        let low = bodies
+            .iter()
+            .map(|(body, size)| {
+                let pos = orbital.pos - body.pos;
+                (
+                    pos.norm() - size.radii.x,
+                    pos.dot(&(orbital.vel - body.vel)),
+                )
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .filter(|(altitude, _)| *altitude < LOW_ALTITUDE);
+        match low {
+            Some((_, climb)) if climb >= 0.0 => HudProfile::Launch,
+            Some(_) => HudProfile::Landing,
+            None => HudProfile::Orbit,
+        }
+    };
+    if hud.profile != profile {
+        hud.profile = profile;
+    }
+}
+
+/// Put every panel where the current layout says.  This runs every frame, so
+/// that panels anchored to the middle or the far side follow the window, and
+/// their own size.
+#[allow(clippy::type_complexity)]
+fn place_panels(
+    hud: Res<Hud>,
+    detached: Res<DetachedPanels>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut nodes: Query<(&HudPanel, &mut Node, &ComputedNode, &mut Visibility)>,
+    mut cameras: Query<(&HudPanel, &mut Camera)>,
+) {
+    let Ok(window) = window.single() else {
+        return;
+    };
+    let Some(layout) = hud.layout() else {
+        return;
+    };
+
+    for (panel, mut node, computed, mut visibility) in nodes.iter_mut() {
+        if detached.contains(panel.0) {
+            // In the top left of its own window.
+            node.position_type = PositionType::Absolute;
+            (node.left, node.right) = (Val::Px(10.0), Val::Auto);
+            (node.top, node.bottom) = (Val::Px(10.0), Val::Auto);
+            visibility.set_if_neq(Visibility::Inherited);
+            continue;
+        }
+        let Some(placement) = layout.0.get(panel.0) else {
+            continue;
+        };
+        let size = computed.size() * computed.inverse_scale_factor();
+        let (across, down) = placement.anchor.sides();
+        node.position_type = PositionType::Absolute;
+        (node.left, node.right) = across.vals(placement.x, size.x, window.width());
+        (node.top, node.bottom) = down.vals(placement.y, size.y, window.height());
+        let shown = if placement.visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        visibility.set_if_neq(shown);
+    }
+
+    // Viewports are in physical pixels.
+    let scale = window.scale_factor();
+    let physical = window.physical_size().as_vec2();
+    for (panel, mut camera) in cameras.iter_mut() {
+        if detached.contains(panel.0) {
+            continue;
+        }
+        let Some(placement) = layout.0.get(panel.0) else {
+            continue;
+        };
+        if !placement.visible {
+            camera.is_active = false;
+            continue;
+        }
+        let Some(viewport) = camera.viewport.as_mut() else {
+            continue;
+        };
+        let size = viewport.physical_size.as_vec2();
+        let offset = Placement {
+            x: placement.x * scale,
+            y: placement.y * scale,
+            ..placement.clone()
+        };
+        viewport.physical_position = offset
+            .origin(size, physical)
+            .clamp(Vec2::ZERO, (physical - size).max(Vec2::ZERO))
+            .as_uvec2();
+    }
+}
+
+fn hud_command(In(args): In<Vec<String>>, mut hud: ResMut<Hud>) -> ConsoleReply {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] => {
+            let mut lines = vec![format!(
+                "profile {}{}",
+                hud.profile.name(),
+                if hud.pinned { "" } else { " (auto)" }
+            )];
+            for (name, placement) in hud.layout().iter().flat_map(|layout| &layout.0) {
+                lines.push(format!(
+                    "{:<10} {:<12} {:6.0} {:6.0}{}",
+                    name,
+                    placement.anchor.name(),
+                    placement.x,
+                    placement.y,
+                    if placement.visible { "" } else { "  hidden" }
+                ));
+            }
+            Ok(lines.join("\n"))
+        }
+        ["profile", "auto"] => {
+            hud.pinned = false;
+            Ok("ok".to_string())
+        }
+        ["profile", name] => {
+            hud.profile = HudProfile::parse(name)?;
+            hud.pinned = true;
+            Ok("ok".to_string())
+        }
+        ["move", panel, anchor, x, y] => {
+            let anchor = Anchor::parse(anchor)?;
+            let (x, y) = (parse_arg(x)? as f32, parse_arg(y)? as f32);
+            let placement = hud.placement_mut(panel)?;
+            placement.anchor = anchor;
+            placement.x = x;
+            placement.y = y;
+            Ok("ok".to_string())
+        }
+        [show @ ("show" | "hide"), panel] => {
+            hud.placement_mut(panel)?.visible = *show == "show";
+            Ok("ok".to_string())
+        }
+        ["save"] => {
+            let path = hud.profile.path();
+            let text = ron::ser::to_string_pretty(hud.layout_mut(), Default::default())
+                .map_err(|e| e.to_string())?;
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            std::fs::write(&path, text).map_err(|e| e.to_string())?;
+            Ok(format!("Saved {}", path.display()))
+        }
+        ["reset"] => {
+            let profile = hud.profile;
+            *hud.layout_mut() = profile.defaults();
+            Ok("ok".to_string())
+        }
+        _ => Err("hud [profile <name>|auto] [move <panel> <anchor> <x> <y>] \
+                  [show|hide <panel>] [save] [reset]"
+            .to_string()),
+    }
+}
@@ -0,0 +1,1070 @@
+//! The basic UI.
+//!
+//! At this level, we display some information about the scene.  This sets up
+//! its own 2d camera to overlay this information on any other camera.
+
+// Recommended alias.
+extern crate nalgebra as na;
+
+use bevy::{
+    camera::{Viewport, visibility::RenderLayers},
+    color::palettes::css::GOLD,
+    diagnostic::{DiagnosticPath, DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    pbr::wireframe::WireframeConfig,
+    prelude::*,
+    scene::SceneInstanceReady,
+};
+use sim_astro::{
+    EarthMarker, SolarState,
+    contact::{Clamped, Landed},
+    frames::Axes,
+    loading::SimPhase,
+};
+use sim_core::{
+    AttitudeState, MassiveBody, OrbitalBody, clock::SimClock, orbit::OrbitFrame, watchdog::Frozen,
+};
+use sim_game::{
+    conservation::ConservationPlugin,
+    debris::Conjunctions,
+    remote::RemoteControl,
+    ship::{
+        ControlLayer, MassProperties, PlayerShip, RcsMode, SasTarget,
+        aero::Aero,
+        engine::{FuelTank, MainEngine},
+        maneuver::ManeuverNode,
+        nav::{NavSource, Navigation},
+        orbit_determination::OrbitDetermination,
+        power::Power,
+        predict::Prediction,
+        propulsion::{G0, PendingJump, Propulsion, PropulsionLedger},
+        radiation::Dosimeter,
+        rcs::{RcsRealism, RcsTank, RcsThrusters},
+        registry::CraftId,
+        staging::Staging,
+        sunlight::Sunlight,
+        torch::TorchPlan,
+    },
+    stats::SimStatsPlugin,
+};
+use sim_render::{
+    interpolate::{Interpolation, LastStep},
+    origin::VIEW_DISTANCE,
+};
+use std::io::Write;
+
+// use bevy::pbr::wireframe::Wireframe;
+
+mod alarm;
+mod cinematic;
+mod console;
+mod countdown;
+mod engine;
+mod flight;
+mod ground_panel;
+mod inspector;
+pub mod layout;
+mod loading;
+mod maneuver;
+mod map;
+mod power;
+mod proximity;
+mod rcs;
+mod sas;
+mod sky_panel;
+mod skylight;
+mod sunlight;
+mod vectors;
+mod views;
+pub mod windows;
+
+use engine::engine_line;
+use layout::HudPanel;
+pub use maneuver::ManeuverViewPlugin;
+use power::power_line;
+use rcs::{rates_line, rcs_line};
+use sunlight::{SHIP_ILLUMINANCE, ShipLight, SunTimes, sunlight_line};
+
+pub const UI_LAYER: RenderLayers = RenderLayers::layer(8);
+pub const BALL_LAYER: RenderLayers = RenderLayers::layer(7);
+
+/// How close, in km, a fragment of debris, or a catalogued object, has to be
+/// coming for the HUD to warn of it.
+const DEBRIS_WARNING: f64 = 5.0;
+
+#[derive(Component)]
+pub struct FpsText;
+
+#[derive(Component)]
+pub struct InfoText;
+
+#[derive(Component)]
+pub struct StatsText;
+
+#[derive(Default)]
+pub struct UIPlugin;
+
+#[derive(Component)]
+pub struct BallMarker;
+
+/// The navball's heading, pitch and roll readout.
+#[derive(Component)]
+pub struct AttitudeText;
+
+/// The navball markers for the orbit, relative to the earth, as the SAS
+/// modes of the same names point.
+#[derive(Clone, Copy, Component, Debug)]
+pub enum OrbitMarker {
+    Prograde,
+    Retrograde,
+    Normal,
+    AntiNormal,
+    RadialOut,
+    RadialIn,
+}
+
+/// The navball marker for the maneuver node burn direction.
+#[derive(Component)]
+pub struct NodeMarker;
+
+/// The navball markers for the SAS target: the way to it, and away, and the
+/// ship's velocity relative to it, and against.
+#[derive(Clone, Copy, Component, Debug)]
+pub enum TargetMarker {
+    Target,
+    AntiTarget,
+    RelativePrograde,
+    RelativeRetrograde,
+}
+
+#[derive(Component)]
+pub struct MainCameraMarker;
+
+impl Plugin for UIPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            alarm::AlarmViewPlugin,
+            cinematic::CinematicPlugin,
+            console::ConsoleOverlayPlugin,
+            countdown::CountdownViewPlugin,
+            flight::FlightHudPlugin,
+            inspector::InspectorPlugin,
+            map::MapPlugin,
+            ground_panel::GroundPanelPlugin,
+            proximity::ProximityViewPlugin,
+            sas::SasViewPlugin,
+            sky_panel::SkyPanelPlugin,
+            skylight::SkylightPlugin,
+            sunlight::SunlightViewPlugin,
+            layout::HudLayoutPlugin,
+            windows::PanelWindowsPlugin,
+        ));
+        // Bevy takes no more than 15 plugins at once.
+        app.add_plugins((
+            loading::LoadingScreenPlugin,
+            vectors::DebugVectorsPlugin,
+            views::CameraModePlugin,
+        ));
+        app.add_systems(Startup, setup_ui);
+        // There is no ship to show until the solar system is loaded.
+        app.add_systems(
+            Update,
+            (
+                update_ui,
+                update_orbit_markers,
+                update_node_marker,
+                update_target_markers,
+                update_stats,
+            )
+                .run_if(in_state(SimPhase::Running)),
+        );
+    }
+}
+
+fn setup_ui(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    // 2D camera for UI.
+    commands.spawn((
+        Camera2d::default(),
+        Camera {
+            order: 8,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("UI Camera"),
+    ));
+
+    // FPS and simulation statistics.
+    commands
+        .spawn((
+            Text::new("FPS: "),
+            TextFont {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 24.0,
+                ..default()
+            },
+            Node {
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            UI_LAYER,
+            Name::new("FPS Text"),
+            HudPanel("fps"),
+        ))
+        .with_child((
+            TextSpan::new("50"),
+            TextFont {
+                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                font_size: 24.0,
+                ..default()
+            },
+            TextColor(GOLD.into()),
+            FpsText,
+        ))
+        .with_child((
+            TextSpan::default(),
+            TextFont {
+                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 16.0,
+                ..default()
+            },
+            StatsText,
+        ));
+
+    // Informative text.
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 24.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("Info Text"),
+        HudPanel("info"),
+        InfoText,
+    ));
+
+    // The ball gets its own 3d camera.
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            order: 7,
+            clear_color: ClearColorConfig::None,
+            viewport: Some(Viewport {
+                physical_size: UVec2::new(200, 200),
+                ..default()
+            }),
+            ..default()
+        },
+        BALL_LAYER,
+        Name::new("Ball Camera"),
+        HudPanel("navball"),
+        Transform::from_xyz(0.0, -250.0, 0.0).looking_at(Vec3::ZERO, Vec3::Z),
+        Projection::Orthographic(OrthographicProjection::default_3d()),
+    ));
+
+    // The ball's heading, pitch and roll, in numbers.
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("Attitude Text"),
+        HudPanel("attitude"),
+        AttitudeText,
+    ));
+
+    // Throw in a sphere to see if I can render it.
+    let ball_mesh = Sphere {
+        radius: 100.0,
+        ..default()
+    }
+    .mesh()
+    .uv(24, 24);
+
+    let ball_tex = asset_server.load("tex/navball_surface_2048x1024.png");
+
+    let ball_material = materials.add(StandardMaterial {
+        // base_color: GREEN.into(),
+        // base_color: Color::linear_rgb(1.0, 0.4, 0.2),
+        base_color_texture: Some(ball_tex),
+        // metallic: 1.0,
+        perceptual_roughness: 0.85,
+        reflectance: 0.02,
+        // unlit: true
+        // cull_mode: None,
+        ..default()
+    });
+
+    let ball = meshes.add(ball_mesh);
+    commands.spawn((
+        Mesh3d(ball),
+        BALL_LAYER,
+        Visibility::Hidden,
+        MeshMaterial3d(ball_material),
+        Transform::from_xyz(0.0, 0.0, 0.0),
+        // Visibility::Hidden,
+        // Wireframe,
+        // BallMarker,
+        Name::new("Ball"),
+    ));
+
+    // Instead of a ball, we can render some arrows to make the scene more obvious.
+    commands
+        .spawn((
+            SceneRoot(asset_server.load("models/arrows.glb#Scene0")),
+            BallMarker,
+            BALL_LAYER,
+            Transform::from_xyz(0.0, 0.0, 0.0).with_scale(Vec3::splat(100.0)),
+        ))
+        .observe(tag_scene_layers);
+
+    commands.insert_resource(WireframeConfig {
+        global: false,
+        ..default()
+    });
+
+    commands.init_resource::<RcsMode>();
+
+    let prograde_mesh: Handle<Mesh> =
+        asset_server.load("models/marker-prograde.glb#Mesh0/Primitive0");
+
+    for (kind, color, name) in [
+        (OrbitMarker::Prograde, [0.5, 1.0, 0.0], "Prograde Marker"),
+        (
+            OrbitMarker::Retrograde,
+            [0.25, 0.5, 0.0],
+            "Retrograde Marker",
+        ),
+        (OrbitMarker::Normal, [0.6, 0.3, 1.0], "Normal Marker"),
+        (
+            OrbitMarker::AntiNormal,
+            [0.3, 0.15, 0.5],
+            "Anti-normal Marker",
+        ),
+        (OrbitMarker::RadialOut, [0.2, 0.9, 1.0], "Radial Out Marker"),
+        (OrbitMarker::RadialIn, [0.1, 0.45, 0.5], "Radial In Marker"),
+    ] {
+        commands.spawn((
+            Mesh3d(prograde_mesh.clone()),
+            BALL_LAYER,
+            Transform::from_xyz(0.0, -100.0, 0.0).with_scale(Vec3::splat(100.0)),
+            MeshMaterial3d(marker_material(&mut materials, color)),
+            kind,
+            Visibility::Hidden,
+            Name::new(name),
+        ));
+    }
+
+    commands.spawn((
+        Mesh3d(prograde_mesh.clone()),
+        BALL_LAYER,
+        Transform::from_xyz(0.0, -100.0, 0.0).with_scale(Vec3::splat(100.0)),
+        MeshMaterial3d(marker_material(&mut materials, [0.2, 0.5, 1.0])),
+        NodeMarker,
+        Visibility::Hidden,
+        Name::new("Node Marker"),
+    ));
+
+    for (kind, color, name) in [
+        (TargetMarker::Target, [1.0, 0.2, 1.0], "Target Marker"),
+        (
+            TargetMarker::AntiTarget,
+            [0.5, 0.1, 0.5],
+            "Anti-target Marker",
+        ),
+        (
+            TargetMarker::RelativePrograde,
+            [1.0, 0.8, 0.0],
+            "Relative Prograde Marker",
+        ),
+        (
+            TargetMarker::RelativeRetrograde,
+            [0.5, 0.4, 0.0],
+            "Relative Retrograde Marker",
+        ),
+    ] {
+        commands.spawn((
+            Mesh3d(prograde_mesh.clone()),
+            BALL_LAYER,
+            Transform::from_xyz(0.0, -100.0, 0.0).with_scale(Vec3::splat(100.0)),
+            MeshMaterial3d(marker_material(&mut materials, color)),
+            kind,
+            Visibility::Hidden,
+            Name::new(name),
+        ));
+    }
+
+    let vignetter_image = asset_server.load("tex/vignette_512.png");
+    commands
+        .spawn((
+            Node {
+                left: px(0.0),
+                top: px(0.0),
+                width: percent(100.0),  // Twice the radius.
+                height: percent(100.0), // Twice the radius.
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                // position_type: PositionType::Absolute,
+                // left: percent(50.0) - px(100.0),
+                // top: percent(50.0) - px(100.0),
+                ..Default::default()
+            },
+            Visibility::Hidden,
+        ))
+        .with_child((
+            Node {
+                width: px(200.0),  // Twice the radius.
+                height: px(200.0), // Twice the radius.
+                ..Default::default()
+            },
+            ImageNode {
+                image: vignetter_image,
+                ..Default::default()
+            },
+        ));
+
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            illuminance: 10_000.0,
+            ..default()
+        },
+        Transform::default().looking_to(Vec3::new(0.0, 1.0, 0.0), Vec3::X),
+        BALL_LAYER,
+        Name::new("Ball Light"),
+    ));
+
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 300.0,
+        ..default()
+    });
+
+    // The main 3d scene.
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            order: 0,
+            ..default()
+        },
+        Name::new("Main 3D Camera"),
+        Transform::from_xyz(0.0, -2.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+        Projection::Perspective(PerspectiveProjection {
+            fov: std::f32::consts::FRAC_PI_3,
+            near: 1.0,
+            far: VIEW_DISTANCE,
+            ..default()
+        }),
+        MainCameraMarker,
+    ));
+
+    // And some light for the ship, which the sun moves.
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            illuminance: SHIP_ILLUMINANCE,
+            ..default()
+        },
+        Transform::default().looking_to(Vec3::new(0.0, 2.0, 10.5).normalize(), Vec3::Z),
+        Name::new("Main Light"),
+        ShipLight,
+    ));
+}
+
+/// Put scenes from the UI into our layer.
+fn tag_scene_layers(
+    trigger: On<SceneInstanceReady>,
+    mut commands: Commands,
+    children: Query<&Children>,
+    transforms: Query<&Transform, Without<RenderLayers>>,
+    query: Query<(Entity, &RenderLayers)>,
+) {
+    let Ok((parent, render_layers)) = query.get(trigger.entity) else {
+        return;
+    };
+    children.iter_descendants(parent).for_each(|entity| {
+        if transforms.contains(entity) {
+            commands.entity(entity).insert(render_layers.clone());
+        }
+    });
+}
+
+// fn tag_scene_layers(
+//     mut commands: Commands,
+//     scenes: Query<(Entity, &SceneRoot), Without<RenderLayers>>,
+// ) {
+//     for (ent, _scene) in scenes.iter() {
+//         commands.entity(ent).insert(BALL_LAYER);
+//     }
+// }
+
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+fn update_ui(
+    mut text: Query<&mut Text, (With<InfoText>, Without<AttitudeText>)>,
+    time: Res<Time<Virtual>>,
+    fixed: Res<Time<Fixed>>,
+    ship: Query<
+        (
+            (&Name, Option<&CraftId>),
+            &OrbitalBody,
+            &AttitudeState,
+            (&RcsThrusters, Option<&RcsTank>),
+            Option<&Landed>,
+            Option<&Aero>,
+            Option<(&Propulsion, &PropulsionLedger, Option<&PendingJump>)>,
+            Option<&TorchPlan>,
+            Option<&Dosimeter>,
+            Option<&Sunlight>,
+            Option<&Power>,
+            Option<(
+                &MainEngine,
+                Option<&FuelTank>,
+                Option<&Staging>,
+                &MassProperties,
+            )>,
+            (Option<&Navigation>, Option<&OrbitDetermination>),
+            Option<&LastStep>,
+            Has<Clamped>,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<(&OrbitalBody, &AttitudeState), With<EarthMarker>>,
+    mut ball: Query<&mut Transform, With<BallMarker>>,
+    mut attitude_text: Query<&mut Text, (With<AttitudeText>, Without<InfoText>)>,
+    rcs: Res<RcsMode>,
+    realism: Res<RcsRealism>,
+    frozen: Query<(&Name, &Frozen)>,
+    sas_target: Res<SasTarget>,
+    targets: Query<(&Name, &OrbitalBody), Without<PlayerShip>>,
+    solar: Res<SolarState>,
+    sun_times: Res<SunTimes>,
+    nav_source: Res<NavSource>,
+    (remote, debris, interpolation, clock, layer, prediction): (
+        Res<RemoteControl>,
+        Res<Conjunctions>,
+        Res<Interpolation>,
+        Res<SimClock>,
+        Res<ControlLayer>,
+        Query<&Prediction, With<PlayerShip>>,
+    ),
+) {
+    let seconds = time.elapsed_secs_f64();
+    let (
+        (name, id),
+        truth,
+        truth_attitude,
+        (ship_rcs, rcs_tank),
+        landed,
+        aero,
+        drive,
+        torch,
+        dosimeter,
+        sunlight,
+        power,
+        engine,
+        (nav, od),
+        last,
+        clamped,
+    ) = ship.single().unwrap();
+    // Everything below is shown as the craft believes it to be, if that's
+    // what it is flown by, and as it was a light time ago, if it is flown
+    // from the ground.
+    // The truth is as drawn, between the steps, so that the navball turns
+    // smoothly with the craft.
+    let heard = remote.heard(fixed.elapsed_secs_f64());
+    let drawn = interpolation.attitude(truth_attitude, last);
+    let (ref ship, ref ship_attitude) = match heard {
+        Some(heard) => (heard.orbital.clone(), heard.attitude.clone()),
+        None => nav_source.view(nav, truth, &drawn),
+    };
+    let rcs = heard.map_or(*rcs, |heard| heard.mode);
+    let (earth, earth_attitude) = earth.single().unwrap();
+    let mut ball = ball.single_mut().unwrap();
+
+    if let Ok(mut text) = text.single_mut() {
+        let mut message = Vec::new();
+        writeln!(message, "Time: {:.3} s", seconds).unwrap();
+        if clock.dropped > 0 {
+            writeln!(
+                message,
+                "SLOW MOTION: {} of {} steps a frame dropped (see steps)",
+                clock.dropped,
+                clock.dropped + clock.frame_steps
+            )
+            .unwrap();
+        }
+        let id = id.map_or(String::new(), |id| format!("{} ", id));
+        writeln!(message, "Craft: {}{} (V to switch)", id, name).unwrap();
+        if let Some(station) = &remote.station {
+            writeln!(
+                message,
+                "Remote from {}: {:.1} s light time",
+                station, remote.delay
+            )
+            .unwrap();
+        }
+        let now = solar.et + fixed.elapsed_secs_f64();
+        if let Some(c) = debris.next_within(now, DEBRIS_WARNING) {
+            writeln!(
+                message,
+                "CONJUNCTION: {} passes {:.2} km off at {:.1} km/s in {:.0} s",
+                c.object,
+                c.miss,
+                c.speed,
+                c.time - now
+            )
+            .unwrap();
+        }
+        for (name, frozen) in frozen.iter() {
+            writeln!(
+                message,
+                "PHYSICS FAULT: {} {}, frozen (F9 restores the last snapshot)",
+                name, frozen.reason
+            )
+            .unwrap();
+        }
+        writeln!(
+            message,
+            "ship pos: {:.3e}, {:.3e}, {:.3e}",
+            ship.pos.x - earth.pos.x,
+            ship.pos.y - earth.pos.y,
+            ship.pos.z - earth.pos.z
+        )
+        .unwrap();
+
+        // Calculate our view frame.  There is none with the velocity
+        // straight up or down, so the ball stays as it was.
+        if let Some((q_ball, _)) = navball_frames(ship, ship_attitude, earth) {
+            ball.rotation = navball_quat_to_bevy(&q_ball);
+        }
+        if let Ok(mut attitude_text) = attitude_text.single_mut() {
+            **attitude_text = attitude_readout(ship, ship_attitude, earth, earth_attitude);
+        }
+
+        if let Some((target_name, target)) = sas_target.0.and_then(|t| targets.get(t).ok()) {
+            let rel_pos = target.pos - ship.pos;
+            let rel_vel = target.vel - ship.vel;
+            let range = rel_pos.norm();
+            writeln!(
+                message,
+                "Target {}: {:.3} km, closing {:.2} m/s, relative {:.2} m/s",
+                target_name,
+                range,
+                -rel_pos.dot(&rel_vel) / range * 1000.0,
+                rel_vel.norm() * 1000.0
+            )
+            .unwrap();
+        }
+        if clamped {
+            writeln!(message, "On the pad, held by the clamps").unwrap();
+        } else if let Some(landed) = landed {
+            writeln!(
+                message,
+                "Landed, sliding at {:.2} m/s",
+                landed.slide_f.norm() * 1000.0
+            )
+            .unwrap();
+        }
+        if let Some(phasing) = aero.and_then(|a| a.phasing.as_ref()) {
+            writeln!(
+                message,
+                "Phasing: {:.1} km ahead, drifting {:.2} m/s, {} drag",
+                phasing.separation,
+                phasing.drift * 1000.0,
+                if phasing.high_drag { "high" } else { "low" }
+            )
+            .unwrap();
+        }
+        if let Some((drive, ledger, jump)) = drive {
+            writeln!(
+                message,
+                "{}: {:.1} m/s, {:.3e} J/kg, {} jumps",
+                drive.0.name(),
+                ledger.dv,
+                ledger.energy,
+                ledger.jumps
+            )
+            .unwrap();
+            if jump.is_some() {
+                writeln!(message, "Jump ready: Y to go, H to call it off").unwrap();
+            }
+        }
+        if let Some(torch) = torch.filter(|t| t.arrival() > fixed.elapsed_secs_f64()) {
+            let now = fixed.elapsed_secs_f64();
+            let flip = torch.burns.get(1).map_or(now, |b| b.start);
+            writeln!(
+                message,
+                "Torch to {} at {:.2} g: flip in {:.0} s, arrive in {:.0} s",
+                torch.target,
+                torch.accel / G0,
+                (flip - now).max(0.0),
+                torch.arrival() - now
+            )
+            .unwrap();
+        }
+        if let Some(dosimeter) = dosimeter {
+            writeln!(
+                message,
+                "Dose: {:.2} mGy, crew {:.2} mSv, at {:.3} mGy/h{}, {} upsets",
+                dosimeter.dose * 1.0e3,
+                dosimeter.crew * 1.0e3,
+                dosimeter.rate.total() * 3.6e6,
+                if dosimeter.rate.solar > 0.0 {
+                    " (solar particle event)"
+                } else {
+                    ""
+                },
+                dosimeter.upsets
+            )
+            .unwrap();
+        }
+        if let Some(sunlight) = sunlight {
+            writeln!(
+                message,
+                "{}",
+                sunlight_line(sunlight, &sun_times, &solar, fixed.elapsed_secs_f64())
+            )
+            .unwrap();
+        }
+        if let Some(power) = power {
+            writeln!(message, "{}", power_line(power)).unwrap();
+        }
+        if let Some((engine, tank, staging, mass)) = engine {
+            writeln!(message, "{}", engine_line(engine, tank, staging, mass)).unwrap();
+        }
+        if let Some(nav) = nav {
+            writeln!(
+                message,
+                "Nav: {:?} (F12), {}",
+                *nav_source,
+                nav.status(truth, truth_attitude)
+            )
+            .unwrap();
+        }
+        if let Ok(Prediction {
+            entry,
+            impact: Some(impact),
+            ..
+        }) = prediction.single()
+        {
+            if let Some(entry) = entry {
+                writeln!(message, "Entry: {}", entry).unwrap();
+            }
+            writeln!(message, "Impact: {}", impact).unwrap();
+        }
+        if let Some(od) = od {
+            writeln!(
+                message,
+                "OD: {}",
+                od.status(
+                    fixed.elapsed_secs_f64(),
+                    &(truth.pos - earth.pos),
+                    &(truth.vel - earth.vel)
+                )
+            )
+            .unwrap();
+        }
+        //  writeln!(message, "Up: {:?}", up).unwrap();
+        writeln!(
+            message,
+            "{}",
+            rcs_line(rcs, *layer, &realism, ship_rcs, rcs_tank)
+        )
+        .unwrap();
+        writeln!(message, "{}", rates_line(ship_attitude)).unwrap();
+
+        **text = String::from_utf8(message).unwrap();
+    }
+}
+
+/// Show the frame rate and the simulation statistics.
+fn update_stats(
+    diagnostics: Res<DiagnosticsStore>,
+    mut fps: Query<&mut TextSpan, (With<FpsText>, Without<StatsText>)>,
+    mut stats: Query<&mut TextSpan, (With<StatsText>, Without<FpsText>)>,
+) {
+    let smoothed = |path: &DiagnosticPath| diagnostics.get(path).and_then(|d| d.smoothed());
+
+    if let Ok(mut fps) = fps.single_mut()
+        && let Some(value) = smoothed(&FrameTimeDiagnosticsPlugin::FPS)
+    {
+        **fps = format!("{value:.2}");
+    }
+
+    let Ok(mut stats) = stats.single_mut() else {
+        return;
+    };
+    let value = |path: &DiagnosticPath| smoothed(path).unwrap_or(0.0);
+    let mut message = Vec::new();
+    writeln!(message).unwrap();
+    writeln!(
+        message,
+        "steps/frame: {:.1}",
+        value(&SimStatsPlugin::STEPS_PER_FRAME)
+    )
+    .unwrap();
+    writeln!(message, "step: {:.3} ms", value(&SimStatsPlugin::STEP_TIME)).unwrap();
+    writeln!(
+        message,
+        "bodies/craft: {:.0}",
+        value(&SimStatsPlugin::GRAVITY_BODIES)
+    )
+    .unwrap();
+    writeln!(
+        message,
+        "predict: {:.3} ms",
+        value(&SimStatsPlugin::PREDICT_TIME)
+    )
+    .unwrap();
+    writeln!(
+        message,
+        "crafts: {:.0} on rails, {:.0} numerical",
+        value(&SimStatsPlugin::CRAFTS_ON_RAILS),
+        value(&SimStatsPlugin::CRAFTS_NUMERICAL)
+    )
+    .unwrap();
+    write!(
+        message,
+        "drift: energy {:.2e}, momentum {:.2e}",
+        value(&ConservationPlugin::ENERGY_DRIFT),
+        value(&ConservationPlugin::MOMENTUM_DRIFT)
+    )
+    .unwrap();
+    **stats = String::from_utf8(message).unwrap();
+}
+
+/// The navball orientation (`q_ball`) and the local horizon frame
+/// (`nav_to_world`) for the ship.  The horizon frame has Z up, and Y along the
+/// horizontal part of the velocity, relative to the earth.  Returns None when
+/// there is no horizontal velocity to orient by.
+fn navball_frames(
+    ship: &OrbitalBody,
+    ship_attitude: &AttitudeState,
+    earth: &OrbitalBody,
+) -> Option<(na::UnitQuaternion<f64>, na::UnitQuaternion<f64>)> {
+    let nav_to_world = Axes::horizon(&(ship.pos - earth.pos), &(ship.vel - earth.vel))?.q_fw;
+
+    let body_to_world = ship_attitude.q_bw;
+    let q_ball = body_to_world * nav_to_world.conjugate();
+    Some((q_ball, nav_to_world))
+}
+
+/// The rotation for a navball marker that points along the world direction
+/// `dir_w`.
+fn navball_marker_rotation(
+    q_ball: &na::UnitQuaternion<f64>,
+    q_fw: &na::UnitQuaternion<f64>,
+    dir_w: &na::Vector3<f64>,
+) -> Quat {
+    let v_f = q_fw.inverse_transform_vector(&dir_w.normalize());
+    let v_ball = q_ball.conjugate().transform_vector(&v_f).normalize();
+    let q_marker = na::UnitQuaternion::rotation_between(&na::Vector3::z(), &v_ball)
+        .unwrap_or(na::UnitQuaternion::identity());
+    navball_quat_to_bevy(&(q_ball * q_marker))
+}
+
+/// Point a navball marker along the world direction `dir_w`, or hide it if
+/// there is none, or it is round the far side of the ball.  The marker sits
+/// out along its +Z, and the ball's camera looks along +Y.
+fn place_marker(
+    transform: &mut Transform,
+    visibility: &mut Visibility,
+    frames: Option<&(na::UnitQuaternion<f64>, na::UnitQuaternion<f64>)>,
+    dir_w: Option<na::Vector3<f64>>,
+) {
+    let rotation = frames
+        .zip(dir_w.filter(|d| d.norm_squared() > 0.0))
+        .map(|((q_ball, q_fw), dir_w)| navball_marker_rotation(q_ball, q_fw, &dir_w))
+        .filter(|rotation| (*rotation * Vec3::Z).y <= 0.0);
+    match rotation {
+        Some(rotation) => {
+            transform.rotation = rotation;
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+/// A navball marker's material, in its color.
+fn marker_material(
+    materials: &mut Assets<StandardMaterial>,
+    color: [f32; 3],
+) -> Handle<StandardMaterial> {
+    materials.add(StandardMaterial {
+        base_color: Color::srgb_from_array(color),
+        perceptual_roughness: 0.85,
+        reflectance: 0.02,
+        cull_mode: None,
+        ..default()
+    })
+}
+
+/// The heading, from north, pitch above the horizon, and roll, of the craft's
+/// nose (its +Z, the way the engine pushes) over the earth's surface.  Its top
+/// is taken to be its -X, which, on the pad, is west, so that a craft pitched
+/// over east to launch is upright.  Pointing straight up or down there is no
+/// heading, or roll, to give.
+fn attitude_readout(
+    ship: &OrbitalBody,
+    ship_attitude: &AttitudeState,
+    earth: &OrbitalBody,
+    earth_attitude: &AttitudeState,
+) -> String {
+    let up = (ship.pos - earth.pos).normalize();
+    let q_bw = ship_attitude.q_bw;
+    let nose = q_bw.transform_vector(&na::Vector3::z());
+    let pitch = nose.dot(&up).clamp(-1.0, 1.0).asin().to_degrees();
+    let pole = earth_attitude.q_bw.transform_vector(&na::Vector3::z());
+    let (Some(enu), Some(level)) = (Axes::horizon(&up, &pole), Axes::horizon(&up, &nose)) else {
+        return format!("HDG ---.-  PIT {:+5.1}  RLL ---.-", pitch);
+    };
+    let nose_enu = enu.q_fw.inverse_transform_vector(&nose);
+    // To the tenth shown, so that just west of north is 000.0, not 360.0
+    // (or -00.0).
+    let heading = ((nose_enu.x.atan2(nose_enu.y).to_degrees() * 10.0)
+        .round()
+        .rem_euclid(3600.0)
+        / 10.0)
+        .abs();
+    // The level frame's X is to the right of the nose, so the top of a craft
+    // with no roll is square to both.  The craft's own right is its -Y.
+    let right = level.q_fw.transform_vector(&na::Vector3::x());
+    let top = right.cross(&nose);
+    let roll = q_bw
+        .transform_vector(&na::Vector3::y())
+        .dot(&top)
+        .atan2(-q_bw.transform_vector(&na::Vector3::x()).dot(&top))
+        .to_degrees();
+    format!(
+        "HDG {:05.1}  PIT {:+5.1}  RLL {:+6.1}",
+        heading, pitch, roll
+    )
+}
+
+/// A navball rotation, in the ball's own scene.  The ball's textures are laid
+/// out for the basis change turned the other way from `sim_to_bevy`'s, so it
+/// keeps that one, rather than `sim_quat_to_bevy`.
+fn navball_quat_to_bevy(q: &na::UnitQuaternion<f64>) -> Quat {
+    let r =
+        na::UnitQuaternion::from_axis_angle(&na::Vector3::x_axis(), -std::f64::consts::FRAC_PI_2);
+    let q = r.conjugate() * q * r;
+    Quat::from_array([q.i as f32, q.j as f32, q.k as f32, q.w as f32])
+}
+
+/// Show the maneuver node's burn direction on the navball, when there is one.
+#[allow(clippy::type_complexity)]
+fn update_node_marker(
+    fixed: Res<Time<Fixed>>,
+    interpolation: Res<Interpolation>,
+    ship: Query<
+        (
+            &OrbitalBody,
+            &AttitudeState,
+            Option<&ManeuverNode>,
+            Option<&LastStep>,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<(&OrbitalBody, &MassiveBody), With<EarthMarker>>,
+    mut marker: Query<(&mut Transform, &mut Visibility), With<NodeMarker>>,
+) {
+    let (Ok((ship, ship_attitude, node, last)), Ok((earth, earth_mass))) =
+        (ship.single(), earth.single())
+    else {
+        return;
+    };
+    let ship_attitude = &interpolation.attitude(ship_attitude, last);
+    let Ok((mut transform, mut visibility)) = marker.single_mut() else {
+        return;
+    };
+
+    let dv_w = node.map(|node| {
+        node.dv_w(
+            &(ship.pos - earth.pos),
+            &(ship.vel - earth.vel),
+            earth_mass.gm,
+            fixed.elapsed_secs_f64(),
+        )
+    });
+    let frames = navball_frames(ship, ship_attitude, earth);
+    place_marker(&mut transform, &mut visibility, frames.as_ref(), dv_w);
+}
+
+/// Show the ways the orbit goes on the navball: along it, across it, and up
+/// and down.
+#[allow(clippy::type_complexity)]
+fn update_orbit_markers(
+    interpolation: Res<Interpolation>,
+    ship: Query<(&OrbitalBody, &AttitudeState, Option<&LastStep>), With<PlayerShip>>,
+    earth: Query<&OrbitalBody, (With<EarthMarker>, Without<PlayerShip>)>,
+    mut markers: Query<(&OrbitMarker, &mut Transform, &mut Visibility)>,
+) {
+    let (Ok((ship, attitude, last)), Ok(earth)) = (ship.single(), earth.single()) else {
+        return;
+    };
+    let frames = navball_frames(ship, &interpolation.attitude(attitude, last), earth);
+    let orbit = OrbitFrame::new(&(ship.pos - earth.pos), &(ship.vel - earth.vel));
+    for (kind, mut transform, mut visibility) in markers.iter_mut() {
+        let dir_w = match kind {
+            OrbitMarker::Prograde => orbit.prograde,
+            OrbitMarker::Retrograde => -orbit.prograde,
+            OrbitMarker::Normal => orbit.normal,
+            OrbitMarker::AntiNormal => -orbit.normal,
+            OrbitMarker::RadialOut => orbit.radial,
+            OrbitMarker::RadialIn => -orbit.radial,
+        };
+        place_marker(
+            &mut transform,
+            &mut visibility,
+            frames.as_ref(),
+            Some(dir_w),
+        );
+    }
+}
+
+/// Show where the SAS target is, and which way the ship moves relative to it,
+/// on the navball.
+#[allow(clippy::type_complexity)]
+fn update_target_markers(
+    sas_target: Res<SasTarget>,
+    interpolation: Res<Interpolation>,
+    ship: Query<(&OrbitalBody, &AttitudeState, Option<&LastStep>), With<PlayerShip>>,
+    earth: Query<&OrbitalBody, (With<EarthMarker>, Without<PlayerShip>)>,
+    targets: Query<&OrbitalBody, Without<PlayerShip>>,
+    mut markers: Query<(&TargetMarker, &mut Transform, &mut Visibility)>,
+) {
+    let frames = match (ship.single(), earth.single()) {
+        (Ok((ship, attitude, last)), Ok(earth)) => {
+            navball_frames(ship, &interpolation.attitude(attitude, last), earth)
+                .zip(sas_target.0.and_then(|t| targets.get(t).ok()))
+                .map(|(frames, target)| (frames, target.pos - ship.pos, ship.vel - target.vel))
+        }
+        _ => None,
+    };
+    for (kind, mut transform, mut visibility) in markers.iter_mut() {
+        let dir_w = frames.as_ref().map(|(_, rel_pos, rel_vel)| match kind {
+            TargetMarker::Target => *rel_pos,
+            TargetMarker::AntiTarget => -rel_pos,
+            TargetMarker::RelativePrograde => *rel_vel,
+            TargetMarker::RelativeRetrograde => -rel_vel,
+        });
+        let ball = frames.as_ref().map(|(frames, ..)| frames);
+        place_marker(&mut transform, &mut visibility, ball, dir_w);
+    }
+}
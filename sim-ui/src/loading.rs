@@ -0,0 +1,69 @@
+//! The loading screen, while the solar system is taken from the kernels (see
+//! `sim_astro::loading`): what it is doing, and the bodies read so far.
+
+use bevy::prelude::*;
+use sim_astro::loading::{LoadProgress, SimPhase};
+
+use crate::UI_LAYER;
+
+/// The most bodies listed at once; the latest are shown.
+const LISTED: usize = 30;
+
+#[derive(Component)]
+struct LoadingText;
+
+#[derive(Default)]
+pub struct LoadingScreenPlugin;
+
+impl Plugin for LoadingScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(SimPhase::Loading), setup_loading_screen);
+        app.add_systems(
+            Update,
+            update_loading_screen.run_if(in_state(SimPhase::Loading)),
+        );
+    }
+}
+
+fn setup_loading_screen(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text::new("Loading"),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 18.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            padding: UiRect::all(Val::Px(40.0)),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK),
+        UI_LAYER,
+        Name::new("Loading Screen"),
+        DespawnOnExit(SimPhase::Loading),
+        LoadingText,
+    ));
+}
+
+fn update_loading_screen(
+    progress: Res<LoadProgress>,
+    mut text: Query<&mut Text, With<LoadingText>>,
+) {
+    if !progress.is_changed() {
+        return;
+    }
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    let mut lines = vec![match &progress.error {
+        Some(e) => format!("Can't load the solar system: {}", e),
+        None => format!("{}...", progress.stage),
+    }];
+    lines.push(format!("{} bodies", progress.bodies.len()));
+    let skip = progress.bodies.len().saturating_sub(LISTED);
+    lines.extend(progress.bodies.iter().skip(skip).cloned());
+    text.0 = lines.join("\n");
+}
@@ -0,0 +1,208 @@
+//! The maneuver node readout, the transfer planner, the ascent and insertion
+//! guidance, the lunar transfer, and the autopilot's program.
+
+use bevy::prelude::*;
+use sim_game::ship::{
+    MassProperties, PlayerShip, SasTarget,
+    ascent::Ascent,
+    autopilot::{Autopilot, Program},
+    engine::{FuelTank, MainEngine},
+    insertion::Insertion,
+    lunar::LunarTransfer,
+    maneuver::ManeuverNode,
+    staging::{Staging, budget},
+    targeting::TransferPlanner,
+};
+use std::io::Write;
+
+use crate::{UI_LAYER, layout::HudPanel};
+
+#[derive(Component)]
+struct NodeText;
+
+#[derive(Default)]
+pub struct ManeuverViewPlugin;
+
+impl Plugin for ManeuverViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_node_text);
+        app.add_systems(Update, update_node_text);
+    }
+}
+
+fn setup_node_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 18.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("Node Text"),
+        HudPanel("node"),
+        NodeText,
+    ));
+}
+
+#[allow(clippy::type_complexity)]
+fn update_node_text(
+    fixed: Res<Time<Fixed>>,
+    sas_target: Res<SasTarget>,
+    planner: Res<TransferPlanner>,
+    names: Query<&Name>,
+    mut text: Query<&mut Text, With<NodeText>>,
+    ship: Query<
+        (
+            Option<&ManeuverNode>,
+            &MainEngine,
+            Option<&FuelTank>,
+            Option<&Staging>,
+            &MassProperties,
+            Option<&Autopilot>,
+            Option<&Ascent>,
+            Option<&Insertion>,
+            Option<&LunarTransfer>,
+        ),
+        With<PlayerShip>,
+    >,
+) {
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    let Ok((node, engine, tank, staging, mass, autopilot, ascent, insertion, lunar)) =
+        ship.single()
+    else {
+        text.clear();
+        return;
+    };
+
+    let now = fixed.elapsed_secs_f64();
+    let mut message = Vec::new();
+    if let Some(name) = sas_target.0.and_then(|target| names.get(target).ok()) {
+        writeln!(message, "Target: {}", name).unwrap();
+        writeln!(
+            message,
+            " transfer: T{:+.0} s, {:.0} s",
+            now - planner.departure,
+            planner.duration
+        )
+        .unwrap();
+        match &planner.transfer {
+            Some(transfer) => writeln!(
+                message,
+                " dv: {:.1} m/s leaving, {:.1} m/s arriving",
+                transfer.departure_dv.norm(),
+                transfer.arrival_dv.norm()
+            )
+            .unwrap(),
+            None => writeln!(message, " no transfer").unwrap(),
+        }
+    }
+    if let Some(ascent) = ascent {
+        writeln!(
+            message,
+            "Ascent: {}, apoapsis {:.1} of {:.0} km",
+            ascent.phase, ascent.predicted, ascent.apoapsis
+        )
+        .unwrap();
+    }
+    if let Some(insertion) = insertion {
+        writeln!(
+            message,
+            "Insertion: {:.0} by {:.0} km, cutoff in {:.0} s",
+            insertion.periapsis,
+            insertion.apoapsis,
+            insertion.guidance.t_go.unwrap_or(0.0)
+        )
+        .unwrap();
+    }
+    if let Some(lunar) = lunar {
+        writeln!(
+            message,
+            "Lunar transfer: {}, arriving T{:+.0} s",
+            lunar.phase,
+            now - lunar.arrival
+        )
+        .unwrap();
+        if let Some(perigee) = lunar.perigee {
+            writeln!(
+                message,
+                " free return, {:.0} km past the Moon, back to a {:.0} km perigee",
+                lunar.altitude, perigee
+            )
+            .unwrap();
+        }
+        for planned in &lunar.burns {
+            writeln!(
+                message,
+                " {} T{:+.0} s, {:.1} m/s",
+                planned.burn,
+                now - planned.time,
+                planned.dv
+            )
+            .unwrap();
+        }
+    }
+    if let Some(autopilot) = autopilot {
+        if let Some(program) = &autopilot.current {
+            writeln!(message, "Program: {}", program).unwrap();
+        }
+        if let Some(error) = &autopilot.error {
+            writeln!(message, "Autopilot: {}", error).unwrap();
+        }
+        if let (Some(cost), Some(Program::PlaneTransfer(..) | Program::CircularizeInto(_))) =
+            (&autopilot.plane_cost, &autopilot.current)
+        {
+            writeln!(
+                message,
+                " turn leaving {:.1}, arriving {:.1}, split {:.1} m/s ({:.2} deg leaving)",
+                cost.departure, cost.arrival, cost.split, cost.split_angle
+            )
+            .unwrap();
+        }
+    }
+    let Some(node) = node else {
+        **text = String::from_utf8(message).unwrap();
+        return;
+    };
+
+    let dv = match node.remaining_w {
+        Some(remaining) => remaining.norm(),
+        None => node.dv.norm(),
+    };
+
+    writeln!(message, "Node: T{:+.1} s", now - node.time).unwrap();
+    writeln!(
+        message,
+        " dv: {:.1} pro, {:.1} nor, {:.1} rad m/s",
+        node.dv.x, node.dv.y, node.dv.z
+    )
+    .unwrap();
+    writeln!(
+        message,
+        " {:.1} m/s, burn {:.1} s{}",
+        dv,
+        engine.burn_time(mass.mass, dv),
+        if node.armed { " [armed]" } else { "" }
+    )
+    .unwrap();
+    // Warn of a burn the craft can't make, or can only by staging.
+    let budget = budget(engine, tank, staging, mass.mass);
+    let total = budget.iter().sum::<f64>();
+    if dv > total {
+        writeln!(message, " not enough dv: {:.1} m/s short", dv - total).unwrap();
+    } else if dv > budget[0] {
+        writeln!(
+            message,
+            " needs staging: {:.1} m/s in this stage",
+            budget[0]
+        )
+        .unwrap();
+    }
+    **text = String::from_utf8(message).unwrap();
+}
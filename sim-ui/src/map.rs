@@ -0,0 +1,1052 @@
+//! The map view.
+//!
+//! M switches between the ship view and the map.  The map is a separate camera
+//! and render layer, at true scale (one unit is one km), centered on the
+//! earth.  It shows the bodies, the ship's predicted path, and markers for
+//! periapsis, apoapsis, and the ascending and descending nodes (relative to
+//! the earth's equator).
+//!
+//! The earth is drawn with its day and night maps (see `sim_render::earth`),
+//! lit by the map's own sun, which shines along the true sun to earth line,
+//! so its day side faces the sun, and on its night side, the cities are lit.
+//!
+//! Right drag orbits the camera, the scroll wheel zooms, and clicking on a
+//! marker shows some information about it.  The orbit's estimated lifetime
+//! (see `ship::lifetime`) is shown along with that.
+//!
+//! The map can also be opened in a window of its own (see `windows`), where
+//! it is always showing, and the main window keeps the ship view.
+//!
+//! Behind the ship is its breadcrumb trail (see `ship::trail`), fading out
+//! with age.
+//!
+//! The conic the ship is on now is drawn faintly, as a ghost, under the path
+//! it will really coast along with the moon's and sun's pull and drag (see
+//! `ship::predict`), with how far apart the two end up.
+//!
+//! Bodies with an atmosphere are ringed at its effective thickness, and at the
+//! lowest safe periapsis (see `sim_astro::atmosphere`).  Between the two, drag
+//! brings a craft down within a few orbits, and below the first, it is
+//! entering.  A periapsis down in either gets a warning.
+//!
+//! The Lagrange points of the pairs of bodies chosen (see
+//! `sim_game::lagrange`) are marked, and the map can turn with one of the
+//! pairs, keeping the earth in the middle.  Then the two bodies and their
+//! points stand still, the trail is drawn as it went in the turning frame,
+//! and in place of the conics, the ship's path is coasted in the three-body
+//! model.
+//!
+//! The map can also be framed on any body's system (`map <body>`): the
+//! camera goes around the system's barycenter (see `sim_astro::hierarchy`),
+//! zoomed out to take in its moons, and zooms in no further than its largest
+//! body, and out to ten times as far.  `map sun` frames the whole solar
+//! system, and `map reset` goes back to the earth.
+//!
+//! Framed on the sun, the map is heliocentric: each planet's orbit about the
+//! sun is drawn, and the ship's, as they go with the sun alone pulling, which
+//! for a ship in cruise between the planets is near enough its path.  Zooming
+//! out past `SYSTEM_SCALE` from a planet goes over to the sun, and zooming in
+//! to it about the sun goes back to the system the ship is in.  Whenever the
+//! map is reframed, `map` too, the camera eases over from one middle and
+//! distance to the other.
+
+use bevy::{
+    camera::visibility::RenderLayers,
+    color::palettes::css::{
+        DEEP_SKY_BLUE, GRAY, LIGHT_SKY_BLUE, LIME, ORANGE, ORANGE_RED, RED, VIOLET, WHITE, YELLOW,
+    },
+    input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use na::Vector3;
+use sim_astro::{
+    EarthMarker, atmosphere::Atmosphere, eclipse::AU, geodesy::Geodetic, hierarchy::BodyTree,
+};
+use sim_core::{AttitudeState, MassiveBody, OrbitalBody, SizedBody, orbit::Conic};
+use sim_game::{
+    console::{ConsoleApp, ConsoleReply},
+    lagrange::Lagrange,
+    ship::{
+        PlayerShip,
+        lifetime::OrbitLifetime,
+        predict::Prediction,
+        trail::{Trail, TrailSettings},
+    },
+};
+use sim_render::{
+    earth::{EarthMaterial, body_sphere, earth_material},
+    sim_quat_to_bevy, sim_to_bevy,
+    trail::TRAIL_COLOR,
+};
+
+use crate::{
+    MainCameraMarker, UI_LAYER,
+    layout::HudPanel,
+    windows::{Detachable, DetachedPanels, target_window},
+};
+
+pub const MAP_LAYER: RenderLayers = RenderLayers::layer(6);
+
+/// Markers within this many pixels of a click are picked.
+const PICK_RADIUS: f32 = 15.0;
+
+/// Markers are drawn at this fraction of their distance from the camera, so
+/// they stay the same size on screen.
+const MARKER_SIZE: f32 = 0.01;
+
+/// Where the map goes between a planet's system and the sun's, as the
+/// camera's distance, km.
+pub const SYSTEM_SCALE: f32 = 1.0e7;
+
+/// How long the camera takes to go over to a new framing, s.
+const TRANSITION_TIME: f32 = 1.0;
+
+/// How many points each orbit about the sun is drawn with.
+const ORBIT_POINTS: usize = 256;
+
+/// How far out from the sun a hyperbolic orbit is drawn, km.
+const HELIO_RANGE: f64 = 1.0e10;
+
+/// The map sun's illuminance, and the dim light the night side gets.
+const MAP_SUN_ILLUMINANCE: f32 = 10_000.0;
+const MAP_AMBIENT: f32 = 20.0;
+
+/// Whether the map is showing.
+#[derive(Resource, Default)]
+pub struct MapMode(pub bool);
+
+/// The system the map is framed on.
+#[derive(Resource)]
+pub struct MapFocus {
+    /// The system's node in the `BodyTree`, or None for around the earth.
+    pub system: Option<usize>,
+    /// How near to, and far from, the middle the camera can zoom, km.
+    pub zoom: (f32, f32),
+}
+
+impl Default for MapFocus {
+    fn default() -> Self {
+        MapFocus {
+            system: None,
+            zoom: (7_000.0, SYSTEM_SCALE),
+        }
+    }
+}
+
+/// Gizmos drawn only in the map.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct MapGizmos;
+
+/// The map camera, orbiting the earth.
+#[derive(Component)]
+pub struct MapCamera {
+    /// Rotation about the bevy Y (sim Z) axis, radians.
+    pub yaw: f32,
+    /// Elevation above the bevy XZ (sim XY) plane, radians.
+    pub pitch: f32,
+    /// Distance from the middle of the system framed, in km.
+    pub distance: f32,
+    /// Where it is looking, and from how far, as shown, partway over to a
+    /// new framing.
+    pub center: Vec3,
+    pub shown: f32,
+    /// Where it was looking, and from how far, when the map was reframed,
+    /// and how far over it is from there, 0 to 1.
+    pub from: (Vec3, f32),
+    pub eased: f32,
+}
+
+impl MapCamera {
+    /// Go over to a new framing from where it is looking now.
+    pub fn reframe(&mut self) {
+        self.from = (self.center, self.shown);
+        self.eased = 0.0;
+    }
+}
+
+/// The map's stand-in for a body.
+#[derive(Component)]
+struct MapBody(Entity);
+
+/// The map's sun.
+#[derive(Component)]
+struct MapSun;
+
+#[derive(Component)]
+struct MapText;
+
+/// The things that can be clicked on in the map.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MapMarkerKind {
+    Ship,
+    Periapsis,
+    Apoapsis,
+    AscendingNode,
+    DescendingNode,
+    Body(Entity),
+    /// One of `Lagrange::points`.
+    Lagrange(usize),
+}
+
+/// A marker, in km relative to the earth, and what to say about it.
+#[derive(Clone, Debug)]
+pub struct MapMarker {
+    pub kind: MapMarkerKind,
+    pub pos: Vector3<f64>,
+    pub label: String,
+}
+
+/// The markers for this frame.
+#[derive(Resource, Default)]
+pub struct MapMarkers(pub Vec<MapMarker>);
+
+/// The marker last clicked on.
+#[derive(Resource, Default)]
+pub struct MapSelection(pub Option<MapMarkerKind>);
+
+/// A warning about the ship's periapsis, for this frame.
+#[derive(Resource, Default)]
+pub struct MapWarning(pub Option<String>);
+
+#[derive(Default)]
+pub struct MapPlugin;
+
+impl Plugin for MapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapMode>();
+        app.init_resource::<MapMarkers>();
+        app.init_resource::<MapSelection>();
+        app.init_resource::<MapWarning>();
+        app.init_resource::<MapFocus>();
+        app.init_gizmo_group::<MapGizmos>();
+        app.add_systems(Startup, setup_map);
+        app.add_systems(
+            Update,
+            (
+                map_keys,
+                spawn_map_bodies,
+                map_camera_controls,
+                update_map_bodies,
+                update_map_sun,
+                map_markers,
+                map_click,
+                draw_map,
+                update_map_text,
+            )
+                .chain(),
+        );
+        app.add_console_command(
+            "map",
+            "map [<body> | reset]   frame the map on a body's system, at its scale (sun: heliocentric)",
+            map_command,
+        );
+    }
+}
+
+fn setup_map(
+    mut commands: Commands,
+    mut config_store: ResMut<GizmoConfigStore>,
+    asset_server: Res<AssetServer>,
+) {
+    let (config, _) = config_store.config_mut::<MapGizmos>();
+    config.render_layers = MAP_LAYER;
+
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            order: 1,
+            is_active: false,
+            ..default()
+        },
+        Projection::Perspective(PerspectiveProjection {
+            fov: std::f32::consts::FRAC_PI_3,
+            near: 1.0,
+            far: 1.0e12,
+            ..default()
+        }),
+        Transform::default(),
+        MAP_LAYER,
+        Name::new("Map Camera"),
+        Detachable {
+            name: "map",
+            size: UVec2::new(1024, 768),
+        },
+        MapCamera {
+            yaw: 0.0,
+            pitch: 0.6,
+            distance: 40_000.0,
+            center: Vec3::ZERO,
+            shown: 40_000.0,
+            from: (Vec3::ZERO, 40_000.0),
+            eased: 1.0,
+        },
+        // Only a little, so that the night side is dark.
+        AmbientLight {
+            brightness: MAP_AMBIENT,
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: MAP_SUN_ILLUMINANCE,
+            ..default()
+        },
+        Transform::default(),
+        MAP_LAYER,
+        Name::new("Map Sun"),
+        MapSun,
+    ));
+
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 18.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("Map Text"),
+        HudPanel("map"),
+        MapText,
+    ));
+}
+
+/// M toggles the map.  In a window of its own, it is always showing, beside
+/// the ship view, and when put back, goes back to being toggled.
+fn map_keys(
+    kb: Res<ButtonInput<KeyCode>>,
+    detached: Res<DetachedPanels>,
+    mut was_detached: Local<bool>,
+    mut mode: ResMut<MapMode>,
+    mut main: Query<&mut Camera, (With<MainCameraMarker>, Without<MapCamera>)>,
+    mut map: Query<&mut Camera, (With<MapCamera>, Without<MainCameraMarker>)>,
+) {
+    let in_window = detached.contains("map");
+    let show = if in_window {
+        true
+    } else if *was_detached {
+        false
+    } else if kb.just_pressed(KeyCode::KeyM) {
+        !mode.0
+    } else {
+        return;
+    };
+    *was_detached = in_window;
+    mode.0 = show;
+    if let Ok(mut main) = main.single_mut() {
+        main.is_active = !show || in_window;
+    }
+    if let Ok(mut map) = map.single_mut() {
+        map.is_active = mode.0;
+    }
+}
+
+/// Give each body a sphere in the map.  The earth's has its maps on it, and
+/// is lit by the map's sun.
+fn spawn_map_bodies(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut earth_materials: ResMut<Assets<EarthMaterial>>,
+    bodies: Query<(Entity, Has<EarthMarker>), Added<SizedBody>>,
+) {
+    for (entity, is_earth) in bodies.iter() {
+        if is_earth {
+            commands.spawn((
+                Mesh3d(meshes.add(body_sphere())),
+                MeshMaterial3d(earth_materials.add(earth_material(&asset_server))),
+                Transform::default(),
+                MAP_LAYER,
+                MapBody(entity),
+            ));
+            continue;
+        }
+        commands.spawn((
+            Mesh3d(meshes.add(Sphere::new(1.0).mesh().ico(5).unwrap())),
+            MeshMaterial3d(materials.add(StandardMaterial {
+                base_color: Color::srgb(0.6, 0.6, 0.6),
+                unlit: true,
+                ..default()
+            })),
+            Transform::default(),
+            MAP_LAYER,
+            MapBody(entity),
+        ));
+    }
+}
+
+/// Right drag to orbit, and scroll to zoom, about the system the map is
+/// framed on.  Scrolling on past the furthest out a planet's system goes, or
+/// in past the nearest in the sun's, goes over to the other (see
+/// `SYSTEM_SCALE`).
+#[allow(clippy::too_many_arguments)]
+fn map_camera_controls(
+    mode: Res<MapMode>,
+    mut focus: ResMut<MapFocus>,
+    tree: Res<BodyTree>,
+    lagrange: Res<Lagrange>,
+    time: Res<Time<Real>>,
+    bodies: Query<(&OrbitalBody, &MassiveBody)>,
+    sizes: Query<&SizedBody>,
+    earth: Query<&OrbitalBody, With<EarthMarker>>,
+    ship: Query<&OrbitalBody, With<PlayerShip>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    motion: Res<AccumulatedMouseMotion>,
+    scroll: Res<AccumulatedMouseScroll>,
+    mut camera: Query<(&mut Transform, &mut MapCamera, &Camera)>,
+    windows: Query<&Window>,
+    primary: Query<Entity, With<PrimaryWindow>>,
+) {
+    if !mode.0 {
+        return;
+    }
+    let Ok((mut transform, mut camera, view)) = camera.single_mut() else {
+        return;
+    };
+    // Only with the mouse over the map, where it has a window of its own.
+    let window = target_window(view, primary.single().ok()).and_then(|w| windows.get(w).ok());
+    if window.is_some_and(|w| w.cursor_position().is_some()) {
+        if buttons.pressed(MouseButton::Right) {
+            camera.yaw -= motion.delta.x * 0.005;
+            camera.pitch = (camera.pitch + motion.delta.y * 0.005).clamp(-1.5, 1.5);
+        }
+        let notches = match scroll.unit {
+            MouseScrollUnit::Line => scroll.delta.y,
+            MouseScrollUnit::Pixel => scroll.delta.y / 32.0,
+        };
+        let heliocentric = focus.system == Some(tree.root);
+        let next = if notches < 0.0 && camera.distance >= focus.zoom.1 && !heliocentric {
+            Some(tree.root)
+        } else if notches > 0.0 && camera.distance <= focus.zoom.0 && heliocentric {
+            ship.single()
+                .ok()
+                .and_then(|ship| ship_system(&tree, &bodies, &ship.pos))
+        } else {
+            None
+        };
+        if let Some(system) = next {
+            match frame(system, &tree, &bodies, &sizes, &mut focus, &mut camera) {
+                Ok(_) => info!("Map on the {} system", tree.nodes[system].name),
+                Err(e) => warn!("{}", e),
+            }
+        }
+        camera.distance =
+            (camera.distance * 0.9f32.powf(notches)).clamp(focus.zoom.0, focus.zoom.1);
+    }
+
+    // The map is drawn relative to the earth, so the system's middle is
+    // where its barycenter is from there.
+    let target = focus
+        .system
+        .and_then(|system| tree.barycenter(system, &bodies))
+        .zip(earth.single().ok())
+        .map_or(Vec3::ZERO, |((center, _), earth)| {
+            let rel = center.pos - earth.pos;
+            sim_to_bevy(&match &lagrange.rotating {
+                Some(view) => view.turn(&rel, view.time),
+                None => rel,
+            })
+        });
+    // Going over smoothly from the last framing, the distance in proportion.
+    camera.eased = (camera.eased + time.delta_secs() / TRANSITION_TIME).min(1.0);
+    let s = camera.eased * camera.eased * (3.0 - 2.0 * camera.eased);
+    let (from, from_distance) = camera.from;
+    let center = from.lerp(target, s);
+    let distance = (from_distance.ln() + (camera.distance.ln() - from_distance.ln()) * s).exp();
+    camera.center = center;
+    camera.shown = distance;
+
+    let rotation = Quat::from_rotation_y(camera.yaw) * Quat::from_rotation_x(-camera.pitch);
+    *transform = Transform::from_translation(center + rotation * Vec3::Z * distance)
+        .looking_at(center, Vec3::Y);
+}
+
+/// The planet's system the ship is in, or failing that, the one it is
+/// nearest to, for the size of its sphere of influence about the sun.
+fn ship_system(
+    tree: &BodyTree,
+    bodies: &Query<(&OrbitalBody, &MassiveBody)>,
+    ship: &Vector3<f64>,
+) -> Option<usize> {
+    let (sun, sun_mass) = bodies.get(tree.nodes[tree.root].entity?).ok()?;
+    tree.nodes[tree.root]
+        .children
+        .iter()
+        .filter_map(|&system| {
+            let (center, gm) = tree.barycenter(system, bodies)?;
+            let soi = (center.pos - sun.pos).norm() * (gm / sun_mass.gm).powf(0.4);
+            Some((system, (ship - center.pos).norm() / soi))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(system, _)| system)
+}
+
+/// Frame the map on `system`, the camera going over from where it is, and
+/// say how far out the system goes from its barycenter.  The camera's
+/// distance is left as it was, within the new framing's.
+fn frame(
+    system: usize,
+    tree: &BodyTree,
+    bodies: &Query<(&OrbitalBody, &MassiveBody)>,
+    sizes: &Query<&SizedBody>,
+    focus: &mut MapFocus,
+    camera: &mut MapCamera,
+) -> Result<f64, String> {
+    let (center, _) = tree
+        .barycenter(system, bodies)
+        .ok_or_else(|| format!("The {} system has no mass", tree.nodes[system].name))?;
+    // Out to the far side of its furthest body, and in to its largest.
+    let (mut extent, mut largest) = (0.0f64, 0.0f64);
+    for entity in tree.system(system) {
+        let radius = sizes.get(entity).map_or(0.0, |size| size.radii.max());
+        if let Ok((orbital, _)) = bodies.get(entity) {
+            extent = extent.max((orbital.pos - center.pos).norm() + radius);
+        }
+        largest = largest.max(radius);
+    }
+    let mut nearest = (largest * 1.1).max(1.0) as f32;
+    let mut furthest = (extent * 10.0) as f32;
+    // The sun's framing takes over where a planet's leaves off.
+    if system == tree.root {
+        nearest = nearest.max(SYSTEM_SCALE);
+    } else {
+        furthest = furthest.max(SYSTEM_SCALE);
+    }
+    focus.system = Some(system);
+    focus.zoom = (nearest, furthest.max(nearest));
+    camera.reframe();
+    camera.distance = camera.distance.clamp(focus.zoom.0, focus.zoom.1);
+    Ok(extent)
+}
+
+/// Move the map bodies to where the bodies are, relative to the earth.
+fn update_map_bodies(
+    lagrange: Res<Lagrange>,
+    mut map_bodies: Query<(&MapBody, &mut Transform)>,
+    bodies: Query<(&OrbitalBody, &SizedBody, &AttitudeState)>,
+    earth: Query<&OrbitalBody, With<EarthMarker>>,
+) {
+    let Ok(earth) = earth.single() else {
+        return;
+    };
+    for (map_body, mut transform) in map_bodies.iter_mut() {
+        let Ok((orbital, size, attitude)) = bodies.get(map_body.0) else {
+            continue;
+        };
+        let rel = orbital.pos - earth.pos;
+        match &lagrange.rotating {
+            Some(view) => {
+                transform.translation = sim_to_bevy(&view.turn(&rel, view.time));
+                transform.rotation = sim_quat_to_bevy(&view.turn_attitude(&attitude.q_bw));
+            }
+            None => {
+                transform.translation = sim_to_bevy(&rel);
+                transform.rotation = sim_quat_to_bevy(&attitude.q_bw);
+            }
+        }
+        // Sim X, Y, Z are bevy X, -Z, Y.
+        transform.scale = Vec3::new(
+            size.radii.x as f32,
+            size.radii.z as f32,
+            size.radii.y as f32,
+        );
+    }
+}
+
+/// Point the map's sun, and the earth's material, along the sun to earth
+/// line.  The sun is the most massive body.
+fn update_map_sun(
+    lagrange: Res<Lagrange>,
+    bodies: Query<(&OrbitalBody, &MassiveBody)>,
+    earth: Query<&OrbitalBody, With<EarthMarker>>,
+    earth_body: Query<&MeshMaterial3d<EarthMaterial>>,
+    mut sun_light: Query<&mut Transform, With<MapSun>>,
+    mut materials: ResMut<Assets<EarthMaterial>>,
+) {
+    let Some((sun, _)) = bodies.iter().max_by(|a, b| a.1.gm.total_cmp(&b.1.gm)) else {
+        return;
+    };
+    let Ok(earth) = earth.single() else {
+        return;
+    };
+    let Some(to_sun) = (sun.pos - earth.pos).try_normalize(0.0) else {
+        return;
+    };
+    let to_sun = sim_to_bevy(&match &lagrange.rotating {
+        Some(view) => view.turn(&to_sun, view.time),
+        None => to_sun,
+    });
+    if let Ok(mut transform) = sun_light.single_mut() {
+        *transform = Transform::default().looking_to(-to_sun, Vec3::Y);
+    }
+    for material in earth_body.iter() {
+        if let Some(material) = materials.get_mut(material) {
+            material.extension.set_sun(to_sun);
+        }
+    }
+}
+
+/// Work out the markers for this frame.
+#[allow(clippy::type_complexity)]
+fn map_markers(
+    mode: Res<MapMode>,
+    lagrange: Res<Lagrange>,
+    mut markers: ResMut<MapMarkers>,
+    mut warning: ResMut<MapWarning>,
+    ship: Query<&OrbitalBody, With<PlayerShip>>,
+    earth: Query<
+        (
+            Entity,
+            &OrbitalBody,
+            &MassiveBody,
+            &SizedBody,
+            &AttitudeState,
+            Option<&Atmosphere>,
+        ),
+        With<EarthMarker>,
+    >,
+    bodies: Query<(Entity, &OrbitalBody, &Name), With<SizedBody>>,
+) {
+    markers.0.clear();
+    warning.0 = None;
+    if !mode.0 {
+        return;
+    }
+    let Ok(ship) = ship.single() else {
+        return;
+    };
+    let Ok((earth_entity, earth, earth_mass, earth_size, earth_attitude, atmosphere)) =
+        earth.single()
+    else {
+        return;
+    };
+
+    let surface = earth_size.radii.z;
+    let altitude = Geodetic::from_world(earth, earth_attitude, &earth_size.radii, &ship.pos).alt;
+    let pos = ship.pos - earth.pos;
+    let vel = ship.vel - earth.vel;
+    markers.0.push(MapMarker {
+        kind: MapMarkerKind::Ship,
+        pos,
+        label: format!("Ship: altitude {:.1} km, {:.3} km/s", altitude, vel.norm()),
+    });
+
+    let conic = Conic::new(&pos, &vel, earth_mass.gm);
+    let now = conic.true_anomaly(&pos);
+    let time_to = |nu: f64| match conic.time_between(now, nu) {
+        Some(dt) => format!(", in {:.0} s", dt),
+        None => String::new(),
+    };
+
+    let periapsis = conic.periapsis();
+    let periapsis_alt = periapsis.norm() - surface;
+    if let Some(atmosphere) = atmosphere {
+        if periapsis_alt < atmosphere.thickness() {
+            warning.0 = Some(format!(
+                "Periapsis {:.1} km is in the atmosphere, below {:.0} km: entry",
+                periapsis_alt,
+                atmosphere.thickness()
+            ));
+        } else if periapsis_alt < atmosphere.safe_periapsis() {
+            warning.0 = Some(format!(
+                "Periapsis {:.1} km is in significant drag, below {:.0} km",
+                periapsis_alt,
+                atmosphere.safe_periapsis()
+            ));
+        }
+    }
+    markers.0.push(MapMarker {
+        kind: MapMarkerKind::Periapsis,
+        pos: periapsis,
+        label: format!("Periapsis: {:.1} km{}", periapsis_alt, time_to(0.0)),
+    });
+    if let Some(apoapsis) = conic.apoapsis() {
+        markers.0.push(MapMarker {
+            kind: MapMarkerKind::Apoapsis,
+            pos: apoapsis,
+            label: format!(
+                "Apoapsis: {:.1} km{}",
+                apoapsis.norm() - surface,
+                time_to(std::f64::consts::PI)
+            ),
+        });
+    }
+
+    let pole = earth_attitude.q_bw.transform_vector(&Vector3::z());
+    if let Some((ascending, descending)) = conic.nodes(&pole) {
+        for (kind, name, nu) in [
+            (MapMarkerKind::AscendingNode, "Ascending node", ascending),
+            (MapMarkerKind::DescendingNode, "Descending node", descending),
+        ] {
+            if let Some(node) = conic.position_at(nu) {
+                markers.0.push(MapMarker {
+                    kind,
+                    pos: node,
+                    label: format!("{}: {:.1} km{}", name, node.norm() - surface, time_to(nu)),
+                });
+            }
+        }
+    }
+
+    for (entity, orbital, name) in bodies.iter() {
+        if entity == earth_entity {
+            continue;
+        }
+        let rel = orbital.pos - earth.pos;
+        markers.0.push(MapMarker {
+            kind: MapMarkerKind::Body(entity),
+            pos: rel,
+            label: format!("{}: {:.0} km", name, rel.norm()),
+        });
+    }
+
+    for (i, point) in lagrange.points.iter().enumerate() {
+        let rel = point.pos - earth.pos;
+        markers.0.push(MapMarker {
+            kind: MapMarkerKind::Lagrange(i),
+            pos: rel,
+            label: format!("{} {}: {:.0} km", point.pair, point.name, rel.norm()),
+        });
+    }
+
+    // Everything as it is now, along the turning axes, leaving out the
+    // conic's points, as the conics aren't drawn.
+    if let Some(view) = &lagrange.rotating {
+        markers.0.retain(|marker| {
+            matches!(
+                marker.kind,
+                MapMarkerKind::Ship | MapMarkerKind::Body(_) | MapMarkerKind::Lagrange(_)
+            )
+        });
+        for marker in markers.0.iter_mut() {
+            marker.pos = view.turn(&marker.pos, view.time);
+        }
+    }
+}
+
+/// Left click picks the nearest marker on screen.
+fn map_click(
+    mode: Res<MapMode>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    markers: Res<MapMarkers>,
+    mut selection: ResMut<MapSelection>,
+    windows: Query<&Window>,
+    primary: Query<Entity, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<MapCamera>>,
+) {
+    if !mode.0 || !buttons.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Ok((camera, camera_transform)) = camera.single() else {
+        return;
+    };
+    let Some(window) =
+        target_window(camera, primary.single().ok()).and_then(|w| windows.get(w).ok())
+    else {
+        return;
+    };
+    let Some(cursor) = window.cursor_position() else {
+        return;
+    };
+
+    selection.0 = markers
+        .0
+        .iter()
+        .filter_map(|marker| {
+            let screen = camera
+                .world_to_viewport(camera_transform, sim_to_bevy(&marker.pos))
+                .ok()?;
+            let distance = screen.distance(cursor);
+            (distance < PICK_RADIUS).then_some((marker.kind, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(kind, _)| kind);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_map(
+    mode: Res<MapMode>,
+    markers: Res<MapMarkers>,
+    selection: Res<MapSelection>,
+    warning: Res<MapWarning>,
+    mut gizmos: Gizmos<MapGizmos>,
+    ship: Query<(&OrbitalBody, &Prediction, Option<&Trail>), With<PlayerShip>>,
+    fixed: Res<Time<Fixed>>,
+    trail_settings: Res<TrailSettings>,
+    camera: Query<&Transform, With<MapCamera>>,
+    atmospheres: Query<(&OrbitalBody, &SizedBody, &Atmosphere)>,
+    earth: Query<&OrbitalBody, With<EarthMarker>>,
+    lagrange: Res<Lagrange>,
+    focus: Res<MapFocus>,
+    tree: Res<BodyTree>,
+    bodies: Query<(&OrbitalBody, &MassiveBody)>,
+) {
+    if !mode.0 {
+        return;
+    }
+    let Ok(camera) = camera.single() else {
+        return;
+    };
+    let now = fixed.elapsed_secs_f64();
+    // Where an offset from the earth at time `t` goes in the map.
+    let view = lagrange.rotating.as_ref();
+    let place = |rel: &Vector3<f64>, t: f64| sim_to_bevy(&view.map_or(*rel, |v| v.turn(rel, t)));
+
+    // The bands, as rings facing the camera, which is near enough the limb
+    // of the sphere.
+    if let Ok(earth) = earth.single() {
+        for (orbital, size, atmosphere) in atmospheres.iter() {
+            let center = place(&(orbital.pos - earth.pos), now);
+            let isometry = Isometry3d::new(center, camera.rotation);
+            let radius = size.radii.x;
+            let edge = if warning.0.is_some() {
+                RED
+            } else {
+                LIGHT_SKY_BLUE
+            };
+            gizmos.circle(
+                isometry,
+                (radius + atmosphere.thickness()) as f32,
+                edge.with_alpha(0.6),
+            );
+            gizmos.circle(
+                isometry,
+                (radius + atmosphere.safe_periapsis()) as f32,
+                LIME.with_alpha(0.4),
+            );
+        }
+    }
+
+    if let (Ok((orbital, _, Some(trail))), Ok(earth)) = (ship.single(), earth.single()) {
+        gizmos.linestrip_gradient(
+            trail
+                .faded(now, trail_settings.length)
+                .zip(&trail.points)
+                .map(|((fade, pos), (t, _))| {
+                    (
+                        place(pos, *t),
+                        Color::from(TRAIL_COLOR.with_alpha(fade as f32)),
+                    )
+                })
+                .chain([(
+                    place(&(orbital.pos - earth.pos), now),
+                    Color::from(TRAIL_COLOR),
+                )]),
+        );
+    }
+
+    if let (Some(view), Ok(earth)) = (view, earth.single()) {
+        // The conics don't mean much turning, so the path coasted in the
+        // three-body model is drawn instead.
+        gizmos.linestrip(view.path_from(&earth.pos).iter().map(sim_to_bevy), VIOLET);
+    } else if let Ok((_, prediction, _)) = ship.single() {
+        for (i, conic) in prediction.conics.iter().enumerate() {
+            let color = if i == 0 && !prediction.perturbed.is_empty() {
+                Color::from(WHITE.with_alpha(0.25))
+            } else if i == 0 {
+                Color::from(WHITE)
+            } else {
+                Color::srgb(0.2, 0.5, 1.0)
+            };
+            gizmos.linestrip(conic.iter().map(sim_to_bevy), color);
+        }
+        gizmos.linestrip(prediction.perturbed.iter().map(sim_to_bevy), ORANGE);
+    }
+
+    // Framed on the sun, each planet's orbit about it, and the ship's, as
+    // they go with the sun alone pulling.
+    let sun = tree.nodes.get(tree.root).and_then(|node| node.entity);
+    if let (Some(Ok((sun, sun_mass))), Ok(earth), None) = (
+        sun.filter(|_| focus.system == Some(tree.root))
+            .map(|sun| bodies.get(sun)),
+        earth.single(),
+        view,
+    ) {
+        let offset = sun.pos - earth.pos;
+        for &system in &tree.nodes[tree.root].children {
+            if let Some((center, _)) = tree.barycenter(system, &bodies) {
+                let conic = Conic::new(
+                    &(center.pos - sun.pos),
+                    &(center.vel - sun.vel),
+                    sun_mass.gm,
+                );
+                gizmos.linestrip(conic_points(&conic, &offset), GRAY.with_alpha(0.6));
+            }
+        }
+        if let Ok((orbital, _, _)) = ship.single() {
+            let conic = Conic::new(
+                &(orbital.pos - sun.pos),
+                &(orbital.vel - sun.vel),
+                sun_mass.gm,
+            );
+            gizmos.linestrip(conic_points(&conic, &offset), LIME);
+        }
+    }
+
+    for marker in &markers.0 {
+        let color = match marker.kind {
+            MapMarkerKind::Ship => WHITE,
+            MapMarkerKind::Periapsis if warning.0.is_some() => RED,
+            MapMarkerKind::Periapsis => ORANGE_RED,
+            MapMarkerKind::Apoapsis => DEEP_SKY_BLUE,
+            MapMarkerKind::AscendingNode | MapMarkerKind::DescendingNode => YELLOW,
+            MapMarkerKind::Body(_) => GRAY,
+            MapMarkerKind::Lagrange(_) => VIOLET,
+        };
+        let pos = sim_to_bevy(&marker.pos);
+        let mut size = camera.translation.distance(pos) * MARKER_SIZE;
+        if selection.0 == Some(marker.kind) {
+            size *= 2.0;
+        }
+        // Face the camera.
+        gizmos.circle(Isometry3d::new(pos, camera.rotation), size, color);
+    }
+}
+
+/// The points around a conic about the sun, `offset` being where the sun is
+/// in the map.  Hyperbolas are cut off at `HELIO_RANGE`.
+fn conic_points(conic: &Conic, offset: &Vector3<f64>) -> Vec<Vec3> {
+    (0..=ORBIT_POINTS)
+        .filter_map(|i| {
+            let nu = std::f64::consts::TAU * i as f64 / ORBIT_POINTS as f64 - std::f64::consts::PI;
+            let pos = conic.position_at(nu)?;
+            (pos.norm() < HELIO_RANGE).then(|| sim_to_bevy(&(pos + offset)))
+        })
+        .collect()
+}
+
+/// Frame the map on the system a body is in, or a barycenter heads.
+fn map_command(
+    In(args): In<Vec<String>>,
+    tree: Res<BodyTree>,
+    mut focus: ResMut<MapFocus>,
+    mut camera: Query<&mut MapCamera>,
+    bodies: Query<(&OrbitalBody, &MassiveBody)>,
+    sizes: Query<&SizedBody>,
+) -> ConsoleReply {
+    let system = match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => {
+            return Ok(match focus.system {
+                Some(system) => format!("map on the {} system", tree.nodes[system].name),
+                None => "map on the earth".to_string(),
+            });
+        }
+        ["reset"] => {
+            *focus = MapFocus::default();
+            if let Ok(mut camera) = camera.single_mut() {
+                camera.reframe();
+                camera.distance = camera.distance.clamp(focus.zoom.0, focus.zoom.1);
+            }
+            return Ok("map on the earth".to_string());
+        }
+        [name] => {
+            let index = tree
+                .find(name)
+                .ok_or_else(|| format!("No such body as {:?}", name))?;
+            match tree.nodes[index].entity {
+                Some(entity) => tree.system_of(entity).unwrap_or(index),
+                None => index,
+            }
+        }
+        _ => return Err("map [<body> | reset]".to_string()),
+    };
+    let mut camera = camera
+        .single_mut()
+        .map_err(|_| "No map camera".to_string())?;
+    let extent = frame(system, &tree, &bodies, &sizes, &mut focus, &mut camera)?;
+    camera.distance = ((extent * 3.0) as f32).clamp(focus.zoom.0, focus.zoom.1);
+    Ok(format!(
+        "map on the {} system, {:.0} km across",
+        tree.nodes[system].name,
+        extent * 2.0
+    ))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_map_text(
+    mode: Res<MapMode>,
+    focus: Res<MapFocus>,
+    tree: Res<BodyTree>,
+    detached: Res<DetachedPanels>,
+    markers: Res<MapMarkers>,
+    selection: Res<MapSelection>,
+    warning: Res<MapWarning>,
+    lagrange: Res<Lagrange>,
+    mut text: Query<&mut Text, With<MapText>>,
+    ship: Query<(&OrbitalBody, &OrbitLifetime, &Prediction), With<PlayerShip>>,
+    bodies: Query<&OrbitalBody, With<MassiveBody>>,
+) {
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    if !mode.0 {
+        text.clear();
+        return;
+    }
+    let selected = selection
+        .0
+        .and_then(|kind| markers.0.iter().find(|m| m.kind == kind))
+        .map(|m| m.label.as_str())
+        .unwrap_or("Click a marker for details");
+    let (lifetime, divergence) = ship
+        .single()
+        .map(|(_, lifetime, prediction)| {
+            (
+                format!("Orbital lifetime: {}\n", lifetime.0),
+                format!(
+                    "Perturbed path: {:.1} km off the conic at its end\n",
+                    prediction.divergence
+                ),
+            )
+        })
+        .unwrap_or_default();
+    let warning = warning
+        .0
+        .as_ref()
+        .map_or(String::new(), |warning| format!("WARNING: {}\n", warning));
+    let rotating = lagrange.rotating.as_ref().map_or(String::new(), |view| {
+        let jacobi = view
+            .jacobi
+            .map_or("-".to_string(), |jacobi| format!("{:.4}", jacobi));
+        format!(
+            "Turning with {}: Jacobi constant {} (L1 {:.4}, L2 {:.4})\n",
+            view.pair.name, jacobi, view.jacobi_l1, view.jacobi_l2
+        )
+    });
+    let system = focus.system.map_or(String::new(), |system| {
+        format!("Framed on the {} system\n", tree.nodes[system].name)
+    });
+    let sun = tree.nodes.get(tree.root).and_then(|node| node.entity);
+    let heliocentric = sun
+        .filter(|_| focus.system == Some(tree.root))
+        .and_then(|sun| bodies.get(sun).ok())
+        .zip(ship.single().ok())
+        .map_or(String::new(), |(sun, (orbital, _, _))| {
+            format!(
+                "Heliocentric: {:.4} AU from the sun, {:.3} km/s\n",
+                (orbital.pos - sun.pos).norm() / AU,
+                (orbital.vel - sun.vel).norm()
+            )
+        });
+    let exit = if detached.contains("map") {
+        "close the window to put it back"
+    } else {
+        "M to exit"
+    };
+    **text = format!(
+        "{}\n{}{}{}{}{}{}Map: right drag to orbit, scroll to zoom, {}",
+        selected, warning, lifetime, divergence, rotating, system, heliocentric, exit
+    );
+}
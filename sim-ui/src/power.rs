@@ -0,0 +1,44 @@
+//! The ship's power, in the info text.
+//!
+//! The battery shows as a gauge, with how long it has left at the present
+//! loads, or how long until it is full.
+
+use sim_game::ship::power::{Power, RESTART_CHARGE};
+
+/// The width of a gauge, in characters.
+const GAUGE_WIDTH: usize = 20;
+
+/// A gauge, `[#####-----]`, filled to `fraction`.
+pub(crate) fn gauge(fraction: f64) -> String {
+    let filled = ((fraction.clamp(0.0, 1.0) * GAUGE_WIDTH as f64).round()) as usize;
+    format!(
+        "[{}{}]",
+        "#".repeat(filled),
+        "-".repeat(GAUGE_WIDTH - filled)
+    )
+}
+
+/// The line for the info text.
+pub fn power_line(power: &Power) -> String {
+    let net = power.generated - power.load;
+    let outlook = if power.brownout {
+        format!(", BROWN-OUT, restarting at {:.0}%", RESTART_CHARGE * 100.0)
+    } else if net < 0.0 && power.charge > 0.0 {
+        format!(", empty in {:.0} min", power.charge / -net * 60.0)
+    } else if net > 0.0 && power.charge < power.capacity {
+        format!(
+            ", full in {:.0} min",
+            (power.capacity - power.charge) / net * 60.0
+        )
+    } else {
+        String::new()
+    };
+    format!(
+        "Power: {} {:.0}%, panels {:.0} W, loads {:.0} W{}",
+        gauge(power.state_of_charge()),
+        power.state_of_charge() * 100.0,
+        power.generated,
+        power.load,
+        outlook
+    )
+}
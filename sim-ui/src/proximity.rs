@@ -0,0 +1,184 @@
+//! The proximity panel and camera.
+//!
+//! When the ship is near its target craft (see `sim_game::ship::proximity`),
+//! a panel on the left shows where the ship is relative to it, in the
+//! target's R-bar, V-bar, and H-bar, and the closest approach if it drifts.
+//!
+//! C switches the main camera into the target's frame: it looks along V-bar,
+//! with R-bar up, so drifting motion looks the way the Clohessy–Wiltshire
+//! equations draw it.  The target, the line to it, and the ship's drift over
+//! the next orbit are drawn in the scene.  C again, or leaving range, puts the
+//! camera back.
+
+use bevy::{
+    color::palettes::css::{GOLD, LIME, RED, SKY_BLUE, WHITE},
+    prelude::*,
+};
+use na::Vector3;
+use sim_game::ship::proximity::Proximity;
+use sim_render::sim_to_bevy;
+use std::f64::consts::TAU;
+
+use crate::{MainCameraMarker, UI_LAYER, layout::HudPanel};
+
+/// Where the camera sits in the target's frame, in m from the ship, as
+/// (radial, along, cross): a little above, and behind.
+const CAMERA_OFFSET: Vector3<f64> = Vector3::new(2.0, -10.0, 0.0);
+
+/// The length of the frame's axes, drawn at the target, in m.
+const AXIS_LENGTH: f64 = 20.0;
+
+/// The points on the drawn drift.
+const DRIFT_SAMPLES: usize = 120;
+
+/// Whether the camera is in the target's frame, and where it was before.
+#[derive(Resource, Default)]
+pub struct CwCamera {
+    pub on: bool,
+    saved: Option<Transform>,
+}
+
+#[derive(Component)]
+struct ProximityText;
+
+#[derive(Default)]
+pub struct ProximityViewPlugin;
+
+impl Plugin for ProximityViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CwCamera>();
+        app.add_systems(Startup, setup_proximity);
+        app.add_systems(
+            Update,
+            (
+                proximity_keys,
+                update_cw_camera,
+                draw_proximity,
+                update_proximity_text,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn setup_proximity(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("Proximity Text"),
+        HudPanel("proximity"),
+        ProximityText,
+    ));
+}
+
+fn proximity_keys(kb: Res<ButtonInput<KeyCode>>, mut cw: ResMut<CwCamera>) {
+    if kb.just_pressed(KeyCode::KeyC) {
+        cw.on = !cw.on;
+    }
+}
+
+/// Line the main camera up with the target's frame, or put it back.
+fn update_cw_camera(
+    proximity: Res<Proximity>,
+    mut cw: ResMut<CwCamera>,
+    mut camera: Query<&mut Transform, With<MainCameraMarker>>,
+) {
+    let Ok(mut transform) = camera.single_mut() else {
+        return;
+    };
+    match (&proximity.0, cw.on) {
+        (Some(state), true) => {
+            if cw.saved.is_none() {
+                cw.saved = Some(*transform);
+            }
+            let q_fw = &state.q_fw;
+            *transform = Transform::from_translation(sim_to_bevy(&(q_fw * CAMERA_OFFSET)))
+                .looking_to(
+                    sim_to_bevy(&(q_fw * Vector3::y())),
+                    sim_to_bevy(&(q_fw * Vector3::x())),
+                );
+        }
+        _ => {
+            if let Some(saved) = cw.saved.take() {
+                *transform = saved;
+            }
+        }
+    }
+}
+
+/// The target, its frame, and the ship's drift, around the ship at the
+/// origin, in m.
+fn draw_proximity(mut gizmos: Gizmos, proximity: Res<Proximity>, cw: Res<CwCamera>) {
+    let Some(state) = &proximity.0 else {
+        return;
+    };
+    if !cw.on {
+        return;
+    }
+    let from_ship = |rac: Vector3<f64>| sim_to_bevy(&(state.q_fw * (rac - state.pos)));
+
+    let target = from_ship(Vector3::zeros());
+    gizmos.sphere(Isometry3d::from_translation(target), 2.0, WHITE);
+    gizmos.line(Vec3::ZERO, target, WHITE.with_alpha(0.3));
+    for (axis, color) in [
+        (Vector3::x(), RED),
+        (Vector3::y(), LIME),
+        (Vector3::z(), SKY_BLUE),
+    ] {
+        gizmos.line(target, from_ship(axis * AXIS_LENGTH), color);
+    }
+
+    let period = TAU / state.rate;
+    gizmos.linestrip(
+        (0..=DRIFT_SAMPLES)
+            .map(|i| from_ship(state.drift(period * i as f64 / DRIFT_SAMPLES as f64))),
+        GOLD,
+    );
+}
+
+fn update_proximity_text(
+    proximity: Res<Proximity>,
+    cw: Res<CwCamera>,
+    names: Query<&Name>,
+    mut text: Query<&mut Text, With<ProximityText>>,
+) {
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    let Some(state) = &proximity.0 else {
+        text.0.clear();
+        return;
+    };
+    let name = names
+        .get(state.target)
+        .map_or("Target".to_string(), |name| name.to_string());
+    text.0 = format!(
+        "{}  {:.1} m  {:+.2} m/s\n\
+         R {:+9.1} m {:+7.3} m/s\n\
+         V {:+9.1} m {:+7.3} m/s\n\
+         H {:+9.1} m {:+7.3} m/s\n\
+         Closest {:.1} m in {:.0} s\n\
+         C: {} camera",
+        name,
+        state.range,
+        state.range_rate,
+        state.pos.x,
+        state.vel.x,
+        state.pos.y,
+        state.vel.y,
+        state.pos.z,
+        state.vel.z,
+        state.closest.1,
+        state.closest.0,
+        if cw.on { "ship" } else { "LVLH" },
+    );
+}
@@ -0,0 +1,53 @@
+//! The RCS, in the info text.
+//!
+//! The mode and how many thrusters are firing, whether the keys are moving
+//! the craft rather than turning it, with the propellant left as a gauge, and
+//! the craft's body rates, in degrees a second about each axis.
+
+use sim_core::AttitudeState;
+use sim_game::ship::{
+    ControlLayer, RcsMode,
+    rcs::{RcsRealism, RcsTank, RcsThrusters},
+};
+
+use crate::power::gauge;
+
+/// The line for the info text.
+pub fn rcs_line(
+    mode: RcsMode,
+    layer: ControlLayer,
+    realism: &RcsRealism,
+    rcs: &RcsThrusters,
+    tank: Option<&RcsTank>,
+) -> String {
+    let propellant = match tank {
+        Some(tank) if tank.is_dry() => ", OUT OF PROPELLANT, no attitude control".to_string(),
+        Some(tank) => format!(
+            ", propellant {} {:.1} kg",
+            gauge(tank.fraction()),
+            tank.propellant
+        ),
+        None => String::new(),
+    };
+    format!(
+        " RCS: {:?}{}{}, {} of {} firing{}",
+        mode,
+        if realism.is_pulsed() { " (pulsed)" } else { "" },
+        match layer {
+            ControlLayer::Rotation => "",
+            ControlLayer::Translation => ", translating (Tab)",
+        },
+        rcs.firing(),
+        rcs.thrusters.len(),
+        propellant
+    )
+}
+
+/// The body rates, as the craft believes them to be.
+pub fn rates_line(attitude: &AttitudeState) -> String {
+    let rates = attitude.omega_b.map(f64::to_degrees);
+    format!(
+        " Rates: X {:+6.2}, Y {:+6.2}, Z {:+6.2} deg/s",
+        rates.x, rates.y, rates.z
+    )
+}
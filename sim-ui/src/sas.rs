@@ -0,0 +1,59 @@
+//! The stability assist's status, next to the navball.
+
+use bevy::prelude::*;
+use sim_game::ship::{HoldAttitude, PlayerShip, RcsMode, sas::StabilityAssist};
+
+use crate::{UI_LAYER, layout::HudPanel};
+
+#[derive(Component)]
+struct SasText;
+
+#[derive(Default)]
+pub struct SasViewPlugin;
+
+impl Plugin for SasViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_sas_text);
+        app.add_systems(Update, update_sas_text);
+    }
+}
+
+fn setup_sas_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 18.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("SAS Text"),
+        HudPanel("sas"),
+        SasText,
+    ));
+}
+
+fn update_sas_text(
+    mode: Res<RcsMode>,
+    ship: Query<(&StabilityAssist, &HoldAttitude), With<PlayerShip>>,
+    mut text: Query<&mut Text, With<SasText>>,
+) {
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    let Ok((sas, hold)) = ship.single() else {
+        text.clear();
+        return;
+    };
+    let state = match (*mode, sas.on, hold.0) {
+        (_, false, _) => "F to turn on".to_string(),
+        (RcsMode::Manual, true, Some(_)) => "holding".to_string(),
+        (RcsMode::Manual, true, None) => "turning".to_string(),
+        (_, true, _) => format!("standing by, in {:?}", *mode),
+    };
+    text.0 = format!("{}\n{}", sas.status(), state);
+}
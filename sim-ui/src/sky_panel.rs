@@ -0,0 +1,154 @@
+//! The sky view panel.
+//!
+//! While there is an observer on the ground (see `sim_game::observer`), this
+//! shows their sky: the horizon is the outer circle, straight up is the
+//! middle, and north is at the top, with east to the right, as on a star chart
+//! held overhead.  Each target's current or next pass is drawn across it, and
+//! where it is now, if it is up.  The first target (the player ship, to start
+//! with) is in gold.  Like the ground track, the panel has its own 2D camera,
+//! drawn into a viewport the HUD layout puts somewhere.
+
+use bevy::{
+    camera::visibility::RenderLayers,
+    color::palettes::css::{GOLD, GRAY, LIGHT_SKY_BLUE, LIME, ORANGE, WHITE},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use sim_astro::geodesy::LookAngles;
+use sim_game::observer::Observer;
+
+use crate::{
+    layout::HudPanel,
+    windows::{Detachable, DetachedPanels},
+};
+
+pub const SKY_LAYER: RenderLayers = RenderLayers::layer(4);
+
+/// The size of the panel, in logical pixels.
+const PANEL_SIZE: Vec2 = Vec2::new(240.0, 240.0);
+
+/// The radius of the horizon on the panel, in logical pixels.
+const HORIZON: f32 = 110.0;
+
+/// The colors of the targets after the first, in turn.
+const TARGET_COLORS: [Srgba; 4] = [WHITE, LIGHT_SKY_BLUE, LIME, ORANGE];
+
+/// Gizmos drawn only in the sky panel.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct SkyGizmos;
+
+#[derive(Component)]
+struct SkyCamera;
+
+#[derive(Default)]
+pub struct SkyPanelPlugin;
+
+impl Plugin for SkyPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_gizmo_group::<SkyGizmos>();
+        app.add_systems(Startup, setup_sky_panel);
+        app.add_systems(Update, (show_sky_panel, draw_sky).chain());
+    }
+}
+
+fn setup_sky_panel(mut commands: Commands, mut config_store: ResMut<GizmoConfigStore>) {
+    let (config, _) = config_store.config_mut::<SkyGizmos>();
+    config.render_layers = SKY_LAYER;
+
+    commands.spawn((
+        Camera2d,
+        Camera {
+            order: 5,
+            is_active: false,
+            clear_color: ClearColorConfig::Custom(Color::srgb(0.01, 0.02, 0.06)),
+            ..default()
+        },
+        SKY_LAYER,
+        Name::new("Sky Camera"),
+        SkyCamera,
+        HudPanel("sky"),
+        Detachable {
+            name: "sky",
+            size: PANEL_SIZE.as_uvec2(),
+        },
+    ));
+}
+
+/// Show the panel when an observer is placed, and hide it when they go.  The
+/// viewport's size follows the window's scale, and the layout moves it.  In a
+/// window of its own, it is always showing, if empty without an observer.
+fn show_sky_panel(
+    observer: Option<Res<Observer>>,
+    detached: Res<DetachedPanels>,
+    mut observing: Local<bool>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut camera: Query<&mut Camera, With<SkyCamera>>,
+) {
+    let Ok(mut camera) = camera.single_mut() else {
+        return;
+    };
+    if detached.contains("sky") {
+        camera.is_active = true;
+        return;
+    }
+    // Put back, it goes back to following the observer.
+    if observer.is_some() != *observing || detached.is_changed() {
+        *observing = observer.is_some();
+        camera.is_active = *observing;
+    }
+    let Ok(window) = window.single() else {
+        return;
+    };
+
+    camera.viewport.get_or_insert_default().physical_size =
+        (PANEL_SIZE * window.scale_factor()).as_uvec2();
+}
+
+/// Where an azimuth and elevation go on the panel.
+fn panel_point(angles: &LookAngles) -> Vec2 {
+    let r = HORIZON * (1.0 - angles.elevation.to_degrees() as f32 / 90.0);
+    let az = angles.azimuth as f32;
+    Vec2::new(r * az.sin(), r * az.cos())
+}
+
+fn draw_sky(
+    mut gizmos: Gizmos<SkyGizmos>,
+    camera: Query<&Camera, With<SkyCamera>>,
+    observer: Option<Res<Observer>>,
+) {
+    let (Ok(camera), Some(observer)) = (camera.single(), observer) else {
+        return;
+    };
+    if !camera.is_active {
+        return;
+    }
+
+    // The horizon, circles at 30 and 60 degrees up, and the compass points,
+    // with north brighter.
+    let grid = Color::srgb(0.2, 0.25, 0.3);
+    gizmos.circle_2d(Isometry2d::IDENTITY, HORIZON, GRAY);
+    for elevation in [30.0, 60.0] {
+        gizmos.circle_2d(
+            Isometry2d::IDENTITY,
+            HORIZON * (1.0 - elevation / 90.0),
+            grid,
+        );
+    }
+    gizmos.line_2d(Vec2::ZERO, Vec2::Y * HORIZON, GRAY);
+    gizmos.line_2d(Vec2::ZERO, -Vec2::Y * HORIZON, grid);
+    gizmos.line_2d(-Vec2::X * HORIZON, Vec2::X * HORIZON, grid);
+
+    for (i, target) in observer.targets.iter().enumerate() {
+        let color = if i == 0 {
+            GOLD
+        } else {
+            TARGET_COLORS[(i - 1) % TARGET_COLORS.len()]
+        };
+        if let Some(pass) = target.passes.first() {
+            gizmos.linestrip_2d(pass.path.iter().map(panel_point), color);
+        }
+        if let Some(now) = target.now.filter(|now| now.elevation > 0.0) {
+            gizmos.circle_2d(Isometry2d::from_translation(panel_point(&now)), 4.0, color);
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! The light and sky near a body's surface, in the ship view.
+//!
+//! Out in space, the view is lit by the sun and a little ambient, against the
+//! plain background.  Coming down to a body, the nearest one by altitude, the
+//! view goes over to that body's look (see `SkyLooks`): the color of its sky,
+//! overhead and at sunset, how much light the sky throws about, and the haze,
+//! as fog, all going with how high the sun stands where the craft is.  On the
+//! earth the sky is blue, going orange at sunset, with a long haze; on Mars it
+//! is butterscotch by day and blue at sunset, with dust; on the moon, and any
+//! other body without air, the sky stays black, and as there is nothing to
+//! scatter the light, the shadows are harsh.  The look fades in from the
+//! height its sky reaches, and is all there by the ground.
+//!
+//! A body not in the table has a gray haze if it has an atmosphere (see
+//! `sim_astro::atmosphere`), and is airless otherwise.
+
+use bevy::prelude::*;
+use sim_astro::{SmallBodyMarker, atmosphere::Atmosphere};
+use sim_core::{OrbitalBody, SizedBody};
+use sim_game::ship::{PlayerShip, sunlight::Sunlight};
+use std::collections::BTreeMap;
+
+use crate::MainCameraMarker;
+
+/// How close, km, to an airless body the light starts to change.
+const AIRLESS_REACH: f64 = 20.0;
+
+/// The sine of the sun's elevation over which twilight goes to night, and
+/// over which the sky goes from its sunset color to its day one.
+const TWILIGHT: f32 = 0.1;
+const SUNSET: f32 = 0.3;
+
+/// How much of the day's ambient light is left at night, under a sky.
+const NIGHT_AMBIENT: f32 = 0.1;
+
+/// How strongly the haze glows toward the sun.
+const SUN_GLOW_EXPONENT: f32 = 20.0;
+
+/// How a body's sky looks from its surface.
+#[derive(Clone, Debug)]
+pub struct SkyLook {
+    /// The sky with the sun high, and with it at the horizon.
+    pub day: Color,
+    pub sunset: Color,
+    /// How high the sky reaches, km.  Above this, the view is as in space.
+    pub height: f64,
+    /// The ambient light on the surface in full day, as for `AmbientLight`.
+    pub ambient: f32,
+    /// How far one can see through the haze in full day, km, if there is
+    /// any.
+    pub visibility: Option<f32>,
+}
+
+impl SkyLook {
+    /// A body without air: a black sky, and very little ambient light.
+    pub fn airless() -> Self {
+        SkyLook {
+            day: Color::BLACK,
+            sunset: Color::BLACK,
+            height: AIRLESS_REACH,
+            ambient: 20.0,
+            visibility: None,
+        }
+    }
+
+    /// A body with an atmosphere, not in the table.
+    fn hazy(atmosphere: &Atmosphere) -> Self {
+        SkyLook {
+            day: Color::srgb(0.6, 0.6, 0.65),
+            sunset: Color::srgb(0.7, 0.5, 0.4),
+            height: atmosphere.thickness() / 2.0,
+            ambient: 300.0,
+            visibility: Some(30.0),
+        }
+    }
+}
+
+/// The bodies' looks, by name.
+#[derive(Resource, Clone, Debug)]
+pub struct SkyLooks(pub BTreeMap<String, SkyLook>);
+
+impl Default for SkyLooks {
+    fn default() -> Self {
+        SkyLooks(BTreeMap::from([
+            (
+                "EARTH".to_string(),
+                SkyLook {
+                    day: Color::srgb(0.35, 0.55, 0.95),
+                    sunset: Color::srgb(0.95, 0.5, 0.25),
+                    height: 60.0,
+                    ambient: 600.0,
+                    visibility: Some(40.0),
+                },
+            ),
+            (
+                "MARS".to_string(),
+                SkyLook {
+                    day: Color::srgb(0.76, 0.6, 0.42),
+                    sunset: Color::srgb(0.35, 0.5, 0.75),
+                    height: 40.0,
+                    ambient: 350.0,
+                    visibility: Some(15.0),
+                },
+            ),
+            (
+                "VENUS".to_string(),
+                SkyLook {
+                    day: Color::srgb(0.85, 0.7, 0.4),
+                    sunset: Color::srgb(0.6, 0.4, 0.2),
+                    height: 70.0,
+                    ambient: 250.0,
+                    visibility: Some(3.0),
+                },
+            ),
+            (
+                "TITAN".to_string(),
+                SkyLook {
+                    day: Color::srgb(0.7, 0.5, 0.2),
+                    sunset: Color::srgb(0.4, 0.25, 0.1),
+                    height: 200.0,
+                    ambient: 60.0,
+                    visibility: Some(5.0),
+                },
+            ),
+        ]))
+    }
+}
+
+#[derive(Default)]
+pub struct SkylightPlugin;
+
+impl Plugin for SkylightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SkyLooks>();
+        app.add_systems(Update, update_skylight);
+    }
+}
+
+/// Blend the ship view from space, with the usual background and ambient
+/// light, to the nearest body's look.
+#[allow(clippy::type_complexity)]
+fn update_skylight(
+    mut commands: Commands,
+    looks: Res<SkyLooks>,
+    space: Res<AmbientLight>,
+    background: Res<ClearColor>,
+    ship: Query<(&OrbitalBody, &Sunlight), With<PlayerShip>>,
+    bodies: Query<
+        (&Name, &OrbitalBody, &SizedBody, Option<&Atmosphere>),
+        (Without<PlayerShip>, Without<SmallBodyMarker>),
+    >,
+    mut camera: Query<(Entity, &mut Camera), With<MainCameraMarker>>,
+) {
+    let Ok((camera_entity, mut camera)) = camera.single_mut() else {
+        return;
+    };
+    let Ok((ship, sunlight)) = ship.single() else {
+        return;
+    };
+    let nearest = bodies
+        .iter()
+        .map(|(name, orbital, size, atmosphere)| {
+            let rel = ship.pos - orbital.pos;
+            (name, rel, rel.norm() - size.radii.z, atmosphere)
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2));
+    let Some((name, rel, altitude, atmosphere)) = nearest else {
+        return;
+    };
+    let look = match (looks.0.get(name.as_str()), atmosphere) {
+        (Some(look), _) => look.clone(),
+        (None, Some(atmosphere)) => SkyLook::hazy(atmosphere),
+        (None, None) => SkyLook::airless(),
+    };
+    // How much of the body's look there is, from none at the top of its sky
+    // to all of it on the ground.
+    let near = (1.0 - altitude / look.height).clamp(0.0, 1.0) as f32;
+    let near = near * near * (3.0 - 2.0 * near);
+    if near == 0.0 {
+        camera.clear_color = ClearColorConfig::Default;
+        commands
+            .entity(camera_entity)
+            .remove::<(AmbientLight, DistanceFog)>();
+        return;
+    }
+
+    let elevation = rel.normalize().dot(&sunlight.sun_w) as f32;
+    let day =
+        ((elevation + TWILIGHT) / (2.0 * TWILIGHT)).clamp(0.0, 1.0) * sunlight.fraction as f32;
+    let high = (elevation / SUNSET).clamp(0.0, 1.0);
+    let sky = look
+        .sunset
+        .mix(&look.day, high)
+        .mix(&Color::BLACK, 1.0 - day);
+    camera.clear_color = ClearColorConfig::Custom(background.0.mix(&sky, near));
+
+    // Under a sky, some of the light is left at night; without one, there
+    // is as little by day as by night.  A sky tints it.
+    let (ambient, tint) = if look.visibility.is_some() {
+        (
+            look.ambient * (NIGHT_AMBIENT + (1.0 - NIGHT_AMBIENT) * day),
+            look.day,
+        )
+    } else {
+        (look.ambient, Color::WHITE)
+    };
+    let mut light = commands.entity(camera_entity);
+    light.insert(AmbientLight {
+        color: Color::WHITE.mix(&tint, near),
+        brightness: space.brightness + (ambient - space.brightness) * near,
+        ..default()
+    });
+    match look.visibility {
+        Some(visibility) => {
+            light.insert(DistanceFog {
+                color: sky,
+                directional_light_color: look.sunset.mix(&Color::WHITE, high).with_alpha(day),
+                directional_light_exponent: SUN_GLOW_EXPONENT,
+                // km to m, and thinning out going up.
+                falloff: FogFalloff::from_visibility(visibility * 1000.0 / near),
+            });
+        }
+        None => {
+            light.remove::<DistanceFog>();
+        }
+    }
+}
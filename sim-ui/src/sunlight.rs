@@ -0,0 +1,99 @@
+//! Sunlight, in the ship view.
+//!
+//! The ship's light shines from the sun, dimmed by however much of it is
+//! hidden (see `sim_game::ship::sunlight`).  The next sunset and sunrise are
+//! taken from the mission events' forecast, every so often, for the countdowns
+//! in the info text.
+
+use bevy::prelude::*;
+use sim_astro::SolarState;
+use sim_game::{
+    events::{EventKind, MissionEvents},
+    ship::{PlayerShip, sunlight::Sunlight},
+};
+use sim_render::sim_to_bevy;
+
+/// The illuminance of the ship's light in full sun.
+pub const SHIP_ILLUMINANCE: f32 = 10_000.0;
+
+/// How often, in real seconds, the sunset and sunrise are looked for.
+const SUN_TIMES_INTERVAL: f64 = 1.0;
+
+/// The light on the ship.
+#[derive(Component)]
+pub struct ShipLight;
+
+/// When the sun next sets and rises for the ship, in seconds past J2000, if
+/// it does within the forecast.
+#[derive(Resource, Default)]
+pub struct SunTimes {
+    pub sunset: Option<f64>,
+    pub sunrise: Option<f64>,
+}
+
+#[derive(Default)]
+pub struct SunlightViewPlugin;
+
+impl Plugin for SunlightViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SunTimes>();
+        app.add_systems(Update, (update_ship_light, update_sun_times));
+    }
+}
+
+/// Point the ship's light from the sun, and dim it in a shadow.
+fn update_ship_light(
+    ship: Query<&Sunlight, With<PlayerShip>>,
+    mut light: Query<(&mut DirectionalLight, &mut Transform), With<ShipLight>>,
+) {
+    let (Ok(sunlight), Ok((mut light, mut transform))) = (ship.single(), light.single_mut()) else {
+        return;
+    };
+    light.illuminance = SHIP_ILLUMINANCE * sunlight.fraction as f32;
+    *transform = Transform::default().looking_to(-sim_to_bevy(&sunlight.sun_w), Vec3::Y);
+}
+
+fn update_sun_times(
+    real: Res<Time<Real>>,
+    mut next: Local<f64>,
+    mut times: ResMut<SunTimes>,
+    events: MissionEvents,
+) {
+    let now = real.elapsed_secs_f64();
+    if now < *next {
+        return;
+    }
+    *next = now + SUN_TIMES_INTERVAL;
+    let forecast = events.forecast();
+    let first = |kind: EventKind| {
+        forecast
+            .iter()
+            .find(|event| event.kind == kind)
+            .map(|event| event.et)
+    };
+    times.sunset = first(EventKind::EclipseEntry);
+    times.sunrise = first(EventKind::EclipseExit);
+}
+
+/// The line for the info text.
+pub fn sunlight_line(
+    sunlight: &Sunlight,
+    times: &SunTimes,
+    solar: &SolarState,
+    now: f64,
+) -> String {
+    let et = solar.et + now;
+    let countdown = |name: &str, at: Option<f64>| {
+        at.map_or(String::new(), |at| {
+            format!(", {} in {:.0} s", name, at - et)
+        })
+    };
+    format!(
+        "Sunlight: {} {:.0}%{}{}, pressure {:.2e} m/s^2",
+        sunlight.shadow,
+        sunlight.fraction * 100.0,
+        countdown("sunset", times.sunset),
+        countdown("sunrise", times.sunrise),
+        sunlight.pressure
+    )
+}
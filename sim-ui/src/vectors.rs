@@ -0,0 +1,181 @@
+//! Debug vectors, in the 3D scene.
+//!
+//! To check the frame math by eye, rather than from printed quaternions,
+//! arrows can be drawn out from the craft being flown, each turned on on its
+//! own:
+//!
+//! - `velocity` (white): the velocity, relative to the earth.
+//! - `momentum` (magenta): the angular momentum.
+//! - `thrust` (orange): the main engine's thrust, out from the engine, as
+//!   long as the throttle is open.
+//! - `sun` (yellow): the way to the sun.
+//! - `nav`: the navball's frame, the local horizon: X (red) to the right, Y
+//!   (green) along the way over the ground, and Z (blue) up.
+//! - `body`: the craft's own axes, X red, Y green, and Z blue, half as long.
+//!
+//! Each is `VECTOR_LENGTH` long, whatever its size, but for the thrust,
+//! which is as much shorter as the throttle is closed.
+//!
+//! - `vectors`: the ones showing.
+//! - `vectors <name...>`: show them, or stop showing them if they are.
+//! - `vectors all`, `vectors none`.
+
+use bevy::{
+    color::palettes::css::{FUCHSIA, LIME, ORANGE, RED, SKY_BLUE, WHITE, YELLOW},
+    prelude::*,
+};
+use na::{UnitQuaternion, Vector3};
+use sim_astro::{EarthMarker, frames::Axes};
+use sim_core::{AttitudeState, OrbitalBody};
+use sim_game::{
+    console::{ConsoleApp, ConsoleReply},
+    ship::{MassProperties, PlayerShip, engine::MainEngine, sunlight::Sunlight},
+};
+use sim_render::{
+    interpolate::{Interpolation, LastStep},
+    origin::FloatingOrigin,
+    sim_to_bevy,
+};
+use std::collections::BTreeSet;
+
+/// How long each arrow is, m.
+const VECTOR_LENGTH: f64 = 10.0;
+
+/// The vectors there are, by name.
+const VECTORS: [&str; 6] = ["velocity", "momentum", "thrust", "sun", "nav", "body"];
+
+/// The vectors showing.
+#[derive(Resource, Default)]
+pub struct DebugVectors(pub BTreeSet<&'static str>);
+
+#[derive(Default)]
+pub struct DebugVectorsPlugin;
+
+impl Plugin for DebugVectorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugVectors>();
+        app.add_systems(Update, draw_vectors);
+        app.add_console_command(
+            "vectors",
+            "vectors [<name...> | all | none]   show debug vectors: velocity, momentum, thrust, sun, nav, body",
+            vectors_command,
+        );
+    }
+}
+
+/// An arrow from `from` along `dir_w`, world frame, `length` m long, or
+/// nothing if there is no direction.
+fn arrow(gizmos: &mut Gizmos, from: Vec3, dir_w: &Vector3<f64>, length: f64, color: Srgba) {
+    if let Some(dir) = dir_w.try_normalize(1.0e-12) {
+        gizmos.arrow(from, from + sim_to_bevy(&(dir * length)), color);
+    }
+}
+
+/// The axes of a frame, frame to world, at `from`.
+fn axes(gizmos: &mut Gizmos, from: Vec3, q_fw: &UnitQuaternion<f64>, length: f64) {
+    for (axis, color) in [
+        (Vector3::x(), RED),
+        (Vector3::y(), LIME),
+        (Vector3::z(), SKY_BLUE),
+    ] {
+        arrow(gizmos, from, &(q_fw * axis), length, color);
+    }
+}
+
+/// Draw the vectors showing, out from the craft.
+#[allow(clippy::type_complexity)]
+fn draw_vectors(
+    shown: Res<DebugVectors>,
+    mut gizmos: Gizmos,
+    origin: Res<FloatingOrigin>,
+    interpolation: Res<Interpolation>,
+    ship: Query<
+        (
+            &OrbitalBody,
+            &AttitudeState,
+            &MassProperties,
+            Option<&LastStep>,
+            Option<&MainEngine>,
+            Option<&Sunlight>,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<&OrbitalBody, (With<EarthMarker>, Without<PlayerShip>)>,
+) {
+    if shown.0.is_empty() {
+        return;
+    }
+    let Ok((orbital, attitude, mass, last, engine, sunlight)) = ship.single() else {
+        return;
+    };
+    let on = |name: &str| shown.0.contains(name);
+    let at = origin.place(&interpolation.pos(orbital, last));
+    let q_bw = interpolation.q_bw(attitude, last);
+    let earth = earth.single().ok();
+    let relative = earth.map(|earth| (orbital.pos - earth.pos, orbital.vel - earth.vel));
+
+    if let (true, Some((_, vel))) = (on("velocity"), &relative) {
+        arrow(&mut gizmos, at, vel, VECTOR_LENGTH, WHITE);
+    }
+    if on("momentum") {
+        let momentum_b = mass.inertia_b.component_mul(&attitude.omega_b);
+        arrow(
+            &mut gizmos,
+            at,
+            &(q_bw * momentum_b),
+            VECTOR_LENGTH,
+            FUCHSIA,
+        );
+    }
+    if let (true, Some(engine)) = (on("thrust"), engine) {
+        let throttle = engine.held_throttle(mass.mass);
+        if throttle > 0.0 {
+            let mount = at + sim_to_bevy(&(q_bw * engine.pos_b));
+            let dir_w = q_bw * engine.direction_b();
+            arrow(&mut gizmos, mount, &dir_w, VECTOR_LENGTH * throttle, ORANGE);
+        }
+    }
+    if let (true, Some(sunlight)) = (on("sun"), sunlight) {
+        arrow(&mut gizmos, at, &sunlight.sun_w, VECTOR_LENGTH, YELLOW);
+    }
+    if let (true, Some((pos, vel))) = (on("nav"), &relative)
+        && let Some(horizon) = Axes::horizon(pos, vel)
+    {
+        axes(&mut gizmos, at, &horizon.q_fw, VECTOR_LENGTH);
+    }
+    if on("body") {
+        axes(&mut gizmos, at, &q_bw, VECTOR_LENGTH / 2.0);
+    }
+}
+
+fn vectors_command(In(args): In<Vec<String>>, mut shown: ResMut<DebugVectors>) -> ConsoleReply {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] => {}
+        ["all"] => shown.0 = VECTORS.into_iter().collect(),
+        ["none"] => shown.0.clear(),
+        names => {
+            let names = names
+                .iter()
+                .map(|name| {
+                    VECTORS
+                        .into_iter()
+                        .find(|vector| vector.eq_ignore_ascii_case(name))
+                        .ok_or_else(|| format!("Unknown vector {:?}", name))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            for name in names {
+                if !shown.0.remove(name) {
+                    shown.0.insert(name);
+                }
+            }
+        }
+    }
+    if shown.0.is_empty() {
+        return Ok("No vectors showing".to_string());
+    }
+    Ok(format!(
+        "Showing {}",
+        shown.0.iter().copied().collect::<Vec<_>>().join(" ")
+    ))
+}
@@ -0,0 +1,409 @@
+//! The camera modes.
+//!
+//! The main camera follows the craft being flown in one of several ways, its
+//! `CameraMode`:
+//!
+//! - Chase: behind the craft, on its way over the earth, a little above, with
+//!   the sky up.
+//! - Free: off the craft in fixed directions, the stars', wherever it is
+//!   turned to.
+//! - Onboard: on the craft's nose, looking out, with the craft's top up.
+//! - Planet: off the craft in the ground's directions, north, east, and up,
+//!   turning with the earth beneath it.
+//! - Target: behind the craft, on the line from its target (see `SasTarget`),
+//!   looking at the target.  With no target, it is as Free.
+//!
+//! B goes to the next mode.  Right drag turns the camera about the craft (on
+//! board, it looks around), and scrolling moves it in and out (on board, it
+//! zooms).  Each mode keeps its own distance, field of view, and smoothing:
+//! how long, in real seconds, the camera takes to catch up with where the
+//! mode puts it, so that a chase camera lags a little, and sways, as the
+//! craft turns.  The proximity camera, the camera path, and its editor, when
+//! they are on, come first.
+//!
+//! - `view`: the mode, and its settings.
+//! - `view <mode>`: change to a mode, by its name.
+//! - `view distance <m>`, `view fov <deg>`, `view smoothing <s>`: set the
+//!   mode's settings.
+
+use bevy::{
+    input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use na::{Rotation3, UnitQuaternion, Vector3};
+use sim_astro::EarthMarker;
+use sim_core::{AttitudeState, OrbitalBody};
+use sim_game::{
+    console::{ConsoleApp, ConsoleReply, parse_arg},
+    keys::{InputMap, KeysApp},
+    ship::{MassProperties, PlayerShip, SasTarget, proximity::Proximity},
+};
+use sim_render::{
+    interpolate::{Interpolation, LastStep},
+    origin::FloatingOrigin,
+    sim_to_bevy,
+};
+
+use crate::{
+    MainCameraMarker, UI_LAYER,
+    cinematic::{CameraEditorOpen, CameraPath},
+    layout::HudPanel,
+    proximity::CwCamera,
+    windows::target_window,
+};
+
+/// Each notch of the scroll wheel moves the camera in or out, or zooms, by
+/// this much.
+const ZOOM_STEP: f64 = 1.1;
+
+/// The nearest the camera comes to the craft, m.
+const NEAREST: f64 = 1.0;
+
+/// The narrowest and widest the field of view goes, deg.
+const FOV_RANGE: (f64, f64) = (5.0, 120.0);
+
+/// Where the onboard camera is, BODY frame, m: just off the nose.
+const ONBOARD_B: Vector3<f64> = Vector3::new(0.0, 0.0, 3.0);
+
+/// How the main camera follows the craft.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    #[default]
+    Chase,
+    Free,
+    Onboard,
+    Planet,
+    Target,
+}
+
+impl CameraMode {
+    const ALL: [CameraMode; 5] = [
+        CameraMode::Chase,
+        CameraMode::Free,
+        CameraMode::Onboard,
+        CameraMode::Planet,
+        CameraMode::Target,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Chase => "chase",
+            CameraMode::Free => "free",
+            CameraMode::Onboard => "onboard",
+            CameraMode::Planet => "planet",
+            CameraMode::Target => "target",
+        }
+    }
+
+    fn next(self) -> CameraMode {
+        CameraMode::ALL[(self as usize + 1) % CameraMode::ALL.len()]
+    }
+}
+
+/// What a mode does with the camera, kept for each mode.
+#[derive(Clone, Debug)]
+pub struct ViewSettings {
+    /// How far the camera is from the craft, m.  Not used on board.
+    pub distance: f64,
+    /// The field of view, deg.
+    pub fov: f64,
+    /// How long, in real seconds, the camera takes to catch up, or 0 for
+    /// at once.
+    pub smoothing: f64,
+    /// The way from the craft to the camera, or on board, the way it looks,
+    /// in the mode's axes: forward, left, and up.
+    pub dir: Vector3<f64>,
+}
+
+impl ViewSettings {
+    fn new(distance: f64, smoothing: f64, dir: Vector3<f64>) -> Self {
+        ViewSettings {
+            distance,
+            fov: 60.0,
+            smoothing,
+            dir: dir.normalize(),
+        }
+    }
+}
+
+/// The settings of each mode, in the order of `CameraMode`.
+#[derive(Resource, Clone, Debug)]
+pub struct CameraViews(pub [ViewSettings; 5]);
+
+impl Default for CameraViews {
+    fn default() -> Self {
+        let behind = Vector3::new(-1.0, 0.0, 0.25);
+        CameraViews([
+            ViewSettings::new(15.0, 0.3, behind),
+            ViewSettings::new(15.0, 0.0, behind),
+            ViewSettings::new(0.0, 0.0, Vector3::x()),
+            ViewSettings::new(30.0, 0.2, Vector3::new(-1.0, 0.0, 0.5)),
+            ViewSettings::new(20.0, 0.3, behind),
+        ])
+    }
+}
+
+impl CameraViews {
+    pub fn get(&self, mode: CameraMode) -> &ViewSettings {
+        &self.0[mode as usize]
+    }
+
+    pub fn get_mut(&mut self, mode: CameraMode) -> &mut ViewSettings {
+        &mut self.0[mode as usize]
+    }
+}
+
+#[derive(Component)]
+struct ViewText;
+
+#[derive(Default)]
+pub struct CameraModePlugin;
+
+impl Plugin for CameraModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraMode>();
+        app.init_resource::<CameraViews>();
+        app.add_key_action("view", &[KeyCode::KeyB]);
+        app.add_systems(Startup, setup_view_text);
+        app.add_systems(
+            Update,
+            (view_keys, view_controls, place_camera, update_view_text).chain(),
+        );
+        app.add_console_command(
+            "view",
+            "view [<mode> | distance <m> | fov <deg> | smoothing <s>]   the camera mode: chase, free, onboard, planet, or target",
+            view_command,
+        );
+    }
+}
+
+fn setup_view_text(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+            font_size: 16.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            ..default()
+        },
+        UI_LAYER,
+        Name::new("View Text"),
+        HudPanel("view"),
+        ViewText,
+    ));
+}
+
+fn view_keys(kb: Res<ButtonInput<KeyCode>>, map: Res<InputMap>, mut mode: ResMut<CameraMode>) {
+    if map.just_pressed(&kb, "view") {
+        *mode = mode.next();
+    }
+}
+
+/// Whether something else has the camera: the proximity camera, or the
+/// camera path, or its editor.
+fn taken(
+    cw: &CwCamera,
+    proximity: &Proximity,
+    path: &CameraPath,
+    editor: &CameraEditorOpen,
+) -> bool {
+    (cw.on && proximity.0.is_some()) || path.playing || editor.0
+}
+
+/// Right drag turns the camera, and scrolling moves it in and out, or on
+/// board, zooms.
+#[allow(clippy::too_many_arguments)]
+fn view_controls(
+    mode: Res<CameraMode>,
+    mut views: ResMut<CameraViews>,
+    (cw, proximity, path, editor): (
+        Res<CwCamera>,
+        Res<Proximity>,
+        Res<CameraPath>,
+        Res<CameraEditorOpen>,
+    ),
+    buttons: Res<ButtonInput<MouseButton>>,
+    motion: Res<AccumulatedMouseMotion>,
+    scroll: Res<AccumulatedMouseScroll>,
+    camera: Query<&Camera, With<MainCameraMarker>>,
+    windows: Query<&Window>,
+    primary: Query<Entity, With<PrimaryWindow>>,
+) {
+    if taken(&cw, &proximity, &path, &editor) {
+        return;
+    }
+    let Ok(view) = camera.single() else {
+        return;
+    };
+    // Only with the mouse over the ship view.
+    let window = target_window(view, primary.single().ok()).and_then(|w| windows.get(w).ok());
+    if !view.is_active || !window.is_some_and(|w| w.cursor_position().is_some()) {
+        return;
+    }
+    let onboard = *mode == CameraMode::Onboard;
+    let settings = views.get_mut(*mode);
+    if buttons.pressed(MouseButton::Right) {
+        // On board, dragging down looks down; off it, it goes over the top.
+        let tilt = if onboard { -1.0 } else { 1.0 };
+        let dir = &settings.dir;
+        let heading = dir.y.atan2(dir.x) - motion.delta.x as f64 * 0.005;
+        let elevation =
+            (dir.z.clamp(-1.0, 1.0).asin() + tilt * motion.delta.y as f64 * 0.005).clamp(-1.5, 1.5);
+        settings.dir = Vector3::new(
+            elevation.cos() * heading.cos(),
+            elevation.cos() * heading.sin(),
+            elevation.sin(),
+        );
+    }
+    let notches = match scroll.unit {
+        MouseScrollUnit::Line => scroll.delta.y,
+        MouseScrollUnit::Pixel => scroll.delta.y / 32.0,
+    };
+    if notches != 0.0 {
+        let step = ZOOM_STEP.powf(-notches as f64);
+        if onboard {
+            settings.fov = (settings.fov * step).clamp(FOV_RANGE.0, FOV_RANGE.1);
+        } else {
+            settings.distance = (settings.distance * step).max(NEAREST);
+        }
+    }
+}
+
+/// Axes, to world, whose X is `forward` and Z `up`, as near as they can be
+/// while square, or None if they line up.
+fn axes(forward: &Vector3<f64>, up: &Vector3<f64>) -> Option<UnitQuaternion<f64>> {
+    let up = up.try_normalize(1.0e-12)?;
+    let forward = (forward - up * forward.dot(&up)).try_normalize(1.0e-9)?;
+    let left = up.cross(&forward);
+    Some(UnitQuaternion::from_rotation_matrix(
+        &Rotation3::from_basis_unchecked(&[forward, left, up]),
+    ))
+}
+
+/// Put the camera where the mode has it, catching up as fast as its
+/// smoothing lets it.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn place_camera(
+    mode: Res<CameraMode>,
+    views: Res<CameraViews>,
+    (cw, proximity, path, editor): (
+        Res<CwCamera>,
+        Res<Proximity>,
+        Res<CameraPath>,
+        Res<CameraEditorOpen>,
+    ),
+    time: Res<Time<Real>>,
+    origin: Res<FloatingOrigin>,
+    interpolation: Res<Interpolation>,
+    sas_target: Res<SasTarget>,
+    ship: Query<
+        (
+            &OrbitalBody,
+            &AttitudeState,
+            &MassProperties,
+            Option<&LastStep>,
+        ),
+        With<PlayerShip>,
+    >,
+    earth: Query<(&OrbitalBody, &AttitudeState), With<EarthMarker>>,
+    targets: Query<(&OrbitalBody, Option<&LastStep>), Without<PlayerShip>>,
+    mut camera: Query<(&mut Transform, &mut Projection), With<MainCameraMarker>>,
+) {
+    if taken(&cw, &proximity, &path, &editor) {
+        return;
+    }
+    let (Ok((mut transform, mut projection)), Ok((orbital, attitude, mass, last))) =
+        (camera.single_mut(), ship.single())
+    else {
+        return;
+    };
+    let settings = views.get(*mode);
+    let ship_m = origin.offset(&interpolation.pos(orbital, last));
+    let q_bw = interpolation.q_bw(attitude, last);
+    let earth = earth.single().ok();
+    // Up, and the way along, over the earth.
+    let (radial, along) = earth.map_or((Vector3::z(), Vector3::x()), |(earth, _)| {
+        (orbital.pos - earth.pos, orbital.vel - earth.vel)
+    });
+    let target_m = sas_target
+        .0
+        .and_then(|target| targets.get(target).ok())
+        .map(|(target, last)| origin.offset(&interpolation.pos(target, last)));
+
+    let frame = match *mode {
+        CameraMode::Chase => axes(&along, &radial),
+        CameraMode::Free => None,
+        CameraMode::Onboard => axes(&(q_bw * Vector3::z()), &(q_bw * -Vector3::x())),
+        CameraMode::Planet => {
+            earth.and_then(|(_, earth)| axes(&(earth.q_bw * Vector3::z()), &radial))
+        }
+        CameraMode::Target => target_m.and_then(|target_m| axes(&(target_m - ship_m), &radial)),
+    }
+    .unwrap_or_else(UnitQuaternion::identity);
+
+    let dir = frame * settings.dir;
+    let (pos, look) = match (*mode, target_m) {
+        (CameraMode::Onboard, _) => (ship_m + q_bw * (ONBOARD_B - mass.cg_b), dir),
+        (CameraMode::Target, Some(target_m)) => {
+            let pos = ship_m + dir * settings.distance;
+            (pos, target_m - pos)
+        }
+        _ => (ship_m + dir * settings.distance, -dir),
+    };
+    let wanted = Transform::from_translation(sim_to_bevy(&pos))
+        .looking_to(sim_to_bevy(&look), sim_to_bevy(&(frame * Vector3::z())));
+
+    let share = if settings.smoothing > 0.0 {
+        1.0 - (-time.delta_secs_f64() / settings.smoothing).exp()
+    } else {
+        1.0
+    };
+    transform.translation = transform.translation.lerp(wanted.translation, share as f32);
+    transform.rotation = transform.rotation.slerp(wanted.rotation, share as f32);
+    if let Projection::Perspective(perspective) = &mut *projection {
+        perspective.fov = settings.fov.to_radians() as f32;
+    }
+}
+
+fn update_view_text(mode: Res<CameraMode>, mut text: Query<&mut Text, With<ViewText>>) {
+    let Ok(mut text) = text.single_mut() else {
+        return;
+    };
+    text.0 = format!("View: {} (B)", mode.name());
+}
+
+fn view_command(
+    In(args): In<Vec<String>>,
+    mut mode: ResMut<CameraMode>,
+    mut views: ResMut<CameraViews>,
+) -> ConsoleReply {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let settings = views.get_mut(*mode);
+    match args.as_slice() {
+        [] => {}
+        ["distance", value] => settings.distance = parse_arg(value)?.max(NEAREST),
+        ["fov", value] => settings.fov = parse_arg(value)?.clamp(FOV_RANGE.0, FOV_RANGE.1),
+        ["smoothing", value] => settings.smoothing = parse_arg(value)?.max(0.0),
+        [name] => {
+            *mode = CameraMode::ALL
+                .into_iter()
+                .find(|view| view.name().eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("Unknown view {:?}", name))?;
+        }
+        _ => {
+            return Err("view [<mode> | distance <m> | fov <deg> | smoothing <s>]".to_string());
+        }
+    }
+    let settings = views.get(*mode);
+    Ok(format!(
+        "view {}, distance {} m, fov {} deg, smoothing {} s",
+        mode.name(),
+        settings.distance,
+        settings.fov,
+        settings.smoothing
+    ))
+}
@@ -0,0 +1,193 @@
+//! Panels in windows of their own.
+//!
+//! For a simpit, or an instructor's station, with more than one monitor, the
+//! views that have their own camera (the map, the ground track, and the sky
+//! view) can each be opened in a separate window, to drag onto another
+//! monitor, leaving the main window a clean view of the ship.  A view in a
+//! window is always showing, whatever its key or the HUD layout say, and
+//! takes its text along with it.  Closing the window puts the view back in
+//! the main one, as it was.
+//!
+//! A view is made detachable by giving its camera a `Detachable`.
+//!
+//! - `window`: the views, and which are in windows.
+//! - `window open <view>`: open a view in a window.
+//! - `window close <view>`: put it back.
+
+use bevy::{
+    camera::RenderTarget,
+    prelude::*,
+    window::{PrimaryWindow, WindowRef, WindowResolution},
+};
+use sim_game::console::{ConsoleApp, ConsoleReply};
+use std::collections::BTreeMap;
+
+use crate::layout::HudPanel;
+
+/// A view that can be opened in its own window, by the name it is opened by,
+/// which is also that of any HUD panel that goes with it.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Detachable {
+    pub name: &'static str,
+    /// The size of the window it opens in, in logical pixels.
+    pub size: UVec2,
+}
+
+/// The views open in windows of their own, and the windows.
+#[derive(Resource, Default)]
+pub struct DetachedPanels(pub BTreeMap<&'static str, Entity>);
+
+impl DetachedPanels {
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+}
+
+/// The window a view is open in.
+#[derive(Component)]
+struct PanelWindow;
+
+#[derive(Default)]
+pub struct PanelWindowsPlugin;
+
+impl Plugin for PanelWindowsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DetachedPanels>();
+        app.add_systems(Update, closed_windows);
+        app.add_console_command(
+            "window",
+            "window [open|close <view>]   views in windows of their own",
+            window_command,
+        );
+    }
+}
+
+/// The window a camera renders into, if any.
+pub fn target_window(camera: &Camera, primary: Option<Entity>) -> Option<Entity> {
+    match camera.target {
+        RenderTarget::Window(WindowRef::Entity(window)) => Some(window),
+        RenderTarget::Window(WindowRef::Primary) => primary,
+        _ => None,
+    }
+}
+
+/// Point a view's camera at `window`, or back at the main window, and take
+/// its text along.
+fn retarget(
+    commands: &mut Commands,
+    name: &str,
+    window: Option<Entity>,
+    cameras: &mut Query<(Entity, &Detachable, &mut Camera)>,
+    nodes: &Query<(Entity, &HudPanel), With<Node>>,
+) {
+    let Some((entity, _, mut camera)) = cameras.iter_mut().find(|(_, d, _)| d.name == name) else {
+        return;
+    };
+    match window {
+        Some(window) => {
+            camera.target = RenderTarget::Window(WindowRef::Entity(window));
+            // It has the whole window.
+            camera.viewport = None;
+        }
+        None => camera.target = RenderTarget::default(),
+    }
+    for (node, _) in nodes.iter().filter(|(_, panel)| panel.0 == name) {
+        match window {
+            Some(_) => commands.entity(node).insert(UiTargetCamera(entity)),
+            None => commands.entity(node).remove::<UiTargetCamera>(),
+        };
+    }
+}
+
+/// Put back the views whose windows have been closed.
+fn closed_windows(
+    mut commands: Commands,
+    mut detached: ResMut<DetachedPanels>,
+    mut windows: RemovedComponents<PanelWindow>,
+    mut cameras: Query<(Entity, &Detachable, &mut Camera)>,
+    nodes: Query<(Entity, &HudPanel), With<Node>>,
+) {
+    let windows: Vec<Entity> = windows.read().collect();
+    let closed: Vec<_> = detached
+        .0
+        .iter()
+        .filter(|(_, window)| windows.contains(window))
+        .map(|(name, _)| *name)
+        .collect();
+    for name in closed {
+        retarget(&mut commands, name, None, &mut cameras, &nodes);
+        detached.0.remove(name);
+    }
+}
+
+fn window_command(
+    In(args): In<Vec<String>>,
+    mut commands: Commands,
+    mut detached: ResMut<DetachedPanels>,
+    mut cameras: Query<(Entity, &Detachable, &mut Camera)>,
+    nodes: Query<(Entity, &HudPanel), With<Node>>,
+    primary: Query<&Window, With<PrimaryWindow>>,
+) -> ConsoleReply {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let find = |name: &str| {
+        cameras
+            .iter()
+            .find(|(_, d, _)| d.name == name)
+            .map(|(_, d, _)| *d)
+            .ok_or_else(|| format!("No such view as {:?}, try window", name))
+    };
+    match args.as_slice() {
+        [] => {
+            let mut views: Vec<_> = cameras
+                .iter()
+                .map(|(_, d, _)| {
+                    format!(
+                        "{:<10} {}",
+                        d.name,
+                        if detached.contains(d.name) {
+                            "in a window"
+                        } else {
+                            "in the main window"
+                        }
+                    )
+                })
+                .collect();
+            views.sort();
+            Ok(views.join("\n"))
+        }
+        ["open", name] => {
+            let view = find(name)?;
+            if detached.contains(view.name) {
+                return Err(format!("{} is already in a window", view.name));
+            }
+            // The same scale as the main window, to start with, so that
+            // the panels come out the same size.
+            let scale = primary.single().map_or(1.0, |w| w.scale_factor());
+            let size = (view.size.as_vec2() * scale).as_uvec2();
+            let window = commands
+                .spawn((
+                    Window {
+                        title: format!("scifisim: {}", view.name),
+                        resolution: WindowResolution::new(size.x, size.y),
+                        ..default()
+                    },
+                    PanelWindow,
+                ))
+                .id();
+            retarget(&mut commands, view.name, Some(window), &mut cameras, &nodes);
+            detached.0.insert(view.name, window);
+            Ok("ok".to_string())
+        }
+        ["close", name] => {
+            let view = find(name)?;
+            let window = detached
+                .0
+                .remove(view.name)
+                .ok_or_else(|| format!("{} isn't in a window", view.name))?;
+            retarget(&mut commands, view.name, None, &mut cameras, &nodes);
+            commands.entity(window).despawn();
+            Ok("ok".to_string())
+        }
+        _ => Err("window [open|close <view>]".to_string()),
+    }
+}
@@ -0,0 +1,65 @@
+//! Ephemeris lookups, from the console and the command line.
+//!
+//! `scifisim ephem <target> <time> [--frame <frame>] [--center <body>]
+//! [--abcorr <correction>]` prints where the target is, and exits, without
+//! starting the sim.  The console's `ephem` takes the same arguments, and
+//! `now` for the time is the sim's current time.  Both need the `spice`
+//! feature; see `sim_spice::ephem`.
+
+use bevy::prelude::*;
+use sim_game::console::{ConsoleApp, ConsoleReply};
+
+#[derive(Default)]
+pub struct EphemPlugin;
+
+impl Plugin for EphemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_console_command(
+            "ephem",
+            "ephem <target> <time|now> [--frame f] [--center body] [--abcorr c]   look up a body",
+            ephem_command,
+        );
+    }
+}
+
+/// Run the `ephem` subcommand, with the arguments that follow it.
+#[cfg(feature = "spice")]
+pub fn run(args: &[String]) -> Result<(), anyhow::Error> {
+    use sim_spice::ephem::{EphemQuery, Ephemeris};
+
+    let query = EphemQuery::parse(args).map_err(|e| anyhow::anyhow!(e))?;
+    sim_spice::init().map_err(|e| anyhow::anyhow!(e))?;
+    println!("{}", Ephemeris::lookup(&query)?);
+    Ok(())
+}
+
+#[cfg(not(feature = "spice"))]
+pub fn run(_args: &[String]) -> Result<(), anyhow::Error> {
+    Err(anyhow::anyhow!("Built without the spice feature"))
+}
+
+#[cfg(feature = "spice")]
+fn ephem_command(
+    In(args): In<Vec<String>>,
+    fixed: Res<Time<Fixed>>,
+    solar: Res<sim_astro::SolarState>,
+) -> ConsoleReply {
+    use sim_spice::ephem::{EphemQuery, Ephemeris};
+
+    let query = EphemQuery::parse(&args)?;
+    // Without the kernels, the game still runs, from its saved ephemeris.
+    sim_spice::init()?;
+    let et = if query.time.eq_ignore_ascii_case("now") {
+        solar.et + fixed.elapsed_secs_f64()
+    } else {
+        query.et().map_err(|e| e.to_string())?
+    };
+    Ephemeris::at(&query, et)
+        .map(|ephemeris| ephemeris.to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "spice"))]
+fn ephem_command(_: In<Vec<String>>) -> ConsoleReply {
+    Err("Built without the spice feature".to_string())
+}
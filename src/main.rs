@@ -1,35 +1,312 @@
 //! A simple orbital mechanics simulator.
 //!
 //! Notably, we use Z-up right handed.
+//!
+//! The sim is split into crates, from the bottom up: `sim_core` (the state of
+//! things, and the physics), `sim_astro` (the solar system), `sim_spice`
+//! (taking the ephemeris from SPICE, with the `spice` feature), `sim_game` (the
+//! ship, and everything that runs headless), and `sim_render` and `sim_ui`
+//! (the views).  This is the game itself, and its command line.
 
 // Recommended alias.
 extern crate nalgebra as na;
 
-mod ship;
-mod solar;
-mod ui;
+mod ephem;
+mod porkchop;
+mod propagate;
+mod soak;
 
-use bevy::{diagnostic::FrameTimeDiagnosticsPlugin, pbr::wireframe::WireframePlugin, prelude::*};
+use bevy::{
+    diagnostic::FrameTimeDiagnosticsPlugin, pbr::wireframe::WireframePlugin, prelude::*,
+    window::ExitCondition,
+};
+use sim_astro::SolarState;
+use sim_game::{
+    conservation, console, drill, keys, recording, ship, sim, snapshot, stats, telemetry,
+};
 
 fn main() -> Result<(), anyhow::Error> {
-    let ephem = if false {
-        let ephem = solar::SolarState::from_spice()
-            .ok_or_else(|| anyhow::anyhow!("Failed to create ephemeris"))?;
-        ephem.save("solar.json")?;
-        ephem
+    // `ephem <target> <time> ...` looks up a body in the kernels, without the
+    // sim.
+    let mut args: Vec<String> = std::env::args().collect();
+    // `--kernels <file>` loads the SPICE kernels listed there (see
+    // `sim_spice::kernels`), rather than those in `assets/spice`.
+    if let Some(pos) = args.iter().position(|a| a == "--kernels") {
+        let path = args
+            .get(pos + 1)
+            .ok_or_else(|| anyhow::anyhow!("--kernels needs a file"))?;
+        use_kernels(path)?;
+        args.drain(pos..=pos + 1);
+    }
+    if args.get(1).is_some_and(|a| a == "ephem") {
+        return ephem::run(&args[2..]);
+    }
+    // `porkchop <from> <to> --depart <span> --arrive <span>` scans transfers
+    // between two bodies, also from the kernels.
+    if args.get(1).is_some_and(|a| a == "porkchop") {
+        return porkchop::run(&args[2..]);
+    }
+
+    // `--spice` takes the solar system from the kernels, rather than from
+    // solar.json.  The game starts while they are read (see
+    // `sim_astro::loading`); the headless modes wait for them.  `--analytic`
+    // works the major bodies out from series instead (see
+    // `sim_astro::analytic`), with the `analytic` feature, as `--spice` does
+    // when built with that and not `spice`.
+    let analytic = args.iter().any(|a| a == "--analytic") || !cfg!(feature = "spice");
+    let ephem = if args.iter().any(|a| a == "--spice" || a == "--analytic") {
+        None
     } else {
-        solar::SolarState::load("solar.json")?
+        Some(SolarState::load("solar.json")?)
+    };
+    // `--horizons <id>[,<id>...]` adds the bodies the ephemeris doesn't have,
+    // from JPL Horizons (see `sim_astro::horizons`), with the `horizons`
+    // feature.
+    let horizons: Vec<i32> = match args.iter().position(|a| a == "--horizons") {
+        Some(pos) => args
+            .get(pos + 1)
+            .ok_or_else(|| anyhow::anyhow!("--horizons needs NAIF ids"))?
+            .split(',')
+            .map(|id| id.trim().parse())
+            .collect::<Result<_, _>>()?,
+        None => Vec::new(),
     };
-    if false {
-        return Ok(());
+    let ephem = ephem
+        .map(|ephem| with_horizons(ephem, &horizons))
+        .transpose()?;
+
+    // `propagate --scenario <file> --duration <s> --out <file.csv>` runs
+    // headlessly, logging the ship.
+    if args.get(1).is_some_and(|a| a == "propagate") {
+        let ephem = ephem.map_or_else(|| with_horizons(from_spice(analytic)?, &horizons), Ok)?;
+        return propagate::run(ephem, &args[2..]);
     }
+
+    // `--soak [days]` runs headlessly, checking the sim holds together.
+    if let Some(pos) = args.iter().position(|a| a == "--soak") {
+        let days = match args.get(pos + 1) {
+            Some(days) => days.parse()?,
+            None => 365.0,
+        };
+        let ephem = ephem.map_or_else(|| with_horizons(from_spice(analytic)?, &horizons), Ok)?;
+        return soak::run(ephem, days);
+    }
+
+    // `--drill <template> [seed]` starts in a random training scenario.
+    let drill = match args.iter().position(|a| a == "--drill") {
+        Some(pos) => {
+            let path = args
+                .get(pos + 1)
+                .ok_or_else(|| anyhow::anyhow!("--drill needs a template"))?;
+            let template = drill::DrillTemplate::load(path)?;
+            let seed = match args.get(pos + 2) {
+                Some(seed) => seed.parse()?,
+                None => rand::random(),
+            };
+            let earth = ephem
+                .as_ref()
+                .ok_or_else(|| {
+                    anyhow::anyhow!("--drill needs solar.json, not --spice or --analytic")
+                })?
+                .bodies
+                .iter()
+                .find(|b| b.name.as_str() == "EARTH")
+                .ok_or_else(|| anyhow::anyhow!("No earth in the ephemeris"))?;
+            let pole = earth.attitude.q_bw.transform_vector(&na::Vector3::z());
+            let drill = template.generate(seed, earth.size.radii.x, &pole);
+            println!("Drill {:?}, seed {}: {:#?}", drill.name, seed, drill);
+            Some(drill)
+        }
+        None => None,
+    };
+
+    // `--launch <lat> <lon> [alt]` starts on (or over) the earth's surface.
+    let launch = match args.iter().position(|a| a == "--launch") {
+        Some(pos) => {
+            let arg = |i: usize| -> Result<Option<f64>, anyhow::Error> {
+                Ok(args.get(pos + i).map(|a| a.parse()).transpose()?)
+            };
+            let (Some(lat), Some(lon)) = (arg(1)?, arg(2)?) else {
+                return Err(anyhow::anyhow!("--launch needs a latitude and longitude"));
+            };
+            Some(ship::ShipSpawn::Surface {
+                lat,
+                lon,
+                alt: arg(3)?.unwrap_or(0.0),
+            })
+        }
+        None => None,
+    };
+
+    // `--load <file>` starts from a saved snapshot.
+    let snapshot = match args.iter().position(|a| a == "--load") {
+        Some(pos) => {
+            let path = args
+                .get(pos + 1)
+                .ok_or_else(|| anyhow::anyhow!("--load needs a snapshot file"))?;
+            Some(snapshot::Snapshot::load(path)?)
+        }
+        None => None,
+    };
+
+    // `--record <file>` records the run, and `--replay <file>` plays one back.
+    let recording = match (
+        args.iter().position(|a| a == "--record"),
+        args.iter().position(|a| a == "--replay"),
+    ) {
+        (Some(pos), None) => Some(recording::RecordingPlugin::record(
+            args.get(pos + 1)
+                .ok_or_else(|| anyhow::anyhow!("--record needs a file"))?,
+        )?),
+        (None, Some(pos)) => Some(recording::RecordingPlugin::replay(
+            args.get(pos + 1)
+                .ok_or_else(|| anyhow::anyhow!("--replay needs a file"))?,
+        )?),
+        (Some(_), Some(_)) => return Err(anyhow::anyhow!("Can't both record and replay")),
+        (None, None) => None,
+    };
+
+    // `--telemetry <file.csv> [rate] [channels]` logs the ship to a CSV file.
+    let telemetry = match args.iter().position(|a| a == "--telemetry") {
+        Some(pos) => {
+            let path = args
+                .get(pos + 1)
+                .ok_or_else(|| anyhow::anyhow!("--telemetry needs a file"))?;
+            let rate = match args.get(pos + 2) {
+                Some(rate) => rate.parse()?,
+                None => 1.0,
+            };
+            let channels = match args.get(pos + 3) {
+                Some(names) => names
+                    .split(',')
+                    .map(telemetry::Channel::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| anyhow::anyhow!(e))?,
+                None => telemetry::Channel::ALL.to_vec(),
+            };
+            Some(telemetry::TelemetryPlugin::new(path, rate, channels)?)
+        }
+        None => None,
+    };
+
+    // `--stream <udp://host:port | ws://host:port> [rate] [channels] [format]`
+    // sends the same channels out live, with the `stream` feature (see
+    // `sim_game::stream`).
+    #[cfg(feature = "stream")]
+    let stream = match args.iter().position(|a| a == "--stream") {
+        Some(pos) => {
+            let address = args
+                .get(pos + 1)
+                .ok_or_else(|| anyhow::anyhow!("--stream needs an address"))?;
+            let rate = match args.get(pos + 2) {
+                Some(rate) => rate.parse()?,
+                None => 10.0,
+            };
+            let channels = match args.get(pos + 3) {
+                Some(names) => names
+                    .split(',')
+                    .map(telemetry::Channel::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| anyhow::anyhow!(e))?,
+                None => telemetry::Channel::ALL.to_vec(),
+            };
+            let format = match args.get(pos + 4) {
+                Some(format) => {
+                    sim_game::stream::Format::parse(format).map_err(|e| anyhow::anyhow!(e))?
+                }
+                None => sim_game::stream::Format::Json,
+            };
+            Some(sim_game::stream::StreamPlugin::new(
+                address, rate, channels, format,
+            )?)
+        }
+        None => None,
+    };
+    #[cfg(not(feature = "stream"))]
+    if args.iter().any(|a| a == "--stream") {
+        return Err(anyhow::anyhow!("Built without the stream feature"));
+    }
+
+    // `--conservation <file.csv>` also logs the conservation diagnostics.
+    let conservation = match args.iter().position(|a| a == "--conservation") {
+        Some(pos) => conservation::ConservationPlugin::with_log(
+            args.get(pos + 1)
+                .ok_or_else(|| anyhow::anyhow!("--conservation needs a file"))?,
+        )?,
+        None => conservation::ConservationPlugin::default(),
+    };
+
+    // `--keys <file>` takes the key bindings from there, rather than from the
+    // config directory (see `sim_game::keys`).
+    let keys = match args.iter().position(|a| a == "--keys") {
+        Some(pos) => Some(keys::KeysFile(
+            args.get(pos + 1)
+                .ok_or_else(|| anyhow::anyhow!("--keys needs a file"))?
+                .into(),
+        )),
+        None => None,
+    };
+
     let mut app = App::new();
-    app.insert_resource(ephem);
-    app.add_plugins((DefaultPlugins, FrameTimeDiagnosticsPlugin::default()));
+    match ephem {
+        Some(ephem) => app.insert_resource(ephem),
+        None => app.insert_resource(spice_loader(analytic, horizons)),
+    };
+    if let Some(snapshot) = snapshot {
+        app.insert_resource(snapshot);
+    }
+    if let Some(launch) = launch {
+        app.insert_resource(launch);
+    }
+    if let Some(keys) = keys {
+        app.insert_resource(keys);
+    }
+    if let Some(drill) = drill {
+        app.insert_resource(drill.orbit.clone());
+        app.insert_resource(drill);
+        app.add_plugins(drill::DrillPlugin);
+    }
+    // Closing the main window quits, even with panels open in windows of
+    // their own (see `sim_ui::windows`).
+    app.add_plugins((
+        DefaultPlugins.set(WindowPlugin {
+            exit_condition: ExitCondition::OnPrimaryClosed,
+            ..default()
+        }),
+        FrameTimeDiagnosticsPlugin::default(),
+    ));
     app.add_plugins(WireframePlugin::default());
-    app.add_plugins(solar::SolarPlugin::default());
-    app.add_plugins(ship::ShipPlugin::default());
-    app.add_plugins(ui::UIPlugin::default());
+    app.add_plugins(sim::SimPlugins);
+    app.add_plugins(stats::SimStatsPlugin::default());
+    app.add_plugins(conservation);
+    app.add_plugins(sim_render::ShipViewPlugin::default());
+    app.add_plugins(sim_ui::ManeuverViewPlugin::default());
+    app.add_plugins(ship::predict::PredictPlugin::default());
+    app.add_plugins(sim_render::PredictionViewPlugin::default());
+    app.add_plugins(sim_render::TrailViewPlugin::default());
+    app.add_plugins(sim_render::DebrisViewPlugin::default());
+    app.add_plugins(sim_render::SmallBodyViewPlugin::default());
+    app.add_plugins(sim_render::EarthMaterialPlugin::default());
+    app.add_plugins(sim_render::FloatingOriginPlugin::default());
+    app.add_plugins(sim_render::InterpolationPlugin::default());
+    app.add_plugins(sim_render::BodyViewPlugin::default());
+    app.add_plugins(sim_ui::UIPlugin::default());
+    app.add_plugins(console::ConsolePlugin::default());
+    app.add_plugins(ephem::EphemPlugin);
+    // The bodies' orientations, from the kernels, in place of the rates they
+    // were read with.
+    #[cfg(feature = "spice")]
+    app.add_plugins(sim_spice::orientation::OrientationPlugin);
+    if let Some(recording) = recording {
+        app.add_plugins(recording);
+    }
+    if let Some(telemetry) = telemetry {
+        app.add_plugins(telemetry);
+    }
+    #[cfg(feature = "stream")]
+    if let Some(stream) = stream {
+        app.add_plugins(stream);
+    }
     // app.add_systems(Startup, setup);
     // app.add_systems(Update, text_update_system);
     // app.add_systems(Update, text_update_fps);
@@ -40,6 +317,100 @@ fn main() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+/// Take the ephemeris from the SPICE kernels, or from series, with
+/// `analytic`.
+fn from_spice(analytic: bool) -> Result<SolarState, anyhow::Error> {
+    solar_state_with(analytic, &mut |_| {}).map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Take the ephemeris as `from_spice` does, as the game starts, and then the
+/// bodies `horizons` from Horizons.
+fn spice_loader(analytic: bool, horizons: Vec<i32>) -> sim_astro::loading::SolarLoader {
+    sim_astro::loading::SolarLoader::spawn(move |progress| {
+        let solar = solar_state_with(analytic, progress)?;
+        if horizons.is_empty() {
+            return Ok(solar);
+        }
+        progress(sim_astro::loading::LoadMessage::Stage(
+            "Asking Horizons".to_string(),
+        ));
+        with_horizons(solar, &horizons).map_err(|e| e.to_string())
+    })
+}
+
+/// The ephemeris from series, with `analytic`, or else from the kernels,
+/// saying what it is doing to `progress`.
+fn solar_state_with(
+    analytic: bool,
+    progress: &mut dyn FnMut(sim_astro::loading::LoadMessage),
+) -> Result<SolarState, String> {
+    if analytic {
+        analytic_state_with(progress)
+    } else {
+        kernel_state_with(progress)
+    }
+}
+
+#[cfg(feature = "spice")]
+fn kernel_state_with(
+    progress: &mut dyn FnMut(sim_astro::loading::LoadMessage),
+) -> Result<SolarState, String> {
+    sim_spice::solar_state_with(progress)
+}
+
+#[cfg(not(feature = "spice"))]
+fn kernel_state_with(
+    _progress: &mut dyn FnMut(sim_astro::loading::LoadMessage),
+) -> Result<SolarState, String> {
+    Err("Built without the spice feature".to_string())
+}
+
+#[cfg(feature = "analytic")]
+fn analytic_state_with(
+    progress: &mut dyn FnMut(sim_astro::loading::LoadMessage),
+) -> Result<SolarState, String> {
+    Ok(sim_astro::analytic::solar_state_with(progress))
+}
+
+#[cfg(not(feature = "analytic"))]
+fn analytic_state_with(
+    _progress: &mut dyn FnMut(sim_astro::loading::LoadMessage),
+) -> Result<SolarState, String> {
+    Err("Built without the analytic feature".to_string())
+}
+
+/// Add the bodies `ids` to `solar`, from Horizons, or as kept from last time.
+#[cfg(feature = "horizons")]
+fn with_horizons(mut solar: SolarState, ids: &[i32]) -> Result<SolarState, anyhow::Error> {
+    if !ids.is_empty() {
+        sim_astro::horizons::HorizonsCache::default()
+            .add_to(&mut solar, ids)
+            .map_err(|e| anyhow::anyhow!(e))?;
+    }
+    Ok(solar)
+}
+
+#[cfg(not(feature = "horizons"))]
+fn with_horizons(solar: SolarState, ids: &[i32]) -> Result<SolarState, anyhow::Error> {
+    if ids.is_empty() {
+        Ok(solar)
+    } else {
+        Err(anyhow::anyhow!("Built without the horizons feature"))
+    }
+}
+
+/// Start SPICE with the kernels listed in `path`.
+#[cfg(feature = "spice")]
+fn use_kernels(path: &str) -> Result<(), anyhow::Error> {
+    let set = sim_spice::KernelSet::load_from(path).map_err(|e| anyhow::anyhow!(e))?;
+    sim_spice::kernels::configure(set).map_err(|e| anyhow::anyhow!(e))
+}
+
+#[cfg(not(feature = "spice"))]
+fn use_kernels(_path: &str) -> Result<(), anyhow::Error> {
+    Err(anyhow::anyhow!("Built without the spice feature"))
+}
+
 // #[derive(Resource)]
 // struct Paused(bool);
 
@@ -345,11 +716,6 @@ fn text_update_system(
 */
 
 /*
-/// Convert a sim vector to a Bevy vector.
-fn sim_to_bevy(v: &na::Vector3<f64>) -> Vec3 {
-    Vec3::new(v.x as f32, v.z as f32, -v.y as f32)
-}
-
 fn text_update_fps(
     diagnostics: Res<DiagnosticsStore>,
     mut query: Query<&mut TextSpan, With<FpsText>>,
@@ -371,21 +737,12 @@ fn setup_sim(app: &mut App) {
     // Create a ship that is just stuck 1km in the air above the surface.
     let ship = Craft::new_above(&earth, 100.0);
 
-    // Let's make a little force to test this.
-    let thrust = Thrust {
-        direction: (ship.position - earth.position).normalize(),
-        magnitude: 15.0, // Newtons
-        from: 0.5,       // seconds
-        until: 2.0,      // seconds
-    };
-
     let sim = Simulation {
         time: 0.0,
         collided: false,
         step_time: 1.0 / 100.0,
         bodies: vec![earth, sun],
         crafts: vec![ship],
-        thrust: Some(thrust),
     };
 
     app.insert_resource(sim);
@@ -510,7 +867,6 @@ struct Simulation {
     collided: bool,
     bodies: Vec<Body>,
     crafts: Vec<Craft>,
-    thrust: Option<Thrust>,
 }
 
 impl Simulation {
@@ -549,7 +905,6 @@ impl Simulation {
     /// Step the simulation forward by the given time step, in seconds.
     fn step(&mut self) {
         // Update the position and velocity of each craft.
-        let mut first = true;
         for craft in &mut self.crafts {
             // Calculate the total acceleration on the craft due to all bodies.
             let mut total_acceleration = na::Vector3::new(0.0, 0.0, 0.0);
@@ -565,17 +920,6 @@ impl Simulation {
                 total_acceleration += acceleration;
             }
 
-            if first {
-                first = false;
-                // Apply thrust if we have it, and it's active.
-                if let Some(thrust) = &self.thrust {
-                    if thrust.is_active(self.time) {
-                        let thrust_accel = thrust.force();
-                        total_acceleration += thrust_accel;
-                    }
-                }
-            }
-
             // Update velocity and position using simple Euler integration.
             craft.velocity += total_acceleration * self.step_time;
             craft.position += craft.velocity * self.step_time;
@@ -605,23 +949,4 @@ impl Simulation {
         self.time += self.step_time;
     }
 }
-
-struct Thrust {
-    direction: na::Vector3<f64>,
-    magnitude: f64,
-    from: f64,
-    until: f64,
-}
-
-impl Thrust {
-    #[allow(dead_code)]
-    fn is_active(&self, time: f64) -> bool {
-        time >= self.from && time <= self.until
-    }
-
-    #[allow(dead_code)]
-    fn force(&self) -> na::Vector3<f64> {
-        self.direction.normalize() * (self.magnitude)
-    }
-}
 */
@@ -0,0 +1,139 @@
+//! Porkchop plots, from the command line.
+//!
+//! `scifisim porkchop <from> <to> --depart <start>..<end> --arrive
+//! <start>..<end> [--step <days>] [--out <file.csv>]` scans transfers from
+//! one body to another (see `sim_core::porkchop`), with both bodies' states
+//! about the sun taken from the SPICE kernels, and exits, without starting
+//! the sim.  The times are in any form SPICE takes, such as
+//! `2026-09-01..2027-01-01`, and the grid is a point every `--step` days (5,
+//! by default) along each.
+//!
+//! It prints the cheapest transfers, by C3 and by delta-v, and, with `--out`,
+//! writes the whole grid to a CSV file, a row for each pair of times, for
+//! contouring: the UTC of each, the time of flight in days, the C3 in
+//! km^2/s^2, and the arrival excess speed and delta-v in km/s (`NaN` where
+//! there is no transfer).
+//!
+//! ```text
+//! scifisim porkchop EARTH MARS --depart 2026-08-01..2027-01-01 --arrive 2027-03-01..2027-12-01
+//! ```
+//!
+//! This needs the `spice` feature.
+
+/// Run the `porkchop` subcommand, with the arguments that follow it.
+#[cfg(feature = "spice")]
+pub fn run(args: &[String]) -> Result<(), anyhow::Error> {
+    use na::Vector3;
+    use sim_core::porkchop::{Ephemeris, Porkchop, Transfer};
+    use std::io::Write;
+
+    let usage = || {
+        anyhow::anyhow!(
+            "porkchop <from> <to> --depart <start>..<end> --arrive <start>..<end> \
+             [--step <days>] [--out <file.csv>]"
+        )
+    };
+    let [from, to, ..] = args else {
+        return Err(usage());
+    };
+    let flag = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|pos| args.get(pos + 1))
+    };
+    let step = match flag("--step") {
+        Some(step) => step.parse::<f64>()? * 86400.0,
+        None => 5.0 * 86400.0,
+    };
+    if step <= 0.0 {
+        return Err(anyhow::anyhow!("The step has to be more than 0"));
+    }
+
+    // The body's states about the sun, every step over the span.  SPICE
+    // doesn't take the `Z` on the end of an ISO time, as for `ephem`.
+    let spice = sim_spice::init().map_err(|e| anyhow::anyhow!(e))?;
+    let et = |time: &str| spice.str2et(time.strip_suffix(['Z', 'z']).unwrap_or(time));
+    let states = |target: &str, span: &str| -> Result<Vec<Ephemeris>, anyhow::Error> {
+        let (start, end) = span.split_once("..").ok_or_else(usage)?;
+        let (start, end) = (et(start)?, et(end)?);
+        let target = target.to_uppercase();
+        let mut states = Vec::new();
+        let mut time = start;
+        while time <= end {
+            let (state, _) = spice.spkezr(&target, time, "ECLIPJ2000", "NONE", "SUN")?;
+            states.push(Ephemeris {
+                time,
+                pos: Vector3::new(state[0], state[1], state[2]),
+                vel: Vector3::new(state[3], state[4], state[5]),
+            });
+            time += step;
+        }
+        Ok(states)
+    };
+    let departs = states(from, flag("--depart").ok_or_else(usage)?)?;
+    let arrives = states(to, flag("--arrive").ok_or_else(usage)?)?;
+    let gm = spice.bodvrd("SUN", "GM", 1)?[0];
+    let porkchop = Porkchop::scan(&departs, &arrives, gm);
+
+    let utc = |et: f64| spice.et2utc(et, "ISOC", 0);
+    let describe = |transfer: &Transfer| -> Result<String, anyhow::Error> {
+        Ok(format!(
+            "leave {}, arrive {} ({:.0} days): C3 {:.2} km^2/s^2, arriving at {:.2} km/s, \
+             {:.2} km/s in all",
+            utc(transfer.depart)?,
+            utc(transfer.arrive)?,
+            transfer.tof() / 86400.0,
+            transfer.c3,
+            transfer.v_inf_arrive,
+            transfer.dv()
+        ))
+    };
+    println!(
+        "{} to {}: {} departures by {} arrivals",
+        from.to_uppercase(),
+        to.to_uppercase(),
+        porkchop.departs.len(),
+        porkchop.arrives.len()
+    );
+    match (porkchop.best(|t| t.c3), porkchop.best(Transfer::dv)) {
+        (Some(c3), Some(dv)) => {
+            println!("Least C3: {}", describe(c3)?);
+            println!("Least delta-v: {}", describe(dv)?);
+        }
+        _ => println!("No transfers"),
+    }
+
+    if let Some(path) = flag("--out") {
+        let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+        writeln!(
+            out,
+            "depart,arrive,tof_days,c3_km2_s2,v_inf_arrive_km_s,dv_km_s"
+        )?;
+        for (i, depart) in porkchop.departs.iter().enumerate() {
+            for (j, arrive) in porkchop.arrives.iter().enumerate() {
+                let (c3, v_inf, dv) = porkchop
+                    .get(i, j)
+                    .map_or((f64::NAN, f64::NAN, f64::NAN), |t| {
+                        (t.c3, t.v_inf_arrive, t.dv())
+                    });
+                writeln!(
+                    out,
+                    "{},{},{:.1},{:.4},{:.4},{:.4}",
+                    utc(*depart)?,
+                    utc(*arrive)?,
+                    (arrive - depart) / 86400.0,
+                    c3,
+                    v_inf,
+                    dv
+                )?;
+            }
+        }
+        println!("Wrote {}", path);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "spice"))]
+pub fn run(_args: &[String]) -> Result<(), anyhow::Error> {
+    Err(anyhow::anyhow!("Built without the spice feature"))
+}
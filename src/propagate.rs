@@ -0,0 +1,108 @@
+//! Headless batch propagation.
+//!
+//! `scifisim propagate --scenario <file> --duration <seconds> --out <file.csv>`
+//! starts the sim from a scenario and runs it, with no window or rendering,
+//! as fast as it will go, for the given stretch of sim time.  The ship is
+//! logged to the CSV file along the way, as with `--telemetry`.  This is for
+//! long propagations, and regression runs, on a machine with no GPU.
+//!
+//! A scenario is a snapshot (as saved with F10), in JSON or, if the file name
+//! ends in `.ron`, RON.  The other options are:
+//!
+//! - `--step <seconds>`: the physics step.  By default, this is the same as
+//!   the game's, so that a run matches what would happen on screen.
+//! - `--rate <samples per second>` and `--channels <list>`: what to log, as
+//!   for `--telemetry`.
+//! - `--conservation <file.csv>`: also log how well energy and angular
+//!   momentum are kept, as for the game's `--conservation`, for comparing
+//!   integrators and steps.
+
+use bevy::{input::InputPlugin, log::LogPlugin, prelude::*, time::TimeUpdateStrategy};
+use sim_astro::SolarState;
+use sim_core::clock::SimClock;
+use sim_game::{
+    conservation::ConservationPlugin,
+    sim::SimPlugins,
+    snapshot::Snapshot,
+    telemetry::{Channel, TelemetryPlugin},
+};
+use std::time::Instant;
+
+/// Physics steps run for each app update, at most.
+const PROPAGATE_STEPS_PER_UPDATE: u32 = 1000;
+
+/// Run the `propagate` subcommand, with the arguments that follow it.
+pub fn run(ephem: SolarState, args: &[String]) -> Result<(), anyhow::Error> {
+    let flag = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|pos| args.get(pos + 1))
+    };
+    let required = |name: &str| -> Result<&String, anyhow::Error> {
+        flag(name).ok_or_else(|| anyhow::anyhow!("propagate needs {} <value>", name))
+    };
+
+    let scenario = Snapshot::load(required("--scenario")?)?;
+    let duration: f64 = required("--duration")?.parse()?;
+    let out = required("--out")?;
+    let step: Option<f64> = flag("--step").map(|s| s.parse()).transpose()?;
+    let rate = match flag("--rate") {
+        Some(rate) => rate.parse()?,
+        None => 1.0,
+    };
+    let channels = match flag("--channels") {
+        Some(names) => names
+            .split(',')
+            .map(Channel::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|e| anyhow::anyhow!(e))?,
+        None => Channel::ALL.to_vec(),
+    };
+
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, InputPlugin, LogPlugin::default()));
+    app.insert_resource(ephem);
+    app.insert_resource(scenario);
+    app.add_plugins(SimPlugins);
+    app.add_plugins(TelemetryPlugin::new(out, rate, channels)?);
+    if let Some(path) = flag("--conservation") {
+        app.add_plugins(ConservationPlugin::with_log(path)?);
+    }
+    if let Some(step) = step {
+        app.insert_resource(Time::<Fixed>::from_seconds(step));
+    }
+
+    app.finish();
+    app.cleanup();
+    let timestep = app.world().resource::<Time<Fixed>>().timestep();
+    let steps = (duration / timestep.as_secs_f64()).round() as u64;
+    app.world_mut()
+        .resource_mut::<Time<Virtual>>()
+        .set_max_delta(timestep * (PROPAGATE_STEPS_PER_UPDATE + 1));
+    // With no frames to keep up with, the clock takes every step it's owed.
+    app.world_mut().resource_mut::<SimClock>().budget = u64::MAX;
+
+    let start = Instant::now();
+    loop {
+        // The fixed clock only ever moves in whole steps, so this is exact.
+        let done = (app.world().resource::<Time<Fixed>>().elapsed().as_nanos()
+            / timestep.as_nanos()) as u64;
+        if done >= steps {
+            break;
+        }
+        let count = (steps - done).min(PROPAGATE_STEPS_PER_UPDATE as u64) as u32;
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep * count));
+        app.update();
+    }
+
+    let wall = start.elapsed().as_secs_f64();
+    println!(
+        "Propagated {:.0} s in {} steps of {} s, in {:.1} s ({:.0}x)",
+        duration,
+        steps,
+        timestep.as_secs_f64(),
+        wall,
+        duration / wall.max(1.0e-3)
+    );
+    Ok(())
+}
@@ -0,0 +1,252 @@
+//! Long-duration soak testing.
+//!
+//! `scifisim --soak [days]` runs the solar system, with a craft in LEO,
+//! headlessly and as fast as it will go, for a long stretch of sim time.  Along
+//! the way, it checks the things that should always hold, and stops at the
+//! first one that doesn't:
+//!
+//! - Nothing is NaN (or infinite).
+//! - Attitude quaternions stay normalized.
+//! - The total energy of the bodies stays put.  Nothing is thrusting, so the
+//!   system is conservative.
+//! - The craft stays within the earth's sphere of influence (which is what the
+//!   planning code assumes), and above its surface.
+//!
+//! The results are written to `soak-report.json`.  Slow numerical failures
+//! tend to only show up after hours of play, and this finds them in minutes.
+
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use na::Vector3;
+use serde::Serialize;
+use sim_astro::{EarthMarker, SolarPlugin, SolarState, loading::SimPhase, setup_solar};
+use sim_core::{
+    AttitudeControl, AttitudeState, LinearControl, MassiveBody, OrbitalBody, PostPhysicsSet,
+    SizedBody,
+};
+use sim_game::ship::ShipOrbit;
+use std::time::{Duration, Instant};
+
+/// The physics step used for the soak, in seconds.
+const SOAK_STEP: f64 = 10.0;
+
+/// Physics steps run for each app update.
+const SOAK_STEPS_PER_UPDATE: u32 = 360;
+
+/// How often, in sim seconds, the invariants are checked.
+const SOAK_CHECK_INTERVAL: f64 = 3600.0;
+
+/// The largest relative change allowed in the total energy of the bodies.
+const SOAK_ENERGY_TOLERANCE: f64 = 1.0e-6;
+
+/// How far a quaternion's norm can be from one.
+const SOAK_QUAT_TOLERANCE: f64 = 1.0e-9;
+
+/// The results of a soak run.
+#[derive(Resource, Clone, Debug, Default, Serialize)]
+pub struct SoakReport {
+    /// The sim time asked for, and how far we got, in days.
+    pub days: f64,
+    pub days_run: f64,
+    pub step: f64,
+    pub checks: u64,
+    /// The total energy of the bodies (scaled by G) at the start.
+    pub initial_energy: Option<f64>,
+    /// The largest relative change in the total energy seen.
+    pub max_energy_drift: f64,
+    /// The craft's specific orbital energy relative to the earth, at the
+    /// start, and the largest relative change seen.  This isn't conserved
+    /// (the sun and moon pull on it), so it is only reported.
+    pub initial_craft_energy: Option<f64>,
+    pub max_craft_energy_drift: f64,
+    /// The craft's lowest and highest altitude seen, in km.
+    pub min_altitude: f64,
+    pub max_altitude: f64,
+    /// What went wrong, if anything.
+    pub failures: Vec<String>,
+    /// How long the run took, in real seconds.
+    pub wall_seconds: f64,
+}
+
+/// The craft being soaked.
+#[derive(Component)]
+struct SoakCraft;
+
+/// Run the soak for the given number of sim days, and write the report.
+pub fn run(ephem: SolarState, days: f64) -> Result<(), anyhow::Error> {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins);
+    app.insert_resource(ephem);
+    app.add_plugins(SolarPlugin);
+    app.insert_resource(Time::<Fixed>::from_seconds(SOAK_STEP));
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+        SOAK_STEP * SOAK_STEPS_PER_UPDATE as f64,
+    )));
+    app.insert_resource(SoakReport {
+        days,
+        step: SOAK_STEP,
+        min_altitude: f64::INFINITY,
+        max_altitude: f64::NEG_INFINITY,
+        ..default()
+    });
+    app.add_systems(OnEnter(SimPhase::Running), setup_soak.after(setup_solar));
+    app.add_systems(FixedUpdate, soak_check.after(PostPhysicsSet));
+
+    app.finish();
+    app.cleanup();
+    // Let the whole update's worth of steps through.
+    app.world_mut()
+        .resource_mut::<Time<Virtual>>()
+        .set_max_delta(Duration::from_secs_f64(
+            SOAK_STEP * (SOAK_STEPS_PER_UPDATE + 1) as f64,
+        ));
+
+    let start = Instant::now();
+    loop {
+        app.update();
+        let report = app.world().resource::<SoakReport>();
+        if report.days_run >= days || !report.failures.is_empty() {
+            break;
+        }
+    }
+
+    let mut report = app.world_mut().resource_mut::<SoakReport>();
+    report.wall_seconds = start.elapsed().as_secs_f64();
+    println!("{:#?}", *report);
+    std::fs::write("soak-report.json", serde_json::to_string_pretty(&*report)?)?;
+    if report.failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Soak failed: {}", report.failures[0]))
+    }
+}
+
+/// A bare craft, in the same orbit the ship starts in.
+fn setup_soak(
+    mut commands: Commands,
+    earth: Query<(&MassiveBody, &OrbitalBody), With<EarthMarker>>,
+) {
+    let (mb, ob) = earth.single().unwrap();
+    let (r_rel, v_rel) = ShipOrbit::new_leo().state(mb.gm);
+    commands.spawn((
+        Name::new("SoakCraft"),
+        OrbitalBody {
+            pos: ob.pos + r_rel,
+            vel: ob.vel + v_rel,
+        },
+        AttitudeState {
+            q_bw: na::UnitQuaternion::identity(),
+            omega_b: Vector3::new(0.0, 0.01, 0.02),
+        },
+        AttitudeControl {
+            alpha_b: Vector3::zeros(),
+        },
+        LinearControl::default(),
+        SoakCraft,
+    ));
+}
+
+/// The total energy of the bodies, kinetic plus potential, scaled by G (so
+/// that GM can stand in for the masses).
+fn total_energy(bodies: &[(&MassiveBody, &OrbitalBody)]) -> f64 {
+    let mut energy = 0.0;
+    for (i, (mb1, ob1)) in bodies.iter().enumerate() {
+        energy += 0.5 * mb1.gm * ob1.vel.norm_squared();
+        for (mb2, ob2) in &bodies[i + 1..] {
+            energy -= mb1.gm * mb2.gm / (ob1.pos - ob2.pos).norm();
+        }
+    }
+    energy
+}
+
+#[allow(clippy::type_complexity)]
+fn soak_check(
+    time: Res<Time>,
+    mut report: ResMut<SoakReport>,
+    mut next_check: Local<f64>,
+    craft: Query<&OrbitalBody, With<SoakCraft>>,
+    bodies: Query<(&MassiveBody, &OrbitalBody)>,
+    earth: Query<(&MassiveBody, &OrbitalBody, &SizedBody), With<EarthMarker>>,
+    everything: Query<(&Name, Option<&OrbitalBody>, Option<&AttitudeState>)>,
+) {
+    let now = time.elapsed_secs_f64();
+    report.days_run = now / 86400.0;
+    if now < *next_check || !report.failures.is_empty() {
+        return;
+    }
+    *next_check = now + SOAK_CHECK_INTERVAL;
+    report.checks += 1;
+    let day = report.days_run;
+
+    for (name, orbital, attitude) in everything.iter() {
+        if let Some(orbital) = orbital
+            && !(orbital.pos.iter().all(|x| x.is_finite())
+                && orbital.vel.iter().all(|x| x.is_finite()))
+        {
+            report.failures.push(format!(
+                "day {:.2}: {} position or velocity is not finite",
+                day, name
+            ));
+        }
+        if let Some(attitude) = attitude {
+            let q = attitude.q_bw.quaternion();
+            if !(q.coords.iter().all(|x| x.is_finite())
+                && attitude.omega_b.iter().all(|x| x.is_finite()))
+            {
+                report
+                    .failures
+                    .push(format!("day {:.2}: {} attitude is not finite", day, name));
+            } else if (q.norm() - 1.0).abs() > SOAK_QUAT_TOLERANCE {
+                report.failures.push(format!(
+                    "day {:.2}: {} quaternion norm is {}",
+                    day,
+                    name,
+                    q.norm()
+                ));
+            }
+        }
+    }
+
+    let massive: Vec<_> = bodies.iter().collect();
+    let energy = total_energy(&massive);
+    let initial = *report.initial_energy.get_or_insert(energy);
+    let drift = ((energy - initial) / initial).abs();
+    report.max_energy_drift = report.max_energy_drift.max(drift);
+    if drift > SOAK_ENERGY_TOLERANCE {
+        report.failures.push(format!(
+            "day {:.2}: total energy drifted by {:.3e}",
+            day, drift
+        ));
+    }
+
+    let (Ok(craft), Ok((earth_mass, earth, earth_size))) = (craft.single(), earth.single()) else {
+        return;
+    };
+    let pos = craft.pos - earth.pos;
+    let vel = craft.vel - earth.vel;
+    let craft_energy = vel.norm_squared() / 2.0 - earth_mass.gm / pos.norm();
+    let initial = *report.initial_craft_energy.get_or_insert(craft_energy);
+    report.max_craft_energy_drift = report
+        .max_craft_energy_drift
+        .max(((craft_energy - initial) / initial).abs());
+
+    let altitude = pos.norm() - earth_size.radii.z;
+    report.min_altitude = report.min_altitude.min(altitude);
+    report.max_altitude = report.max_altitude.max(altitude);
+    if altitude < 0.0 {
+        report
+            .failures
+            .push(format!("day {:.2}: the craft hit the earth", day));
+    }
+
+    // The earth's sphere of influence, against the most massive body (the
+    // sun).
+    if let Some((sun_mass, sun)) = bodies.iter().max_by(|a, b| a.0.gm.total_cmp(&b.0.gm)) {
+        let soi = (earth.pos - sun.pos).norm() * (earth_mass.gm / sun_mass.gm).powf(0.4);
+        if pos.norm() > soi {
+            report.failures.push(format!(
+                "day {:.2}: the craft left the earth's sphere of influence ({:.0} km)",
+                day, soi
+            ));
+        }
+    }
+}
@@ -1,287 +0,0 @@
-//! Solar system modeling.
-
-// The rust-spice crate has a locking mechanism to ensure single threaded
-// access. However, it only implements a handeful of the SPICE functions, and
-// when enabled, makes the raw versions of the functions inaccessible. In order
-// for spice to actually be useful, we'll need to use our own lock, and just
-// make sure we only use the API while holding the lock.
-
-use std::path::Path;
-
-use bevy::prelude::*;
-use nalgebra::{Matrix3, Vector3};
-use serde::{Deserialize, Serialize};
-
-mod spice;
-
-/// A marker for the Earth.
-#[derive(Component)]
-pub struct EarthMarker;
-
-/// Indicates the id of an item that came (directly or indirectly) from SPICE.
-#[derive(Clone, Component, Debug, Serialize, Deserialize)]
-pub struct SpiceId(i32);
-
-/// An object that has sufficient mass to be considered a body for orbital
-/// mechanics.  Units are in km^3/s^2.
-#[derive(Clone, Component, Debug, Serialize, Deserialize)]
-pub struct MassiveBody {
-    pub gm: f64, // Gravitational constant * mass, km^3/s^2
-}
-
-/// A celestial object that has a position and velocity in space.  Units are in
-/// km and km/s, with an origin at the solar system barycenter.
-#[derive(Clone, Component, Debug, Serialize, Deserialize)]
-pub struct OrbitalBody {
-    pub pos: Vector3<f64>,
-    pub vel: Vector3<f64>,
-}
-
-/// An object that has a meaningful notion of a radius.  Units are in km, and
-/// are along the x, y, and z axes.
-#[derive(Clone, Component, Debug, Serialize, Deserialize)]
-pub struct SizedBody {
-    pub radii: Vector3<f64>,
-}
-
-/// The AttitudeState represents the current orientation, and angular velocity of the body.
-/// The orientation is a convertion between body to world, and the omega is the angular velocity relative to the body frame.
-#[derive(Clone, Component, Debug, Serialize, Deserialize)]
-pub struct AttitudeState {
-    pub q_bw: na::UnitQuaternion<f64>,
-    pub omega_b: Vector3<f64>,
-}
-
-/// The attitude can also be under acceleration (such as by an RCS system). This
-/// is represented here as an angular acceleration in the body frame (with Z
-/// being the axis along which the main engine fires).
-#[derive(Clone, Component, Debug, Serialize, Deserialize)]
-pub struct AttitudeControl {
-    pub alpha_b: Vector3<f64>,
-}
-
-/// All of the above are captured by "Body" which is primarily used to serialize
-/// data in and out to avoid needing the entire set of SPICE kernels for normal
-/// gameplay.
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Body {
-    pub id: SpiceId,
-    pub name: Name,
-    pub massive: MassiveBody,
-    pub orbital: OrbitalBody,
-    pub size: SizedBody,
-    pub attitude: AttitudeState,
-}
-
-impl Body {
-    pub fn new_from(id: i32, et: f64) -> Option<Self> {
-        let sl = spice::get_instance();
-        let name = sl.bodc2n(id)?;
-
-        // Reject barycenters.
-        if name.ends_with(" BARYCENTER") {
-            return None;
-        }
-
-        let gm = sl.bodvrd(&name, "GM", 1).ok()?;
-        // Reject "small" bodies.  This also avoids bodies that don't have a
-        // radius.
-        if gm[0] < 1.0 {
-            return None;
-        }
-        let radii = sl.bodvrd(&name, "RADII", 3).ok()?;
-        let radii = Vector3::new(radii[0], radii[1], radii[2]);
-
-        let xform = sl.sxform(&format!("IAU_{}", name), "ECLIPJ2000", et).ok()?;
-        let (rot, av) = sl.xf2rav(&xform).ok()?;
-        let rot = Matrix3::from_row_slice(&[
-            rot[0][0], rot[0][1], rot[0][2], // Row 0
-            rot[1][0], rot[1][1], rot[1][2], // Row 1
-            rot[2][0], rot[2][1], rot[2][2], // Row 2
-        ]);
-
-        let av = Vector3::new(av[0], av[1], av[2]);
-        let r_bw = na::Rotation3::from_matrix(&rot);
-        let q_bw = na::UnitQuaternion::from_rotation_matrix(&r_bw);
-
-        // Invert the angular velocity as spice is returning a frame rotation, not the earth's rotation.
-        let omega_b = -av;
-
-        let (state, _) = sl.spkezr(&name, et, "ECLIPJ2000", "NONE", "SSB").ok()?;
-
-        let pos = Vector3::new(state[0], state[1], state[2]);
-        let vel = Vector3::new(state[3], state[4], state[5]);
-
-        Some(Self {
-            id: SpiceId(id),
-            orbital: OrbitalBody { pos, vel },
-            size: SizedBody { radii },
-            attitude: AttitudeState { q_bw, omega_b },
-            massive: MassiveBody { gm: gm[0] },
-            name: Name::new(name),
-        })
-    }
-}
-
-#[derive(Component, Resource, Debug, Serialize, Deserialize)]
-pub struct SolarState {
-    /// The time represented by this snapshot, in seconds past J2000.
-    pub et: f64,
-    /// The time, as a string, to make this more readable.
-    pub time: String,
-    /// The bodies in the solar system.
-    pub bodies: Vec<Body>,
-}
-
-impl SolarState {
-    pub fn from_spice() -> Option<Self> {
-        let sl = spice::get_instance();
-        // TODO: Better start date.
-        let time = "2024-01-01T00:00:00";
-        let et = sl.str2et(time).ok()?;
-        let mut bodies = Vec::new();
-        let mut start = 0;
-        loop {
-            let limit = 500;
-            let names = sl.gnpool("BODY*_GM", start, limit).ok()?;
-            println!("Names: count: {}", names.len());
-            for name in &names {
-                let code = name[4..name.len() - 3].parse::<i32>().ok()?;
-
-                if let Some(body) = Body::new_from(code, et) {
-                    if body.massive.gm > 1.0 && !body.name.ends_with(" BARYCENTER") {
-                        bodies.push(body);
-                    }
-                    continue;
-                }
-            }
-            if names.len() < limit {
-                break;
-            }
-
-            start += names.len();
-        }
-        bodies.sort_by(|a, b| b.massive.gm.partial_cmp(&a.massive.gm).unwrap());
-        Some(Self {
-            et,
-            time: time.to_string(),
-            bodies,
-        })
-    }
-
-    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
-        let file = std::fs::File::create(path)?;
-        serde_json::to_writer_pretty(file, self).map_err(|e| {
-            std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Serialization error: {}", e),
-            )
-        })
-    }
-
-    pub(crate) fn load(arg: &str) -> std::io::Result<Self> {
-        let file = std::fs::File::open(arg)?;
-        let state = serde_json::from_reader(file).map_err(|e| {
-            std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Deserialization error: {}", e),
-            )
-        })?;
-        Ok(state)
-    }
-}
-
-#[derive(Default)]
-pub struct SolarPlugin;
-
-impl Plugin for SolarPlugin {
-    fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_systems(Startup, setup_solar);
-        app.add_systems(
-            FixedUpdate,
-            (
-                physics_step,
-                rot_accel_step.before(rotation_step),
-                rotation_step,
-            ),
-        );
-    }
-}
-
-pub fn setup_solar(ephem: Res<SolarState>, mut commands: bevy::prelude::Commands) {
-    for body in &ephem.bodies {
-        let e = commands
-            .spawn((
-                body.id.clone(),
-                body.massive.clone(),
-                body.orbital.clone(),
-                body.size.clone(),
-                body.attitude.clone(),
-                body.name.clone(),
-            ))
-            .id();
-
-        if body.name.as_str() == "EARTH" {
-            commands.entity(e).insert(EarthMarker);
-        }
-    }
-}
-
-/// The big physics update.
-fn physics_step(
-    mut bodies: Query<(Entity, Option<&MassiveBody>, &mut OrbitalBody)>,
-    time: Res<Time>,
-) {
-    let dt = time.delta_secs_f64();
-
-    let mut updates = Vec::new();
-    for (e1, _, ob1) in bodies.iter() {
-        let mut total_acceleration = na::Vector3::zeros();
-        for (e2, mb2, ob2) in bodies.iter() {
-            if e1 == e2 {
-                continue;
-            }
-
-            if let Some(mb2) = mb2 {
-                let rel_pos = ob2.pos - ob1.pos;
-                let distance = rel_pos.norm();
-                // TODO: Impact check.
-                let acceleration = rel_pos * mb2.gm / (distance * distance * distance);
-                total_acceleration += acceleration;
-            }
-        }
-        updates.push(total_acceleration);
-    }
-
-    // Now, go through again, iteratively, and apply all of the updates.
-    for ((_, _, mut ob), update) in bodies.iter_mut().zip(updates.iter()) {
-        ob.vel += *update * dt;
-        let delta = ob.vel * dt;
-        ob.pos += delta;
-    }
-}
-
-/// Update the rotation based on the rotation vector.
-fn rot_accel_step(mut bodies: Query<(&mut AttitudeState, &AttitudeControl)>, time: Res<Time>) {
-    let dt = time.delta_secs_f64();
-
-    for (mut attitude, control) in bodies.iter_mut() {
-        // Update the angular velocity based on the current angular acceleration.
-        attitude.omega_b += control.alpha_b * dt;
-    }
-}
-
-fn rotation_step(mut bodies: Query<&mut AttitudeState>, time: Res<Time>) {
-    let dt = time.delta_secs_f64();
-
-    for mut attitude in bodies.iter_mut() {
-        // Update the attitude state based on the current angular velocity.
-        let angle = attitude.omega_b.norm() * dt;
-        if angle > 1.0e-12 {
-            let axis = na::Unit::new_normalize(attitude.omega_b);
-            let delta_q = na::UnitQuaternion::from_axis_angle(&axis, angle);
-            attitude.q_bw = delta_q * attitude.q_bw;
-            attitude.q_bw.renormalize();
-        }
-    }
-}